@@ -0,0 +1,47 @@
+//! 一天 1 万条记录时的上下文构建耗时：cargo bench --bench context
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use opencowork_lib::bench::{ContextStrategy, SearchResult, SummaryRecord};
+
+fn sample_day(count: usize) -> SearchResult {
+    let records = (0..count)
+        .map(|i| {
+            serde_json::from_value::<SummaryRecord>(serde_json::json!({
+                "timestamp": format!("2024-05-01T{:02}:{:02}:{:02}", i / 3600, i / 60 % 60, i % 60),
+                "summary": format!("在编辑器中修改 context.rs 第 {} 行并运行 cargo test", i),
+                "app": if i % 3 == 0 { "Code" } else { "Terminal" },
+                "action": "active",
+                "keywords": ["cargo", "test"],
+                "has_issue": i % 50 == 0,
+                "detail": "终端输出：\nerror[E0308]: mismatched types\n  --> src/main.rs:10:5",
+            }))
+            .unwrap()
+        })
+        .collect();
+    SearchResult {
+        records,
+        aggregated: Vec::new(),
+        source: "bench".to_string(),
+    }
+}
+
+fn build_context_10k_records(c: &mut Criterion) {
+    let result = sample_day(10_000);
+    let strategies = [
+        ("recent_first", ContextStrategy::RecentFirst),
+        ("issue_first", ContextStrategy::IssueFirst),
+        (
+            "semantic",
+            ContextStrategy::Semantic(vec!["cargo".to_string(), "error".to_string()]),
+        ),
+    ];
+    let mut group = c.benchmark_group("build_context_10k");
+    for (name, strategy) in &strategies {
+        group.bench_function(*name, |b| {
+            b.iter(|| result.build_context(black_box(strategy), None, 20_000, true, None))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build_context_10k_records);
+criterion_main!(benches);
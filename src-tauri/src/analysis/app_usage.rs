@@ -0,0 +1,205 @@
+use super::digest::{parse_timestamp, round_minutes, MAX_RECORD_GAP_SECS};
+use crate::storage::{StorageManager, SummaryRecord};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MAX_APP_USAGE_DAYS: i64 = 366;
+// 对比上期时忽略两期都不足该分钟数的应用，避免零碎使用产生夸张的百分比
+const MIN_COMPARE_MINUTES: f64 = 10.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppUsageItem {
+    pub app: String,
+    pub minutes: f64,
+    pub records: usize,
+    pub sessions: usize,  // 连续使用的段数
+    pub longest_streak_minutes: f64,
+}
+
+/// 连续停留在同一应用的最长时段
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusStreak {
+    pub app: String,
+    pub start: String,
+    pub end: String,
+    pub minutes: f64,
+}
+
+/// 与上一周期相比的应用时长变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppUsageChange {
+    pub app: String,
+    pub minutes: f64,
+    pub previous_minutes: f64,
+    pub change_percent: Option<f64>,  // 上期为 0 时为空
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppUsageStats {
+    pub start_date: String,
+    pub end_date: String,
+    pub active_minutes: f64,
+    pub context_switches: usize,
+    pub longest_focus: Option<FocusStreak>,
+    pub apps: Vec<AppUsageItem>,
+    pub comparison: Vec<AppUsageChange>,  // 与等长的上一周期对比，未请求时为空
+}
+
+impl StorageManager {
+    /// 读取日期范围内的摘要记录（不含隐私跳过的记录），按时间排序
+    pub fn records_between(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<SummaryRecord>, String> {
+        let mut dates = Vec::new();
+        let mut day = start;
+        while day <= end {
+            dates.push(day.format("%Y-%m-%d").to_string());
+            day += Duration::days(1);
+        }
+        let mut records = Vec::new();
+        for day in self.load_days_parallel(&dates, |storage, date| storage.get_summaries(date)) {
+            records.extend(day?.into_iter().filter(|record| record.action != "private"));
+        }
+        records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(records)
+    }
+
+    /// 按应用统计使用时长、切换次数和最长专注时段；compare 时附带与上一等长周期的对比
+    pub fn app_usage_stats(&self, start: NaiveDate, end: NaiveDate, compare: bool) -> Result<AppUsageStats, String> {
+        if end < start {
+            return Err("结束日期不能早于开始日期".to_string());
+        }
+        if (end - start).num_days() >= MAX_APP_USAGE_DAYS {
+            return Err(format!("统计范围不能超过 {} 天", MAX_APP_USAGE_DAYS));
+        }
+        let mut stats = compute_app_usage(&self.records_between(start, end)?);
+        stats.start_date = start.format("%Y-%m-%d").to_string();
+        stats.end_date = end.format("%Y-%m-%d").to_string();
+        if compare {
+            let days = (end - start).num_days() + 1;
+            let previous = compute_app_usage(&self.records_between(
+                start - Duration::days(days),
+                end - Duration::days(days),
+            )?);
+            stats.comparison = compare_app_usage(&stats.apps, &previous.apps);
+        }
+        Ok(stats)
+    }
+}
+
+fn app_name(record: &SummaryRecord) -> &str {
+    let app = record.app.trim();
+    if app.is_empty() { "Unknown" } else { app }
+}
+
+/// records 需按时间排序；每条记录的时长按到下一条的间隔估算，间隔过长视为离开
+pub fn compute_app_usage(records: &[SummaryRecord]) -> AppUsageStats {
+    // app -> (秒数, 记录数, 段数, 最长连续秒数)
+    let mut apps: HashMap<&str, (f64, usize, usize, f64)> = HashMap::new();
+    let mut active_seconds = 0f64;
+    let mut context_switches = 0;
+    let mut longest: Option<(usize, usize, f64)> = None;  // 起止下标和秒数
+    let mut streak: Option<(usize, f64)> = None;  // 当前连续段的起始下标和秒数
+
+    for (index, record) in records.iter().enumerate() {
+        let Some(time) = parse_timestamp(&record.timestamp) else {
+            continue;
+        };
+        let app = app_name(record);
+        let seconds = records
+            .get(index + 1)
+            .and_then(|next| parse_timestamp(&next.timestamp))
+            .map(|next| (next - time).num_seconds())
+            .filter(|gap| *gap > 0 && *gap <= MAX_RECORD_GAP_SECS)
+            .unwrap_or(0) as f64;
+        active_seconds += seconds;
+
+        let entry = apps.entry(app).or_insert((0.0, 0, 0, 0.0));
+        entry.0 += seconds;
+        entry.1 += 1;
+        let (start, streak_seconds) = match streak {
+            Some((start, streak_seconds)) => (start, streak_seconds + seconds),
+            None => {
+                entry.2 += 1;
+                (index, seconds)
+            }
+        };
+        entry.3 = entry.3.max(streak_seconds);
+        if longest.map_or(true, |(_, _, best)| streak_seconds > best) {
+            longest = Some((start, index, streak_seconds));
+        }
+
+        // 下一条换了应用或中途离开时，当前连续段结束
+        let next_app = records.get(index + 1).map(app_name);
+        streak = if seconds > 0.0 && next_app == Some(app) {
+            Some((start, streak_seconds))
+        } else {
+            if seconds > 0.0 && next_app.is_some() {
+                context_switches += 1;
+            }
+            None
+        };
+    }
+
+    let mut items: Vec<AppUsageItem> = apps
+        .into_iter()
+        .map(|(app, (seconds, records, sessions, longest))| AppUsageItem {
+            app: app.to_string(),
+            minutes: round_minutes(seconds),
+            records,
+            sessions,
+            longest_streak_minutes: round_minutes(longest),
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.minutes
+            .partial_cmp(&a.minutes)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.records.cmp(&a.records))
+    });
+
+    AppUsageStats {
+        start_date: String::new(),
+        end_date: String::new(),
+        active_minutes: round_minutes(active_seconds),
+        context_switches,
+        longest_focus: longest
+            .filter(|(_, _, seconds)| *seconds > 0.0)
+            .map(|(start, end, seconds)| FocusStreak {
+                app: app_name(&records[start]).to_string(),
+                start: records[start].timestamp.clone(),
+                end: records
+                    .get(end + 1)
+                    .map_or(&records[end].timestamp, |next| &next.timestamp)
+                    .clone(),
+                minutes: round_minutes(seconds),
+            }),
+        apps: items,
+        comparison: Vec::new(),
+    }
+}
+
+/// 按变化幅度排序的应用时长对比
+pub fn compare_app_usage(current: &[AppUsageItem], previous: &[AppUsageItem]) -> Vec<AppUsageChange> {
+    let mut minutes: HashMap<&str, (f64, f64)> = HashMap::new();
+    for item in current {
+        minutes.entry(item.app.as_str()).or_default().0 = item.minutes;
+    }
+    for item in previous {
+        minutes.entry(item.app.as_str()).or_default().1 = item.minutes;
+    }
+    let mut changes: Vec<AppUsageChange> = minutes
+        .into_iter()
+        .filter(|(_, (now, before))| now.max(*before) >= MIN_COMPARE_MINUTES)
+        .map(|(app, (now, before))| AppUsageChange {
+            app: app.to_string(),
+            minutes: now,
+            previous_minutes: before,
+            change_percent: (before > 0.0).then(|| ((now - before) / before * 1000.0).round() / 10.0),
+        })
+        .collect();
+    changes.sort_by(|a, b| {
+        let delta = |change: &AppUsageChange| (change.minutes - change.previous_minutes).abs();
+        delta(b).partial_cmp(&delta(a)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    changes
+}
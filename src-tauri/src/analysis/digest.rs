@@ -0,0 +1,414 @@
+use super::app_usage::{compare_app_usage, compute_app_usage, AppUsageChange};
+use crate::model::ModelManager;
+use crate::storage::{storage_actor, StorageManager, StoragePriority, SummaryRecord};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+const DIGEST_CHECK_INTERVAL_SECS: u64 = 30 * 60;
+// 相邻记录间隔超过该值视为离开，不计入时长
+pub(super) const MAX_RECORD_GAP_SECS: i64 = 5 * 60;
+const TOP_N: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.map(|v| v.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("daily") | Some("day") => Ok(DigestPeriod::Daily),
+            Some("weekly") | Some("week") => Ok(DigestPeriod::Weekly),
+            Some(other) => Err(format!("不支持的摘要周期: {}", other)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            DigestPeriod::Daily => "daily",
+            DigestPeriod::Weekly => "weekly",
+        }
+    }
+
+    /// 返回周期的起止日期（周报从周一开始）
+    pub fn range(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            DigestPeriod::Daily => (date, date),
+            DigestPeriod::Weekly => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                (start, start + Duration::days(6))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestItem {
+    pub name: String,
+    pub count: usize,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestBucket {
+    pub label: String, // 日报为小时 "09:00"，周报为日期
+    pub count: usize,
+    pub minutes: f64,
+    pub top_app: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestIssue {
+    pub timestamp: String,
+    pub issue_type: String,
+    pub summary: String,
+    #[serde(default)]
+    pub resolution: String,
+}
+
+/// 日报/周报
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestRecord {
+    pub period: String, // daily | weekly
+    pub start_date: String,
+    pub end_date: String,
+    pub generated_at: String,
+    pub record_count: usize,
+    pub active_minutes: f64,
+    pub apps: Vec<DigestItem>,
+    pub intents: Vec<DigestItem>,
+    pub scenes: Vec<DigestItem>,
+    pub buckets: Vec<DigestBucket>,
+    pub issues: Vec<DigestIssue>,
+    // 周报中与上周相比的应用时长变化
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub app_changes: Vec<AppUsageChange>,
+    #[serde(default)]
+    pub narrative: String,
+}
+
+impl StorageManager {
+    fn digest_path(&self, period: DigestPeriod, start: NaiveDate) -> PathBuf {
+        self.get_data_dir()
+            .join("digests")
+            .join(format!("{}-{}.json", period.as_str(), start.format("%Y-%m-%d")))
+    }
+
+    pub fn load_digest(&self, period: DigestPeriod, date: NaiveDate) -> Result<Option<DigestRecord>, String> {
+        let (start, _) = period.range(date);
+        let path = self.digest_path(period, start);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = self.read_data_string(&path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("解析摘要失败: {}", e))
+    }
+
+    pub fn save_digest(&self, period: DigestPeriod, digest: &DigestRecord) -> Result<(), String> {
+        let start = NaiveDate::parse_from_str(&digest.start_date, "%Y-%m-%d")
+            .map_err(|e| format!("摘要日期无效: {}", e))?;
+        let path = self.digest_path(period, start);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建摘要目录失败: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(digest)
+            .map_err(|e| format!("序列化摘要失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    /// 汇总周期内的记录（不调用模型，narrative 为空）
+    pub fn build_digest(&self, period: DigestPeriod, date: NaiveDate) -> Result<DigestRecord, String> {
+        let (start, end) = period.range(date);
+        let records = self.records_between(start, end)?;
+        let mut digest = aggregate_digest(period, start, end, &records);
+        if period == DigestPeriod::Weekly {
+            let previous = self.records_between(start - Duration::days(7), end - Duration::days(7))?;
+            if !previous.is_empty() {
+                let mut changes = compare_app_usage(
+                    &compute_app_usage(&records).apps,
+                    &compute_app_usage(&previous).apps,
+                );
+                changes.truncate(TOP_N);
+                digest.app_changes = changes;
+            }
+        }
+        Ok(digest)
+    }
+}
+
+pub(super) fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.get(..19).unwrap_or(value), "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+fn aggregate_digest(
+    period: DigestPeriod,
+    start: NaiveDate,
+    end: NaiveDate,
+    records: &[SummaryRecord],
+) -> DigestRecord {
+    let mut apps: HashMap<String, (usize, f64)> = HashMap::new();
+    let mut intents: HashMap<String, (usize, f64)> = HashMap::new();
+    let mut scenes: HashMap<String, (usize, f64)> = HashMap::new();
+    let mut buckets: Vec<(String, usize, f64, HashMap<String, f64>)> = Vec::new();
+    let mut active_seconds = 0f64;
+    let mut issues = Vec::new();
+
+    for (idx, record) in records.iter().enumerate() {
+        let Some(time) = parse_timestamp(&record.timestamp) else {
+            continue;
+        };
+        // 每条记录的时长按到下一条记录的间隔估算
+        let seconds = records
+            .get(idx + 1)
+            .and_then(|next| parse_timestamp(&next.timestamp))
+            .map(|next| (next - time).num_seconds())
+            .filter(|gap| *gap > 0 && *gap <= MAX_RECORD_GAP_SECS)
+            .unwrap_or(0) as f64;
+        active_seconds += seconds;
+
+        let app = if record.app.trim().is_empty() { "Unknown" } else { record.app.trim() };
+        add_item(&mut apps, app, seconds);
+        if !record.intent.trim().is_empty() {
+            add_item(&mut intents, record.intent.trim(), seconds);
+        }
+        if !record.scene.trim().is_empty() {
+            add_item(&mut scenes, record.scene.trim(), seconds);
+        }
+
+        let label = match period {
+            DigestPeriod::Daily => format!("{:02}:00", time.hour()),
+            DigestPeriod::Weekly => time.format("%Y-%m-%d").to_string(),
+        };
+        if buckets.last().map(|b| b.0 != label).unwrap_or(true) {
+            buckets.push((label, 0, 0.0, HashMap::new()));
+        }
+        if let Some(bucket) = buckets.last_mut() {
+            bucket.1 += 1;
+            bucket.2 += seconds;
+            *bucket.3.entry(app.to_string()).or_insert(0.0) += seconds;
+        }
+
+        if record.has_issue {
+            issues.push(DigestIssue {
+                timestamp: record.timestamp.clone(),
+                issue_type: record.issue_type.clone(),
+                summary: if record.issue_summary.is_empty() {
+                    record.summary.clone()
+                } else {
+                    record.issue_summary.clone()
+                },
+                resolution: record.resolution.clone(),
+            });
+        }
+    }
+
+    DigestRecord {
+        period: period.as_str().to_string(),
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        generated_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        record_count: records.len(),
+        active_minutes: round_minutes(active_seconds),
+        apps: top_items(apps),
+        intents: top_items(intents),
+        scenes: top_items(scenes),
+        buckets: buckets
+            .into_iter()
+            .map(|(label, count, seconds, apps)| DigestBucket {
+                label,
+                count,
+                minutes: round_minutes(seconds),
+                top_app: apps
+                    .into_iter()
+                    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(app, _)| app)
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        issues,
+        app_changes: Vec::new(),
+        narrative: String::new(),
+    }
+}
+
+fn add_item(map: &mut HashMap<String, (usize, f64)>, name: &str, seconds: f64) {
+    let entry = map.entry(name.to_string()).or_insert((0, 0.0));
+    entry.0 += 1;
+    entry.1 += seconds;
+}
+
+fn top_items(map: HashMap<String, (usize, f64)>) -> Vec<DigestItem> {
+    let mut items: Vec<DigestItem> = map
+        .into_iter()
+        .map(|(name, (count, seconds))| DigestItem {
+            name,
+            count,
+            minutes: round_minutes(seconds),
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.minutes
+            .partial_cmp(&a.minutes)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.count.cmp(&a.count))
+    });
+    items.truncate(TOP_N);
+    items
+}
+
+pub(super) fn round_minutes(seconds: f64) -> f64 {
+    (seconds / 60.0 * 10.0).round() / 10.0
+}
+
+fn build_digest_prompt(digest: &DigestRecord) -> String {
+    let format_items = |items: &[DigestItem]| {
+        items
+            .iter()
+            .map(|i| format!("{}（{} 分钟）", i.name, i.minutes))
+            .collect::<Vec<_>>()
+            .join("、")
+    };
+    let timeline = digest
+        .buckets
+        .iter()
+        .map(|b| format!("- {}: {} 分钟，主要在 {}", b.label, b.minutes, b.top_app))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let issues = digest
+        .issues
+        .iter()
+        .take(20)
+        .map(|i| {
+            let resolution = match i.resolution.as_str() {
+                "resolved" => "（已解决）",
+                "unresolved" => "（未解决）",
+                _ => "",
+            };
+            format!("- {} {}{}", i.timestamp.get(11..16).unwrap_or(""), i.summary, resolution)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let period = if digest.period == "weekly" { "本周" } else { "当天" };
+    let changes = digest
+        .app_changes
+        .iter()
+        .take(5)
+        .map(describe_app_change)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"请根据以下{}的屏幕活动统计，为用户写一段简洁的工作回顾（中文，150-300 字）：概括主要在做什么、时间分配、遇到的问题及是否解决，最后给出一条改进建议。不要编造统计中没有的内容。
+
+时间范围: {} ~ {}
+有效时长: {} 分钟
+常用应用: {}
+主要意图: {}
+场景分布: {}
+
+时间线:
+{}
+
+遇到的问题:
+{}{}"#,
+        period,
+        digest.start_date,
+        digest.end_date,
+        digest.active_minutes,
+        format_items(&digest.apps),
+        format_items(&digest.intents),
+        format_items(&digest.scenes),
+        timeline,
+        if issues.is_empty() { "无".to_string() } else { issues },
+        if changes.is_empty() { String::new() } else { format!("\n\n与上周相比:\n{}", changes) }
+    )
+}
+
+/// 如「Chrome: 12.5 小时，比上周多 30%」
+pub fn describe_app_change(change: &AppUsageChange) -> String {
+    let hours = |minutes: f64| format!("{:.1}", minutes / 60.0);
+    match change.change_percent {
+        Some(percent) if percent >= 0.5 => format!("- {}: {} 小时，比上周多 {:.0}%", change.app, hours(change.minutes), percent),
+        Some(percent) if percent <= -0.5 => format!("- {}: {} 小时，比上周少 {:.0}%", change.app, hours(change.minutes), -percent),
+        Some(_) => format!("- {}: {} 小时，与上周持平", change.app, hours(change.minutes)),
+        None => format!("- {}: {} 小时，上周未使用", change.app, hours(change.minutes)),
+    }
+}
+
+/// 汇总记录并调用一次模型生成叙述，保存后返回
+pub async fn generate_digest(period: DigestPeriod, date: NaiveDate) -> Result<DigestRecord, String> {
+    let (mut digest, config) = storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            Ok((storage.build_digest(period, date)?, storage.load_config()?))
+        })
+        .await?;
+
+    if digest.record_count > 0 {
+        let model_config = ModelManager::resolve_model_config(&config.model, None);
+        digest.narrative = ModelManager::new()
+            .chat_with_system_prompt(
+                &model_config,
+                "你是用户的工作回顾助手，根据屏幕活动统计撰写简洁、客观的总结。",
+                &build_digest_prompt(&digest),
+                None,
+            )
+            .await?
+            .trim()
+            .to_string();
+    }
+
+    let to_save = digest.clone();
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| storage.save_digest(period, &to_save))
+        .await?;
+    Ok(digest)
+}
+
+/// 定时生成昨天的日报，每周一生成上周的周报；已生成的不会重复调用模型
+pub fn start_digest_scheduler(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let yesterday = Local::now().date_naive() - Duration::days(1);
+            let mut due = vec![(DigestPeriod::Daily, yesterday)];
+            if Local::now().weekday() == chrono::Weekday::Mon {
+                due.push((DigestPeriod::Weekly, yesterday));
+            }
+            for (period, date) in due {
+                let pending = storage_actor()
+                    .run(StoragePriority::Background, move |storage| {
+                        if !storage.load_config()?.storage.digest_enabled {
+                            return Ok(false);
+                        }
+                        let (start, end) = period.range(date);
+                        let exists = storage.load_digest(period, date)?.is_some();
+                        let mut has_records = false;
+                        let mut day = start;
+                        while day <= end && !has_records {
+                            has_records = !storage.get_summaries(&day.format("%Y-%m-%d").to_string())?.is_empty();
+                            day += Duration::days(1);
+                        }
+                        Ok(!exists && has_records)
+                    })
+                    .await;
+                match pending {
+                    Ok(true) => match generate_digest(period, date).await {
+                        Ok(digest) => {
+                            let _ = app_handle.emit("digest-generated", digest);
+                        }
+                        Err(err) => eprintln!("[digest] 生成{}失败: {}", period.as_str(), err),
+                    },
+                    Ok(false) => {}
+                    Err(err) => eprintln!("[digest] 检查失败: {}", err),
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(DIGEST_CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
@@ -0,0 +1,135 @@
+use crate::storage::{PromptExperiment, StorageManager};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const MAX_REPORT_DAYS: i64 = 90;
+
+/// 本帧使用的提示词变体
+pub struct PromptVariant {
+    pub label: String,     // 实验名:A / 实验名:B，写入记录
+    pub template: String,  // 空表示内置提示词
+}
+
+/// 实验启用时按帧交替返回 A/B 变体
+pub fn next_prompt_variant(experiment: &PromptExperiment) -> Option<PromptVariant> {
+    static FRAME_COUNTER: AtomicU64 = AtomicU64::new(0);
+    if !experiment.enabled {
+        return None;
+    }
+    let name = match experiment.name.trim() {
+        "" => "default",
+        name => name,
+    };
+    let (variant, template) = if FRAME_COUNTER.fetch_add(1, Ordering::Relaxed) % 2 == 0 {
+        ("A", &experiment.variant_a)
+    } else {
+        ("B", &experiment.variant_b)
+    };
+    Some(PromptVariant {
+        label: format!("{}:{}", name, variant),
+        template: template.trim().to_string(),
+    })
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VariantStats {
+    pub variant: String,
+    pub records: usize,
+    pub issues: usize,
+    pub avg_confidence: f32,
+    pub feedback_good: usize,
+    pub feedback_bad: usize,
+    pub accuracy: Option<f32>,  // good / (good + bad)，没有评价时为空
+    pub resolved: usize,        // 提醒复查结果
+    pub unresolved: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentReport {
+    pub name: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub variants: Vec<VariantStats>,
+}
+
+impl StorageManager {
+    /// 按变体汇总实验期间的记录：问题检出、平均置信度、用户评价和复查结果
+    pub fn experiment_report(
+        &self,
+        name: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<ExperimentReport, String> {
+        if end < start {
+            return Err("结束日期不能早于开始日期".to_string());
+        }
+        if (end - start).num_days() >= MAX_REPORT_DAYS {
+            return Err(format!("统计范围不能超过 {} 天", MAX_REPORT_DAYS));
+        }
+        let prefix = format!("{}:", name.trim());
+
+        let mut stats: BTreeMap<String, (VariantStats, f32)> = BTreeMap::new();
+        let mut date = start;
+        while date <= end {
+            for record in self.get_summaries(&date.format("%Y-%m-%d").to_string())? {
+                if record.prompt_variant.is_empty()
+                    || (!name.trim().is_empty() && !record.prompt_variant.starts_with(&prefix))
+                {
+                    continue;
+                }
+                let (entry, confidence_sum) = stats
+                    .entry(record.prompt_variant.clone())
+                    .or_insert_with(|| {
+                        (
+                            VariantStats {
+                                variant: record.prompt_variant.clone(),
+                                ..Default::default()
+                            },
+                            0.0,
+                        )
+                    });
+                entry.records += 1;
+                *confidence_sum += record.confidence;
+                if record.has_issue {
+                    entry.issues += 1;
+                }
+                match record.feedback.as_str() {
+                    "good" => entry.feedback_good += 1,
+                    "bad" => entry.feedback_bad += 1,
+                    _ => {}
+                }
+                match record.resolution.as_str() {
+                    "resolved" => entry.resolved += 1,
+                    "unresolved" => entry.unresolved += 1,
+                    _ => {}
+                }
+            }
+            date = match date.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        let variants = stats
+            .into_values()
+            .map(|(mut entry, confidence_sum)| {
+                if entry.records > 0 {
+                    entry.avg_confidence = confidence_sum / entry.records as f32;
+                }
+                let rated = entry.feedback_good + entry.feedback_bad;
+                if rated > 0 {
+                    entry.accuracy = Some(entry.feedback_good as f32 / rated as f32);
+                }
+                entry
+            })
+            .collect();
+        Ok(ExperimentReport {
+            name: name.trim().to_string(),
+            start_date: start.format("%Y-%m-%d").to_string(),
+            end_date: end.format("%Y-%m-%d").to_string(),
+            variants,
+        })
+    }
+}
@@ -1,5 +1,17 @@
+pub mod app_usage;
 pub mod diff;
+pub mod digest;
+pub mod experiments;
 pub mod extractor;
+pub mod rules;
+pub mod semantic;
+pub mod skill_suggestions;
 
+pub use app_usage::*;
 pub use diff::*;
+pub use digest::*;
+pub use experiments::*;
 pub use extractor::*;
+pub use rules::*;
+pub use semantic::*;
+pub use skill_suggestions::*;
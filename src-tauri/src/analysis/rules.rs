@@ -0,0 +1,132 @@
+use crate::storage::AlertRule;
+use regex::{Regex, RegexBuilder};
+
+const MAX_SNIPPET_CHARS: usize = 120;
+const MAX_PATTERN_CHARS: usize = 500;
+
+/// 规则匹配的输入：前台应用、窗口标题和（可选的）OCR 文字
+pub struct RuleInput<'a> {
+    pub app: &'a str,
+    pub window_title: &'a str,
+    pub ocr_text: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub field: &'static str,
+    pub snippet: String,  // 命中所在的行
+    pub urgency: String,
+    pub message: String,
+    pub suggestion: String,
+}
+
+fn compile(rule: &AlertRule) -> Result<Regex, String> {
+    let pattern = rule.pattern.trim();
+    if pattern.is_empty() {
+        return Err(format!("规则 {} 的匹配内容为空", rule.id));
+    }
+    if pattern.chars().count() > MAX_PATTERN_CHARS {
+        return Err(format!("规则 {} 的匹配内容过长", rule.id));
+    }
+    let source = if rule.is_regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("规则 {} 的正则无效: {}", rule.id, e))
+}
+
+pub fn validate_alert_rule(rule: &AlertRule) -> Result<(), String> {
+    if rule.id.trim().is_empty() {
+        return Err("规则 ID 不能为空".to_string());
+    }
+    for field in &rule.fields {
+        if !matches!(field.as_str(), "ocr" | "title" | "app") {
+            return Err(format!("规则 {} 的匹配范围无效: {}（可选 ocr/title/app）", rule.id, field));
+        }
+    }
+    compile(rule).map(|_| ())
+}
+
+/// 是否有启用的规则需要 OCR 文字
+pub fn rules_need_ocr(rules: &[AlertRule]) -> bool {
+    rules
+        .iter()
+        .any(|rule| rule.enabled && rule.fields.iter().any(|field| field == "ocr"))
+}
+
+/// 依次检查启用的规则，每条规则最多返回一个命中；无效的规则跳过
+pub fn evaluate_alert_rules(rules: &[AlertRule], input: &RuleInput) -> Vec<RuleMatch> {
+    let app = input.app.to_lowercase();
+    let mut matches = Vec::new();
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        if !rule.apps.is_empty()
+            && !rule
+                .apps
+                .iter()
+                .any(|name| !name.trim().is_empty() && app.contains(&name.trim().to_lowercase()))
+        {
+            continue;
+        }
+        let regex = match compile(rule) {
+            Ok(regex) => regex,
+            Err(err) => {
+                eprintln!("[rules] {}", err);
+                continue;
+            }
+        };
+        let hit = rule.fields.iter().find_map(|field| {
+            let (name, text): (&'static str, &str) = match field.as_str() {
+                "ocr" => ("ocr", input.ocr_text?),
+                "title" => ("title", input.window_title),
+                "app" => ("app", input.app),
+                _ => return None,
+            };
+            let found = regex.find(text)?;
+            Some((name, snippet_around(text, found.start(), found.end())))
+        });
+        let Some((field, snippet)) = hit else {
+            continue;
+        };
+        let rule_name = if rule.name.trim().is_empty() {
+            rule.pattern.trim().to_string()
+        } else {
+            rule.name.trim().to_string()
+        };
+        let message = if rule.message.trim().is_empty() {
+            format!("规则「{}」命中：{}", rule_name, snippet)
+        } else {
+            rule.message.trim().to_string()
+        };
+        matches.push(RuleMatch {
+            rule_id: rule.id.clone(),
+            rule_name,
+            field,
+            snippet,
+            urgency: rule.urgency.clone(),
+            message,
+            suggestion: rule.suggestion.trim().to_string(),
+        });
+    }
+    matches
+}
+
+/// 取命中位置所在的整行，过长时截断
+fn snippet_around(text: &str, start: usize, end: usize) -> String {
+    let line_start = text[..start].rfind('\n').map_or(0, |idx| idx + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |idx| end + idx);
+    let line = text[line_start..line_end].trim();
+    if line.chars().count() <= MAX_SNIPPET_CHARS {
+        line.to_string()
+    } else {
+        let mut snippet: String = line.chars().take(MAX_SNIPPET_CHARS).collect();
+        snippet.push('…');
+        snippet
+    }
+}
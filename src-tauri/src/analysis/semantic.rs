@@ -0,0 +1,95 @@
+use crate::logs;
+use crate::model::{with_usage_feature, ModelManager};
+use crate::storage::{
+    parse_time_expression_with, storage_actor, Config, SemanticQuery, StorageManager, StoragePriority, TimeRange,
+};
+use chrono::{Duration, Local};
+
+const EMBEDDING_INDEX_INTERVAL_SECS: u64 = 5 * 60;
+const EMBEDDING_BATCH_SIZE: usize = 32;
+// 每轮最多计算的记录数，首次开启时分多轮补齐历史记录
+const EMBEDDING_MAX_PER_CYCLE: usize = 256;
+
+/// 后台为摘要记录计算向量：每 5 分钟检查一次，从今天往前补齐最近 index_days 天
+pub fn start_embedding_indexer() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            if let Err(err) = index_pending_embeddings().await {
+                logs::warn("embedding", format!("计算记录向量失败: {}", err));
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(EMBEDDING_INDEX_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+async fn index_pending_embeddings() -> Result<(), String> {
+    let config = StorageManager::new().load_config()?;
+    if !config.embedding.enabled {
+        return Ok(());
+    }
+    let model = ModelManager::embedding_model(&config.model, &config.embedding.model);
+    let today = Local::now().date_naive();
+    let mut remaining = EMBEDDING_MAX_PER_CYCLE;
+    for offset in 0..config.embedding.index_days.max(1) as i64 {
+        if remaining == 0 {
+            break;
+        }
+        let date = (today - Duration::days(offset)).format("%Y-%m-%d").to_string();
+        let pending = {
+            let (date, model) = (date.clone(), model.clone());
+            storage_actor()
+                .run(StoragePriority::Background, move |storage| {
+                    Ok(storage.records_missing_embeddings(&date, &model, remaining))
+                })
+                .await?
+        };
+        for batch in pending.chunks(EMBEDDING_BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+            let vectors = with_usage_feature(
+                "embedding",
+                ModelManager::new().embed_texts(&config.model, &model, &texts),
+            )
+            .await?;
+            let items: Vec<(String, Vec<f32>)> = batch
+                .iter()
+                .map(|(timestamp, _)| timestamp.clone())
+                .zip(vectors)
+                .collect();
+            remaining = remaining.saturating_sub(items.len());
+            let (date, model) = (date.clone(), model.clone());
+            storage_actor()
+                .run(StoragePriority::Background, move |storage| storage.save_embeddings(&date, &model, items))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// 计算问题向量；问题里没有时间表达时在全部已索引的天里匹配。失败时只记日志，退回关键词检索
+pub async fn semantic_query(config: &Config, message: &str) -> Option<SemanticQuery> {
+    let model = ModelManager::embedding_model(&config.model, &config.embedding.model);
+    let result = with_usage_feature(
+        "embedding",
+        ModelManager::new().embed_texts(&config.model, &model, &[message.to_string()]),
+    )
+    .await;
+    let vector = match result {
+        Ok(mut vectors) if !vectors.is_empty() => vectors.remove(0),
+        Ok(_) => return None,
+        Err(err) => {
+            logs::warn("embedding", format!("计算问题向量失败，仅使用关键词检索: {}", err));
+            return None;
+        }
+    };
+    let options = config.calendar.time_options().unwrap_or_default();
+    let range = parse_time_expression_with(message, Local::now().naive_local(), &options)
+        .is_none()
+        .then(|| TimeRange::Days(config.embedding.index_days.max(1)));
+    Some(SemanticQuery {
+        model,
+        vector,
+        min_similarity: config.embedding.min_similarity,
+        max_results: config.embedding.max_results,
+        range,
+    })
+}
@@ -0,0 +1,331 @@
+use crate::analysis::DigestPeriod;
+use crate::model::{parse_json_lenient, with_usage_feature, ModelManager};
+use crate::skills::SkillManager;
+use crate::storage::{storage_actor, StorageManager, StoragePriority};
+use chrono::{Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Emitter};
+
+const SUGGESTIONS_FILE: &str = "skill_suggestions.json";
+const SUGGESTION_CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+// 一周内至少出现这么多次才算反复出现的痛点
+const MIN_PAIN_POINT_OCCURRENCES: usize = 3;
+const MAX_PAIN_POINTS: usize = 8;
+const MAX_EXAMPLES: usize = 5;
+const MAX_SUGGESTIONS_PER_RUN: usize = 3;
+
+/// 一周内反复出现的问题或求助场景
+#[derive(Debug, Clone, Serialize)]
+pub struct PainPoint {
+    pub scene: String,
+    pub issue_type: String,
+    pub occurrences: usize,
+    pub unresolved: usize,
+    pub apps: Vec<String>,
+    pub examples: Vec<String>,
+}
+
+/// 待用户确认的新 skill 建议
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillSuggestion {
+    pub name: String,  // 符合 skill 命名规则，同时作为建议 id
+    pub description: String,
+    pub instructions: String,  // SKILL.md 正文草稿
+    #[serde(default)]
+    pub scene: String,
+    #[serde(default)]
+    pub occurrences: usize,
+    #[serde(default)]
+    pub evidence: Vec<String>,
+    pub week_start: String,
+    pub created_at: String,
+    #[serde(default = "default_status")]
+    pub status: String,  // pending | approved | dismissed
+}
+
+fn default_status() -> String {
+    "pending".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SuggestionStore {
+    #[serde(default)]
+    last_week_start: String,  // 最近一次生成建议的周（周一日期）
+    #[serde(default)]
+    suggestions: Vec<SkillSuggestion>,
+}
+
+impl StorageManager {
+    fn load_suggestion_store(&self) -> Result<SuggestionStore, String> {
+        let path = self.get_data_dir().join(SUGGESTIONS_FILE);
+        if !path.exists() {
+            return Ok(SuggestionStore::default());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_suggestion_store(&self, store: &SuggestionStore) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(store)
+            .map_err(|e| format!("序列化 skill 建议失败: {}", e))?;
+        self.write_data_file(&self.get_data_dir().join(SUGGESTIONS_FILE), content.as_bytes())
+    }
+
+    pub fn list_skill_suggestions(&self, include_closed: bool) -> Result<Vec<SkillSuggestion>, String> {
+        let mut suggestions = self.load_suggestion_store()?.suggestions;
+        if !include_closed {
+            suggestions.retain(|item| item.status == "pending");
+        }
+        suggestions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(suggestions)
+    }
+
+    /// 更新建议状态，返回更新后的建议
+    pub fn set_skill_suggestion_status(&self, name: &str, status: &str) -> Result<SkillSuggestion, String> {
+        let mut store = self.load_suggestion_store()?;
+        let item = store
+            .suggestions
+            .iter_mut()
+            .find(|item| item.name == name)
+            .ok_or_else(|| format!("skill 建议不存在: {}", name))?;
+        item.status = status.to_string();
+        let updated = item.clone();
+        self.save_suggestion_store(&store)?;
+        Ok(updated)
+    }
+
+    /// 统计周期内反复出现的问题/求助场景，按次数排序
+    pub fn collect_pain_points(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<PainPoint>, String> {
+        let mut groups: HashMap<(String, String), PainPoint> = HashMap::new();
+        let mut day = start;
+        while day <= end {
+            for record in self.get_summaries(&day.format("%Y-%m-%d").to_string())? {
+                let wants_help = record.has_issue || record.urgency == "high" || record.urgency == "medium";
+                if record.action == "private" || !wants_help {
+                    continue;
+                }
+                let scene = record.scene.trim().to_lowercase();
+                let issue_type = record.issue_type.trim().to_string();
+                if scene.is_empty() && issue_type.is_empty() {
+                    continue;
+                }
+                let point = groups
+                    .entry((scene.clone(), issue_type.clone()))
+                    .or_insert_with(|| PainPoint {
+                        scene,
+                        issue_type,
+                        occurrences: 0,
+                        unresolved: 0,
+                        apps: Vec::new(),
+                        examples: Vec::new(),
+                    });
+                point.occurrences += 1;
+                if record.resolution == "unresolved" {
+                    point.unresolved += 1;
+                }
+                if !record.app.is_empty() && !point.apps.contains(&record.app) {
+                    point.apps.push(record.app.clone());
+                }
+                let example = if record.issue_summary.is_empty() {
+                    record.summary.clone()
+                } else {
+                    record.issue_summary.clone()
+                };
+                if point.examples.len() < MAX_EXAMPLES && !point.examples.contains(&example) {
+                    point.examples.push(example);
+                }
+            }
+            day += Duration::days(1);
+        }
+
+        let mut points: Vec<PainPoint> = groups
+            .into_values()
+            .filter(|point| point.occurrences >= MIN_PAIN_POINT_OCCURRENCES)
+            .collect();
+        points.sort_by(|a, b| {
+            b.occurrences
+                .cmp(&a.occurrences)
+                .then_with(|| b.unresolved.cmp(&a.unresolved))
+        });
+        points.truncate(MAX_PAIN_POINTS);
+        Ok(points)
+    }
+}
+
+fn build_suggestion_prompt(points: &[PainPoint], existing_skills: &[String]) -> String {
+    let points = points
+        .iter()
+        .map(|point| {
+            format!(
+                "- 场景: {}，问题类型: {}，出现 {} 次（未解决 {} 次），应用: {}\n  示例: {}",
+                if point.scene.is_empty() { "未知" } else { point.scene.as_str() },
+                if point.issue_type.is_empty() { "无" } else { point.issue_type.as_str() },
+                point.occurrences,
+                point.unresolved,
+                point.apps.join("、"),
+                point.examples.join("；")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"以下是用户上周在屏幕上反复遇到的问题或需要帮助的场景：
+{}
+
+已有 skills（不要重复）: {}
+
+请挑选最值得沉淀为 skill 的痛点（最多 {} 个），为每个设计一个新 skill。只输出 JSON 数组，不要解释：
+[{{"name": "小写字母、数字和连字符组成的名称", "description": "一句话说明何时使用", "scene": "对应的场景", "instructions": "SKILL.md 正文草稿（Markdown，包含适用场景、排查步骤和注意事项）"}}]
+没有值得建议的 skill 时输出 []。"#,
+        points,
+        if existing_skills.is_empty() { "无".to_string() } else { existing_skills.join(", ") },
+        MAX_SUGGESTIONS_PER_RUN
+    )
+}
+
+fn normalize_skill_name(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            result.push(c);
+        } else if !result.ends_with('-') && !result.is_empty() {
+            result.push('-');
+        }
+    }
+    result.trim_end_matches('-').chars().take(64).collect::<String>().trim_end_matches('-').to_string()
+}
+
+/// 分析 date 所在周的痛点，调用一次模型生成 skill 建议并加入待确认队列
+pub async fn generate_skill_suggestions(date: NaiveDate) -> Result<Vec<SkillSuggestion>, String> {
+    let (start, end) = DigestPeriod::Weekly.range(date);
+    let (points, config) = storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            Ok((storage.collect_pain_points(start, end)?, storage.load_config()?))
+        })
+        .await?;
+    let week_start = start.format("%Y-%m-%d").to_string();
+    if points.is_empty() {
+        let week = week_start.clone();
+        storage_actor()
+            .run(StoragePriority::Background, move |storage| {
+                let mut store = storage.load_suggestion_store()?;
+                store.last_week_start = week;
+                storage.save_suggestion_store(&store)
+            })
+            .await?;
+        return Ok(Vec::new());
+    }
+
+    // 已有 skill 和已提过的建议（包括被拒绝的）都不再重复建议
+    let existing_skills: Vec<String> = SkillManager::new()
+        .discover_skills()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|skill| skill.name)
+        .collect();
+    let known = storage_actor()
+        .run(StoragePriority::Background, |storage| storage.list_skill_suggestions(true))
+        .await?;
+    let mut excluded = existing_skills;
+    excluded.extend(known.into_iter().map(|item| item.name));
+    let mut taken: HashSet<String> = excluded.iter().cloned().collect();
+
+    let model_config = ModelManager::resolve_model_config(&config.model, None);
+    let response = with_usage_feature(
+        "skills",
+        ModelManager::new().chat_with_system_prompt(
+            &model_config,
+            "你是技能设计助手，根据用户反复遇到的问题设计可复用的 skill。",
+            &build_suggestion_prompt(&points, &excluded),
+            None,
+        ),
+    )
+    .await?;
+    let items = parse_json_lenient(&response)
+        .and_then(|value| value.as_array().cloned())
+        .ok_or_else(|| "模型未返回有效的 skill 建议 JSON".to_string())?;
+
+    let created_at = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let mut suggestions = Vec::new();
+    for item in items.iter().take(MAX_SUGGESTIONS_PER_RUN) {
+        let field = |key: &str| item.get(key).and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+        let name = normalize_skill_name(&field("name"));
+        let description = field("description");
+        let instructions = field("instructions");
+        if name.is_empty() || description.is_empty() || instructions.is_empty() || !taken.insert(name.clone()) {
+            continue;
+        }
+        let scene = field("scene");
+        let point = points
+            .iter()
+            .find(|point| !scene.is_empty() && point.scene == scene.to_lowercase());
+        suggestions.push(SkillSuggestion {
+            name,
+            description,
+            instructions,
+            scene,
+            occurrences: point.map_or(0, |point| point.occurrences),
+            evidence: point.map(|point| point.examples.clone()).unwrap_or_default(),
+            week_start: week_start.clone(),
+            created_at: created_at.clone(),
+            status: default_status(),
+        });
+    }
+
+    let to_save = suggestions.clone();
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            let mut store = storage.load_suggestion_store()?;
+            store.last_week_start = week_start;
+            store.suggestions.extend(to_save);
+            storage.save_suggestion_store(&store)
+        })
+        .await?;
+    Ok(suggestions)
+}
+
+/// 确认建议：按草稿创建 skill
+pub fn approve_skill_suggestion(storage: &StorageManager, name: &str) -> Result<SkillSuggestion, String> {
+    let suggestion = storage
+        .list_skill_suggestions(true)?
+        .into_iter()
+        .find(|item| item.name == name)
+        .ok_or_else(|| format!("skill 建议不存在: {}", name))?;
+    if suggestion.status != "pending" {
+        return Err(format!("该建议已处理: {}", suggestion.status));
+    }
+    SkillManager::new().create_skill(&suggestion.name, &suggestion.description, &suggestion.instructions)?;
+    storage.set_skill_suggestion_status(name, "approved")
+}
+
+/// 每周一分析上周的痛点生成 skill 建议；每周只生成一次
+pub fn start_skill_suggestion_scheduler(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let last_week = Local::now().date_naive() - Duration::days(7);
+            let (start, _) = DigestPeriod::Weekly.range(last_week);
+            let week_start = start.format("%Y-%m-%d").to_string();
+            let due = storage_actor()
+                .run(StoragePriority::Background, move |storage| {
+                    if !storage.load_config()?.storage.skill_suggestions_enabled {
+                        return Ok(false);
+                    }
+                    Ok(storage.load_suggestion_store()?.last_week_start < week_start)
+                })
+                .await;
+            match due {
+                Ok(true) => match generate_skill_suggestions(last_week).await {
+                    Ok(suggestions) if !suggestions.is_empty() => {
+                        let _ = app_handle.emit("skill-suggestions", suggestions);
+                    }
+                    Ok(_) => {}
+                    Err(err) => eprintln!("[skill-suggestions] 生成失败: {}", err),
+                },
+                Ok(false) => {}
+                Err(err) => eprintln!("[skill-suggestions] 检查失败: {}", err),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(SUGGESTION_CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
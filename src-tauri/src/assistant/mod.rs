@@ -1,5 +1,7 @@
 pub mod context;
 pub mod intent;
+pub mod packs;
 
 pub use context::*;
 pub use intent::*;
+pub use packs::*;
@@ -0,0 +1,160 @@
+use crate::storage::{Config, ContextPack, StorageManager};
+use chrono::{Duration, Local};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+
+// 最近记录最多看这么多条，足够覆盖 lookback_minutes
+const MAX_RECENT_RECORDS: usize = 200;
+const MAX_PACK_FILE_BYTES: u64 = 1024 * 1024;
+
+/// 当前生效的资料包
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveContextPack {
+    pub id: String,
+    pub name: String,
+    pub scene: String,  // 触发的场景
+    pub chars: usize,
+    pub notes: Vec<String>,  // 读取失败或被截断的文件
+}
+
+pub fn validate_context_pack(pack: &ContextPack) -> Result<(), String> {
+    if pack.name.trim().is_empty() {
+        return Err("资料包名称不能为空".to_string());
+    }
+    if pack.scenes.iter().all(|scene| scene.trim().is_empty()) {
+        return Err(format!("资料包「{}」至少需要一个场景", pack.name.trim()));
+    }
+    if pack.content.trim().is_empty() && pack.files.iter().all(|file| file.trim().is_empty()) {
+        return Err(format!("资料包「{}」没有内容或文件", pack.name.trim()));
+    }
+    Ok(())
+}
+
+/// 最近 lookback_minutes 内记录里出现过的场景（小写），按最近出现排序
+fn recent_scenes(config: &Config, storage: &StorageManager) -> Vec<String> {
+    let minutes = config.context_packs.lookback_minutes.max(1) as i64;
+    let cutoff = (Local::now() - Duration::minutes(minutes))
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+    let mut seen = HashSet::new();
+    storage
+        .get_recent_records(MAX_RECENT_RECORDS, 1)
+        .into_iter()
+        .rev()
+        .filter(|record| record.timestamp >= cutoff && record.action != "private")
+        .map(|record| record.scene.trim().to_lowercase())
+        .filter(|scene| !scene.is_empty() && seen.insert(scene.clone()))
+        .collect()
+}
+
+fn read_pack_text(pack: &ContextPack) -> (String, Vec<String>) {
+    let mut sections = Vec::new();
+    let mut notes = Vec::new();
+    if !pack.content.trim().is_empty() {
+        sections.push(pack.content.trim().to_string());
+    }
+    for path in pack.files.iter().map(|file| file.trim()).filter(|file| !file.is_empty()) {
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        match fs::metadata(path) {
+            Ok(meta) if meta.len() > MAX_PACK_FILE_BYTES => {
+                notes.push(format!("{}（文件过大，已跳过）", name));
+                continue;
+            }
+            Err(err) => {
+                notes.push(format!("{}（读取失败: {}）", name, err));
+                continue;
+            }
+            _ => {}
+        }
+        match fs::read(path).map(String::from_utf8) {
+            Ok(Ok(text)) if !text.trim().is_empty() => {
+                sections.push(format!("### {}\n{}", name, text.trim()));
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) => notes.push(format!("{}（不是文本文件，已跳过）", name)),
+            Err(err) => notes.push(format!("{}（读取失败: {}）", name, err)),
+        }
+    }
+    (sections.join("\n\n"), notes)
+}
+
+/// 按最近场景匹配启用的资料包，返回各包内容；总字数超出 max_chars 时截断后面的包
+fn collect_active_packs(config: &Config, storage: &StorageManager) -> Vec<(ActiveContextPack, String)> {
+    let packs: Vec<&ContextPack> = config
+        .context_packs
+        .items
+        .iter()
+        .filter(|pack| pack.enabled)
+        .collect();
+    if packs.is_empty() {
+        return Vec::new();
+    }
+    let scenes = recent_scenes(config, storage);
+    let mut remaining = config.context_packs.max_chars;
+    let mut active = Vec::new();
+    for scene in &scenes {
+        for pack in &packs {
+            let matched = pack
+                .scenes
+                .iter()
+                .any(|item| item.trim().eq_ignore_ascii_case(scene));
+            if !matched || active.iter().any(|(item, _): &(ActiveContextPack, String)| item.id == pack.id) {
+                continue;
+            }
+            if remaining == 0 {
+                return active;
+            }
+            let (text, mut notes) = read_pack_text(pack);
+            if text.is_empty() {
+                continue;
+            }
+            let mut text = text;
+            if text.chars().count() > remaining {
+                text = text.chars().take(remaining).collect();
+                notes.push("内容超出字数上限，已截断".to_string());
+            }
+            let chars = text.chars().count();
+            remaining -= chars;
+            active.push((
+                ActiveContextPack {
+                    id: pack.id.clone(),
+                    name: pack.name.clone(),
+                    scene: scene.clone(),
+                    chars,
+                    notes,
+                },
+                text,
+            ));
+        }
+    }
+    active
+}
+
+/// 当前会注入对话的资料包（用于界面展示）
+pub fn active_context_packs(config: &Config, storage: &StorageManager) -> Vec<ActiveContextPack> {
+    collect_active_packs(config, storage)
+        .into_iter()
+        .map(|(pack, _)| pack)
+        .collect()
+}
+
+/// 构建资料包上下文部分，没有匹配的资料包时返回空字符串
+pub fn build_context_pack_section(config: &Config, storage: &StorageManager) -> String {
+    let packs = collect_active_packs(config, storage);
+    if packs.is_empty() {
+        return String::new();
+    }
+    let body = packs
+        .iter()
+        .map(|(pack, text)| format!("### {}（场景: {}）\n{}", pack.name, pack.scene, text))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "## 当前场景的参考资料\n用户最近在处理以下场景，回答相关问题时优先参考这些资料：\n\n{}\n\n",
+        body
+    )
+}
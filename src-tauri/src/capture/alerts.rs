@@ -0,0 +1,186 @@
+use super::AssistantAlert;
+use crate::logs;
+use crate::storage::StorageManager;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const ALERT_STATE_FILE: &str = "alert_state.json";
+const ALERT_ENTRY_TTL_HOURS: i64 = 24;
+const ALERT_BUDGET_WINDOW_MINUTES: i64 = 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertEntry {
+    #[serde(default)]
+    pub last_emitted: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub persist_count: u32, // 暂停期间连续出现的次数
+}
+
+/// 提醒状态：冷却、暂停和升级计数，写入 data_dir/alert_state.json 以便重启后保留
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AlertTracker {
+    #[serde(default)]
+    entries: HashMap<String, AlertEntry>,
+    #[serde(default)]
+    held: Vec<AssistantAlert>,  // 用户离开时暂存、回来后再推送的提醒
+    #[serde(default)]
+    pushed_at: Vec<DateTime<Local>>,  // 最近一小时实际推送提醒的时间，用于全局上限
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl AlertTracker {
+    pub fn load(storage: &StorageManager) -> Self {
+        let path = storage.get_data_dir().join(ALERT_STATE_FILE);
+        let mut tracker = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<AlertTracker>(&content).ok())
+            .unwrap_or_default();
+        tracker.path = Some(path);
+        tracker
+    }
+
+    /// 冷却期内或暂停中的提醒不再推送
+    pub fn should_emit(&mut self, key: &str, now: DateTime<Local>, cooldown_seconds: u64) -> bool {
+        let cooldown = Duration::seconds(cooldown_seconds.max(5) as i64);
+        let entry = self.entries.entry(key.to_string()).or_default();
+        if entry.snoozed_until.map_or(false, |until| now < until) {
+            return false;
+        }
+        if let Some(prev) = entry.last_emitted {
+            if now.signed_duration_since(prev) < cooldown {
+                return false;
+            }
+        }
+        entry.last_emitted = Some(now);
+        entry.snoozed_until = None;
+        entry.persist_count = 0;
+        self.save();
+        true
+    }
+
+    /// 全局每小时上限：高紧急度可用满额度，中紧急度保留最后 1/4 给高紧急度，
+    /// 其余（低紧急度等）只能用前一半；允许推送时记下本次推送
+    pub fn take_budget(&mut self, urgency: &str, now: DateTime<Local>, max_per_hour: u32) -> bool {
+        if max_per_hour == 0 {
+            return true;
+        }
+        let window = Duration::minutes(ALERT_BUDGET_WINDOW_MINUTES);
+        self.pushed_at.retain(|at| now.signed_duration_since(*at) < window);
+        let max = max_per_hour as usize;
+        let limit = match urgency {
+            "high" => max,
+            "medium" => max - (max / 4).max(1).min(max - 1),
+            _ => (max / 2).max(1),
+        };
+        if self.pushed_at.len() >= limit {
+            return false;
+        }
+        self.pushed_at.push(now);
+        self.save();
+        true
+    }
+
+    pub fn is_snoozed(&self, key: &str, now: DateTime<Local>) -> bool {
+        self.entries
+            .get(key)
+            .and_then(|entry| entry.snoozed_until)
+            .map_or(false, |until| now < until)
+    }
+
+    /// 暂停期间问题仍在：高紧急度问题连续出现达到阈值时返回 true（需要升级提醒）
+    pub fn record_snoozed_hit(
+        &mut self,
+        key: &str,
+        high_urgency: bool,
+        now: DateTime<Local>,
+        escalation_captures: u32,
+    ) -> bool {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+        if !high_urgency || escalation_captures == 0 {
+            entry.persist_count = 0;
+            return false;
+        }
+        entry.persist_count += 1;
+        if entry.persist_count < escalation_captures {
+            return false;
+        }
+        entry.persist_count = 0;
+        entry.snoozed_until = None;
+        entry.last_emitted = Some(now);
+        self.save();
+        true
+    }
+
+    /// 问题没有连续出现时清零升级计数
+    pub fn reset_streaks_except(&mut self, current_key: Option<&str>) {
+        for (key, entry) in self.entries.iter_mut() {
+            if Some(key.as_str()) != current_key {
+                entry.persist_count = 0;
+            }
+        }
+    }
+
+    /// 暂停提醒；minutes 为 0 时取消暂停
+    pub fn snooze(&mut self, key: &str, minutes: u64, now: DateTime<Local>) {
+        let entry = self.entries.entry(key.to_string()).or_default();
+        entry.persist_count = 0;
+        entry.snoozed_until = if minutes == 0 {
+            None
+        } else {
+            Some(now + Duration::minutes(minutes as i64))
+        };
+        self.save();
+    }
+
+    /// 用户离开时暂存提醒，同一 key 只保留最新一条
+    pub fn hold(&mut self, alert: AssistantAlert) {
+        self.held.retain(|item| item.key != alert.key);
+        self.held.push(alert);
+        self.save();
+    }
+
+    pub fn has_held(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    pub fn take_held(&mut self) -> Vec<AssistantAlert> {
+        let held = std::mem::take(&mut self.held);
+        if !held.is_empty() {
+            self.save();
+        }
+        held
+    }
+
+    fn save(&mut self) {
+        let now = Local::now();
+        let ttl = Duration::hours(ALERT_ENTRY_TTL_HOURS);
+        self.entries.retain(|_, entry| {
+            entry.snoozed_until.map_or(false, |until| until > now)
+                || entry
+                    .last_emitted
+                    .map_or(false, |at| now.signed_duration_since(at) < ttl)
+        });
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(content) => {
+                if let Err(err) = fs::write(path, content) {
+                    logs::warn("capture.alerts", format!("保存提醒状态失败: {}", err));
+                }
+            }
+            Err(err) => logs::warn("capture.alerts", format!("序列化提醒状态失败: {}", err)),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::{build_analysis_prompt, build_window_hint, extract_json_value, parse_analysis};
+use super::{ActiveWindow, AnalysisResult};
+use crate::storage::{CaptureBatchConfig, CaptureRegion};
+use chrono::{DateTime, Local};
+
+/// 等待批量分析的一帧；截图已保存，图片数据留在内存中直到分析
+pub(super) struct BatchFrame {
+    pub now: DateTime<Local>,
+    pub screenshot_ref: Option<String>,
+    pub image_hash: u64,
+    pub image_base64: String,
+    pub active_window: Option<ActiveWindow>,
+    pub changed_regions: Vec<CaptureRegion>,
+}
+
+/// 截屏循环中攒下的待分析帧
+#[derive(Default)]
+pub(super) struct FrameBatch {
+    pub frames: Vec<BatchFrame>,
+}
+
+impl FrameBatch {
+    /// 攒够帧数或第一帧等待超时后应立即分析
+    pub fn is_due(&self, config: &CaptureBatchConfig, now: DateTime<Local>) -> bool {
+        let Some(first) = self.frames.first() else {
+            return false;
+        };
+        self.frames.len() >= config.max_frames.max(1)
+            || (now - first.now).num_seconds() >= config.window_seconds as i64
+    }
+}
+
+/// 批量分析提示词：沿用单帧字段，要求按截图顺序输出 {"frames": [...]}
+pub(super) fn build_batch_prompt(frames: &[BatchFrame], recent_context: &str) -> String {
+    let frame_lines = frames
+        .iter()
+        .enumerate()
+        .map(|(index, frame)| {
+            let window = frame
+                .active_window
+                .as_ref()
+                .map(|window| build_window_hint(&window.process_name, &window.title))
+                .unwrap_or_default();
+            format!(
+                "第 {} 张（{}）{}",
+                index + 1,
+                frame.now.format("%H:%M:%S"),
+                window.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "{}\n\n本次按时间顺序附带了 {} 张截图：\n{}\n\n请逐张分析，不要合并。改为输出一个 JSON 对象 {{\"frames\": [...]}}，数组中每个元素对应一张截图，包含上述全部字段，并额外包含 \"frame\" 字段表示截图序号（从 1 开始）。",
+        build_analysis_prompt("", recent_context, None),
+        frames.len(),
+        frame_lines
+    )
+}
+
+/// 按截图顺序拆分批量分析结果；缺失或无法解析的帧为 None，由调用方放回待分析队列
+pub(super) fn split_batch_analysis(analysis: &str, count: usize) -> Vec<Option<AnalysisResult>> {
+    let mut results: Vec<Option<AnalysisResult>> = (0..count).map(|_| None).collect();
+    let Some(value) = extract_json_value(analysis) else {
+        return results;
+    };
+    let items = match value.get("frames").and_then(|frames| frames.as_array()) {
+        Some(items) => items.clone(),
+        None => value.as_array().cloned().unwrap_or_default(),
+    };
+    for (position, item) in items.iter().enumerate() {
+        if !item.is_object() {
+            continue;
+        }
+        // 优先按模型给出的序号对应，没有序号时按数组顺序
+        let index = item
+            .get("frame")
+            .and_then(|frame| frame.as_u64())
+            .and_then(|frame| (frame as usize).checked_sub(1))
+            .unwrap_or(position);
+        if let Some(slot) = results.get_mut(index) {
+            if slot.is_none() {
+                *slot = Some(parse_analysis(&item.to_string()));
+            }
+        }
+    }
+    results
+}
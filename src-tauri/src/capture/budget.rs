@@ -0,0 +1,117 @@
+use crate::storage::{CaptureBudget, StorageManager};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+const USAGE_REFRESH_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CaptureBudgetStatus {
+    pub analyses_last_hour: u32,
+    pub analyses_today: u32,
+    pub cost_today_usd: f64,
+    pub exhausted_reason: Option<String>,  // 为空表示预算内
+    pub pending_analyses: usize,           // 等待补分析的截图数
+}
+
+/// 截屏分析预算：每小时/每天分析次数和当日费用上限；超出后截图只保存、延后分析
+#[derive(Default)]
+pub struct BudgetGuard {
+    recent: VecDeque<DateTime<Local>>,  // 最近一小时内的分析时间
+    day: Option<NaiveDate>,
+    today_count: u32,
+    // 从用量记录读取的当日截屏分析次数和费用（重启后仍然有效）
+    usage_requests: u32,
+    usage_cost: f64,
+    usage_checked_at: Option<DateTime<Local>>,
+}
+
+impl BudgetGuard {
+    fn roll(&mut self, now: &DateTime<Local>) {
+        let hour_ago = *now - Duration::hours(1);
+        while self.recent.front().map_or(false, |at| *at < hour_ago) {
+            self.recent.pop_front();
+        }
+        let today = now.date_naive();
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.today_count = 0;
+            self.usage_requests = 0;
+            self.usage_cost = 0.0;
+            self.usage_checked_at = None;
+        }
+    }
+
+    fn refresh_usage(&mut self, storage: &StorageManager, now: &DateTime<Local>) {
+        if self
+            .usage_checked_at
+            .map_or(false, |at| (*now - at).num_seconds() < USAGE_REFRESH_SECS)
+        {
+            return;
+        }
+        self.usage_checked_at = Some(*now);
+        let date = now.format("%Y-%m-%d").to_string();
+        if let Ok(entries) = storage.load_usage(&date) {
+            let capture = entries.iter().filter(|entry| entry.feature == "capture");
+            let (requests, cost) = capture.fold((0u64, 0.0f64), |(requests, cost), entry| {
+                (requests + entry.requests, cost + entry.cost_usd)
+            });
+            self.usage_requests = requests.min(u32::MAX as u64) as u32;
+            self.usage_cost = cost;
+        }
+    }
+
+    fn analyses_today(&self) -> u32 {
+        self.today_count.max(self.usage_requests)
+    }
+
+    /// 返回超出预算的原因，None 表示可以继续分析
+    pub fn exhausted_reason(
+        &mut self,
+        budget: &CaptureBudget,
+        storage: &StorageManager,
+        now: &DateTime<Local>,
+    ) -> Option<String> {
+        self.roll(now);
+        if budget.max_analyses_per_hour > 0 && self.recent.len() as u32 >= budget.max_analyses_per_hour {
+            return Some(format!("已达每小时分析上限（{} 次）", budget.max_analyses_per_hour));
+        }
+        if budget.max_analyses_per_day == 0 && budget.daily_cost_cap_usd <= 0.0 {
+            return None;
+        }
+        self.refresh_usage(storage, now);
+        if budget.max_analyses_per_day > 0 && self.analyses_today() >= budget.max_analyses_per_day {
+            return Some(format!("已达每日分析上限（{} 次）", budget.max_analyses_per_day));
+        }
+        if budget.daily_cost_cap_usd > 0.0 && self.usage_cost >= budget.daily_cost_cap_usd {
+            return Some(format!(
+                "今日分析费用约 ${:.2}，已达上限 ${:.2}",
+                self.usage_cost, budget.daily_cost_cap_usd
+            ));
+        }
+        None
+    }
+
+    pub fn record_analysis(&mut self, now: DateTime<Local>) {
+        self.roll(&now);
+        self.recent.push_back(now);
+        self.today_count += 1;
+    }
+
+    pub fn status(
+        &mut self,
+        budget: &CaptureBudget,
+        storage: &StorageManager,
+        now: &DateTime<Local>,
+    ) -> CaptureBudgetStatus {
+        let exhausted_reason = self.exhausted_reason(budget, storage, now);
+        self.refresh_usage(storage, now);
+        CaptureBudgetStatus {
+            analyses_last_hour: self.recent.len() as u32,
+            analyses_today: self.analyses_today(),
+            cost_today_usd: self.usage_cost,
+            exhausted_reason,
+            pending_analyses: storage.pending_capture_count(),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::logs;
+use crate::storage::StorageManager;
+use chrono::{DateTime, Local, NaiveDateTime};
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+const FOCUS_SESSIONS_FILE: &str = "focus_sessions.json";
+const MAX_FOCUS_SESSIONS: usize = 200;
+// 连续偏离目标的帧数达到该值才提醒，避免短暂查资料就被打断
+const DRIFT_CHECKS_BEFORE_NUDGE: u32 = 2;
+const NUDGE_COOLDOWN_SECS: i64 = 5 * 60;
+
+/// 专注时段：用户设定目标后，截屏分析会判断当前活动是否偏离目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusSession {
+    pub id: String,
+    pub goal: String,
+    pub started_at: String,
+    #[serde(default)]
+    pub planned_minutes: Option<u32>,  // 番茄钟时长，空表示手动结束
+    #[serde(default)]
+    pub ended_at: String,
+    #[serde(default)]
+    pub on_goal_checks: u32,
+    #[serde(default)]
+    pub off_goal_checks: u32,
+    #[serde(default)]
+    pub nudges: u32,
+    #[serde(default)]
+    pub drift_apps: BTreeMap<String, u32>,  // 偏离目标时所在的应用及次数
+    #[serde(default)]
+    pub summary: String,
+    #[serde(skip)]
+    drift_streak: u32,
+    #[serde(skip)]
+    last_nudge: Option<DateTime<Local>>,
+}
+
+/// 偏离目标时推送的提醒（focus-nudge 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusNudge {
+    pub session_id: String,
+    pub goal: String,
+    pub app: String,
+    pub message: String,
+}
+
+fn active_focus_session() -> &'static ParkingMutex<Option<FocusSession>> {
+    static SESSION: OnceLock<ParkingMutex<Option<FocusSession>>> = OnceLock::new();
+    SESSION.get_or_init(|| ParkingMutex::new(None))
+}
+
+fn format_time(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+impl FocusSession {
+    fn elapsed_minutes(&self, end: &str) -> i64 {
+        match (parse_time(&self.started_at), parse_time(end)) {
+            (Some(start), Some(end)) => (end - start).num_minutes().max(0),
+            _ => 0,
+        }
+    }
+
+    fn build_summary(&self) -> String {
+        let checks = self.on_goal_checks + self.off_goal_checks;
+        let mut summary = format!("专注「{}」{} 分钟", self.goal, self.elapsed_minutes(&self.ended_at));
+        if checks > 0 {
+            summary.push_str(&format!(
+                "，{}% 的时间在目标上",
+                self.on_goal_checks * 100 / checks
+            ));
+        }
+        let mut drift: Vec<(&String, &u32)> = self.drift_apps.iter().collect();
+        drift.sort_by(|a, b| b.1.cmp(a.1));
+        if !drift.is_empty() {
+            let apps: Vec<&str> = drift.iter().take(3).map(|(app, _)| app.as_str()).collect();
+            summary.push_str(&format!("，偏离时主要在 {}", apps.join("、")));
+        }
+        if self.nudges > 0 {
+            summary.push_str(&format!("，提醒 {} 次", self.nudges));
+        }
+        summary
+    }
+}
+
+impl StorageManager {
+    pub fn list_focus_sessions(&self) -> Result<Vec<FocusSession>, String> {
+        let path = self.get_data_dir().join(FOCUS_SESSIONS_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| format!("解析专注记录失败: {}", e))
+    }
+
+    fn append_focus_session(&self, session: &FocusSession) -> Result<(), String> {
+        let mut sessions = self.list_focus_sessions().unwrap_or_default();
+        sessions.push(session.clone());
+        if sessions.len() > MAX_FOCUS_SESSIONS {
+            sessions.drain(..sessions.len() - MAX_FOCUS_SESSIONS);
+        }
+        let content = serde_json::to_string_pretty(&sessions)
+            .map_err(|e| format!("序列化专注记录失败: {}", e))?;
+        self.write_data_file(&self.get_data_dir().join(FOCUS_SESSIONS_FILE), content.as_bytes())
+    }
+}
+
+/// 开始专注时段，已有进行中的时段时报错
+pub fn start_focus_session(goal: &str, planned_minutes: Option<u32>) -> Result<FocusSession, String> {
+    let goal = goal.trim();
+    if goal.is_empty() {
+        return Err("请填写专注目标".to_string());
+    }
+    let mut active = active_focus_session().lock();
+    if active.is_some() {
+        return Err("已有进行中的专注时段".to_string());
+    }
+    let now = Local::now();
+    let session = FocusSession {
+        id: format!("focus-{}", now.timestamp_millis()),
+        goal: goal.to_string(),
+        started_at: format_time(now),
+        planned_minutes: planned_minutes.filter(|minutes| *minutes > 0),
+        ended_at: String::new(),
+        on_goal_checks: 0,
+        off_goal_checks: 0,
+        nudges: 0,
+        drift_apps: BTreeMap::new(),
+        summary: String::new(),
+        drift_streak: 0,
+        last_nudge: None,
+    };
+    *active = Some(session.clone());
+    logs::info("focus", format!("开始专注: {}", session.goal));
+    Ok(session)
+}
+
+/// 结束专注时段，生成小结并保存；id 不为空时只结束对应的时段
+pub fn end_focus_session(storage: &StorageManager, id: Option<&str>) -> Result<Option<FocusSession>, String> {
+    let session = {
+        let mut active = active_focus_session().lock();
+        if id.is_some_and(|id| active.as_ref().map_or(true, |session| session.id != id)) {
+            return Ok(None);
+        }
+        active.take()
+    };
+    let Some(mut session) = session else {
+        return Ok(None);
+    };
+    session.ended_at = format_time(Local::now());
+    session.summary = session.build_summary();
+    storage.append_focus_session(&session)?;
+    logs::info("focus", session.summary.clone());
+    Ok(Some(session))
+}
+
+pub fn current_focus_session() -> Option<FocusSession> {
+    active_focus_session().lock().clone()
+}
+
+/// 专注期间追加到截屏分析提示词末尾，要求模型判断是否偏离目标
+pub(super) fn focus_prompt_note() -> Option<String> {
+    let active = active_focus_session().lock();
+    let session = active.as_ref()?;
+    Some(format!(
+        "\n\n用户当前处于专注时段，目标是「{}」。请在 JSON 中额外输出 \"on_goal\": true 或 false，表示画面中的活动是否服务于这个目标（为目标查资料、沟通也算在目标上）。",
+        session.goal
+    ))
+}
+
+/// 记录一次专注判断；连续偏离且不在冷却期时返回需要推送的提醒
+pub(super) fn record_focus_check(on_goal: Option<bool>, app: &str, now: DateTime<Local>) -> Option<FocusNudge> {
+    let mut active = active_focus_session().lock();
+    let session = active.as_mut()?;
+    let started = parse_time(&session.started_at)?;
+    if now.naive_local() < started {
+        return None;
+    }
+    let on_goal = on_goal?;
+    if on_goal {
+        session.on_goal_checks += 1;
+        session.drift_streak = 0;
+        return None;
+    }
+    session.off_goal_checks += 1;
+    session.drift_streak += 1;
+    let app = if app.trim().is_empty() { "Unknown" } else { app.trim() };
+    *session.drift_apps.entry(app.to_string()).or_insert(0) += 1;
+
+    let cooling = session
+        .last_nudge
+        .map_or(false, |last| (now - last).num_seconds() < NUDGE_COOLDOWN_SECS);
+    if session.drift_streak < DRIFT_CHECKS_BEFORE_NUDGE || cooling {
+        return None;
+    }
+    session.nudges += 1;
+    session.last_nudge = Some(now);
+    session.drift_streak = 0;
+    Some(FocusNudge {
+        session_id: session.id.clone(),
+        goal: session.goal.clone(),
+        app: app.to_string(),
+        message: format!("你好像在 {} 里待了一会儿，要回到「{}」吗？", app, session.goal),
+    })
+}
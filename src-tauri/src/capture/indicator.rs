@@ -0,0 +1,132 @@
+use crate::logs;
+use crate::storage::CaptureIndicatorConfig;
+use parking_lot::Mutex as ParkingMutex;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, WebviewUrl, WebviewWindowBuilder};
+
+const INDICATOR_LABEL: &str = "capture-indicator";
+const INDICATOR_SIZE: f64 = 18.0;
+const INDICATOR_MARGIN: f64 = 6.0;
+
+#[derive(Default)]
+struct IndicatorState {
+    config: CaptureIndicatorConfig,
+    capturing: bool,  // 截屏循环或单次截屏进行中
+    manual: bool,  // 用户通过命令手动显示
+}
+
+fn indicator_state() -> &'static ParkingMutex<IndicatorState> {
+    static STATE: OnceLock<ParkingMutex<IndicatorState>> = OnceLock::new();
+    STATE.get_or_init(|| ParkingMutex::new(IndicatorState::default()))
+}
+
+pub fn capture_indicator_visible(app_handle: &AppHandle) -> bool {
+    app_handle
+        .get_webview_window(INDICATOR_LABEL)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false)
+}
+
+/// 启动和保存配置时调用，按新配置刷新指示器
+pub fn apply_capture_indicator_config(app_handle: &AppHandle, config: &CaptureIndicatorConfig) {
+    indicator_state().lock().config = config.clone();
+    if let Err(err) = sync_indicator(app_handle) {
+        logs::warn("capture", format!("截屏指示器显示失败: {}", err));
+    }
+}
+
+/// 截屏开始/结束时调用；配置要求显示指示器但无法显示时返回错误，调用方应放弃截屏
+pub fn set_capture_indicator_capturing(app_handle: &AppHandle, capturing: bool) -> Result<(), String> {
+    let required = {
+        let mut state = indicator_state().lock();
+        state.capturing = capturing;
+        state.config.required
+    };
+    match sync_indicator(app_handle) {
+        Err(err) if required && capturing => Err(err),
+        Err(err) => {
+            logs::warn("capture", format!("截屏指示器显示失败: {}", err));
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+/// 手动显示/隐藏指示器；要求显示时截屏期间不能隐藏
+pub fn set_capture_indicator_manual(app_handle: &AppHandle, visible: bool) -> Result<bool, String> {
+    {
+        let mut state = indicator_state().lock();
+        if !visible && state.capturing && state.config.required {
+            return Err("已设置截屏时必须显示指示器，截屏期间不能隐藏".to_string());
+        }
+        state.manual = visible;
+    }
+    sync_indicator(app_handle)?;
+    Ok(capture_indicator_visible(app_handle))
+}
+
+fn sync_indicator(app_handle: &AppHandle) -> Result<(), String> {
+    let (visible, position) = {
+        let state = indicator_state().lock();
+        let wanted = state.config.enabled || state.config.required;
+        (state.manual || (state.capturing && wanted), state.config.position.clone())
+    };
+    let was_visible = capture_indicator_visible(app_handle);
+    if visible {
+        show_indicator(app_handle, &position)?;
+    } else if let Some(window) = app_handle.get_webview_window(INDICATOR_LABEL) {
+        window.hide().map_err(|e| format!("隐藏截屏指示器失败: {}", e))?;
+    }
+    if visible != was_visible {
+        logs::info("capture", if visible { "显示截屏指示器" } else { "隐藏截屏指示器" });
+        let _ = app_handle.emit("capture-indicator-changed", visible);
+    }
+    Ok(())
+}
+
+fn show_indicator(app_handle: &AppHandle, position: &str) -> Result<(), String> {
+    let window = match app_handle.get_webview_window(INDICATOR_LABEL) {
+        Some(window) => window,
+        None => {
+            let window = WebviewWindowBuilder::new(
+                app_handle,
+                INDICATOR_LABEL,
+                WebviewUrl::App("/capture-indicator".into()),
+            )
+            .title("OpenCowork 正在分析屏幕")
+            .inner_size(INDICATOR_SIZE, INDICATOR_SIZE)
+            .resizable(false)
+            .decorations(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .transparent(true)
+            .shadow(false)
+            .focused(false)
+            .build()
+            .map_err(|e| format!("创建截屏指示器失败: {}", e))?;
+            // 不拦截鼠标，避免挡住下面的窗口
+            let _ = window.set_ignore_cursor_events(true);
+            window
+        }
+    };
+
+    if let Some(monitor) = window.current_monitor().ok().flatten() {
+        let scale = monitor.scale_factor();
+        let size = (INDICATOR_SIZE * scale) as i32;
+        let margin = (INDICATOR_MARGIN * scale) as i32;
+        let origin = monitor.position();
+        let area = monitor.size();
+        let left = origin.x + margin;
+        let right = origin.x + area.width as i32 - size - margin;
+        let top = origin.y + margin;
+        let bottom = origin.y + area.height as i32 - size - margin;
+        let (x, y) = match position {
+            "top-left" => (left, top),
+            "bottom-left" => (left, bottom),
+            "bottom-right" => (right, bottom),
+            _ => (right, top),
+        };
+        let _ = window.set_position(PhysicalPosition::new(x, y));
+    }
+    window.show().map_err(|e| format!("显示截屏指示器失败: {}", e))
+}
@@ -1,28 +1,75 @@
+mod alerts;
+mod batch;
+mod budget;
+mod focus;
+mod indicator;
+mod ocr;
+mod presence;
+mod privacy;
+mod regions;
 mod screen;
 mod scheduler;
-
+mod suppression;
+mod target;
+mod verify;
+mod vision;
+mod window;
+
+pub use alerts::*;
+pub use budget::*;
+pub use focus::*;
+pub use indicator::*;
+pub use ocr::*;
+pub use presence::*;
+pub use privacy::*;
+pub use regions::*;
 pub use screen::*;
 pub use scheduler::*;
-
-use crate::model::{build_model_error_alert, ModelManager};
-use crate::storage::{Config, StorageManager, SummaryRecord};
-use chrono::{DateTime, Duration, Local};
+pub use suppression::*;
+pub use target::*;
+pub use verify::*;
+pub use vision::*;
+pub use window::*;
+
+use crate::analysis::{
+    evaluate_alert_rules, next_prompt_variant, rules_need_ocr, PromptVariant, RuleInput, RuleMatch,
+};
+use batch::{build_batch_prompt, split_batch_analysis, BatchFrame, FrameBatch};
+use crate::error::AppError;
+use crate::logs;
+use crate::metrics;
+use crate::model::{build_model_error_alert, retry_delay, with_usage_feature, ModelManager};
+use crate::storage::{
+    storage_actor, CaptureRegion, Config, ModelConfig, OcrTextLayer, PendingCapture, StorageManager, StoragePriority,
+    SummaryRecord,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
 use image::DynamicImage;
 use parking_lot::Mutex as ParkingMutex;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
 use tokio::sync::mpsc;
 
 const RECENT_CONTEXT_MINUTES: i64 = 3;
+const MAX_OCR_PROMPT_CHARS: usize = 8000;
+const PENDING_RETRY_BASE_SECS: u64 = 60;
+const PENDING_RETRY_MAX_SECS: u64 = 30 * 60;
+const PENDING_RETRY_BATCH: usize = 10;
+const MAX_PENDING_ATTEMPTS: u32 = 5;
+const ANALYSIS_JSON_FIELDS: &str =
+    "字段: summary, app, detail, has_issue, issue_type, issue_summary, suggestion, confidence, intent, scene, needs_help, help_type, urgency, related_skill";
 
 pub struct CaptureManager {
     is_running: Arc<ParkingMutex<bool>>,
     record_count: Arc<ParkingMutex<u64>>,
     skip_count: Arc<ParkingMutex<u64>>,  // 跳过的帧数
     stop_tx: Option<mpsc::Sender<()>>,
-    recent_alerts: Arc<ParkingMutex<HashMap<String, DateTime<Local>>>>,
+    recent_alerts: Arc<ParkingMutex<AlertTracker>>,
     last_issue_key: Arc<ParkingMutex<Option<String>>>,
+    privacy_skipped: Arc<ParkingMutex<bool>>,  // 当前是否处于隐私跳过状态
+    budget: Arc<ParkingMutex<BudgetGuard>>,
 }
 
 impl CaptureManager {
@@ -32,8 +79,10 @@ impl CaptureManager {
             record_count: Arc::new(ParkingMutex::new(0)),
             skip_count: Arc::new(ParkingMutex::new(0)),
             stop_tx: None,
-            recent_alerts: Arc::new(ParkingMutex::new(HashMap::new())),
+            recent_alerts: Arc::new(ParkingMutex::new(AlertTracker::load(&StorageManager::new()))),
             last_issue_key: Arc::new(ParkingMutex::new(None)),
+            privacy_skipped: Arc::new(ParkingMutex::new(false)),
+            budget: Arc::new(ParkingMutex::new(BudgetGuard::default())),
         }
     }
 
@@ -62,10 +111,14 @@ impl CaptureManager {
         let skip_count = self.skip_count.clone();
         let recent_alerts = self.recent_alerts.clone();
         let last_issue_key = self.last_issue_key.clone();
+        let privacy_skipped = self.privacy_skipped.clone();
+        let budget = self.budget.clone();
         let interval_ms = config.capture.interval_ms;
 
         *is_running.lock() = true;
 
+        spawn_pending_worker(config.clone(), is_running.clone(), budget.clone());
+
         tokio::spawn(async move {
             let model_manager = ModelManager::new();
             let storage_manager = StorageManager::new();
@@ -73,8 +126,17 @@ impl CaptureManager {
                 tokio::time::Duration::from_millis(interval_ms)
             );
 
+            if let Err(err) = set_capture_indicator_capturing(&app_handle, true) {
+                logs::error("capture", format!("已要求显示截屏指示器，但无法显示，停止截屏: {}", err));
+                *is_running.lock() = false;
+                let _ = set_capture_indicator_capturing(&app_handle, false);
+                let _ = app_handle.emit("capture-status-changed", false);
+                return;
+            }
+
             // 上一帧的图像哈希（用于对比）
-            let mut prev_image_hash: Option<u64> = None;
+            let mut prev_frame = PrevFrame::default();
+            let mut frame_batch = FrameBatch::default();
             loop {
                 tokio::select! {
                     _ = interval.tick() => {
@@ -82,25 +144,37 @@ impl CaptureManager {
                             break;
                         }
 
+                        // 用户回来后补发离开期间暂存的提醒
+                        deliver_held_alerts(&recent_alerts, &app_handle, &config);
+
                         // 执行截屏和识别
-                        match capture_and_analyze_with_diff(
+                        let frame_started = std::time::Instant::now();
+                        let frame_result = with_usage_feature("capture", capture_and_analyze_with_diff(
                             &config,
                             &model_manager,
                             &storage_manager,
                             &recent_alerts,
                             &last_issue_key,
+                            &privacy_skipped,
+                            &is_running,
                             &app_handle,
-                            &mut prev_image_hash,
-                        ).await {
+                            &budget,
+                            &mut prev_frame,
+                            Some(&mut frame_batch),
+                        )).await;
+                        metrics::record_capture(frame_started.elapsed(), &frame_result);
+                        match frame_result {
                             Ok(analyzed) => {
                                 if analyzed {
                                     *record_count.lock() += 1;
+                                    logs::debug("capture", "截屏已分析并保存记录");
                                 } else {
                                     *skip_count.lock() += 1;
+                                    logs::debug("capture", "画面无明显变化，跳过分析");
                                 }
                             }
                             Err(e) => {
-                                eprintln!("截屏分析失败: {}", e);
+                                logs::error("capture", format!("截屏分析失败: {}", e));
                             }
                         }
 
@@ -111,10 +185,81 @@ impl CaptureManager {
                 }
             }
 
+            // 停止时还未分析的批量帧放入待分析队列，下次启动后补分析
+            enqueue_batch_frames(&frame_batch.frames, "batch").await;
             *is_running.lock() = false;
+            let _ = set_capture_indicator_capturing(&app_handle, false);
         });
     }
 
+    /// 立即截屏并分析一次（不受变化检测影响）；返回的任务不持有 CaptureManager 的锁
+    pub fn capture_now_task(
+        &self,
+        config: Config,
+        app_handle: AppHandle,
+    ) -> impl std::future::Future<Output = Result<bool, String>> + Send + 'static {
+        let record_count = self.record_count.clone();
+        let recent_alerts = self.recent_alerts.clone();
+        let last_issue_key = self.last_issue_key.clone();
+        let is_running = self.is_running.clone();
+        let privacy_skipped = self.privacy_skipped.clone();
+        let budget = self.budget.clone();
+        async move {
+            // 截屏循环未运行时，单次截屏期间也显示指示器
+            let flash_indicator = !*is_running.lock();
+            if flash_indicator {
+                if let Err(err) = set_capture_indicator_capturing(&app_handle, true) {
+                    let _ = set_capture_indicator_capturing(&app_handle, false);
+                    return Err(format!("截屏指示器无法显示，已取消截屏: {}", err));
+                }
+            }
+            let model_manager = ModelManager::new();
+            let storage_manager = StorageManager::new();
+            let mut prev_frame = PrevFrame::default();
+            let analyzed = with_usage_feature(
+                "capture",
+                capture_and_analyze_with_diff(
+                    &config,
+                    &model_manager,
+                    &storage_manager,
+                    &recent_alerts,
+                    &last_issue_key,
+                    &privacy_skipped,
+                    &is_running,
+                    &app_handle,
+                    &budget,
+                    &mut prev_frame,
+                    None,
+                ),
+            )
+            .await;
+            if flash_indicator && !*is_running.lock() {
+                let _ = set_capture_indicator_capturing(&app_handle, false);
+            }
+            let analyzed = analyzed?;
+            if analyzed {
+                *record_count.lock() += 1;
+            }
+            Ok(analyzed)
+        }
+    }
+
+    pub fn budget_status(&self, config: &Config) -> CaptureBudgetStatus {
+        self.budget
+            .lock()
+            .status(&config.capture.budget, &StorageManager::new(), &Local::now())
+    }
+
+    /// 暂停某类提醒 minutes 分钟（0 表示取消暂停）
+    pub fn snooze_alert(&self, key: &str, minutes: u64) -> Result<(), String> {
+        let key = key.trim();
+        if key.is_empty() {
+            return Err("提醒标识不能为空".to_string());
+        }
+        self.recent_alerts.lock().snooze(key, minutes, Local::now());
+        Ok(())
+    }
+
     pub async fn stop(&mut self) {
         *self.is_running.lock() = false;
         if let Some(tx) = self.stop_tx.take() {
@@ -151,17 +296,19 @@ fn save_screenshot(
     let dir = match storage_manager.screenshots_dir() {
         Ok(dir) => dir,
         Err(err) => {
-            eprintln!("获取截图目录失败: {}", err);
+            logs::warn("capture", format!("获取截图目录失败: {}", err));
             return None;
         }
     };
 
     let filename = format!("{}.jpg", now.format("%Y%m%d-%H%M%S-%.3f"));
     let path = dir.join(&filename);
-    let path_str = path.to_string_lossy();
 
-    if let Err(err) = ScreenCapture::save_to_file(image, path_str.as_ref(), quality) {
-        eprintln!("保存截图失败: {}", err);
+    // 通过存储层写入，启用加密时落盘为密文
+    let saved = ScreenCapture::image_to_jpeg_bytes(image, quality)
+        .and_then(|bytes| storage_manager.write_data_file(&path, &bytes));
+    if let Err(err) = saved {
+        logs::error("capture", format!("保存截图失败: {}", err));
         return None;
     }
 
@@ -175,51 +322,987 @@ fn hash_similarity(hash1: u64, hash2: u64) -> f32 {
     1.0 - (diff_bits as f32 / 64.0)
 }
 
-/// 截屏并分析，支持跳过无变化的帧
-async fn capture_and_analyze_with_diff(
+/// 上一次分析的帧，用于判断画面是否变化
+#[derive(Default)]
+struct PrevFrame {
+    hash: Option<u64>,
+    grid: Option<BlockGrid>,  // 开启分块变化检测时才有
+}
+
+/// 截屏并分析，支持跳过无变化的帧
+async fn capture_and_analyze_with_diff(
+    config: &Config,
+    model_manager: &ModelManager,
+    storage_manager: &StorageManager,
+    recent_alerts: &Arc<ParkingMutex<AlertTracker>>,
+    last_issue_key: &Arc<ParkingMutex<Option<String>>>,
+    privacy_skipped: &Arc<ParkingMutex<bool>>,
+    is_running: &Arc<ParkingMutex<bool>>,
+    app_handle: &AppHandle,
+    budget: &Arc<ParkingMutex<BudgetGuard>>,
+    prev_frame: &mut PrevFrame,
+    mut batch: Option<&mut FrameBatch>,
+) -> Result<bool, String> {
+    // 画面长时间无变化时，已攒下的批量帧也按时分析
+    if let Some(batch) = batch.as_deref_mut() {
+        if batch.is_due(&config.capture.batch, Local::now()) {
+            let frames = std::mem::take(&mut batch.frames);
+            if let Err(err) = analyze_batch(
+                config,
+                model_manager,
+                storage_manager,
+                recent_alerts,
+                last_issue_key,
+                is_running,
+                app_handle,
+                budget,
+                frames,
+            )
+            .await
+            {
+                logs::error("capture", format!("批量分析失败: {}", err));
+            }
+        }
+    }
+
+    // 1. 先检查前台窗口是否命中隐私规则，命中时不截图；否则按配置的范围（整屏、固定区域或指定窗口）截屏
+    let now = Local::now();
+    let active_window = get_active_window();
+    let image = match capture_with_privacy(&config.capture, active_window.as_ref(), &now)? {
+        GuardedCapture::Captured(image) => {
+            *privacy_skipped.lock() = false;
+            image
+        }
+        GuardedCapture::Blocked(reason) => {
+            // 连续命中只记一条，避免每秒写入
+            let already_skipped = std::mem::replace(&mut *privacy_skipped.lock(), true);
+            *prev_frame = PrevFrame::default();
+            if !already_skipped {
+                logs::info("capture", format!("隐私规则命中，跳过截屏: {}", reason));
+                let record = private_skip_record(&now);
+                storage_actor()
+                    .run(StoragePriority::Background, move |storage| storage.save_summary(&record))
+                    .await?;
+            }
+            metrics::record_capture_skip("privacy");
+            return Ok(false);
+        }
+        GuardedCapture::TargetMissing => {
+            // 目标窗口不可见时跳过，不退回整屏截图
+            *privacy_skipped.lock() = false;
+            *prev_frame = PrevFrame::default();
+            metrics::record_capture_skip("target_missing");
+            return Ok(false);
+        }
+    };
+
+    // 2. 与上一帧对比，如果启用了跳过无变化且相似度超过阈值，跳过这一帧
+    let current_hash = compute_image_hash(&image);
+    let similarity = prev_frame.hash.map(|prev| hash_similarity(prev, current_hash));
+    // 开启分块检测时逐块比较，小范围变化也不会被整图相似度掩盖
+    let region_diff = &config.capture.region_diff;
+    let grid = region_diff
+        .enabled
+        .then(|| BlockGrid::compute(&image, region_diff.cols, region_diff.rows));
+    let changed_blocks = match (&grid, &prev_frame.grid) {
+        (Some(grid), Some(prev)) => {
+            Some(grid.changed_blocks(prev, region_diff.pixel_tolerance, region_diff.min_changed_samples))
+        }
+        _ => None,
+    };
+    let unchanged = match &changed_blocks {
+        Some(blocks) => blocks.is_empty(),
+        None => similarity.map_or(false, |value| value >= config.capture.change_threshold),
+    };
+    if config.capture.skip_unchanged && unchanged {
+        metrics::record_capture_skip("unchanged");
+        return Ok(false);  // 返回false表示跳过
+    }
+
+    // 更新上一帧
+    prev_frame.hash = Some(current_hash);
+    prev_frame.grid = grid.clone();
+    let changed_regions = match (&grid, &changed_blocks) {
+        (Some(grid), Some(blocks)) => grid.changed_regions(blocks),
+        _ => Vec::new(),
+    };
+
+    // 3. 保存截图
+    let screenshot_ref = save_screenshot(storage_manager, &image, &now, config.capture.compress_quality);
+    crate::storage::record_audit_event(
+        "capture",
+        serde_json::json!({
+            "app": active_window.as_ref().and_then(|window| window.app_name()),
+            "window_title": active_window.as_ref().map(|window| window.title.clone()),
+            "target": config.capture.target.mode,
+            "screenshot": screenshot_ref,
+        }),
+    );
+
+    // 4. 画面布局基本不变、只有文字变化时，用本地 OCR 文本代替整张截图；
+    //    有需要 OCR 的提醒规则时每帧都识别；模型不支持图片时始终只发送 OCR 文本
+    let capture_model = ModelManager::resolve_model_config(
+        &config.model,
+        Some(config.model.capture_profile.as_str()),
+    );
+    let no_vision = vision_unsupported(&capture_model);
+    let text_only = no_vision
+        || (config.capture.ocr_enabled
+            && similarity.map_or(false, |value| value >= config.capture.ocr_text_only_threshold));
+    let rules_want_ocr = config.capture.ocr_enabled && rules_need_ocr(&config.capture.alert_rules);
+    let screen_text = if text_only || rules_want_ocr || config.capture.ocr_index_enabled {
+        ocr_screenshot(storage_manager, screenshot_ref.as_deref(), config).await
+    } else {
+        None
+    };
+    let ocr_text = if text_only { screen_text.clone() } else { None };
+    if no_vision && ocr_text.is_none() {
+        logs::debug("capture", "模型不支持图片且 OCR 未识别出文字，跳过分析");
+        metrics::record_capture_skip("no_vision");
+        return Ok(false);
+    }
+
+    // 本地规则提醒：不依赖模型，模型离线或置信度低时也能触发
+    if !config.capture.alert_rules.is_empty() {
+        let app = active_window.as_ref().and_then(|w| w.app_name()).unwrap_or_default();
+        let input = RuleInput {
+            app: &app,
+            window_title: active_window.as_ref().map_or("", |w| w.title.as_str()),
+            ocr_text: screen_text.as_deref(),
+        };
+        let matches = evaluate_alert_rules(&config.capture.alert_rules, &input);
+        emit_rule_alerts(matches, &app, config, storage_manager, recent_alerts, app_handle, &now);
+    }
+
+    // 超出分析预算时只保存截图并加入待分析队列，预算恢复后再补分析
+    let over_budget = budget
+        .lock()
+        .exhausted_reason(&config.capture.budget, storage_manager, &now);
+    if over_budget.is_some() {
+        if let Some(screenshot) = screenshot_ref.as_deref() {
+            enqueue_pending(screenshot, &now, current_hash, active_window.as_ref(), "budget").await?;
+        }
+        metrics::record_capture_skip("budget");
+        return Ok(false);
+    }
+
+    // 批量分析：先攒下有变化的帧，攒够数量或等待超时后合并为一次模型调用；只发送 OCR 文本的帧仍单独分析
+    if let Some(batch) = batch.filter(|_| config.capture.batch.enabled && ocr_text.is_none()) {
+        batch.frames.push(BatchFrame {
+            now,
+            screenshot_ref,
+            image_hash: current_hash,
+            image_base64: ScreenCapture::image_to_base64(&image, config.capture.compress_quality)?,
+            active_window,
+            changed_regions,
+        });
+        if !batch.is_due(&config.capture.batch, now) {
+            logs::debug(
+                "capture",
+                format!("已加入批量分析（{}/{}）", batch.frames.len(), config.capture.batch.max_frames),
+            );
+            return Ok(false);
+        }
+        let frames = std::mem::take(&mut batch.frames);
+        return analyze_batch(
+            config,
+            model_manager,
+            storage_manager,
+            recent_alerts,
+            last_issue_key,
+            is_running,
+            app_handle,
+            budget,
+            frames,
+        )
+        .await;
+    }
+
+    // 5. 发送给大模型识别
+    let recent_context = build_recent_summary_context(
+        config.capture.recent_summary_limit,
+        config.capture.recent_detail_limit,
+    )
+    .await;
+    let window_hint = match &active_window {
+        Some(window) => build_window_hint(&window.process_name, &window.title),
+        None => String::new(),
+    };
+    // 提示词实验：相邻帧交替使用 A/B 变体
+    let prompt_variant = next_prompt_variant(&config.capture.prompt_experiment);
+    let prompt = build_analysis_prompt(&window_hint, &recent_context, prompt_variant.as_ref());
+    // 变化范围较小时只发送裁剪后的变化区域
+    let cropped = match (&grid, &changed_blocks) {
+        (Some(grid), Some(blocks)) if ocr_text.is_none() => crop_changed_area(&image, grid, blocks, region_diff),
+        _ => None,
+    };
+
+    let (input, frame_prompt) = match &ocr_text {
+        Some(text) => (FrameInput::OcrText(text), prompt.clone()),
+        None => {
+            let frame = cropped.as_ref().map_or(&image, |(cropped, _)| cropped);
+            let mut frame_prompt = prompt.clone();
+            if let Some((_, bounds)) = &cropped {
+                frame_prompt.push_str(&format!(
+                    "\n\n注意：本次只附带了屏幕上发生变化的区域（位于整屏 ({}, {}) 处，大小 {}x{}），其余部分与上一帧相同，请结合近期记录理解上下文。",
+                    bounds.x, bounds.y, bounds.width, bounds.height
+                ));
+            }
+            (
+                FrameInput::Image(ScreenCapture::image_to_base64(frame, config.capture.compress_quality)?),
+                frame_prompt,
+            )
+        }
+    };
+    budget.lock().record_analysis(now);
+    let parsed = match analyze_frame(model_manager, &capture_model, &frame_prompt, input).await {
+        Ok(parsed) => parsed,
+        Err(err @ AppError::ImageUnsupported { .. }) => {
+            // 模型不接受图片：提示一次，本帧改用 OCR 文本重试，之后的帧直接走 OCR
+            mark_vision_unsupported(&capture_model, Some(app_handle), &err.to_string());
+            let text = match screen_text {
+                Some(text) => Some(text),
+                None => ocr_screenshot(storage_manager, screenshot_ref.as_deref(), config).await,
+            };
+            let Some(text) = text else {
+                return Err(err.into());
+            };
+            analyze_frame(model_manager, &capture_model, &prompt, FrameInput::OcrText(&text)).await?
+        }
+        Err(err) => {
+            // 网络等临时故障时保留截图，模型恢复后由后台任务补分析
+            if err.is_retryable() {
+                if let Some(screenshot) = screenshot_ref.as_deref() {
+                    enqueue_pending(screenshot, &now, current_hash, active_window.as_ref(), "network").await?;
+                }
+            }
+            emit_model_error_once(
+                recent_alerts,
+                app_handle,
+                &err.to_string(),
+                "capture",
+                now,
+                config.capture.alert_cooldown_seconds,
+            );
+            return Err(err.into());
+        }
+    };
+    finish_frame(
+        config,
+        model_manager,
+        storage_manager,
+        recent_alerts,
+        last_issue_key,
+        is_running,
+        app_handle,
+        AnalyzedFrame {
+            parsed,
+            now,
+            screenshot_ref,
+            active_window,
+            recent_context,
+            prompt_variant: prompt_variant.map(|variant| variant.label).unwrap_or_default(),
+            changed_regions,
+        },
+    )
+    .await
+}
+
+/// 一帧的分析结果及截屏时的上下文
+struct AnalyzedFrame {
+    parsed: AnalysisResult,
+    now: DateTime<Local>,
+    screenshot_ref: Option<String>,
+    active_window: Option<ActiveWindow>,
+    recent_context: String,
+    prompt_variant: String,
+    changed_regions: Vec<CaptureRegion>,
+}
+
+/// 根据分析结果判断是否提醒、保存摘要并推送提示
+async fn finish_frame(
+    config: &Config,
+    model_manager: &ModelManager,
+    storage_manager: &StorageManager,
+    recent_alerts: &Arc<ParkingMutex<AlertTracker>>,
+    last_issue_key: &Arc<ParkingMutex<Option<String>>>,
+    is_running: &Arc<ParkingMutex<bool>>,
+    app_handle: &AppHandle,
+    frame: AnalyzedFrame,
+) -> Result<bool, String> {
+    let AnalyzedFrame {
+        mut parsed,
+        now,
+        screenshot_ref,
+        active_window,
+        recent_context,
+        prompt_variant,
+        changed_regions,
+    } = frame;
+
+    // 6. 解析结果：前台窗口可读取时以系统信息为准，不依赖模型从画面猜测
+    if let Some(app) = active_window.as_ref().and_then(|w| w.app_name()) {
+        parsed.app = app;
+    }
+    if let Some(nudge) = record_focus_check(parsed.on_goal, &parsed.app, now) {
+        if let Err(err) = app_handle.emit("focus-nudge", nudge) {
+            logs::warn("capture", format!("发送专注提醒失败: {}", err));
+        }
+    }
+    let alert_threshold = config.capture.alert_confidence_threshold.clamp(0.0, 1.0);
+    let mut issue_message = if parsed.issue_message.is_empty() {
+        parsed.summary.clone()
+    } else {
+        parsed.issue_message.clone()
+    };
+    // 内置的自身窗口判断和用户添加的屏蔽规则
+    let suppressed = should_suppress_alert(&parsed)
+        || is_alert_suppressed(&parsed.app, &parsed.scene, &parsed.issue_type, &issue_message);
+    let mut should_emit = false;
+    let mut escalated = false;
+    let mut current_issue_key: Option<String> = None;
+    let mut ticket_url = String::new();
+
+    if parsed.has_issue && parsed.confidence >= alert_threshold && !suppressed {
+        let alert_key = build_alert_key(&parsed, &issue_message);
+        current_issue_key = Some(alert_key.clone());
+        // 已建工单的问题不再重复提醒，记录上引用已有工单
+        ticket_url = storage_manager
+            .find_ticket_link(&alert_key)
+            .map(|link| link.url)
+            .unwrap_or_default();
+
+        let last_key = last_issue_key.lock().clone();
+        if ticket_url.is_empty() {
+            let mut tracker = recent_alerts.lock();
+            if tracker.is_snoozed(&alert_key, now) {
+                // 暂停期间高紧急度问题持续出现，升级提醒
+                escalated = tracker.record_snoozed_hit(
+                    &alert_key,
+                    parsed.urgency == "high",
+                    now,
+                    config.capture.alert_escalation_captures,
+                );
+                should_emit = escalated;
+            } else if last_key.as_deref() != Some(alert_key.as_str()) {
+                should_emit = tracker.should_emit(
+                    &alert_key,
+                    now,
+                    config.capture.alert_cooldown_seconds,
+                );
+            }
+        }
+
+        if escalated {
+            issue_message = format!("【持续未解决】{}", issue_message);
+            parsed.suggestion = format!(
+                "该问题在暂停提醒后仍持续出现，请尽快处理。{}",
+                parsed.suggestion
+            );
+        }
+
+        if should_emit && parsed.suggestion.trim().is_empty() {
+            match generate_issue_suggestion(&model_manager, &config, &recent_context, &parsed).await {
+                Ok(suggestion) => parsed.suggestion = suggestion,
+                Err(err) => {
+                    logs::warn("capture", format!("生成建议失败: {}", err));
+                    parsed.suggestion = "建议生成失败，请查看详情或稍后重试。".to_string();
+                }
+            }
+        }
+    }
+
+    recent_alerts.lock().reset_streaks_except(current_issue_key.as_deref());
+    *last_issue_key.lock() = current_issue_key.clone();
+
+    // 7. 保存摘要
+    let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let issue_summary = issue_message.clone();
+
+    let mut summary = analysis_to_record(
+        &parsed,
+        timestamp.clone(),
+        issue_summary,
+        screenshot_ref.unwrap_or_default(),
+        active_window.as_ref().map(|w| w.title.clone()).unwrap_or_default(),
+        active_window.as_ref().map(|w| w.process_name.clone()).unwrap_or_default(),
+    );
+    summary.prompt_variant = prompt_variant;
+    summary.ticket_url = ticket_url;
+    summary.changed_regions = changed_regions;
+
+    // 截屏写入走低优先级队列，避免阻塞对话检索
+    let record = summary.clone();
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| storage.save_summary(&record))
+        .await?;
+
+    // 8. 如果需要帮助（包括错误或主动建议），推送提示
+    let should_notify = (parsed.has_issue || parsed.needs_help)
+        && parsed.confidence >= alert_threshold
+        && !suppressed
+        && (parsed.urgency == "high" || parsed.urgency == "medium");
+
+    if should_notify && should_emit {
+        let mut alert_message = AssistantAlert {
+            key: current_issue_key.clone().unwrap_or_default(),
+            escalated,
+            delayed: false,
+            timestamp: timestamp.clone(),
+            issue_type: parsed.issue_type.clone(),
+            message: issue_message.clone(),
+            suggestion: parsed.suggestion.clone(),
+            intent: parsed.intent.clone(),
+            scene: parsed.scene.clone(),
+            help_type: parsed.help_type.clone(),
+            urgency: parsed.urgency.clone(),
+            related_skill: parsed.related_skill.clone(),
+            actions: Vec::new(),
+        };
+        alert_message.actions = build_alert_actions(&alert_message);
+
+        let mut alert_log = String::new();
+        alert_log.push_str(&format!("time: {}\n", timestamp));
+        alert_log.push_str(&format!("intent: {}\n", alert_message.intent));
+        alert_log.push_str(&format!("scene: {}\n", alert_message.scene));
+        alert_log.push_str(&format!("help_type: {}\n", alert_message.help_type));
+        alert_log.push_str(&format!("urgency: {}\n", alert_message.urgency));
+        alert_log.push_str(&format!("issue_type: {}\n", alert_message.issue_type));
+        alert_log.push_str(&format!("message: {}\n", alert_message.message));
+        if !alert_message.suggestion.is_empty() {
+            alert_log.push_str(&format!("suggestion: {}\n", alert_message.suggestion));
+        }
+        alert_log.push_str(&format!(
+            "confidence: {:.2}\nthreshold: {:.2}\n",
+            parsed.confidence, alert_threshold
+        ));
+        if let Err(err) = storage_manager.write_log_snapshot("assistant-alert", &alert_log) {
+            logs::warn("capture", format!("写入提醒日志失败: {}", err));
+        }
+
+        let pending = PendingVerification {
+            timestamp: timestamp.clone(),
+            key: alert_message.key.clone(),
+            message: alert_message.message.clone(),
+            suggestion: alert_message.suggestion.clone(),
+        };
+
+        // 用户离开时先暂存，回来后再推送（可选同时发送到 webhook）
+        if user_is_away(config.capture.alert_idle_threshold_seconds) {
+            send_alert_webhook(&config.capture.alert_webhook_url, &alert_message);
+            let mut alert_message = alert_message;
+            alert_message.delayed = true;
+            recent_alerts.lock().hold(alert_message);
+            return Ok(true);
+        }
+
+        if !recent_alerts
+            .lock()
+            .take_budget(&alert_message.urgency, now, config.capture.alert_max_per_hour)
+        {
+            log_dropped_alert(&alert_message);
+            return Ok(true);
+        }
+        deliver_alert(config, app_handle, alert_message);
+
+        // 给出了建议的提醒，几分钟后复查问题是否已解决
+        if !pending.suggestion.trim().is_empty() {
+            schedule_alert_verification(
+                config.clone(),
+                app_handle.clone(),
+                Arc::clone(is_running),
+                pending,
+            );
+        }
+    }
+
+    Ok(true)  // 返回true表示已分析
+}
+
+fn analysis_to_record(
+    parsed: &AnalysisResult,
+    timestamp: String,
+    issue_summary: String,
+    detail_ref: String,
+    window_title: String,
+    process_name: String,
+) -> SummaryRecord {
+    SummaryRecord {
+        timestamp,
+        summary: parsed.summary.clone(),
+        app: parsed.app.clone(),
+        action: if parsed.has_issue { "issue".to_string() } else { "active".to_string() },
+        keywords: extract_keywords_from_analysis(&parsed.summary),
+        has_issue: parsed.has_issue,
+        issue_type: parsed.issue_type.clone(),
+        issue_summary,
+        suggestion: parsed.suggestion.clone(),
+        confidence: parsed.confidence,
+        detail: parsed.detail.clone(),
+        detail_ref,
+        // 意图识别相关字段
+        intent: parsed.intent.clone(),
+        scene: parsed.scene.clone(),
+        urgency: parsed.urgency.clone(),
+        related_skill: parsed.related_skill.clone(),
+        window_title,
+        process_name,
+        resolution: String::new(),
+        resolution_note: String::new(),
+        prompt_variant: String::new(),
+        feedback: String::new(),
+        ticket_url: String::new(),
+        changed_regions: Vec::new(),
+    }
+}
+
+async fn enqueue_pending(
+    screenshot_ref: &str,
+    now: &DateTime<Local>,
+    image_hash: u64,
+    active_window: Option<&ActiveWindow>,
+    reason: &str,
+) -> Result<(), String> {
+    let item = PendingCapture {
+        timestamp: now.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        screenshot_ref: screenshot_ref.to_string(),
+        image_hash,
+        window_title: active_window.map(|w| w.title.clone()).unwrap_or_default(),
+        process_name: active_window.map(|w| w.process_name.clone()).unwrap_or_default(),
+        reason: reason.to_string(),
+        attempts: 0,
+    };
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            storage.enqueue_pending_capture(item).map(|_| ())
+        })
+        .await
+}
+
+/// 后台补分析任务：定期处理待分析队列，连续失败时逐步拉长间隔，截屏停止后退出
+fn spawn_pending_worker(
+    config: Config,
+    is_running: Arc<ParkingMutex<bool>>,
+    budget: Arc<ParkingMutex<BudgetGuard>>,
+) {
+    // 快速停止再启动时旧任务可能还在休眠，只保留一个
+    static WORKER_ACTIVE: AtomicBool = AtomicBool::new(false);
+    if WORKER_ACTIVE.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let model_manager = ModelManager::new();
+        let storage_manager = StorageManager::new();
+        let mut failures: u32 = 0;
+        loop {
+            let delay = PENDING_RETRY_BASE_SECS
+                .saturating_mul(1 << failures.min(6))
+                .min(PENDING_RETRY_MAX_SECS);
+            tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+            if !*is_running.lock() {
+                break;
+            }
+            for _ in 0..PENDING_RETRY_BATCH {
+                let result = with_usage_feature(
+                    "capture",
+                    analyze_next_pending(&config, &model_manager, &storage_manager, &budget),
+                )
+                .await;
+                match result {
+                    Ok(true) => failures = 0,
+                    Ok(false) => break,
+                    Err(err) => {
+                        logs::error("capture", format!("补分析失败: {}", err));
+                        failures = failures.saturating_add(1);
+                        break;
+                    }
+                }
+            }
+        }
+        WORKER_ACTIVE.store(false, Ordering::SeqCst);
+    });
+}
+
+/// 批量分析：多张截图合并为一次模型调用；较早的帧直接保存记录，最新一帧按正常流程判断提醒
+async fn analyze_batch(
+    config: &Config,
+    model_manager: &ModelManager,
+    storage_manager: &StorageManager,
+    recent_alerts: &Arc<ParkingMutex<AlertTracker>>,
+    last_issue_key: &Arc<ParkingMutex<Option<String>>>,
+    is_running: &Arc<ParkingMutex<bool>>,
+    app_handle: &AppHandle,
+    budget: &Arc<ParkingMutex<BudgetGuard>>,
+    mut frames: Vec<BatchFrame>,
+) -> Result<bool, String> {
+    if frames.is_empty() {
+        return Ok(false);
+    }
+    let now = Local::now();
+    let recent_context = build_recent_summary_context(
+        config.capture.recent_summary_limit,
+        config.capture.recent_detail_limit,
+    )
+    .await;
+    let prompt = build_batch_prompt(&frames, &recent_context);
+    let images: Vec<String> = frames
+        .iter_mut()
+        .map(|frame| std::mem::take(&mut frame.image_base64))
+        .collect();
+    let capture_model = ModelManager::resolve_model_config(
+        &config.model,
+        Some(config.model.capture_profile.as_str()),
+    );
+    budget.lock().record_analysis(now);
+    logs::info("capture", format!("批量分析 {} 帧", frames.len()));
+
+    let mut attempt = 0usize;
+    let analysis = loop {
+        match model_manager.analyze_images(&capture_model, &images, &prompt).await {
+            Ok(analysis) => break analysis,
+            Err(err) => {
+                attempt += 1;
+                if let Some(delay) = retry_delay(capture_model.retry_policy(), attempt, &err) {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                if matches!(err, AppError::ImageUnsupported { .. }) {
+                    // 补分析时会改用 OCR 文本
+                    mark_vision_unsupported(&capture_model, Some(app_handle), &err.to_string());
+                    enqueue_batch_frames(&frames, "no_vision").await;
+                    return Err(err.into());
+                }
+                if err.is_retryable() {
+                    enqueue_batch_frames(&frames, "network").await;
+                }
+                emit_model_error_once(
+                    recent_alerts,
+                    app_handle,
+                    &err.to_string(),
+                    "capture",
+                    now,
+                    config.capture.alert_cooldown_seconds,
+                );
+                return Err(err.into());
+            }
+        }
+    };
+
+    let mut results = split_batch_analysis(&analysis, frames.len());
+    let latest_result = results.pop().flatten();
+    let latest = frames.pop();
+    let mut records = Vec::new();
+    let mut missing = Vec::new();
+    for (frame, parsed) in frames.into_iter().zip(results) {
+        let Some(mut parsed) = parsed else {
+            missing.push(frame);
+            continue;
+        };
+        if let Some(app) = frame.active_window.as_ref().and_then(|w| w.app_name()) {
+            parsed.app = app;
+        }
+        let issue_summary = if parsed.issue_message.is_empty() {
+            parsed.summary.clone()
+        } else {
+            parsed.issue_message.clone()
+        };
+        let window = frame.active_window.unwrap_or_default();
+        let mut record = analysis_to_record(
+            &parsed,
+            frame.now.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            issue_summary,
+            frame.screenshot_ref.unwrap_or_default(),
+            window.title,
+            window.process_name,
+        );
+        record.changed_regions = frame.changed_regions;
+        records.push(record);
+    }
+    let saved = !records.is_empty();
+    if saved {
+        storage_actor()
+            .run(StoragePriority::Background, move |storage| {
+                records.iter().try_for_each(|record| storage.save_summary(record))
+            })
+            .await?;
+    }
+
+    // 模型漏掉的帧放回待分析队列逐帧补分析
+    let latest = match (latest, latest_result) {
+        (Some(frame), Some(parsed)) => Some((frame, parsed)),
+        (Some(frame), None) => {
+            missing.push(frame);
+            None
+        }
+        _ => None,
+    };
+    if !missing.is_empty() {
+        logs::warn("capture", format!("批量分析结果缺少 {} 帧，改为逐帧补分析", missing.len()));
+        enqueue_batch_frames(&missing, "batch").await;
+    }
+
+    let Some((frame, mut parsed)) = latest else {
+        return Ok(saved);
+    };
+    if let Some(app) = frame.active_window.as_ref().and_then(|w| w.app_name()) {
+        parsed.app = app;
+    }
+    finish_frame(
+        config,
+        model_manager,
+        storage_manager,
+        recent_alerts,
+        last_issue_key,
+        is_running,
+        app_handle,
+        AnalyzedFrame {
+            parsed,
+            now: frame.now,
+            screenshot_ref: frame.screenshot_ref,
+            active_window: frame.active_window,
+            recent_context,
+            prompt_variant: String::new(),
+            changed_regions: frame.changed_regions,
+        },
+    )
+    .await
+}
+
+/// 批量帧无法分析时放回待分析队列，由后台任务逐帧补分析
+async fn enqueue_batch_frames(frames: &[BatchFrame], reason: &str) {
+    for frame in frames {
+        let Some(screenshot) = frame.screenshot_ref.as_deref() else {
+            continue;
+        };
+        let result = enqueue_pending(
+            screenshot,
+            &frame.now,
+            frame.image_hash,
+            frame.active_window.as_ref(),
+            reason,
+        )
+        .await;
+        if let Err(err) = result {
+            logs::warn("capture", format!("批量帧加入待分析队列失败: {}", err));
+        }
+    }
+}
+
+/// 预算允许时补分析一帧排队的截图并回填摘要（不推送提醒，画面已过时）；返回是否处理了一帧
+async fn analyze_next_pending(
     config: &Config,
     model_manager: &ModelManager,
     storage_manager: &StorageManager,
-    recent_alerts: &Arc<ParkingMutex<HashMap<String, DateTime<Local>>>>,
-    last_issue_key: &Arc<ParkingMutex<Option<String>>>,
-    app_handle: &AppHandle,
-    prev_hash: &mut Option<u64>,
+    budget: &Arc<ParkingMutex<BudgetGuard>>,
 ) -> Result<bool, String> {
-    // 1. 截屏
-    let image = ScreenCapture::capture_primary()?;
     let now = Local::now();
+    if budget
+        .lock()
+        .exhausted_reason(&config.capture.budget, storage_manager, &now)
+        .is_some()
+    {
+        return Ok(false);
+    }
+    let Some(item) = storage_actor()
+        .run(StoragePriority::Background, |storage| storage.next_pending_capture())
+        .await?
+    else {
+        return Ok(false);
+    };
 
-    // 2. 如果启用了跳过无变化，进行对比
-    if config.capture.skip_unchanged {
-        let current_hash = compute_image_hash(&image);
-
-        if let Some(prev) = *prev_hash {
-            let similarity = hash_similarity(prev, current_hash);
+    let image = storage_manager
+        .screenshots_dir()
+        .and_then(|dir| storage_manager.read_data_file(&dir.join(&item.screenshot_ref)));
+    let image = match image {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            logs::warn("capture", format!("待分析截图不可用，移出队列: {}", err));
+            let timestamp = item.timestamp.clone();
+            storage_actor()
+                .run(StoragePriority::Background, move |storage| {
+                    storage.remove_pending_capture(&timestamp)
+                })
+                .await?;
+            return Ok(true);
+        }
+    };
 
-            // 如果相似度超过阈值，跳过这一帧
-            if similarity >= config.capture.change_threshold {
-                return Ok(false);  // 返回false表示跳过
+    let window_hint = if item.process_name.is_empty() && item.window_title.is_empty() {
+        String::new()
+    } else {
+        build_window_hint(&item.process_name, &item.window_title)
+    };
+    let prompt = build_analysis_prompt(&window_hint, "（补分析，无近期记录）", None);
+    let capture_model = ModelManager::resolve_model_config(
+        &config.model,
+        Some(config.model.capture_profile.as_str()),
+    );
+    // 模型不支持图片时改用 OCR 文本，识别不出文字的帧移出队列
+    let ocr_text = if vision_unsupported(&capture_model) {
+        match ocr_screenshot(storage_manager, Some(item.screenshot_ref.as_str()), config).await {
+            Some(text) => Some(text),
+            None => {
+                logs::warn("capture", "模型不支持图片且 OCR 未识别出文字，待分析截图移出队列");
+                let timestamp = item.timestamp.clone();
+                storage_actor()
+                    .run(StoragePriority::Background, move |storage| {
+                        storage.remove_pending_capture(&timestamp)
+                    })
+                    .await?;
+                return Ok(true);
             }
         }
-
-        // 更新上一帧哈希
-        *prev_hash = Some(current_hash);
+    } else {
+        None
+    };
+    let input = match &ocr_text {
+        Some(text) => FrameInput::OcrText(text),
+        None => FrameInput::Image(BASE64.encode(&image)),
+    };
+    budget.lock().record_analysis(now);
+    let result = analyze_frame(model_manager, &capture_model, &prompt, input).await;
+    let mut parsed = match result {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            // 非临时错误或重试次数用尽时放弃该帧，避免队列卡住；模型不支持图片时下次改用 OCR 重试
+            let timestamp = item.timestamp.clone();
+            let no_vision = matches!(err, AppError::ImageUnsupported { .. }) && ocr_text.is_none();
+            if no_vision {
+                mark_vision_unsupported(&capture_model, None, &err.to_string());
+            }
+            let retry = err.is_retryable() || no_vision;
+            storage_actor()
+                .run(StoragePriority::Background, move |storage| {
+                    storage.record_pending_attempt(&timestamp, retry, MAX_PENDING_ATTEMPTS)
+                })
+                .await?;
+            return Err(err.into());
+        }
+    };
+    let app = ActiveWindow {
+        title: item.window_title.clone(),
+        process_name: item.process_name.clone(),
+    }
+    .app_name();
+    if let Some(app) = app {
+        parsed.app = app;
     }
+    let issue_summary = if parsed.issue_message.is_empty() {
+        parsed.summary.clone()
+    } else {
+        parsed.issue_message.clone()
+    };
+    let record = analysis_to_record(
+        &parsed,
+        item.timestamp.clone(),
+        issue_summary,
+        item.screenshot_ref.clone(),
+        item.window_title.clone(),
+        item.process_name.clone(),
+    );
+    let timestamp = item.timestamp;
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            storage.save_summary(&record)?;
+            storage.remove_pending_capture(&timestamp)
+        })
+        .await?;
+    Ok(true)
+}
 
-    // 3. 保存截图
-    let screenshot_ref = save_screenshot(storage_manager, &image, &now, config.capture.compress_quality);
+/// 发送给模型的画面内容：整张截图，或画面仅文字变化时的 OCR 文本
+enum FrameInput<'a> {
+    Image(String),
+    OcrText(&'a str),
+}
 
-    // 4. 转换为 base64
-    let image_base64 = ScreenCapture::image_to_base64(&image, config.capture.compress_quality)?;
+/// 调用模型分析一帧并解析结果：JSON 无法解析时先让模型修正一次，仍失败再退回文本启发式
+async fn analyze_frame(
+    model_manager: &ModelManager,
+    capture_model: &ModelConfig,
+    prompt: &str,
+    input: FrameInput<'_>,
+) -> Result<AnalysisResult, AppError> {
+    // 临时故障按模型配置的重试策略重试，仍失败时由调用方放入补分析队列
+    let mut attempt = 0usize;
+    let analysis = loop {
+        let result = match &input {
+            FrameInput::OcrText(text) => {
+                let text: String = text.chars().take(MAX_OCR_PROMPT_CHARS).collect();
+                model_manager
+                    .chat_with_system_prompt(
+                        capture_model,
+                        prompt,
+                        &format!(
+                            "本帧未附带截图，以下是本地 OCR 从当前屏幕识别出的文字（可能有识别误差），请据此输出 JSON：\n{}",
+                            text
+                        ),
+                        None,
+                    )
+                    .await
+            }
+            FrameInput::Image(image_base64) => {
+                model_manager
+                    .analyze_image(capture_model, image_base64, prompt)
+                    .await
+            }
+        };
+        match result {
+            Ok(analysis) => break analysis,
+            Err(err) => {
+                attempt += 1;
+                match retry_delay(capture_model.retry_policy(), attempt, &err) {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => return Err(err),
+                }
+            }
+        }
+    };
 
-    // 5. 发送给大模型识别
-    let recent_context = build_recent_summary_context(
-        storage_manager,
-        config.capture.recent_summary_limit,
-        config.capture.recent_detail_limit,
-    );
-    let prompt = format!(
-        r#"你是屏幕截图分析器和智能助手。请严格只输出一个可解析的 JSON 对象，不要输出任何解释、Markdown 或代码块。
+    let analysis = if extract_json_value(&analysis).is_none() {
+        match model_manager
+            .repair_json_response(capture_model, &analysis, ANALYSIS_JSON_FIELDS)
+            .await
+        {
+            Some(json) => json.to_string(),
+            None => analysis,
+        }
+    } else {
+        analysis
+    };
+    Ok(parse_analysis(&analysis))
+}
+
+/// 截屏分析提示词；实验变体提供了模板时使用模板，否则使用内置提示词
+fn build_analysis_prompt(
+    window_hint: &str,
+    recent_context: &str,
+    variant: Option<&PromptVariant>,
+) -> String {
+    let mut prompt = build_base_analysis_prompt(window_hint, recent_context, variant);
+    if let Some(note) = focus_prompt_note() {
+        prompt.push_str(&note);
+    }
+    prompt
+}
+
+fn build_base_analysis_prompt(
+    window_hint: &str,
+    recent_context: &str,
+    variant: Option<&PromptVariant>,
+) -> String {
+    if let Some(variant) = variant.filter(|variant| !variant.template.is_empty()) {
+        return format!(
+            "{}\n\n请严格只输出一个可解析的 JSON 对象（{}）。",
+            variant
+                .template
+                .replace("{window_hint}", window_hint)
+                .replace("{recent_context}", recent_context),
+            ANALYSIS_JSON_FIELDS
+        );
+    }
+    format!(
+        r#"{}你是屏幕截图分析器和智能助手。请严格只输出一个可解析的 JSON 对象，不要输出任何解释、Markdown 或代码块。
 
 必须包含以下字段：
 {{
@@ -298,139 +1381,247 @@ async fn capture_and_analyze_with_diff(
 近期记录（仅供参考，可能不完整）：
 {}
 "#,
+        window_hint,
         recent_context
-    );
+    )
+}
 
-    let analysis = match model_manager
-        .analyze_image(&config.model, &image_base64, &prompt)
-        .await
-    {
+fn build_window_hint(process_name: &str, title: &str) -> String {
+    format!(
+        "前台窗口（系统读取，准确）：进程 {}，标题 {}\n\n",
+        if process_name.is_empty() { "未知" } else { process_name },
+        if title.is_empty() { "（无）" } else { title }
+    )
+}
+
+async fn ocr_screenshot(
+    storage_manager: &StorageManager,
+    screenshot_ref: Option<&str>,
+    config: &Config,
+) -> Option<String> {
+    let screenshot_ref = screenshot_ref?;
+    let path = storage_manager.screenshots_dir().ok()?.join(screenshot_ref);
+    if config.capture.ocr_index_enabled {
+        return ocr_and_index_screenshot(screenshot_ref, &path, config).await;
+    }
+    match ocr_image_file(&path, &config.capture).await {
+        Ok(text) if !text.trim().is_empty() => Some(text),
+        Ok(_) => None,
+        Err(err) => {
+            logs::warn("capture", format!("OCR 识别失败: {}", err));
+            None
+        }
+    }
+}
+
+/// 识别文字行并保存截图文字层，返回整屏文字
+async fn ocr_and_index_screenshot(
+    screenshot_ref: &str,
+    path: &std::path::Path,
+    config: &Config,
+) -> Option<String> {
+    let (width, height, lines) = match ocr_image_lines(path, &config.capture).await {
         Ok(result) => result,
         Err(err) => {
-            emit_model_error_once(
-                recent_alerts,
-                app_handle,
-                &err,
-                "capture",
-                now,
-                config.capture.alert_cooldown_seconds,
-            );
-            return Err(err);
+            logs::warn("capture", format!("OCR 识别失败: {}", err));
+            return None;
         }
     };
-
-    // 6. 解析分析结果
-    let mut parsed = parse_analysis(&analysis);
-    let alert_threshold = config.capture.alert_confidence_threshold.clamp(0.0, 1.0);
-    let issue_message = if parsed.issue_message.is_empty() {
-        parsed.summary.clone()
-    } else {
-        parsed.issue_message.clone()
+    let text = lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n");
+    // 截图文件名形如 20240101-120000-.123.jpg
+    let timestamp = NaiveDateTime::parse_from_str(screenshot_ref.get(..15)?, "%Y%m%d-%H%M%S")
+        .ok()?
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+    let layer = OcrTextLayer {
+        timestamp,
+        screenshot: screenshot_ref.to_string(),
+        width,
+        height,
+        lines,
     };
-    let mut should_emit = false;
-    let mut current_issue_key: Option<String> = None;
+    let saved = storage_actor()
+        .run(StoragePriority::Background, move |storage| storage.save_ocr_layer(layer))
+        .await;
+    if let Err(err) = saved {
+        logs::warn("capture", format!("保存截图文字层失败: {}", err));
+    }
+    (!text.trim().is_empty()).then_some(text)
+}
 
-    if parsed.has_issue && parsed.confidence >= alert_threshold && !should_suppress_alert(&parsed) {
-        let alert_key = build_alert_key(&parsed, &issue_message);
-        current_issue_key = Some(alert_key.clone());
+/// 推送规则命中的提醒，与模型提醒共用冷却、暂停和离开暂存逻辑
+fn emit_rule_alerts(
+    matches: Vec<RuleMatch>,
+    app: &str,
+    config: &Config,
+    storage_manager: &StorageManager,
+    recent_alerts: &Arc<ParkingMutex<AlertTracker>>,
+    app_handle: &AppHandle,
+    now: &DateTime<Local>,
+) {
+    let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
+    for hit in matches {
+        let key = format!("rule:{}", hit.rule_id);
+        if is_alert_suppressed(app, "", &hit.rule_name, &hit.message) {
+            continue;
+        }
+        if !recent_alerts
+            .lock()
+            .should_emit(&key, *now, config.capture.alert_cooldown_seconds)
+        {
+            continue;
+        }
+        let mut alert = AssistantAlert {
+            key,
+            escalated: false,
+            delayed: false,
+            timestamp: timestamp.clone(),
+            issue_type: hit.rule_name.clone(),
+            message: hit.message.clone(),
+            suggestion: hit.suggestion.clone(),
+            intent: String::new(),
+            scene: String::new(),
+            help_type: "rule".to_string(),
+            urgency: hit.urgency.clone(),
+            related_skill: String::new(),
+            actions: Vec::new(),
+        };
+        alert.actions = build_alert_actions(&alert);
 
-        let last_key = last_issue_key.lock().clone();
-        if last_key.as_deref() != Some(alert_key.as_str()) {
-            should_emit = should_emit_alert(
-                recent_alerts,
-                &alert_key,
-                now,
-                config.capture.alert_cooldown_seconds,
-            );
+        let alert_log = format!(
+            "time: {}\nrule: {} ({})\nfield: {}\nmatched: {}\nmessage: {}\n",
+            timestamp, hit.rule_name, hit.rule_id, hit.field, hit.snippet, alert.message
+        );
+        if let Err(err) = storage_manager.write_log_snapshot("assistant-alert", &alert_log) {
+            logs::warn("capture", format!("写入提醒日志失败: {}", err));
         }
 
-        if should_emit && parsed.suggestion.trim().is_empty() {
-            match generate_issue_suggestion(&model_manager, &config, &recent_context, &parsed).await {
-                Ok(suggestion) => parsed.suggestion = suggestion,
-                Err(err) => {
-                    eprintln!("生成建议失败: {}", err);
-                    parsed.suggestion = "建议生成失败，请查看详情或稍后重试。".to_string();
-                }
-            }
+        if user_is_away(config.capture.alert_idle_threshold_seconds) {
+            send_alert_webhook(&config.capture.alert_webhook_url, &alert);
+            let mut alert = alert;
+            alert.delayed = true;
+            recent_alerts.lock().hold(alert);
+            continue;
+        }
+        if !recent_alerts
+            .lock()
+            .take_budget(&alert.urgency, *now, config.capture.alert_max_per_hour)
+        {
+            log_dropped_alert(&alert);
+            continue;
         }
+        deliver_alert(config, app_handle, alert);
     }
+}
 
-    *last_issue_key.lock() = current_issue_key;
-
-    // 7. 保存摘要
-    let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
-    let issue_summary = issue_message.clone();
-
-    let summary = SummaryRecord {
-        timestamp: timestamp.clone(),
-        summary: parsed.summary.clone(),
-        app: parsed.app.clone(),
-        action: if parsed.has_issue { "issue".to_string() } else { "active".to_string() },
-        keywords: extract_keywords_from_analysis(&parsed.summary),
-        has_issue: parsed.has_issue,
-        issue_type: parsed.issue_type.clone(),
-        issue_summary,
-        suggestion: parsed.suggestion.clone(),
-        confidence: parsed.confidence,
-        detail: parsed.detail.clone(),
-        detail_ref: screenshot_ref.unwrap_or_default(),
-        // 意图识别相关字段
-        intent: parsed.intent.clone(),
-        scene: parsed.scene.clone(),
-        urgency: parsed.urgency.clone(),
-        related_skill: parsed.related_skill.clone(),
-    };
-
-    storage_manager.save_summary(&summary)?;
+fn user_is_away(idle_threshold_seconds: u64) -> bool {
+    idle_threshold_seconds > 0 && idle_seconds().map_or(false, |idle| idle >= idle_threshold_seconds)
+}
 
-    // 8. 如果需要帮助（包括错误或主动建议），推送提示
-    let should_notify = (parsed.has_issue || parsed.needs_help)
-        && parsed.confidence >= alert_threshold
-        && !should_suppress_alert(&parsed)
-        && (parsed.urgency == "high" || parsed.urgency == "medium");
+fn log_dropped_alert(alert: &AssistantAlert) {
+    logs::info(
+        "capture",
+        format!("已达到每小时提醒上限，丢弃 {} 紧急度提醒: {}", alert.urgency, alert.message),
+    );
+}
 
-    if should_notify && should_emit {
-        let alert_message = AssistantAlert {
-            timestamp: timestamp.clone(),
-            issue_type: parsed.issue_type.clone(),
-            message: issue_message.clone(),
-            suggestion: parsed.suggestion.clone(),
-            intent: parsed.intent.clone(),
-            scene: parsed.scene.clone(),
-            help_type: parsed.help_type.clone(),
-            urgency: parsed.urgency.clone(),
-            related_skill: parsed.related_skill.clone(),
-        };
+/// 推送提醒：按配置朗读并发送到界面。用户离开时不调用，朗读随暂存的提醒在用户回来后进行
+fn deliver_alert(config: &Config, app_handle: &AppHandle, alert: AssistantAlert) {
+    crate::tts::speak_alert(config, &alert.message, &alert.suggestion, &alert.urgency);
+    if let Err(err) = app_handle.emit("assistant-alert", alert) {
+        logs::warn("capture", format!("发送提醒失败: {}", err));
+    }
+}
 
-        let mut alert_log = String::new();
-        alert_log.push_str(&format!("time: {}\n", timestamp));
-        alert_log.push_str(&format!("intent: {}\n", alert_message.intent));
-        alert_log.push_str(&format!("scene: {}\n", alert_message.scene));
-        alert_log.push_str(&format!("help_type: {}\n", alert_message.help_type));
-        alert_log.push_str(&format!("urgency: {}\n", alert_message.urgency));
-        alert_log.push_str(&format!("issue_type: {}\n", alert_message.issue_type));
-        alert_log.push_str(&format!("message: {}\n", alert_message.message));
-        if !alert_message.suggestion.is_empty() {
-            alert_log.push_str(&format!("suggestion: {}\n", alert_message.suggestion));
-        }
-        alert_log.push_str(&format!(
-            "confidence: {:.2}\nthreshold: {:.2}\n",
-            parsed.confidence, alert_threshold
-        ));
-        if let Err(err) = storage_manager.write_log_snapshot("assistant-alert", &alert_log) {
-            eprintln!("写入提醒日志失败: {}", err);
+fn deliver_held_alerts(
+    recent_alerts: &Arc<ParkingMutex<AlertTracker>>,
+    app_handle: &AppHandle,
+    config: &Config,
+) {
+    if !recent_alerts.lock().has_held() || user_is_away(config.capture.alert_idle_threshold_seconds) {
+        return;
+    }
+    let mut held = recent_alerts.lock().take_held();
+    // 额度不够时优先补发紧急度高的提醒
+    held.sort_by_key(|alert| match alert.urgency.as_str() {
+        "high" => 0,
+        "medium" => 1,
+        _ => 2,
+    });
+    let now = Local::now();
+    for alert in held {
+        if !recent_alerts
+            .lock()
+            .take_budget(&alert.urgency, now, config.capture.alert_max_per_hour)
+        {
+            log_dropped_alert(&alert);
+            continue;
         }
+        deliver_alert(config, app_handle, alert);
+    }
+}
 
-        if let Err(err) = app_handle.emit("assistant-alert", alert_message) {
-            eprintln!("发送提醒失败: {}", err);
-        }
+fn send_alert_webhook(url: &str, alert: &AssistantAlert) {
+    let url = url.trim();
+    if url.is_empty() {
+        return;
     }
+    let url = url.to_string();
+    let alert = alert.clone();
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let result = client
+            .post(&url)
+            .timeout(std::time::Duration::from_secs(10))
+            .json(&alert)
+            .send()
+            .await;
+        match result {
+            Ok(resp) if !resp.status().is_success() => {
+                logs::warn("capture", format!("提醒 webhook 返回 {}", resp.status()));
+            }
+            Ok(_) => {}
+            Err(err) => logs::warn("capture", format!("发送提醒 webhook 失败: {}", err)),
+        }
+    });
+}
 
-    Ok(true)  // 返回true表示已分析
+/// 隐私跳过时写入的占位记录，不包含应用、标题等任何画面信息
+fn private_skip_record(now: &DateTime<Local>) -> SummaryRecord {
+    SummaryRecord {
+        timestamp: now.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        summary: "skipped (私密)".to_string(),
+        app: "Private".to_string(),
+        action: "private".to_string(),
+        keywords: Vec::new(),
+        has_issue: false,
+        issue_type: String::new(),
+        issue_summary: String::new(),
+        suggestion: String::new(),
+        confidence: 0.0,
+        detail: String::new(),
+        detail_ref: String::new(),
+        intent: String::new(),
+        scene: String::new(),
+        urgency: String::new(),
+        related_skill: String::new(),
+        window_title: String::new(),
+        process_name: String::new(),
+        resolution: String::new(),
+        resolution_note: String::new(),
+        prompt_variant: String::new(),
+        feedback: String::new(),
+        ticket_url: String::new(),
+        changed_regions: Vec::new(),
+    }
 }
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct AssistantAlert {
+    pub key: String,  // 用于 snooze_alert
+    pub escalated: bool,
+    #[serde(default)]
+    pub delayed: bool,  // 用户离开期间暂存、回来后补发
     pub timestamp: String,
     pub issue_type: String,
     pub message: String,
@@ -441,6 +1632,66 @@ pub struct AssistantAlert {
     pub help_type: String,
     pub urgency: String,
     pub related_skill: String,
+    #[serde(default)]
+    pub actions: Vec<AlertAction>,  // 通知上的操作按钮，通过 run_alert_action 执行
+}
+
+/// 提醒上的操作：skill 用预填参数调用技能，snooze 暂停该提醒，ignore_issue_type 屏蔽同类问题
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AlertAction {
+    pub id: String,
+    pub label: String,
+    pub kind: String,  // skill | snooze | ignore_issue_type
+    #[serde(default)]
+    pub skill: String,
+    #[serde(default)]
+    pub args: String,
+    #[serde(default)]
+    pub minutes: u64,
+    #[serde(default)]
+    pub issue_type: String,
+}
+
+fn build_alert_actions(alert: &AssistantAlert) -> Vec<AlertAction> {
+    let mut actions = Vec::new();
+    let skill = alert.related_skill.trim().trim_start_matches('/');
+    if !skill.is_empty() {
+        let args = if alert.suggestion.trim().is_empty() {
+            alert.message.clone()
+        } else {
+            format!("{}\n建议: {}", alert.message, alert.suggestion)
+        };
+        actions.push(AlertAction {
+            id: "skill".to_string(),
+            label: format!("用 /{} 处理", skill),
+            kind: "skill".to_string(),
+            skill: skill.to_string(),
+            args,
+            minutes: 0,
+            issue_type: String::new(),
+        });
+    }
+    actions.push(AlertAction {
+        id: "snooze".to_string(),
+        label: "暂停提醒 1 小时".to_string(),
+        kind: "snooze".to_string(),
+        skill: String::new(),
+        args: String::new(),
+        minutes: 60,
+        issue_type: String::new(),
+    });
+    if !alert.issue_type.trim().is_empty() {
+        actions.push(AlertAction {
+            id: "ignore".to_string(),
+            label: "忽略此类问题".to_string(),
+            kind: "ignore_issue_type".to_string(),
+            skill: String::new(),
+            args: String::new(),
+            minutes: 0,
+            issue_type: alert.issue_type.clone(),
+        });
+    }
+    actions
 }
 
 fn should_suppress_alert(parsed: &AnalysisResult) -> bool {
@@ -472,6 +1723,15 @@ fn should_suppress_alert(parsed: &AnalysisResult) -> bool {
     false
 }
 
+/// 由已保存的记录还原提醒 key（与 build_alert_key 一致）
+pub fn record_alert_key(record: &SummaryRecord) -> String {
+    let issue_type = normalize_key(&record.issue_type);
+    if !issue_type.is_empty() {
+        return issue_type;
+    }
+    normalize_issue_text(&record.issue_summary)
+}
+
 fn build_alert_key(parsed: &AnalysisResult, issue_message: &str) -> String {
     let issue_type = normalize_key(&parsed.issue_type);
     if !issue_type.is_empty() {
@@ -512,25 +1772,8 @@ fn normalize_issue_text(text: &str) -> String {
     out.trim().to_string()
 }
 
-fn should_emit_alert(
-    recent_alerts: &Arc<ParkingMutex<HashMap<String, DateTime<Local>>>>,
-    alert_key: &str,
-    now: DateTime<Local>,
-    cooldown_seconds: u64,
-) -> bool {
-    let cooldown = Duration::seconds(cooldown_seconds.max(5) as i64);
-    let mut alerts = recent_alerts.lock();
-    if let Some(prev) = alerts.get(alert_key) {
-        if now.signed_duration_since(*prev) < cooldown {
-            return false;
-        }
-    }
-    alerts.insert(alert_key.to_string(), now);
-    true
-}
-
 fn emit_model_error_once(
-    recent_alerts: &Arc<ParkingMutex<HashMap<String, DateTime<Local>>>>,
+    recent_alerts: &Arc<ParkingMutex<AlertTracker>>,
     app_handle: &AppHandle,
     detail: &str,
     source: &str,
@@ -539,7 +1782,7 @@ fn emit_model_error_once(
 ) {
     let alert = build_model_error_alert(detail, source);
     let key = format!("model:{}:{}", &alert.error_type, &alert.message);
-    if should_emit_alert(recent_alerts, &key, now, cooldown_seconds) {
+    if recent_alerts.lock().should_emit(&key, now, cooldown_seconds) {
         let _ = app_handle.emit("model-error", alert);
     }
 }
@@ -561,6 +1804,7 @@ struct AnalysisResult {
     help_type: String,        // 帮助类型: error/reminder/suggestion/info
     urgency: String,          // 紧急程度: high/medium/low
     related_skill: String,    // 预留：相关 Skill
+    on_goal: Option<bool>,    // 专注时段内是否服务于目标
 }
 
 fn parse_analysis(analysis: &str) -> AnalysisResult {
@@ -621,6 +1865,7 @@ fn parse_analysis(analysis: &str) -> AnalysisResult {
             help_type,
             urgency,
             related_skill,
+            on_goal: json.get("on_goal").and_then(|v| v.as_bool()),
         };
     }
 
@@ -649,59 +1894,12 @@ fn parse_analysis(analysis: &str) -> AnalysisResult {
         help_type: if has_issue { "error".to_string() } else { String::new() },
         urgency: if has_issue { "medium".to_string() } else { "low".to_string() },
         related_skill: String::new(),
+        on_goal: None,
     }
 }
 
 fn extract_json_value(text: &str) -> Option<serde_json::Value> {
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(text) {
-        return Some(json);
-    }
-
-    if let Some(inner) = extract_fenced_json(text) {
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&inner) {
-            return Some(json);
-        }
-    }
-
-    if let Some(inner) = extract_braced_json(text) {
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&inner) {
-            return Some(json);
-        }
-    }
-
-    None
-}
-
-fn extract_fenced_json(text: &str) -> Option<String> {
-    if let Some(start) = text.find("```json") {
-        let rest = &text[start + 7..];
-        return extract_fence_body(rest);
-    }
-
-    if let Some(start) = text.find("```") {
-        let rest = &text[start + 3..];
-        return extract_fence_body(rest);
-    }
-
-    None
-}
-
-fn extract_fence_body(text: &str) -> Option<String> {
-    let end = text.find("```")?;
-    let mut body = text[..end].trim().to_string();
-    if let Some(stripped) = body.strip_prefix("json") {
-        body = stripped.trim_start().to_string();
-    }
-    Some(body)
-}
-
-fn extract_braced_json(text: &str) -> Option<String> {
-    let start = text.find('{')?;
-    let end = text.rfind('}')?;
-    if end <= start {
-        return None;
-    }
-    Some(text[start..=end].to_string())
+    crate::model::parse_json_lenient(text)
 }
 
 async fn generate_issue_suggestion(
@@ -733,7 +1931,7 @@ async fn generate_issue_suggestion(
 
     let question = "基于以上信息给出 1-3 条可执行的解决建议，尽量具体，不要复述背景。";
 
-    model_manager.chat(&config.model, &context, question).await
+    Ok(model_manager.chat(&config.model, &context, question).await?)
 }
 
 fn extract_app_from_text(text: &str) -> String {
@@ -776,18 +1974,17 @@ fn extract_keywords_from_analysis(analysis: &str) -> Vec<String> {
     keywords
 }
 
-fn build_recent_summary_context(
-    storage_manager: &StorageManager,
-    max_items: usize,
-    detail_limit: usize,
-) -> String {
+async fn build_recent_summary_context(max_items: usize, detail_limit: usize) -> String {
     let now = Local::now();
     let date = now.format("%Y-%m-%d").to_string();
     let cutoff = (now - Duration::minutes(RECENT_CONTEXT_MINUTES))
         .format("%Y-%m-%dT%H:%M:%S")
         .to_string();
 
-    let records = match storage_manager.get_summaries(&date) {
+    let records = match storage_actor()
+        .run(StoragePriority::Background, move |storage| storage.get_summaries(&date))
+        .await
+    {
         Ok(data) => data,
         Err(_) => return "（无）".to_string(),
     };
@@ -0,0 +1,161 @@
+use crate::storage::{is_encrypted, CaptureConfig, OcrLine, StorageManager};
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+const OCR_TIMEOUT_SECS: u64 = 30;
+
+/// 调用本地 OCR 引擎（默认 tesseract）识别图片中的文字
+pub async fn ocr_image_file(path: &Path, config: &CaptureConfig) -> Result<String, String> {
+    let output = ocr_output(path, config, &[]).await?;
+    Ok(normalize_ocr_text(&output))
+}
+
+/// 识别图片中的文字行及位置，返回 (图片宽, 图片高, 文字行)
+pub async fn ocr_image_lines(path: &Path, config: &CaptureConfig) -> Result<(u32, u32, Vec<OcrLine>), String> {
+    let output = ocr_output(path, config, &["tsv"]).await?;
+    Ok(parse_tsv_lines(&output))
+}
+
+async fn ocr_output(path: &Path, config: &CaptureConfig, extra_args: &[&str]) -> Result<String, String> {
+    if !path.is_file() {
+        return Err(format!("图片不存在: {}", path.display()));
+    }
+
+    // 加密的截图解密后通过标准输入交给 OCR 引擎，明文不落盘
+    let data = fs::read(path).map_err(|e| format!("读取图片失败: {}", e))?;
+    if is_encrypted(&data) {
+        let plain = StorageManager::new().read_data_file(path)?;
+        return run_ocr(path, Some(plain), config, extra_args).await;
+    }
+    run_ocr(path, None, config, extra_args).await
+}
+
+/// input 为 Some 时图片从标准输入传入（tesseract 的 stdin 参数），否则按路径读取
+async fn run_ocr(
+    path: &Path,
+    input: Option<Vec<u8>>,
+    config: &CaptureConfig,
+    extra_args: &[&str],
+) -> Result<String, String> {
+    let command = if config.ocr_command.trim().is_empty() {
+        "tesseract"
+    } else {
+        config.ocr_command.trim()
+    };
+
+    let mut cmd = tokio::process::Command::new(command);
+    match input {
+        Some(_) => cmd.arg("stdin"),
+        None => cmd.arg(path),
+    };
+    cmd.arg("stdout");
+    if !config.ocr_languages.trim().is_empty() {
+        cmd.arg("-l").arg(config.ocr_languages.trim());
+    }
+    cmd.args(extra_args);
+    cmd.stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("启动 OCR 失败（请确认已安装 {}）: {}", command, e))?;
+    if let (Some(mut stdin), Some(data)) = (child.stdin.take(), input) {
+        // 与读取输出并行写入，避免引擎输出填满管道时互相等待；写完后关闭标准输入
+        tokio::spawn(async move {
+            let _ = stdin.write_all(&data).await;
+        });
+    }
+    let output = tokio::time::timeout(
+        std::time::Duration::from_secs(OCR_TIMEOUT_SECS),
+        child.wait_with_output(),
+    )
+    .await
+    .map_err(|_| "OCR 超时".to_string())?
+    .map_err(|e| format!("OCR 执行失败: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("OCR 失败: {}", stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// 去掉空行和多余空白；tesseract 识别中文时常在字间插入空格
+fn normalize_ocr_text(text: &str) -> String {
+    text.lines()
+        .map(normalize_ocr_line)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn normalize_ocr_line(line: &str) -> String {
+    let mut out = String::new();
+    let chars: Vec<char> = line.trim().chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        if *c == ' ' {
+            let prev_cjk = i > 0 && is_cjk(chars[i - 1]);
+            let next_cjk = chars.get(i + 1).map_or(false, |n| is_cjk(*n));
+            if prev_cjk && next_cjk {
+                continue;
+            }
+        }
+        out.push(*c);
+    }
+    out
+}
+
+/// 解析 tesseract 的 tsv 输出：level 1 为整页尺寸，level 5 为单词；按 block/par/line 合并成行并取外接框
+fn parse_tsv_lines(tsv: &str) -> (u32, u32, Vec<OcrLine>) {
+    let mut size = (0, 0);
+    let mut lines: Vec<((u32, u32, u32), Vec<String>, [u32; 4])> = Vec::new();
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.split('\t').collect();
+        if cols.len() < 12 {
+            continue;
+        }
+        let num = |i: usize| cols[i].trim().parse::<u32>().unwrap_or(0);
+        let (left, top, width, height) = (num(6), num(7), num(8), num(9));
+        match cols[0].trim() {
+            "1" => size = (width, height),
+            "5" if !cols[11].trim().is_empty() => {
+                let key = (num(2), num(3), num(4));
+                let (right, bottom) = (left + width, top + height);
+                match lines.last_mut().filter(|line| line.0 == key) {
+                    Some(line) => {
+                        line.1.push(cols[11].trim().to_string());
+                        line.2 = [
+                            line.2[0].min(left),
+                            line.2[1].min(top),
+                            line.2[2].max(right),
+                            line.2[3].max(bottom),
+                        ];
+                    }
+                    None => lines.push((key, vec![cols[11].trim().to_string()], [left, top, right, bottom])),
+                }
+            }
+            _ => {}
+        }
+    }
+    let lines = lines
+        .into_iter()
+        .map(|(_, words, [left, top, right, bottom])| OcrLine {
+            text: normalize_ocr_line(&words.join(" ")),
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        })
+        .filter(|line| !line.text.is_empty())
+        .collect();
+    (size.0, size.1, lines)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0x3000..=0x303F | 0xFF00..=0xFFEF)
+}
@@ -0,0 +1,47 @@
+/// 距离用户最后一次键盘/鼠标输入的秒数，无法获取时返回 None
+pub fn idle_seconds() -> Option<u64> {
+    platform_idle_seconds()
+}
+
+#[cfg(target_os = "windows")]
+fn platform_idle_seconds() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    unsafe {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if GetLastInputInfo(&mut info) == 0 {
+            return None;
+        }
+        let elapsed_ms = GetTickCount().wrapping_sub(info.dwTime);
+        Some(elapsed_ms as u64 / 1000)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_idle_seconds() -> Option<u64> {
+    // HIDIdleTime 单位为纳秒
+    let output = super::window::run_quiet("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?;
+    output
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))
+        .and_then(|line| line.rsplit('=').next())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|nanos| nanos / 1_000_000_000)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_idle_seconds() -> Option<u64> {
+    // 依赖 xprintidle（X11），单位为毫秒
+    super::window::run_quiet("xprintidle", &[])
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|millis| millis / 1000)
+}
+
+#[cfg(not(any(target_os = "windows", unix)))]
+fn platform_idle_seconds() -> Option<u64> {
+    None
+}
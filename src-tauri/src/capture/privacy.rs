@@ -0,0 +1,155 @@
+use super::target::capture_target;
+use super::ActiveWindow;
+use crate::storage::{CaptureConfig, PrivacyConfig, PrivacyTimeRule};
+use chrono::{DateTime, Datelike, Local, NaiveTime};
+use image::DynamicImage;
+
+/// 受隐私规则约束的截屏结果
+pub enum GuardedCapture {
+    Captured(DynamicImage),
+    Blocked(String),  // 命中隐私规则，附原因
+    TargetMissing,    // 目标窗口不可见
+}
+
+/// 所有截屏入口共用：先检查隐私规则，再按配置的截屏范围截图
+pub fn capture_with_privacy(
+    capture: &CaptureConfig,
+    window: Option<&ActiveWindow>,
+    now: &DateTime<Local>,
+) -> Result<GuardedCapture, String> {
+    if let Some(reason) = privacy_block_reason(&capture.privacy, window, now) {
+        return Ok(GuardedCapture::Blocked(reason));
+    }
+    Ok(match capture_target(&capture.target)? {
+        Some(image) => GuardedCapture::Captured(image),
+        None => GuardedCapture::TargetMissing,
+    })
+}
+
+/// 检查当前是否命中隐私规则，命中时返回原因（只用于日志，不写入记录）
+pub fn privacy_block_reason(
+    config: &PrivacyConfig,
+    window: Option<&ActiveWindow>,
+    now: &DateTime<Local>,
+) -> Option<String> {
+    if !config.enabled {
+        return None;
+    }
+
+    if let Some(rule) = config.time_rules.iter().find(|rule| time_rule_matches(rule, now)) {
+        return Some(format!("time rule {}-{}", rule.start, rule.end));
+    }
+
+    let window = window?;
+    let process = window.process_name.to_lowercase();
+    let app = window.app_name().unwrap_or_default().to_lowercase();
+    let title = window.title.to_lowercase();
+
+    for item in &config.blocked_apps {
+        let item = item.trim().to_lowercase();
+        if item.is_empty() {
+            continue;
+        }
+        let item = item.strip_suffix(".exe").unwrap_or(&item);
+        if app == item || process.contains(item) {
+            return Some(format!("blocked app {}", item));
+        }
+    }
+
+    for keyword in &config.blocked_title_keywords {
+        let keyword = keyword.trim().to_lowercase();
+        if !keyword.is_empty() && title.contains(&keyword) {
+            return Some(format!("blocked title keyword {}", keyword));
+        }
+    }
+
+    None
+}
+
+/// 时间规则：HH:MM 区间，支持跨午夜；weekdays 为空表示每天（1=周一 … 7=周日）
+fn time_rule_matches(rule: &PrivacyTimeRule, now: &DateTime<Local>) -> bool {
+    let (Ok(start), Ok(end)) = (
+        NaiveTime::parse_from_str(rule.start.trim(), "%H:%M"),
+        NaiveTime::parse_from_str(rule.end.trim(), "%H:%M"),
+    ) else {
+        return false;
+    };
+    let time = now.time();
+    let weekday = now.weekday().number_from_monday();
+
+    if start <= end {
+        let in_range = time >= start && time < end;
+        in_range && day_matches(rule, weekday)
+    } else if time >= start {
+        day_matches(rule, weekday)
+    } else if time < end {
+        // 跨午夜的后半段属于前一天的规则
+        let previous = if weekday == 1 { 7 } else { weekday - 1 };
+        day_matches(rule, previous)
+    } else {
+        false
+    }
+}
+
+fn day_matches(rule: &PrivacyTimeRule, weekday: u32) -> bool {
+    rule.weekdays.is_empty() || rule.weekdays.contains(&weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn rule(start: &str, end: &str, weekdays: &[u32]) -> PrivacyTimeRule {
+        PrivacyTimeRule {
+            start: start.to_string(),
+            end: end.to_string(),
+            weekdays: weekdays.to_vec(),
+        }
+    }
+
+    // 2024-05-06 是周一
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_same_day_range() {
+        let rule = rule("09:00", "18:00", &[]);
+        assert!(time_rule_matches(&rule, &at(6, 9, 0)));
+        assert!(time_rule_matches(&rule, &at(6, 17, 59)));
+        assert!(!time_rule_matches(&rule, &at(6, 18, 0)));
+        assert!(!time_rule_matches(&rule, &at(6, 8, 59)));
+    }
+
+    #[test]
+    fn test_range_crossing_midnight() {
+        let rule = rule("22:00", "06:00", &[]);
+        assert!(time_rule_matches(&rule, &at(6, 23, 30)));
+        assert!(time_rule_matches(&rule, &at(7, 2, 0)));
+        assert!(!time_rule_matches(&rule, &at(7, 6, 0)));
+        assert!(!time_rule_matches(&rule, &at(7, 12, 0)));
+    }
+
+    #[test]
+    fn test_weekday_rollover_after_midnight() {
+        // 周五晚上的规则覆盖到周六凌晨，但不覆盖周五凌晨
+        let friday_night = rule("22:00", "06:00", &[5]);
+        assert!(time_rule_matches(&friday_night, &at(10, 23, 0)));
+        assert!(time_rule_matches(&friday_night, &at(11, 2, 0)));
+        assert!(!time_rule_matches(&friday_night, &at(10, 2, 0)));
+
+        // 周日晚上的规则延续到周一凌晨
+        let sunday_night = rule("23:00", "01:30", &[7]);
+        assert!(time_rule_matches(&sunday_night, &at(6, 1, 0)));
+        assert!(!time_rule_matches(&sunday_night, &at(7, 1, 0)));
+    }
+
+    #[test]
+    fn test_weekday_filter_and_invalid_times() {
+        let weekdays = rule("09:00", "18:00", &[1, 2, 3, 4, 5]);
+        assert!(time_rule_matches(&weekdays, &at(10, 10, 0)));
+        assert!(!time_rule_matches(&weekdays, &at(11, 10, 0)));
+        assert!(!time_rule_matches(&rule("9am", "18:00", &[]), &at(6, 10, 0)));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::storage::{CaptureRegion, RegionDiffConfig};
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+// 每个区块缩放后的边长（像素），逐块比较这些采样点
+const BLOCK_SAMPLE: u32 = 16;
+const MAX_GRID: u32 = 32;
+
+/// 按网格切分后的画面采样，用于找出发生变化的区块
+#[derive(Debug, Clone)]
+pub struct BlockGrid {
+    cols: u32,
+    rows: u32,
+    width: u32,  // 原图尺寸
+    height: u32,
+    hashes: Vec<u64>,
+    samples: Vec<Vec<u8>>,  // 每块的灰度采样
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl BlockGrid {
+    pub fn compute(image: &DynamicImage, cols: u32, rows: u32) -> Self {
+        let cols = cols.clamp(1, MAX_GRID);
+        let rows = rows.clamp(1, MAX_GRID);
+        // 先整体缩放再切块，Triangle 取区域平均，小字变化也能反映到采样上
+        let small = image
+            .resize_exact(cols * BLOCK_SAMPLE, rows * BLOCK_SAMPLE, FilterType::Triangle)
+            .to_luma8();
+        let mut samples = Vec::with_capacity((cols * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let mut block = Vec::with_capacity((BLOCK_SAMPLE * BLOCK_SAMPLE) as usize);
+                for y in 0..BLOCK_SAMPLE {
+                    for x in 0..BLOCK_SAMPLE {
+                        block.push(small.get_pixel(col * BLOCK_SAMPLE + x, row * BLOCK_SAMPLE + y).0[0]);
+                    }
+                }
+                samples.push(block);
+            }
+        }
+        Self {
+            cols,
+            rows,
+            width: image.width(),
+            height: image.height(),
+            hashes: samples.iter().map(|block| fnv1a(block)).collect(),
+            samples,
+        }
+    }
+
+    /// 与上一帧比较，返回变化的区块下标；网格或画面尺寸不同时视为全部变化。
+    /// 区块内超过 tolerance 的采样点不少于 min_samples 个才算变化，避免光标闪烁触发分析
+    pub fn changed_blocks(&self, previous: &BlockGrid, tolerance: u8, min_samples: u32) -> Vec<usize> {
+        if self.cols != previous.cols
+            || self.rows != previous.rows
+            || self.width != previous.width
+            || self.height != previous.height
+        {
+            return (0..self.hashes.len()).collect();
+        }
+        let min_samples = min_samples.max(1) as usize;
+        (0..self.hashes.len())
+            .filter(|&index| {
+                self.hashes[index] != previous.hashes[index]
+                    && self.samples[index]
+                        .iter()
+                        .zip(&previous.samples[index])
+                        .filter(|(a, b)| a.abs_diff(**b) > tolerance)
+                        .count()
+                        >= min_samples
+            })
+            .collect()
+    }
+
+    /// 区块在原图中的像素范围
+    fn block_bounds(&self, index: usize) -> (u32, u32, u32, u32) {
+        let col = index as u32 % self.cols;
+        let row = index as u32 / self.cols;
+        (
+            col * self.width / self.cols,
+            row * self.height / self.rows,
+            (col + 1) * self.width / self.cols,
+            (row + 1) * self.height / self.rows,
+        )
+    }
+
+    /// 同一行相邻的变化区块合并为一个矩形，坐标为截图像素坐标
+    pub fn changed_regions(&self, blocks: &[usize]) -> Vec<CaptureRegion> {
+        let mut regions: Vec<(usize, CaptureRegion)> = Vec::new();
+        for &index in blocks {
+            let (left, top, right, bottom) = self.block_bounds(index);
+            if let Some((last, region)) = regions.last_mut() {
+                let same_row = *last as u32 / self.cols == index as u32 / self.cols;
+                if same_row && *last + 1 == index {
+                    region.width = right - region.x as u32;
+                    *last = index;
+                    continue;
+                }
+            }
+            regions.push((
+                index,
+                CaptureRegion {
+                    x: left as i32,
+                    y: top as i32,
+                    width: right - left,
+                    height: bottom - top,
+                },
+            ));
+        }
+        regions.into_iter().map(|(_, region)| region).collect()
+    }
+
+    /// 覆盖所有变化区块的外接矩形
+    fn bounding_region(&self, blocks: &[usize]) -> Option<CaptureRegion> {
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for &index in blocks {
+            let (left, top, right, bottom) = self.block_bounds(index);
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                None => (left, top, right, bottom),
+            });
+        }
+        let (left, top, right, bottom) = bounds?;
+        Some(CaptureRegion {
+            x: left as i32,
+            y: top as i32,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+}
+
+/// 变化范围足够小时裁剪出来单独发送给模型；返回 None 表示应发送整张截图
+pub fn crop_changed_area(
+    image: &DynamicImage,
+    grid: &BlockGrid,
+    blocks: &[usize],
+    config: &RegionDiffConfig,
+) -> Option<(DynamicImage, CaptureRegion)> {
+    if !config.crop_changed || blocks.is_empty() {
+        return None;
+    }
+    let bounds = grid.bounding_region(blocks)?;
+    let ratio = (bounds.width as f32 * bounds.height as f32) / (grid.width as f32 * grid.height as f32);
+    if ratio > config.max_crop_ratio {
+        return None;
+    }
+    let cropped = image.crop_imm(bounds.x as u32, bounds.y as u32, bounds.width, bounds.height);
+    Some((cropped, bounds))
+}
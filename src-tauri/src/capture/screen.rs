@@ -1,8 +1,6 @@
 use image::{DynamicImage, ImageOutputFormat};
 use screenshots::Screen;
-use std::fs::File;
 use std::io::Cursor;
-use std::path::Path;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
 pub struct ScreenCapture;
@@ -23,45 +21,57 @@ impl ScreenCapture {
 
         let width = image.width();
         let height = image.height();
-        let rgba = image.into_raw();
+        to_dynamic_image(width, height, image.into_raw())
+    }
+
+    /// 截取指定区域；区域跨屏时只截取区域中心所在的屏幕部分
+    pub fn capture_region(x: i32, y: i32, width: u32, height: u32) -> Result<DynamicImage, String> {
+        if width == 0 || height == 0 {
+            return Err("截屏区域的宽高必须大于 0".to_string());
+        }
+        let center_x = x.saturating_add((width / 2) as i32);
+        let center_y = y.saturating_add((height / 2) as i32);
+        let screen = Screen::from_point(center_x, center_y)
+            .map_err(|e| format!("截屏区域不在任何屏幕上: {}", e))?;
+        let info = screen.display_info;
+
+        let left = x.max(info.x);
+        let top = y.max(info.y);
+        let right = x.saturating_add(width as i32).min(info.x + info.width as i32);
+        let bottom = y.saturating_add(height as i32).min(info.y + info.height as i32);
+        if right <= left || bottom <= top {
+            return Err("截屏区域不在屏幕范围内".to_string());
+        }
+
+        let image = screen
+            .capture_area(left - info.x, top - info.y, (right - left) as u32, (bottom - top) as u32)
+            .map_err(|e| format!("截屏失败: {}", e))?;
 
-        image::RgbaImage::from_raw(width, height, rgba)
-            .map(DynamicImage::ImageRgba8)
-            .ok_or_else(|| "图像转换失败".to_string())
+        let width = image.width();
+        let height = image.height();
+        to_dynamic_image(width, height, image.into_raw())
     }
 
     /// 将图片转换为 Base64
     pub fn image_to_base64(image: &DynamicImage, quality: u8) -> Result<String, String> {
-        let mut buffer = Cursor::new(Vec::new());
+        Ok(BASE64.encode(Self::image_to_jpeg_bytes(image, quality)?))
+    }
 
-        // 压缩为 JPEG
+    /// 压缩为 JPEG 字节
+    pub fn image_to_jpeg_bytes(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+        let mut buffer = Cursor::new(Vec::new());
         let jpeg = image.to_rgb8();
         let quality = clamp_jpeg_quality(quality);
         jpeg.write_to(&mut buffer, ImageOutputFormat::Jpeg(quality))
             .map_err(|e| format!("图片编码失败: {}", e))?;
-
-        Ok(BASE64.encode(buffer.into_inner()))
+        Ok(buffer.into_inner())
     }
+}
 
-    /// 保存截图到文件
-    pub fn save_to_file(image: &DynamicImage, path: &str, quality: u8) -> Result<(), String> {
-        let ext = Path::new(path)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_ascii_lowercase();
-        if ext == "jpg" || ext == "jpeg" {
-            let quality = clamp_jpeg_quality(quality);
-            let mut file = File::create(path).map_err(|e| format!("保存截图失败: {}", e))?;
-            image
-                .write_to(&mut file, ImageOutputFormat::Jpeg(quality))
-                .map_err(|e| format!("保存截图失败: {}", e))
-        } else {
-            image
-                .save(path)
-                .map_err(|e| format!("保存截图失败: {}", e))
-        }
-    }
+fn to_dynamic_image(width: u32, height: u32, rgba: Vec<u8>) -> Result<DynamicImage, String> {
+    image::RgbaImage::from_raw(width, height, rgba)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| "图像转换失败".to_string())
 }
 
 fn clamp_jpeg_quality(quality: u8) -> u8 {
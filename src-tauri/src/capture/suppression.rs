@@ -0,0 +1,205 @@
+use crate::storage::StorageManager;
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+const SUPPRESSION_RULES_FILE: &str = "alert_suppressions.json";
+
+static SUPPRESSION_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// 每条提醒都要逐条匹配规则，按正则文本缓存编译结果；无效正则缓存为 None
+fn compiled_pattern(pattern: &str) -> Option<Regex> {
+    static CACHE: OnceLock<ParkingMutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    CACHE
+        .get_or_init(|| ParkingMutex::new(HashMap::new()))
+        .lock()
+        .entry(pattern.to_string())
+        .or_insert_with(|| RegexBuilder::new(pattern).case_insensitive(true).build().ok())
+        .clone()
+}
+
+/// 用户设置的提醒屏蔽规则：所有非空条件都满足时不再推送该提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuppressionRule {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub app: String,  // 应用名包含该文字（不区分大小写）
+    #[serde(default)]
+    pub scene: String,
+    #[serde(default)]
+    pub issue_type: String,
+    #[serde(default)]
+    pub message_pattern: String,  // 匹配提醒内容的正则
+    #[serde(default)]
+    pub created_at: String,
+}
+
+impl SuppressionRule {
+    pub fn matches(&self, app: &str, scene: &str, issue_type: &str, message: &str) -> bool {
+        let app_rule = self.app.trim().to_lowercase();
+        let pattern = self.message_pattern.trim();
+        if app_rule.is_empty() && self.scene.trim().is_empty() && self.issue_type.trim().is_empty() && pattern.is_empty() {
+            return false;
+        }
+        if !app_rule.is_empty() && !app.to_lowercase().contains(&app_rule) {
+            return false;
+        }
+        if !self.scene.trim().is_empty() && !self.scene.trim().eq_ignore_ascii_case(scene.trim()) {
+            return false;
+        }
+        if !self.issue_type.trim().is_empty() && !self.issue_type.trim().eq_ignore_ascii_case(issue_type.trim()) {
+            return false;
+        }
+        if !pattern.is_empty() {
+            let matched = compiled_pattern(pattern).map_or(false, |regex| regex.is_match(message));
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn suppression_lock() -> &'static ParkingMutex<()> {
+    static LOCK: OnceLock<ParkingMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| ParkingMutex::new(()))
+}
+
+pub fn load_suppression_rules() -> Vec<SuppressionRule> {
+    let storage = StorageManager::new();
+    let path = storage.get_data_dir().join(SUPPRESSION_RULES_FILE);
+    if !path.exists() {
+        return Vec::new();
+    }
+    storage
+        .read_data_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_suppression_rules(rules: &[SuppressionRule]) -> Result<(), String> {
+    let storage = StorageManager::new();
+    let content = serde_json::to_string_pretty(rules).map_err(|e| format!("序列化屏蔽规则失败: {}", e))?;
+    storage.write_data_file(&storage.get_data_dir().join(SUPPRESSION_RULES_FILE), content.as_bytes())
+}
+
+/// 添加屏蔽规则；已有条件完全相同的规则时直接返回该规则
+pub fn add_suppression_rule(mut rule: SuppressionRule) -> Result<SuppressionRule, String> {
+    if rule.app.trim().is_empty()
+        && rule.scene.trim().is_empty()
+        && rule.issue_type.trim().is_empty()
+        && rule.message_pattern.trim().is_empty()
+    {
+        return Err("屏蔽规则至少需要一个条件".to_string());
+    }
+    if !rule.message_pattern.trim().is_empty() {
+        RegexBuilder::new(rule.message_pattern.trim())
+            .build()
+            .map_err(|e| format!("无效的正则: {}", e))?;
+    }
+    let _guard = suppression_lock().lock();
+    let mut rules = load_suppression_rules();
+    let same = |other: &SuppressionRule| {
+        other.app.trim() == rule.app.trim()
+            && other.scene.trim() == rule.scene.trim()
+            && other.issue_type.trim() == rule.issue_type.trim()
+            && other.message_pattern.trim() == rule.message_pattern.trim()
+    };
+    if let Some(existing) = rules.iter().find(|other| same(other)) {
+        return Ok(existing.clone());
+    }
+    let now = Local::now();
+    rule.id = format!(
+        "suppress-{}-{}",
+        now.timestamp_millis(),
+        SUPPRESSION_SEQ.fetch_add(1, Ordering::Relaxed)
+    );
+    rule.created_at = now.format("%Y-%m-%dT%H:%M:%S").to_string();
+    rules.push(rule.clone());
+    save_suppression_rules(&rules)?;
+    Ok(rule)
+}
+
+/// 删除屏蔽规则，返回是否存在该规则
+pub fn delete_suppression_rule(id: &str) -> Result<bool, String> {
+    let _guard = suppression_lock().lock();
+    let mut rules = load_suppression_rules();
+    let before = rules.len();
+    rules.retain(|rule| rule.id != id);
+    if rules.len() == before {
+        return Ok(false);
+    }
+    save_suppression_rules(&rules)?;
+    Ok(true)
+}
+
+/// 提醒是否命中任一屏蔽规则
+pub fn is_alert_suppressed(app: &str, scene: &str, issue_type: &str, message: &str) -> bool {
+    load_suppression_rules()
+        .iter()
+        .any(|rule| rule.matches(app, scene, issue_type, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(app: &str, scene: &str, issue_type: &str, message_pattern: &str) -> SuppressionRule {
+        SuppressionRule {
+            id: String::new(),
+            app: app.to_string(),
+            scene: scene.to_string(),
+            issue_type: issue_type.to_string(),
+            message_pattern: message_pattern.to_string(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn rule_without_conditions_never_matches() {
+        assert!(!rule("", " ", "", "").matches("Code", "coding", "error", "build failed"));
+    }
+
+    #[test]
+    fn app_matches_substring_ignoring_case() {
+        let rule = rule("code", "", "", "");
+        assert!(rule.matches("Visual Studio Code", "", "", ""));
+        assert!(!rule.matches("Terminal", "", "", ""));
+    }
+
+    #[test]
+    fn scene_and_issue_type_match_whole_value_ignoring_case() {
+        let rule = rule("", "Coding", "ERROR", "");
+        assert!(rule.matches("", " coding ", "error", ""));
+        assert!(!rule.matches("", "coding-review", "error", ""));
+        assert!(!rule.matches("", "coding", "warning", ""));
+    }
+
+    #[test]
+    fn message_pattern_is_case_insensitive_regex() {
+        let rule = rule("", "", "", r"build (failed|broken)");
+        assert!(rule.matches("", "", "", "The BUILD FAILED again"));
+        assert!(!rule.matches("", "", "", "build passed"));
+        // 编译结果被缓存后再次匹配结果不变
+        assert!(rule.matches("", "", "", "build broken"));
+    }
+
+    #[test]
+    fn invalid_pattern_never_matches() {
+        assert!(!rule("", "", "", "(unclosed").matches("", "", "", "(unclosed"));
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let rule = rule("slack", "chat", "", "standup");
+        assert!(rule.matches("Slack", "chat", "info", "daily standup in 5 min"));
+        assert!(!rule.matches("Slack", "chat", "info", "lunch"));
+        assert!(!rule.matches("Teams", "chat", "info", "daily standup in 5 min"));
+    }
+}
@@ -0,0 +1,227 @@
+use super::ScreenCapture;
+use crate::storage::{CaptureRegion, CaptureTarget};
+use image::DynamicImage;
+
+/// 保存配置前检查截屏范围是否完整
+pub fn validate_capture_target(target: &CaptureTarget) -> Result<(), String> {
+    match target.mode.as_str() {
+        "screen" => Ok(()),
+        "region" => match target.region {
+            Some(region) if region.width > 0 && region.height > 0 => Ok(()),
+            _ => Err("区域截屏需要设置宽高大于 0 的区域".to_string()),
+        },
+        "window" => {
+            if target.window_handle.is_none()
+                && target.window_title.trim().is_empty()
+                && target.window_process.trim().is_empty()
+            {
+                Err("窗口截屏需要设置窗口句柄、标题关键词或进程名".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        other => Err(format!("未知的截屏范围: {}", other)),
+    }
+}
+
+/// 按截屏范围截图；目标窗口找不到或已最小化时返回 None，不退回整屏截图。
+/// 窗口模式截取的是窗口所在的屏幕区域，被其他窗口遮挡时会截到遮挡内容。
+/// 不检查隐私规则，外部请使用 capture_with_privacy
+pub(super) fn capture_target(target: &CaptureTarget) -> Result<Option<DynamicImage>, String> {
+    match target.mode.as_str() {
+        "region" => {
+            let region = target
+                .region
+                .ok_or_else(|| "未设置截屏区域".to_string())?;
+            capture_bounds(region).map(Some)
+        }
+        "window" => match find_window_bounds(target) {
+            Some(bounds) => capture_bounds(bounds).map(Some),
+            None => Ok(None),
+        },
+        _ => ScreenCapture::capture_primary().map(Some),
+    }
+}
+
+fn capture_bounds(region: CaptureRegion) -> Result<DynamicImage, String> {
+    ScreenCapture::capture_region(region.x, region.y, region.width, region.height)
+}
+
+/// 标题和进程名都设置时需同时命中
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"))))]
+fn window_matches(target: &CaptureTarget, window: &super::ActiveWindow) -> bool {
+    let title = target.window_title.trim().to_lowercase();
+    if !title.is_empty() && !window.title.to_lowercase().contains(&title) {
+        return false;
+    }
+    let process = target.window_process.trim().to_lowercase();
+    if !process.is_empty() {
+        let process = process.strip_suffix(".exe").unwrap_or(&process);
+        let app = window.app_name().unwrap_or_default().to_lowercase();
+        if app != process && !window.process_name.to_lowercase().contains(process) {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(target_os = "windows")]
+fn find_window_bounds(target: &CaptureTarget) -> Option<CaptureRegion> {
+    use super::window::window_info;
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, RECT};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetWindowRect, IsIconic, IsWindow, IsWindowVisible,
+    };
+
+    struct Search<'a> {
+        target: &'a CaptureTarget,
+        found: HWND,
+    }
+
+    unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> i32 {
+        let search = &mut *(lparam as *mut Search);
+        if IsWindowVisible(hwnd) == 0 || IsIconic(hwnd) != 0 {
+            return 1;
+        }
+        if window_matches(search.target, &window_info(hwnd)) {
+            search.found = hwnd;
+            return 0;
+        }
+        1
+    }
+
+    unsafe {
+        let hwnd = match target.window_handle {
+            Some(handle) => handle as usize as HWND,
+            None => {
+                let mut search = Search {
+                    target,
+                    found: std::ptr::null_mut(),
+                };
+                EnumWindows(Some(visit), &mut search as *mut Search as LPARAM);
+                search.found
+            }
+        };
+        if hwnd.is_null() || IsWindow(hwnd) == 0 || IsIconic(hwnd) != 0 {
+            return None;
+        }
+        let mut rect: RECT = std::mem::zeroed();
+        if GetWindowRect(hwnd, &mut rect) == 0 || rect.right <= rect.left || rect.bottom <= rect.top {
+            return None;
+        }
+        Some(CaptureRegion {
+            x: rect.left,
+            y: rect.top,
+            width: (rect.right - rect.left) as u32,
+            height: (rect.bottom - rect.top) as u32,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn find_window_bounds(target: &CaptureTarget) -> Option<CaptureRegion> {
+    // 系统没有可用的窗口句柄，只按标题和进程名匹配
+    fn quote(text: &str) -> String {
+        format!("\"{}\"", text.trim().replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    let process_match = if target.window_process.trim().is_empty() {
+        "true".to_string()
+    } else {
+        format!("(name of proc) contains {}", quote(&target.window_process))
+    };
+    let title_match = if target.window_title.trim().is_empty() {
+        "true".to_string()
+    } else {
+        format!("(name of win) contains {}", quote(&target.window_title))
+    };
+    if process_match == "true" && title_match == "true" {
+        return None;
+    }
+    let script = format!(
+        r#"tell application "System Events"
+    repeat with proc in (application processes whose visible is true)
+        if {} then
+            repeat with win in windows of proc
+                if {} then
+                    set {{px, py}} to position of win
+                    set {{sw, sh}} to size of win
+                    return (px as text) & "," & (py as text) & "," & (sw as text) & "," & (sh as text)
+                end if
+            end repeat
+        end if
+    end repeat
+end tell
+return """#,
+        process_match, title_match
+    );
+    let output = super::window::run_quiet("osascript", &["-e", &script])?;
+    let parts: Vec<i64> = output
+        .split(',')
+        .filter_map(|part| part.trim().parse().ok())
+        .collect();
+    match parts.as_slice() {
+        [x, y, width, height] if *width > 0 && *height > 0 => Some(CaptureRegion {
+            x: *x as i32,
+            y: *y as i32,
+            width: *width as u32,
+            height: *height as u32,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn find_window_bounds(target: &CaptureTarget) -> Option<CaptureRegion> {
+    use super::window::run_quiet;
+
+    // 依赖 xdotool（X11），与前台窗口检测一致
+    let id = match target.window_handle {
+        Some(handle) => handle.to_string(),
+        None => {
+            let title = target.window_title.trim();
+            let ids = if !title.is_empty() {
+                run_quiet("xdotool", &["search", "--onlyvisible", "--name", &regex::escape(title)])?
+            } else {
+                let process = regex::escape(target.window_process.trim());
+                run_quiet("xdotool", &["search", "--onlyvisible", "--classname", &process])?
+            };
+            ids.lines()
+                .map(str::trim)
+                .find(|id| {
+                    let title = run_quiet("xdotool", &["getwindowname", id]).unwrap_or_default();
+                    let process_name = run_quiet("xdotool", &["getwindowpid", id])
+                        .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid.trim())).ok())
+                        .unwrap_or_default();
+                    window_matches(
+                        target,
+                        &super::ActiveWindow {
+                            title,
+                            process_name: process_name.trim().to_string(),
+                        },
+                    )
+                })?
+                .to_string()
+        }
+    };
+    let geometry = run_quiet("xdotool", &["getwindowgeometry", "--shell", &id])?;
+    let value = |key: &str| -> Option<i64> {
+        geometry
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('=')?.trim().parse().ok())
+    };
+    let (width, height) = (value("WIDTH")?, value("HEIGHT")?);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    Some(CaptureRegion {
+        x: value("X")? as i32,
+        y: value("Y")? as i32,
+        width: width as u32,
+        height: height as u32,
+    })
+}
+
+#[cfg(not(any(target_os = "windows", unix)))]
+fn find_window_bounds(_target: &CaptureTarget) -> Option<CaptureRegion> {
+    None
+}
@@ -0,0 +1,121 @@
+use super::{
+    capture_with_privacy, extract_json_value, get_active_window, vision_unsupported, GuardedCapture, ScreenCapture,
+};
+use crate::logs;
+use crate::model::{with_usage_feature, ModelManager};
+use crate::storage::{storage_actor, Config, StoragePriority};
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+/// 待复查的提醒
+pub struct PendingVerification {
+    pub timestamp: String,
+    pub key: String,
+    pub message: String,
+    pub suggestion: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct AlertVerification {
+    pub timestamp: String,
+    pub key: String,
+    pub resolution: String, // resolved | unresolved
+    pub reason: String,
+}
+
+/// 给出建议若干分钟后再截一次屏，确认问题是否已经解决，并把结果写回提醒对应的记录
+pub fn schedule_alert_verification(
+    config: Config,
+    app_handle: AppHandle,
+    is_running: Arc<ParkingMutex<bool>>,
+    pending: PendingVerification,
+) {
+    let minutes = config.capture.alert_verify_minutes;
+    if minutes == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(minutes * 60)).await;
+        // 截屏已停止时不再复查，避免在用户不知情时截屏
+        if !*is_running.lock() {
+            return;
+        }
+        match with_usage_feature("capture", verify_alert(&config, &pending)).await {
+            Ok(verification) => {
+                let resolution = verification.resolution.clone();
+                let note = verification.reason.clone();
+                let timestamp = verification.timestamp.clone();
+                let saved = storage_actor()
+                    .run(StoragePriority::Background, move |storage| {
+                        storage.set_record_resolution(&timestamp, &resolution, &note)
+                    })
+                    .await;
+                if let Err(err) = saved {
+                    logs::warn("capture.verify", format!("保存复查结果失败: {}", err));
+                }
+                if let Err(err) = app_handle.emit("alert-verified", verification) {
+                    logs::warn("capture.verify", format!("发送复查结果失败: {}", err));
+                }
+            }
+            Err(err) => logs::warn("capture.verify", format!("提醒复查失败: {}", err)),
+        }
+    });
+}
+
+async fn verify_alert(
+    config: &Config,
+    pending: &PendingVerification,
+) -> Result<AlertVerification, String> {
+    let capture_model = ModelManager::resolve_model_config(
+        &config.model,
+        Some(config.model.capture_profile.as_str()),
+    );
+    if vision_unsupported(&capture_model) {
+        return Err("截屏模型不支持图片输入，跳过复查".to_string());
+    }
+    // 与定时截屏相同：命中隐私规则时不截屏，按配置的截屏范围截取
+    let image = match capture_with_privacy(&config.capture, get_active_window().as_ref(), &Local::now())? {
+        GuardedCapture::Captured(image) => image,
+        GuardedCapture::Blocked(reason) => return Err(format!("隐私规则命中，跳过复查: {}", reason)),
+        GuardedCapture::TargetMissing => return Err("截屏目标窗口不可见，跳过复查".to_string()),
+    };
+    let image_base64 = ScreenCapture::image_to_base64(&image, config.capture.compress_quality)?;
+    let prompt = format!(
+        r#"几分钟前检测到用户遇到以下问题，并给出了建议：
+- 问题: {}
+- 建议: {}
+
+请根据当前截图判断该问题是否仍然存在。严格只输出一个 JSON 对象，不要输出其他内容：
+{{"resolved": true 或 false, "reason": "一句话说明判断依据"}}
+如果当前画面已切换到无关内容、看不到该问题，视为已解决。"#,
+        pending.message, pending.suggestion
+    );
+
+    let model_manager = ModelManager::new();
+    let analysis = model_manager
+        .analyze_image(&capture_model, &image_base64, &prompt)
+        .await?;
+    let json = match extract_json_value(&analysis) {
+        Some(json) => json,
+        None => model_manager
+            .repair_json_response(&capture_model, &analysis, "字段: resolved, reason")
+            .await
+            .ok_or_else(|| "复查结果无法解析".to_string())?,
+    };
+    let resolved = json.get("resolved").and_then(|v| v.as_bool()).unwrap_or(false);
+    let reason = json
+        .get("reason")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim()
+        .to_string();
+
+    Ok(AlertVerification {
+        timestamp: pending.timestamp.clone(),
+        key: pending.key.clone(),
+        resolution: if resolved { "resolved" } else { "unresolved" }.to_string(),
+        reason,
+    })
+}
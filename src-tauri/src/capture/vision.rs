@@ -0,0 +1,38 @@
+use crate::logs;
+use crate::model::build_model_error_alert;
+use crate::storage::ModelConfig;
+use parking_lot::Mutex as ParkingMutex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+// 已确认不支持图片输入的截屏模型（provider|endpoint|model），换用其他模型后自动恢复截图分析
+fn text_only_models() -> &'static ParkingMutex<HashSet<String>> {
+    static MODELS: OnceLock<ParkingMutex<HashSet<String>>> = OnceLock::new();
+    MODELS.get_or_init(|| ParkingMutex::new(HashSet::new()))
+}
+
+fn model_key(config: &ModelConfig) -> String {
+    let (endpoint, model) = match config.provider.as_str() {
+        "gemini" => (&config.gemini.endpoint, &config.gemini.model),
+        "ollama" => (&config.ollama.endpoint, &config.ollama.model),
+        _ => (&config.api.endpoint, &config.api.model),
+    };
+    format!("{}|{}|{}", config.provider, endpoint.trim(), model.trim())
+}
+
+/// 截屏模型是否已确认不支持图片输入，需改用 OCR 文本分析
+pub fn vision_unsupported(config: &ModelConfig) -> bool {
+    text_only_models().lock().contains(&model_key(config))
+}
+
+/// 记下模型不支持图片输入；每个模型只提示一次，提示中附带处理建议
+pub fn mark_vision_unsupported(config: &ModelConfig, app_handle: Option<&AppHandle>, detail: &str) {
+    if !text_only_models().lock().insert(model_key(config)) {
+        return;
+    }
+    logs::warn("capture", format!("截屏模型不支持图片输入，改用 OCR 文字分析: {}", detail));
+    if let Some(app_handle) = app_handle {
+        let _ = app_handle.emit("model-error", build_model_error_alert(detail, "capture"));
+    }
+}
@@ -0,0 +1,133 @@
+/// 截屏时的前台窗口信息
+#[derive(Debug, Clone, Default)]
+pub struct ActiveWindow {
+    pub title: String,
+    pub process_name: String,
+}
+
+impl ActiveWindow {
+    /// 用于填充 SummaryRecord.app 的应用名（去掉 .exe 等后缀）
+    pub fn app_name(&self) -> Option<String> {
+        let name = self.process_name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let name = name
+            .strip_suffix(".exe")
+            .or_else(|| name.strip_suffix(".EXE"))
+            .unwrap_or(name);
+        Some(name.to_string())
+    }
+}
+
+/// 读取当前前台窗口的标题和进程名，失败时返回 None
+pub fn get_active_window() -> Option<ActiveWindow> {
+    let window = platform_active_window()?;
+    if window.title.trim().is_empty() && window.process_name.trim().is_empty() {
+        return None;
+    }
+    Some(ActiveWindow {
+        title: window.title.trim().to_string(),
+        process_name: window.process_name.trim().to_string(),
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn platform_active_window() -> Option<ActiveWindow> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+        Some(window_info(hwnd))
+    }
+}
+
+/// 读取指定窗口的标题和进程名
+#[cfg(target_os = "windows")]
+pub(super) unsafe fn window_info(hwnd: windows_sys::Win32::Foundation::HWND) -> ActiveWindow {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId,
+    };
+
+    let len = GetWindowTextLengthW(hwnd);
+    let title = if len > 0 {
+        let mut buf = vec![0u16; len as usize + 1];
+        let copied = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+        String::from_utf16_lossy(&buf[..copied.max(0) as usize])
+    } else {
+        String::new()
+    };
+
+    let mut pid: u32 = 0;
+    GetWindowThreadProcessId(hwnd, &mut pid);
+    let mut process_name = String::new();
+    if pid != 0 {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if !handle.is_null() {
+            let mut buf = vec![0u16; 1024];
+            let mut size = buf.len() as u32;
+            if QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut size) != 0 {
+                let path = String::from_utf16_lossy(&buf[..size as usize]);
+                process_name = std::path::Path::new(&path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or(path);
+            }
+            CloseHandle(handle);
+        }
+    }
+
+    ActiveWindow { title, process_name }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_active_window() -> Option<ActiveWindow> {
+    let script = r#"tell application "System Events"
+    set frontProc to first application process whose frontmost is true
+    set procName to name of frontProc
+    set winTitle to ""
+    try
+        set winTitle to name of front window of frontProc
+    end try
+end tell
+return procName & linefeed & winTitle"#;
+    let output = run_quiet("osascript", &["-e", script])?;
+    let mut lines = output.lines();
+    let process_name = lines.next().unwrap_or("").to_string();
+    let title = lines.next().unwrap_or("").to_string();
+    Some(ActiveWindow { title, process_name })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_active_window() -> Option<ActiveWindow> {
+    // 依赖 xdotool（X11）；Wayland 下通常不可用，直接返回 None
+    let title = run_quiet("xdotool", &["getactivewindow", "getwindowname"]).unwrap_or_default();
+    let process_name = run_quiet("xdotool", &["getactivewindow", "getwindowpid"])
+        .and_then(|pid| {
+            std::fs::read_to_string(format!("/proc/{}/comm", pid.trim())).ok()
+        })
+        .unwrap_or_default();
+    Some(ActiveWindow { title, process_name })
+}
+
+#[cfg(not(any(target_os = "windows", unix)))]
+fn platform_active_window() -> Option<ActiveWindow> {
+    None
+}
+
+#[cfg(unix)]
+pub(super) fn run_quiet(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,312 @@
+use crate::capture::{get_active_window, privacy_block_reason};
+use crate::logs;
+use crate::storage::{storage_actor, ClipboardConfig, StorageManager, StoragePriority};
+use chrono::{Duration, Local, NaiveDate};
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio_util::sync::CancellationToken;
+
+const CLIPBOARD_DIR: &str = "clipboard";
+const MIN_POLL_MS: u64 = 500;
+const DEFAULT_SEARCH_DAYS: i64 = 7;
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// 一条剪贴板记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardEntry {
+    pub timestamp: String,
+    pub text: String,
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub app: Option<String>,  // 复制时的前台应用
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DailyClipboard {
+    date: String,
+    entries: Vec<ClipboardEntry>,
+}
+
+struct ClipboardRecorder {
+    config: ClipboardConfig,
+    cancel: CancellationToken,
+}
+
+fn recorder() -> &'static ParkingMutex<Option<ClipboardRecorder>> {
+    static RECORDER: OnceLock<ParkingMutex<Option<ClipboardRecorder>>> = OnceLock::new();
+    RECORDER.get_or_init(|| ParkingMutex::new(None))
+}
+
+// 最近一次看到或写入的内容，避免重复记录和记录助手自己写入的内容
+fn last_seen() -> &'static ParkingMutex<Option<String>> {
+    static LAST: OnceLock<ParkingMutex<Option<String>>> = OnceLock::new();
+    LAST.get_or_init(|| ParkingMutex::new(None))
+}
+
+pub fn read_text() -> Result<String, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| format!("无法访问剪贴板: {}", e))?;
+    match clipboard.get_text() {
+        Ok(text) => Ok(text),
+        Err(arboard::Error::ContentNotAvailable) => Ok(String::new()),
+        Err(e) => Err(format!("读取剪贴板失败: {}", e)),
+    }
+}
+
+pub fn write_text(text: &str) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| format!("无法访问剪贴板: {}", e))?;
+    clipboard
+        .set_text(text.to_string())
+        .map_err(|e| format!("写入剪贴板失败: {}", e))?;
+    *last_seen().lock() = Some(text.to_string());
+    Ok(())
+}
+
+/// 启动和保存配置时调用；配置未变化时不做任何事
+pub fn apply_clipboard_config(config: &ClipboardConfig) {
+    let mut slot = recorder().lock();
+    if slot.as_ref().map_or(false, |running| running.config == *config) {
+        return;
+    }
+    if let Some(running) = slot.take() {
+        running.cancel.cancel();
+    }
+    if !config.history_enabled {
+        return;
+    }
+    let cancel = CancellationToken::new();
+    tauri::async_runtime::spawn(record_loop(config.clone(), cancel.clone()));
+    *slot = Some(ClipboardRecorder {
+        config: config.clone(),
+        cancel,
+    });
+}
+
+async fn record_loop(config: ClipboardConfig, cancel: CancellationToken) {
+    let interval = std::time::Duration::from_millis(config.poll_interval_ms.max(MIN_POLL_MS));
+    // 启动时剪贴板里已有的内容不算新复制
+    if let Ok(Ok(text)) = tokio::task::spawn_blocking(read_text).await {
+        *last_seen().lock() = Some(text);
+    }
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        let text = match tokio::task::spawn_blocking(read_text).await {
+            Ok(Ok(text)) => text,
+            Ok(Err(err)) => {
+                logs::debug("clipboard", err);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        {
+            let mut last = last_seen().lock();
+            if last.as_deref() == Some(text.as_str()) {
+                continue;
+            }
+            *last = Some(text.clone());
+        }
+        if let Err(err) = record_entry(&config, text).await {
+            logs::warn("clipboard", format!("保存剪贴板记录失败: {}", err));
+        }
+    }
+}
+
+async fn record_entry(config: &ClipboardConfig, text: String) -> Result<(), String> {
+    let window = get_active_window();
+    let max_chars = config.max_entry_chars;
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            let app_config = storage.load_config()?;
+            let now = Local::now();
+            if let Some(reason) = privacy_block_reason(&app_config.capture.privacy, window.as_ref(), &now) {
+                logs::debug("clipboard", format!("命中隐私规则，不记录: {}", reason));
+                return Ok(());
+            }
+            let truncated = max_chars > 0 && text.chars().count() > max_chars;
+            let text = if truncated { text.chars().take(max_chars).collect() } else { text };
+            storage.append_clipboard_entry(ClipboardEntry {
+                timestamp: now.to_rfc3339(),
+                text,
+                truncated,
+                app: window.as_ref().and_then(|window| window.app_name()),
+            })
+        })
+        .await
+}
+
+impl StorageManager {
+    fn clipboard_path(&self, date: &str) -> PathBuf {
+        self.get_data_dir().join(CLIPBOARD_DIR).join(format!("{}.json", date))
+    }
+
+    fn load_clipboard_day(&self, date: &str) -> DailyClipboard {
+        let path = self.clipboard_path(date);
+        if !path.exists() {
+            return DailyClipboard {
+                date: date.to_string(),
+                entries: Vec::new(),
+            };
+        }
+        self.read_data_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| DailyClipboard {
+                date: date.to_string(),
+                entries: Vec::new(),
+            })
+    }
+
+    pub fn append_clipboard_entry(&self, entry: ClipboardEntry) -> Result<(), String> {
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        let dir = self.get_data_dir().join(CLIPBOARD_DIR);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建剪贴板目录失败: {}", e))?;
+        let mut daily = self.load_clipboard_day(&date);
+        daily.entries.push(entry);
+        let content = serde_json::to_string_pretty(&daily).map_err(|e| format!("序列化剪贴板记录失败: {}", e))?;
+        self.write_data_file(&self.clipboard_path(&date), content.as_bytes())
+    }
+
+    /// 查询剪贴板历史，最新的在前；指定 date 时只查当天，否则查最近 days 天
+    pub fn search_clipboard_history(
+        &self,
+        query: Option<&str>,
+        date: Option<&str>,
+        days: Option<i64>,
+        limit: Option<usize>,
+    ) -> Result<Vec<ClipboardEntry>, String> {
+        let dates: Vec<String> = match date.map(str::trim).filter(|d| !d.is_empty()) {
+            Some(date) => {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("日期格式应为 YYYY-MM-DD: {}", date))?;
+                vec![date.to_string()]
+            }
+            None => {
+                let today = Local::now().date_naive();
+                (0..days.unwrap_or(DEFAULT_SEARCH_DAYS).max(1))
+                    .map(|offset| (today - Duration::days(offset)).format("%Y-%m-%d").to_string())
+                    .collect()
+            }
+        };
+        let keywords: Vec<String> = query
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect();
+        let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).max(1);
+        let mut results = Vec::new();
+        for date in dates {
+            let mut entries = self.load_clipboard_day(&date).entries;
+            entries.reverse();
+            for entry in entries {
+                let text = entry.text.to_lowercase();
+                if keywords.iter().all(|word| text.contains(word)) {
+                    results.push(entry);
+                    if results.len() >= limit {
+                        return Ok(results);
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    pub fn clear_clipboard_history(&self) -> Result<usize, String> {
+        let dir = self.get_data_dir().join(CLIPBOARD_DIR);
+        if !dir.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in fs::read_dir(&dir).map_err(|e| format!("读取剪贴板目录失败: {}", e))?.flatten() {
+            if fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 清理超过保留天数的剪贴板记录
+    pub fn prune_clipboard_history(&self, retention_days: u32) -> usize {
+        if retention_days == 0 {
+            return 0;
+        }
+        let cutoff = (Local::now() - Duration::days(retention_days as i64))
+            .format("%Y-%m-%d")
+            .to_string();
+        let Ok(entries) = fs::read_dir(self.get_data_dir().join(CLIPBOARD_DIR)) else {
+            return 0;
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map_or(false, |date| date < cutoff.as_str())
+            })
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+}
+
+fn format_entries(entries: &[ClipboardEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let app = entry.app.as_deref().map(|app| format!(" [{}]", app)).unwrap_or_default();
+            let more = if entry.truncated { "\n...(已截断)" } else { "" };
+            format!("- {}{}\n{}{}", entry.timestamp, app, entry.text, more)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// clipboard_read：默认读取当前剪贴板；history=true 时查询复制历史（需开启记录）
+pub async fn clipboard_read_tool(storage: &StorageManager, args: &serde_json::Value) -> Result<String, String> {
+    let history = args.get("history").and_then(|v| v.as_bool()).unwrap_or(false);
+    if !history {
+        let text = tokio::task::spawn_blocking(read_text)
+            .await
+            .map_err(|e| format!("读取剪贴板失败: {}", e))??;
+        return Ok(if text.is_empty() {
+            "剪贴板中没有文本内容。".to_string()
+        } else {
+            text
+        });
+    }
+    let entries = storage.search_clipboard_history(
+        args.get("query").and_then(|v| v.as_str()),
+        args.get("date").and_then(|v| v.as_str()),
+        args.get("days").and_then(|v| v.as_i64()),
+        args.get("limit").and_then(|v| v.as_u64()).map(|v| v as usize),
+    )?;
+    if entries.is_empty() {
+        let enabled = storage.load_config().map(|config| config.clipboard.history_enabled).unwrap_or(false);
+        return Ok(if enabled {
+            "没有找到匹配的剪贴板记录。".to_string()
+        } else {
+            "剪贴板历史记录未开启，可在设置中开启后再查询。".to_string()
+        });
+    }
+    Ok(format_entries(&entries))
+}
+
+pub async fn clipboard_write_tool(args: &serde_json::Value) -> Result<String, String> {
+    let text = args
+        .get("text")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "缺少 text 参数".to_string())?
+        .to_string();
+    let chars = text.chars().count();
+    tokio::task::spawn_blocking(move || write_text(&text))
+        .await
+        .map_err(|e| format!("写入剪贴板失败: {}", e))??;
+    Ok(format!("已复制到剪贴板（{} 个字符）", chars))
+}
@@ -0,0 +1,545 @@
+use super::browser::BROWSER_PERMISSION;
+use super::diff::FileDiff;
+use super::git::{is_write_action, repo_dir, write_permission_pattern};
+use super::{
+    command_allowed, extract_command_token, path_is_allowed, resolve_path, ProgressEmitter,
+    ToolAccess,
+};
+use crate::error::AppError;
+use crate::logs;
+use crate::storage::StorageManager;
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+const APPROVALS_FILE: &str = "tool_approvals.json";
+const APPROVAL_TIMEOUT_SECS: u64 = 300;
+
+/// 持久化的授权规则：按技能（scope）+ 命令/目录匹配
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolApprovalRule {
+    pub scope: String,    // 技能名，主对话为 assistant
+    pub kind: String,     // command | path | edit
+    pub pattern: String,  // 命令名（如 git）、目录或被修改的文件
+    pub allow: bool,
+    pub created_at: String,
+}
+
+/// 发给前端的授权请求（tool-approval-request 事件）
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolApprovalRequest {
+    pub approval_id: String,
+    pub request_id: String,
+    pub scope: String,
+    pub tool: String,
+    pub kind: String,
+    pub pattern: String,
+    pub detail: String,  // 完整命令或文件路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,  // 修改文件时的 unified diff
+}
+
+/// 授权请求结束（tool-approval-resolved 事件），各窗口据此关闭对话框
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolApprovalResolved {
+    pub approval_id: String,
+    pub status: String,  // allow_once | allow_session | deny | timeout | cancelled
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApprovalDecision {
+    AllowOnce,
+    AllowSession,
+    Deny,
+}
+
+impl ApprovalDecision {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "allow_once" => Ok(ApprovalDecision::AllowOnce),
+            "allow_session" => Ok(ApprovalDecision::AllowSession),
+            "deny" => Ok(ApprovalDecision::Deny),
+            other => Err(format!("未知的授权选项: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalDecision::AllowOnce => "allow_once",
+            ApprovalDecision::AllowSession => "allow_session",
+            ApprovalDecision::Deny => "deny",
+        }
+    }
+}
+
+/// 白名单之外、需要用户确认的一项操作
+struct ApprovalTarget {
+    kind: &'static str,
+    pattern: String,
+    detail: String,
+}
+
+struct PendingApproval {
+    request: ToolApprovalRequest,
+    sender: oneshot::Sender<ApprovalDecision>,
+}
+
+fn pending_approvals() -> &'static ParkingMutex<HashMap<String, PendingApproval>> {
+    static PENDING: OnceLock<ParkingMutex<HashMap<String, PendingApproval>>> = OnceLock::new();
+    PENDING.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+// 本次运行内「允许（本次会话）」的授权：(scope, kind, pattern)
+fn session_grants() -> &'static ParkingMutex<HashSet<(String, String, String)>> {
+    static GRANTS: OnceLock<ParkingMutex<HashSet<(String, String, String)>>> = OnceLock::new();
+    GRANTS.get_or_init(|| ParkingMutex::new(HashSet::new()))
+}
+
+// 已挂载授权对话框的窗口
+fn approval_listeners() -> &'static ParkingMutex<HashSet<String>> {
+    static LISTENERS: OnceLock<ParkingMutex<HashSet<String>>> = OnceLock::new();
+    LISTENERS.get_or_init(|| ParkingMutex::new(HashSet::new()))
+}
+
+/// 窗口挂载/卸载授权对话框时登记，只有登记过的窗口才会收到授权请求
+pub fn set_approval_listener(window_label: &str, active: bool) {
+    let mut listeners = approval_listeners().lock();
+    if active {
+        listeners.insert(window_label.to_string());
+    } else {
+        listeners.remove(window_label);
+    }
+}
+
+/// 发起请求的窗口（或广播时任一窗口）能显示授权对话框
+fn can_ask(progress: &ProgressEmitter) -> bool {
+    let listeners = approval_listeners().lock();
+    match &progress.window {
+        Some(label) => listeners.contains(label),
+        None => !listeners.is_empty(),
+    }
+}
+
+fn next_approval_id() -> String {
+    static SEQ: AtomicU64 = AtomicU64::new(1);
+    format!(
+        "approval-{}-{}",
+        Local::now().timestamp_millis(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl StorageManager {
+    pub fn load_tool_approval_rules(&self) -> Result<Vec<ToolApprovalRule>, String> {
+        let path = self.get_data_dir().join(APPROVALS_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_tool_approval_rules(&self, rules: &[ToolApprovalRule]) -> Result<(), String> {
+        let path = self.get_data_dir().join(APPROVALS_FILE);
+        if rules.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("删除授权规则失败: {}", e))?;
+            }
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(rules)
+            .map_err(|e| format!("序列化授权规则失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    /// 同一 scope/kind/pattern 只保留最新决定
+    pub fn add_tool_approval_rule(&self, rule: ToolApprovalRule) -> Result<(), String> {
+        let mut rules = self.load_tool_approval_rules()?;
+        rules.retain(|item| {
+            !(item.scope == rule.scope && item.kind == rule.kind && item.pattern == rule.pattern)
+        });
+        rules.push(rule);
+        self.save_tool_approval_rules(&rules)
+    }
+
+    pub fn remove_tool_approval_rule(&self, scope: &str, kind: &str, pattern: &str) -> Result<bool, String> {
+        let mut rules = self.load_tool_approval_rules()?;
+        let before = rules.len();
+        rules.retain(|item| !(item.scope == scope && item.kind == kind && item.pattern == pattern));
+        if rules.len() == before {
+            return Ok(false);
+        }
+        self.save_tool_approval_rules(&rules)?;
+        Ok(true)
+    }
+}
+
+/// 白名单模式下 Bash/Write/Edit 超出允许范围的部分
+fn approval_targets(access: &ToolAccess, tool_name: &str, args: &serde_json::Value) -> Vec<ApprovalTarget> {
+    let mut targets = Vec::new();
+    if access.mode != "whitelist" {
+        return targets;
+    }
+    let text_arg = |key: &str| {
+        args.get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    };
+    match tool_name {
+        "Bash" | "run_command" => {
+            let Some(command) = text_arg("command") else {
+                return targets;
+            };
+            if !command_allowed(access, command) {
+                let token = extract_command_token(command);
+                let pattern = Path::new(&token)
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or(&token)
+                    .to_lowercase();
+                targets.push(ApprovalTarget {
+                    kind: "command",
+                    pattern,
+                    detail: command.to_string(),
+                });
+            }
+            let cwd = text_arg("cwd")
+                .map(|dir| resolve_path(access, dir))
+                .unwrap_or_else(|| access.base_dir.clone());
+            if !path_is_allowed(access, &cwd) {
+                targets.push(ApprovalTarget {
+                    kind: "path",
+                    pattern: cwd.to_string_lossy().to_string(),
+                    detail: cwd.to_string_lossy().to_string(),
+                });
+            }
+        }
+        "git" => {
+            let action = text_arg("action").unwrap_or("").to_lowercase();
+            if is_write_action(&action, args) {
+                let pattern = write_permission_pattern(&action);
+                if !command_allowed(access, "git") && !command_allowed(access, &pattern) {
+                    targets.push(ApprovalTarget {
+                        kind: "command",
+                        detail: format!("git {}", action),
+                        pattern,
+                    });
+                }
+            }
+            let dir = repo_dir(access, args);
+            if !path_is_allowed(access, &dir) {
+                targets.push(ApprovalTarget {
+                    kind: "path",
+                    pattern: dir.to_string_lossy().to_string(),
+                    detail: dir.to_string_lossy().to_string(),
+                });
+            }
+        }
+        "browser" => {
+            if !command_allowed(access, BROWSER_PERMISSION) {
+                targets.push(ApprovalTarget {
+                    kind: "command",
+                    pattern: BROWSER_PERMISSION.to_string(),
+                    detail: format!("browser {}", text_arg("action").unwrap_or("")),
+                });
+            }
+        }
+        "Write" | "Edit" | "Update" => {
+            let Some(path) = text_arg("path") else {
+                return targets;
+            };
+            let resolved = resolve_path(access, path);
+            if !path_is_allowed(access, &resolved) {
+                let dir = resolved.parent().unwrap_or(&resolved).to_path_buf();
+                targets.push(ApprovalTarget {
+                    kind: "path",
+                    pattern: dir.to_string_lossy().to_string(),
+                    detail: resolved.to_string_lossy().to_string(),
+                });
+            }
+        }
+        _ => {}
+    }
+    targets
+}
+
+/// 白名单外的操作请求用户授权；全部允许时返回放宽后的 ToolAccess，无需授权时返回 None。
+/// 没有前端可询问（progress 为空或没有窗口挂载授权对话框）时不弹窗，由工具按原有白名单拒绝
+pub(super) async fn authorize_tool_call(
+    access: &ToolAccess,
+    storage: &StorageManager,
+    scope: &str,
+    tool_name: &str,
+    args: &serde_json::Value,
+    cancel_token: Option<&CancellationToken>,
+    progress: Option<&ProgressEmitter>,
+) -> Result<Option<ToolAccess>, AppError> {
+    let targets = approval_targets(access, tool_name, args);
+    let Some(progress) = progress.filter(|progress| !targets.is_empty() && can_ask(progress)) else {
+        return Ok(None);
+    };
+    let rules = storage.load_tool_approval_rules().unwrap_or_default();
+    let mut granted = access.clone();
+    for target in targets {
+        let rule = rules
+            .iter()
+            .find(|rule| rule.scope == scope && rule.kind == target.kind && rule.pattern == target.pattern);
+        let allowed = match rule {
+            Some(rule) => rule.allow,
+            None if session_grants().lock().contains(&(
+                scope.to_string(),
+                target.kind.to_string(),
+                target.pattern.clone(),
+            )) => true,
+            None => {
+                let request = ToolApprovalRequest {
+                    approval_id: next_approval_id(),
+                    request_id: progress.request_id.clone(),
+                    scope: scope.to_string(),
+                    tool: tool_name.to_string(),
+                    kind: target.kind.to_string(),
+                    pattern: target.pattern.clone(),
+                    detail: target.detail.clone(),
+                    diff: None,
+                };
+                wait_for_approval(request, cancel_token, progress).await? != ApprovalDecision::Deny
+            }
+        };
+        if !allowed {
+            return Err(AppError::ToolDenied {
+                tool: tool_name.to_string(),
+                message: format!("用户拒绝了该操作: {}", target.detail),
+            });
+        }
+        match target.kind {
+            "command" => granted.allowed_commands.push(target.pattern),
+            _ => granted.allowed_dirs.push(Path::new(&target.pattern).to_path_buf()),
+        }
+    }
+    Ok(Some(granted))
+}
+
+/// 大段修改文件前请用户确认，返回是否允许
+pub(super) async fn confirm_file_change(
+    scope: &str,
+    tool_name: &str,
+    diff: &FileDiff,
+    cancel_token: Option<&CancellationToken>,
+    progress: &ProgressEmitter,
+) -> Result<bool, AppError> {
+    let key = (scope.to_string(), "edit".to_string(), diff.path.clone());
+    if session_grants().lock().contains(&key) {
+        return Ok(true);
+    }
+    let request = ToolApprovalRequest {
+        approval_id: next_approval_id(),
+        request_id: progress.request_id.clone(),
+        scope: key.0,
+        tool: tool_name.to_string(),
+        kind: key.1,
+        pattern: key.2,
+        detail: format!("{} (+{} -{})", diff.path, diff.added, diff.removed),
+        diff: Some(diff.diff.clone()),
+    };
+    Ok(wait_for_approval(request, cancel_token, progress).await? != ApprovalDecision::Deny)
+}
+
+/// 助手提议修改设置时请用户确认；每次都询问，不使用会话授权和已记住的规则
+pub(super) async fn confirm_config_change(
+    scope: &str,
+    changes: &[String],
+    reason: &str,
+    cancel_token: Option<&CancellationToken>,
+    progress: &ProgressEmitter,
+) -> Result<bool, AppError> {
+    let detail = if reason.is_empty() {
+        changes.join("；")
+    } else {
+        format!("{}（{}）", changes.join("；"), reason)
+    };
+    let request = ToolApprovalRequest {
+        approval_id: next_approval_id(),
+        request_id: progress.request_id.clone(),
+        scope: scope.to_string(),
+        tool: "manage_config".to_string(),
+        kind: "config".to_string(),
+        pattern: "config".to_string(),
+        detail,
+        diff: Some(changes.join("\n")),
+    };
+    Ok(wait_for_approval(request, cancel_token, progress).await? != ApprovalDecision::Deny)
+}
+
+async fn wait_for_approval(
+    request: ToolApprovalRequest,
+    cancel_token: Option<&CancellationToken>,
+    progress: &ProgressEmitter,
+) -> Result<ApprovalDecision, AppError> {
+    // 没有窗口能答复时直接拒绝，不空等超时
+    if !can_ask(progress) {
+        logs::warn("tool.approval", format!("没有可显示授权对话框的窗口，已拒绝: {}", request.detail));
+        return Ok(ApprovalDecision::Deny);
+    }
+    let approval_id = request.approval_id.clone();
+    let (tx, rx) = oneshot::channel();
+    pending_approvals().lock().insert(
+        approval_id.clone(),
+        PendingApproval {
+            request: request.clone(),
+            sender: tx,
+        },
+    );
+    progress.emit_info("等待用户授权".to_string(), Some(request.detail.clone()));
+    if let Err(err) = progress.emit_event("tool-approval-request", &request) {
+        pending_approvals().lock().remove(&approval_id);
+        logs::warn("tool.approval", format!("发送授权请求失败: {}", err));
+        return Ok(ApprovalDecision::Deny);
+    }
+
+    let timeout = tokio::time::sleep(Duration::from_secs(APPROVAL_TIMEOUT_SECS));
+    let decision = match cancel_token {
+        Some(token) => tokio::select! {
+            _ = token.cancelled() => Err(AppError::Cancelled),
+            _ = timeout => Ok(ApprovalDecision::Deny),
+            decision = rx => Ok(decision.unwrap_or(ApprovalDecision::Deny)),
+        },
+        None => tokio::select! {
+            _ = timeout => Ok(ApprovalDecision::Deny),
+            decision = rx => Ok(decision.unwrap_or(ApprovalDecision::Deny)),
+        },
+    };
+    // 条目仍在说明用户没有答复（超时或请求被取消），通知窗口关闭对话框
+    if pending_approvals().lock().remove(&approval_id).is_some() {
+        let status = if decision.is_err() { "cancelled" } else { "timeout" };
+        logs::info("tool.approval", format!("授权请求已结束（{}）: {}", status, request.detail));
+        let _ = progress.app_handle.emit(
+            "tool-approval-resolved",
+            ToolApprovalResolved {
+                approval_id,
+                status: status.to_string(),
+            },
+        );
+    }
+    decision
+}
+
+/// 处理用户的授权答复；remember 时把允许/拒绝持久化为该技能的规则
+pub fn answer_approval(
+    storage: &StorageManager,
+    approval_id: &str,
+    decision: ApprovalDecision,
+    remember: bool,
+) -> Result<(), String> {
+    let pending = pending_approvals()
+        .lock()
+        .remove(approval_id)
+        .ok_or_else(|| "授权请求不存在或已过期".to_string())?;
+    let request = &pending.request;
+    if decision == ApprovalDecision::AllowSession {
+        session_grants().lock().insert((
+            request.scope.clone(),
+            request.kind.clone(),
+            request.pattern.clone(),
+        ));
+    }
+    // 设置修改每次都需确认，不记住答复
+    if remember && decision != ApprovalDecision::AllowOnce && request.kind != "config" {
+        storage.add_tool_approval_rule(ToolApprovalRule {
+            scope: request.scope.clone(),
+            kind: request.kind.clone(),
+            pattern: request.pattern.clone(),
+            allow: decision == ApprovalDecision::AllowSession,
+            created_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        })?;
+    }
+    let _ = pending.sender.send(decision);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn workspace() -> PathBuf {
+        std::env::temp_dir().join("opencowork-approval-workspace")
+    }
+
+    fn access(mode: &str) -> ToolAccess {
+        ToolAccess {
+            mode: mode.to_string(),
+            allowed_commands: vec!["ls".to_string(), "npm*".to_string()],
+            allowed_dirs: vec![workspace()],
+            base_dir: workspace(),
+            tasks_dir: workspace().join("tasks"),
+        }
+    }
+
+    fn summary(targets: &[ApprovalTarget]) -> Vec<(&'static str, String)> {
+        targets.iter().map(|target| (target.kind, target.pattern.clone())).collect()
+    }
+
+    #[test]
+    fn only_whitelist_mode_asks() {
+        let args = json!({ "command": "curl https://example.com" });
+        assert!(approval_targets(&access("allow_all"), "Bash", &args).is_empty());
+        assert_eq!(approval_targets(&access("whitelist"), "Bash", &args).len(), 1);
+    }
+
+    #[test]
+    fn allowed_command_in_allowed_dir_needs_no_approval() {
+        let args = json!({ "command": "npm test", "cwd": "sub" });
+        assert!(approval_targets(&access("whitelist"), "run_command", &args).is_empty());
+        assert!(approval_targets(&access("whitelist"), "Bash", &json!({ "command": "  " })).is_empty());
+    }
+
+    #[test]
+    fn unlisted_command_asks_for_its_lowercase_name() {
+        let args = json!({ "command": "/usr/bin/Curl -s https://example.com" });
+        let targets = approval_targets(&access("whitelist"), "Bash", &args);
+        assert_eq!(summary(&targets), vec![("command", "curl".to_string())]);
+        assert_eq!(targets[0].detail, "/usr/bin/Curl -s https://example.com");
+    }
+
+    #[test]
+    fn cwd_outside_allowed_dirs_asks_for_the_dir() {
+        let outside = std::env::temp_dir().join("opencowork-approval-outside");
+        let args = json!({ "command": "ls", "cwd": outside.to_string_lossy() });
+        let targets = approval_targets(&access("whitelist"), "Bash", &args);
+        assert_eq!(summary(&targets), vec![("path", outside.to_string_lossy().to_string())]);
+    }
+
+    #[test]
+    fn write_outside_allowed_dirs_asks_for_the_parent_dir() {
+        let outside = std::env::temp_dir().join("opencowork-approval-outside");
+        let file = outside.join("notes.md");
+        let args = json!({ "path": file.to_string_lossy(), "content": "x" });
+        let targets = approval_targets(&access("whitelist"), "Write", &args);
+        assert_eq!(summary(&targets), vec![("path", outside.to_string_lossy().to_string())]);
+        assert_eq!(targets[0].detail, file.to_string_lossy());
+
+        let inside = json!({ "path": "docs/notes.md", "content": "x" });
+        assert!(approval_targets(&access("whitelist"), "Edit", &inside).is_empty());
+        // 用 .. 跳出工作区同样需要授权
+        let escaped = json!({ "path": "../opencowork-approval-outside/notes.md" });
+        assert_eq!(approval_targets(&access("whitelist"), "Update", &escaped).len(), 1);
+    }
+
+    #[test]
+    fn browser_needs_its_own_permission() {
+        let targets = approval_targets(&access("whitelist"), "browser", &json!({ "action": "open" }));
+        assert_eq!(summary(&targets), vec![("command", BROWSER_PERMISSION.to_string())]);
+        assert!(approval_targets(&access("whitelist"), "Read", &json!({ "path": "/etc/hosts" })).is_empty());
+    }
+}
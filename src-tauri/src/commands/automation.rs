@@ -0,0 +1,280 @@
+use super::ToolAccess;
+use crate::error::AppError;
+use crate::logs;
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const MAX_UI_STEPS: usize = 50;
+const DEFAULT_STEP_DELAY_MS: u64 = 150;
+const MAX_STEP_DELAY_MS: u64 = 5_000;
+const MAX_TYPE_CHARS: usize = 2_000;
+// 鼠标移到屏幕左上角时立即停止，防止自动操作失控
+const FAILSAFE_MARGIN: i32 = 2;
+
+// 每次 stop_ui_automation 加一，进行中的操作发现变化后停止
+static STOP_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// ui_action 的单步操作
+#[derive(Debug, Clone)]
+enum UiStep {
+    Move { x: i32, y: i32 },
+    Click { x: Option<i32>, y: Option<i32>, button: Button, double: bool },
+    Type { text: String },
+    Key { key: Key, modifiers: Vec<Key> },
+    Scroll { amount: i32, horizontal: bool },
+    Wait { ms: u64 },
+}
+
+fn int_arg(step: &Value, key: &str) -> Option<i32> {
+    step.get(key).and_then(|v| v.as_i64()).map(|v| v as i32)
+}
+
+fn parse_key(name: &str) -> Result<Key, String> {
+    let lower = name.trim().to_lowercase();
+    let key = match lower.as_str() {
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "escape" | "esc" => Key::Escape,
+        "space" => Key::Space,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "ctrl" | "control" => Key::Control,
+        "shift" => Key::Shift,
+        "alt" | "option" => Key::Alt,
+        "meta" | "cmd" | "command" | "win" | "super" => Key::Meta,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        _ => {
+            let mut chars = lower.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Key::Unicode(c),
+                _ => return Err(format!("不支持的按键: {}", name)),
+            }
+        }
+    };
+    Ok(key)
+}
+
+fn parse_step(step: &Value) -> Result<UiStep, String> {
+    let action = step
+        .get("action")
+        .and_then(|v| v.as_str())
+        .map(|v| v.trim().to_lowercase())
+        .ok_or_else(|| "缺少 action 参数".to_string())?;
+    match action.as_str() {
+        "move" => Ok(UiStep::Move {
+            x: int_arg(step, "x").ok_or_else(|| "move 缺少 x".to_string())?,
+            y: int_arg(step, "y").ok_or_else(|| "move 缺少 y".to_string())?,
+        }),
+        "click" => {
+            let button = match step.get("button").and_then(|v| v.as_str()).unwrap_or("left") {
+                "right" => Button::Right,
+                "middle" => Button::Middle,
+                _ => Button::Left,
+            };
+            Ok(UiStep::Click {
+                x: int_arg(step, "x"),
+                y: int_arg(step, "y"),
+                button,
+                double: step.get("double").and_then(|v| v.as_bool()).unwrap_or(false),
+            })
+        }
+        "type" => {
+            let text = step.get("text").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            if text.is_empty() {
+                return Err("type 缺少 text".to_string());
+            }
+            if text.chars().count() > MAX_TYPE_CHARS {
+                return Err(format!("一次最多输入 {} 个字符", MAX_TYPE_CHARS));
+            }
+            Ok(UiStep::Type { text })
+        }
+        "key" => {
+            let key = parse_key(step.get("key").and_then(|v| v.as_str()).unwrap_or(""))?;
+            let modifiers = match step.get("modifiers") {
+                Some(Value::Array(items)) => items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .map(parse_key)
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => Vec::new(),
+            };
+            Ok(UiStep::Key { key, modifiers })
+        }
+        "scroll" => Ok(UiStep::Scroll {
+            amount: int_arg(step, "amount").unwrap_or(3),
+            horizontal: step.get("horizontal").and_then(|v| v.as_bool()).unwrap_or(false),
+        }),
+        "wait" => Ok(UiStep::Wait {
+            ms: step.get("ms").and_then(|v| v.as_u64()).unwrap_or(500).min(MAX_STEP_DELAY_MS),
+        }),
+        other => Err(format!("未知的 ui_action 操作: {}", other)),
+    }
+}
+
+/// 单个操作或 steps 列表
+fn parse_steps(args: &Value) -> Result<Vec<UiStep>, String> {
+    let steps = match args.get("steps") {
+        Some(Value::Array(items)) => items.iter().map(parse_step).collect::<Result<Vec<_>, _>>()?,
+        _ => vec![parse_step(args)?],
+    };
+    if steps.is_empty() {
+        return Err("没有要执行的操作".to_string());
+    }
+    if steps.len() > MAX_UI_STEPS {
+        return Err(format!("一次最多执行 {} 步操作", MAX_UI_STEPS));
+    }
+    Ok(steps)
+}
+
+pub(super) fn describe_steps(args: &Value) -> String {
+    match parse_steps(args) {
+        Ok(steps) => steps.iter().map(describe_step).collect::<Vec<_>>().join("；"),
+        Err(err) => err,
+    }
+}
+
+fn describe_step(step: &UiStep) -> String {
+    match step {
+        UiStep::Move { x, y } => format!("移动鼠标到 ({}, {})", x, y),
+        UiStep::Click { x: Some(x), y: Some(y), button, double } => {
+            format!("在 ({}, {}) {}{:?} 键", x, y, if *double { "双击" } else { "单击" }, button)
+        }
+        UiStep::Click { button, double, .. } => {
+            format!("在当前位置{}{:?} 键", if *double { "双击" } else { "单击" }, button)
+        }
+        UiStep::Type { text } => format!("输入 {} 个字符", text.chars().count()),
+        UiStep::Key { key, modifiers } if modifiers.is_empty() => format!("按键 {:?}", key),
+        UiStep::Key { key, modifiers } => format!("组合键 {:?}+{:?}", modifiers, key),
+        UiStep::Scroll { amount, horizontal } => {
+            format!("{}滚动 {}", if *horizontal { "水平" } else { "垂直" }, amount)
+        }
+        UiStep::Wait { ms } => format!("等待 {} ms", ms),
+    }
+}
+
+fn run_step(enigo: &mut Enigo, step: &UiStep) -> Result<(), String> {
+    let input = |e: enigo::InputError| format!("输入操作失败: {}", e);
+    match step {
+        UiStep::Move { x, y } => enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(input),
+        UiStep::Click { x, y, button, double } => {
+            if let (Some(x), Some(y)) = (x, y) {
+                enigo.move_mouse(*x, *y, Coordinate::Abs).map_err(input)?;
+            }
+            enigo.button(*button, Direction::Click).map_err(input)?;
+            if *double {
+                enigo.button(*button, Direction::Click).map_err(input)?;
+            }
+            Ok(())
+        }
+        UiStep::Type { text } => enigo.text(text).map_err(input),
+        UiStep::Key { key, modifiers } => {
+            for modifier in modifiers {
+                enigo.key(*modifier, Direction::Press).map_err(input)?;
+            }
+            let result = enigo.key(*key, Direction::Click).map_err(input);
+            // 无论按键是否成功都要松开修饰键
+            for modifier in modifiers.iter().rev() {
+                let _ = enigo.key(*modifier, Direction::Release);
+            }
+            result
+        }
+        UiStep::Scroll { amount, horizontal } => {
+            let axis = if *horizontal { Axis::Horizontal } else { Axis::Vertical };
+            enigo.scroll(*amount, axis).map_err(input)
+        }
+        UiStep::Wait { ms } => {
+            std::thread::sleep(Duration::from_millis(*ms));
+            Ok(())
+        }
+    }
+}
+
+/// 在阻塞线程中逐步执行；每步前检查取消、停止和左上角紧急停止
+fn run_steps(steps: Vec<UiStep>, delay_ms: u64, cancel: CancellationToken) -> Result<Vec<String>, AppError> {
+    let generation = STOP_GENERATION.load(Ordering::SeqCst);
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| format!("无法初始化输入控制: {}", e))?;
+    let mut done = Vec::new();
+    for (idx, step) in steps.iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
+        if STOP_GENERATION.load(Ordering::SeqCst) != generation {
+            return Err(format!("已手动停止，完成 {}/{} 步", idx, steps.len()).into());
+        }
+        if let Ok((x, y)) = enigo.location() {
+            if x <= FAILSAFE_MARGIN && y <= FAILSAFE_MARGIN {
+                return Err(format!("鼠标位于屏幕左上角，已紧急停止，完成 {}/{} 步", idx, steps.len()).into());
+            }
+        }
+        let description = describe_step(step);
+        logs::info("automation", format!("[{}/{}] {}", idx + 1, steps.len(), description));
+        run_step(&mut enigo, step).map_err(|err| {
+            logs::warn("automation", format!("{} 失败: {}", description, err));
+            format!("第 {} 步（{}）失败: {}", idx + 1, description, err)
+        })?;
+        done.push(description);
+        if idx + 1 < steps.len() && delay_ms > 0 {
+            std::thread::sleep(Duration::from_millis(delay_ms));
+        }
+    }
+    Ok(done)
+}
+
+pub(super) async fn ui_action_tool(
+    access: &ToolAccess,
+    automation_enabled: bool,
+    args: &Value,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<String, AppError> {
+    if access.mode == "unset" {
+        return Err(AppError::ToolModeUnset);
+    }
+    if !automation_enabled {
+        return Err(AppError::ToolDenied {
+            tool: "ui_action".to_string(),
+            message: "桌面自动化未开启，需要用户在设置中允许 ui_action 操作鼠标和键盘".to_string(),
+        });
+    }
+    let steps = parse_steps(args)?;
+    let delay_ms = args
+        .get("delay_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_STEP_DELAY_MS)
+        .min(MAX_STEP_DELAY_MS);
+    let cancel = cancel_token.cloned().unwrap_or_default();
+    let total = steps.len();
+    let done = tokio::task::spawn_blocking(move || run_steps(steps, delay_ms, cancel))
+        .await
+        .map_err(|e| format!("桌面操作中断: {}", e))??;
+    Ok(format!("已完成 {}/{} 步：{}", done.len(), total, done.join("；")))
+}
+
+/// 立即停止进行中的 ui_action（当前步骤执行完后停止）
+#[tauri::command]
+pub async fn stop_ui_automation() -> Result<(), String> {
+    STOP_GENERATION.fetch_add(1, Ordering::SeqCst);
+    logs::info("automation", "用户手动停止桌面操作");
+    Ok(())
+}
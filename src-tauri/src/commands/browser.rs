@@ -0,0 +1,332 @@
+use super::{
+    await_with_cancel, check_cancel, command_allowed, truncate_string, ToolAccess,
+    DEFAULT_AGENT_BROWSER_TIMEOUT_MS,
+};
+use crate::error::AppError;
+use crate::storage::{storage_actor, BrowserConfig, StorageManager, StoragePriority};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::process::Command as TokioCommand;
+use tokio::time::{timeout, Duration as TokioDuration};
+use tokio_util::sync::CancellationToken;
+
+/// 白名单模式下需要允许 agent-browser 命令
+pub(super) const BROWSER_PERMISSION: &str = "agent-browser";
+// 工具输出中以该前缀开头的行是截图路径，发给模型前转换为图片
+const TOOL_IMAGE_PREFIX: &str = "[tool-image] ";
+const MAX_BROWSER_TEXT_CHARS: usize = 20_000;
+const DEFAULT_SESSION: &str = "default";
+const SESSION_PREFIX: &str = "opencowork";
+
+/// 会修改页面状态的操作，预演模式下不执行
+const PAGE_ACTIONS: &[&str] = &["click", "type"];
+
+/// 一个浏览器会话；同一会话在多轮对话之间保留页面和登录状态，空闲超时后自动关闭
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserSession {
+    pub name: String,
+    pub scope: String,
+    pub url: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    #[serde(skip)]
+    last_used: Instant,
+}
+
+fn browser_sessions() -> &'static ParkingMutex<HashMap<String, BrowserSession>> {
+    static SESSIONS: OnceLock<ParkingMutex<HashMap<String, BrowserSession>>> = OnceLock::new();
+    SESSIONS.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+pub(super) fn is_page_action(action: &str) -> bool {
+    PAGE_ACTIONS.contains(&action)
+}
+
+fn sanitize_name(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    name.trim_matches('-').to_string()
+}
+
+/// agent-browser 的会话名：按授权范围隔离，助手和各技能互不共享页面
+fn session_name(scope: &str, args: &Value) -> String {
+    let session = args
+        .get("session")
+        .and_then(|v| v.as_str())
+        .map(sanitize_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_SESSION.to_string());
+    format!("{}-{}-{}", SESSION_PREFIX, sanitize_name(scope), session)
+}
+
+fn text_arg<'a>(args: &'a Value, key: &str) -> Option<&'a str> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// 选择器可以是 CSS 选择器或 snapshot 返回的 @ref；不允许以 - 开头，避免被当成命令行选项
+fn selector_arg(args: &Value) -> Result<String, String> {
+    match text_arg(args, "selector") {
+        Some(selector) if selector.starts_with('-') => Err(format!("无效的选择器: {}", selector)),
+        Some(selector) => Ok(selector.to_string()),
+        None => Err("缺少 selector 参数".to_string()),
+    }
+}
+
+fn url_arg(args: &Value) -> Result<String, String> {
+    let url = text_arg(args, "url").ok_or_else(|| "缺少 url 参数".to_string())?;
+    let lower = url.to_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") || lower == "about:blank" {
+        Ok(url.to_string())
+    } else if !url.contains("://") && !url.starts_with('-') {
+        Ok(format!("https://{}", url))
+    } else {
+        Err(format!("只支持 http/https 地址: {}", url))
+    }
+}
+
+async fn run_browser(config: &BrowserConfig, session: &str, args: &[String]) -> Result<String, String> {
+    let mut cmd = TokioCommand::new(config.command.trim());
+    cmd.arg("--session").arg(session);
+    if config.headed {
+        cmd.arg("--headed");
+    }
+    // 取消时 future 被丢弃，子进程随之结束
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let output = timeout(TokioDuration::from_millis(DEFAULT_AGENT_BROWSER_TIMEOUT_MS), cmd.output())
+        .await
+        .map_err(|_| format!("浏览器操作超时（{} ms）", DEFAULT_AGENT_BROWSER_TIMEOUT_MS))?
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                format!(
+                    "未找到 {}，请先安装（npm install -g agent-browser && agent-browser install）或在设置中填写路径",
+                    config.command
+                )
+            } else {
+                format!("启动浏览器失败: {}", e)
+            }
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let message = if stderr.is_empty() { stdout } else { stderr };
+        return Err(format!("浏览器操作失败: {}", message));
+    }
+    Ok(stdout)
+}
+
+fn touch_session(name: &str, scope: &str, url: Option<String>) {
+    let now = Local::now().to_rfc3339();
+    let mut sessions = browser_sessions().lock();
+    let session = sessions.entry(name.to_string()).or_insert_with(|| BrowserSession {
+        name: name.to_string(),
+        scope: scope.to_string(),
+        url: None,
+        created_at: now.clone(),
+        last_used_at: now.clone(),
+        last_used: Instant::now(),
+    });
+    session.last_used_at = now;
+    session.last_used = Instant::now();
+    if url.is_some() {
+        session.url = url;
+    }
+}
+
+/// 关闭空闲超时的会话，在每次调用浏览器工具时顺带检查
+fn close_idle_sessions(config: &BrowserConfig) {
+    if config.idle_timeout_secs == 0 {
+        return;
+    }
+    let idle: Vec<String> = {
+        let mut sessions = browser_sessions().lock();
+        let expired: Vec<String> = sessions
+            .values()
+            .filter(|session| session.last_used.elapsed().as_secs() >= config.idle_timeout_secs)
+            .map(|session| session.name.clone())
+            .collect();
+        for name in &expired {
+            sessions.remove(name);
+        }
+        expired
+    };
+    for name in idle {
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(err) = run_browser(&config, &name, &["close".to_string()]).await {
+                crate::logs::debug("browser", format!("关闭空闲会话 {} 失败: {}", name, err));
+            }
+        });
+    }
+}
+
+fn screenshot_path(session: &str) -> Result<PathBuf, String> {
+    let dir = StorageManager::new().get_data_dir().join("browser");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("创建截图目录失败: {}", e))?;
+    Ok(dir.join(format!("{}-{}.png", session, Local::now().format("%Y%m%d-%H%M%S%3f"))))
+}
+
+async fn page_info(config: &BrowserConfig, session: &str) -> (Option<String>, Option<String>) {
+    let get = |what: &str| vec!["get".to_string(), what.to_string()];
+    let url = run_browser(config, session, &get("url")).await.ok();
+    let title = run_browser(config, session, &get("title")).await.ok();
+    (url, title)
+}
+
+async fn run_action(
+    config: &BrowserConfig,
+    session: &str,
+    scope: &str,
+    action: &str,
+    args: &Value,
+) -> Result<String, String> {
+    let result = match action {
+        "navigate" => {
+            let url = url_arg(args)?;
+            run_browser(config, session, &["open".to_string(), url]).await?;
+            let (url, title) = page_info(config, session).await;
+            touch_session(session, scope, url.clone());
+            json!({ "session": session, "url": url, "title": title })
+        }
+        "click" => {
+            let selector = selector_arg(args)?;
+            run_browser(config, session, &["click".to_string(), selector.clone()]).await?;
+            let (url, title) = page_info(config, session).await;
+            touch_session(session, scope, url.clone());
+            json!({ "session": session, "clicked": selector, "url": url, "title": title })
+        }
+        "type" => {
+            let selector = selector_arg(args)?;
+            let text = args.get("text").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            run_browser(config, session, &["fill".to_string(), selector.clone(), text]).await?;
+            let submit = args.get("submit").and_then(|v| v.as_bool()).unwrap_or(false);
+            if submit {
+                run_browser(config, session, &["press".to_string(), "Enter".to_string()]).await?;
+            }
+            touch_session(session, scope, None);
+            json!({ "session": session, "typed_into": selector, "submitted": submit })
+        }
+        "screenshot" => {
+            let path = screenshot_path(session)?;
+            let mut cli_args = vec!["screenshot".to_string(), path.display().to_string()];
+            if args.get("full_page").and_then(|v| v.as_bool()).unwrap_or(false) {
+                cli_args.push("--full".to_string());
+            }
+            run_browser(config, session, &cli_args).await?;
+            if !path.exists() {
+                return Err("浏览器没有生成截图文件".to_string());
+            }
+            touch_session(session, scope, None);
+            let info = json!({ "session": session, "path": path.display().to_string() });
+            return Ok(format!("{}\n{}{}", info, TOOL_IMAGE_PREFIX, path.display()));
+        }
+        "extract_text" => {
+            let selector = match text_arg(args, "selector") {
+                Some(_) => selector_arg(args)?,
+                None => "body".to_string(),
+            };
+            let text = run_browser(config, session, &["get".to_string(), "text".to_string(), selector.clone()]).await?;
+            touch_session(session, scope, None);
+            let (text, truncated) = truncate_string(&text, MAX_BROWSER_TEXT_CHARS);
+            json!({ "session": session, "selector": selector, "text": text, "truncated": truncated })
+        }
+        "snapshot" => {
+            let tree = run_browser(config, session, &["snapshot".to_string(), "-i".to_string()]).await?;
+            touch_session(session, scope, None);
+            let (tree, truncated) = truncate_string(&tree, MAX_BROWSER_TEXT_CHARS);
+            json!({ "session": session, "elements": tree, "truncated": truncated })
+        }
+        "close" => {
+            let existed = browser_sessions().lock().remove(session).is_some();
+            run_browser(config, session, &["close".to_string()]).await?;
+            json!({ "session": session, "closed": existed })
+        }
+        other => return Err(format!("未知的浏览器操作: {}", other)),
+    };
+    serde_json::to_string_pretty(&result).map_err(|e| format!("序列化浏览器结果失败: {}", e))
+}
+
+pub(super) async fn browser_tool(
+    access: &ToolAccess,
+    config: &BrowserConfig,
+    scope: &str,
+    args: &Value,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<String, AppError> {
+    if access.mode == "unset" {
+        return Err(AppError::ToolModeUnset);
+    }
+    if access.mode == "whitelist" && !command_allowed(access, BROWSER_PERMISSION) {
+        return Err(AppError::ToolDenied {
+            tool: "browser".to_string(),
+            message: format!("使用浏览器需要在允许的命令中加入 {}", BROWSER_PERMISSION),
+        });
+    }
+    let action = text_arg(args, "action")
+        .map(str::to_lowercase)
+        .ok_or_else(|| "缺少 action 参数".to_string())?;
+    close_idle_sessions(config);
+    check_cancel(cancel_token)?;
+    let session = session_name(scope, args);
+
+    let run = run_action(config, &session, scope, &action, args);
+    match cancel_token {
+        Some(token) => await_with_cancel(token, run).await,
+        None => run.await.map_err(AppError::from),
+    }
+}
+
+/// 取出工具输出中的截图：返回去掉标记行的文本，以及可直接发给模型的 data URL
+pub(super) fn take_tool_images(output: &str) -> (String, Vec<String>) {
+    if !output.contains(TOOL_IMAGE_PREFIX) {
+        return (output.to_string(), Vec::new());
+    }
+    let mut images = Vec::new();
+    let mut lines = Vec::new();
+    for line in output.lines() {
+        match line.strip_prefix(TOOL_IMAGE_PREFIX) {
+            Some(path) => match std::fs::read(Path::new(path.trim())) {
+                Ok(bytes) => images.push(format!("data:image/png;base64,{}", BASE64.encode(bytes))),
+                Err(err) => crate::logs::debug("browser", format!("读取截图失败 {}: {}", path, err)),
+            },
+            None => lines.push(line),
+        }
+    }
+    (lines.join("\n"), images)
+}
+
+#[tauri::command]
+pub async fn list_browser_sessions() -> Result<Vec<BrowserSession>, String> {
+    let mut sessions: Vec<BrowserSession> = browser_sessions().lock().values().cloned().collect();
+    sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(sessions)
+}
+
+#[tauri::command]
+pub async fn close_browser_session(name: String) -> Result<bool, String> {
+    let existed = browser_sessions().lock().remove(&name).is_some();
+    if !existed {
+        return Ok(false);
+    }
+    let config = storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.load_config())
+        .await?;
+    run_browser(&config.browser, &name, &["close".to_string()]).await?;
+    Ok(true)
+}
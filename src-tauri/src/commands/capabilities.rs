@@ -0,0 +1,171 @@
+use super::{build_tool_access, is_model_invocable_skill};
+use crate::model::ModelManager;
+use crate::skills::SkillMetadata;
+use crate::storage::{Config, StorageManager};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCapabilities {
+    pub provider: String,
+    pub model: String,
+    pub supports_tools: bool,
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCapabilities {
+    pub mode: String,  // unset | whitelist | allow_all
+    pub ask_approval: bool,
+    pub allowed_commands: Vec<String>,
+    pub allowed_dirs: Vec<String>,
+    pub base_dir: String,
+    pub workspace: Option<String>,
+    pub mcp_servers: Vec<String>,  // 已启用的 MCP 服务器
+    pub automation: bool,  // 是否允许 ui_action 操作鼠标键盘
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillCapability {
+    pub name: String,
+    pub description: String,
+    pub model_invocable: bool,
+    pub user_invocable: bool,
+    pub allowed_tools: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureCapabilities {
+    pub running: bool,
+    pub interval_ms: u64,
+    pub ocr_enabled: bool,
+    pub skip_unchanged: bool,
+}
+
+/// 当前配置下助手实际能做什么
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub model: ModelCapabilities,
+    pub tools: ToolCapabilities,
+    pub skills: Vec<SkillCapability>,
+    pub capture: CaptureCapabilities,
+}
+
+fn current_model_name(config: &Config) -> String {
+    match config.model.provider.as_str() {
+        "ollama" => config.model.ollama.model.clone(),
+        "gemini" => config.model.gemini.model.clone(),
+        _ => config.model.api.model.clone(),
+    }
+}
+
+pub(super) fn collect_capabilities(
+    config: &Config,
+    storage: &StorageManager,
+    skills: &[SkillMetadata],
+    capture_running: bool,
+) -> Capabilities {
+    let access = build_tool_access(config, storage, None);
+    let display = |path: &std::path::Path| path.display().to_string();
+    Capabilities {
+        model: ModelCapabilities {
+            provider: config.model.provider.clone(),
+            model: current_model_name(config),
+            supports_tools: ModelManager::supports_tools(&config.model),
+            profiles: config.model.profiles.iter().map(|profile| profile.name.clone()).collect(),
+        },
+        tools: ToolCapabilities {
+            mode: access.mode.clone(),
+            ask_approval: config.tools.ask_approval,
+            allowed_commands: access.allowed_commands.clone(),
+            allowed_dirs: access.allowed_dirs.iter().map(|dir| display(dir)).collect(),
+            base_dir: display(&access.base_dir),
+            workspace: crate::workspaces::active_workspace_dir(config)
+                .and(config.workspaces.active.clone()),
+            mcp_servers: config
+                .tools
+                .mcp_servers
+                .iter()
+                .filter(|server| server.enabled)
+                .map(|server| server.name.clone())
+                .collect(),
+            automation: config.tools.automation,
+        },
+        skills: skills
+            .iter()
+            .map(|skill| SkillCapability {
+                name: skill.name.clone(),
+                description: skill.description.clone(),
+                model_invocable: is_model_invocable_skill(skill),
+                user_invocable: skill.user_invocable.unwrap_or(true),
+                allowed_tools: skill.allowed_tools.clone(),
+            })
+            .collect(),
+        capture: CaptureCapabilities {
+            running: capture_running,
+            interval_ms: config.capture.interval_ms,
+            ocr_enabled: config.capture.ocr_enabled,
+            skip_unchanged: config.capture.skip_unchanged,
+        },
+    }
+}
+
+impl Capabilities {
+    /// 追加到系统提示词的能力与限制说明，避免模型承诺配置不允许的操作
+    pub(super) fn prompt_section(&self) -> String {
+        let mut lines = vec!["\n\n## 当前能力与限制".to_string()];
+        lines.push(format!(
+            "- 模型: {} / {}{}",
+            self.model.provider,
+            self.model.model,
+            if self.model.supports_tools { "" } else { "（不支持工具调用）" }
+        ));
+        let tools = &self.tools;
+        lines.push(match tools.mode.as_str() {
+            "unset" => "- 文件和命令工具：用户尚未设置工具权限，Read/Write/Edit/Glob/Grep/Bash 都不可用，不要承诺读写文件或运行命令，需要时请用户先在设置中选择工具模式".to_string(),
+            "allow_all" => format!("- 文件和命令工具：完全放开，默认工作目录 {}", tools.base_dir),
+            _ => {
+                let commands = if tools.allowed_commands.is_empty() {
+                    "无".to_string()
+                } else {
+                    tools.allowed_commands.join(", ")
+                };
+                let approval = if tools.ask_approval {
+                    "白名单外的命令和写入会请求用户授权"
+                } else {
+                    "白名单外的命令和写入会被拒绝"
+                };
+                format!(
+                    "- 文件和命令工具：白名单模式，可访问目录 {}；允许的命令 {}；{}。默认工作目录 {}",
+                    tools.allowed_dirs.join(", "),
+                    commands,
+                    approval,
+                    tools.base_dir
+                )
+            }
+        });
+        if let Some(workspace) = &tools.workspace {
+            lines.push(format!("- 当前工作区: {}", workspace));
+        }
+        if !tools.mcp_servers.is_empty() {
+            lines.push(format!("- 已启用的 MCP 服务器: {}", tools.mcp_servers.join(", ")));
+        }
+        if !tools.automation {
+            lines.push("- 桌面自动化未开启：ui_action 不可用，需要点击或输入时请告诉用户具体步骤".to_string());
+        }
+        let blocked: Vec<&str> = self
+            .skills
+            .iter()
+            .filter(|skill| !skill.model_invocable)
+            .map(|skill| skill.name.as_str())
+            .collect();
+        if !blocked.is_empty() {
+            lines.push(format!("- 以下技能只能由用户手动调用，不要通过 invoke_skill 调用: {}", blocked.join(", ")));
+        }
+        lines.push(if self.capture.running {
+            format!("- 屏幕记录：运行中（每 {} ms 一帧）", self.capture.interval_ms)
+        } else {
+            "- 屏幕记录：未运行，最近的操作记录可能缺失；不要声称能看到用户当前屏幕".to_string()
+        });
+        lines.join("\n")
+    }
+}
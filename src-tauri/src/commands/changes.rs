@@ -0,0 +1,211 @@
+use super::diff::write_atomic;
+use crate::storage::StorageManager;
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+const CHANGES_FILE: &str = "file_changes.json";
+const SHADOW_DIR: &str = "file_changes";
+const MAX_JOURNAL_ENTRIES: usize = 500;
+// 过大的文件不做快照，避免撑满数据目录
+const MAX_SNAPSHOT_BYTES: usize = 20 * 1024 * 1024;
+
+static CHANGE_SEQ: AtomicU64 = AtomicU64::new(1);
+// 同一请求里的工具调用可能并行，读写日志时串行化
+fn journal_lock() -> &'static ParkingMutex<()> {
+    static LOCK: OnceLock<ParkingMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| ParkingMutex::new(()))
+}
+
+/// 一次 Write/Edit 修改的记录；snapshot 为修改前内容在影子目录中的文件名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub id: String,
+    pub request_id: String,
+    pub scope: String,  // assistant 或 skill 名称
+    pub tool: String,
+    pub path: String,
+    pub existed: bool,  // 修改前文件是否存在，不存在时回滚即删除
+    #[serde(default)]
+    pub snapshot: Option<String>,
+    pub size: usize,
+    pub created_at: String,
+    #[serde(default)]
+    pub reverted_at: Option<String>,
+}
+
+fn sanitize_dir_name(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(80)
+        .collect();
+    if name.is_empty() {
+        "direct".to_string()
+    } else {
+        name
+    }
+}
+
+impl StorageManager {
+    fn file_changes_path(&self) -> PathBuf {
+        self.get_data_dir().join(CHANGES_FILE)
+    }
+
+    fn shadow_path(&self, change: &FileChange) -> Option<PathBuf> {
+        let snapshot = change.snapshot.as_deref()?;
+        Some(
+            self.get_data_dir()
+                .join(SHADOW_DIR)
+                .join(sanitize_dir_name(&change.request_id))
+                .join(snapshot),
+        )
+    }
+
+    fn load_file_changes(&self) -> Result<Vec<FileChange>, String> {
+        let path = self.file_changes_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_file_changes(&self, changes: &[FileChange]) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(changes)
+            .map_err(|e| format!("序列化修改记录失败: {}", e))?;
+        self.write_data_file(&self.file_changes_path(), content.as_bytes())
+    }
+
+    /// 修改文件前调用：把原内容存入影子目录并写入修改日志，返回记录 ID
+    pub fn record_file_change(
+        &self,
+        request_id: &str,
+        scope: &str,
+        tool: &str,
+        path: &Path,
+        original: Option<&[u8]>,
+    ) -> Result<String, String> {
+        if original.map_or(false, |data| data.len() > MAX_SNAPSHOT_BYTES) {
+            return Err(format!(
+                "文件超过 {} MB，无法保存修改前快照: {}",
+                MAX_SNAPSHOT_BYTES / 1024 / 1024,
+                path.display()
+            ));
+        }
+        let now = Local::now();
+        let id = format!(
+            "change-{}-{}",
+            now.timestamp_millis(),
+            CHANGE_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        let change = FileChange {
+            id: id.clone(),
+            request_id: request_id.to_string(),
+            scope: scope.to_string(),
+            tool: tool.to_string(),
+            path: path.display().to_string(),
+            existed: original.is_some(),
+            snapshot: original.map(|_| format!("{}.orig", id)),
+            size: original.map_or(0, |data| data.len()),
+            created_at: now.to_rfc3339(),
+            reverted_at: None,
+        };
+        if let (Some(data), Some(shadow)) = (original, self.shadow_path(&change)) {
+            if let Some(dir) = shadow.parent() {
+                fs::create_dir_all(dir).map_err(|e| format!("创建快照目录失败: {}", e))?;
+            }
+            self.write_data_file(&shadow, data)?;
+        }
+
+        let _guard = journal_lock().lock();
+        let mut changes = self.load_file_changes()?;
+        changes.push(change);
+        if changes.len() > MAX_JOURNAL_ENTRIES {
+            let dropped: Vec<FileChange> = changes.drain(..changes.len() - MAX_JOURNAL_ENTRIES).collect();
+            for old in &dropped {
+                if let Some(shadow) = self.shadow_path(old) {
+                    let _ = fs::remove_file(&shadow);
+                    if let Some(dir) = shadow.parent() {
+                        let _ = fs::remove_dir(dir);  // 目录空了才会删除成功
+                    }
+                }
+            }
+        }
+        self.save_file_changes(&changes)?;
+        self.record_audit_event_sync(
+            "file_change",
+            serde_json::json!({
+                "change_id": id,
+                "request_id": request_id,
+                "scope": scope,
+                "tool": tool,
+                "path": path.display().to_string(),
+                "existed": original.is_some(),
+            }),
+        );
+        Ok(id)
+    }
+
+    /// 按请求列出修改记录；request_id 为空时返回全部（新的在前）
+    pub fn list_file_changes(&self, request_id: Option<&str>) -> Result<Vec<FileChange>, String> {
+        let _guard = journal_lock().lock();
+        let mut changes: Vec<FileChange> = self
+            .load_file_changes()?
+            .into_iter()
+            .filter(|change| request_id.map_or(true, |id| id.is_empty() || change.request_id == id))
+            .collect();
+        changes.reverse();
+        Ok(changes)
+    }
+
+    /// 把文件恢复到该次修改之前的状态；同一文件之后的修改也一并标记为已回滚
+    pub fn revert_file_change(&self, change_id: &str) -> Result<FileChange, String> {
+        let _guard = journal_lock().lock();
+        let mut changes = self.load_file_changes()?;
+        let index = changes
+            .iter()
+            .position(|change| change.id == change_id)
+            .ok_or_else(|| format!("修改记录不存在: {}", change_id))?;
+        let target = changes[index].clone();
+        if target.reverted_at.is_some() {
+            return Err(format!("该修改已回滚: {}", target.path));
+        }
+
+        let path = PathBuf::from(&target.path);
+        if target.existed {
+            let shadow = self
+                .shadow_path(&target)
+                .ok_or_else(|| "修改前快照缺失，无法回滚".to_string())?;
+            let original = self.read_data_file(&shadow)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+            }
+            write_atomic(&path, &original)?;
+        } else if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除文件失败: {}", e))?;
+        }
+
+        let reverted_at = Local::now().to_rfc3339();
+        for change in changes[index..].iter_mut() {
+            if change.path == target.path && change.reverted_at.is_none() {
+                change.reverted_at = Some(reverted_at.clone());
+            }
+        }
+        self.save_file_changes(&changes)?;
+        self.record_audit_event_sync(
+            "file_revert",
+            serde_json::json!({
+                "change_id": target.id,
+                "tool": target.tool,
+                "path": target.path,
+            }),
+        );
+        crate::logs::info("tool", format!("已回滚 {} 的修改: {}", target.tool, target.path));
+        Ok(changes[index].clone())
+    }
+}
@@ -0,0 +1,145 @@
+use super::approval::confirm_config_change;
+use super::{apply_config, restart_capture_if_running, ProgressEmitter};
+use crate::error::AppError;
+use crate::storage::{Config, StorageManager};
+use tokio_util::sync::CancellationToken;
+
+const MIN_CAPTURE_INTERVAL_MS: u64 = 1000;
+const MAX_CAPTURE_INTERVAL_MS: u64 = 60 * 60 * 1000;
+const CONTEXT_MODES: &[&str] = &["auto", "always", "off"];
+
+/// 助手可以提议修改的设置，其余设置只能由用户在设置页修改
+#[derive(Debug, Default)]
+struct ConfigChanges {
+    capture_interval_ms: Option<u64>,
+    context_mode: Option<String>,
+    alert_confidence_threshold: Option<f32>,
+}
+
+impl ConfigChanges {
+    fn parse(value: Option<&serde_json::Value>) -> Result<Self, String> {
+        let Some(object) = value.and_then(|v| v.as_object()) else {
+            return Err("缺少 changes 参数".to_string());
+        };
+        let mut changes = ConfigChanges::default();
+        for (key, value) in object {
+            match key.as_str() {
+                "capture_interval_ms" => {
+                    let interval = value
+                        .as_u64()
+                        .ok_or_else(|| "capture_interval_ms 应为整数毫秒".to_string())?;
+                    if !(MIN_CAPTURE_INTERVAL_MS..=MAX_CAPTURE_INTERVAL_MS).contains(&interval) {
+                        return Err(format!(
+                            "capture_interval_ms 应在 {} 到 {} 之间",
+                            MIN_CAPTURE_INTERVAL_MS, MAX_CAPTURE_INTERVAL_MS
+                        ));
+                    }
+                    changes.capture_interval_ms = Some(interval);
+                }
+                "context_mode" => {
+                    let mode = value.as_str().unwrap_or("").trim().to_lowercase();
+                    if !CONTEXT_MODES.contains(&mode.as_str()) {
+                        return Err("context_mode 只能是 auto、always 或 off".to_string());
+                    }
+                    changes.context_mode = Some(mode);
+                }
+                "alert_confidence_threshold" => {
+                    let threshold = value
+                        .as_f64()
+                        .filter(|v| (0.0..=1.0).contains(v))
+                        .ok_or_else(|| "alert_confidence_threshold 应为 0 到 1 之间的数".to_string())?;
+                    changes.alert_confidence_threshold = Some(threshold as f32);
+                }
+                other => return Err(format!("不允许通过助手修改设置项: {}", other)),
+            }
+        }
+        Ok(changes)
+    }
+
+    /// 与当前配置不同的项，每项一行「名称: 旧值 -> 新值」
+    fn describe(&self, config: &Config) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(interval) = self.capture_interval_ms.filter(|v| *v != config.capture.interval_ms) {
+            lines.push(format!("截屏间隔（毫秒）: {} -> {}", config.capture.interval_ms, interval));
+        }
+        if let Some(mode) = self.context_mode.as_ref().filter(|v| **v != config.storage.context_mode) {
+            lines.push(format!("对话上下文模式: {} -> {}", config.storage.context_mode, mode));
+        }
+        if let Some(threshold) = self
+            .alert_confidence_threshold
+            .filter(|v| (*v - config.capture.alert_confidence_threshold).abs() > f32::EPSILON)
+        {
+            lines.push(format!(
+                "提醒置信度阈值: {} -> {}",
+                config.capture.alert_confidence_threshold, threshold
+            ));
+        }
+        lines
+    }
+
+    fn apply(self, config: &mut Config) {
+        if let Some(interval) = self.capture_interval_ms {
+            config.capture.interval_ms = interval;
+        }
+        if let Some(mode) = self.context_mode {
+            config.storage.context_mode = mode;
+        }
+        if let Some(threshold) = self.alert_confidence_threshold {
+            config.capture.alert_confidence_threshold = threshold;
+        }
+    }
+}
+
+fn current_settings(config: &Config) -> String {
+    serde_json::json!({
+        "capture_interval_ms": config.capture.interval_ms,
+        "context_mode": config.storage.context_mode,
+        "alert_confidence_threshold": config.capture.alert_confidence_threshold,
+    })
+    .to_string()
+}
+
+/// manage_config 工具：get 读取可修改的设置；propose 提议修改，用户在界面上确认后才保存
+pub(super) async fn manage_config_tool(
+    storage: &StorageManager,
+    scope: &str,
+    args: &serde_json::Value,
+    cancel_token: Option<&CancellationToken>,
+    progress: Option<&ProgressEmitter>,
+) -> Result<String, AppError> {
+    let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("get");
+    let config = storage.load_config()?;
+    match action {
+        "get" => {
+            if let Some(progress) = progress {
+                progress.emit_step("读取设置".to_string(), None);
+            }
+            Ok(current_settings(&config))
+        }
+        "propose" => {
+            let changes = ConfigChanges::parse(args.get("changes"))?;
+            let lines = changes.describe(&config);
+            if lines.is_empty() {
+                return Ok("提议的设置与当前配置相同，无需修改。".to_string());
+            }
+            let Some(progress) = progress else {
+                return Ok("当前无法请求用户确认，设置未修改。".to_string());
+            };
+            progress.emit_step("请求修改设置".to_string(), Some(lines.join("；")));
+            let reason = args.get("reason").and_then(|v| v.as_str()).unwrap_or("").trim();
+            let confirmed = confirm_config_change(scope, &lines, reason, cancel_token, progress).await?;
+            if !confirmed {
+                return Ok("用户拒绝了设置修改，配置保持不变。".to_string());
+            }
+
+            // 确认期间用户可能在设置页改过配置，保存前重新读取
+            let mut config = storage.load_config()?;
+            changes.apply(&mut config);
+            apply_config(&progress.app_handle, config.clone())?;
+            let restarted = restart_capture_if_running(&progress.app_handle, config).await;
+            let suffix = if restarted { "，截屏已按新设置重启" } else { "" };
+            Ok(format!("用户已确认，设置已保存{}：\n{}", suffix, lines.join("\n")))
+        }
+        other => Ok(format!("未知操作: {}", other)),
+    }
+}
@@ -0,0 +1,301 @@
+use super::approval::confirm_file_change;
+use super::ProgressEmitter;
+use crate::storage::StorageManager;
+use chrono::Local;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+
+const DIFF_CONTEXT_LINES: usize = 3;
+const MAX_DIFF_CHARS: usize = 20_000;
+// 超过该规模不做逐行 LCS，整段按删除+新增处理
+const MAX_LCS_CELLS: usize = 1_000_000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// 文件修改的 unified diff
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub diff: String,
+    pub added: usize,
+    pub removed: usize,
+    pub truncated: bool,
+}
+
+impl FileDiff {
+    pub fn changed_lines(&self) -> usize {
+        self.added + self.removed
+    }
+}
+
+/// tool-diff 事件内容
+#[derive(Debug, Clone, serde::Serialize)]
+struct ToolDiffEvent<'a> {
+    request_id: &'a str,
+    tool: &'a str,
+    #[serde(flatten)]
+    diff: &'a FileDiff,
+}
+
+fn diff_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(DiffOp, &'a str)> {
+    let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<(DiffOp, &str)> = old[..prefix].iter().map(|line| (DiffOp::Equal, *line)).collect();
+    if (old_mid.len() + 1) * (new_mid.len() + 1) > MAX_LCS_CELLS {
+        ops.extend(old_mid.iter().map(|line| (DiffOp::Delete, *line)));
+        ops.extend(new_mid.iter().map(|line| (DiffOp::Insert, *line)));
+    } else {
+        // lcs[i][j]：old_mid[i..] 与 new_mid[j..] 的最长公共子序列长度
+        let cols = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * cols];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * cols + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * cols + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * cols + j].max(lcs[i * cols + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() && j < new_mid.len() {
+            if old_mid[i] == new_mid[j] {
+                ops.push((DiffOp::Equal, old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * cols + j] >= lcs[i * cols + j + 1] {
+                ops.push((DiffOp::Delete, old_mid[i]));
+                i += 1;
+            } else {
+                ops.push((DiffOp::Insert, new_mid[j]));
+                j += 1;
+            }
+        }
+        ops.extend(old_mid[i..].iter().map(|line| (DiffOp::Delete, *line)));
+        ops.extend(new_mid[j..].iter().map(|line| (DiffOp::Insert, *line)));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|line| (DiffOp::Equal, *line)));
+    ops
+}
+
+/// 生成 unified diff（上下文 3 行），过长时截断
+pub fn unified_diff(path: &str, old: &str, new: &str) -> FileDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+    let added = ops.iter().filter(|(op, _)| *op == DiffOp::Insert).count();
+    let removed = ops.iter().filter(|(op, _)| *op == DiffOp::Delete).count();
+
+    // 每个操作之前已经过的旧/新行数，用于 hunk 头
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut old_pos, mut new_pos) = (0usize, 0usize);
+    for (op, _) in &ops {
+        positions.push((old_pos, new_pos));
+        match op {
+            DiffOp::Equal => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            DiffOp::Delete => old_pos += 1,
+            DiffOp::Insert => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (idx, _) in ops.iter().enumerate().filter(|(_, (op, _))| *op != DiffOp::Equal) {
+        let start = idx.saturating_sub(DIFF_CONTEXT_LINES);
+        let end = (idx + DIFF_CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut text = String::new();
+    if !hunks.is_empty() {
+        text.push_str(&format!("--- a/{}\n+++ b/{}\n", path, path));
+    }
+    for (start, end) in hunks {
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        let header_start = |begin: usize, len: usize| if len == 0 { begin } else { begin + 1 };
+        text.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            header_start(old_start, old_end - old_start),
+            old_end - old_start,
+            header_start(new_start, new_end - new_start),
+            new_end - new_start
+        ));
+        for (op, line) in &ops[start..end] {
+            let sign = match op {
+                DiffOp::Equal => ' ',
+                DiffOp::Delete => '-',
+                DiffOp::Insert => '+',
+            };
+            text.push(sign);
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    let truncated = text.chars().count() > MAX_DIFF_CHARS;
+    if truncated {
+        text = text.chars().take(MAX_DIFF_CHARS).collect();
+        text.push_str("\n...(diff truncated)\n");
+    }
+    FileDiff {
+        path: path.to_string(),
+        diff: text,
+        added,
+        removed,
+        truncated,
+    }
+}
+
+/// 写入前的检查：发送 tool-diff 事件，改动超过 confirm_lines 行时等待用户确认
+pub(super) struct FileChangeReview<'a> {
+    pub scope: &'a str,
+    pub tool: &'a str,
+    pub confirm_lines: usize,  // 0 表示不需要确认
+    pub cancel_token: Option<&'a CancellationToken>,
+    pub progress: Option<&'a ProgressEmitter>,
+}
+
+impl FileChangeReview<'_> {
+    /// 返回 diff；用户拒绝时返回错误
+    pub async fn review(&self, path: &Path, old: &[u8], new: &[u8]) -> Result<FileDiff, String> {
+        let diff = unified_diff(
+            &path.display().to_string(),
+            &String::from_utf8_lossy(old),
+            &String::from_utf8_lossy(new),
+        );
+        let Some(progress) = self.progress else {
+            return Ok(diff);
+        };
+        if progress.enabled && !diff.diff.is_empty() {
+            let event = ToolDiffEvent {
+                request_id: &progress.request_id,
+                tool: self.tool,
+                diff: &diff,
+            };
+            let _ = progress.emit_event("tool-diff", event);
+        }
+        if self.confirm_lines > 0 && diff.changed_lines() > self.confirm_lines {
+            let approved = confirm_file_change(self.scope, self.tool, &diff, self.cancel_token, progress)
+                .await
+                .map_err(String::from)?;
+            if !approved {
+                return Err(format!("用户拒绝了对 {} 的修改", diff.path));
+            }
+        }
+        Ok(diff)
+    }
+
+    /// 写入前保存原内容，供 revert_file_change 回滚；original 为 None 表示新建文件
+    pub fn record_original(&self, path: &Path, original: Option<&[u8]>) -> Result<(), String> {
+        let request_id = self.progress.map_or("direct", |progress| progress.request_id.as_str());
+        StorageManager::new().record_file_change(request_id, self.scope, self.tool, path, original)?;
+        Ok(())
+    }
+}
+
+/// 先写同目录临时文件再重命名，避免写到一半时留下损坏的文件
+pub(super) fn write_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    let temp = dir.join(format!(
+        ".{}.{}-{}.tmp",
+        name,
+        std::process::id(),
+        Local::now().timestamp_millis()
+    ));
+    let result = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        drop(file);
+        if let Ok(meta) = fs::metadata(path) {
+            fs::set_permissions(&temp, meta.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("写入失败: {}", err));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_input_has_no_diff() {
+        let diff = unified_diff("a.txt", "", "");
+        assert!(diff.diff.is_empty());
+        assert_eq!((diff.added, diff.removed, diff.truncated), (0, 0, false));
+    }
+
+    #[test]
+    fn new_file_is_all_insertions() {
+        let diff = unified_diff("a.txt", "", "one\ntwo\n");
+        assert_eq!(diff.diff, "--- a/a.txt\n+++ b/a.txt\n@@ -0,0 +1,2 @@\n+one\n+two\n");
+        assert_eq!((diff.added, diff.removed), (2, 0));
+    }
+
+    #[test]
+    fn identical_input_has_no_diff() {
+        let text = "fn main() {\n    println!(\"hi\");\n}\n";
+        let diff = unified_diff("main.rs", text, text);
+        assert!(diff.diff.is_empty());
+        assert_eq!(diff.changed_lines(), 0);
+    }
+
+    #[test]
+    fn distant_changes_produce_separate_hunks() {
+        let old: Vec<String> = (1..=20).map(|n| format!("l{}", n)).collect();
+        let mut new = old.clone();
+        new[1] = "X".to_string();
+        new[17] = "Y".to_string();
+        let diff = unified_diff("f", &old.join("\n"), &new.join("\n"));
+        assert_eq!(diff.diff.matches("@@ -").count(), 2);
+        assert!(diff.diff.contains("@@ -1,5 +1,5 @@\n l1\n-l2\n+X\n l3\n l4\n l5\n"));
+        assert!(diff.diff.contains("@@ -15,6 +15,6 @@\n l15\n l16\n l17\n-l18\n+Y\n l19\n l20\n"));
+        assert_eq!((diff.added, diff.removed), (2, 2));
+    }
+
+    #[test]
+    fn large_diff_is_truncated_at_the_cap() {
+        let old: Vec<String> = (0..3000).map(|n| format!("old line {}", n)).collect();
+        let new: Vec<String> = (0..3000).map(|n| format!("new line {}", n)).collect();
+        let diff = unified_diff("big.txt", &old.join("\n"), &new.join("\n"));
+        assert!(diff.truncated);
+        assert!(diff.diff.ends_with("\n...(diff truncated)\n"));
+        assert!(diff.diff.chars().count() <= MAX_DIFF_CHARS + "\n...(diff truncated)\n".len());
+        // 截断只影响展示，统计仍是完整的
+        assert_eq!((diff.added, diff.removed), (3000, 3000));
+    }
+}
@@ -0,0 +1,296 @@
+use parking_lot::Mutex as ParkingMutex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+// 遍历目录时最多查看的条目数，超过后只展示已遍历部分
+const MAX_WALK_ENTRIES: usize = 3000;
+const MAX_TREE_LINES: usize = 150;
+const MAX_SELECTED_FILES: usize = 6;
+const MAX_SELECTED_FILE_CHARS: usize = 2500;
+// 超过该大小的文件不自动附带内容，仍可通过 read_attachment_file 读取
+const MAX_SELECTED_FILE_BYTES: u64 = 200 * 1024;
+const DEFAULT_READ_CHARS: usize = 20_000;
+// 附加目录在本次运行中保持可读的时长
+const ATTACHED_DIR_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+const ALWAYS_SKIPPED_DIRS: &[&str] = &[
+    ".git", ".svn", ".hg", "node_modules", "target", "dist", "build", ".venv", "venv", "__pycache__", ".idea", ".vscode",
+];
+const LOCK_FILES: &[&str] = &["Cargo.lock", "package-lock.json", "yarn.lock", "pnpm-lock.yaml", "poetry.lock"];
+const MANIFEST_FILES: &[&str] = &[
+    "Cargo.toml", "package.json", "pyproject.toml", "go.mod", "pom.xml", "build.gradle", "requirements.txt",
+    "Makefile", "Dockerfile", "tsconfig.json", "CMakeLists.txt",
+];
+const SOURCE_EXTS: &[&str] = &[
+    "rs", "ts", "tsx", "js", "jsx", "vue", "py", "go", "java", "kt", "c", "h", "cpp", "hpp", "cs", "rb", "php",
+    "swift", "sh", "ps1", "sql", "html", "css", "scss",
+];
+const DOC_EXTS: &[&str] = &["md", "txt", "rst"];
+const CONFIG_EXTS: &[&str] = &["toml", "json", "yaml", "yml", "ini", "cfg"];
+
+/// 用户附加过的目录，read_attachment_file 只能读取这些目录内的文件
+fn attached_dirs() -> &'static ParkingMutex<HashMap<PathBuf, Instant>> {
+    static DIRS: OnceLock<ParkingMutex<HashMap<PathBuf, Instant>>> = OnceLock::new();
+    DIRS.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+fn register_attached_dir(dir: &Path) {
+    let mut dirs = attached_dirs().lock();
+    dirs.retain(|_, at| at.elapsed() < ATTACHED_DIR_TTL);
+    dirs.insert(dir.to_path_buf(), Instant::now());
+}
+
+/// 仓库根目录 .gitignore 中的规则（不支持 ! 取反）
+struct IgnoreRule {
+    pattern: glob::Pattern,
+    dir_only: bool,
+    anchored: bool,  // 含 / 的规则按相对路径匹配，否则按文件名匹配
+}
+
+fn load_gitignore(root: &Path) -> Vec<IgnoreRule> {
+    let Ok(content) = fs::read_to_string(root.join(".gitignore")) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| {
+            let dir_only = line.ends_with('/');
+            let trimmed = line.trim_end_matches('/');
+            let anchored = trimmed.contains('/');
+            let pattern = glob::Pattern::new(trimmed.trim_start_matches('/')).ok()?;
+            Some(IgnoreRule {
+                pattern,
+                dir_only,
+                anchored,
+            })
+        })
+        .collect()
+}
+
+fn is_ignored(rules: &[IgnoreRule], relative: &str, name: &str, is_dir: bool) -> bool {
+    if is_dir && ALWAYS_SKIPPED_DIRS.contains(&name) {
+        return true;
+    }
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    rules.iter().any(|rule| {
+        if rule.dir_only && !is_dir {
+            return false;
+        }
+        if rule.anchored {
+            rule.pattern.matches_with(relative, options)
+        } else {
+            rule.pattern.matches(name)
+        }
+    })
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|comp| comp.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1}KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+struct DirFile {
+    relative: String,
+    size: u64,
+    depth: usize,
+}
+
+/// 按文件名/扩展名、目录深度和大小估计文件对理解项目的价值；返回 None 表示不自动附带
+fn file_priority(file: &DirFile) -> Option<i64> {
+    let name = file.relative.rsplit('/').next().unwrap_or(&file.relative);
+    if file.size == 0 || file.size > MAX_SELECTED_FILE_BYTES || LOCK_FILES.contains(&name) {
+        return None;
+    }
+    let lower = name.to_lowercase();
+    let ext = lower.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    let stem = lower.split('.').next().unwrap_or("");
+    let base = if lower.starts_with("readme") {
+        100
+    } else if MANIFEST_FILES.contains(&name) {
+        80
+    } else if SOURCE_EXTS.contains(&ext) && matches!(stem, "main" | "lib" | "index" | "app" | "mod") {
+        60
+    } else if SOURCE_EXTS.contains(&ext) {
+        40
+    } else if DOC_EXTS.contains(&ext) {
+        30
+    } else if CONFIG_EXTS.contains(&ext) {
+        20
+    } else {
+        return None;
+    };
+    let size_penalty = if file.size > 20 * 1024 { 20 } else { 0 };
+    Some(base - file.depth as i64 * 5 - size_penalty)
+}
+
+fn read_text_file(path: &Path, max_chars: usize) -> Result<(String, bool), String> {
+    let bytes = fs::read(path).map_err(|e| format!("读取失败: {}", e))?;
+    if bytes.iter().take(8192).any(|b| *b == 0) {
+        return Err("二进制文件，无法按文本读取".to_string());
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let truncated = text.chars().count() > max_chars;
+    Ok((text.chars().take(max_chars).collect(), truncated))
+}
+
+/// 把附加的目录整理成附件内容：目录结构（遵循 .gitignore）+ 最有代表性的几个文件；
+/// 同时登记该目录，之后模型可用 read_attachment_file 按需读取其他文件
+pub(super) fn summarize_directory(dir: &Path, name: &str) -> Result<String, String> {
+    let root = dir.canonicalize().map_err(|e| format!("无法访问目录: {}", e))?;
+    let rules = load_gitignore(&root);
+
+    let mut tree = Vec::new();
+    let mut files = Vec::new();
+    let mut dir_count = 0usize;
+    let mut truncated_walk = false;
+    let walker = WalkDir::new(&root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let relative = relative_path(&root, entry.path());
+            let entry_name = entry.file_name().to_string_lossy();
+            !is_ignored(&rules, &relative, &entry_name, entry.file_type().is_dir())
+        });
+    for (index, entry) in walker.filter_map(Result::ok).enumerate() {
+        if index >= MAX_WALK_ENTRIES {
+            truncated_walk = true;
+            break;
+        }
+        let depth = entry.depth() - 1;
+        let entry_name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type().is_dir() {
+            dir_count += 1;
+            tree.push(format!("{}{}/", "  ".repeat(depth), entry_name));
+        } else if entry.file_type().is_file() {
+            let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+            tree.push(format!("{}{} ({})", "  ".repeat(depth), entry_name, format_size(size)));
+            files.push(DirFile {
+                relative: relative_path(&root, entry.path()),
+                size,
+                depth,
+            });
+        }
+    }
+    register_attached_dir(&root);
+
+    let mut output = format!(
+        "### {}（目录 {}，{} 个文件，{} 个子目录{}）\n目录结构:\n",
+        name,
+        root.display(),
+        files.len(),
+        dir_count,
+        if truncated_walk { "，条目过多仅列出部分" } else { "" }
+    );
+    let hidden_lines = tree.len().saturating_sub(MAX_TREE_LINES);
+    for line in tree.into_iter().take(MAX_TREE_LINES) {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    if hidden_lines > 0 {
+        output.push_str(&format!("...(另有 {} 项未列出)\n", hidden_lines));
+    }
+
+    let mut ranked: Vec<(i64, &DirFile)> = files
+        .iter()
+        .filter_map(|file| file_priority(file).map(|score| (score, file)))
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.relative.cmp(&b.1.relative)));
+    for (_, file) in ranked.into_iter().take(MAX_SELECTED_FILES) {
+        let Ok((content, truncated)) = read_text_file(&root.join(&file.relative), MAX_SELECTED_FILE_CHARS) else {
+            continue;
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+        output.push_str(&format!("\n#### {}\n{}", file.relative, content.trim_end()));
+        if truncated {
+            output.push_str("\n...(已截断)");
+        }
+        output.push('\n');
+    }
+    output.push_str("\n其余文件可用 read_attachment_file 工具读取（path 为相对该目录的路径）。");
+    Ok(output)
+}
+
+/// read_attachment_file 工具：读取用户附加目录内的文本文件，不受工具模式限制，但不能读出目录之外
+pub(super) fn read_attachment_file_tool(args: &serde_json::Value) -> Result<String, String> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| "缺少 path 参数".to_string())?;
+    let dir_hint = args.get("dir").and_then(|v| v.as_str()).map(str::trim).filter(|v| !v.is_empty());
+    let max_chars = args
+        .get("max_chars")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_READ_CHARS, |v| (v as usize).clamp(1, DEFAULT_READ_CHARS));
+
+    let mut dirs: Vec<(PathBuf, Instant)> = {
+        let mut registered = attached_dirs().lock();
+        registered.retain(|_, at| at.elapsed() < ATTACHED_DIR_TTL);
+        registered.iter().map(|(dir, at)| (dir.clone(), *at)).collect()
+    };
+    if dirs.is_empty() {
+        return Err("当前没有附加的目录，请让用户先把文件夹作为附件发送".to_string());
+    }
+    // 最近附加的目录优先
+    dirs.sort_by(|a, b| b.1.cmp(&a.1));
+    if let Some(hint) = dir_hint {
+        dirs.retain(|(dir, _)| {
+            dir == Path::new(hint) || dir.file_name().map_or(false, |name| name.to_string_lossy() == hint)
+        });
+        if dirs.is_empty() {
+            return Err(format!("未找到附加的目录: {}", hint));
+        }
+    }
+
+    let requested = Path::new(path);
+    for (dir, _) in &dirs {
+        let candidate = if requested.is_absolute() {
+            requested.to_path_buf()
+        } else {
+            dir.join(requested)
+        };
+        let Ok(resolved) = candidate.canonicalize() else {
+            continue;
+        };
+        if !resolved.starts_with(dir) {
+            continue;
+        }
+        if resolved.is_dir() {
+            return Err(format!("{} 是目录，请指定文件", path));
+        }
+        let (content, truncated) = read_text_file(&resolved, max_chars)?;
+        let mut output = format!("{}\n{}", relative_path(dir, &resolved), content);
+        if truncated {
+            output.push_str(&format!("\n...(已截断，仅返回前 {} 个字符)", max_chars));
+        }
+        return Ok(output);
+    }
+    Err(format!("附加的目录中没有找到文件: {}", path))
+}
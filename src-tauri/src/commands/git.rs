@@ -0,0 +1,342 @@
+use super::diff::FileChangeReview;
+use super::{command_allowed, path_is_allowed, resolve_path, truncate_string, ToolAccess};
+use crate::error::AppError;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command as TokioCommand;
+use tokio::time::{timeout, Duration as TokioDuration};
+
+const GIT_TIMEOUT_MS: u64 = 30_000;
+const MAX_GIT_DIFF_CHARS: usize = 30_000;
+const DEFAULT_GIT_LOG_LIMIT: usize = 20;
+const MAX_GIT_LOG_LIMIT: usize = 200;
+// log 输出的字段/记录分隔符
+const FIELD_SEP: char = '\u{1f}';
+const RECORD_SEP: char = '\u{1e}';
+
+/// 只读操作，工作目录允许访问即可执行
+const READ_ACTIONS: &[&str] = &["status", "diff", "log", "branch"];
+
+/// 会修改仓库或工作区的操作；branch 带 create 参数时也算
+pub(super) fn is_write_action(action: &str, args: &Value) -> bool {
+    match action {
+        "branch" => args
+            .get("create")
+            .and_then(|v| v.as_str())
+            .map_or(false, |name| !name.trim().is_empty()),
+        _ => !READ_ACTIONS.contains(&action),
+    }
+}
+
+/// 白名单模式下写操作需要允许 git，或单独允许 git:<action>（如 git:add、git:commit）
+pub(super) fn write_permission_pattern(action: &str) -> String {
+    format!("git:{}", action)
+}
+
+fn git_write_allowed(access: &ToolAccess, action: &str) -> bool {
+    command_allowed(access, "git") || command_allowed(access, &write_permission_pattern(action))
+}
+
+pub(super) fn repo_dir(access: &ToolAccess, args: &Value) -> PathBuf {
+    match args.get("path").and_then(|v| v.as_str()).map(str::trim) {
+        Some(dir) if !dir.is_empty() => resolve_path(access, dir),
+        _ => access.base_dir.clone(),
+    }
+}
+
+fn string_list(args: &Value, key: &str) -> Vec<String> {
+    match args.get(key) {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        Some(Value::String(item)) if !item.trim().is_empty() => vec![item.trim().to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// 版本参数（提交、分支、范围）；不允许以 - 开头，避免被当成 git 选项
+fn rev_arg(args: &Value) -> Result<Option<String>, String> {
+    match args.get("rev").and_then(|v| v.as_str()).map(str::trim) {
+        Some(rev) if rev.starts_with('-') => Err(format!("无效的版本参数: {}", rev)),
+        Some(rev) if !rev.is_empty() => Ok(Some(rev.to_string())),
+        _ => Ok(None),
+    }
+}
+
+async fn run_git(dir: &Path, args: &[String]) -> Result<String, String> {
+    let mut cmd = TokioCommand::new("git");
+    cmd.arg("-c")
+        .arg("core.quotepath=false")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let output = timeout(TokioDuration::from_millis(GIT_TIMEOUT_MS), cmd.output())
+        .await
+        .map_err(|_| "git 命令超时".to_string())?
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                "未找到 git，请先安装 git 并加入 PATH".to_string()
+            } else {
+                format!("执行 git 失败: {}", e)
+            }
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        return Err(format!("git {} 失败: {}", args.first().map(String::as_str).unwrap_or(""), message));
+    }
+    Ok(stdout)
+}
+
+fn git_args(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+fn with_paths(mut args: Vec<String>, paths: &[String]) -> Vec<String> {
+    if !paths.is_empty() {
+        args.push("--".to_string());
+        args.extend(paths.iter().cloned());
+    }
+    args
+}
+
+/// "## main...origin/main [ahead 1, behind 2]"
+fn parse_branch_header(line: &str) -> Value {
+    let header = line.trim_start_matches("## ");
+    let (names, tracking) = match header.split_once(" [") {
+        Some((names, rest)) => (names, rest.trim_end_matches(']')),
+        None => (header, ""),
+    };
+    let (branch, upstream) = match names.split_once("...") {
+        Some((branch, upstream)) => (branch, Some(upstream)),
+        None => (names, None),
+    };
+    let count = |key: &str| {
+        tracking
+            .split(", ")
+            .find_map(|part| part.strip_prefix(key))
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+    json!({
+        "branch": branch.trim_start_matches("No commits yet on "),
+        "upstream": upstream,
+        "ahead": count("ahead "),
+        "behind": count("behind "),
+    })
+}
+
+async fn git_status(dir: &Path) -> Result<Value, String> {
+    let output = run_git(dir, &git_args(&["status", "--porcelain=v1", "-b"])).await?;
+    let mut lines = output.lines();
+    let mut result = lines.next().map(parse_branch_header).unwrap_or_else(|| json!({}));
+    let files: Vec<Value> = lines
+        .filter(|line| line.len() > 3)
+        .map(|line| {
+            let index = &line[..1];
+            let worktree = &line[1..2];
+            let path = line[3..].to_string();
+            let (path, from) = match path.split_once(" -> ") {
+                Some((from, to)) => (to.to_string(), Some(from.to_string())),
+                None => (path, None),
+            };
+            json!({
+                "path": path,
+                "renamed_from": from,
+                "index": index.trim(),
+                "worktree": worktree.trim(),
+                "untracked": index == "?",
+            })
+        })
+        .collect();
+    result["clean"] = json!(files.is_empty());
+    result["files"] = json!(files);
+    Ok(result)
+}
+
+async fn git_diff(dir: &Path, args: &Value) -> Result<Value, String> {
+    let staged = args.get("staged").and_then(|v| v.as_bool()).unwrap_or(false);
+    let paths = string_list(args, "paths");
+    let mut base = git_args(&["diff"]);
+    if staged {
+        base.push("--cached".to_string());
+    }
+    if let Some(rev) = rev_arg(args)? {
+        base.push(rev);
+    }
+    let mut numstat = base.clone();
+    numstat.push("--numstat".to_string());
+    let stat_output = run_git(dir, &with_paths(numstat, &paths)).await?;
+    let files: Vec<Value> = stat_output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let added = parts.next()?;
+            let removed = parts.next()?;
+            let path = parts.next()?;
+            Some(json!({
+                "path": path,
+                "added": added.parse::<u64>().ok(),  // 二进制文件为 null
+                "removed": removed.parse::<u64>().ok(),
+            }))
+        })
+        .collect();
+    let patch = run_git(dir, &with_paths(base, &paths)).await?;
+    let (diff, truncated) = truncate_string(patch.trim_end(), MAX_GIT_DIFF_CHARS);
+    Ok(json!({ "staged": staged, "files": files, "diff": diff, "truncated": truncated }))
+}
+
+async fn git_log(dir: &Path, args: &Value) -> Result<Value, String> {
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+        .unwrap_or(DEFAULT_GIT_LOG_LIMIT)
+        .clamp(1, MAX_GIT_LOG_LIMIT);
+    let format = format!("--pretty=format:%H{0}%an{0}%aI{0}%s{1}", FIELD_SEP, RECORD_SEP);
+    let mut log_args = vec!["log".to_string(), format!("-n{}", limit), format];
+    if let Some(rev) = rev_arg(args)? {
+        log_args.push(rev);
+    }
+    let output = run_git(dir, &with_paths(log_args, &string_list(args, "paths"))).await?;
+    let commits: Vec<Value> = output
+        .split(RECORD_SEP)
+        .map(|record| record.trim())
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            let mut fields = record.split(FIELD_SEP);
+            Some(json!({
+                "hash": fields.next()?,
+                "author": fields.next()?,
+                "date": fields.next()?,
+                "subject": fields.next().unwrap_or(""),
+            }))
+        })
+        .collect();
+    Ok(json!({ "commits": commits }))
+}
+
+async fn git_branch(dir: &Path, args: &Value) -> Result<Value, String> {
+    if let Some(name) = args.get("create").and_then(|v| v.as_str()).map(str::trim).filter(|v| !v.is_empty()) {
+        if name.starts_with('-') {
+            return Err(format!("无效的分支名: {}", name));
+        }
+        let mut branch_args = git_args(&["branch", name]);
+        if let Some(start) = rev_arg(args)? {
+            branch_args.push(start);
+        }
+        run_git(dir, &branch_args).await?;
+        return Ok(json!({ "created": name }));
+    }
+    let format = format!("--format=%(refname:short){0}%(HEAD){0}%(upstream:short)", FIELD_SEP);
+    let output = run_git(dir, &["branch".to_string(), format]).await?;
+    let branches: Vec<Value> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(FIELD_SEP);
+            let name = fields.next()?;
+            let current = fields.next().map_or(false, |head| head.trim() == "*");
+            let upstream = fields.next().map(str::trim).filter(|v| !v.is_empty());
+            Some(json!({ "name": name, "current": current, "upstream": upstream }))
+        })
+        .collect();
+    Ok(json!({ "branches": branches }))
+}
+
+async fn git_add(dir: &Path, args: &Value) -> Result<Value, String> {
+    let all = args.get("all").and_then(|v| v.as_bool()).unwrap_or(false);
+    let paths = string_list(args, "paths");
+    if paths.is_empty() && !all {
+        return Err("add 需要 paths 参数，或设置 all=true 暂存全部改动".to_string());
+    }
+    let add_args = if all { git_args(&["add", "-A"]) } else { git_args(&["add"]) };
+    run_git(dir, &with_paths(add_args, &paths)).await?;
+    git_status(dir).await
+}
+
+async fn git_commit(dir: &Path, args: &Value) -> Result<Value, String> {
+    let message = args
+        .get("message")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| "commit 需要 message 参数".to_string())?;
+    let output = run_git(dir, &git_args(&["commit", "-m", message])).await?;
+    let hash = run_git(dir, &git_args(&["rev-parse", "HEAD"])).await?;
+    Ok(json!({ "hash": hash.trim(), "output": output.trim() }))
+}
+
+async fn git_restore(dir: &Path, args: &Value, review: &FileChangeReview<'_>) -> Result<Value, String> {
+    let staged = args.get("staged").and_then(|v| v.as_bool()).unwrap_or(false);
+    let paths = string_list(args, "paths");
+    if paths.is_empty() {
+        return Err("restore 需要 paths 参数".to_string());
+    }
+    if !staged {
+        // 丢弃工作区改动前先存快照，之后可以用 revert_file_change 找回
+        for path in &paths {
+            let file = dir.join(path);
+            if file.is_file() {
+                let original = std::fs::read(&file).map_err(|e| format!("读取失败: {}", e))?;
+                review.record_original(&file, Some(&original))?;
+            }
+        }
+    }
+    let restore_args = if staged {
+        git_args(&["restore", "--staged"])
+    } else {
+        git_args(&["restore"])
+    };
+    run_git(dir, &with_paths(restore_args, &paths)).await?;
+    git_status(dir).await
+}
+
+/// git 工具：结构化输出，只读操作和写操作分开授权
+pub(super) async fn git_tool(
+    access: &ToolAccess,
+    args: &Value,
+    review: &FileChangeReview<'_>,
+) -> Result<String, AppError> {
+    if access.mode == "unset" {
+        return Err(AppError::ToolModeUnset);
+    }
+    let action = args
+        .get("action")
+        .and_then(|v| v.as_str())
+        .map(|v| v.trim().to_lowercase())
+        .ok_or_else(|| "缺少 action 参数".to_string())?;
+    let dir = repo_dir(access, args);
+    if access.mode == "whitelist" && !path_is_allowed(access, &dir) {
+        return Ok(format!("仓库目录不在允许范围内: {}", dir.display()));
+    }
+    if is_write_action(&action, args) && access.mode == "whitelist" && !git_write_allowed(access, &action) {
+        return Err(AppError::ToolDenied {
+            tool: "git".to_string(),
+            message: format!(
+                "git {} 会修改仓库，需要在允许的命令中加入 git 或 {}",
+                action,
+                write_permission_pattern(&action)
+            ),
+        });
+    }
+
+    let result = match action.as_str() {
+        "status" => git_status(&dir).await,
+        "diff" => git_diff(&dir, args).await,
+        "log" => git_log(&dir, args).await,
+        "branch" => git_branch(&dir, args).await,
+        "add" => git_add(&dir, args).await,
+        "commit" => git_commit(&dir, args).await,
+        "restore" => git_restore(&dir, args, review).await,
+        other => Err(format!("未知的 git 操作: {}", other)),
+    }?;
+    serde_json::to_string_pretty(&result).map_err(|e| AppError::from(format!("序列化 git 结果失败: {}", e)))
+}
@@ -1,9 +1,66 @@
-use crate::capture::CaptureManager;
-use crate::model::{is_transient_model_error, ChatWithToolsResult, ModelManager, ToolCall};
-use crate::skills::{Skill, SkillFrontmatterOverrides, SkillManager, SkillMetadata, SkillsWatcher};
+mod approval;
+mod automation;
+mod browser;
+mod capabilities;
+mod changes;
+mod config_tool;
+mod diff;
+mod dir_attachment;
+mod offline_queue;
+mod git;
+mod notification;
+mod plan;
+mod preview;
+mod tasks;
+mod url_attachment;
+mod voice;
+mod windows;
+
+pub use approval::*;
+pub use automation::*;
+pub use browser::*;
+pub use capabilities::*;
+pub use notification::{open_alert, run_alert_action};
+pub use offline_queue::{cancel_queued_chat, list_queued_chats, start_offline_queue_worker};
+pub use plan::*;
+pub use tasks::*;
+pub use voice::*;
+pub use windows::*;
+
+use changes::FileChange;
+use diff::{write_atomic, FileChangeReview};
+
+use crate::analysis::{
+    evaluate_alert_rules, validate_alert_rule, AppUsageStats, DigestPeriod, DigestRecord, ExperimentReport, RuleInput, SkillSuggestion,
+};
+use crate::assistant::{
+    active_context_packs, build_context_pack_section, validate_context_pack, ActiveContextPack,
+};
+use crate::capture::{
+    add_suppression_rule, capture_with_privacy, delete_suppression_rule, get_active_window, load_suppression_rules,
+    validate_capture_target, vision_unsupported, CaptureManager, FocusSession, GuardedCapture, SuppressionRule,
+};
+use crate::error::{AppError, TOOL_MODE_UNSET_ERROR};
+use crate::export::SessionImportResult;
+use crate::folder_watch::{apply_watch_folder_config, validate_watch_folder, WatchRun};
+use crate::logs::{self, LogLevel, LogSubscription};
+use crate::model::tokenizer::{token_counter_for_config, TokenCounter};
+use crate::model::{
+    retry_delay, with_usage_feature, ChatWithToolsResult, ModelManager, ToolCall,
+};
+use crate::skills::registry::RegistrySkill;
+use crate::skills::{
+    render_structured_output, split_structured_output, structured_output_instruction,
+    validate_against_schema, Skill, SkillFrontmatterOverrides, SkillManager, SkillMetadata,
+    SkillsWatcher,
+};
 use crate::storage::{
-    Config, SearchQuery, StorageConfig, StorageManager, SummaryRecord, TimeRange,
+    storage_actor, ActivityHeatmap, AgentPreset, AggregationGranularity, AlertRule, CalendarConfig, CaptureTarget, Config, RedactionConfig, RedactionReport, Redactor, UsageStats, Conversation, ConversationSummary, DoctorCheck, EncryptionStatus, ContextStrategy, ScreenshotTextMatch, SearchQuery, SearchResult, StorageConfig, StorageManager,
+    RetentionNotice, RetryPolicy, StoragePriority, StorageUsage, SummaryRecord, TimeRange, parse_time_expression_with, TimelineBucket, Workspace,
 };
+use crate::snippets::{snippets_tool, Snippet};
+use crate::tickets::TicketLink;
+use crate::workspaces::WorkspaceStatus;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use chrono::{Duration, Local, NaiveDateTime, TimeZone};
 use glob::glob;
@@ -18,7 +75,7 @@ use std::path::{Component, Path, PathBuf};
 use std::process::Stdio;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
-use tauri::{AppHandle, Emitter, Manager, State};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
 use tauri_plugin_shell::ShellExt;
 use tokio::process::Command as TokioCommand;
 use tokio::sync::Mutex as TokioMutex;
@@ -30,10 +87,11 @@ use zip::ZipArchive;
 pub struct AppState {
     pub capture_manager: Arc<TokioMutex<CaptureManager>>,
     pub storage_manager: Arc<StorageManager>,
-    pub request_cancellations: Arc<TokioMutex<HashMap<String, CancellationToken>>>,
+    pub request_cancellations: Arc<TokioMutex<HashMap<String, ActiveRequest>>>,
     pub skills_watcher: Mutex<Option<SkillsWatcher>>,
     pub skills_version: Arc<AtomicU64>,
     pub skills_cache: Arc<TokioMutex<SkillsSnapshotCache>>,
+    pub background_tasks: Arc<BackgroundTaskManager>,
 }
 
 #[derive(Default)]
@@ -41,20 +99,114 @@ pub struct SkillsSnapshotCache {
     pub initialized: bool,
     pub loaded_version: u64,
     pub skills: Vec<SkillMetadata>,
+    // 增量同步：每个 skill 最后变化时的版本和内容指纹，以及已删除 skill 的删除版本
+    pub changed_at: HashMap<String, (u64, String)>,
+    pub removed_at: HashMap<String, u64>,
+    pub base_version: u64,  // 早于该版本的增量无法还原，只能返回完整列表
+}
+
+const MAX_SKILL_TOMBSTONES: usize = 500;
+
+impl SkillsSnapshotCache {
+    /// 用新扫描的结果替换缓存，并记录相对上一版本的变化
+    fn replace(&mut self, version: u64, skills: Vec<SkillMetadata>) {
+        if !self.initialized {
+            self.base_version = version;
+        }
+        let mut seen = HashSet::new();
+        for skill in &skills {
+            let fingerprint = serde_json::to_string(skill).unwrap_or_default();
+            seen.insert(skill.name.clone());
+            let unchanged = self
+                .changed_at
+                .get(&skill.name)
+                .map_or(false, |(_, previous)| *previous == fingerprint);
+            if !unchanged {
+                self.changed_at.insert(skill.name.clone(), (version, fingerprint));
+            }
+            self.removed_at.remove(&skill.name);
+        }
+        let removed: Vec<String> = self
+            .changed_at
+            .keys()
+            .filter(|name| !seen.contains(*name))
+            .cloned()
+            .collect();
+        for name in removed {
+            self.changed_at.remove(&name);
+            self.removed_at.insert(name, version);
+        }
+        if self.removed_at.len() > MAX_SKILL_TOMBSTONES {
+            // 丢弃最早的删除记录，更早的版本改为返回完整列表
+            let mut versions: Vec<u64> = self.removed_at.values().copied().collect();
+            versions.sort_unstable();
+            let floor = versions[self.removed_at.len() - MAX_SKILL_TOMBSTONES];
+            self.removed_at.retain(|_, removed| *removed >= floor);
+            self.base_version = self.base_version.max(floor);
+        }
+        self.initialized = true;
+        self.loaded_version = version;
+        self.skills = skills;
+    }
+
+    fn delta_since(&self, since_version: u64) -> SkillsDelta {
+        if since_version == 0 || since_version < self.base_version {
+            return SkillsDelta {
+                version: self.loaded_version,
+                full: true,
+                upserted: self.skills.clone(),
+                removed: Vec::new(),
+            };
+        }
+        let mut removed: Vec<String> = self
+            .removed_at
+            .iter()
+            .filter(|(_, version)| **version > since_version)
+            .map(|(name, _)| name.clone())
+            .collect();
+        removed.sort();
+        SkillsDelta {
+            version: self.loaded_version,
+            full: false,
+            upserted: self
+                .skills
+                .iter()
+                .filter(|skill| {
+                    self.changed_at
+                        .get(&skill.name)
+                        .map_or(true, |(version, _)| *version > since_version)
+                })
+                .cloned()
+                .collect(),
+            removed,
+        }
+    }
+}
+
+/// skills 列表的增量；full 为 true 时 upserted 是完整列表，前端应整体替换
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SkillsDelta {
+    pub version: u64,
+    pub full: bool,
+    pub upserted: Vec<SkillMetadata>,
+    pub removed: Vec<String>,
 }
 
 const MIN_RECENT_DETAIL_RECORDS: usize = 20;
 const RELEASE_PAGE_URL: &str = "https://github.com/mypengpengli/OpenCowork/releases/latest";
-const TOOL_MODE_UNSET_ERROR: &str = "TOOLS_MODE_UNSET";
-const REQUEST_CANCELLED_ERROR: &str = "REQUEST_CANCELLED";
 const TOOL_ERROR_PREFIX: &str = "TOOL_ERROR:";
 const MAX_TOOL_LOOPS: usize = 999;
 const MAX_REPEAT_TOOL_LOOPS: usize = 3;
-const MODEL_MAX_RETRIES: usize = 2;
+// 主对话（非技能）的授权规则 scope
+const ASSISTANT_APPROVAL_SCOPE: &str = "assistant";
 const MODEL_MAX_CONTINUES: usize = 1;
+const REQUEST_TOKEN_TTL_SECS: i64 = 6 * 60 * 60;
 const MIN_HISTORY_MESSAGES_BEFORE_COMPRESSION: usize = 14;
 const MAX_PERSISTED_TOOL_CONTEXT_CHARS: usize = 3000;
+const HISTORY_SUMMARY_KEEP_RECENT: usize = 12;
+const TOOL_OUTPUT_SUMMARY_TRIGGER_CHARS: usize = 12_000;
 static BACKGROUND_TASK_COUNTER: AtomicU64 = AtomicU64::new(1);
+static REQUEST_GENERATION_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 const DEFAULT_MAX_READ_BYTES: usize = 200_000;
 const DEFAULT_MAX_GLOB_RESULTS: usize = 500;
@@ -74,6 +226,7 @@ impl AppState {
             skills_watcher: Mutex::new(None),
             skills_version: Arc::new(AtomicU64::new(1)),
             skills_cache: Arc::new(TokioMutex::new(SkillsSnapshotCache::default())),
+            background_tasks: background_task_manager(),
         }
     }
 
@@ -151,10 +304,60 @@ fn windows_ui_is_zh() -> Option<bool> {
     Some(primary_lang == 0x04)
 }
 
+/// 保存设置页提交的配置；设置页只提交它管理的字段，其余字段沿用当前配置
 #[tauri::command]
-pub async fn save_config(config: Config) -> Result<(), String> {
+pub async fn save_config(config: serde_json::Value, app_handle: AppHandle) -> Result<(), String> {
+    let current = StorageManager::new().load_config().map_err(|e| e.to_string())?;
+    apply_config(&app_handle, current.merged_with(config)?)
+}
+
+/// 校验并保存配置，同时让热键、API 服务等子系统按新配置生效；自动切换配置方案时也走这里
+pub(crate) fn apply_config(app_handle: &AppHandle, mut config: Config) -> Result<(), String> {
     let storage = StorageManager::new();
-    storage.save_config(&config).map_err(|e| e.to_string())
+    let previous = storage.load_config().ok();
+    for rule in &config.capture.alert_rules {
+        validate_alert_rule(rule)?;
+    }
+    validate_capture_target(&config.capture.target)?;
+    for pack in &config.context_packs.items {
+        validate_context_pack(pack)?;
+    }
+    for folder in &config.watch_folders.items {
+        validate_watch_folder(folder)?;
+    }
+    Redactor::new(&config.redaction)?;
+    crate::profile_schedule::validate_profile_schedule(&config.profile_schedule)?;
+    config.calendar.time_options()?;
+    if config.api_server.enabled && config.api_server.token.trim().is_empty() {
+        config.api_server.token = crate::server::generate_token();
+    }
+    storage.save_config(&config).map_err(|e| e.to_string())?;
+    if previous.map_or(true, |prev| prev.hotkeys != config.hotkeys) {
+        for err in crate::hotkeys::register_hotkeys(app_handle, &config.hotkeys) {
+            eprintln!("{}", err);
+        }
+    }
+    crate::server::apply_api_server_config(app_handle, &config.api_server);
+    apply_watch_folder_config(app_handle, &config.watch_folders);
+    crate::faults::apply_fault_config(&config.faults);
+    crate::metrics::apply_metrics_config(&config.metrics);
+    crate::clipboard::apply_clipboard_config(&config.clipboard);
+    crate::storage::apply_audit_config(&config.audit);
+    crate::capture::apply_capture_indicator_config(app_handle, &config.capture.indicator);
+    crate::storage::apply_redaction_config(&config.redaction)?;
+    Ok(())
+}
+
+/// 截屏正在运行时按新配置重启，返回是否重启
+pub(crate) async fn restart_capture_if_running(app_handle: &AppHandle, config: Config) -> bool {
+    let state = app_handle.state::<AppState>();
+    let mut manager = state.capture_manager.lock().await;
+    if !manager.is_running() {
+        return false;
+    }
+    manager.stop().await;
+    manager.start(config, app_handle.clone()).await;
+    true
 }
 
 #[tauri::command]
@@ -186,7 +389,7 @@ pub async fn delete_profile(name: String) -> Result<(), String> {
 #[tauri::command]
 pub async fn test_model_connection(config: Config) -> Result<(), String> {
     let model_manager = ModelManager::new();
-    model_manager.test_connection(&config.model).await
+    Ok(model_manager.test_connection(&config.model).await?)
 }
 
 #[tauri::command]
@@ -196,12 +399,91 @@ pub async fn start_capture(
 ) -> Result<(), String> {
     let storage = StorageManager::new();
     let config = storage.load_config().map_err(|e| e.to_string())?;
+    storage.ensure_unlocked()?;
+
+    if config.model.provider == "ollama" {
+        let capture_model = ModelManager::resolve_model_config(
+            &config.model,
+            Some(config.model.capture_profile.as_str()),
+        );
+        let warm_up_app = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = run_model_warm_up(&warm_up_app, &capture_model).await;
+        });
+    }
 
     let mut manager = state.capture_manager.lock().await;
     manager.start(config, app_handle).await;
     Ok(())
 }
 
+#[derive(Clone, serde::Serialize)]
+struct ModelWarmupEvent {
+    model: String,
+    stage: String, // loading | ready | error
+    elapsed_secs: u64,
+    message: String,
+}
+
+/// 预热模型并通过 model-warmup 事件汇报加载进度
+async fn run_model_warm_up(
+    app_handle: &AppHandle,
+    model_config: &crate::storage::ModelConfig,
+) -> Result<(), String> {
+    if model_config.provider != "ollama" {
+        return Ok(());
+    }
+    let model = model_config.ollama.model.clone();
+    let started = std::time::Instant::now();
+    let emit = |stage: &str, message: String| {
+        let _ = app_handle.emit(
+            "model-warmup",
+            ModelWarmupEvent {
+                model: model.clone(),
+                stage: stage.to_string(),
+                elapsed_secs: started.elapsed().as_secs(),
+                message,
+            },
+        );
+    };
+
+    emit("loading", format!("正在加载模型 {}", model));
+    let model_manager = ModelManager::new();
+    let warm_up = model_manager.warm_up_model(model_config);
+    tokio::pin!(warm_up);
+    let mut ticker = tokio::time::interval(TokioDuration::from_secs(5));
+    ticker.tick().await;
+    let result = loop {
+        tokio::select! {
+            result = &mut warm_up => break result,
+            _ = ticker.tick() => {
+                emit("loading", format!("模型 {} 加载中，已用时 {} 秒", model, started.elapsed().as_secs()));
+            }
+        }
+    };
+    match result {
+        Ok(_) => {
+            emit("ready", format!("模型 {} 已就绪", model));
+            Ok(())
+        }
+        Err(err) => {
+            emit("error", err.to_string());
+            Err(err.into())
+        }
+    }
+}
+
+/// 手动预热模型；model 为空时预热截屏分析使用的模型
+#[tauri::command]
+pub async fn warm_up_model(app_handle: AppHandle, model: Option<String>) -> Result<(), String> {
+    let config = StorageManager::new().load_config()?;
+    let name = model
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| config.model.capture_profile.clone());
+    let model_config = ModelManager::resolve_model_config(&config.model, Some(name.as_str()));
+    run_model_warm_up(&app_handle, &model_config).await
+}
+
 #[tauri::command]
 pub async fn stop_capture(state: State<'_, AppState>) -> Result<(), String> {
     let mut manager = state.capture_manager.lock().await;
@@ -209,21 +491,155 @@ pub async fn stop_capture(state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// 开始专注时段；minutes 为番茄钟时长，到时自动结束并发送 focus-session-ended 事件
+#[tauri::command]
+pub async fn start_focus_session(
+    goal: String,
+    minutes: Option<u32>,
+    app_handle: AppHandle,
+) -> Result<FocusSession, String> {
+    let session = crate::capture::start_focus_session(&goal, minutes)?;
+    if let Some(minutes) = session.planned_minutes {
+        let id = session.id.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(TokioDuration::from_secs(minutes as u64 * 60)).await;
+            let ended = storage_actor()
+                .run(StoragePriority::Background, move |storage| {
+                    crate::capture::end_focus_session(storage, Some(&id))
+                })
+                .await;
+            match ended {
+                Ok(Some(session)) => {
+                    let _ = app_handle.emit("focus-session-ended", session);
+                }
+                Ok(None) => {}
+                Err(err) => logs::warn("focus", format!("结束专注时段失败: {}", err)),
+            }
+        });
+    }
+    Ok(session)
+}
+
+/// 提前结束当前专注时段，返回带小结的记录；没有进行中的时段时返回 None
+#[tauri::command]
+pub async fn end_focus_session(app_handle: AppHandle) -> Result<Option<FocusSession>, String> {
+    let ended = storage_actor()
+        .run(StoragePriority::Interactive, |storage| crate::capture::end_focus_session(storage, None))
+        .await?;
+    if let Some(session) = &ended {
+        let _ = app_handle.emit("focus-session-ended", session.clone());
+    }
+    Ok(ended)
+}
+
+#[tauri::command]
+pub async fn get_focus_session() -> Result<Option<FocusSession>, String> {
+    Ok(crate::capture::current_focus_session())
+}
+
+/// 已结束的专注时段，最近的在前
+#[tauri::command]
+pub async fn list_focus_sessions(limit: Option<usize>) -> Result<Vec<FocusSession>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            let mut sessions = storage.list_focus_sessions()?;
+            sessions.reverse();
+            sessions.truncate(limit.unwrap_or(50));
+            Ok(sessions)
+        })
+        .await
+}
+
+/// 立即截屏并分析当前屏幕
+#[tauri::command]
+pub async fn capture_now(state: State<'_, AppState>, app_handle: AppHandle) -> Result<bool, String> {
+    let storage = StorageManager::new();
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+    storage.ensure_unlocked()?;
+    let task = {
+        let manager = state.capture_manager.lock().await;
+        manager.capture_now_task(config, app_handle)
+    };
+    task.await
+}
+
+/// 按指定范围立即截屏并分析一次；不传 target 时使用配置中的截屏范围
+#[tauri::command]
+pub async fn capture_region_now(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+    target: Option<CaptureTarget>,
+) -> Result<bool, String> {
+    let storage = StorageManager::new();
+    let mut config = storage.load_config().map_err(|e| e.to_string())?;
+    storage.ensure_unlocked()?;
+    if let Some(target) = target {
+        validate_capture_target(&target)?;
+        config.capture.target = target;
+    }
+    let task = {
+        let manager = state.capture_manager.lock().await;
+        manager.capture_now_task(config, app_handle)
+    };
+    task.await
+}
+
+/// 手动显示/隐藏截屏指示器，返回指示器当前是否可见
+#[tauri::command]
+pub async fn set_capture_indicator(app_handle: AppHandle, visible: bool) -> Result<bool, String> {
+    crate::capture::set_capture_indicator_manual(&app_handle, visible)
+}
+
+/// 切换截屏状态，返回切换后是否正在截屏
+#[tauri::command]
+pub async fn toggle_capture(state: State<'_, AppState>, app_handle: AppHandle) -> Result<bool, String> {
+    let storage = StorageManager::new();
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+
+    let mut manager = state.capture_manager.lock().await;
+    if manager.is_running() {
+        manager.stop().await;
+    } else {
+        storage.ensure_unlocked()?;
+        manager.start(config, app_handle.clone()).await;
+    }
+    let is_capturing = manager.is_running();
+    let _ = app_handle.emit("capture-status-changed", is_capturing);
+    Ok(is_capturing)
+}
+
 #[tauri::command]
-pub async fn get_capture_status(state: State<'_, AppState>) -> Result<CaptureStatus, String> {
+pub async fn get_capture_status(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<CaptureStatus, String> {
+    let config = StorageManager::new().load_config().map_err(|e| e.to_string())?;
     let manager = state.capture_manager.lock().await;
     Ok(CaptureStatus {
         is_capturing: manager.is_running(),
         record_count: manager.get_count(),
         last_capture_time: None,
+        budget: manager.budget_status(&config),
+        indicator_visible: crate::capture::capture_indicator_visible(&app_handle),
     })
 }
 
+/// 汇总当前模型、工具权限、技能和截屏状态，与注入系统提示词的内容一致
+#[tauri::command]
+pub async fn get_capabilities(state: State<'_, AppState>) -> Result<Capabilities, String> {
+    let storage = StorageManager::new();
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+    let skill_manager = SkillManager::new();
+    let skills = get_available_skills_cached(&state, &skill_manager).await;
+    let capture_running = state.capture_manager.lock().await.is_running();
+    Ok(collect_capabilities(&config, &storage, &skills, capture_running))
+}
+
 #[tauri::command]
 pub async fn cancel_request(state: State<'_, AppState>, request_id: String) -> Result<(), String> {
     let token = {
         let map = state.request_cancellations.lock().await;
-        map.get(&request_id).cloned()
+        map.get(&request_id).map(|entry| entry.token.clone())
     };
     if let Some(token) = token {
         token.cancel();
@@ -231,14 +647,112 @@ pub async fn cancel_request(state: State<'_, AppState>, request_id: String) -> R
     Ok(())
 }
 
+/// 进行中的 chat / skill 请求（任务管理器视图）
+#[tauri::command]
+pub async fn get_active_requests(
+    state: State<'_, AppState>,
+) -> Result<Vec<ActiveRequestInfo>, String> {
+    let mut map = state.request_cancellations.lock().await;
+    let now = Local::now();
+    sweep_expired_requests(&mut map, now);
+    let mut list: Vec<ActiveRequestInfo> = map
+        .iter()
+        .map(|(request_id, entry)| {
+            let (stage, message, current_tool, updated_at) = match entry.status.lock() {
+                Ok(status) => (
+                    status.stage.clone(),
+                    status.message.clone(),
+                    status.current_tool.clone(),
+                    status.updated_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                ),
+                Err(_) => (String::new(), String::new(), None, String::new()),
+            };
+            ActiveRequestInfo {
+                request_id: request_id.clone(),
+                kind: entry.kind.clone(),
+                label: entry.label.clone(),
+                started_at: entry.started_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                elapsed_ms: now
+                    .signed_duration_since(entry.started_at)
+                    .num_milliseconds()
+                    .max(0) as u64,
+                cancelled: entry.token.is_cancelled(),
+                stage,
+                message,
+                current_tool,
+                updated_at,
+            }
+        })
+        .collect();
+    list.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(list)
+}
+
+#[derive(serde::Serialize)]
+pub struct ActiveRequestInfo {
+    pub request_id: String,
+    pub kind: String,
+    pub label: String,
+    pub started_at: String,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+    pub stage: String,
+    pub message: String,
+    pub current_tool: Option<String>,
+    pub updated_at: String,
+}
+
+#[tauri::command]
+pub async fn list_mcp_servers() -> Result<Vec<crate::mcp::McpServerStatus>, String> {
+    let storage = StorageManager::new();
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+    crate::mcp::sync_servers(&config.tools).await;
+    Ok(crate::mcp::server_statuses(&config.tools).await)
+}
+
+#[tauri::command]
+pub async fn reload_mcp_servers() -> Result<Vec<crate::mcp::McpServerStatus>, String> {
+    let storage = StorageManager::new();
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+    crate::mcp::reload_servers(&config.tools).await;
+    Ok(crate::mcp::server_statuses(&config.tools).await)
+}
+
+#[tauri::command]
+pub async fn list_background_tasks(
+    state: State<'_, AppState>,
+) -> Result<Vec<BackgroundTaskInfo>, String> {
+    Ok(state.background_tasks.list())
+}
+
+#[tauri::command]
+pub async fn get_task_output(
+    state: State<'_, AppState>,
+    task_id: String,
+    max_bytes: Option<u64>,
+) -> Result<String, String> {
+    let info = state
+        .background_tasks
+        .get(&task_id)
+        .ok_or_else(|| format!("background task not found: {}", task_id))?;
+    read_task_output(&info, max_bytes)
+}
+
+#[tauri::command]
+pub async fn kill_background_task(state: State<'_, AppState>, task_id: String) -> Result<(), String> {
+    state.background_tasks.kill(&task_id)
+}
+
 #[derive(serde::Serialize)]
 pub struct CaptureStatus {
     pub is_capturing: bool,
     pub record_count: u64,
     pub last_capture_time: Option<String>,
+    pub budget: crate::capture::CaptureBudgetStatus,  // 分析次数/费用预算与待补分析数量
+    pub indicator_visible: bool,
 }
 
-#[derive(serde::Deserialize, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct ChatHistoryMessage {
     pub role: String,
     pub content: String,
@@ -262,6 +776,17 @@ pub struct ChatResponse {
     pub tool_context: Vec<ToolContextMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub active_skill: Option<String>,
+    /// skill 声明了 output-schema 时解析出的结构化结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_output: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_output_error: Option<String>,
+    /// 预演模式下被拦截、未实际执行的操作
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub planned_actions: Vec<PlannedAction>,
+    /// 无法连接模型时加入离线队列的请求 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued_request: Option<String>,
 }
 
 #[derive(serde::Serialize, Clone)]
@@ -274,7 +799,7 @@ pub struct ToolContextMessage {
     pub tool_calls: Option<Vec<ToolCallInfo>>,
 }
 
-#[derive(serde::Deserialize, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct AttachmentInput {
     pub path: String,
     #[serde(default)]
@@ -283,6 +808,19 @@ pub struct AttachmentInput {
     pub kind: Option<String>,
 }
 
+/// chat_with_assistant 的可选参数，前端按 camelCase 传入，缺省字段取默认值
+#[derive(serde::Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ChatOptions {
+    pub history: Option<Vec<ChatHistoryMessage>>,
+    pub attachments: Option<Vec<AttachmentInput>>,
+    pub request_id: Option<String>,
+    pub model: Option<String>,  // 命名模型配置或具体模型 ID
+    pub context_strategy: Option<String>,
+    pub plan_only: Option<bool>,
+    pub preset: Option<String>,
+}
+
 #[derive(Default)]
 struct AttachmentPayload {
     text: String,
@@ -359,11 +897,19 @@ struct ProgressEmitter {
     app_handle: AppHandle,
     request_id: String,
     enabled: bool,
+    status: Option<Arc<Mutex<RequestStatus>>>,
+    window: Option<String>,  // 发起请求的窗口，为空时广播给所有窗口
 }
 
 impl ProgressEmitter {
-    fn new(app_handle: &AppHandle, enabled: bool, request_id: Option<String>) -> Option<Self> {
-        if !enabled {
+    /// 关闭进度显示时仍返回 emitter，只更新请求状态（供 get_active_requests 使用），不发送事件
+    fn new(
+        app_handle: &AppHandle,
+        enabled: bool,
+        request_id: Option<String>,
+        status: Option<Arc<Mutex<RequestStatus>>>,
+    ) -> Option<Self> {
+        if !enabled && status.is_none() {
             return None;
         }
         let request_id =
@@ -371,11 +917,33 @@ impl ProgressEmitter {
         Some(Self {
             app_handle: app_handle.clone(),
             request_id,
-            enabled: true,
+            enabled,
+            status,
+            window: None,
         })
     }
 
+    /// 事件只发给指定窗口（多窗口时各窗口只收到自己请求的进度）
+    fn routed_to(mut self, window: Option<String>) -> Self {
+        self.window = window;
+        self
+    }
+
+    fn emit_event<S: serde::Serialize + Clone>(&self, event: &str, payload: S) -> tauri::Result<()> {
+        match &self.window {
+            Some(label) => self.app_handle.emit_to(label.as_str(), event, payload),
+            None => self.app_handle.emit(event, payload),
+        }
+    }
+
     fn emit(&self, stage: &str, message: String, detail: Option<String>) {
+        if let Some(status) = &self.status {
+            if let Ok(mut status) = status.lock() {
+                status.stage = stage.to_string();
+                status.message = message.clone();
+                status.updated_at = Local::now();
+            }
+        }
         if !self.enabled {
             return;
         }
@@ -386,7 +954,7 @@ impl ProgressEmitter {
             detail,
             timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
         };
-        let _ = self.app_handle.emit("assistant-progress", event);
+        let _ = self.emit_event("assistant-progress", event);
     }
 
     fn emit_start(&self, message: &str) {
@@ -408,18 +976,149 @@ impl ProgressEmitter {
     fn emit_error(&self, message: &str) {
         self.emit("error", message.to_string(), None);
     }
+
+    fn set_current_tool(&self, tool: Option<&str>) {
+        if let Some(status) = &self.status {
+            if let Ok(mut status) = status.lock() {
+                status.current_tool = tool.map(|t| t.to_string());
+            }
+        }
+    }
 }
 
-async fn register_cancel_token(state: &State<'_, AppState>, request_id: &str) -> CancellationToken {
-    let mut map = state.request_cancellations.lock().await;
-    map.entry(request_id.to_string())
-        .or_insert_with(CancellationToken::new)
-        .clone()
+/// 请求当前所处阶段（由 ProgressEmitter 更新）
+pub struct RequestStatus {
+    pub stage: String,
+    pub message: String,
+    pub current_tool: Option<String>,
+    pub updated_at: chrono::DateTime<Local>,
 }
 
-async fn clear_cancel_token(state: &State<'_, AppState>, request_id: &str) {
-    let mut map = state.request_cancellations.lock().await;
-    map.remove(request_id);
+/// 进行中的请求（chat / skill），用于取消和调试
+pub struct ActiveRequest {
+    pub token: CancellationToken,
+    pub kind: String,
+    pub label: String,
+    pub started_at: chrono::DateTime<Local>,
+    pub status: Arc<Mutex<RequestStatus>>,
+    generation: u64,  // 条目创建时分配，超时清理后同 ID 重新注册的条目与旧持有者区分开
+    holders: usize,  // 共用该条目的调用数，全部结束后才删除
+}
+
+/// 请求取消令牌的持有者：正常结束时调用 release，panic 或 future 被丢弃时由 Drop 兜底清理
+struct CancelGuard {
+    map: Arc<TokioMutex<HashMap<String, ActiveRequest>>>,
+    request_id: String,
+    generation: u64,
+    token: CancellationToken,
+    status: Arc<Mutex<RequestStatus>>,
+    released: bool,
+}
+
+impl CancelGuard {
+    fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    fn status(&self) -> Arc<Mutex<RequestStatus>> {
+        Arc::clone(&self.status)
+    }
+
+    async fn release(mut self) {
+        let mut map = self.map.lock().await;
+        remove_request_entry(&mut map, &self.request_id, self.generation);
+        self.released = true;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        if let Ok(mut map) = self.map.try_lock() {
+            remove_request_entry(&mut map, &self.request_id, self.generation);
+            return;
+        }
+        let map = Arc::clone(&self.map);
+        let request_id = std::mem::take(&mut self.request_id);
+        let generation = self.generation;
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                let mut map = map.lock().await;
+                remove_request_entry(&mut map, &request_id, generation);
+            });
+        }
+    }
+}
+
+fn remove_request_entry(
+    map: &mut HashMap<String, ActiveRequest>,
+    request_id: &str,
+    generation: u64,
+) {
+    let Some(entry) = map.get_mut(request_id) else {
+        return;
+    };
+    if entry.generation != generation {
+        return;
+    }
+    entry.holders = entry.holders.saturating_sub(1);
+    if entry.holders == 0 {
+        map.remove(request_id);
+    }
+}
+
+fn sweep_expired_requests(map: &mut HashMap<String, ActiveRequest>, now: chrono::DateTime<Local>) {
+    let ttl = Duration::seconds(REQUEST_TOKEN_TTL_SECS);
+    map.retain(|request_id, entry| {
+        let expired = now.signed_duration_since(entry.started_at) > ttl;
+        if expired {
+            eprintln!("[request] sweep expired request token: {}", request_id);
+            entry.token.cancel();
+        }
+        !expired
+    });
+}
+
+async fn register_cancel_token(
+    state: &State<'_, AppState>,
+    request_id: &str,
+    kind: &str,
+    label: &str,
+) -> CancelGuard {
+    let map_ref = Arc::clone(&state.request_cancellations);
+    let mut map = map_ref.lock().await;
+    sweep_expired_requests(&mut map, Local::now());
+    let entry = map.entry(request_id.to_string()).or_insert_with(|| ActiveRequest {
+        token: CancellationToken::new(),
+        kind: kind.to_string(),
+        label: truncate_string(label.trim(), 80).0,
+        started_at: Local::now(),
+        status: Arc::new(Mutex::new(RequestStatus {
+            stage: "start".to_string(),
+            message: String::new(),
+            current_tool: None,
+            updated_at: Local::now(),
+        })),
+        generation: REQUEST_GENERATION_COUNTER.fetch_add(1, Ordering::SeqCst),
+        holders: 0,
+    });
+    // 同一 request_id 重复注册时共用取消令牌并增加引用计数，
+    // 先结束的调用释放时不会删掉仍在运行的调用的条目
+    entry.holders += 1;
+    let token = entry.token.clone();
+    let status = Arc::clone(&entry.status);
+    let generation = entry.generation;
+    drop(map);
+    CancelGuard {
+        map: map_ref,
+        request_id: request_id.to_string(),
+        generation,
+        token,
+        status,
+        released: false,
+    }
 }
 
 async fn get_available_skills_cached(
@@ -436,68 +1135,60 @@ async fn get_available_skills_cached(
 
     let discovered = skill_manager.discover_skills().unwrap_or_default();
     let mut cache = state.skills_cache.lock().await;
-    cache.initialized = true;
-    cache.loaded_version = version;
-    cache.skills = discovered.clone();
+    cache.replace(version, discovered.clone());
     discovered
 }
 
-fn check_cancel(cancel_token: Option<&CancellationToken>) -> Result<(), String> {
+fn check_cancel(cancel_token: Option<&CancellationToken>) -> Result<(), AppError> {
     if let Some(token) = cancel_token {
         if token.is_cancelled() {
-            return Err(REQUEST_CANCELLED_ERROR.to_string());
+            return Err(AppError::Cancelled);
         }
     }
     Ok(())
 }
 
-async fn await_with_cancel<T, F>(token: &CancellationToken, fut: F) -> Result<T, String>
+async fn await_with_cancel<T, E, F>(token: &CancellationToken, fut: F) -> Result<T, AppError>
 where
-    F: Future<Output = Result<T, String>>,
+    E: Into<AppError>,
+    F: Future<Output = Result<T, E>>,
 {
     tokio::select! {
-        _ = token.cancelled() => Err(REQUEST_CANCELLED_ERROR.to_string()),
-        result = fut => result,
-    }
-}
-
-fn should_retry_model_error(err: &str) -> bool {
-    if err == REQUEST_CANCELLED_ERROR || err == TOOL_MODE_UNSET_ERROR {
-        return false;
+        _ = token.cancelled() => Err(AppError::Cancelled),
+        result = fut => result.map_err(Into::into),
     }
-    is_transient_model_error(err)
 }
 
 async fn retry_with_cancel<T, F, Fut>(
     token: &CancellationToken,
     progress: Option<&ProgressEmitter>,
+    policy: &RetryPolicy,
     label: &str,
     mut make_fut: F,
-) -> Result<T, String>
+) -> Result<T, AppError>
 where
     F: FnMut() -> Fut,
-    Fut: Future<Output = Result<T, String>>,
+    Fut: Future<Output = Result<T, AppError>>,
 {
     let mut attempt = 0usize;
     loop {
         let result = await_with_cancel(token, make_fut()).await;
         match result {
             Ok(value) => return Ok(value),
+            Err(AppError::Cancelled) => return Err(AppError::Cancelled),
             Err(err) => {
-                if err == REQUEST_CANCELLED_ERROR {
-                    return Err(err);
-                }
                 attempt += 1;
-                if attempt > MODEL_MAX_RETRIES || !should_retry_model_error(&err) {
-                    return Err(err);
-                }
+                let delay = match retry_delay(policy, attempt, &err) {
+                    Some(delay) => delay,
+                    None => return Err(err),
+                };
                 if let Some(progress) = progress {
                     progress.emit_info(
-                        format!("Retrying {} ({}/{})", label, attempt, MODEL_MAX_RETRIES),
-                        Some(err.clone()),
+                        format!("Retrying {} ({}/{})", label, attempt, policy.max_retries),
+                        Some(err.to_string()),
                     );
                 }
-                sleep(TokioDuration::from_millis(400 * attempt as u64)).await;
+                sleep(delay).await;
             }
         }
     }
@@ -525,27 +1216,15 @@ fn response_looks_incomplete(text: &str) -> bool {
     (short && (ends_with_colon || ends_with_ellipsis)) || unbalanced_fence || engine_hint
 }
 
-fn estimate_text_tokens(text: &str) -> usize {
-    let mut ascii_chars = 0usize;
-    let mut non_ascii_chars = 0usize;
-    for ch in text.chars() {
-        if ch.is_ascii() {
-            ascii_chars += 1;
-        } else {
-            non_ascii_chars += 1;
-        }
-    }
-    (ascii_chars + 3) / 4 + non_ascii_chars + 1
-}
-
 fn estimate_history_tokens(
+    counter: &dyn TokenCounter,
     system_prompt: &str,
     user_message: &str,
     history: &[ChatHistoryMessage],
 ) -> usize {
-    let mut total = estimate_text_tokens(system_prompt) + estimate_text_tokens(user_message) + 24;
+    let mut total = counter.count(system_prompt) + counter.count(user_message) + 24;
     for msg in history {
-        total += estimate_text_tokens(&msg.content) + 8;
+        total += counter.count(&msg.content) + 8;
     }
     total
 }
@@ -586,57 +1265,209 @@ fn build_history_compression_summary(history: &[ChatHistoryMessage], max_chars:
     summary
 }
 
-fn compress_history_if_needed(
-    history: Option<Vec<ChatHistoryMessage>>,
+fn history_needs_compression(
+    history: &[ChatHistoryMessage],
     system_prompt: &str,
     user_message: &str,
     storage: &StorageConfig,
-    progress: Option<&ProgressEmitter>,
-) -> Option<Vec<ChatHistoryMessage>> {
-    let history = history?;
-    if history.len() <= 2 {
-        return Some(history);
-    }
+    counter: &dyn TokenCounter,
+) -> bool {
     // Align with mainstream agent behavior: avoid eager compaction on short chats.
     // Keep full history for early turns and only compact once conversation is truly long.
-    if history.len() < MIN_HISTORY_MESSAGES_BEFORE_COMPRESSION {
-        return Some(history);
+    if history.len() <= 2 || history.len() < MIN_HISTORY_MESSAGES_BEFORE_COMPRESSION {
+        return false;
     }
-
     let max_context_tokens = storage.max_context_tokens.max(4096);
     let trigger_ratio = storage.context_compress_trigger_ratio.clamp(0.70, 0.99);
     let trigger_tokens = ((max_context_tokens as f32) * trigger_ratio).floor() as usize;
+    estimate_history_tokens(counter, system_prompt, user_message, history) > trigger_tokens
+}
 
-    let before_tokens = estimate_history_tokens(system_prompt, user_message, &history);
-    if before_tokens <= trigger_tokens {
-        return Some(history);
-    }
-
-    let keep_recent = history.len().min(12);
-    let split_idx = history.len().saturating_sub(keep_recent);
-    let older = &history[..split_idx];
-    let recent = &history[split_idx..];
-    let mut compressed = Vec::new();
-    let has_summary = !older.is_empty();
-    if has_summary {
-        compressed.push(ChatHistoryMessage {
-            role: "assistant".to_string(),
-            content: build_history_compression_summary(older, 6000),
-            tool_call_id: None,
-            tool_calls: None,
-        });
+/// 配置了摘要模型时返回其模型配置，历史压缩和长工具输出改由它总结
+fn summarizer_model_config(config: &Config) -> Option<crate::storage::ModelConfig> {
+    let profile = config.model.summarizer_profile.trim();
+    if profile.is_empty() {
+        return None;
     }
-    compressed.extend(recent.iter().cloned());
-
-    let target_ratio = (trigger_ratio - 0.08).max(0.70);
-    let target_tokens = ((max_context_tokens as f32) * target_ratio).floor() as usize;
+    Some(ModelManager::resolve_model_config(&config.model, Some(profile)))
+}
 
-    let mut loops = 0usize;
-    while estimate_history_tokens(system_prompt, user_message, &compressed) > target_tokens
-        && compressed.len() > 4
-        && loops < 128
-    {
-        let remove_idx = if has_summary && compressed.len() > 1 {
+/// 需要压缩时先用摘要模型总结较早的对话，再按原有规则裁剪；未配置摘要模型或调用失败时退回规则摘要
+async fn compress_history_with_summarizer(
+    history: Option<Vec<ChatHistoryMessage>>,
+    system_prompt: &str,
+    user_message: &str,
+    config: &Config,
+    model_manager: &ModelManager,
+    progress: Option<&ProgressEmitter>,
+) -> Option<Vec<ChatHistoryMessage>> {
+    let counter = token_counter_for_config(&config.model);
+    let summarizer = match (summarizer_model_config(config), history.as_ref()) {
+        (Some(summarizer), Some(items))
+            if history_needs_compression(
+                items,
+                system_prompt,
+                user_message,
+                &config.storage,
+                counter,
+            ) =>
+        {
+            summarizer
+        }
+        _ => {
+            return compress_history_if_needed(
+                history,
+                system_prompt,
+                user_message,
+                &config.storage,
+                counter,
+                progress,
+            )
+        }
+    };
+    let history = history.unwrap_or_default();
+    let split_idx = history.len().saturating_sub(HISTORY_SUMMARY_KEEP_RECENT);
+    let (older, recent) = history.split_at(split_idx);
+
+    let mut transcript = String::new();
+    for msg in older {
+        let (content, _) = truncate_string(msg.content.trim(), 2000);
+        transcript.push_str(&format!("[{}]\n{}\n\n", msg.role, content));
+    }
+    let (transcript, _) = truncate_string(&transcript, 40_000);
+    let summary = model_manager
+        .chat_with_system_prompt(
+            &summarizer,
+            "You compress chat history. Summarize the conversation below so an assistant can continue it: keep user goals, decisions, facts, file paths, commands, results and open questions. Be concise, at most 600 words, in the conversation's language.",
+            &transcript,
+            None,
+        )
+        .await;
+
+    let mut summarized = match summary {
+        Ok(summary) if !summary.trim().is_empty() => {
+            if let Some(progress) = progress {
+                progress.emit_info(
+                    "Earlier history summarized by summarizer model".to_string(),
+                    Some(format!("{} messages", older.len())),
+                );
+            }
+            vec![ChatHistoryMessage {
+                role: "assistant".to_string(),
+                content: format!(
+                    "Context compression summary of earlier conversation:\n{}",
+                    summary.trim()
+                ),
+                tool_call_id: None,
+                tool_calls: None,
+            }]
+        }
+        Ok(_) | Err(_) => {
+            return compress_history_if_needed(
+                Some(history),
+                system_prompt,
+                user_message,
+                &config.storage,
+                counter,
+                progress,
+            )
+        }
+    };
+    summarized.extend(recent.iter().cloned());
+    compress_history_if_needed(
+        Some(summarized),
+        system_prompt,
+        user_message,
+        &config.storage,
+        counter,
+        progress,
+    )
+}
+
+/// 工具输出过长时用摘要模型压缩，避免长输出占用主模型上下文
+async fn summarize_tool_output_if_needed(
+    config: &Config,
+    model_manager: &ModelManager,
+    tool_name: &str,
+    output: String,
+    progress: Option<&ProgressEmitter>,
+) -> String {
+    if output.chars().count() <= TOOL_OUTPUT_SUMMARY_TRIGGER_CHARS || is_tool_failure(&output) {
+        return output;
+    }
+    let Some(summarizer) = summarizer_model_config(config) else {
+        return output;
+    };
+    let prompt = format!("Tool: {}\n\nOutput:\n{}", tool_name, output);
+    match model_manager
+        .chat_with_system_prompt(
+            &summarizer,
+            "Summarize this tool output for another assistant. Keep every error message, path, identifier, number and line that matters for the task verbatim; drop repetition and noise.",
+            &prompt,
+            None,
+        )
+        .await
+    {
+        Ok(summary) if !summary.trim().is_empty() => {
+            if let Some(progress) = progress {
+                progress.emit_info(
+                    "Long tool output summarized".to_string(),
+                    Some(format!("{} ({} chars)", tool_name, output.chars().count())),
+                );
+            }
+            format!(
+                "[Tool output ({} chars) summarized by summarizer model]\n{}",
+                output.chars().count(),
+                summary.trim()
+            )
+        }
+        _ => output,
+    }
+}
+
+fn compress_history_if_needed(
+    history: Option<Vec<ChatHistoryMessage>>,
+    system_prompt: &str,
+    user_message: &str,
+    storage: &StorageConfig,
+    counter: &dyn TokenCounter,
+    progress: Option<&ProgressEmitter>,
+) -> Option<Vec<ChatHistoryMessage>> {
+    let history = history?;
+    if !history_needs_compression(&history, system_prompt, user_message, storage, counter) {
+        return Some(history);
+    }
+
+    let max_context_tokens = storage.max_context_tokens.max(4096);
+    let trigger_ratio = storage.context_compress_trigger_ratio.clamp(0.70, 0.99);
+    let trigger_tokens = ((max_context_tokens as f32) * trigger_ratio).floor() as usize;
+    let before_tokens = estimate_history_tokens(counter, system_prompt, user_message, &history);
+
+    let keep_recent = history.len().min(HISTORY_SUMMARY_KEEP_RECENT);
+    let split_idx = history.len().saturating_sub(keep_recent);
+    let older = &history[..split_idx];
+    let recent = &history[split_idx..];
+    let mut compressed = Vec::new();
+    let has_summary = !older.is_empty();
+    if has_summary {
+        compressed.push(ChatHistoryMessage {
+            role: "assistant".to_string(),
+            content: build_history_compression_summary(older, 6000),
+            tool_call_id: None,
+            tool_calls: None,
+        });
+    }
+    compressed.extend(recent.iter().cloned());
+
+    let target_ratio = (trigger_ratio - 0.08).max(0.70);
+    let target_tokens = ((max_context_tokens as f32) * target_ratio).floor() as usize;
+
+    let mut loops = 0usize;
+    while estimate_history_tokens(counter, system_prompt, user_message, &compressed) > target_tokens
+        && compressed.len() > 4
+        && loops < 128
+    {
+        let remove_idx = if has_summary && compressed.len() > 1 {
             1
         } else {
             0
@@ -647,7 +1478,7 @@ fn compress_history_if_needed(
 
     if has_summary && !compressed.is_empty() {
         let mut summary_limit = 3000usize;
-        while estimate_history_tokens(system_prompt, user_message, &compressed) > target_tokens
+        while estimate_history_tokens(counter, system_prompt, user_message, &compressed) > target_tokens
             && summary_limit > 600
         {
             let (shortened, truncated) = truncate_string(&compressed[0].content, summary_limit);
@@ -660,7 +1491,7 @@ fn compress_history_if_needed(
         }
     }
 
-    while estimate_history_tokens(system_prompt, user_message, &compressed) > trigger_tokens
+    while estimate_history_tokens(counter, system_prompt, user_message, &compressed) > trigger_tokens
         && compressed.len() > 2
     {
         let remove_idx = if has_summary && compressed.len() > 1 {
@@ -671,7 +1502,7 @@ fn compress_history_if_needed(
         compressed.remove(remove_idx);
     }
 
-    let after_tokens = estimate_history_tokens(system_prompt, user_message, &compressed);
+    let after_tokens = estimate_history_tokens(counter, system_prompt, user_message, &compressed);
     if let Some(progress) = progress {
         progress.emit_info(
             "Context compression activated".to_string(),
@@ -690,20 +1521,6 @@ fn compress_history_if_needed(
     Some(compressed)
 }
 
-fn is_context_overflow_error(err: &str) -> bool {
-    let lower = err.to_lowercase();
-    lower.contains("context_length_exceeded")
-        || lower.contains("context length")
-        || lower.contains("context window")
-        || lower.contains("maximum context")
-        || lower.contains("too many tokens")
-        || lower.contains("token limit")
-        || lower.contains("prompt is too long")
-        || lower.contains("input is too long")
-        || lower.contains("improperly formed request")
-        || lower.contains("bad request")
-}
-
 fn squeeze_history_keep_recent(
     history: &Option<Vec<ChatHistoryMessage>>,
     keep_recent: usize,
@@ -748,6 +1565,7 @@ fn build_overflow_recovery_histories(
     system_prompt: &str,
     user_message: &str,
     storage: &StorageConfig,
+    counter: &dyn TokenCounter,
 ) -> Vec<Option<Vec<ChatHistoryMessage>>> {
     let mut candidates = Vec::new();
     candidates.push(history.clone());
@@ -766,6 +1584,7 @@ fn build_overflow_recovery_histories(
         system_prompt,
         user_message,
         &aggressive_storage,
+        counter,
         None,
     );
     candidates.push(squeeze_history_keep_recent(
@@ -842,17 +1661,46 @@ struct ToolAccess {
     tasks_dir: PathBuf,
 }
 
+/// 聊天命令；进度和授权事件只发给发起请求的窗口
 #[tauri::command]
 pub async fn chat_with_assistant(
     message: String,
-    history: Option<Vec<ChatHistoryMessage>>,
-    attachments: Option<Vec<AttachmentInput>>,
-    request_id: Option<String>,
+    options: Option<ChatOptions>,
+    window: WebviewWindow,
     app_handle: AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
+    let window_label = Some(window.label().to_string());
+    chat_with_assistant_internal(message, options, window_label, app_handle, state).await
+}
+
+/// 聊天主流程；window_label 为空时（API 服务等后台调用）事件广播给所有窗口
+pub async fn chat_with_assistant_internal(
+    message: String,
+    options: Option<ChatOptions>,
+    window_label: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let ChatOptions {
+        history,
+        attachments,
+        request_id,
+        model,
+        context_strategy,
+        plan_only,
+        preset,
+    } = options.unwrap_or_default();
+    let plan_only = plan_only.unwrap_or(false);
     let storage = StorageManager::new();
-    let config = storage.load_config().map_err(|e| e.to_string())?;
+    let mut config = storage.load_config().map_err(|e| e.to_string())?;
+    // 可选的助手预设，只作用于本次请求
+    let active_preset = crate::presets::find_agent_preset(&config, preset.as_deref())?;
+    if let Some(preset) = &active_preset {
+        crate::presets::apply_agent_preset(&mut config, preset);
+    }
+    // 可选的模型覆盖：命名配置（如 fast / coding）或具体模型 ID
+    config.model = ModelManager::resolve_model_config(&config.model, model.as_deref());
     let model_manager = ModelManager::new();
     let skill_manager = SkillManager::new();
 
@@ -864,15 +1712,20 @@ pub async fn chat_with_assistant(
     let detail_cutoff = build_detail_cutoff(&config);
     let context = if use_context {
         // 分析用户问题，提取时间范围和关键词
-        let query = parse_user_query(&message);
+        let mut query = parse_user_query(&message, &config.calendar);
+        query.aggregation =
+            AggregationGranularity::parse(&config.storage.aggregation_granularity);
+        if config.embedding.enabled {
+            query.semantic = crate::analysis::semantic_query(&config, &message).await;
+        }
 
-        // 智能检索相关记录
-        let mut search_result = storage.smart_search(&query)?;
+        // 智能检索相关记录（交互优先级，不排在截屏写入之后）
+        let mut search_result = interactive_search(query.clone()).await?;
 
         if search_result.records.is_empty() && !query.keywords.is_empty() {
             let mut relaxed = query.clone();
             relaxed.keywords.clear();
-            if let Ok(relaxed_result) = storage.smart_search(&relaxed) {
+            if let Ok(relaxed_result) = interactive_search(relaxed).await {
                 if !relaxed_result.records.is_empty() || !relaxed_result.aggregated.is_empty() {
                     search_result = relaxed_result;
                 }
@@ -882,8 +1735,13 @@ pub async fn chat_with_assistant(
         if matches!(query.time_range, TimeRange::Recent(_))
             && search_result.records.len() < MIN_RECENT_DETAIL_RECORDS
         {
-            let mut fallback = storage
-                .get_recent_records(MIN_RECENT_DETAIL_RECORDS, config.storage.retention_days);
+            let retention_days = config.storage.retention_days;
+            let mut fallback = storage_actor()
+                .run(StoragePriority::Interactive, move |storage| {
+                    Ok(storage.get_recent_records(MIN_RECENT_DETAIL_RECORDS, retention_days))
+                })
+                .await
+                .unwrap_or_default();
             if let Some(ref cutoff) = detail_cutoff {
                 fallback.retain(|record| record.timestamp >= *cutoff);
             }
@@ -897,23 +1755,37 @@ pub async fn chat_with_assistant(
         }
 
         let include_detail = query.include_detail && config.storage.context_detail_hours != 0;
+        // 上下文策略：请求参数优先，其次使用配置
+        let strategy = ContextStrategy::parse(
+            context_strategy
+                .as_deref()
+                .unwrap_or(config.storage.context_strategy.as_str()),
+            &query,
+        );
         // 构建上下文（使用配置中的最大字符数）
         let context = search_result.build_context(
+            &strategy,
+            config.storage.context_aggregate_ratio,
             config.storage.max_context_chars,
             include_detail,
             detail_cutoff.as_deref(),
         );
 
         // 注入启用的全局提示词
-        build_context_with_global_prompts(&config, context)
+        build_context_with_global_prompts(&config, context).await
     } else {
-        build_context_with_global_prompts(&config, String::new())
+        build_context_with_global_prompts(&config, String::new()).await
+    };
+    let context = match &active_preset {
+        Some(preset) => format!("{}{}", context, crate::presets::preset_prompt_section(preset)),
+        None => context,
     };
 
-    // 处理附件内容
+    // 处理附件内容（网址附件先抓取网页正文）
+    let attachments = url_attachment::fetch_url_attachments(attachments).await;
     let attachment_payload = attachments
         .as_deref()
-        .map(build_attachment_payload)
+        .map(|items| build_attachment_payload(items, !vision_unsupported(&config.model)))
         .unwrap_or_default();
     let has_attachments = attachments
         .as_ref()
@@ -923,25 +1795,60 @@ pub async fn chat_with_assistant(
 
     let request_id =
         request_id.unwrap_or_else(|| format!("req-{}", Local::now().timestamp_millis()));
-    let cancel_token = register_cancel_token(&state, &request_id).await;
+    let cancel_guard = register_cancel_token(&state, &request_id, "chat", &message).await;
+    let cancel_token = cancel_guard.token().clone();
     let progress = ProgressEmitter::new(
         &app_handle,
         config.ui.show_progress,
         Some(request_id.clone()),
-    );
+        Some(cancel_guard.status()),
+    )
+    .map(|progress| progress.routed_to(event_target(&app_handle, window_label.as_deref())));
+
+    if ModelManager::supports_tools(&config.model) {
+        crate::mcp::sync_servers(&config.tools).await;
+    }
+
+    // 离线排队时固定提交时的上下文，恢复连接后不重新检索
+    let queued_chat = || offline_queue::QueuedChat {
+        id: String::new(),
+        queued_at: String::new(),
+        message: message.clone(),
+        context: context.clone(),
+        user_message: user_message.clone(),
+        history: history.clone().unwrap_or_default(),
+        image_urls: attachment_payload.image_urls.clone(),
+        image_base64: attachment_payload.image_base64.clone(),
+        model: model.clone(),
+        attempts: 0,
+    };
 
     let response = (async {
-        let response = if config.model.provider == "api" {
-        let system_prompt = build_tool_system_prompt(&context, skill_manager.get_skills_dir(), &available_skills);
-        let system_prompt =
+        let response = if ModelManager::supports_tools(&config.model) {
+        let capture_running = state.capture_manager.lock().await.is_running();
+        let capabilities = collect_capabilities(&config, &storage, &available_skills, capture_running);
+        let mut system_prompt = build_tool_system_prompt(
+            &context,
+            skill_manager.get_skills_dir(),
+            &available_skills,
+            &message,
+            config.tools.max_prompt_skills,
+        );
+        system_prompt.push_str(&capabilities.prompt_section());
+        let mut system_prompt =
             apply_skill_block_to_system_prompt(&system_prompt, inherited_skill_block.as_deref());
-        let mut model_history = compress_history_if_needed(
+        if plan_only {
+            system_prompt.push_str(PLAN_ONLY_PROMPT);
+        }
+        let mut model_history = compress_history_with_summarizer(
             history.clone(),
             &system_prompt,
             &user_message,
-            &config.storage,
+            &config,
+            &model_manager,
             progress.as_ref(),
-        );
+        )
+        .await;
         if let Some(ref progress) = progress {
             progress.emit_start("开始处理请求");
             progress.emit_info("Analyze request & plan".to_string(), None);
@@ -951,10 +1858,11 @@ pub async fn chat_with_assistant(
             &system_prompt,
             &user_message,
             &config.storage,
+            token_counter_for_config(&config.model),
         );
         let total_candidates = history_candidates.len();
         let mut result: Option<ChatWithToolsResult> = None;
-        let mut last_error: Option<String> = None;
+        let mut last_error: Option<AppError> = None;
         for (idx, candidate_history) in history_candidates.into_iter().enumerate() {
             let attempt = if attachment_payload.image_urls.is_empty()
                 && attachment_payload.image_base64.is_empty()
@@ -963,6 +1871,7 @@ pub async fn chat_with_assistant(
                 retry_with_cancel(
                     &cancel_token,
                     progress.as_ref(),
+                    config.model.retry_policy(),
                     "model",
                     || model_manager.chat_with_tools_with_system_prompt(
                         &config.model,
@@ -978,6 +1887,7 @@ pub async fn chat_with_assistant(
                 retry_with_cancel(
                     &cancel_token,
                     progress.as_ref(),
+                    config.model.retry_policy(),
                     "model",
                     || model_manager.chat_with_tools_with_system_prompt_with_images(
                         &config.model,
@@ -998,7 +1908,8 @@ pub async fn chat_with_assistant(
                     break;
                 }
                 Err(err) => {
-                    let can_retry = idx + 1 < total_candidates && is_context_overflow_error(&err);
+                    let can_retry = idx + 1 < total_candidates
+                        && matches!(err, AppError::ContextOverflow { .. });
                     if can_retry {
                         if let Some(ref progress) = progress {
                             progress.emit_info(
@@ -1009,6 +1920,9 @@ pub async fn chat_with_assistant(
                         last_error = Some(err);
                         continue;
                     }
+                    if offline_queue::should_queue_offline(&config, &err) {
+                        return Ok(offline_queue::queue_offline_chat(queued_chat(), progress.as_ref())?);
+                    }
                     return Err(err);
                 }
             }
@@ -1016,7 +1930,7 @@ pub async fn chat_with_assistant(
         let result = if let Some(value) = result {
             value
         } else {
-            return Err(last_error.unwrap_or_else(|| "model request failed".to_string()));
+            return Err(last_error.unwrap_or_else(|| AppError::other("model request failed")));
         };
 
         let tool_loop_result = run_tool_loop(
@@ -1028,14 +1942,17 @@ pub async fn chat_with_assistant(
             result,
             &available_skills,
             &None,
+            ASSISTANT_APPROVAL_SCOPE,
+            plan_only,
             None,
             Some(&cancel_token),
             progress.as_ref(),
         )
         .await;
-        let (response, mut tool_context) = if let Ok(result) = tool_loop_result {
+        let (response, mut tool_context, planned_actions) = if let Ok(result) = tool_loop_result {
             let mut combined = result.response;
             let mut combined_context = result.tool_context;
+            let mut planned_actions = result.planned_actions;
             if MODEL_MAX_CONTINUES > 0 && response_looks_incomplete(&combined) {
                 if let Some(ref progress) = progress {
                     progress.emit_info("Continuing incomplete response".to_string(), None);
@@ -1060,6 +1977,7 @@ pub async fn chat_with_assistant(
                     retry_with_cancel(
                         &cancel_token,
                         progress.as_ref(),
+                        config.model.retry_policy(),
                         "continue",
                         || model_manager.chat_with_tools_with_system_prompt(
                             &config.model,
@@ -1074,6 +1992,7 @@ pub async fn chat_with_assistant(
                     retry_with_cancel(
                         &cancel_token,
                         progress.as_ref(),
+                        config.model.retry_policy(),
                         "continue",
                         || model_manager.chat_with_tools_with_system_prompt_with_images(
                             &config.model,
@@ -1098,6 +2017,8 @@ pub async fn chat_with_assistant(
                         followup_result,
                         &available_skills,
                         &None,
+                        ASSISTANT_APPROVAL_SCOPE,
+                        plan_only,
                         None,
                         Some(&cancel_token),
                         progress.as_ref(),
@@ -1113,12 +2034,13 @@ pub async fn chat_with_assistant(
                             );
                         }
                         combined_context.extend(followup_loop_result.tool_context);
+                        planned_actions.extend(followup_loop_result.planned_actions);
                     }
                 }
             }
-            (Ok(combined), combined_context)
+            (Ok(combined), combined_context, planned_actions)
         } else {
-            (tool_loop_result.map(|r| r.response), Vec::new())
+            (tool_loop_result.map(|r| r.response), Vec::new(), Vec::new())
         };
 if let Some(ref progress) = progress {
             if response.is_ok() {
@@ -1134,6 +2056,10 @@ if let Some(ref progress) = progress {
                     response: text,
                     tool_context,
                     active_skill: None,
+                    structured_output: None,
+                    structured_output_error: None,
+                    planned_actions,
+                    queued_request: None,
                 };
                 Ok(serde_json::to_string(&chat_response).unwrap_or_else(|_| chat_response.response))
             }
@@ -1166,19 +2092,22 @@ if let Some(ref progress) = progress {
         let context_with_skills = format!("{}{}", context, skills_hint);
         let context_with_skills =
             apply_skill_block_to_system_prompt(&context_with_skills, inherited_skill_block.as_deref());
-        let model_history = compress_history_if_needed(
+        let model_history = compress_history_with_summarizer(
             history.clone(),
             &context_with_skills,
             &user_message,
-            &config.storage,
+            &config,
+            &model_manager,
             progress.as_ref(),
-        );
+        )
+        .await;
         let response = if attachment_payload.image_urls.is_empty()
             && attachment_payload.image_base64.is_empty()
         {
             retry_with_cancel(
                 &cancel_token,
                 progress.as_ref(),
+                config.model.retry_policy(),
                 "model",
                 || model_manager.chat_with_history(
                     &config.model,
@@ -1192,6 +2121,7 @@ if let Some(ref progress) = progress {
             retry_with_cancel(
                 &cancel_token,
                 progress.as_ref(),
+                config.model.retry_policy(),
                 "model",
                 || model_manager.chat_with_history_with_images(
                     &config.model,
@@ -1204,6 +2134,11 @@ if let Some(ref progress) = progress {
             )
             .await
         };
+        if let Err(err) = &response {
+            if offline_queue::should_queue_offline(&config, err) {
+                return Ok(offline_queue::queue_offline_chat(queued_chat(), progress.as_ref())?);
+            }
+        }
         let response = if let Ok(text) = response {
             let mut combined = text;
             if MODEL_MAX_CONTINUES > 0 && response_looks_incomplete(&combined) {
@@ -1229,6 +2164,7 @@ if let Some(ref progress) = progress {
                     retry_with_cancel(
                         &cancel_token,
                         progress.as_ref(),
+                        config.model.retry_policy(),
                         "continue",
                         || model_manager.chat_with_history(
                             &config.model,
@@ -1242,6 +2178,7 @@ if let Some(ref progress) = progress {
                     retry_with_cancel(
                         &cancel_token,
                         progress.as_ref(),
+                        config.model.retry_policy(),
                         "continue",
                         || model_manager.chat_with_history_with_images(
                             &config.model,
@@ -1282,7 +2219,7 @@ if let Some(ref progress) = progress {
         response
     })
     .await;
-    clear_cancel_token(&state, &request_id).await;
+    cancel_guard.release().await;
     response
 }
 
@@ -1298,9 +2235,21 @@ async fn execute_skill_internal(
     attachments: Option<Vec<AttachmentInput>>,
     cancel_token: Option<&CancellationToken>,
     progress: Option<&ProgressEmitter>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     // 加载 skill
     let skill = skill_manager.load_skill(skill_name)?;
+    // skill 可通过 frontmatter 的 model 字段指定模型配置
+    let skill_config;
+    let config = match skill.metadata.model.as_deref() {
+        Some(model) if !model.trim().is_empty() => {
+            skill_config = Config {
+                model: ModelManager::resolve_model_config(&config.model, Some(model)),
+                ..config.clone()
+            };
+            &skill_config
+        }
+        _ => config,
+    };
     let rendered_instructions = inject_skill_arguments(&skill.instructions, args.as_deref());
     check_cancel(cancel_token)?;
     if let Some(progress) = progress {
@@ -1323,9 +2272,10 @@ async fn execute_skill_internal(
         format!("执行技能 /{}", skill_name)
     };
 
+    let attachments = url_attachment::fetch_url_attachments(attachments).await;
     let attachment_payload = attachments
         .as_deref()
-        .map(build_attachment_payload)
+        .map(|items| build_attachment_payload(items, !vision_unsupported(&config.model)))
         .unwrap_or_default();
     let has_attachments = attachments
         .as_ref()
@@ -1335,11 +2285,16 @@ async fn execute_skill_internal(
     // 根据 skill 的 context 设置决定是否包含屏幕记录
     let include_screen_context = skill.metadata.context.as_deref() == Some("screen");
     let screen_context = if include_screen_context {
-        let query = parse_user_query(args.as_deref().unwrap_or_default());
-        let search_result = storage.smart_search(&query).unwrap_or_default();
+        let mut query = parse_user_query(args.as_deref().unwrap_or_default(), &config.calendar);
+        query.aggregation =
+            AggregationGranularity::parse(&config.storage.aggregation_granularity);
+        let strategy = ContextStrategy::parse(&config.storage.context_strategy, &query);
+        let search_result = interactive_search(query).await.unwrap_or_default();
         let include_detail = config.storage.context_detail_hours != 0;
         let detail_cutoff = build_detail_cutoff(config);
         search_result.build_context(
+            &strategy,
+            config.storage.context_aggregate_ratio,
             config.storage.max_context_chars,
             include_detail,
             detail_cutoff.as_deref(),
@@ -1347,13 +2302,17 @@ async fn execute_skill_internal(
     } else {
         String::new()
     };
-    let context = build_context_with_global_prompts(config, screen_context);
+    let context = build_context_with_global_prompts(config, screen_context).await;
     let available_skills: Vec<SkillMetadata> = Vec::new();
-    let system_prompt = build_skill_execution_system_prompt(
+    let mut system_prompt = build_skill_execution_system_prompt(
         &context,
         skill_manager.get_skills_dir(),
         &skill_instruction_block,
     );
+    let output_schema = skill.metadata.output_schema.clone().filter(|s| s.is_object());
+    if let Some(schema) = output_schema.as_ref() {
+        system_prompt.push_str(&structured_output_instruction(schema));
+    }
     let effective_allowed_tools = skill.metadata.allowed_tools.clone();
 
     if let Some(progress) = progress {
@@ -1363,32 +2322,35 @@ async fn execute_skill_internal(
         );
     }
 
-    let model_history = compress_history_if_needed(
+    let model_history = compress_history_with_summarizer(
         history,
         &system_prompt,
         &user_message,
-        &config.storage,
+        config,
+        model_manager,
         progress,
-    );
+    )
+    .await;
 
-    if config.model.provider == "api" {
+    if ModelManager::supports_tools(&config.model) {
         let allowed_tools = &effective_allowed_tools;
         let history_candidates = build_overflow_recovery_histories(
             &model_history,
             &system_prompt,
             &user_message,
             &config.storage,
+            token_counter_for_config(&config.model),
         );
         let total_candidates = history_candidates.len();
         let mut result: Option<ChatWithToolsResult> = None;
-        let mut last_error: Option<String> = None;
+        let mut last_error: Option<AppError> = None;
         for (idx, candidate_history) in history_candidates.into_iter().enumerate() {
             let attempt = if attachment_payload.image_urls.is_empty()
                 && attachment_payload.image_base64.is_empty()
             {
                 let history_for_call = candidate_history.clone();
                 if let Some(token) = cancel_token {
-                    retry_with_cancel(token, progress, "model", || {
+                    retry_with_cancel(token, progress, config.model.retry_policy(), "model", || {
                         model_manager.chat_with_tools_with_system_prompt_filtered(
                             &config.model,
                             &system_prompt,
@@ -1414,7 +2376,7 @@ async fn execute_skill_internal(
             } else {
                 let history_for_call = candidate_history.clone();
                 if let Some(token) = cancel_token {
-                    retry_with_cancel(token, progress, "model", || {
+                    retry_with_cancel(token, progress, config.model.retry_policy(), "model", || {
                         model_manager.chat_with_tools_with_system_prompt_with_images_filtered(
                             &config.model,
                             &system_prompt,
@@ -1449,7 +2411,8 @@ async fn execute_skill_internal(
                     break;
                 }
                 Err(err) => {
-                    let can_retry = idx + 1 < total_candidates && is_context_overflow_error(&err);
+                    let can_retry = idx + 1 < total_candidates
+                        && matches!(err, AppError::ContextOverflow { .. });
                     if can_retry {
                         if let Some(progress) = progress {
                             progress.emit_info(
@@ -1468,7 +2431,7 @@ async fn execute_skill_internal(
         let result = if let Some(value) = result {
             value
         } else {
-            return Err(last_error.unwrap_or_else(|| "model request failed".to_string()));
+            return Err(last_error.unwrap_or_else(|| AppError::other("model request failed")));
         };
 
         return match Box::pin(run_tool_loop(
@@ -1480,6 +2443,8 @@ async fn execute_skill_internal(
             result,
             &available_skills,
             allowed_tools,
+            &skill.metadata.name,
+            false,
             Some(skill_dir),
             cancel_token,
             progress,
@@ -1494,10 +2459,23 @@ async fn execute_skill_internal(
                     tool_calls: None,
                 }];
                 tool_context.extend(result.tool_context);
+                let (response, structured_output, structured_output_error) =
+                    finalize_structured_output(
+                        model_manager,
+                        config,
+                        output_schema.as_ref(),
+                        result.response,
+                        progress,
+                    )
+                    .await;
                 let chat_response = ChatResponse {
-                    response: result.response,
+                    response,
                     tool_context,
                     active_skill: Some(skill_name.to_string()),
+                    structured_output,
+                    structured_output_error,
+                    planned_actions: Vec::new(),
+                    queued_request: None,
                 };
                 Ok(
                     serde_json::to_string(&chat_response)
@@ -1512,7 +2490,7 @@ async fn execute_skill_internal(
         && attachment_payload.image_base64.is_empty()
     {
         if let Some(token) = cancel_token {
-            retry_with_cancel(token, progress, "model", || {
+            retry_with_cancel(token, progress, config.model.retry_policy(), "model", || {
                 model_manager.chat_with_system_prompt(
                     &config.model,
                     &system_prompt,
@@ -1532,7 +2510,7 @@ async fn execute_skill_internal(
                 .await
         }
     } else if let Some(token) = cancel_token {
-        retry_with_cancel(token, progress, "model", || {
+        retry_with_cancel(token, progress, config.model.retry_policy(), "model", || {
             model_manager.chat_with_system_prompt_with_images(
                 &config.model,
                 &system_prompt,
@@ -1556,8 +2534,16 @@ async fn execute_skill_internal(
             .await
     }?;
 
+    let (response, structured_output, structured_output_error) = finalize_structured_output(
+        model_manager,
+        config,
+        output_schema.as_ref(),
+        response_text,
+        progress,
+    )
+    .await;
     let chat_response = ChatResponse {
-        response: response_text,
+        response,
         tool_context: vec![ToolContextMessage {
             role: "user".to_string(),
             content: Some(skill_instruction_block),
@@ -1565,41 +2551,116 @@ async fn execute_skill_internal(
             tool_calls: None,
         }],
         active_skill: Some(skill_name.to_string()),
+        structured_output,
+        structured_output_error,
+        planned_actions: Vec::new(),
+        queued_request: None,
     };
     Ok(serde_json::to_string(&chat_response).unwrap_or_else(|_| chat_response.response))
 }
 
+/// 解析并校验 skill 的结构化结果；不合法时让模型按错误修正一次
+async fn finalize_structured_output(
+    model_manager: &ModelManager,
+    config: &Config,
+    schema: Option<&serde_json::Value>,
+    response: String,
+    progress: Option<&ProgressEmitter>,
+) -> (String, Option<serde_json::Value>, Option<String>) {
+    let Some(schema) = schema else {
+        return (response, None, None);
+    };
+
+    let (text, parsed) = split_structured_output(&response);
+    let first_error = match parsed {
+        Some(value) => match validate_against_schema(&value, schema) {
+            Ok(()) => return finish_structured_output(text, value),
+            Err(errors) => errors.join("; "),
+        },
+        None => "未找到 JSON 结果".to_string(),
+    };
+
+    if let Some(progress) = progress {
+        progress.emit_info(
+            "Structured output invalid; requesting repair".to_string(),
+            Some(first_error.clone()),
+        );
+    }
+    let repair_prompt = format!(
+        "The following answer must end with a ```json block conforming to the schema, but validation failed: {}\n\nSchema:\n{}\n\nAnswer:\n{}\n\nReturn only the corrected JSON in a ```json block.",
+        first_error,
+        schema,
+        response
+    );
+    let repaired = model_manager
+        .chat_with_system_prompt(
+            &config.model,
+            "You repair JSON so that it conforms to a JSON Schema. Do not add commentary.",
+            &repair_prompt,
+            None,
+        )
+        .await;
+    if let Ok(repaired) = repaired {
+        if let (_, Some(value)) = split_structured_output(&repaired) {
+            if validate_against_schema(&value, schema).is_ok() {
+                return finish_structured_output(text, value);
+            }
+        }
+    }
+
+    (response, None, Some(format!("结构化结果校验失败: {}", first_error)))
+}
+
+fn finish_structured_output(
+    text: String,
+    value: serde_json::Value,
+) -> (String, Option<serde_json::Value>, Option<String>) {
+    let text = if text.trim().is_empty() {
+        render_structured_output(&value)
+    } else {
+        text
+    };
+    (text, Some(value), None)
+}
+
+async fn interactive_search(query: SearchQuery) -> Result<SearchResult, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.smart_search(&query))
+        .await
+}
+
 /// 解析用户问题，提取时间范围和关键词
-fn parse_user_query(message: &str) -> SearchQuery {
+fn parse_user_query(message: &str, calendar: &CalendarConfig) -> SearchQuery {
     let msg_lower = message.to_lowercase();
 
-    // 提取时间范围
-    let time_range = if msg_lower.contains("刚才") || msg_lower.contains("刚刚") {
-        TimeRange::Recent(5) // 最近5分钟
-    } else if msg_lower.contains("最近") && msg_lower.contains("分钟") {
-        // 尝试提取分钟数
-        let minutes = extract_number(&msg_lower).unwrap_or(10);
-        TimeRange::Recent(minutes)
-    } else if msg_lower.contains("今天") || msg_lower.contains("上午") || msg_lower.contains("下午")
-    {
-        TimeRange::Today
-    } else if msg_lower.contains("昨天") {
-        TimeRange::Days(2)
-    } else if msg_lower.contains("这周") || msg_lower.contains("本周") {
-        TimeRange::Days(7)
-    } else {
+    // 提取时间范围（中英文），按配置的一周起始日和工作时间理解「本周」「上班时间」
+    let options = calendar.time_options().unwrap_or_default();
+    let time_range = match parse_time_expression_with(message, Local::now().naive_local(), &options) {
+        Some(range) => range,
+        None if msg_lower.contains("最近") && msg_lower.contains("分钟") => {
+            // 尝试提取分钟数
+            let minutes = extract_number(&msg_lower).unwrap_or(10);
+            TimeRange::Recent(minutes)
+        }
         // 默认：最近10分钟 + 今天的聚合
-        TimeRange::Recent(10)
+        None => TimeRange::Recent(10),
     };
 
     // 提取关键词
     let keywords = extract_keywords(message);
-    let include_detail = wants_detail(message) || matches!(time_range, TimeRange::Recent(_));
+    let short_range = match &time_range {
+        TimeRange::Recent(_) => true,
+        TimeRange::Between(start, end) => *end - *start <= Duration::hours(3),
+        _ => false,
+    };
+    let include_detail = wants_detail(message) || short_range;
 
     SearchQuery {
         time_range,
         keywords,
         include_detail,
+        aggregation: AggregationGranularity::Stored,
+        semantic: None,
     }
 }
 
@@ -1661,6 +2722,14 @@ fn extract_keywords(message: &str) -> Vec<String> {
         }
     }
 
+    // 提取进程名（如 chrome.exe），与记录中的前台进程名匹配
+    for token in message.split(|c: char| c.is_whitespace() || "，。,;；:：()（）".contains(c)) {
+        let token = token.trim();
+        if token.len() > 4 && token.to_lowercase().ends_with(".exe") {
+            keywords.push(token.to_string());
+        }
+    }
+
     keywords
 }
 
@@ -1769,14 +2838,24 @@ fn build_detail_cutoff(config: &Config) -> Option<String> {
     Some(cutoff.format("%Y-%m-%dT%H:%M:%S").to_string())
 }
 
-/// 构建包含全局提示词的上下文
-fn build_context_with_global_prompts(config: &Config, context: String) -> String {
+/// 构建包含全局提示词和当前场景资料包的上下文
+async fn build_context_with_global_prompts(config: &Config, context: String) -> String {
     let global_section = build_global_prompts_section(config);
-    if global_section.is_empty() {
-        context
-    } else {
-        format!("{}{}", global_section, context)
+    let pack_config = config.clone();
+    let pack_section = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            Ok(build_context_pack_section(&pack_config, storage))
+        })
+        .await
+        .unwrap_or_default();
+    if global_section.is_empty() && pack_section.is_empty() {
+        return context;
     }
+    let mut output = String::with_capacity(global_section.len() + pack_section.len() + context.len());
+    output.push_str(&global_section);
+    output.push_str(&pack_section);
+    output.push_str(&context);
+    output
 }
 
 /// 构建全局提示词部分
@@ -1805,21 +2884,22 @@ fn merge_recent_records(
         return records;
     }
 
-    let mut seen = HashSet::new();
-    let mut merged = Vec::new();
+    let mut merged = records;
+    merged.reserve(fallback.len());
+    merged.extend(fallback);
 
-    for record in records.into_iter().chain(fallback.into_iter()) {
-        let key = format!("{}|{}|{}", record.timestamp, record.app, record.summary);
-        if seen.insert(key) {
-            merged.push(record);
-        }
-    }
-
-    merged.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    // 稳定排序后重复记录相邻，去重时保留先出现的检索结果，不必为每条记录拼接去重键
+    merged.sort_by(|a, b| {
+        a.timestamp
+            .cmp(&b.timestamp)
+            .then_with(|| a.app.cmp(&b.app))
+            .then_with(|| a.summary.cmp(&b.summary))
+    });
+    merged.dedup_by(|a, b| a.timestamp == b.timestamp && a.app == b.app && a.summary == b.summary);
 
     if merged.len() > limit {
-        let start = merged.len() - limit;
-        merged = merged.split_off(start);
+        let excess = merged.len() - limit;
+        merged.drain(..excess);
     }
 
     merged
@@ -1827,66 +2907,837 @@ fn merge_recent_records(
 
 #[tauri::command]
 pub async fn get_summaries(date: String) -> Result<Vec<SummaryRecord>, String> {
-    let storage = StorageManager::new();
-    storage.get_summaries(&date).map_err(|e| e.to_string())
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.get_summaries(&date))
+        .await
+}
+
+/// 按时间段聚合的当天活动时间线，bucket_minutes 默认 60
+#[tauri::command]
+pub async fn get_timeline(date: String, bucket_minutes: Option<u32>) -> Result<Vec<TimelineBucket>, String> {
+    let bucket_minutes = bucket_minutes.unwrap_or(crate::storage::DEFAULT_TIMELINE_BUCKET_MINUTES);
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.get_timeline(&date, bucket_minutes))
+        .await
+}
+
+/// 日历热力图数据，period 为 week、month、quarter 或 year，默认 month
+#[tauri::command]
+pub async fn get_activity_heatmap(period: Option<String>) -> Result<ActivityHeatmap, String> {
+    let period = period.unwrap_or_default();
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.get_activity_heatmap(&period))
+        .await
+}
+
+/// 在截图的本地 OCR 文字层中查找文字，返回截图时间和命中文字的位置；需开启截图文字索引
+#[tauri::command]
+pub async fn search_screenshots(
+    text: String,
+    date: Option<String>,
+    days: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<ScreenshotTextMatch>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.search_screenshot_text(&text, date.as_deref(), days, limit)
+        })
+        .await
 }
 
 #[tauri::command]
 pub async fn clear_summaries(date: String) -> Result<usize, String> {
-    let storage = StorageManager::new();
-    storage
-        .delete_summaries_for_date(&date)
-        .map_err(|e| e.to_string())
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.delete_summaries_for_date(&date))
+        .await
 }
 
 #[tauri::command]
 pub async fn clear_all_summaries() -> Result<usize, String> {
-    let storage = StorageManager::new();
-    storage.delete_all_summaries().map_err(|e| e.to_string())
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.delete_all_summaries())
+        .await
 }
 
 #[tauri::command]
-pub async fn open_screenshots_dir(app_handle: AppHandle) -> Result<(), String> {
-    let storage = StorageManager::new();
-    let dir = storage.screenshots_dir()?;
-    let dir_str = dir.to_string_lossy().to_string();
-    app_handle
-        .shell()
-        .open(dir_str, None)
-        .map_err(|e| e.to_string())
+pub async fn get_storage_usage() -> Result<StorageUsage, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.storage_usage())
+        .await
 }
 
-/// 读取图片文件并返回 base64 编码
-/// file_type: "attachment" | "screenshot"
+/// 界面挂载时查询尚未确认的数据清理通知（启动时的事件可能早于界面挂载）
 #[tauri::command]
-pub async fn read_image_base64(
-    file_path: String,
-    file_type: Option<String>,
-) -> Result<String, String> {
-    let storage = StorageManager::new();
-    let data_dir = storage.get_data_dir().to_path_buf();
+pub async fn get_retention_notice() -> Result<Option<RetentionNotice>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| Ok(storage.pending_retention_notice()))
+        .await
+}
 
-    // 根据类型确定基础目录
-    let base_dir = match file_type.as_deref() {
-        Some("screenshot") => data_dir.join("screenshots"),
-        Some("attachment") => data_dir.join("attachments"),
-        _ => data_dir.clone(),
-    };
+#[tauri::command]
+pub async fn acknowledge_retention_notice() -> Result<(), String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.acknowledge_retention_notice())
+        .await
+}
 
-    // 构建完整路径
-    let full_path = if Path::new(&file_path).is_absolute() {
-        PathBuf::from(&file_path)
-    } else {
-        base_dir.join(&file_path)
-    };
+#[tauri::command]
+pub async fn get_encryption_status() -> Result<EncryptionStatus, String> {
+    Ok(StorageManager::new().encryption_status())
+}
 
-    // 安全检查：确保路径在数据目录内
-    let canonical = full_path
-        .canonicalize()
-        .map_err(|e| format!("文件不存在: {}", e))?;
-    let data_canonical = data_dir
-        .canonicalize()
-        .map_err(|e| format!("数据目录错误: {}", e))?;
+/// 启动时输入密码解锁加密存储
+#[tauri::command]
+pub async fn unlock_storage(passphrase: String) -> Result<(), String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.unlock_storage(&passphrase))
+        .await
+}
+
+/// 启用/更换/关闭存储加密：首次启用时 old_passphrase 为空，new_passphrase 为空表示关闭加密
+#[tauri::command]
+pub async fn change_encryption_passphrase(
+    old_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+) -> Result<usize, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.change_encryption_passphrase(old_passphrase.as_deref(), new_passphrase.as_deref())
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn list_conversations() -> Result<Vec<ConversationSummary>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.list_conversations())
+        .await
+}
+
+#[tauri::command]
+pub async fn load_conversation(id: String) -> Result<Conversation, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.load_conversation(&id))
+        .await
+}
+
+/// 保存会话（id 为空时新建），前端每轮对话结束后调用；
+/// 首轮问答完成后在后台生成标题，完成时发送 conversation-title-updated 事件
+#[tauri::command]
+pub async fn save_conversation(
+    conversation: Conversation,
+    app_handle: AppHandle,
+) -> Result<ConversationSummary, String> {
+    let first_exchange = first_conversation_exchange(&conversation);
+    let summary = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.save_conversation(conversation))
+        .await?;
+    if summary.title_source.is_empty() {
+        if let Some((question, answer)) = first_exchange {
+            let id = summary.id.clone();
+            tauri::async_runtime::spawn(async move {
+                match generate_conversation_title(&question, &answer).await {
+                    Ok(title) => {
+                        let saved = storage_actor()
+                            .run(StoragePriority::Background, move |storage| {
+                                storage.set_conversation_title(&id, &title, "model")
+                            })
+                            .await;
+                        match saved {
+                            Ok(updated) => {
+                                let _ = app_handle.emit("conversation-title-updated", updated);
+                            }
+                            Err(err) => eprintln!("保存会话标题失败: {}", err),
+                        }
+                    }
+                    Err(err) => eprintln!("生成会话标题失败: {}", err),
+                }
+            });
+        }
+    }
+    Ok(summary)
+}
+
+fn first_conversation_exchange(conversation: &Conversation) -> Option<(String, String)> {
+    let user_idx = conversation
+        .messages
+        .iter()
+        .position(|msg| msg.role == "user" && !msg.content.trim().is_empty())?;
+    let answer = conversation.messages[user_idx + 1..]
+        .iter()
+        .find(|msg| msg.role == "assistant" && !msg.content.trim().is_empty())?;
+    Some((
+        conversation.messages[user_idx].content.clone(),
+        answer.content.clone(),
+    ))
+}
+
+/// 用摘要模型（未配置时用默认模型）把首轮问答概括为不超过 8 个词的标题
+async fn generate_conversation_title(question: &str, answer: &str) -> Result<String, String> {
+    let config = StorageManager::new().load_config()?;
+    let model_config = summarizer_model_config(&config).unwrap_or_else(|| config.model.clone());
+    let (question, _) = truncate_string(question.trim(), 1500);
+    let (answer, _) = truncate_string(answer.trim(), 1500);
+    let title = ModelManager::new()
+        .chat_with_system_prompt(
+            &model_config,
+            "Summarize the conversation topic as a short title of at most 8 words, in the same language as the user. Output only the title, without quotes or punctuation at the end.",
+            &format!("User:\n{}\n\nAssistant:\n{}", question, answer),
+            None,
+        )
+        .await?;
+    let title = title
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("")
+        .trim_matches(|c: char| c == '"' || c == '“' || c == '”' || c == '「' || c == '」' || c == '《' || c == '》')
+        .trim_end_matches(|c: char| c == '。' || c == '.')
+        .chars()
+        .take(40)
+        .collect::<String>();
+    if title.trim().is_empty() {
+        return Err("模型未返回标题".to_string());
+    }
+    Ok(title)
+}
+
+#[tauri::command]
+pub async fn delete_conversation(id: String) -> Result<(), String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.delete_conversation(&id))
+        .await
+}
+
+#[tauri::command]
+pub async fn rename_conversation(id: String, title: String) -> Result<ConversationSummary, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.rename_conversation(&id, &title)
+        })
+        .await
+}
+
+/// 读取已生成的日报/周报: period = daily | weekly
+#[tauri::command]
+pub async fn get_digest(date: String, period: Option<String>) -> Result<Option<DigestRecord>, String> {
+    let period = DigestPeriod::parse(period.as_deref())?;
+    let date = crate::export::parse_date(&date)?;
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.load_digest(period, date))
+        .await
+}
+
+/// 立即（重新）生成日报/周报
+#[tauri::command]
+pub async fn generate_digest(date: String, period: Option<String>) -> Result<DigestRecord, String> {
+    let period = DigestPeriod::parse(period.as_deref())?;
+    let date = crate::export::parse_date(&date)?;
+    crate::analysis::generate_digest(period, date).await
+}
+
+/// 根据最近的场景，当前对话会自动附带的资料包
+#[tauri::command]
+pub async fn get_active_context_packs() -> Result<Vec<ActiveContextPack>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| {
+            let config = storage.load_config()?;
+            Ok(active_context_packs(&config, storage))
+        })
+        .await
+}
+
+/// 待确认（include_closed 时包含已处理）的 skill 建议
+#[tauri::command]
+pub async fn get_skill_suggestions(include_closed: Option<bool>) -> Result<Vec<SkillSuggestion>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.list_skill_suggestions(include_closed.unwrap_or(false))
+        })
+        .await
+}
+
+/// 立即分析 date 所在周（默认上周）的痛点并生成 skill 建议
+#[tauri::command]
+pub async fn generate_skill_suggestions(date: Option<String>) -> Result<Vec<SkillSuggestion>, String> {
+    let date = match date.as_deref().map(str::trim).filter(|value| !value.is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => Local::now().date_naive() - Duration::days(7),
+    };
+    crate::analysis::generate_skill_suggestions(date).await
+}
+
+/// 确认建议并按草稿创建 skill
+#[tauri::command]
+pub async fn approve_skill_suggestion(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<SkillSuggestion, String> {
+    let suggestion = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            crate::analysis::approve_skill_suggestion(storage, &name)
+        })
+        .await?;
+    state.bump_skills_version();
+    Ok(suggestion)
+}
+
+#[tauri::command]
+pub async fn dismiss_skill_suggestion(name: String) -> Result<SkillSuggestion, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.set_skill_suggestion_status(&name, "dismissed")
+        })
+        .await
+}
+
+/// 模型用量统计：按天、按功能（capture / chat / skills）和按模型汇总，默认最近 7 天
+#[tauri::command]
+pub async fn get_usage_stats(
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<UsageStats, String> {
+    let end = match end_date.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => Local::now().date_naive(),
+    };
+    let start = match start_date.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => end - Duration::days(6),
+    };
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.usage_stats(start, end))
+        .await
+}
+
+/// 按应用统计使用时长、切换次数和最长专注时段，默认最近 7 天；compare 时附带与上一等长周期的对比
+#[tauri::command]
+pub async fn get_app_usage_stats(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    compare: Option<bool>,
+) -> Result<AppUsageStats, String> {
+    let end = match end_date.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => Local::now().date_naive(),
+    };
+    let start = match start_date.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => end - Duration::days(6),
+    };
+    let compare = compare.unwrap_or(false);
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.app_usage_stats(start, end, compare))
+        .await
+}
+
+/// 评价某条截屏分析结果: feedback = good | bad，空字符串清除评价
+#[tauri::command]
+pub async fn rate_record(timestamp: String, feedback: String) -> Result<(), String> {
+    let feedback = feedback.trim().to_lowercase();
+    if !matches!(feedback.as_str(), "" | "good" | "bad") {
+        return Err(format!("无效的评价: {}（可选 good/bad）", feedback));
+    }
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.set_record_feedback(&timestamp, &feedback)
+        })
+        .await
+}
+
+/// 为提醒记录创建外部工单（GitHub / Jira）；同一问题已有工单时直接返回已有关联
+#[tauri::command]
+pub async fn create_ticket(timestamp: String, alert_key: Option<String>) -> Result<TicketLink, String> {
+    let config = StorageManager::new().load_config()?;
+    let record = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.find_record(&timestamp))
+        .await?;
+    if !record.has_issue {
+        return Err("该记录没有检测到问题，无需创建工单".to_string());
+    }
+    let alert_key = alert_key
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .unwrap_or_else(|| crate::capture::record_alert_key(&record));
+
+    let existing_key = alert_key.clone();
+    let existing = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            Ok(storage.find_ticket_link(&existing_key))
+        })
+        .await?;
+    let link = match existing {
+        Some(link) => link,
+        None => {
+            let link = crate::tickets::create_ticket(&config.tickets, &record, &alert_key).await?;
+            let saved = link.clone();
+            storage_actor()
+                .run(StoragePriority::Interactive, move |storage| storage.add_ticket_link(saved))
+                .await?;
+            link
+        }
+    };
+
+    let url = link.url.clone();
+    let timestamp = record.timestamp.clone();
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.update_record(&timestamp, |record| record.ticket_url = url)
+        })
+        .await?;
+    Ok(link)
+}
+
+#[tauri::command]
+pub async fn list_tickets() -> Result<Vec<TicketLink>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.load_ticket_links())
+        .await
+}
+
+/// 监视文件夹的执行记录（最新在前），watch_id 为空时返回全部
+#[tauri::command]
+pub async fn get_watch_folder_history(watch_id: Option<String>) -> Result<Vec<WatchRun>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.load_watch_runs(watch_id.as_deref().filter(|id| !id.trim().is_empty()))
+        })
+        .await
+}
+
+/// 保存助手回复中的片段（选中文本或代码块）
+#[tauri::command]
+pub async fn save_snippet(
+    content: String,
+    title: Option<String>,
+    language: Option<String>,
+    tags: Option<Vec<String>>,
+    conversation_id: Option<String>,
+) -> Result<Snippet, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.add_snippet(title, content, language, tags.unwrap_or_default(), conversation_id)
+        })
+        .await
+}
+
+/// 片段列表（最新在前），query 为空时返回全部
+#[tauri::command]
+pub async fn list_snippets(query: Option<String>) -> Result<Vec<Snippet>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.search_snippets(query.as_deref(), None)
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn delete_snippet(id: String) -> Result<bool, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.delete_snippet(&id))
+        .await
+}
+
+/// 回复工具授权请求：decision 为 allow_once | allow_session | deny，remember 时按技能/命令持久化
+#[tauri::command]
+pub async fn answer_tool_approval(
+    approval_id: String,
+    decision: String,
+    remember: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let decision = ApprovalDecision::parse(&decision)?;
+    let resolved = ToolApprovalResolved {
+        approval_id: approval_id.clone(),
+        status: decision.as_str().to_string(),
+    };
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            answer_approval(storage, &approval_id, decision, remember.unwrap_or(false))
+        })
+        .await?;
+    // 广播的请求可能在多个窗口弹出，答复后通知其他窗口关闭对话框
+    let _ = app_handle.emit("tool-approval-resolved", resolved);
+    Ok(())
+}
+
+/// 前端挂载/卸载授权对话框时调用，未登记的窗口不会收到授权请求
+#[tauri::command]
+pub fn register_tool_approval_listener(window: tauri::WebviewWindow, active: bool) {
+    set_approval_listener(window.label(), active);
+}
+
+#[tauri::command]
+pub async fn list_tool_approval_rules() -> Result<Vec<ToolApprovalRule>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.load_tool_approval_rules())
+        .await
+}
+
+#[tauri::command]
+pub async fn remove_tool_approval_rule(scope: String, kind: String, pattern: String) -> Result<bool, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.remove_tool_approval_rule(&scope, &kind, &pattern)
+        })
+        .await
+}
+
+/// 列出 Write/Edit 修改记录（新的在前），request_id 为空时返回全部
+#[tauri::command]
+pub async fn list_file_changes(request_id: Option<String>) -> Result<Vec<FileChange>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.list_file_changes(request_id.as_deref())
+        })
+        .await
+}
+
+/// 把文件恢复到某次修改之前的内容（修改前不存在的文件会被删除）
+#[tauri::command]
+pub async fn revert_file_change(change_id: String) -> Result<FileChange, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.revert_file_change(&change_id))
+        .await
+}
+
+#[tauri::command]
+pub async fn list_workspaces() -> Result<Vec<WorkspaceStatus>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.list_workspaces())
+        .await
+}
+
+/// 登记工作区目录，开启自动识别时登记所在项目的根目录
+#[tauri::command]
+pub async fn add_workspace(path: String, name: Option<String>) -> Result<Workspace, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.add_workspace(&path, name))
+        .await
+}
+
+#[tauri::command]
+pub async fn remove_workspace(name: String) -> Result<bool, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.remove_workspace(&name))
+        .await
+}
+
+/// 切换当前工作区（名称或目录路径），Read/Glob/Grep/Bash 默认在该目录下执行；为空时取消
+#[tauri::command]
+pub async fn set_active_workspace(workspace: Option<String>) -> Result<Option<Workspace>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.set_active_workspace(workspace.as_deref())
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn list_agent_presets() -> Result<Vec<AgentPreset>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.list_agent_presets())
+        .await
+}
+
+/// 新建或覆盖同名预设
+#[tauri::command]
+pub async fn save_agent_preset(preset: AgentPreset) -> Result<AgentPreset, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.save_agent_preset(preset))
+        .await
+}
+
+#[tauri::command]
+pub async fn delete_agent_preset(name: String) -> Result<bool, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.delete_agent_preset(&name))
+        .await
+}
+
+/// 按当前脱敏规则改写已保存的摘要和详情（截图不受影响）
+#[tauri::command]
+pub async fn redact_existing_records() -> Result<RedactionReport, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            let config = storage.load_config()?;
+            storage.redact_existing_records(&config.redaction)
+        })
+        .await
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RedactionPreview {
+    pub text: String,
+    pub replacements: usize,
+}
+
+/// 用给定规则（为空时用已保存的规则）试运行脱敏，不修改任何记录
+#[tauri::command]
+pub async fn preview_redaction(text: String, config: Option<RedactionConfig>) -> Result<RedactionPreview, String> {
+    let config = match config {
+        Some(config) => config,
+        None => StorageManager::new().load_config()?.redaction,
+    };
+    let redactor = Redactor::new(&config)?;
+    let mut text = text;
+    let replacements = redactor.redact_text(&mut text);
+    Ok(RedactionPreview { text, replacements })
+}
+
+/// 剪贴板历史（需在设置中开启），最新的在前
+#[tauri::command]
+pub async fn get_clipboard_history(
+    query: Option<String>,
+    date: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::clipboard::ClipboardEntry>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.search_clipboard_history(query.as_deref(), date.as_deref(), None, limit)
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn clear_clipboard_history() -> Result<usize, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.clear_clipboard_history())
+        .await
+}
+
+/// 本地统计（需在设置中开启），数据不会上传
+#[tauri::command]
+pub async fn get_metrics() -> Result<crate::metrics::MetricsSnapshot, String> {
+    tokio::task::spawn_blocking(crate::metrics::snapshot)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reset_metrics() -> Result<(), String> {
+    tokio::task::spawn_blocking(crate::metrics::reset)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 订阅实时日志（log-entry 事件），level 为最低级别，modules 按前缀过滤（如 capture、tool）
+#[tauri::command]
+pub async fn subscribe_logs(
+    level: Option<String>,
+    modules: Option<Vec<String>>,
+    backlog: Option<usize>,
+) -> Result<LogSubscription, String> {
+    let level = level.as_deref().map(LogLevel::parse).unwrap_or(LogLevel::Info);
+    Ok(logs::subscribe(level, modules.unwrap_or_default(), backlog))
+}
+
+#[tauri::command]
+pub async fn unsubscribe_logs(subscription_id: String) -> Result<bool, String> {
+    Ok(logs::unsubscribe(&subscription_id))
+}
+
+/// 运行环境自检（数据目录是否可写、是否位于网络共享/漫游配置、是否按账户隔离）
+#[tauri::command]
+pub async fn run_doctor() -> Result<Vec<DoctorCheck>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| Ok(storage.doctor_checks()))
+        .await
+}
+
+/// 取消问题与工单的关联，之后该问题会重新提醒
+#[tauri::command]
+pub async fn unlink_ticket(alert_key: String) -> Result<bool, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.remove_ticket_link(&alert_key)
+        })
+        .await
+}
+
+/// 提示词实验报告：按变体对比问题检出、置信度、用户评价和复查结果；name 为空时使用当前实验名
+#[tauri::command]
+pub async fn get_experiment_report(
+    name: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<ExperimentReport, String> {
+    let name = match name.filter(|v| !v.trim().is_empty()) {
+        Some(name) => name,
+        None => {
+            let config = StorageManager::new().load_config().map_err(|e| e.to_string())?;
+            match config.capture.prompt_experiment.name.trim() {
+                "" => "default".to_string(),
+                name => name.to_string(),
+            }
+        }
+    };
+    let end = match end_date.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => Local::now().date_naive(),
+    };
+    let start = match start_date.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => end - Duration::days(6),
+    };
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.experiment_report(&name, start, end)
+        })
+        .await
+}
+
+/// 导出日报与对话记录: format = markdown | html | pdf，返回导出文件路径
+#[tauri::command]
+pub async fn export_summaries(
+    start_date: String,
+    end_date: Option<String>,
+    format: String,
+    transcript: Option<Vec<ChatHistoryMessage>>,
+    include_screenshots: Option<bool>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    let start = crate::export::parse_date(&start_date)?;
+    let end = match end_date.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => start,
+    };
+    let request = crate::export::ExportRequest {
+        start_date: start,
+        end_date: end,
+        format: crate::export::ExportFormat::parse(&format)?,
+        transcript: transcript
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|msg| (msg.role == "user" || msg.role == "assistant") && !msg.content.trim().is_empty())
+            .map(|msg| (msg.role, msg.content))
+            .collect(),
+        include_screenshots: include_screenshots.unwrap_or(true),
+        output_path: output_path
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from),
+    };
+    let path = storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            crate::export::export_summaries(storage, &request)
+        })
+        .await?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 导出合规审计记录（签名的 JSONL）：截屏、模型请求端点和工具改动文件
+#[tauri::command]
+pub async fn export_audit_trail(
+    start_date: String,
+    end_date: Option<String>,
+    output_path: Option<String>,
+) -> Result<crate::export::AuditExportResult, String> {
+    let start = crate::export::parse_date(&start_date)?;
+    let end = match end_date.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(value) => crate::export::parse_date(value)?,
+        None => start,
+    };
+    let output_path = output_path
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from);
+    storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            crate::export::export_audit_trail(storage, start, end, output_path)
+        })
+        .await
+}
+
+/// 把某天的截图合成为延时视频（需要本机 ffmpeg）；speed 默认 60 倍速，format 为 mp4 或 webm
+#[tauri::command]
+pub async fn generate_timelapse(
+    date: String,
+    speed: Option<f32>,
+    format: Option<String>,
+    output_path: Option<String>,
+) -> Result<crate::export::TimelapseResult, String> {
+    let date = crate::export::parse_date(&date)?.format("%Y-%m-%d").to_string();
+    let format = crate::export::TimelapseFormat::parse(format.as_deref().unwrap_or_default())?;
+    let speed = speed.unwrap_or(crate::export::DEFAULT_TIMELAPSE_SPEED);
+    let output_path = output_path
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from);
+    // 编码耗时较长，不占用存储队列
+    tokio::task::spawn_blocking(move || {
+        crate::export::generate_timelapse(&StorageManager::new(), &date, speed, format, output_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 导出会话迁移包（会话、工具上下文、引用文件），可在另一台机器上导入继续任务
+#[tauri::command]
+pub async fn export_session(id: String, output_path: Option<String>) -> Result<String, String> {
+    let output_path = output_path
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from);
+    let path = storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            crate::export::export_session(storage, id.trim(), output_path)
+        })
+        .await?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn import_session(path: String) -> Result<SessionImportResult, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            crate::export::import_session(storage, Path::new(path.trim()))
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn open_screenshots_dir(app_handle: AppHandle) -> Result<(), String> {
+    let storage = StorageManager::new();
+    let dir = storage.screenshots_dir()?;
+    let dir_str = dir.to_string_lossy().to_string();
+    app_handle
+        .shell()
+        .open(dir_str, None)
+        .map_err(|e| e.to_string())
+}
+
+/// 读取图片文件并返回 base64 编码
+/// file_type: "attachment" | "screenshot"
+#[tauri::command]
+pub async fn read_image_base64(
+    file_path: String,
+    file_type: Option<String>,
+) -> Result<String, String> {
+    let storage = StorageManager::new();
+    let data_dir = storage.get_data_dir().to_path_buf();
+
+    // 根据类型确定基础目录
+    let base_dir = match file_type.as_deref() {
+        Some("screenshot") => data_dir.join("screenshots"),
+        Some("attachment") => data_dir.join("attachments"),
+        _ => data_dir.clone(),
+    };
+
+    // 构建完整路径
+    let full_path = if Path::new(&file_path).is_absolute() {
+        PathBuf::from(&file_path)
+    } else {
+        base_dir.join(&file_path)
+    };
+
+    // 安全检查：确保路径在数据目录内
+    let canonical = full_path
+        .canonicalize()
+        .map_err(|e| format!("文件不存在: {}", e))?;
+    let data_canonical = data_dir
+        .canonicalize()
+        .map_err(|e| format!("数据目录错误: {}", e))?;
 
     if !canonical.starts_with(&data_canonical) {
         return Err("不允许访问数据目录外的文件".to_string());
@@ -1899,7 +3750,8 @@ pub async fn read_image_base64(
     }
 
     // 读取文件并编码
-    let bytes = fs::read(&canonical).map_err(|e| format!("读取文件失败: {}", e))?;
+    // 截图可能已加密，统一通过存储层读取
+    let bytes = storage.read_data_file(&canonical)?;
 
     // 根据扩展名确定 MIME 类型
     let ext = canonical
@@ -2014,8 +3866,8 @@ pub async fn ensure_bash_runtime(
             let run = timeout(TokioDuration::from_secs(15 * 60), cmd.output()).await;
             match run {
                 Ok(Ok(output)) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                    let stdout = decode_command_output(&output.stdout).trim().to_string();
+                    let stderr = decode_command_output(&output.stderr).trim().to_string();
                     logs.push(format!(
                         "winget {} -> exit {}\nstdout: {}\nstderr: {}",
                         args.join(" "),
@@ -2081,6 +3933,8 @@ pub struct AlertRecord {
     pub help_type: String,
     pub urgency: String,
     pub related_skill: String,
+    pub resolution: String,
+    pub resolution_note: String,
 }
 
 #[tauri::command]
@@ -2096,21 +3950,27 @@ pub async fn get_recent_alerts(since: Option<String>) -> Result<Vec<AlertRecord>
         .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok())
         .and_then(|dt| Local.from_local_datetime(&dt).single());
 
-    let mut records = Vec::new();
-    for i in 0..days {
-        let date = (Local::now() - Duration::days(i as i64))
-            .format("%Y-%m-%d")
-            .to_string();
-        if let Ok(mut daily) = storage.get_summaries(&date) {
-            records.append(&mut daily);
-        }
-    }
+    // 按索引跳过没有问题记录的天，其余并发读取
+    let dates: Vec<String> = (0..days)
+        .map(|i| (Local::now() - Duration::days(i as i64)).format("%Y-%m-%d").to_string())
+        .collect();
+    let mut records: Vec<SummaryRecord> = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            let dates = storage.dates_with_issues(&dates);
+            Ok(storage
+                .load_days_parallel(&dates, |storage, date| storage.get_summaries(date).unwrap_or_default())
+                .into_iter()
+                .flatten()
+                .collect())
+        })
+        .await?;
 
     records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
 
     let mut last_seen: std::collections::HashMap<String, chrono::DateTime<Local>> =
         std::collections::HashMap::new();
     let mut alerts = Vec::new();
+    let suppression_rules = load_suppression_rules();
 
     for record in records {
         if !record.has_issue || record.confidence < threshold {
@@ -2134,6 +3994,12 @@ pub async fn get_recent_alerts(since: Option<String>) -> Result<Vec<AlertRecord>
         } else {
             record.issue_summary.clone()
         };
+        if suppression_rules
+            .iter()
+            .any(|rule| rule.matches(&record.app, &record.scene, &record.issue_type, &message))
+        {
+            continue;
+        }
         let key = format!("{}:{}", record.issue_type, message);
         if let Some(prev) = last_seen.get(&key) {
             if dt.signed_duration_since(*prev).num_seconds() < cooldown {
@@ -2161,12 +4027,197 @@ pub async fn get_recent_alerts(since: Option<String>) -> Result<Vec<AlertRecord>
             },
             urgency: record.urgency,
             related_skill: record.related_skill,
+            resolution: record.resolution,
+            resolution_note: record.resolution_note,
         });
     }
 
     Ok(alerts)
 }
 
+/// 列出提醒屏蔽规则（不同于 capture.alert_rules 中按关键词触发提醒的规则）
+#[tauri::command]
+pub async fn list_alert_rules() -> Result<Vec<SuppressionRule>, String> {
+    Ok(load_suppression_rules())
+}
+
+/// 添加提醒屏蔽规则：按应用、场景、问题类型和提醒内容正则匹配，截屏分析和提醒历史都会跳过命中的提醒
+#[tauri::command]
+pub async fn add_alert_rule(rule: SuppressionRule) -> Result<SuppressionRule, String> {
+    add_suppression_rule(rule)
+}
+
+/// 删除提醒屏蔽规则，返回是否删除了规则
+#[tauri::command]
+pub async fn delete_alert_rule(id: String) -> Result<bool, String> {
+    delete_suppression_rule(id.trim())
+}
+
+/// 暂停某类提醒（key 来自 assistant-alert 事件），duration_minutes 为 0 时取消暂停
+#[tauri::command]
+pub async fn snooze_alert(
+    key: String,
+    duration_minutes: u64,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let manager = state.capture_manager.lock().await;
+    manager.snooze_alert(&key, duration_minutes)
+}
+
+#[derive(serde::Serialize)]
+pub struct AlertRuleTestResult {
+    pub field: String,
+    pub snippet: String,
+    pub message: String,
+}
+
+/// 用示例文本测试提醒规则，未命中返回 None
+#[tauri::command]
+pub async fn test_alert_rule(
+    rule: AlertRule,
+    text: String,
+    app: Option<String>,
+    window_title: Option<String>,
+) -> Result<Option<AlertRuleTestResult>, String> {
+    validate_alert_rule(&rule)?;
+    let mut rule = rule;
+    rule.enabled = true;
+    let app = app.unwrap_or_default();
+    let window_title = window_title.unwrap_or_default();
+    let input = RuleInput {
+        app: &app,
+        window_title: &window_title,
+        ocr_text: Some(&text),
+    };
+    Ok(evaluate_alert_rules(std::slice::from_ref(&rule), &input)
+        .into_iter()
+        .next()
+        .map(|hit| AlertRuleTestResult {
+            field: hit.field.to_string(),
+            snippet: hit.snippet,
+            message: hit.message,
+        }))
+}
+
+const EXPLAIN_CONTEXT_BEFORE: usize = 8;
+const EXPLAIN_CONTEXT_AFTER: usize = 4;
+
+#[derive(serde::Serialize)]
+pub struct AlertExplanation {
+    pub timestamp: String,
+    pub issue_type: String,
+    pub message: String,
+    pub explanation: String,
+    pub related_skill: String,
+    pub used_screenshot: bool,
+}
+
+/// 针对单条提醒做一次深入分析：结合截图与前后记录给出根因、建议的 skill 和命令
+#[tauri::command]
+pub async fn explain_alert(
+    timestamp: String,
+    state: State<'_, AppState>,
+) -> Result<AlertExplanation, String> {
+    let storage = StorageManager::new();
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+    let date = timestamp
+        .get(..10)
+        .ok_or_else(|| format!("无效的时间戳: {}", timestamp))?
+        .to_string();
+
+    let records = storage_actor()
+        .run(StoragePriority::Interactive, move |storage| storage.get_summaries(&date))
+        .await?;
+    let index = records
+        .iter()
+        .position(|record| record.timestamp == timestamp)
+        .ok_or_else(|| format!("未找到该提醒对应的记录: {}", timestamp))?;
+    let record = &records[index];
+
+    let start = index.saturating_sub(EXPLAIN_CONTEXT_BEFORE);
+    let end = (index + 1 + EXPLAIN_CONTEXT_AFTER).min(records.len());
+    let mut surrounding = String::new();
+    for (i, item) in records[start..end].iter().enumerate() {
+        let marker = if start + i == index { " <== 提醒" } else { "" };
+        surrounding.push_str(&format!(
+            "- [{}] ({}) {}{}\n",
+            item.timestamp.get(11..19).unwrap_or(&item.timestamp),
+            item.app,
+            item.summary,
+            marker
+        ));
+    }
+
+    let skill_manager = SkillManager::new();
+    let skills = get_available_skills_cached(&state, &skill_manager).await;
+    let skills_text = if skills.is_empty() {
+        "(无)".to_string()
+    } else {
+        skills
+            .iter()
+            .map(|skill| format!("- {}: {}", skill.name, skill.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let message = if record.issue_summary.is_empty() {
+        record.summary.clone()
+    } else {
+        record.issue_summary.clone()
+    };
+    let prompt = format!(
+        "用户收到了一条提醒，请深入分析其根本原因。\n\n\
+         提醒信息:\n- 时间: {}\n- 应用: {} {}\n- 问题类型: {}\n- 问题: {}\n- 细节: {}\n- 原建议: {}\n\n\
+         前后记录:\n{}\n\n可用的 skills:\n{}\n\n\
+         请用 Markdown 输出：\n1. 根本原因（结合截图与前后记录，说明判断依据）\n\
+         2. 解决步骤（具体可执行）\n3. 可直接运行的命令（如有，使用代码块）\n\
+         4. 推荐的 skill（只能从上面的列表中选择，没有合适的就写“无”）",
+        record.timestamp,
+        record.app,
+        record.window_title,
+        if record.issue_type.is_empty() { "未分类" } else { record.issue_type.as_str() },
+        message,
+        record.detail,
+        record.suggestion,
+        surrounding,
+        skills_text
+    );
+
+    let screenshot = if record.detail_ref.is_empty() {
+        None
+    } else {
+        storage
+            .screenshots_dir()
+            .ok()
+            .and_then(|dir| storage.read_data_file(&dir.join(&record.detail_ref)).ok())
+            .map(|bytes| BASE64.encode(bytes))
+    };
+
+    let model_manager = ModelManager::new();
+    let used_screenshot = screenshot.is_some();
+    let explanation = match screenshot {
+        Some(image_base64) => {
+            model_manager
+                .analyze_image(&config.model, &image_base64, &prompt)
+                .await?
+        }
+        None => {
+            model_manager
+                .chat(&config.model, &surrounding, &prompt)
+                .await?
+        }
+    };
+
+    Ok(AlertExplanation {
+        timestamp: record.timestamp.clone(),
+        issue_type: record.issue_type.clone(),
+        message,
+        explanation,
+        related_skill: record.related_skill.clone(),
+        used_screenshot,
+    })
+}
+
 // ==================== Skills 相关命令 ====================
 
 /// 列出所有可用的 skills
@@ -2176,6 +4227,18 @@ pub async fn list_skills(state: State<'_, AppState>) -> Result<Vec<SkillMetadata
     Ok(get_available_skills_cached(&state, &skill_manager).await)
 }
 
+/// skills 列表相对 since_version 的变化；since_version 为 0 或过旧时返回完整列表
+#[tauri::command]
+pub async fn get_skills_delta(
+    since_version: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<SkillsDelta, String> {
+    let skill_manager = SkillManager::new();
+    get_available_skills_cached(&state, &skill_manager).await;
+    let cache = state.skills_cache.lock().await;
+    Ok(cache.delta_since(since_version.unwrap_or(0)))
+}
+
 /// 获取完整的 skill 信息
 #[tauri::command]
 pub async fn get_skill(name: String) -> Result<Skill, String> {
@@ -2183,7 +4246,7 @@ pub async fn get_skill(name: String) -> Result<Skill, String> {
     skill_manager.load_skill(&name)
 }
 
-/// 调用 skill
+/// 调用 skill；进度事件只发给发起请求的窗口
 #[tauri::command]
 pub async fn invoke_skill(
     name: String,
@@ -2191,39 +4254,68 @@ pub async fn invoke_skill(
     history: Option<Vec<ChatHistoryMessage>>,
     attachments: Option<Vec<AttachmentInput>>,
     request_id: Option<String>,
+    window: WebviewWindow,
     app_handle: AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
+    let window_label = Some(window.label().to_string());
+    invoke_skill_internal(name, args, history, attachments, request_id, window_label, app_handle, state).await
+}
+
+/// 技能调用主流程；window_label 为空时（文件夹监听等后台调用）事件广播给所有窗口
+pub async fn invoke_skill_internal(
+    name: String,
+    args: Option<String>,
+    history: Option<Vec<ChatHistoryMessage>>,
+    attachments: Option<Vec<AttachmentInput>>,
+    request_id: Option<String>,
+    window_label: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
     let storage = StorageManager::new();
     let config = storage.load_config().map_err(|e| e.to_string())?;
     let model_manager = ModelManager::new();
     let skill_manager = SkillManager::new();
     let request_id =
         request_id.unwrap_or_else(|| format!("req-{}", Local::now().timestamp_millis()));
-    let cancel_token = register_cancel_token(&state, &request_id).await;
+    let cancel_guard =
+        register_cancel_token(&state, &request_id, "skill", &format!("/{}", name)).await;
+    let cancel_token = cancel_guard.token().clone();
     let progress = ProgressEmitter::new(
         &app_handle,
         config.ui.show_progress,
         Some(request_id.clone()),
-    );
+        Some(cancel_guard.status()),
+    )
+    .map(|progress| progress.routed_to(event_target(&app_handle, window_label.as_deref())));
     if let Some(ref progress) = progress {
         progress.emit_start(&format!("开始执行技能 /{}", name));
         progress.emit_info("Prepare to run skill".to_string(), None);
         progress.emit_step("调用技能".to_string(), Some(format!("/{}", name)));
     }
-    let result = execute_skill_internal(
-        &storage,
-        &config,
-        &model_manager,
-        &skill_manager,
-        &name,
-        args,
-        history,
-        attachments,
-        Some(&cancel_token),
-        progress.as_ref(),
+    if ModelManager::supports_tools(&config.model) {
+        crate::mcp::sync_servers(&config.tools).await;
+    }
+    let result = with_usage_feature(
+        "skills",
+        execute_skill_internal(
+            &storage,
+            &config,
+            &model_manager,
+            &skill_manager,
+            &name,
+            args,
+            history,
+            attachments,
+            Some(&cancel_token),
+            progress.as_ref(),
+        ),
     )
     .await;
+    if result.is_ok() {
+        crate::skills::record_skill_use(&name);
+    }
     if let Some(ref progress) = progress {
         if result.is_ok() {
             progress.emit_done("处理完成");
@@ -2231,7 +4323,7 @@ pub async fn invoke_skill(
             progress.emit_error("处理失败");
         }
     }
-    clear_cancel_token(&state, &request_id).await;
+    cancel_guard.release().await;
     result
 }
 
@@ -2265,6 +4357,60 @@ pub async fn get_skills_dir() -> Result<String, String> {
     Ok(skill_manager.get_skills_dir().to_string_lossy().to_string())
 }
 
+/// 浏览 skill 市场
+#[tauri::command]
+pub async fn browse_skill_registry() -> Result<Vec<RegistrySkill>, String> {
+    let config = StorageManager::new().load_config()?;
+    crate::skills::registry::fetch_registry(&SkillManager::new(), &config.tools.skill_registry_url)
+        .await
+}
+
+/// 从 skill 市场安装（或升级）skill，安装前弹出系统对话框由用户确认
+#[tauri::command]
+pub async fn install_registry_skill(
+    name: String,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<SkillMetadata, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let config = StorageManager::new().load_config()?;
+    let index_url = config.tools.skill_registry_url.clone();
+    let skill_manager = SkillManager::new();
+    let skills = crate::skills::registry::fetch_registry(&skill_manager, &index_url).await?;
+    let entry = crate::skills::registry::find_registry_skill(&skills, &name)?;
+
+    let message = format!(
+        "安装 skill \"{}\" {}\n作者: {}\n说明: {}\n来源: {}\nSHA-256: {}\n\nskill 可以指导助手执行命令和读写文件，请只安装信任的来源。",
+        entry.name,
+        if entry.version.is_empty() { String::new() } else { format!("v{}", entry.version) },
+        if entry.author.is_empty() { "未知" } else { entry.author.as_str() },
+        entry.description,
+        entry.url,
+        entry.sha256
+    );
+    let dialog_app = app_handle.clone();
+    let confirmed = tokio::task::spawn_blocking(move || {
+        dialog_app
+            .dialog()
+            .message(message)
+            .title("安装 Skill")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel)
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("确认对话框失败: {}", e))?;
+    if !confirmed {
+        return Err("已取消安装".to_string());
+    }
+
+    let metadata =
+        crate::skills::registry::install_registry_skill(&skill_manager, &entry, &index_url).await?;
+    state.bump_skills_version();
+    Ok(metadata)
+}
+
 /// 打开 skills 目录
 #[tauri::command]
 pub async fn open_skills_dir(app_handle: AppHandle) -> Result<(), String> {
@@ -2285,7 +4431,7 @@ pub async fn open_skills_dir(app_handle: AppHandle) -> Result<(), String> {
 
 // ==================== 通知窗口相关命令 ====================
 
-/// 显示通知窗口
+/// 显示提醒通知，按配置使用通知窗口或系统通知
 #[tauri::command]
 pub async fn show_notification(
     app_handle: AppHandle,
@@ -2295,70 +4441,22 @@ pub async fn show_notification(
     summary: String,
     suggestion: String,
     urgency: String,
+    key: Option<String>,
+    actions: Option<Vec<crate::capture::AlertAction>>,
 ) -> Result<(), String> {
-    use tauri::{PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindowBuilder};
-
-    // 检查是否已存在通知窗口
-    if let Some(window) = app_handle.get_webview_window("notification") {
-        // 窗口已存在，发送更新事件
-        let _ = window.emit(
-            "notification-update",
-            serde_json::json!({
-                "intent": intent,
-                "scene": scene,
-                "help_type": help_type,
-                "summary": summary,
-                "suggestion": suggestion,
-                "urgency": urgency,
-            }),
-        );
-        let _ = window.show();
-        let _ = window.set_focus();
-        return Ok(());
-    }
-
-    // 获取主显示器信息以定位窗口到右下角
-    let window_width = 380u32;
-    let window_height = 140u32;
-    let margin = 20i32;
-
-    // 创建新的通知窗口
-    let notification_url = format!(
-        "/notification?intent={}&scene={}&help_type={}&summary={}&suggestion={}&urgency={}",
-        urlencoding::encode(&intent),
-        urlencoding::encode(&scene),
-        urlencoding::encode(&help_type),
-        urlencoding::encode(&summary),
-        urlencoding::encode(&suggestion),
-        urlencoding::encode(&urgency),
-    );
-
-    let window = WebviewWindowBuilder::new(
+    notification::deliver_notification(
         &app_handle,
-        "notification",
-        WebviewUrl::App(notification_url.into()),
+        notification::AlertNotification {
+            key: key.unwrap_or_default(),
+            intent,
+            scene,
+            help_type,
+            summary,
+            suggestion,
+            urgency,
+            actions: actions.unwrap_or_default(),
+        },
     )
-    .title("OpenCowork 提醒")
-    .inner_size(window_width as f64, window_height as f64)
-    .resizable(false)
-    .decorations(false)
-    .always_on_top(true)
-    .skip_taskbar(true)
-    .transparent(true)
-    .build()
-    .map_err(|e| format!("创建通知窗口失败: {}", e))?;
-
-    // 尝试将窗口定位到右下角
-    if let Some(monitor) = window.current_monitor().ok().flatten() {
-        let monitor_size = monitor.size();
-        let monitor_position = monitor.position();
-        let x = monitor_position.x + monitor_size.width as i32 - window_width as i32 - margin;
-        let y =
-            monitor_position.y + monitor_size.height as i32 - window_height as i32 - margin - 40; // 40 for taskbar
-        let _ = window.set_position(PhysicalPosition::new(x, y));
-    }
-
-    Ok(())
 }
 
 /// 关闭通知窗口
@@ -2377,17 +4475,80 @@ pub async fn close_notification(app_handle: AppHandle) -> Result<(), String> {
 pub async fn focus_main_window(app_handle: AppHandle) -> Result<(), String> {
     if let Some(window) = app_handle.get_webview_window("main") {
         let _ = window.show();
-        let _ = window.unminimize();
-        window
-            .set_focus()
-            .map_err(|e| format!("聚焦主窗口失败: {}", e))?;
+        let _ = window.unminimize();
+        window
+            .set_focus()
+            .map_err(|e| format!("聚焦主窗口失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 打开快捷提问窗口，并把当前屏幕截图作为附件预置进去
+#[tauri::command]
+pub async fn open_quick_ask(app_handle: AppHandle) -> Result<(), String> {
+    use tauri::{WebviewUrl, WebviewWindowBuilder};
+
+    let storage = StorageManager::new();
+    let attachments_dir = storage.get_data_dir().join("attachments");
+    fs::create_dir_all(&attachments_dir).map_err(|e| format!("创建附件目录失败: {}", e))?;
+
+    // 在窗口弹出前截屏，避免把提问窗口本身截进去；命中隐私规则时不附带截图
+    let config = storage.load_config().map_err(|e| e.to_string())?;
+    let now = Local::now();
+    let image = match capture_with_privacy(&config.capture, get_active_window().as_ref(), &now)? {
+        GuardedCapture::Captured(image) => Some(image),
+        GuardedCapture::Blocked(reason) => {
+            crate::logs::info("quick_ask", format!("隐私规则命中，不附带截图: {}", reason));
+            None
+        }
+        GuardedCapture::TargetMissing => None,
+    };
+    let attachment = match image {
+        Some(image) => {
+            let filename = format!("quick-ask-{}.jpg", now.format("%Y%m%d-%H%M%S"));
+            let path = attachments_dir.join(&filename);
+            // 与截图相同，启用存储加密时写入密文
+            let bytes = crate::capture::ScreenCapture::image_to_jpeg_bytes(&image, config.capture.compress_quality)?;
+            storage.write_data_file(&path, &bytes)?;
+            Some(AttachmentInput {
+                path: path.to_string_lossy().to_string(),
+                name: filename,
+                kind: Some("image".to_string()),
+            })
+        }
+        None => None,
+    };
+
+    if let Some(window) = app_handle.get_webview_window("quick-ask") {
+        if let Some(attachment) = &attachment {
+            let _ = app_handle.emit_to("quick-ask", "quick-ask-attachment", attachment);
+        }
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
     }
+
+    let url = match &attachment {
+        Some(attachment) => format!("/?quick_ask=1&attachment={}", urlencoding::encode(&attachment.path)),
+        None => "/?quick_ask=1".to_string(),
+    };
+    let window = WebviewWindowBuilder::new(&app_handle, "quick-ask", WebviewUrl::App(url.into()))
+        .title("OpenCowork 快捷提问")
+        .inner_size(520.0, 640.0)
+        .always_on_top(true)
+        .center()
+        .build()
+        .map_err(|e| format!("创建快捷提问窗口失败: {}", e))?;
+    let _ = window.set_focus();
     Ok(())
 }
 
 const MAX_ATTACHMENT_BYTES: u64 = 5 * 1024 * 1024;
 const MAX_ATTACHMENT_TEXT_CHARS: usize = 8000;
 const MAX_ATTACHMENT_IMAGES: usize = 4;
+// PDF 每页至少保留的字符数，页数多时按总长度平均分配
+const MIN_PDF_PAGE_CHARS: usize = 400;
+const PDF_PREVIEW_DPI: &str = "110";
 
 fn merge_user_message(message: &str, attachment_text: &str, has_attachments: bool) -> String {
     let mut merged = message.trim().to_string();
@@ -2403,7 +4564,8 @@ fn merge_user_message(message: &str, attachment_text: &str, has_attachments: boo
     merged
 }
 
-fn build_attachment_payload(attachments: &[AttachmentInput]) -> AttachmentPayload {
+/// render_pdf_preview 为 true 时把 PDF 首页渲染成图片一并发送（用于支持图片输入的模型）
+fn build_attachment_payload(attachments: &[AttachmentInput], render_pdf_preview: bool) -> AttachmentPayload {
     if attachments.is_empty() {
         return AttachmentPayload::default();
     }
@@ -2422,6 +4584,27 @@ fn build_attachment_payload(attachments: &[AttachmentInput]) -> AttachmentPayloa
         let name = attachment_name(&attachment.path, &attachment.name);
         let ext = attachment_extension(&attachment.path);
 
+        // 网址附件已抓取到 attachments/ 下的缓存文件，路径仍是网址说明抓取失败
+        if attachment.kind.as_deref() == Some("url") {
+            match fs::read_to_string(&attachment.path) {
+                Ok(content) => {
+                    let (content, truncated) = truncate_string(content.trim(), MAX_ATTACHMENT_TEXT_CHARS);
+                    let suffix = if truncated { "\n...(已截断)" } else { "" };
+                    doc_sections.push(format!("### {}（网页）\n{}{}", name, content, suffix));
+                }
+                Err(_) => notes.push(format!("- {} (无法获取网页内容)", name)),
+            }
+            continue;
+        }
+
+        if Path::new(&attachment.path).is_dir() {
+            match dir_attachment::summarize_directory(Path::new(&attachment.path), &name) {
+                Ok(summary) => doc_sections.push(summary),
+                Err(err) => notes.push(format!("- {} (读取目录失败: {})", name, err)),
+            }
+            continue;
+        }
+
         if let Ok(meta) = fs::metadata(&attachment.path) {
             if meta.len() > MAX_ATTACHMENT_BYTES {
                 notes.push(format!("- {} (文件过大，已跳过内容)", name));
@@ -2438,7 +4621,8 @@ fn build_attachment_payload(attachments: &[AttachmentInput]) -> AttachmentPayloa
                 notes.push(format!("- {} (图片数量超过限制)", name));
                 continue;
             }
-            match fs::read(&attachment.path) {
+            // 快捷提问等应用内保存的图片可能已加密，通过存储层读取（明文文件原样返回）
+            match StorageManager::new().read_data_file(Path::new(&attachment.path)) {
                 Ok(bytes) => {
                     let encoded = BASE64.encode(bytes);
                     let mime = image_mime(&ext);
@@ -2479,6 +4663,8 @@ fn build_attachment_payload(attachments: &[AttachmentInput]) -> AttachmentPayloa
             let parsed = match ext.as_str() {
                 "docx" => extract_docx_text(&attachment.path, MAX_ATTACHMENT_TEXT_CHARS),
                 "xlsx" => extract_xlsx_text(&attachment.path, MAX_ATTACHMENT_TEXT_CHARS),
+                "pptx" => extract_pptx_text(&attachment.path, MAX_ATTACHMENT_TEXT_CHARS),
+                "odt" | "ods" | "odp" => extract_odf_text(&attachment.path, MAX_ATTACHMENT_TEXT_CHARS),
                 _ => Err(format!("不支持的 Office 格式: {}", ext)),
             };
             match parsed {
@@ -2501,6 +4687,36 @@ fn build_attachment_payload(attachments: &[AttachmentInput]) -> AttachmentPayloa
             continue;
         }
 
+        if ext == "pdf" {
+            let pages = match extract_pdf_pages(&attachment.path) {
+                Ok(pages) => pages,
+                Err(err) => {
+                    notes.push(format!("- {} (解析失败: {})", name, err));
+                    continue;
+                }
+            };
+            let content = format_pdf_pages(&pages, MAX_ATTACHMENT_TEXT_CHARS);
+            if content.is_empty() {
+                notes.push(format!("- {} (PDF，共 {} 页，未提取到文字，可能是扫描件)", name, pages.len()));
+            } else {
+                doc_sections.push(format!("### {}（PDF，共 {} 页）\n{}", name, pages.len(), content));
+            }
+            if render_pdf_preview && image_urls.len() < MAX_ATTACHMENT_IMAGES {
+                match render_pdf_first_page(&attachment.path) {
+                    Ok(bytes) => {
+                        let encoded = BASE64.encode(bytes);
+                        image_urls.push(format!("data:image/png;base64,{}", encoded));
+                        image_base64.push(encoded);
+                        image_names.push(format!("{} 第 1 页", name));
+                    }
+                    Err(err) => {
+                        crate::logs::warn("attachment", format!("渲染 PDF 首页失败: {}", err));
+                    }
+                }
+            }
+            continue;
+        }
+
         notes.push(format!("- {} (二进制附件，未解析内容)", name));
     }
 
@@ -2588,7 +4804,64 @@ fn is_text_doc_ext(ext: &str) -> bool {
 }
 
 fn is_office_doc_ext(ext: &str) -> bool {
-    matches!(ext, "docx" | "xlsx")
+    matches!(ext, "docx" | "xlsx" | "pptx" | "odt" | "ods" | "odp")
+}
+
+fn extract_pdf_pages(path: &str) -> Result<Vec<String>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("读取失败: {}", e))?;
+    // pdf-extract 遇到个别不规范的 PDF 会 panic，这里兜住当作解析失败
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(&bytes))
+        .map_err(|_| "PDF 格式无法识别".to_string())?
+        .map_err(|e| format!("解析 PDF 失败: {}", e))
+}
+
+/// 按页拼接 PDF 文字，每页按平均额度截断，总长度不超过 max_chars
+fn format_pdf_pages(pages: &[String], max_chars: usize) -> String {
+    let per_page = (max_chars / pages.len().max(1)).max(MIN_PDF_PAGE_CHARS);
+    let mut output = String::new();
+    let mut used = 0usize;
+    for (index, page) in pages.iter().enumerate() {
+        let text = page.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            continue;
+        }
+        if used >= max_chars {
+            output.push_str(&format!("\n...(其余 {} 页已省略)", pages.len() - index));
+            break;
+        }
+        let budget = per_page.min(max_chars - used);
+        let count = text.chars().count();
+        let page_text: String = text.chars().take(budget).collect();
+        used += page_text.chars().count();
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("--- 第 {} 页 ---\n{}", index + 1, page_text));
+        if count > budget {
+            output.push_str("...(本页已截断)");
+        }
+    }
+    output
+}
+
+/// 调用 poppler 的 pdftoppm 把首页渲染成 PNG；未安装时返回错误，只影响预览图
+fn render_pdf_first_page(path: &str) -> Result<Vec<u8>, String> {
+    let prefix = std::env::temp_dir().join(format!("opencowork-pdf-{}", Local::now().timestamp_millis()));
+    let output = std::process::Command::new("pdftoppm")
+        .args(["-f", "1", "-l", "1", "-png", "-singlefile", "-r", PDF_PREVIEW_DPI])
+        .arg(path)
+        .arg(&prefix)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("无法运行 pdftoppm（需安装 poppler）: {}", e))?;
+    let image_path = prefix.with_extension("png");
+    if !output.status.success() {
+        let _ = fs::remove_file(&image_path);
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let bytes = fs::read(&image_path).map_err(|e| format!("读取渲染结果失败: {}", e));
+    let _ = fs::remove_file(&image_path);
+    bytes
 }
 
 fn extract_docx_text(path: &str, max_chars: usize) -> Result<String, String> {
@@ -2766,6 +5039,174 @@ fn extract_xlsx_text(path: &str, max_chars: usize) -> Result<String, String> {
     Ok(output)
 }
 
+fn extract_pptx_text(path: &str, max_chars: usize) -> Result<String, String> {
+    let file = fs::File::open(path).map_err(|e| format!("读取失败: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("打开压缩失败: {}", e))?;
+
+    // ppt/slides/slide{N}.xml，按编号排序（slide10 在 slide9 之后）
+    let mut slides: Vec<(u32, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?;
+            Some((number.parse().ok()?, name.to_string()))
+        })
+        .collect();
+    if slides.is_empty() {
+        return Err("未找到幻灯片内容".to_string());
+    }
+    slides.sort();
+
+    let mut output = String::new();
+    for (number, slide_name) in slides {
+        if output.len() >= max_chars {
+            break;
+        }
+        let slide_file = archive
+            .by_name(&slide_name)
+            .map_err(|e| format!("读取幻灯片失败: {}", e))?;
+        let mut reader = Reader::from_reader(BufReader::new(slide_file));
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut slide_text = String::new();
+        let mut in_text = false;
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(e)) => {
+                    if e.name().as_ref() == b"a:t" {
+                        in_text = true;
+                    }
+                }
+                Ok(Event::End(e)) => match e.name().as_ref() {
+                    b"a:t" => in_text = false,
+                    b"a:p" => {
+                        if !slide_text.is_empty() && !slide_text.ends_with('\n') {
+                            slide_text.push('\n');
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(Event::Text(e)) => {
+                    if in_text {
+                        let content = e
+                            .unescape()
+                            .map_err(|err| format!("解析 PPT 失败: {}", err))?;
+                        slide_text.push_str(&content);
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(err) => return Err(format!("解析 PPT 失败: {}", err)),
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        let slide_text = slide_text.trim();
+        if !slide_text.is_empty() {
+            output.push_str(&format!("Slide {}:\n{}\n\n", number, slide_text));
+        }
+    }
+
+    Ok(output)
+}
+
+/// OpenDocument（odt/ods/odp）的正文都在 content.xml：段落按行输出，表格单元格用制表符分隔
+fn extract_odf_text(path: &str, max_chars: usize) -> Result<String, String> {
+    let file = fs::File::open(path).map_err(|e| format!("读取失败: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("打开压缩失败: {}", e))?;
+    let content_file = archive
+        .by_name("content.xml")
+        .map_err(|_| "未找到 content.xml".to_string())?;
+
+    let mut reader = Reader::from_reader(BufReader::new(content_file));
+    reader.trim_text(false);
+
+    let mut buf = Vec::new();
+    let mut output = String::new();
+    let mut row = String::new();
+    let mut cell_depth = 0usize;
+    let mut in_body = false;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => match e.name().as_ref() {
+                b"office:body" => in_body = true,
+                b"table:table" => {
+                    for attr in e.attributes().with_checks(false).flatten() {
+                        if attr.key.as_ref() == b"table:name" {
+                            let name = attr
+                                .unescape_value()
+                                .map_err(|err| format!("解析 OpenDocument 失败: {}", err))?;
+                            output.push_str(&format!("Sheet: {}\n", name));
+                        }
+                    }
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => cell_depth += 1,
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => {
+                let target = if cell_depth > 0 { &mut row } else { &mut output };
+                match e.name().as_ref() {
+                    b"text:s" => target.push(' '),
+                    b"text:tab" => target.push('\t'),
+                    b"text:line-break" => target.push('\n'),
+                    b"table:table-cell" | b"table:covered-table-cell" => row.push('\t'),
+                    _ => {}
+                }
+            }
+            Ok(Event::End(e)) => match e.name().as_ref() {
+                b"office:body" => in_body = false,
+                b"text:p" | b"text:h" => {
+                    if cell_depth > 0 {
+                        row.push(' ');
+                    } else if !output.is_empty() && !output.ends_with('\n') {
+                        output.push('\n');
+                    }
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    cell_depth = cell_depth.saturating_sub(1);
+                    if cell_depth == 0 {
+                        let trimmed = row.trim_end_matches(' ').len();
+                        row.truncate(trimmed);
+                        row.push('\t');
+                    }
+                }
+                b"table:table-row" => {
+                    let line = row.trim_end();
+                    if !line.trim().is_empty() {
+                        output.push_str(line);
+                        output.push('\n');
+                    }
+                    row.clear();
+                }
+                b"table:table" => output.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Text(e)) => {
+                if in_body {
+                    let content = e
+                        .unescape()
+                        .map_err(|err| format!("解析 OpenDocument 失败: {}", err))?;
+                    if cell_depth > 0 {
+                        row.push_str(&content);
+                    } else {
+                        output.push_str(&content);
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(err) => return Err(format!("解析 OpenDocument 失败: {}", err)),
+            _ => {}
+        }
+        if output.len() >= max_chars {
+            break;
+        }
+        buf.clear();
+    }
+
+    Ok(output)
+}
+
 fn read_shared_strings(archive: &mut ZipArchive<fs::File>) -> Result<Vec<String>, String> {
     let file = match archive.by_name("xl/sharedStrings.xml") {
         Ok(file) => file,
@@ -2869,13 +5310,20 @@ fn build_tool_access(
         allowed_dirs.push(normalize_path(&resolved));
     }
 
+    // 当前工作区是用户主动登记的目录，视为允许访问
+    let workspace_dir = crate::workspaces::active_workspace_dir(config).map(|dir| normalize_path(&dir));
+    if let Some(dir) = &workspace_dir {
+        if !allowed_dirs.iter().any(|allowed| dir.starts_with(allowed)) {
+            allowed_dirs.push(dir.clone());
+        }
+    }
+
     if allowed_dirs.is_empty() {
         allowed_dirs.push(normalize_path(&data_dir));
     }
 
-    let default_base_dir = allowed_dirs
-        .get(0)
-        .cloned()
+    let default_base_dir = workspace_dir
+        .or_else(|| allowed_dirs.get(0).cloned())
         .unwrap_or_else(|| normalize_path(&data_dir));
     let base_dir = if let Some(dir) = preferred_base_dir {
         let preferred = normalize_path(dir);
@@ -3292,7 +5740,84 @@ fn read_file_tool(access: &ToolAccess, args: ReadArgs) -> Result<String, String>
     Ok(text)
 }
 
-fn write_file_tool(access: &ToolAccess, args: WriteArgs) -> Result<String, String> {
+/// 本地 OCR：按记录时间戳读取其截图，或识别指定图片
+async fn ocr_tool(
+    access: &ToolAccess,
+    storage: &StorageManager,
+    config: &Config,
+    args: &serde_json::Value,
+) -> Result<String, String> {
+    let screenshots_dir = storage.screenshots_dir()?;
+    let timestamp = args
+        .get("timestamp")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty());
+    let path_arg = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty());
+
+    let path = if let Some(timestamp) = timestamp {
+        let date = timestamp
+            .get(..10)
+            .ok_or_else(|| format!("无效的时间戳: {}", timestamp))?;
+        let date = date.to_string();
+        let records = storage_actor()
+            .run(StoragePriority::Interactive, move |storage| storage.get_summaries(&date))
+            .await?;
+        let record = records
+            .iter()
+            .find(|record| record.timestamp == timestamp)
+            .ok_or_else(|| format!("未找到记录: {}", timestamp))?;
+        if record.detail_ref.is_empty() {
+            return Err(format!("该记录没有保存截图: {}", timestamp));
+        }
+        screenshots_dir.join(&record.detail_ref)
+    } else if let Some(path) = path_arg {
+        // 相对路径仅在规范化后仍位于截图目录内时直接放行（防止 ../ 逃逸），其余按白名单检查
+        let in_screenshots = if Path::new(path).is_absolute() {
+            None
+        } else {
+            let root = screenshots_dir.canonicalize().ok();
+            screenshots_dir
+                .join(path)
+                .canonicalize()
+                .ok()
+                .filter(|candidate| candidate.is_file())
+                .filter(|candidate| root.as_ref().map_or(false, |root| candidate.starts_with(root)))
+        };
+        match in_screenshots {
+            Some(candidate) => candidate,
+            None => {
+                if access.mode == "unset" {
+                    return Err(TOOL_MODE_UNSET_ERROR.to_string());
+                }
+                ensure_path_allowed(access, path)?
+            }
+        }
+    } else {
+        return Err("Missing timestamp or path parameter".to_string());
+    };
+
+    let text = crate::capture::ocr_image_file(&path, &config.capture).await?;
+    if text.trim().is_empty() {
+        return Ok("(no text recognized)".to_string());
+    }
+    let (text, truncated) = truncate_string(&text, DEFAULT_MAX_READ_BYTES);
+    if truncated {
+        Ok(format!("{}\n\n[truncated]", text))
+    } else {
+        Ok(text)
+    }
+}
+
+async fn write_file_tool(
+    access: &ToolAccess,
+    args: WriteArgs,
+    review: &FileChangeReview<'_>,
+) -> Result<String, String> {
     if access.mode == "unset" {
         return Err(TOOL_MODE_UNSET_ERROR.to_string());
     }
@@ -3300,22 +5825,31 @@ fn write_file_tool(access: &ToolAccess, args: WriteArgs) -> Result<String, Strin
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
     }
-    if args.append.unwrap_or(false) {
-        use std::io::Write;
-        let mut file = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)
-            .map_err(|e| format!("写入失败: {}", e))?;
-        file.write_all(args.content.as_bytes())
-            .map_err(|e| format!("写入失败: {}", e))?;
+    let original = fs::read(&path).ok();
+    let old = original.as_deref().unwrap_or_default();
+    let content = if args.append.unwrap_or(false) {
+        let mut content = old.to_vec();
+        content.extend_from_slice(args.content.as_bytes());
+        content
     } else {
-        fs::write(&path, args.content.as_bytes()).map_err(|e| format!("写入失败: {}", e))?;
-    }
-    Ok(format!("写入成功: {}", path.display()))
-}
-
-fn edit_file_tool(access: &ToolAccess, args: EditArgs) -> Result<String, String> {
+        args.content.into_bytes()
+    };
+    let diff = review.review(&path, old, &content).await?;
+    review.record_original(&path, original.as_deref())?;
+    write_atomic(&path, &content)?;
+    Ok(format!(
+        "写入成功: {} (+{} -{})",
+        path.display(),
+        diff.added,
+        diff.removed
+    ))
+}
+
+async fn edit_file_tool(
+    access: &ToolAccess,
+    args: EditArgs,
+    review: &FileChangeReview<'_>,
+) -> Result<String, String> {
     if access.mode == "unset" {
         return Err(TOOL_MODE_UNSET_ERROR.to_string());
     }
@@ -3331,7 +5865,11 @@ fn edit_file_tool(access: &ToolAccess, args: EditArgs) -> Result<String, String>
     if updated == content {
         return Ok("未找到可替换内容".to_string());
     }
-    fs::write(&path, updated.as_bytes()).map_err(|e| format!("写入失败: {}", e))?;
+    review
+        .review(&path, content.as_bytes(), updated.as_bytes())
+        .await?;
+    review.record_original(&path, Some(content.as_bytes()))?;
+    write_atomic(&path, updated.as_bytes())?;
     Ok(format!("替换完成: {} 处", count))
 }
 
@@ -3501,29 +6039,11 @@ async fn run_command_tool(access: &ToolAccess, args: BashArgs) -> Result<String,
         .max(1_000);
 
     if command_requests_background(&args.command) {
-        fs::create_dir_all(&access.tasks_dir)
-            .map_err(|e| format!("create tasks dir failed: {}", e))?;
-        let task_id = next_background_task_id();
-        let output_path = access.tasks_dir.join(format!("{}.output", task_id));
-        let stdout_file =
-            fs::File::create(&output_path).map_err(|e| format!("create output file failed: {}", e))?;
-        let stderr_file = stdout_file
-            .try_clone()
-            .map_err(|e| format!("prepare stderr output file failed: {}", e))?;
-
-        let mut bg_cmd = build_shell_command(&args.command);
-        bg_cmd
-            .current_dir(&cwd)
-            .stdout(Stdio::from(stdout_file))
-            .stderr(Stdio::from(stderr_file));
-        bg_cmd
-            .spawn()
-            .map_err(|e| format!("start background command failed: {}", e))?;
-
+        let task = background_task_manager().spawn(&args.command, &cwd, &access.tasks_dir)?;
         return Ok(format!(
-            "Command running in background with ID: {}. Output is being written to: {}",
-            task_id,
-            output_path.display()
+            "Command running in background with ID: {}. Output is being written to: {}. Use task_status to check progress.",
+            task.id,
+            task.output_path
         ));
     }
 
@@ -3537,8 +6057,8 @@ async fn run_command_tool(access: &ToolAccess, args: BashArgs) -> Result<String,
         .map_err(|_| "命令超时".to_string())?
         .map_err(|e| format!("执行失败: {}", e))?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let stdout = decode_command_output(&output.stdout);
+    let stderr = decode_command_output(&output.stderr);
     let mut response = format!("exit_code: {}\n", output.status.code().unwrap_or(-1));
 
     if !stdout.trim().is_empty() {
@@ -3563,6 +6083,42 @@ async fn run_command_tool(access: &ToolAccess, args: BashArgs) -> Result<String,
     Ok(response.trim_end().to_string())
 }
 
+/// 解码命令输出：优先 UTF-8，Windows 下回退到控制台代码页（如 GBK）
+fn decode_command_output(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let code_page = unsafe { windows_sys::Win32::Globalization::GetOEMCP() };
+        if let Some(encoding) = encoding_for_code_page(code_page) {
+            let (text, _, _) = encoding.decode(bytes);
+            return text.into_owned();
+        }
+    }
+
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+#[cfg(target_os = "windows")]
+fn encoding_for_code_page(code_page: u32) -> Option<&'static encoding_rs::Encoding> {
+    let encoding = match code_page {
+        936 => encoding_rs::GBK,
+        54936 => encoding_rs::GB18030,
+        950 => encoding_rs::BIG5,
+        932 => encoding_rs::SHIFT_JIS,
+        949 => encoding_rs::EUC_KR,
+        866 => encoding_rs::IBM866,
+        1250 => encoding_rs::WINDOWS_1250,
+        1251 => encoding_rs::WINDOWS_1251,
+        1252 => encoding_rs::WINDOWS_1252,
+        65001 => encoding_rs::UTF_8,
+        _ => return None,
+    };
+    Some(encoding)
+}
+
 #[cfg(target_os = "windows")]
 fn build_shell_command(command: &str) -> TokioCommand {
     if let Some(bash_path) = find_windows_bash_path() {
@@ -3687,22 +6243,34 @@ fn build_skill_execution_system_prompt(context: &str, skills_dir: &Path, skill_b
     )
 }
 
+/// 技能较多时按与问题的相关度和使用情况排序，只列出前 max_skills 个
 fn build_tool_system_prompt(
     context: &str,
     skills_dir: &Path,
     available_skills: &[SkillMetadata],
+    query: &str,
+    max_skills: usize,
 ) -> String {
     // 构建可用技能列表
     let skills_section = if available_skills.is_empty() {
         "当前没有已安装的技能。你可以使用 manage_skill 工具创建新技能。".to_string()
     } else {
-        let skills_list: Vec<String> = available_skills
+        let invocable: Vec<&SkillMetadata> =
+            available_skills.iter().filter(|s| is_model_invocable_skill(s)).collect();
+        let (ranked, omitted) =
+            crate::skills::rank_skills(invocable, query, &crate::skills::load_skill_usage(), max_skills, Local::now());
+        let skills_list: Vec<String> = ranked
             .iter()
-            .filter(|s| is_model_invocable_skill(s))
             .map(|s| format!("- {}: {}", s.name, s.description))
             .collect();
         if skills_list.is_empty() {
             "当前没有用户可调用的技能。".to_string()
+        } else if omitted > 0 {
+            format!(
+                "以下是与当前问题最相关或最常用的技能，可通过 invoke_skill 工具调用：\n{}\n（另有 {} 个技能未列出，如需可在技能目录中查找名称后调用）",
+                skills_list.join("\n"),
+                omitted
+            )
         } else {
             format!(
                 "以下是已安装的技能，可通过 invoke_skill 工具调用：\n{}",
@@ -3752,7 +6320,14 @@ fn build_tool_system_prompt(
 1. 如果需要某个技能完成任务，请调用 invoke_skill，skill_name 必须是上面列出的技能名称之一。
 2. 如果需要创建/更新/删除技能，请调用 manage_skill。
 3. 可用 Read/Write/Edit/Update/Glob/Grep 读取与搜索文件。
-4. 可用 Bash/run_command 运行命令（受权限限制）。"#,
+4. 可用 Bash/run_command 运行命令（受权限限制）；以 & 结尾的命令在后台运行，可用 task_status 查看状态和输出。
+5. 需要查看历史截图上的文字时，可调用 ocr（传入记录时间戳）在本地识别，无需视觉模型。
+6. 用户提到之前保存的命令、配置或代码片段时，可调用 snippets 搜索（search）并读取（get）。
+7. 查看或操作 git 仓库时优先使用 git 工具（status/diff/log/branch 只读；add/commit/restore 会修改仓库），不要通过 Bash 运行 git。
+8. 需要打开网页、点击、填写表单或读取页面内容时使用 browser 工具：先 navigate，再用 snapshot 获取元素引用（@e1 等）后 click/type，extract_text 读取文字，screenshot 截图会以图片形式返回。
+9. 用户问“刚才/之前复制了什么”时调用 clipboard_read（history=true 查询复制历史）；需要把生成的文本交给用户粘贴时可调用 clipboard_write。
+10. 用户开启桌面自动化后，可用 ui_action 操作鼠标键盘（move/click/type/key/scroll，可用 steps 连续执行）；坐标以截图中的屏幕像素为准，鼠标移到屏幕左上角会紧急停止。
+11. 用户希望调整截屏间隔、对话上下文模式或提醒置信度阈值时，先用 manage_config 的 get 查看当前值，再用 propose 提议修改；修改需用户在界面上确认后才会保存。"#,
         context, skills_section
     )
 }
@@ -3761,6 +6336,7 @@ fn build_tool_system_prompt(
 struct ToolLoopResult {
     response: String,
     tool_context: Vec<ToolContextMessage>,
+    planned_actions: Vec<PlannedAction>,
 }
 
 async fn run_tool_loop(
@@ -3772,15 +6348,18 @@ async fn run_tool_loop(
     mut result: ChatWithToolsResult,
     available_skills: &[SkillMetadata],
     allowed_tools: &Option<Vec<String>>,
+    approval_scope: &str,
+    plan_only: bool,
     preferred_base_dir: Option<&Path>,
     cancel_token: Option<&CancellationToken>,
     progress: Option<&ProgressEmitter>,
-) -> Result<ToolLoopResult, String> {
+) -> Result<ToolLoopResult, AppError> {
     let access = build_tool_access(config, storage, preferred_base_dir);
     let mut loops = 0usize;
     let mut last_tool_calls: Option<Vec<(String, String)>> = None;
     let mut repeat_loops = 0usize;
     let mut collected_tool_context: Vec<ToolContextMessage> = Vec::new();
+    let mut planned_actions: Vec<PlannedAction> = Vec::new();
 
     loop {
         check_cancel(cancel_token)?;
@@ -3794,6 +6373,7 @@ async fn run_tool_loop(
                 return Ok(ToolLoopResult {
                     response: text,
                     tool_context: collected_tool_context,
+                    planned_actions,
                 });
             }
             ChatWithToolsResult::ToolCalls { calls, messages } => {
@@ -3813,6 +6393,7 @@ async fn run_tool_loop(
                             MAX_TOOL_LOOPS, pending_hint
                         ),
                         tool_context: collected_tool_context,
+                        planned_actions,
                     });
                 }
 
@@ -3838,9 +6419,32 @@ async fn run_tool_loop(
                     .collect();
 
                 let mut tool_results = Vec::new();
+                let mut tool_images: Vec<String> = Vec::new();
                 for call in &calls {
                     check_cancel(cancel_token)?;
-                    let output_result = if let Some(token) = cancel_token {
+                    // 预演模式：有副作用的工具只返回将要执行的操作
+                    let simulated = if plan_only {
+                        simulate_tool_call(&access, &call.function.name, &call.function.arguments)
+                    } else {
+                        None
+                    };
+                    let tool_started = std::time::Instant::now();
+                    logs::debug(
+                        "tool",
+                        format!(
+                            "{} 参数: {}",
+                            call.function.name,
+                            truncate_string(&call.function.arguments, 500).0
+                        ),
+                    );
+                    let output_result = if let Some(action) = simulated {
+                        if let Some(progress) = progress {
+                            progress.emit_step("预演（未执行）".to_string(), Some(action.target.clone()));
+                        }
+                        let output = action.tool_output();
+                        planned_actions.push(action);
+                        Ok(output)
+                    } else if let Some(token) = cancel_token {
                         await_with_cancel(
                             token,
                             execute_tool_call(
@@ -3852,6 +6456,7 @@ async fn run_tool_loop(
                                 skill_manager,
                                 available_skills,
                                 allowed_tools,
+                                approval_scope,
                                 Some(token),
                                 progress,
                             ),
@@ -3867,20 +6472,46 @@ async fn run_tool_loop(
                             skill_manager,
                             available_skills,
                             allowed_tools,
+                            approval_scope,
                             None,
                             progress,
                         )
                         .await
                     };
+                    if let Some(progress) = progress {
+                        progress.set_current_tool(None);
+                    }
+                    crate::metrics::record_tool_call(
+                        &call.function.name,
+                        tool_started.elapsed(),
+                        output_result.is_ok(),
+                    );
+                    let elapsed_ms = tool_started.elapsed().as_millis();
+                    match &output_result {
+                        Ok(_) => logs::info("tool", format!("{} 完成（{} ms）", call.function.name, elapsed_ms)),
+                        Err(err) => logs::warn(
+                            "tool",
+                            format!("{} 失败（{} ms）: {}", call.function.name, elapsed_ms, err),
+                        ),
+                    }
                     let output = match output_result {
                         Ok(text) => text,
-                        Err(err) => {
-                            if err == TOOL_MODE_UNSET_ERROR || err == REQUEST_CANCELLED_ERROR {
-                                return Err(err);
-                            }
-                            format!("{} {}", TOOL_ERROR_PREFIX, err)
-                        }
+                        Err(err @ (AppError::ToolModeUnset | AppError::Cancelled)) => return Err(err),
+                        Err(err) => format!("{} {}", TOOL_ERROR_PREFIX, err),
                     };
+                    // 浏览器截图作为图片随下一次请求发给模型
+                    let (output, images) = browser::take_tool_images(&output);
+                    if config.browser.send_screenshots {
+                        tool_images.extend(images);
+                    }
+                    let output = summarize_tool_output_if_needed(
+                        config,
+                        model_manager,
+                        &call.function.name,
+                        output,
+                        progress,
+                    )
+                    .await;
                     tool_results.push((call.id.clone(), output.clone()));
 
                     let persisted_output =
@@ -3919,16 +6550,18 @@ async fn run_tool_loop(
                             pending_hint
                         ),
                         tool_context: collected_tool_context,
+                        planned_actions,
                     });
                 }
 
                 let next_result = if let Some(token) = cancel_token {
-                    retry_with_cancel(token, progress, "model", || {
+                    retry_with_cancel(token, progress, config.model.retry_policy(), "model", || {
                         model_manager.continue_with_tool_results_filtered(
                             &config.model,
                             system_prompt,
                             messages.clone(),
                             tool_results.clone(),
+                            &tool_images,
                             available_skills,
                             allowed_tools,
                         )
@@ -3941,14 +6574,16 @@ async fn run_tool_loop(
                             system_prompt,
                             messages.clone(),
                             tool_results.clone(),
+                            &tool_images,
                             available_skills,
                             allowed_tools,
                         )
                         .await
+                        .map_err(AppError::from)
                 };
                 result = match next_result {
                     Ok(value) => value,
-                    Err(err) if is_context_overflow_error(&err) => {
+                    Err(AppError::ContextOverflow { .. }) => {
                         if let Some(progress) = progress {
                             progress.emit_info(
                                 "Tool context too large; retrying with truncated tool output"
@@ -3969,12 +6604,13 @@ async fn run_tool_loop(
                             })
                             .collect();
                         if let Some(token) = cancel_token {
-                            retry_with_cancel(token, progress, "model", || {
+                            retry_with_cancel(token, progress, config.model.retry_policy(), "model", || {
                                 model_manager.continue_with_tool_results_filtered(
                                     &config.model,
                                     system_prompt,
                                     messages.clone(),
                                     truncated_results.clone(),
+                                    &[],
                                     available_skills,
                                     allowed_tools,
                                 )
@@ -3987,6 +6623,7 @@ async fn run_tool_loop(
                                     system_prompt,
                                     messages.clone(),
                                     truncated_results,
+                                    &[],
                                     available_skills,
                                     allowed_tools,
                                 )
@@ -4010,83 +6647,99 @@ async fn execute_tool_call(
     skill_manager: &SkillManager,
     _available_skills: &[SkillMetadata],
     allowed_tools: &Option<Vec<String>>,
+    approval_scope: &str,
     cancel_token: Option<&CancellationToken>,
     progress: Option<&ProgressEmitter>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     let tool_name = tool_call.function.name.as_str();
     let args_value: serde_json::Value = serde_json::from_str(&tool_call.function.arguments)
         .map_err(|e| format!("解析工具参数失败: {}", e))?;
     check_cancel(cancel_token)?;
+    if let Some(progress) = progress {
+        progress.set_current_tool(Some(tool_name));
+    }
 
     let needs_skill_permission = matches!(
         tool_name,
-        "Read" | "Write" | "Edit" | "Update" | "Glob" | "Grep" | "Bash" | "run_command"
+        "Read" | "Write" | "Edit" | "Update" | "Glob" | "Grep" | "Bash" | "run_command" | "git" | "browser" | "ui_action"
     );
     if needs_skill_permission && !tool_allowed_in_skill(tool_name, allowed_tools) {
-        return Err(format!("工具未被 skill 允许: {}", tool_name));
+        return Err(AppError::tool_denied(tool_name));
+    }
+    // 白名单外的 Bash/Write/Edit 询问用户，允许后本次调用使用放宽的权限
+    let approved_access = if config.tools.ask_approval {
+        authorize_tool_call(access, storage, approval_scope, tool_name, &args_value, cancel_token, progress)
+            .await?
+    } else {
+        None
+    };
+    let access = approved_access.as_ref().unwrap_or(access);
+    crate::faults::inject_tool_fault(tool_name)?;
+    // 执行前把调用渲染成可读的操作描述，进度面板和审计日志都用它代替原始 JSON 参数
+    let action = preview::describe_tool_call(tool_name, &args_value);
+    crate::storage::record_audit_event(
+        "tool_call",
+        serde_json::json!({
+            "tool": tool_name,
+            "scope": approval_scope,
+            "action": action,
+        }),
+    );
+    // manage_config 自己发送确认相关的进度，progress_update 的内容本身就是进度
+    if let Some(progress) = progress.filter(|_| !matches!(tool_name, "manage_config" | "progress_update")) {
+        progress.emit_step(action, None);
+    }
+    let file_review = FileChangeReview {
+        scope: approval_scope,
+        tool: tool_name,
+        confirm_lines: config.tools.confirm_edit_lines,
+        cancel_token,
+        progress,
+    };
+
+    if let Some((server, mcp_tool)) = crate::mcp::parse_tool_name(tool_name) {
+        if access.mode == "unset" {
+            return Err(AppError::ToolModeUnset);
+        }
+        if !tool_allowed_in_skill(tool_name, allowed_tools) {
+            return Err(AppError::tool_denied(tool_name));
+        }
+        let call = crate::mcp::call_tool(server, mcp_tool, args_value);
+        return match cancel_token {
+            Some(token) => await_with_cancel(token, call).await,
+            None => call.await.map_err(AppError::from),
+        };
     }
 
-    match tool_name {
+    let output: Result<String, String> = match tool_name {
         "Read" => {
             let args: ReadArgs =
                 serde_json::from_value(args_value).map_err(|e| format!("Read 参数错误: {}", e))?;
-            if let Some(progress) = progress {
-                progress.emit_step("读取文件".to_string(), Some(args.path.clone()));
-            }
             read_file_tool(access, args)
         }
         "Write" => {
             let args: WriteArgs =
                 serde_json::from_value(args_value).map_err(|e| format!("Write 参数错误: {}", e))?;
-            if let Some(progress) = progress {
-                progress.emit_step("写入文件".to_string(), Some(args.path.clone()));
-            }
-            write_file_tool(access, args)
+            write_file_tool(access, args, &file_review).await
         }
         "Edit" | "Update" => {
             let args: EditArgs =
                 serde_json::from_value(args_value).map_err(|e| format!("Edit 参数错误: {}", e))?;
-            if let Some(progress) = progress {
-                progress.emit_step("修改文件".to_string(), Some(args.path.clone()));
-            }
-            edit_file_tool(access, args)
+            edit_file_tool(access, args, &file_review).await
         }
         "Glob" => {
             let args: GlobArgs =
                 serde_json::from_value(args_value).map_err(|e| format!("Glob 参数错误: {}", e))?;
-            if let Some(progress) = progress {
-                let (detail, _) = truncate_string(&args.pattern, 200);
-                progress.emit_step("匹配文件".to_string(), Some(detail));
-            }
             glob_files_tool(access, args)
         }
         "Grep" => {
             let args: GrepArgs =
                 serde_json::from_value(args_value).map_err(|e| format!("Grep 参数错误: {}", e))?;
-            if let Some(progress) = progress {
-                let mut detail = args.pattern.clone();
-                if let Some(path) = &args.path {
-                    detail = format!("{} ({})", detail, path);
-                } else if let Some(glob) = &args.glob {
-                    detail = format!("{} ({})", detail, glob);
-                }
-                let (detail, _) = truncate_string(&detail, 200);
-                progress.emit_step("搜索内容".to_string(), Some(detail));
-            }
             grep_files_tool(access, args)
         }
         "Bash" | "run_command" => {
             let args: BashArgs =
                 serde_json::from_value(args_value).map_err(|e| format!("Bash 参数错误: {}", e))?;
-            if let Some(progress) = progress {
-                let (detail, _) = truncate_string(&args.command, 200);
-                let step_label = if command_mentions_script(&args.command) {
-                    "运行脚本"
-                } else {
-                    "执行命令"
-                };
-                progress.emit_step(step_label.to_string(), Some(detail));
-            }
             run_command_tool(access, args).await
         }
         "invoke_skill" => {
@@ -4099,22 +6752,26 @@ async fn execute_tool_call(
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
 
-            if let Some(progress) = progress {
-                progress.emit_step("调用技能".to_string(), Some(format!("/{}", skill_name)));
-            }
-            execute_skill_internal(
-                storage,
-                config,
-                model_manager,
-                skill_manager,
-                skill_name,
-                skill_args,
-                None,
-                None,
-                cancel_token,
-                progress,
+            let result = with_usage_feature(
+                "skills",
+                execute_skill_internal(
+                    storage,
+                    config,
+                    model_manager,
+                    skill_manager,
+                    skill_name,
+                    skill_args,
+                    None,
+                    None,
+                    cancel_token,
+                    progress,
+                ),
             )
-            .await
+            .await;
+            if result.is_ok() {
+                crate::skills::record_skill_use(skill_name);
+            }
+            return result;
         }
         "manage_skill" => {
             let action = args_value
@@ -4126,10 +6783,6 @@ async fn execute_tool_call(
                 .and_then(|v| v.as_str())
                 .ok_or_else(|| "缺少 name 参数".to_string())?;
 
-            if let Some(progress) = progress {
-                let detail = format!("{} {}", action, name);
-                progress.emit_step("管理技能".to_string(), Some(detail));
-            }
             let overrides = SkillFrontmatterOverrides {
                 allowed_tools: parse_string_list(args_value.get("allowed_tools")),
                 model: parse_optional_string(args_value.get("model")),
@@ -4139,6 +6792,19 @@ async fn execute_tool_call(
                     .get("disable_model_invocation")
                     .and_then(|v| v.as_bool()),
                 metadata: parse_metadata_map(args_value.get("metadata")),
+                output_schema: args_value.get("output_schema").filter(|v| v.is_object()).cloned(),
+                icon: parse_optional_string(args_value.get("icon")),
+                category: parse_optional_string(args_value.get("category")),
+                author: parse_optional_string(args_value.get("author")),
+                homepage: parse_optional_string(args_value.get("homepage")),
+                examples: args_value.get("examples").and_then(|v| v.as_array()).map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_str())
+                        .map(|item| item.trim().to_string())
+                        .filter(|item| !item.is_empty())
+                        .collect()
+                }),
             };
 
             match action {
@@ -4187,6 +6853,54 @@ async fn execute_tool_call(
                 _ => Ok(format!("未知操作: {}", action)),
             }
         }
+        "manage_config" => {
+            return config_tool::manage_config_tool(storage, approval_scope, &args_value, cancel_token, progress)
+                .await;
+        }
+        "task_status" => {
+            let task_id = args_value.get("task_id").and_then(|v| v.as_str());
+            let max_bytes = args_value.get("max_bytes").and_then(|v| v.as_u64());
+            Ok(format_task_status(task_id, max_bytes))
+        }
+        "ocr" => {
+            ocr_tool(access, storage, config, &args_value).await
+        }
+        "git" => {
+            return git::git_tool(access, &args_value, &file_review).await;
+        }
+        "ui_action" => {
+            return automation::ui_action_tool(
+                access,
+                config.tools.automation,
+                &args_value,
+                cancel_token,
+            )
+            .await;
+        }
+        "browser" => {
+            return browser::browser_tool(
+                access,
+                &config.browser,
+                approval_scope,
+                &args_value,
+                cancel_token,
+            )
+            .await;
+        }
+        "clipboard_read" => {
+            if access.mode == "unset" {
+                return Err(AppError::ToolModeUnset);
+            }
+            crate::clipboard::clipboard_read_tool(storage, &args_value).await
+        }
+        "clipboard_write" => {
+            if access.mode == "unset" {
+                return Err(AppError::ToolModeUnset);
+            }
+            crate::clipboard::clipboard_write_tool(&args_value).await
+        }
+        "snippets" => snippets_tool(storage, &args_value),
+        "read_attachment_file" => dir_attachment::read_attachment_file_tool(&args_value),
         "progress_update" => {
             let message = args_value
                 .get("message")
@@ -4203,5 +6917,6 @@ async fn execute_tool_call(
             Ok("ok".to_string())
         }
         _ => Ok(format!("未知工具: {}", tool_name)),
-    }
+    };
+    output.map_err(AppError::from)
 }
@@ -0,0 +1,277 @@
+use super::{invoke_skill_internal, AppState};
+use crate::capture::{add_suppression_rule, AlertAction, SuppressionRule};
+use crate::logs;
+use crate::storage::StorageManager;
+use parking_lot::Mutex as ParkingMutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tauri::plugin::PermissionState;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+// 同一场景的提醒在该时间内合并计数
+const SCENE_GROUP_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// 提醒的展示方式：window 为自绘的无边框通知窗口，点击后跳转到对应提醒；
+/// native 使用系统通知中心（遵循系统的勿扰/专注模式，按场景分组），
+/// 桌面端通知插件没有点击回调，点击只会激活应用，不跳转到具体提醒
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NotificationBackend {
+    Window,
+    Native,
+}
+
+impl NotificationBackend {
+    fn from_config(value: &str) -> Self {
+        match value.trim() {
+            "native" => NotificationBackend::Native,
+            _ => NotificationBackend::Window,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct AlertNotification {
+    pub key: String,
+    pub intent: String,
+    pub scene: String,
+    pub help_type: String,
+    pub summary: String,
+    pub suggestion: String,
+    pub urgency: String,
+    pub actions: Vec<AlertAction>,
+}
+
+/// 通知被点击后主窗口收到的 open-alert 事件
+#[derive(Debug, Clone, Serialize)]
+struct OpenAlert {
+    key: String,
+    scene: String,
+}
+
+fn scene_groups() -> &'static ParkingMutex<HashMap<String, (u32, Instant)>> {
+    static GROUPS: OnceLock<ParkingMutex<HashMap<String, (u32, Instant)>>> = OnceLock::new();
+    GROUPS.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+/// 按配置选择通知方式；系统通知不可用或未获授权时退回通知窗口
+pub(super) fn deliver_notification(app_handle: &AppHandle, alert: AlertNotification) -> Result<(), String> {
+    let backend = StorageManager::new()
+        .load_config()
+        .map(|config| NotificationBackend::from_config(&config.ui.notification_backend))
+        .unwrap_or(NotificationBackend::Window);
+    if backend == NotificationBackend::Native {
+        match show_native_notification(app_handle, &alert) {
+            Ok(()) => return Ok(()),
+            Err(err) => logs::warn("notification", format!("系统通知不可用，改用通知窗口: {}", err)),
+        }
+    }
+    show_window_notification(app_handle, &alert)
+}
+
+fn show_native_notification(app_handle: &AppHandle, alert: &AlertNotification) -> Result<(), String> {
+    let notification = app_handle.notification();
+    let permission = notification.permission_state().map_err(|e| e.to_string())?;
+    if permission != PermissionState::Granted {
+        let requested = notification.request_permission().map_err(|e| e.to_string())?;
+        if requested != PermissionState::Granted {
+            return Err("未获得系统通知权限".to_string());
+        }
+    }
+
+    let scene = match alert.scene.trim() {
+        "" => "其他",
+        scene => scene,
+    };
+    let count = {
+        let mut groups = scene_groups().lock();
+        groups.retain(|_, (_, at)| at.elapsed() < SCENE_GROUP_WINDOW);
+        let entry = groups.entry(scene.to_string()).or_insert((0, Instant::now()));
+        entry.0 += 1;
+        entry.1 = Instant::now();
+        entry.0
+    };
+    let title = if count > 1 {
+        format!("OpenCowork · {}（{} 条提醒）", scene, count)
+    } else {
+        format!("OpenCowork · {}", scene)
+    };
+    let mut body = alert.summary.trim().to_string();
+    if !alert.suggestion.trim().is_empty() {
+        body.push_str(&format!("\n建议: {}", alert.suggestion.trim()));
+    }
+    notification
+        .builder()
+        .title(title)
+        .body(body)
+        .group(scene.to_string())
+        .show()
+        .map_err(|e| format!("发送系统通知失败: {}", e))?;
+    Ok(())
+}
+
+fn show_window_notification(app_handle: &AppHandle, alert: &AlertNotification) -> Result<(), String> {
+    use tauri::{LogicalSize, PhysicalPosition, WebviewUrl, WebviewWindowBuilder};
+
+    // 有操作按钮时加高窗口
+    let window_width = 380u32;
+    let window_height = if alert.actions.is_empty() { 140u32 } else { 176u32 };
+
+    // 检查是否已存在通知窗口
+    if let Some(window) = app_handle.get_webview_window("notification") {
+        // 窗口已存在，发送更新事件
+        let _ = window.set_size(LogicalSize::new(window_width as f64, window_height as f64));
+        let _ = window.emit("notification-update", alert);
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    // 获取主显示器信息以定位窗口到右下角
+    let margin = 20i32;
+
+    // 创建新的通知窗口
+    // actions 以 JSON 传入，按钮点击后调用 run_alert_action
+    let actions = serde_json::to_string(&alert.actions).unwrap_or_else(|_| "[]".to_string());
+    let notification_url = format!(
+        "/notification?intent={}&scene={}&help_type={}&summary={}&suggestion={}&urgency={}&key={}&actions={}",
+        urlencoding::encode(&alert.intent),
+        urlencoding::encode(&alert.scene),
+        urlencoding::encode(&alert.help_type),
+        urlencoding::encode(&alert.summary),
+        urlencoding::encode(&alert.suggestion),
+        urlencoding::encode(&alert.urgency),
+        urlencoding::encode(&alert.key),
+        urlencoding::encode(&actions),
+    );
+
+    let window = WebviewWindowBuilder::new(
+        app_handle,
+        "notification",
+        WebviewUrl::App(notification_url.into()),
+    )
+    .title("OpenCowork 提醒")
+    .inner_size(window_width as f64, window_height as f64)
+    .resizable(false)
+    .decorations(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .transparent(true)
+    .build()
+    .map_err(|e| format!("创建通知窗口失败: {}", e))?;
+
+    // 尝试将窗口定位到右下角
+    if let Some(monitor) = window.current_monitor().ok().flatten() {
+        let monitor_size = monitor.size();
+        let monitor_position = monitor.position();
+        let x = monitor_position.x + monitor_size.width as i32 - window_width as i32 - margin;
+        let y =
+            monitor_position.y + monitor_size.height as i32 - window_height as i32 - margin - 40; // 40 for taskbar
+        let _ = window.set_position(PhysicalPosition::new(x, y));
+    }
+
+    Ok(())
+}
+
+fn focus_main_and_open_alert(app_handle: &AppHandle, alert: OpenAlert) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        let _ = app_handle.emit_to("main", "open-alert", alert);
+    }
+}
+
+/// 聚焦主窗口并跳转到指定提醒（通知窗口点击时调用）
+#[tauri::command]
+pub async fn open_alert(app_handle: AppHandle, key: String, scene: Option<String>) -> Result<(), String> {
+    focus_main_and_open_alert(
+        &app_handle,
+        OpenAlert {
+            key,
+            scene: scene.unwrap_or_default(),
+        },
+    );
+    Ok(())
+}
+
+/// 执行提醒上的操作按钮：skill 在主窗口中用预填参数调用技能并返回结果，
+/// snooze 暂停该提醒，ignore_issue_type 添加按问题类型屏蔽的规则
+#[tauri::command]
+pub async fn run_alert_action(
+    key: String,
+    action: AlertAction,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    crate::storage::record_audit_event(
+        "alert_action",
+        serde_json::json!({ "key": key, "kind": action.kind, "label": action.label }),
+    );
+    match action.kind.as_str() {
+        "skill" => {
+            let skill = action.skill.trim().trim_start_matches('/').to_string();
+            if skill.is_empty() {
+                return Err("操作未指定技能".to_string());
+            }
+            let request_id = format!("alert-{}", chrono::Local::now().timestamp_millis());
+            if let Some(window) = app_handle.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+                let _ = app_handle.emit_to(
+                    "main",
+                    "alert-action-started",
+                    serde_json::json!({ "key": key, "skill": skill, "args": action.args, "request_id": request_id }),
+                );
+            }
+            let args = (!action.args.trim().is_empty()).then(|| action.args.clone());
+            let result = invoke_skill_internal(
+                skill.clone(),
+                args,
+                None,
+                None,
+                Some(request_id.clone()),
+                Some("main".to_string()),
+                app_handle.clone(),
+                state,
+            )
+            .await;
+            let _ = app_handle.emit_to(
+                "main",
+                "alert-action-completed",
+                serde_json::json!({
+                    "key": key,
+                    "skill": skill,
+                    "request_id": request_id,
+                    "response": result.as_ref().ok(),
+                    "error": result.as_ref().err().map(|err| err.to_string()),
+                }),
+            );
+            result.map_err(String::from)
+        }
+        "snooze" => {
+            let minutes = if action.minutes == 0 { 60 } else { action.minutes };
+            state.capture_manager.lock().await.snooze_alert(&key, minutes)?;
+            Ok(format!("已暂停该提醒 {} 分钟", minutes))
+        }
+        "ignore_issue_type" => {
+            let issue_type = action.issue_type.trim();
+            if issue_type.is_empty() {
+                return Err("操作未指定问题类型".to_string());
+            }
+            add_suppression_rule(SuppressionRule {
+                id: String::new(),
+                app: String::new(),
+                scene: String::new(),
+                issue_type: issue_type.to_string(),
+                message_pattern: String::new(),
+                created_at: String::new(),
+            })?;
+            Ok(format!("已不再提醒「{}」类问题", issue_type))
+        }
+        other => Err(format!("未知的提醒操作: {}", other)),
+    }
+}
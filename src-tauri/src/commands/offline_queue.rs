@@ -0,0 +1,232 @@
+use super::{ChatHistoryMessage, ChatResponse, ProgressEmitter};
+use crate::error::AppError;
+use crate::logs;
+use crate::model::{with_usage_feature, ModelManager};
+use crate::storage::{storage_actor, Config, StorageManager, StoragePriority};
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+const OFFLINE_QUEUE_FILE: &str = "offline_queue.json";
+const MAX_QUEUED_CHATS: usize = 50;
+const OFFLINE_RETRY_INTERVAL_SECS: u64 = 60;
+// 非网络错误（如鉴权失败）重试几次后放弃
+const MAX_QUEUED_ATTEMPTS: u32 = 3;
+
+/// 无法连接模型时排队的对话请求；上下文在提交时固定，恢复连接后原样发送
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueuedChat {
+    pub id: String,
+    pub queued_at: String,
+    pub message: String,  // 用户原始输入，用于展示
+    #[serde(default)]
+    pub context: String,  // 提交时检索到的屏幕记录和全局提示词
+    #[serde(default)]
+    pub user_message: String,  // 合并附件内容后的消息
+    #[serde(default)]
+    pub history: Vec<ChatHistoryMessage>,
+    #[serde(default)]
+    pub image_urls: Vec<String>,
+    #[serde(default)]
+    pub image_base64: Vec<String>,
+    #[serde(default)]
+    pub model: Option<String>,  // 提交时指定的模型配置名
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// 排队请求的执行结果，通过 queued-chat-completed 事件发送
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedChatResult {
+    pub id: String,
+    pub queued_at: String,
+    pub message: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+fn offline_queue_lock() -> &'static ParkingMutex<()> {
+    static LOCK: OnceLock<ParkingMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| ParkingMutex::new(()))
+}
+
+impl StorageManager {
+    pub fn load_queued_chats(&self) -> Result<Vec<QueuedChat>, String> {
+        let path = self.get_data_dir().join(OFFLINE_QUEUE_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_queued_chats(&self, items: &[QueuedChat]) -> Result<(), String> {
+        let path = self.get_data_dir().join(OFFLINE_QUEUE_FILE);
+        if items.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("删除离线队列失败: {}", e))?;
+            }
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(items).map_err(|e| format!("序列化离线队列失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    fn update_queued_chats<T>(&self, update: impl FnOnce(&mut Vec<QueuedChat>) -> T) -> Result<T, String> {
+        let _guard = offline_queue_lock().lock();
+        let mut items = self.load_queued_chats()?;
+        let result = update(&mut items);
+        self.save_queued_chats(&items)?;
+        Ok(result)
+    }
+}
+
+/// 连接失败（而不是鉴权、参数等错误）且开启了离线队列时才排队
+pub(super) fn should_queue_offline(config: &Config, err: &AppError) -> bool {
+    config.model.queue_when_offline && matches!(err, AppError::Network { .. })
+}
+
+/// 保存请求并返回排队提示作为本次回答
+pub(super) fn queue_offline_chat(mut item: QueuedChat, progress: Option<&ProgressEmitter>) -> Result<String, String> {
+    item.id = format!("offline-{}", Local::now().timestamp_millis());
+    item.queued_at = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let id = item.id.clone();
+    let position = StorageManager::new().update_queued_chats(|items| {
+        items.push(item);
+        if items.len() > MAX_QUEUED_CHATS {
+            let overflow = items.len() - MAX_QUEUED_CHATS;
+            items.drain(..overflow);
+        }
+        items.len()
+    })?;
+    logs::info("offline", format!("无法连接模型服务，请求已加入离线队列（第 {} 个）", position));
+    if let Some(progress) = progress {
+        progress.emit_done("已加入离线队列");
+    }
+    let chat_response = ChatResponse {
+        response: format!(
+            "当前无法连接模型服务，问题已加入离线队列（第 {} 个）。恢复连接后会按提问时的屏幕记录自动回答，并通知你。",
+            position
+        ),
+        tool_context: Vec::new(),
+        active_skill: None,
+        structured_output: None,
+        structured_output_error: None,
+        planned_actions: Vec::new(),
+        queued_request: Some(id),
+    };
+    Ok(serde_json::to_string(&chat_response).unwrap_or_else(|_| chat_response.response))
+}
+
+/// 后台每分钟尝试发送最早的排队请求；仍然连接失败时等下一轮，成功或放弃时发送 queued-chat-completed
+pub fn start_offline_queue_worker(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(OFFLINE_RETRY_INTERVAL_SECS)).await;
+            loop {
+                match run_next_queued_chat(&app_handle).await {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(err) => {
+                        logs::warn("offline", format!("处理离线队列失败: {}", err));
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// 发送队首请求，返回是否应继续处理下一个
+async fn run_next_queued_chat(app_handle: &AppHandle) -> Result<bool, String> {
+    let (config, next) = storage_actor()
+        .run(StoragePriority::Background, |storage| {
+            Ok((storage.load_config()?, storage.load_queued_chats()?.into_iter().next()))
+        })
+        .await?;
+    let Some(item) = next else {
+        return Ok(false);
+    };
+
+    let model_config = ModelManager::resolve_model_config(&config.model, item.model.as_deref());
+    let model_manager = ModelManager::new();
+    let history = (!item.history.is_empty()).then(|| item.history.clone());
+    let result = if item.image_urls.is_empty() && item.image_base64.is_empty() {
+        with_usage_feature(
+            "chat",
+            model_manager.chat_with_history(&model_config, &item.context, &item.user_message, history),
+        )
+        .await
+    } else {
+        with_usage_feature(
+            "chat",
+            model_manager.chat_with_history_with_images(
+                &model_config,
+                &item.context,
+                &item.user_message,
+                history,
+                item.image_urls.clone(),
+                item.image_base64.clone(),
+            ),
+        )
+        .await
+    };
+
+    let storage = StorageManager::new();
+    let (response, error) = match result {
+        Ok(text) => (Some(text), None),
+        Err(classified) => {
+            if matches!(classified, AppError::Network { .. }) {
+                return Ok(false);
+            }
+            let attempts = storage.update_queued_chats(|items| {
+                let entry = items.iter_mut().find(|queued| queued.id == item.id)?;
+                entry.attempts += 1;
+                Some(entry.attempts)
+            })?;
+            // 发送期间已被取消
+            let Some(attempts) = attempts else {
+                return Ok(true);
+            };
+            if attempts < MAX_QUEUED_ATTEMPTS && classified.is_retryable() {
+                return Ok(false);
+            }
+            (None, Some(classified.to_string()))
+        }
+    };
+    storage.update_queued_chats(|items| items.retain(|queued| queued.id != item.id))?;
+    let _ = app_handle.emit(
+        "queued-chat-completed",
+        QueuedChatResult {
+            id: item.id,
+            queued_at: item.queued_at,
+            message: item.message,
+            response,
+            error,
+        },
+    );
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn list_queued_chats() -> Result<Vec<QueuedChat>, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, |storage| storage.load_queued_chats())
+        .await
+}
+
+#[tauri::command]
+pub async fn cancel_queued_chat(id: String) -> Result<bool, String> {
+    storage_actor()
+        .run(StoragePriority::Interactive, move |storage| {
+            storage.update_queued_chats(|items| {
+                let before = items.len();
+                items.retain(|item| item.id != id);
+                items.len() != before
+            })
+        })
+        .await
+}
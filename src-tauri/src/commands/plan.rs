@@ -0,0 +1,207 @@
+use super::automation::describe_steps;
+use super::browser::{is_page_action, BROWSER_PERMISSION};
+use super::git::{is_write_action, repo_dir, write_permission_pattern};
+use super::{command_allowed, command_requests_background, path_is_allowed, resolve_path, ToolAccess};
+use std::fs;
+
+/// 预演模式下追加到系统提示词末尾
+pub(super) const PLAN_ONLY_PROMPT: &str = "\n\n## 预演模式\n当前为预演（plan-only）模式：写文件、修改文件、运行命令、修改仓库的 git 操作、浏览器点击和输入、写入剪贴板、鼠标键盘操作、调用技能和 MCP 工具都不会真正执行，只返回将要发生的操作。读取类工具（Read/Glob/Grep 等）正常执行。请假设这些操作都已成功，继续规划完整步骤，最后列出将修改的文件和将运行的命令，提醒用户确认后关闭预演重新执行。";
+
+/// 预演模式下被拦截的一次工具调用
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PlannedAction {
+    pub tool: String,
+    pub action: String,  // write | edit | command | git | browser | clipboard | ui | skill | manage_skill | config | mcp
+    pub target: String,  // 文件路径 / 命令 / 技能名
+    pub detail: String,
+    pub allowed: bool,  // 按当前工具权限是否可以直接执行（false 表示会被拒绝或需要授权）
+}
+
+impl PlannedAction {
+    fn new(tool: &str, action: &str, target: String, detail: String, allowed: bool) -> Self {
+        Self {
+            tool: tool.to_string(),
+            action: action.to_string(),
+            target,
+            detail,
+            allowed,
+        }
+    }
+
+    /// 返回给模型的模拟结果
+    pub(super) fn tool_output(&self) -> String {
+        let permission = if self.allowed {
+            String::new()
+        } else {
+            "；注意：按当前工具权限，实际执行时会被拒绝或需要用户授权".to_string()
+        };
+        format!("[预演，未实际执行] {}{}", self.detail, permission)
+    }
+}
+
+/// 有副作用的工具返回模拟结果；只读工具返回 None，照常执行
+pub(super) fn simulate_tool_call(access: &ToolAccess, tool_name: &str, arguments: &str) -> Option<PlannedAction> {
+    let args: serde_json::Value = serde_json::from_str(arguments).unwrap_or(serde_json::Value::Null);
+    let text_arg = |key: &str| args.get(key).and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+    let usable = access.mode != "unset";
+
+    if let Some((server, mcp_tool)) = crate::mcp::parse_tool_name(tool_name) {
+        return Some(PlannedAction::new(
+            tool_name,
+            "mcp",
+            format!("{}/{}", server, mcp_tool),
+            format!("将调用 MCP 工具 {}/{}，参数: {}", server, mcp_tool, args),
+            usable,
+        ));
+    }
+
+    match tool_name {
+        "Write" => {
+            let path = resolve_path(access, &text_arg("path"));
+            let bytes = text_arg("content").len();
+            let append = args.get("append").and_then(|v| v.as_bool()).unwrap_or(false);
+            let verb = if append {
+                "追加写入"
+            } else if path.exists() {
+                "覆盖"
+            } else {
+                "新建"
+            };
+            Some(PlannedAction::new(
+                tool_name,
+                "write",
+                path.display().to_string(),
+                format!("将{}文件 {}（{} 字节）", verb, path.display(), bytes),
+                usable && path_is_allowed(access, &path),
+            ))
+        }
+        "Edit" | "Update" => {
+            let path = resolve_path(access, &text_arg("path"));
+            let old = args.get("old").and_then(|v| v.as_str()).unwrap_or("");
+            let detail = match fs::read_to_string(&path) {
+                Ok(content) if !old.is_empty() => {
+                    let count = content.matches(old).count();
+                    let replace_all = args.get("replace_all").and_then(|v| v.as_bool()).unwrap_or(true);
+                    let count = if replace_all { count } else { count.min(1) };
+                    format!("将修改文件 {}，替换 {} 处", path.display(), count)
+                }
+                Ok(_) => format!("将修改文件 {}（未提供要替换的内容）", path.display()),
+                Err(err) => format!("将修改文件 {}，但当前无法读取: {}", path.display(), err),
+            };
+            Some(PlannedAction::new(
+                tool_name,
+                "edit",
+                path.display().to_string(),
+                detail,
+                usable && path_is_allowed(access, &path),
+            ))
+        }
+        "Bash" | "run_command" => {
+            let command = text_arg("command");
+            let cwd = match text_arg("cwd") {
+                dir if dir.is_empty() => access.base_dir.clone(),
+                dir => resolve_path(access, &dir),
+            };
+            let mode = if command_requests_background(&command) {
+                "在后台运行"
+            } else {
+                "运行"
+            };
+            Some(PlannedAction::new(
+                tool_name,
+                "command",
+                command.clone(),
+                format!("将在 {} {}命令: {}", cwd.display(), mode, command),
+                usable && command_allowed(access, &command) && path_is_allowed(access, &cwd),
+            ))
+        }
+        "git" => {
+            let action = text_arg("action").to_lowercase();
+            if !is_write_action(&action, &args) {
+                return None;
+            }
+            let dir = repo_dir(access, &args);
+            let target = match action.as_str() {
+                "commit" => text_arg("message"),
+                "branch" => text_arg("create"),
+                _ => args.get("paths").map(|paths| paths.to_string()).unwrap_or_default(),
+            };
+            let permitted = command_allowed(access, "git")
+                || command_allowed(access, &write_permission_pattern(&action));
+            Some(PlannedAction::new(
+                tool_name,
+                "git",
+                dir.display().to_string(),
+                format!("将在 {} 执行 git {} {}", dir.display(), action, target),
+                usable && permitted && path_is_allowed(access, &dir),
+            ))
+        }
+        "browser" => {
+            let action = text_arg("action").to_lowercase();
+            if !is_page_action(&action) {
+                return None;
+            }
+            let selector = text_arg("selector");
+            let detail = match action.as_str() {
+                "type" => format!("将在浏览器元素 {} 中输入: {}", selector, text_arg("text")),
+                _ => format!("将点击浏览器元素 {}", selector),
+            };
+            Some(PlannedAction::new(
+                tool_name,
+                "browser",
+                selector,
+                detail,
+                usable && command_allowed(access, BROWSER_PERMISSION),
+            ))
+        }
+        "ui_action" => Some(PlannedAction::new(
+            tool_name,
+            "ui",
+            "desktop".to_string(),
+            format!("将操作鼠标键盘: {}", describe_steps(&args)),
+            usable,
+        )),
+        "clipboard_write" => {
+            let text = text_arg("text");
+            Some(PlannedAction::new(
+                tool_name,
+                "clipboard",
+                "clipboard".to_string(),
+                format!("将把 {} 个字符写入剪贴板", text.chars().count()),
+                usable,
+            ))
+        }
+        "invoke_skill" => {
+            let skill_name = text_arg("skill_name");
+            let skill_args = text_arg("args");
+            let detail = if skill_args.is_empty() {
+                format!("将调用技能 /{}", skill_name)
+            } else {
+                format!("将调用技能 /{}，参数: {}", skill_name, skill_args)
+            };
+            Some(PlannedAction::new(tool_name, "skill", skill_name, detail, true))
+        }
+        "manage_skill" => {
+            let action = text_arg("action");
+            let name = text_arg("name");
+            Some(PlannedAction::new(
+                tool_name,
+                "manage_skill",
+                name.clone(),
+                format!("将对技能 {} 执行 {}", name, action),
+                true,
+            ))
+        }
+        "manage_config" if text_arg("action") == "propose" => {
+            let changes = args.get("changes").map(|v| v.to_string()).unwrap_or_default();
+            Some(PlannedAction::new(
+                tool_name,
+                "config",
+                "config".to_string(),
+                format!("将提议修改设置（需用户确认）: {}", changes),
+                true,
+            ))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,139 @@
+use super::automation::describe_steps;
+use super::{command_mentions_script, truncate_string};
+use serde_json::Value;
+
+// 进度和审计日志里单个参数（命令、搜索词等）最多展示的字符数
+const MAX_PREVIEW_ARG_CHARS: usize = 200;
+
+fn text_arg<'a>(args: &'a Value, key: &str) -> Option<&'a str> {
+    args.get(key).and_then(|v| v.as_str()).map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn shorten(value: &str) -> String {
+    let single_line = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (text, cut) = truncate_string(&single_line, MAX_PREVIEW_ARG_CHARS);
+    if cut {
+        format!("{}…", text)
+    } else {
+        text
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1}KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+fn line_count(text: &str) -> usize {
+    text.lines().count().max(1)
+}
+
+/// 把工具调用渲染成一行可读的操作描述（如 "写入 2.3KB 到 reports/summary.md"），
+/// 执行前发给进度面板并写入审计日志；参数缺失时也尽量给出描述，不报错
+pub(super) fn describe_tool_call(tool_name: &str, args: &Value) -> String {
+    if let Some((server, tool)) = crate::mcp::parse_tool_name(tool_name) {
+        return format!("调用 MCP 工具 {}/{}", server, tool);
+    }
+    let path = text_arg(args, "path").unwrap_or("?");
+    match tool_name {
+        "Read" => match args.get("max_bytes").and_then(|v| v.as_u64()) {
+            Some(max) => format!("读取 {}（最多 {}）", path, format_size(max as usize)),
+            None => format!("读取 {}", path),
+        },
+        "Write" => {
+            let size = format_size(args.get("content").and_then(|v| v.as_str()).unwrap_or("").len());
+            if args.get("append").and_then(|v| v.as_bool()).unwrap_or(false) {
+                format!("追加 {} 到 {}", size, path)
+            } else {
+                format!("写入 {} 到 {}", size, path)
+            }
+        }
+        "Edit" | "Update" => {
+            let old = args.get("old").and_then(|v| v.as_str()).unwrap_or("");
+            let new = args.get("new").and_then(|v| v.as_str()).unwrap_or("");
+            let scope = if args.get("replace_all").and_then(|v| v.as_bool()).unwrap_or(false) {
+                "（全部匹配处）"
+            } else {
+                ""
+            };
+            format!("修改 {}：{} 行替换为 {} 行{}", path, line_count(old), line_count(new), scope)
+        }
+        "Glob" => format!("查找匹配 {} 的文件", shorten(text_arg(args, "pattern").unwrap_or("?"))),
+        "Grep" => {
+            let pattern = shorten(text_arg(args, "pattern").unwrap_or("?"));
+            match text_arg(args, "path").or_else(|| text_arg(args, "glob")) {
+                Some(target) => format!("在 {} 中搜索「{}」", target, pattern),
+                None => format!("搜索「{}」", pattern),
+            }
+        }
+        "Bash" | "run_command" => {
+            let command = text_arg(args, "command").unwrap_or("");
+            let label = if command_mentions_script(command) { "运行脚本" } else { "执行命令" };
+            match text_arg(args, "cwd") {
+                Some(cwd) => format!("{}: {}（目录 {}）", label, shorten(command), cwd),
+                None => format!("{}: {}", label, shorten(command)),
+            }
+        }
+        "invoke_skill" => {
+            let name = text_arg(args, "skill_name").unwrap_or("?");
+            match text_arg(args, "args") {
+                Some(skill_args) => format!("调用技能 /{} {}", name, shorten(skill_args)),
+                None => format!("调用技能 /{}", name),
+            }
+        }
+        "manage_skill" => {
+            let name = text_arg(args, "name").unwrap_or("?");
+            match text_arg(args, "action").unwrap_or("") {
+                "create" => format!("创建技能 {}", name),
+                "update" => format!("更新技能 {}", name),
+                "delete" => format!("删除技能 {}", name),
+                other => format!("管理技能 {} {}", other, name),
+            }
+        }
+        "manage_config" => match text_arg(args, "action").unwrap_or("get") {
+            "propose" => "请求修改设置".to_string(),
+            _ => "读取设置".to_string(),
+        },
+        "task_status" => match text_arg(args, "task_id") {
+            Some(id) => format!("查看后台任务 {}", id),
+            None => "查看全部后台任务".to_string(),
+        },
+        "ocr" => match text_arg(args, "timestamp").or_else(|| text_arg(args, "path")) {
+            Some(target) => format!("识别 {} 中的文字", target),
+            None => "识别图片文字".to_string(),
+        },
+        "git" => format!("执行 git {}", text_arg(args, "action").unwrap_or("?")),
+        "ui_action" => format!("桌面操作：{}", describe_steps(args)),
+        "browser" => {
+            let action = text_arg(args, "action").unwrap_or("?").to_lowercase();
+            match text_arg(args, "url").or_else(|| text_arg(args, "selector")) {
+                Some(target) => format!("浏览器 {} {}", action, shorten(target)),
+                None => format!("浏览器 {}", action),
+            }
+        }
+        "clipboard_read" => {
+            let history = args.get("history").and_then(|v| v.as_bool()).unwrap_or(false);
+            match (history, text_arg(args, "query")) {
+                (_, Some(query)) => format!("在剪贴板历史中查找「{}」", shorten(query)),
+                (true, None) => "查询剪贴板历史".to_string(),
+                (false, None) => "读取剪贴板".to_string(),
+            }
+        }
+        "clipboard_write" => {
+            let chars = args.get("text").and_then(|v| v.as_str()).unwrap_or("").chars().count();
+            format!("写入剪贴板（{} 字）", chars)
+        }
+        "read_attachment_file" => format!("读取附加目录中的 {}", path),
+        "snippets" => match text_arg(args, "id").or_else(|| text_arg(args, "query")) {
+            Some(target) => format!("查找已保存片段 {}", shorten(target)),
+            None => "查找已保存片段".to_string(),
+        },
+        "progress_update" => text_arg(args, "message").unwrap_or("").to_string(),
+        other => format!("调用工具 {}", other),
+    }
+}
@@ -0,0 +1,334 @@
+use super::{build_shell_command, next_background_task_id, truncate_string};
+use chrono::Local;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::oneshot;
+
+const DEFAULT_TASK_OUTPUT_BYTES: u64 = 20_000;
+const MAX_TRACKED_FINISHED_TASKS: usize = 50;
+
+/// 后台任务信息（run_command 以 & 结尾时启动）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BackgroundTaskInfo {
+    pub id: String,
+    pub pid: Option<u32>,
+    pub command: String,
+    pub cwd: String,
+    pub status: String, // running | exited | killed | failed
+    pub exit_code: Option<i32>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub output_path: String,
+}
+
+struct BackgroundTask {
+    info: BackgroundTaskInfo,
+    kill_tx: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+pub struct BackgroundTaskManager {
+    tasks: Mutex<HashMap<String, BackgroundTask>>,
+}
+
+impl BackgroundTaskManager {
+    /// 启动后台命令，stdout/stderr 写入 tasks_dir/<id>.output
+    pub fn spawn(
+        self: &Arc<Self>,
+        command: &str,
+        cwd: &Path,
+        tasks_dir: &Path,
+    ) -> Result<BackgroundTaskInfo, String> {
+        fs::create_dir_all(tasks_dir).map_err(|e| format!("create tasks dir failed: {}", e))?;
+        let task_id = next_background_task_id();
+        let output_path = tasks_dir.join(format!("{}.output", task_id));
+        let stdout_file =
+            fs::File::create(&output_path).map_err(|e| format!("create output file failed: {}", e))?;
+        let stderr_file = stdout_file
+            .try_clone()
+            .map_err(|e| format!("prepare stderr output file failed: {}", e))?;
+
+        // 去掉结尾的 &，让 shell 进程本身就是被跟踪的任务
+        let trimmed = command.trim();
+        let foreground = trimmed
+            .strip_suffix('&')
+            .filter(|rest| !rest.ends_with('&'))
+            .unwrap_or(trimmed)
+            .trim();
+
+        let mut cmd = build_shell_command(foreground);
+        cmd.current_dir(cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::from(stdout_file))
+            .stderr(Stdio::from(stderr_file));
+        // shell 自成一个进程组，结束任务时连同管道、子命令等子孙进程一起结束
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("start background command failed: {}", e))?;
+        let group = ProcessGroup::attach(&child);
+
+        let info = BackgroundTaskInfo {
+            id: task_id.clone(),
+            pid: child.id(),
+            command: command.trim().to_string(),
+            cwd: cwd.to_string_lossy().to_string(),
+            status: "running".to_string(),
+            exit_code: None,
+            started_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+            finished_at: None,
+            output_path: output_path.to_string_lossy().to_string(),
+        };
+
+        let (kill_tx, kill_rx) = oneshot::channel::<()>();
+        {
+            let mut tasks = self.tasks.lock().map_err(|_| "task registry poisoned".to_string())?;
+            prune_finished(&mut tasks);
+            tasks.insert(
+                task_id.clone(),
+                BackgroundTask {
+                    info: info.clone(),
+                    kill_tx: Some(kill_tx),
+                },
+            );
+        }
+
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            let (status, exit_code) = tokio::select! {
+                result = child.wait() => match result {
+                    Ok(exit) => ("exited", exit.code()),
+                    Err(_) => ("failed", None),
+                },
+                _ = kill_rx => {
+                    group.kill();
+                    let _ = child.kill().await;
+                    ("killed", None)
+                }
+            };
+            manager.mark_finished(&task_id, status, exit_code);
+        });
+
+        Ok(info)
+    }
+
+    pub fn list(&self) -> Vec<BackgroundTaskInfo> {
+        let mut list: Vec<BackgroundTaskInfo> = match self.tasks.lock() {
+            Ok(tasks) => tasks.values().map(|task| task.info.clone()).collect(),
+            Err(_) => Vec::new(),
+        };
+        list.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        list
+    }
+
+    pub fn get(&self, task_id: &str) -> Option<BackgroundTaskInfo> {
+        self.tasks
+            .lock()
+            .ok()
+            .and_then(|tasks| tasks.get(task_id).map(|task| task.info.clone()))
+    }
+
+    pub fn kill(&self, task_id: &str) -> Result<(), String> {
+        let mut tasks = self.tasks.lock().map_err(|_| "task registry poisoned".to_string())?;
+        let task = tasks
+            .get_mut(task_id)
+            .ok_or_else(|| format!("background task not found: {}", task_id))?;
+        match task.kill_tx.take() {
+            Some(tx) => {
+                let _ = tx.send(());
+                Ok(())
+            }
+            None => Err(format!("background task already finished: {}", task_id)),
+        }
+    }
+
+    fn mark_finished(&self, task_id: &str, status: &str, exit_code: Option<i32>) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            if let Some(task) = tasks.get_mut(task_id) {
+                task.kill_tx = None;
+                task.info.status = status.to_string();
+                task.info.exit_code = exit_code;
+                task.info.finished_at = Some(Local::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+            }
+        }
+    }
+}
+
+/// 后台任务的进程组：Unix 为以 shell 为组长的进程组，Windows 为作业对象
+#[cfg(unix)]
+struct ProcessGroup {
+    pgid: Option<u32>,
+}
+
+#[cfg(unix)]
+impl ProcessGroup {
+    fn attach(child: &tokio::process::Child) -> Self {
+        Self { pgid: child.id() }
+    }
+
+    fn kill(&self) {
+        if let Some(pgid) = self.pgid.and_then(|pid| i32::try_from(pid).ok()) {
+            unsafe {
+                libc::kill(-pgid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+struct ProcessGroup {
+    job: isize, // 作业对象句柄，0 表示创建失败
+}
+
+#[cfg(windows)]
+impl ProcessGroup {
+    /// 创建作业对象并加入 shell 进程，之后由它启动的子进程自动加入同一作业
+    fn attach(child: &tokio::process::Child) -> Self {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+
+        let Some(process) = child.raw_handle() else {
+            return Self { job: 0 };
+        };
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Self { job: 0 };
+            }
+            if AssignProcessToJobObject(job, process as _) == 0 {
+                CloseHandle(job);
+                return Self { job: 0 };
+            }
+            Self { job: job as isize }
+        }
+    }
+
+    fn kill(&self) {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+        if self.job != 0 {
+            unsafe {
+                TerminateJobObject(self.job as _, 1);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if self.job != 0 {
+            unsafe {
+                windows_sys::Win32::Foundation::CloseHandle(self.job as _);
+            }
+        }
+    }
+}
+
+/// 只保留最近的已结束任务，避免长时间运行后列表无限增长
+fn prune_finished(tasks: &mut HashMap<String, BackgroundTask>) {
+    let mut finished: Vec<(String, String)> = tasks
+        .values()
+        .filter(|task| task.info.status != "running")
+        .map(|task| (task.info.id.clone(), task.info.started_at.clone()))
+        .collect();
+    if finished.len() <= MAX_TRACKED_FINISHED_TASKS {
+        return;
+    }
+    finished.sort_by(|a, b| a.1.cmp(&b.1));
+    let remove_count = finished.len() - MAX_TRACKED_FINISHED_TASKS;
+    for (id, _) in finished.into_iter().take(remove_count) {
+        tasks.remove(&id);
+    }
+}
+
+/// 全局后台任务管理器（AppState 与工具调用共享同一实例）
+pub fn background_task_manager() -> Arc<BackgroundTaskManager> {
+    static BACKGROUND_TASKS: OnceLock<Arc<BackgroundTaskManager>> = OnceLock::new();
+    Arc::clone(BACKGROUND_TASKS.get_or_init(|| Arc::new(BackgroundTaskManager::default())))
+}
+
+/// 读取任务输出的末尾部分
+pub fn read_task_output(info: &BackgroundTaskInfo, max_bytes: Option<u64>) -> Result<String, String> {
+    let path = PathBuf::from(&info.output_path);
+    let mut file = fs::File::open(&path).map_err(|e| format!("open task output failed: {}", e))?;
+    let len = file
+        .metadata()
+        .map_err(|e| format!("read task output failed: {}", e))?
+        .len();
+    let max_bytes = max_bytes.unwrap_or(DEFAULT_TASK_OUTPUT_BYTES).max(1);
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("read task output failed: {}", e))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("read task output failed: {}", e))?;
+    // 截断位置可能落在多字节字符中间，跳过开头的 UTF-8 续字节，避免被当成本地代码页解码
+    if start > 0 {
+        let skip = bytes
+            .iter()
+            .take(3)
+            .take_while(|byte| (**byte & 0xC0) == 0x80)
+            .count();
+        bytes.drain(..skip);
+    }
+    let text = super::decode_command_output(&bytes);
+    if start > 0 {
+        Ok(format!("[output truncated, showing last {} bytes]\n{}", bytes.len(), text))
+    } else {
+        Ok(text)
+    }
+}
+
+/// task_status 工具的文本输出
+pub fn format_task_status(task_id: Option<&str>, max_bytes: Option<u64>) -> String {
+    let manager = background_task_manager();
+    match task_id.map(|id| id.trim()).filter(|id| !id.is_empty()) {
+        Some(id) => match manager.get(id) {
+            Some(info) => {
+                let mut out = format_task_line(&info);
+                match read_task_output(&info, max_bytes) {
+                    Ok(output) if !output.trim().is_empty() => {
+                        out.push_str("\noutput:\n");
+                        out.push_str(output.trim_end());
+                    }
+                    Ok(_) => out.push_str("\noutput: (empty)"),
+                    Err(err) => out.push_str(&format!("\noutput: {}", err)),
+                }
+                out
+            }
+            None => format!("background task not found: {}", id),
+        },
+        None => {
+            let tasks = manager.list();
+            if tasks.is_empty() {
+                return "no background tasks".to_string();
+            }
+            tasks
+                .iter()
+                .map(format_task_line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+fn format_task_line(info: &BackgroundTaskInfo) -> String {
+    let (command, _) = truncate_string(&info.command, 120);
+    format!(
+        "{} [{}{}] pid={} started={} cmd={}",
+        info.id,
+        info.status,
+        info.exit_code
+            .map(|code| format!(" exit {}", code))
+            .unwrap_or_default(),
+        info.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()),
+        info.started_at,
+        command
+    )
+}
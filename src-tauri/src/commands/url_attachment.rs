@@ -0,0 +1,252 @@
+use super::AttachmentInput;
+use crate::storage::StorageManager;
+use chrono::Local;
+use scraper::{ElementRef, Html, Selector};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const URL_FETCH_TIMEOUT_SECS: u64 = 20;
+const MAX_URL_RESPONSE_BYTES: usize = 5 * 1024 * 1024;
+// 缓存的正文上限，发给模型时再按附件长度截断
+const MAX_CACHED_TEXT_CHARS: usize = 200_000;
+// 同一网址在该时间内重复附加时直接使用缓存
+const URL_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+// 正文候选容器至少包含的字符数，否则按段落打分重新挑选
+const MIN_ARTICLE_CHARS: usize = 500;
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "nav", "header", "footer", "aside", "form", "button", "iframe",
+];
+const BLOCK_TAGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6", "p", "li", "pre", "blockquote", "td", "th"];
+
+fn is_url(path: &str) -> bool {
+    let lower = path.trim().to_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// 抓取 kind 为 url 的附件并缓存到 attachments/ 下，把附件路径替换为缓存文件；
+/// 抓取失败时保持原网址，附件内容中会注明无法获取
+pub(super) async fn fetch_url_attachments(attachments: Option<Vec<AttachmentInput>>) -> Option<Vec<AttachmentInput>> {
+    let mut attachments = attachments?;
+    for attachment in attachments.iter_mut() {
+        if attachment.kind.as_deref() != Some("url") || !is_url(&attachment.path) {
+            continue;
+        }
+        let url = attachment.path.trim().to_string();
+        match fetch_url_cached(&url).await {
+            Ok((path, title)) => {
+                if attachment.name.trim().is_empty() {
+                    attachment.name = title.unwrap_or_else(|| url.clone());
+                }
+                attachment.path = path.to_string_lossy().to_string();
+            }
+            Err(err) => {
+                crate::logs::warn("attachment", format!("抓取网页 {} 失败: {}", url, err));
+                if attachment.name.trim().is_empty() {
+                    attachment.name = url;
+                }
+            }
+        }
+    }
+    Some(attachments)
+}
+
+fn url_cache_path(url: &str) -> PathBuf {
+    let digest = Sha256::digest(url.as_bytes());
+    let hash: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    StorageManager::new().get_data_dir().join("attachments").join(format!("url-{}.md", hash))
+}
+
+/// 返回缓存文件路径和网页标题
+async fn fetch_url_cached(url: &str) -> Result<(PathBuf, Option<String>), String> {
+    let path = url_cache_path(url);
+    let fresh = fs::metadata(&path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map_or(false, |age| age < URL_CACHE_TTL);
+    if fresh {
+        let title = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| content.lines().next().and_then(|line| line.strip_prefix("# ")).map(str::to_string));
+        return Ok((path, title));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(URL_FETCH_TIMEOUT_SECS))
+        .user_agent("Mozilla/5.0 (compatible; OpenCowork)")
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+    let mut response = client.get(url).send().await.map_err(|e| format!("请求失败: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+    if !content_type.is_empty()
+        && !content_type.contains("html")
+        && !content_type.starts_with("text/")
+        && !content_type.contains("json")
+        && !content_type.contains("xml")
+    {
+        return Err(format!("不支持的内容类型: {}", content_type));
+    }
+    // 声明的长度超限时直接放弃；未声明或声明不实时边读边计数，超限即停止读取
+    if response.content_length().map_or(false, |len| len > MAX_URL_RESPONSE_BYTES as u64) {
+        return Err("网页过大".to_string());
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("读取响应失败: {}", e))? {
+        if bytes.len() + chunk.len() > MAX_URL_RESPONSE_BYTES {
+            return Err("网页过大".to_string());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    let body = String::from_utf8_lossy(&bytes);
+    let (title, text) = if content_type.is_empty() || content_type.contains("html") {
+        extract_readable_text(&body)
+    } else {
+        (None, body.trim().to_string())
+    };
+    if text.trim().is_empty() {
+        return Err("未提取到正文".to_string());
+    }
+
+    let (text, truncated) = super::truncate_string(&text, MAX_CACHED_TEXT_CHARS);
+    let mut content = format!(
+        "# {}\n来源: {}\n抓取时间: {}\n\n{}",
+        title.as_deref().unwrap_or(url),
+        url,
+        Local::now().format("%Y-%m-%d %H:%M"),
+        text
+    );
+    if truncated {
+        content.push_str("\n...(已截断)");
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建附件目录失败: {}", e))?;
+    }
+    fs::write(&path, content).map_err(|e| format!("保存网页缓存失败: {}", e))?;
+    Ok((path, title))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn inside_skipped(element: ElementRef, root: ElementRef) -> bool {
+    element.ancestors().take_while(|node| node.id() != root.id()).any(|node| {
+        node.value()
+            .as_element()
+            .map_or(false, |el| SKIPPED_TAGS.contains(&el.name()))
+    })
+}
+
+/// 类似 readability 的正文提取：优先 article/main，否则选包含最多段落文字的容器，
+/// 去掉导航、页眉页脚、脚本等，按段落输出；返回网页标题和正文
+fn extract_readable_text(html: &str) -> (Option<String>, String) {
+    let document = Html::parse_document(html);
+    let select_first = |selector: &str| {
+        Selector::parse(selector)
+            .ok()
+            .and_then(|sel| document.select(&sel).next())
+    };
+
+    let title = select_first("meta[property=\"og:title\"]")
+        .and_then(|el| el.value().attr("content").map(str::to_string))
+        .or_else(|| select_first("title").map(|el| el.text().collect::<String>()))
+        .map(|title| collapse_whitespace(&title))
+        .filter(|title| !title.is_empty());
+
+    let root = ["article", "main", "[role=\"main\"]"]
+        .into_iter()
+        .filter_map(|selector| select_first(selector))
+        .find(|el| el.text().map(str::len).sum::<usize>() >= MIN_ARTICLE_CHARS)
+        .or_else(|| best_scored_container(&document))
+        .or_else(|| select_first("body"));
+    let Some(root) = root else {
+        return (title, String::new());
+    };
+
+    let Ok(block_selector) = Selector::parse(&BLOCK_TAGS.join(",")) else {
+        return (title, String::new());
+    };
+    let mut blocks = Vec::new();
+    for element in root.select(&block_selector) {
+        if inside_skipped(element, root) {
+            continue;
+        }
+        // 嵌套在其他段落块中的（如 li 里的 p）由外层一起输出
+        let nested = element.ancestors().take_while(|node| node.id() != root.id()).any(|node| {
+            node.value()
+                .as_element()
+                .map_or(false, |el| BLOCK_TAGS.contains(&el.name()))
+        });
+        if nested {
+            continue;
+        }
+        let name = element.value().name();
+        let raw: String = element.text().collect();
+        let text = if name == "pre" {
+            raw.trim_end().to_string()
+        } else {
+            collapse_whitespace(&raw)
+        };
+        if text.is_empty() {
+            continue;
+        }
+        let block = match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(2);
+                format!("{} {}", "#".repeat(level), text)
+            }
+            "li" => format!("- {}", text),
+            "blockquote" => format!("> {}", text),
+            "pre" => format!("```\n{}\n```", text),
+            _ => text,
+        };
+        if blocks.last() != Some(&block) {
+            blocks.push(block);
+        }
+    }
+    if blocks.is_empty() {
+        return (title, collapse_whitespace(&root.text().collect::<String>()));
+    }
+    (title, blocks.join("\n\n"))
+}
+
+/// 每个足够长的段落给父容器加分（祖父容器加一半），选得分最高的容器
+fn best_scored_container(document: &Html) -> Option<ElementRef<'_>> {
+    let selector = Selector::parse("p").ok()?;
+    let mut scores: HashMap<_, (f64, ElementRef)> = HashMap::new();
+    for paragraph in document.select(&selector) {
+        let text: String = paragraph.text().collect();
+        let len = collapse_whitespace(&text).chars().count();
+        if len < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + (len / 100).min(3) as f64 + text.matches([',', '，']).count() as f64;
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        for weight in [1.0, 0.5] {
+            let Some(container) = ancestors.next() else {
+                break;
+            };
+            if SKIPPED_TAGS.contains(&container.value().name()) {
+                break;
+            }
+            scores.entry(container.id()).or_insert((0.0, container)).0 += score * weight;
+        }
+    }
+    scores
+        .into_values()
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, container)| container)
+}
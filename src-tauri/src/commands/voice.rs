@@ -0,0 +1,180 @@
+use crate::model::{with_usage_feature, ModelManager};
+use crate::storage::{SpeechConfig, StorageManager};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// OpenAI 转写接口的文件大小上限
+const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+/// 解析录音：data URL、本地文件路径或纯 base64；返回音频字节和带扩展名的文件名
+fn decode_audio(audio: &str, file_name: Option<&str>) -> Result<(Vec<u8>, String), String> {
+    let audio = audio.trim();
+    if audio.is_empty() {
+        return Err("录音为空".to_string());
+    }
+    let (bytes, ext) = if let Some(rest) = audio.strip_prefix("data:") {
+        let (header, data) = rest.split_once(',').ok_or_else(|| "无效的 data URL".to_string())?;
+        let mime = header.split(';').next().unwrap_or("");
+        let bytes = BASE64.decode(data.trim()).map_err(|e| format!("解码录音失败: {}", e))?;
+        (bytes, audio_ext_from_mime(mime).to_string())
+    } else if audio.len() < 1024 && Path::new(audio).is_file() {
+        let ext = Path::new(audio)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("wav")
+            .to_lowercase();
+        (fs::read(audio).map_err(|e| format!("读取录音失败: {}", e))?, ext)
+    } else {
+        let bytes = BASE64.decode(audio).map_err(|e| format!("解码录音失败: {}", e))?;
+        (bytes, "webm".to_string())
+    };
+    if bytes.len() > MAX_AUDIO_BYTES {
+        return Err("录音过长，请分段录制（上限 25MB）".to_string());
+    }
+    let name = match file_name.map(str::trim).filter(|name| name.contains('.')) {
+        Some(name) => name.to_string(),
+        None => format!("voice.{}", ext),
+    };
+    Ok((bytes, name))
+}
+
+fn audio_ext_from_mime(mime: &str) -> &'static str {
+    match mime.trim().to_lowercase().as_str() {
+        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+        "audio/ogg" => "ogg",
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/mp4" | "audio/m4a" | "audio/x-m4a" => "m4a",
+        "audio/flac" => "flac",
+        _ => "webm",
+    }
+}
+
+/// 语音输入：把前端录制的音频转成文字，结果由前端填入输入框。
+/// audio 可以是 data URL、base64 或本地文件路径；language 为空时使用配置（仍为空则自动识别）
+#[tauri::command]
+pub async fn transcribe_audio(
+    audio: String,
+    file_name: Option<String>,
+    language: Option<String>,
+) -> Result<String, String> {
+    let config = StorageManager::new().load_config()?;
+    let (bytes, file_name) = decode_audio(&audio, file_name.as_deref())?;
+    let language = language
+        .map(|lang| lang.trim().to_string())
+        .filter(|lang| !lang.is_empty())
+        .unwrap_or_else(|| config.speech.language.trim().to_string());
+
+    let text = match config.speech.stt_provider.as_str() {
+        "whisper_cpp" => {
+            let speech = config.speech.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                transcribe_with_whisper_cpp(&speech, &bytes, &file_name, &language)
+            })
+            .await
+            .map_err(|e| format!("转写任务失败: {}", e))??
+        }
+        _ => {
+            with_usage_feature(
+                "speech",
+                ModelManager::new().transcribe_audio(
+                    &config.model,
+                    config.speech.stt_model.trim(),
+                    &bytes,
+                    &file_name,
+                    &language,
+                ),
+            )
+            .await?
+        }
+    };
+    if text.trim().is_empty() {
+        return Err("未识别到语音内容".to_string());
+    }
+    Ok(text.trim().to_string())
+}
+
+/// 本地 whisper.cpp 转写；非 wav 录音先用 ffmpeg 转成 16kHz 单声道
+fn transcribe_with_whisper_cpp(
+    speech: &SpeechConfig,
+    audio: &[u8],
+    file_name: &str,
+    language: &str,
+) -> Result<String, String> {
+    let model = speech.whisper_cpp_model.trim();
+    if model.is_empty() {
+        return Err("未配置 whisper.cpp 模型文件".to_string());
+    }
+    let binary = match speech.whisper_cpp_binary.trim() {
+        "" => "whisper-cli",
+        binary => binary,
+    };
+    let ext = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("webm")
+        .to_lowercase();
+    let base = std::env::temp_dir().join(format!("opencowork-voice-{}", Local::now().timestamp_millis()));
+    let input = base.with_extension(&ext);
+    fs::write(&input, audio).map_err(|e| format!("保存录音失败: {}", e))?;
+    let mut temp_files: Vec<PathBuf> = vec![input.clone()];
+
+    let result = (|| {
+        let wav = if ext == "wav" {
+            input.clone()
+        } else {
+            let wav = base.with_extension("16k.wav");
+            temp_files.push(wav.clone());
+            let output = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-i"])
+                .arg(&input)
+                .args(["-ar", "16000", "-ac", "1"])
+                .arg(&wav)
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| format!("无法运行 ffmpeg（转换录音格式需要）: {}", e))?;
+            if !output.status.success() {
+                return Err(format!("转换录音格式失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            wav
+        };
+        let output = Command::new(binary)
+            .args(["-m", model, "-nt", "-np", "-l"])
+            .arg(if language.is_empty() { "auto" } else { language })
+            .arg("-f")
+            .arg(&wav)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("无法运行 whisper.cpp（{}）: {}", binary, e))?;
+        if !output.status.success() {
+            return Err(format!("whisper.cpp 转写失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        let text = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(text)
+    })();
+
+    for path in temp_files {
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+/// 朗读文字（如助手回答）；voice 为空时使用配置中的音色
+#[tauri::command]
+pub async fn speak_text(text: String, voice: Option<String>) -> Result<(), String> {
+    let config = StorageManager::new().load_config()?;
+    crate::tts::speak(&config, &text, voice.as_deref()).await
+}
+
+/// 停止当前朗读，返回是否有正在进行的朗读
+#[tauri::command]
+pub fn stop_speaking() -> bool {
+    crate::tts::stop_speaking()
+}
@@ -0,0 +1,114 @@
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+
+// 额外聊天窗口的 label 前缀，capabilities 中按 chat-* 授权
+const CHAT_WINDOW_PREFIX: &str = "chat-";
+
+static CHAT_WINDOW_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// 一个独立的聊天窗口，session_id 用于区分各窗口的会话和请求
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatWindow {
+    pub label: String,
+    pub session_id: String,
+    pub title: String,
+    pub created_at: String,
+}
+
+fn chat_windows() -> &'static ParkingMutex<HashMap<String, ChatWindow>> {
+    static WINDOWS: OnceLock<ParkingMutex<HashMap<String, ChatWindow>>> = OnceLock::new();
+    WINDOWS.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+/// 进度/授权事件只发给发起请求的窗口；label 无效时返回 None，退回广播
+pub(super) fn event_target(app_handle: &AppHandle, label: Option<&str>) -> Option<String> {
+    let label = label.map(str::trim).filter(|label| !label.is_empty())?;
+    app_handle.get_webview_window(label).map(|_| label.to_string())
+}
+
+/// 打开新的聊天窗口；传入已打开窗口的 session_id 时聚焦该窗口
+#[tauri::command]
+pub async fn open_chat_window(
+    app_handle: AppHandle,
+    session_id: Option<String>,
+    title: Option<String>,
+) -> Result<ChatWindow, String> {
+    let session_id = session_id.map(|id| id.trim().to_string()).filter(|id| !id.is_empty());
+    if let Some(session_id) = &session_id {
+        let existing = chat_windows()
+            .lock()
+            .values()
+            .find(|window| &window.session_id == session_id)
+            .cloned();
+        if let Some(existing) = existing {
+            if let Some(window) = app_handle.get_webview_window(&existing.label) {
+                let _ = window.show();
+                let _ = window.set_focus();
+                return Ok(existing);
+            }
+        }
+    }
+
+    let seq = CHAT_WINDOW_SEQ.fetch_add(1, Ordering::Relaxed);
+    let label = format!("{}{}", CHAT_WINDOW_PREFIX, seq);
+    let session_id =
+        session_id.unwrap_or_else(|| format!("session-{}-{}", Local::now().timestamp_millis(), seq));
+    let title = title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| format!("OpenCowork 对话 {}", seq));
+    let url = format!(
+        "/?chat_window=1&session={}&window={}",
+        urlencoding::encode(&session_id),
+        urlencoding::encode(&label)
+    );
+    let window = WebviewWindowBuilder::new(&app_handle, &label, WebviewUrl::App(url.into()))
+        .title(&title)
+        .inner_size(760.0, 680.0)
+        .min_inner_size(480.0, 400.0)
+        .build()
+        .map_err(|e| format!("创建聊天窗口失败: {}", e))?;
+
+    let info = ChatWindow {
+        label: label.clone(),
+        session_id,
+        title,
+        created_at: Local::now().to_rfc3339(),
+    };
+    chat_windows().lock().insert(label.clone(), info.clone());
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            chat_windows().lock().remove(&label);
+            super::set_approval_listener(&label, false);
+        }
+    });
+    let _ = window.set_focus();
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn list_chat_windows() -> Result<Vec<ChatWindow>, String> {
+    let mut windows: Vec<ChatWindow> = chat_windows().lock().values().cloned().collect();
+    windows.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(windows)
+}
+
+#[tauri::command]
+pub async fn close_chat_window(app_handle: AppHandle, label: String) -> Result<bool, String> {
+    if !label.starts_with(CHAT_WINDOW_PREFIX) {
+        return Err(format!("不是聊天窗口: {}", label));
+    }
+    chat_windows().lock().remove(&label);
+    match app_handle.get_webview_window(&label) {
+        Some(window) => {
+            window.close().map_err(|e| format!("关闭窗口失败: {}", e))?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,173 @@
+use crate::model::is_image_unsupported_error;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+
+// 与前端约定的标记字符串，取消/未设置工具模式时前端按此识别
+pub const REQUEST_CANCELLED_ERROR: &str = "REQUEST_CANCELLED";
+pub const TOOL_MODE_UNSET_ERROR: &str = "TOOLS_MODE_UNSET";
+
+// 各服务商上下文超长的报错关键字
+const CONTEXT_OVERFLOW_MARKERS: &[&str] = &[
+    "context_length_exceeded",
+    "context length",
+    "context window",
+    "maximum context",
+    "too many tokens",
+    "token limit",
+    "prompt is too long",
+    "input is too long",
+    "improperly formed request",
+    "bad request",
+];
+
+/// 应用内统一错误类型；Display 保持原有错误文本，命令边界可直接转成 String 返回前端
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AppError {
+    #[error("{}", REQUEST_CANCELLED_ERROR)]
+    Cancelled,
+    #[error("{}", TOOL_MODE_UNSET_ERROR)]
+    ToolModeUnset,
+    #[error("{message}")]
+    ContextOverflow { message: String },
+    #[error("{message}")]
+    ImageUnsupported { message: String },  // 模型不接受图片输入
+    #[error("{message}")]
+    ToolDenied { tool: String, message: String },
+    #[error("{message}")]
+    ProviderHttp {
+        status: u16,
+        message: String,
+        retry_after: Option<u64>,  // 服务端 Retry-After（秒）
+    },
+    #[error("{message}")]
+    Network { message: String },  // 连接失败、DNS、超时
+    #[error("{message}")]
+    Io { message: String },
+    #[error("{message}")]
+    Other { message: String },
+}
+
+impl AppError {
+    pub fn tool_denied(tool: &str) -> Self {
+        AppError::ToolDenied {
+            tool: tool.to_string(),
+            message: format!("工具未被 skill 允许: {}", tool),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Cancelled => "cancelled",
+            AppError::ToolModeUnset => "tool_mode_unset",
+            AppError::ContextOverflow { .. } => "context_overflow",
+            AppError::ImageUnsupported { .. } => "image_unsupported",
+            AppError::ToolDenied { .. } => "tool_denied",
+            AppError::ProviderHttp { .. } => "provider_http",
+            AppError::Network { .. } => "network",
+            AppError::Io { .. } => "io",
+            AppError::Other { .. } => "other",
+        }
+    }
+
+    /// 临时故障（网络、超时、限流、5xx），可以重试
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AppError::Network { .. } => true,
+            AppError::ProviderHttp { status, .. } => matches!(*status, 408 | 429 | 500..=599),
+            _ => false,
+        }
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, AppError::ProviderHttp { status: 429, .. })
+    }
+
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::ProviderHttp { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        AppError::Other { message: message.into() }
+    }
+
+    /// 服务商返回的错误：status 为 HTTP 状态码（响应体里的 error 字段为 None），
+    /// 再按服务商的错误信息区分上下文超长和不支持图片
+    pub fn provider(status: Option<u16>, message: String, retry_after: Option<u64>) -> Self {
+        // 限流和服务端错误优先，其报错正文里也可能出现 token limit 之类的字样
+        if let Some(status @ (429 | 500..=599)) = status {
+            return AppError::ProviderHttp { status, message, retry_after };
+        }
+        let lower = message.to_lowercase();
+        // 不支持图片的报错常是 400 Bad Request，需在上下文超长之前判断
+        if is_image_unsupported_error(&lower) {
+            return AppError::ImageUnsupported { message };
+        }
+        if CONTEXT_OVERFLOW_MARKERS.iter().any(|marker| lower.contains(marker)) {
+            return AppError::ContextOverflow { message };
+        }
+        match status {
+            Some(status) => AppError::ProviderHttp { status, message, retry_after },
+            None => AppError::Other { message },
+        }
+    }
+
+    /// 请求没有拿到响应：连接失败、DNS、超时归为网络错误
+    pub fn request_failed(err: &reqwest::Error, message: String) -> Self {
+        if err.is_timeout() || err.is_connect() || err.is_request() {
+            AppError::Network { message }
+        } else {
+            AppError::Other { message }
+        }
+    }
+}
+
+/// 尚未改为 AppError 的层返回的错误文本；只识别约定的标记字符串，其余一律为 Other
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        match message.trim() {
+            REQUEST_CANCELLED_ERROR => AppError::Cancelled,
+            TOOL_MODE_UNSET_ERROR => AppError::ToolModeUnset,
+            _ => AppError::Other { message },
+        }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::from(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        AppError::Io { message: err.to_string() }
+    }
+}
+
+impl From<AppError> for String {
+    fn from(err: AppError) -> Self {
+        err.to_string()
+    }
+}
+
+/// 序列化为 { kind, message, status?, tool?, retry_after? }，前端按 kind 区分错误类型
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            AppError::ProviderHttp { status, retry_after, .. } => {
+                state.serialize_field("status", status)?;
+                state.serialize_field("retry_after", retry_after)?;
+            }
+            AppError::ToolDenied { tool, .. } => state.serialize_field("tool", tool)?,
+            _ => {}
+        }
+        state.end()
+    }
+}
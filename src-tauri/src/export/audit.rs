@@ -0,0 +1,121 @@
+use crate::storage::{AuditEvent, StorageManager};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{Duration, Local, NaiveDate};
+use ed25519_dalek::Signer;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+const AUDIT_TRAIL_VERSION: u32 = 1;
+const MAX_AUDIT_EXPORT_DAYS: i64 = 366;
+
+/// 审计导出结果；public_key 需由管理员另行留存，用于核验导出文件未被改动
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditExportResult {
+    pub path: String,
+    pub events: usize,
+    pub public_key: String,
+    pub last_hash: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 导出签名的 JSONL 审计记录：
+/// 首行为 header（含公钥），之后每行的 prev_hash 为上一行原始文本的 sha256，
+/// 末行用 Ed25519 对最后一行的哈希签名，任意一行被改动或删除都会导致核验失败
+pub fn export_audit_trail(
+    storage: &StorageManager,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    output_path: Option<PathBuf>,
+) -> Result<AuditExportResult, String> {
+    if end_date < start_date {
+        return Err("结束日期不能早于开始日期".to_string());
+    }
+    if (end_date - start_date).num_days() >= MAX_AUDIT_EXPORT_DAYS {
+        return Err(format!("一次最多导出 {} 天", MAX_AUDIT_EXPORT_DAYS));
+    }
+
+    let signing_key = storage.audit_signing_key()?;
+    let public_key = BASE64.encode(signing_key.verifying_key().to_bytes());
+
+    let mut events: Vec<AuditEvent> = Vec::new();
+    let mut date = start_date;
+    while date <= end_date {
+        events.extend(storage.load_audit_events(&date.format("%Y-%m-%d").to_string()));
+        date += Duration::days(1);
+    }
+    events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    let header = json!({
+        "type": "header",
+        "version": AUDIT_TRAIL_VERSION,
+        "app": "OpenCowork",
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "generated_at": Local::now().to_rfc3339(),
+        "start_date": start_date.format("%Y-%m-%d").to_string(),
+        "end_date": end_date.format("%Y-%m-%d").to_string(),
+        "hash_algorithm": "sha256",
+        "signature_algorithm": "ed25519",
+        "public_key": public_key,
+    })
+    .to_string();
+
+    // 按原始行文本计算哈希，核验时无需重新序列化 JSON
+    let mut prev_hash = sha256_hex(header.as_bytes());
+    let mut lines = vec![header];
+    for (index, event) in events.iter().enumerate() {
+        let line = json!({
+            "type": "event",
+            "seq": index + 1,
+            "timestamp": event.timestamp,
+            "kind": event.kind,
+            "detail": event.detail,
+            "prev_hash": prev_hash,
+        })
+        .to_string();
+        prev_hash = sha256_hex(line.as_bytes());
+        lines.push(line);
+    }
+    let signature = signing_key.sign(prev_hash.as_bytes());
+    lines.push(
+        json!({
+            "type": "signature",
+            "count": events.len(),
+            "last_hash": prev_hash,
+            "signature": BASE64.encode(signature.to_bytes()),
+        })
+        .to_string(),
+    );
+
+    let output = match output_path {
+        Some(path) => path,
+        None => {
+            let dir = storage.get_data_dir().join("exports");
+            fs::create_dir_all(&dir).map_err(|e| format!("创建导出目录失败: {}", e))?;
+            dir.join(format!(
+                "audit-{}-{}-{}.jsonl",
+                start_date,
+                end_date,
+                Local::now().format("%Y%m%d%H%M%S")
+            ))
+        }
+    };
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建导出目录失败: {}", e))?;
+    }
+    let mut content = lines.join("\n");
+    content.push('\n');
+    fs::write(&output, content).map_err(|e| format!("写入导出文件失败: {}", e))?;
+
+    Ok(AuditExportResult {
+        path: output.to_string_lossy().to_string(),
+        events: events.len(),
+        public_key,
+        last_hash: prev_hash,
+    })
+}
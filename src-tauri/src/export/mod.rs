@@ -0,0 +1,471 @@
+use crate::storage::{AggregationGranularity, StorageManager, SummaryRecord};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::{Duration, Local, NaiveDate};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod audit;
+mod session;
+mod timelapse;
+pub use audit::*;
+pub use session::*;
+pub use timelapse::*;
+
+const MAX_EXPORT_DAYS: i64 = 92;
+const MAX_EXPORT_SCREENSHOTS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" | "htm" => Ok(ExportFormat::Html),
+            "pdf" => Ok(ExportFormat::Pdf),
+            other => Err(format!("不支持的导出格式: {}", other)),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+pub struct ExportRequest {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub format: ExportFormat,
+    pub transcript: Vec<(String, String)>, // (role, content)
+    pub include_screenshots: bool,
+    pub output_path: Option<PathBuf>,
+}
+
+/// 报告中的一张截图（Markdown 复制到资源目录，HTML/PDF 内嵌）
+struct ExportImage {
+    file_name: String,
+    bytes: Vec<u8>,
+}
+
+struct DayReport {
+    date: String,
+    record_count: usize,
+    apps: Vec<(String, usize)>,
+    timeline: Vec<String>,
+    issues: Vec<IssueEntry>,
+}
+
+struct IssueEntry {
+    time: String,
+    title: String,
+    suggestion: String,
+    resolution: String,
+    image: Option<ExportImage>,
+}
+
+pub fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("日期格式应为 YYYY-MM-DD: {}", value))
+}
+
+/// 导出日报与对话记录，返回生成的文件路径
+pub fn export_summaries(storage: &StorageManager, request: &ExportRequest) -> Result<PathBuf, String> {
+    if request.end_date < request.start_date {
+        return Err("结束日期不能早于开始日期".to_string());
+    }
+    if (request.end_date - request.start_date).num_days() >= MAX_EXPORT_DAYS {
+        return Err(format!("一次最多导出 {} 天", MAX_EXPORT_DAYS));
+    }
+
+    let reports = collect_reports(storage, request)?;
+    let title = if request.start_date == request.end_date {
+        format!("OpenCowork 活动报告 {}", request.start_date)
+    } else {
+        format!("OpenCowork 活动报告 {} ~ {}", request.start_date, request.end_date)
+    };
+
+    let output = match &request.output_path {
+        Some(path) => path.clone(),
+        None => {
+            let dir = storage.get_data_dir().join("exports");
+            fs::create_dir_all(&dir).map_err(|e| format!("创建导出目录失败: {}", e))?;
+            dir.join(format!(
+                "report-{}-{}.{}",
+                request.start_date,
+                Local::now().format("%Y%m%d%H%M%S"),
+                request.format.extension()
+            ))
+        }
+    };
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建导出目录失败: {}", e))?;
+    }
+
+    match request.format {
+        ExportFormat::Markdown => {
+            let markdown = render_markdown(&title, &reports, &request.transcript, &output)?;
+            fs::write(&output, markdown).map_err(|e| format!("写入导出文件失败: {}", e))?;
+        }
+        ExportFormat::Html => {
+            let html = render_html(&title, &reports, &request.transcript);
+            fs::write(&output, html).map_err(|e| format!("写入导出文件失败: {}", e))?;
+        }
+        ExportFormat::Pdf => {
+            let html = render_html(&title, &reports, &request.transcript);
+            let temp_html = std::env::temp_dir().join(format!(
+                "opencowork-export-{}.html",
+                Local::now().timestamp_millis()
+            ));
+            fs::write(&temp_html, html).map_err(|e| format!("写入临时文件失败: {}", e))?;
+            let result = print_html_to_pdf(&temp_html, &output);
+            let _ = fs::remove_file(&temp_html);
+            result?;
+        }
+    }
+
+    Ok(output)
+}
+
+fn collect_reports(storage: &StorageManager, request: &ExportRequest) -> Result<Vec<DayReport>, String> {
+    let screenshots_dir = storage.screenshots_dir()?;
+    let mut reports = Vec::new();
+    let mut image_budget = if request.include_screenshots { MAX_EXPORT_SCREENSHOTS } else { 0 };
+    let mut date = request.start_date;
+    while date <= request.end_date {
+        let date_str = date.format("%Y-%m-%d").to_string();
+        let records = storage.get_summaries(&date_str)?;
+        date += Duration::days(1);
+        let records: Vec<SummaryRecord> = records
+            .into_iter()
+            .filter(|record| record.action != "private")
+            .collect();
+        if records.is_empty() {
+            continue;
+        }
+
+        let mut app_counts: HashMap<String, usize> = HashMap::new();
+        for record in &records {
+            let app = record.app.trim();
+            if !app.is_empty() && app != "Unknown" {
+                *app_counts.entry(app.to_string()).or_insert(0) += 1;
+            }
+        }
+        let mut apps: Vec<(String, usize)> = app_counts.into_iter().collect();
+        apps.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        apps.truncate(8);
+
+        let timeline = storage
+            .aggregate_by(&records, AggregationGranularity::AppSession)
+            .iter()
+            .map(|agg| {
+                format!(
+                    "{} ~ {} {}",
+                    agg.start_time.get(11..16).unwrap_or(""),
+                    agg.end_time.get(11..16).unwrap_or(""),
+                    agg.summary
+                )
+            })
+            .collect();
+
+        let mut issues = Vec::new();
+        for record in records.iter().filter(|record| record.has_issue) {
+            let image = if image_budget > 0 && !record.detail_ref.is_empty() {
+                storage
+                    .read_data_file(&screenshots_dir.join(&record.detail_ref))
+                    .ok()
+                    .map(|bytes| ExportImage {
+                        file_name: record.detail_ref.clone(),
+                        bytes,
+                    })
+            } else {
+                None
+            };
+            if image.is_some() {
+                image_budget -= 1;
+            }
+            let title = if record.issue_summary.is_empty() {
+                record.summary.clone()
+            } else {
+                record.issue_summary.clone()
+            };
+            issues.push(IssueEntry {
+                time: record.timestamp.get(11..19).unwrap_or("").to_string(),
+                title: if record.issue_type.is_empty() {
+                    title
+                } else {
+                    format!("[{}] {}", record.issue_type, title)
+                },
+                suggestion: record.suggestion.clone(),
+                resolution: record.resolution.clone(),
+                image,
+            });
+        }
+
+        reports.push(DayReport {
+            date: date_str,
+            record_count: records.len(),
+            apps,
+            timeline,
+            issues,
+        });
+    }
+    Ok(reports)
+}
+
+fn resolution_label(resolution: &str) -> &'static str {
+    match resolution {
+        "resolved" => "已解决",
+        "unresolved" => "未解决",
+        _ => "",
+    }
+}
+
+fn render_markdown(
+    title: &str,
+    reports: &[DayReport],
+    transcript: &[(String, String)],
+    output: &Path,
+) -> Result<String, String> {
+    // 截图复制到与报告同名的 _assets 目录，使用相对路径引用
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "report".to_string());
+    let assets_name = format!("{}_assets", stem);
+    let assets_dir = output
+        .parent()
+        .map(|dir| dir.join(&assets_name))
+        .unwrap_or_else(|| PathBuf::from(&assets_name));
+
+    let mut md = format!("# {}\n\n", title);
+    if reports.is_empty() {
+        md.push_str("所选时间范围内没有活动记录。\n\n");
+    }
+    for report in reports {
+        md.push_str(&format!("## {}\n\n", report.date));
+        md.push_str(&format!("- 记录数: {}\n", report.record_count));
+        if !report.apps.is_empty() {
+            let apps: Vec<String> = report
+                .apps
+                .iter()
+                .map(|(app, count)| format!("{} ({})", app, count))
+                .collect();
+            md.push_str(&format!("- 常用应用: {}\n", apps.join(", ")));
+        }
+        md.push('\n');
+
+        if !report.timeline.is_empty() {
+            md.push_str("### 时间线\n\n");
+            for line in &report.timeline {
+                md.push_str(&format!("- {}\n", line));
+            }
+            md.push('\n');
+        }
+
+        if !report.issues.is_empty() {
+            md.push_str("### 问题与提醒\n\n");
+            for issue in &report.issues {
+                md.push_str(&format!("- **{}** {}", issue.time, issue.title));
+                let label = resolution_label(&issue.resolution);
+                if !label.is_empty() {
+                    md.push_str(&format!("（{}）", label));
+                }
+                md.push('\n');
+                if !issue.suggestion.is_empty() {
+                    md.push_str(&format!("  - 建议: {}\n", issue.suggestion.replace('\n', " ")));
+                }
+                if let Some(image) = &issue.image {
+                    fs::create_dir_all(&assets_dir).map_err(|e| format!("创建资源目录失败: {}", e))?;
+                    fs::write(assets_dir.join(&image.file_name), &image.bytes)
+                        .map_err(|e| format!("写入截图失败: {}", e))?;
+                    md.push_str(&format!("\n  ![{}]({}/{})\n", issue.time, assets_name, image.file_name));
+                }
+            }
+            md.push('\n');
+        }
+    }
+
+    if !transcript.is_empty() {
+        md.push_str("## 对话记录\n\n");
+        for (role, content) in transcript {
+            md.push_str(&format!("**{}**\n\n{}\n\n", role_label(role), content.trim()));
+        }
+    }
+    Ok(md)
+}
+
+fn render_html(title: &str, reports: &[DayReport], transcript: &[(String, String)]) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape_html(title));
+    if reports.is_empty() {
+        body.push_str("<p>所选时间范围内没有活动记录。</p>\n");
+    }
+    for report in reports {
+        body.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape_html(&report.date)));
+        body.push_str(&format!("<li>记录数: {}</li>\n", report.record_count));
+        if !report.apps.is_empty() {
+            let apps: Vec<String> = report
+                .apps
+                .iter()
+                .map(|(app, count)| format!("{} ({})", escape_html(app), count))
+                .collect();
+            body.push_str(&format!("<li>常用应用: {}</li>\n", apps.join(", ")));
+        }
+        body.push_str("</ul>\n");
+
+        if !report.timeline.is_empty() {
+            body.push_str("<h3>时间线</h3>\n<ul>\n");
+            for line in &report.timeline {
+                body.push_str(&format!("<li>{}</li>\n", escape_html(line)));
+            }
+            body.push_str("</ul>\n");
+        }
+
+        if !report.issues.is_empty() {
+            body.push_str("<h3>问题与提醒</h3>\n");
+            for issue in &report.issues {
+                body.push_str("<div class=\"issue\">\n");
+                body.push_str(&format!(
+                    "<p><strong>{}</strong> {}",
+                    escape_html(&issue.time),
+                    escape_html(&issue.title)
+                ));
+                let label = resolution_label(&issue.resolution);
+                if !label.is_empty() {
+                    body.push_str(&format!(" <span class=\"tag {}\">{}</span>", issue.resolution, label));
+                }
+                body.push_str("</p>\n");
+                if !issue.suggestion.is_empty() {
+                    body.push_str(&format!("<p class=\"suggestion\">建议: {}</p>\n", escape_html(&issue.suggestion)));
+                }
+                if let Some(image) = &issue.image {
+                    body.push_str(&format!(
+                        "<img src=\"data:image/jpeg;base64,{}\" alt=\"{}\">\n",
+                        BASE64.encode(&image.bytes),
+                        escape_html(&issue.time)
+                    ));
+                }
+                body.push_str("</div>\n");
+            }
+        }
+    }
+
+    if !transcript.is_empty() {
+        body.push_str("<h2>对话记录</h2>\n");
+        for (role, content) in transcript {
+            body.push_str(&format!(
+                "<div class=\"message {}\"><div class=\"role\">{}</div><pre>{}</pre></div>\n",
+                if role == "user" { "user" } else { "assistant" },
+                role_label(role),
+                escape_html(content.trim())
+            ));
+        }
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", "Microsoft YaHei", sans-serif; max-width: 960px; margin: 32px auto; padding: 0 16px; color: #222; line-height: 1.6; }}
+h1 {{ border-bottom: 2px solid #eee; padding-bottom: 8px; }}
+h2 {{ margin-top: 32px; border-bottom: 1px solid #eee; }}
+.issue {{ border-left: 3px solid #e67e22; padding: 4px 12px; margin: 12px 0; }}
+.suggestion {{ color: #555; }}
+.tag {{ font-size: 12px; padding: 1px 6px; border-radius: 4px; background: #eee; }}
+.tag.resolved {{ background: #d4edda; }}
+.tag.unresolved {{ background: #f8d7da; }}
+img {{ max-width: 100%; border: 1px solid #ddd; margin: 8px 0; }}
+.message {{ margin: 12px 0; }}
+.message .role {{ font-weight: bold; }}
+.message pre {{ white-space: pre-wrap; word-break: break-word; font-family: inherit; margin: 4px 0; }}
+.message.user pre {{ background: #f4f6fa; padding: 8px; border-radius: 6px; }}
+</style>
+</head>
+<body>
+{}
+</body>
+</html>
+"#,
+        escape_html(title),
+        body
+    )
+}
+
+fn role_label(role: &str) -> &'static str {
+    match role {
+        "user" => "用户",
+        "assistant" => "助手",
+        "system" => "系统",
+        _ => "其他",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 使用本机 Chrome/Edge/Chromium 的无头模式把 HTML 打印为 PDF
+fn print_html_to_pdf(html_path: &Path, output: &Path) -> Result<(), String> {
+    let browser = find_headless_browser()
+        .ok_or_else(|| "未找到可用于导出 PDF 的浏览器（Chrome / Edge / Chromium）".to_string())?;
+    let url = format!("file:///{}", html_path.to_string_lossy().replace('\\', "/").trim_start_matches('/'));
+    let status = Command::new(&browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-pdf-header-footer")
+        .arg(format!("--print-to-pdf={}", output.to_string_lossy()))
+        .arg(url)
+        .status()
+        .map_err(|e| format!("启动浏览器失败: {}", e))?;
+    if !status.success() || !output.exists() {
+        return Err("PDF 生成失败".to_string());
+    }
+    Ok(())
+}
+
+fn find_headless_browser() -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if cfg!(target_os = "windows") {
+        let mut list = Vec::new();
+        for base in ["ProgramFiles", "ProgramFiles(x86)", "LocalAppData"] {
+            if let Ok(dir) = std::env::var(base) {
+                let dir = PathBuf::from(dir);
+                list.push(dir.join("Microsoft\\Edge\\Application\\msedge.exe"));
+                list.push(dir.join("Google\\Chrome\\Application\\chrome.exe"));
+            }
+        }
+        list
+    } else if cfg!(target_os = "macos") {
+        vec![
+            PathBuf::from("/Applications/Google Chrome.app/Contents/MacOS/Google Chrome"),
+            PathBuf::from("/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge"),
+            PathBuf::from("/Applications/Chromium.app/Contents/MacOS/Chromium"),
+        ]
+    } else {
+        ["google-chrome", "google-chrome-stable", "chromium", "chromium-browser", "microsoft-edge"]
+            .iter()
+            .filter_map(|name| {
+                std::env::var_os("PATH").and_then(|paths| {
+                    std::env::split_paths(&paths)
+                        .map(|dir| dir.join(name))
+                        .find(|path| path.is_file())
+                })
+            })
+            .collect()
+    };
+    candidates.into_iter().find(|path| path.is_file())
+}
@@ -0,0 +1,253 @@
+use crate::storage::{Conversation, ConversationSummary, StorageManager};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const CONVERSATION_ENTRY: &str = "conversation.json";
+const FILES_PREFIX: &str = "files/";
+const MAX_BUNDLE_FILE_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_BUNDLE_TOTAL_BYTES: u64 = 100 * 1024 * 1024;
+// 工具参数中表示文件路径的字段
+const PATH_ARGUMENT_KEYS: &[&str] = &["path", "file_path", "filePath", "file", "target"];
+
+/// 会话迁移包中引用的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFileEntry {
+    pub path: String,      // 原机器上的路径
+    pub source: String,    // attachment | tool
+    pub size: u64,
+    #[serde(default)]
+    pub archive_path: String,  // 包内路径，空表示未打包（过大或读取失败）
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub version: u32,
+    pub exported_at: String,
+    pub app_version: String,
+    pub conversation_id: String,
+    pub title: String,
+    pub message_count: usize,
+    pub files: Vec<SessionFileEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionImportResult {
+    pub conversation: ConversationSummary,
+    pub files_dir: String,
+    pub restored_files: usize,
+    pub missing_files: Vec<String>,  // 原包中未打包的文件，需要手动复制
+}
+
+/// 收集会话引用的文件：附件和工具调用参数中的路径（仅限存在的文件）
+fn referenced_files(conversation: &Conversation) -> Vec<(String, &'static str)> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for message in &conversation.messages {
+        for attachment in &message.attachments {
+            if seen.insert(attachment.path.clone()) {
+                files.push((attachment.path.clone(), "attachment"));
+            }
+        }
+        for context in &message.tool_context {
+            let calls = context["tool_calls"].as_array().cloned().unwrap_or_default();
+            for call in calls {
+                let args = call["arguments"]
+                    .as_str()
+                    .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+                    .unwrap_or(Value::Null);
+                for key in PATH_ARGUMENT_KEYS {
+                    let path = args[*key].as_str().unwrap_or("").trim();
+                    if !path.is_empty() && Path::new(path).is_file() && seen.insert(path.to_string()) {
+                        files.push((path.to_string(), "tool"));
+                    }
+                }
+            }
+        }
+    }
+    files
+}
+
+fn archive_name(index: usize, path: &str) -> String {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    format!("{}{:03}-{}", FILES_PREFIX, index + 1, name)
+}
+
+/// 导出会话迁移包（zip）：会话与工具上下文、引用文件清单及文件副本
+pub fn export_session(
+    storage: &StorageManager,
+    conversation_id: &str,
+    output_path: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let conversation = storage.load_conversation(conversation_id)?;
+    let output = match output_path {
+        Some(path) => path,
+        None => {
+            let dir = storage.get_data_dir().join("exports");
+            fs::create_dir_all(&dir).map_err(|e| format!("创建导出目录失败: {}", e))?;
+            dir.join(format!(
+                "session-{}-{}.zip",
+                conversation.id,
+                Local::now().format("%Y%m%d%H%M%S")
+            ))
+        }
+    };
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建导出目录失败: {}", e))?;
+    }
+
+    let file = File::create(&output).map_err(|e| format!("创建迁移包失败: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let data_dir = storage.get_data_dir();
+    let mut entries = Vec::new();
+    let mut total = 0u64;
+    for (index, (path, source)) in referenced_files(&conversation).into_iter().enumerate() {
+        let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        let mut entry = SessionFileEntry {
+            path: path.clone(),
+            source: source.to_string(),
+            size,
+            archive_path: String::new(),
+            note: String::new(),
+        };
+        if size > MAX_BUNDLE_FILE_BYTES {
+            entry.note = "文件过大，未打包".to_string();
+        } else if total + size > MAX_BUNDLE_TOTAL_BYTES {
+            entry.note = "超出迁移包大小上限，未打包".to_string();
+        } else {
+            // 数据目录内的文件可能已加密，打包明文，导入到其他设备后才能读取
+            let bytes = if Path::new(&path).starts_with(&data_dir) {
+                storage.read_data_file(Path::new(&path))
+            } else {
+                fs::read(&path).map_err(|e| e.to_string())
+            };
+            match bytes {
+                Ok(bytes) => {
+                    let name = archive_name(index, &path);
+                    zip.start_file(name.as_str(), options)
+                        .map_err(|e| format!("写入迁移包失败: {}", e))?;
+                    zip.write_all(&bytes)
+                        .map_err(|e| format!("写入迁移包失败: {}", e))?;
+                    total += size;
+                    entry.archive_path = name;
+                }
+                Err(err) => entry.note = format!("读取失败: {}", err),
+            }
+        }
+        entries.push(entry);
+    }
+
+    let manifest = SessionManifest {
+        version: BUNDLE_VERSION,
+        exported_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        conversation_id: conversation.id.clone(),
+        title: conversation.title.clone(),
+        message_count: conversation.messages.len(),
+        files: entries,
+    };
+    let conversation_json = serde_json::to_string_pretty(&conversation)
+        .map_err(|e| format!("序列化会话失败: {}", e))?;
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("序列化清单失败: {}", e))?;
+    for (name, content) in [(CONVERSATION_ENTRY, conversation_json), (MANIFEST_ENTRY, manifest_json)] {
+        zip.start_file(name, options)
+            .map_err(|e| format!("写入迁移包失败: {}", e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| format!("写入迁移包失败: {}", e))?;
+    }
+    zip.finish().map_err(|e| format!("写入迁移包失败: {}", e))?;
+    Ok(output)
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("迁移包缺少 {}", name))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("读取迁移包失败: {}", e))?;
+    Ok(bytes)
+}
+
+/// 导入会话迁移包：文件解压到数据目录 imported/<会话 ID>，附件路径改写为新位置；
+/// 本机已有同 ID 会话时以新 ID 导入
+pub fn import_session(storage: &StorageManager, bundle_path: &Path) -> Result<SessionImportResult, String> {
+    let file = File::open(bundle_path).map_err(|e| format!("打开迁移包失败: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("迁移包格式无效: {}", e))?;
+    let manifest: SessionManifest = serde_json::from_slice(&read_entry(&mut archive, MANIFEST_ENTRY)?)
+        .map_err(|e| format!("解析清单失败: {}", e))?;
+    if manifest.version > BUNDLE_VERSION {
+        return Err(format!(
+            "迁移包版本 {} 高于当前支持的版本 {}，请先升级 OpenCowork",
+            manifest.version, BUNDLE_VERSION
+        ));
+    }
+    let mut conversation: Conversation =
+        serde_json::from_slice(&read_entry(&mut archive, CONVERSATION_ENTRY)?)
+            .map_err(|e| format!("解析会话失败: {}", e))?;
+    if storage.load_conversation(&conversation.id).is_ok() {
+        conversation.id = format!("{}-imported-{}", conversation.id, Local::now().format("%H%M%S"));
+    }
+
+    let files_dir = storage.get_data_dir().join("imported").join(&conversation.id);
+    let mut restored = Vec::new();
+    let mut missing_files = Vec::new();
+    for entry in &manifest.files {
+        if entry.archive_path.is_empty() {
+            missing_files.push(entry.path.clone());
+            continue;
+        }
+        // 只接受 files/ 下的普通文件名，防止路径穿越
+        let relative = match entry.archive_path.strip_prefix(FILES_PREFIX) {
+            Some(name) if !name.is_empty() && Path::new(name).file_name() == Some(OsStr::new(name)) => name,
+            _ => {
+                missing_files.push(entry.path.clone());
+                continue;
+            }
+        };
+        let bytes = match read_entry(&mut archive, &entry.archive_path) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                missing_files.push(entry.path.clone());
+                continue;
+            }
+        };
+        fs::create_dir_all(&files_dir).map_err(|e| format!("创建导入目录失败: {}", e))?;
+        let target = files_dir.join(relative);
+        fs::write(&target, bytes).map_err(|e| format!("写入导入文件失败: {}", e))?;
+        restored.push((entry.path.clone(), target.to_string_lossy().to_string()));
+    }
+
+    for message in &mut conversation.messages {
+        for attachment in &mut message.attachments {
+            if let Some((_, target)) = restored.iter().find(|(path, _)| *path == attachment.path) {
+                attachment.path = target.clone();
+            }
+        }
+    }
+    let summary = storage.save_conversation(conversation)?;
+    Ok(SessionImportResult {
+        conversation: summary,
+        files_dir: files_dir.to_string_lossy().to_string(),
+        restored_files: restored.len(),
+        missing_files,
+    })
+}
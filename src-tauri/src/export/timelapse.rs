@@ -0,0 +1,222 @@
+use crate::storage::{StorageManager, SummaryRecord};
+use chrono::{Local, NaiveDateTime};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const DEFAULT_TIMELAPSE_SPEED: f32 = 60.0;
+const MIN_FRAME_SECONDS: f64 = 1.0 / 30.0;
+const MAX_FRAME_SECONDS: f64 = 3.0;
+const LAST_FRAME_SECONDS: f64 = 1.0;
+const VIDEO_WIDTH: u32 = 1280;
+const VIDEO_HEIGHT: u32 = 720;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelapseFormat {
+    Mp4,
+    Webm,
+}
+
+impl TimelapseFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "mp4" => Ok(TimelapseFormat::Mp4),
+            "webm" => Ok(TimelapseFormat::Webm),
+            other => Err(format!("不支持的视频格式: {}", other)),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            TimelapseFormat::Mp4 => "mp4",
+            TimelapseFormat::Webm => "webm",
+        }
+    }
+
+    fn codec_args(&self) -> &'static [&'static str] {
+        match self {
+            TimelapseFormat::Mp4 => &["-c:v", "libx264", "-preset", "veryfast", "-crf", "26", "-movflags", "+faststart"],
+            TimelapseFormat::Webm => &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "36", "-row-mt", "1"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelapseResult {
+    pub path: String,
+    pub frames: usize,
+    pub duration_seconds: f64,
+}
+
+/// 把某天保存的截图按时间顺序合成为延时视频，字幕显示截屏时间和应用。
+/// speed 为倍速（60 表示现实 1 分钟播放 1 秒），相邻截图的间隔按倍速换算为帧时长
+pub fn generate_timelapse(
+    storage: &StorageManager,
+    date: &str,
+    speed: f32,
+    format: TimelapseFormat,
+    output_path: Option<PathBuf>,
+) -> Result<TimelapseResult, String> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err("倍速必须大于 0".to_string());
+    }
+    let ffmpeg = find_ffmpeg().ok_or_else(|| "未找到 ffmpeg，请先安装或放在应用目录中".to_string())?;
+    let screenshots_dir = storage.screenshots_dir()?;
+    let records: Vec<SummaryRecord> = storage
+        .get_summaries(date)?
+        .into_iter()
+        .filter(|record| record.action != "private" && !record.detail_ref.is_empty())
+        .filter(|record| screenshots_dir.join(&record.detail_ref).is_file())
+        .collect();
+    if records.is_empty() {
+        return Err(format!("{} 没有可用的截图", date));
+    }
+
+    let output = match output_path {
+        Some(path) => path,
+        None => {
+            let dir = storage.get_data_dir().join("exports");
+            fs::create_dir_all(&dir).map_err(|e| format!("创建导出目录失败: {}", e))?;
+            dir.join(format!(
+                "timelapse-{}-{}.{}",
+                date,
+                Local::now().format("%Y%m%d%H%M%S"),
+                format.extension()
+            ))
+        }
+    };
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建导出目录失败: {}", e))?;
+    }
+
+    // 截图可能已加密，先解密到临时目录，生成后立即删除
+    let work_dir = std::env::temp_dir().join(format!(
+        "opencowork-timelapse-{}",
+        Local::now().timestamp_millis()
+    ));
+    fs::create_dir_all(&work_dir).map_err(|e| format!("创建临时目录失败: {}", e))?;
+    let result = render_timelapse(storage, &ffmpeg, &screenshots_dir, &records, speed, format, &work_dir, &output);
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn render_timelapse(
+    storage: &StorageManager,
+    ffmpeg: &Path,
+    screenshots_dir: &Path,
+    records: &[SummaryRecord],
+    speed: f32,
+    format: TimelapseFormat,
+    work_dir: &Path,
+    output: &Path,
+) -> Result<TimelapseResult, String> {
+    let mut frames = Vec::new();
+    for record in records {
+        let Ok(bytes) = storage.read_data_file(&screenshots_dir.join(&record.detail_ref)) else {
+            continue;
+        };
+        let file_name = format!("frame_{:05}.jpg", frames.len() + 1);
+        fs::write(work_dir.join(&file_name), bytes).map_err(|e| format!("写入临时文件失败: {}", e))?;
+        frames.push((file_name, record));
+    }
+    if frames.is_empty() {
+        return Err("截图均无法读取".to_string());
+    }
+
+    // concat 列表控制每帧时长，SRT 字幕叠加时间和应用名
+    let mut playlist = String::from("ffconcat version 1.0\n");
+    let mut subtitles = String::new();
+    let mut elapsed = 0.0f64;
+    for (index, (file_name, record)) in frames.iter().enumerate() {
+        let seconds = match frames.get(index + 1) {
+            Some((_, next)) => match (parse_timestamp(&record.timestamp), parse_timestamp(&next.timestamp)) {
+                (Some(current), Some(next)) => {
+                    let gap = (next - current).num_milliseconds().max(0) as f64 / 1000.0;
+                    (gap / speed as f64).clamp(MIN_FRAME_SECONDS, MAX_FRAME_SECONDS)
+                }
+                _ => MIN_FRAME_SECONDS,
+            },
+            None => LAST_FRAME_SECONDS,
+        };
+        let _ = writeln!(playlist, "file '{}'\nduration {:.3}", file_name, seconds);
+        let label = if record.app.trim().is_empty() {
+            record.timestamp.get(11..19).unwrap_or("").to_string()
+        } else {
+            format!("{}  {}", record.timestamp.get(11..19).unwrap_or(""), record.app.trim())
+        };
+        let _ = writeln!(
+            subtitles,
+            "{}\n{} --> {}\n{}\n",
+            index + 1,
+            srt_time(elapsed),
+            srt_time(elapsed + seconds),
+            label
+        );
+        elapsed += seconds;
+    }
+    // concat 分离器会忽略最后一项的 duration，需要重复最后一帧
+    if let Some((file_name, _)) = frames.last() {
+        let _ = writeln!(playlist, "file '{}'", file_name);
+    }
+    fs::write(work_dir.join("frames.txt"), playlist).map_err(|e| format!("写入临时文件失败: {}", e))?;
+    fs::write(work_dir.join("labels.srt"), subtitles).map_err(|e| format!("写入临时文件失败: {}", e))?;
+
+    // 在临时目录中运行，滤镜里使用相对路径，避免 Windows 盘符冒号需要转义
+    let filter = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,subtitles=labels.srt:force_style='FontSize=18,Alignment=7',format=yuv420p",
+        w = VIDEO_WIDTH,
+        h = VIDEO_HEIGHT
+    );
+    let result = Command::new(ffmpeg)
+        .current_dir(work_dir)
+        .args(["-y", "-hide_banner", "-loglevel", "error"])
+        .args(["-f", "concat", "-safe", "0", "-i", "frames.txt"])
+        .args(["-vf", &filter, "-r", "30"])
+        .args(format.codec_args())
+        .arg(output)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("启动 ffmpeg 失败: {}", e))?;
+    if !result.status.success() || !output.exists() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("生成视频失败: {}", stderr.trim()));
+    }
+
+    Ok(TimelapseResult {
+        path: output.to_string_lossy().to_string(),
+        frames: frames.len(),
+        duration_seconds: elapsed,
+    })
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(timestamp.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+fn srt_time(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// 优先使用随应用分发在可执行文件旁的 ffmpeg，其次从 PATH 中查找
+fn find_ffmpeg() -> Option<PathBuf> {
+    let name = if cfg!(target_os = "windows") { "ffmpeg.exe" } else { "ffmpeg" };
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)));
+    bundled.filter(|path| path.is_file()).or_else(|| {
+        std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(name))
+                .find(|path| path.is_file())
+        })
+    })
+}
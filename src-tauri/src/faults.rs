@@ -0,0 +1,124 @@
+use crate::error::AppError;
+use crate::storage::FaultConfig;
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 集成测试可通过环境变量传入 JSON 格式的 FaultConfig，优先于配置文件
+const FAULTS_ENV: &str = "OPENCOWORK_FAULTS";
+const DEFAULT_MODEL_ERROR_STATUSES: &[u16] = &[429, 500];
+
+fn fault_state() -> &'static RwLock<FaultConfig> {
+    static STATE: OnceLock<RwLock<FaultConfig>> = OnceLock::new();
+    STATE.get_or_init(|| RwLock::new(FaultConfig::default()))
+}
+
+fn env_override() -> Option<FaultConfig> {
+    static OVERRIDE: OnceLock<Option<FaultConfig>> = OnceLock::new();
+    OVERRIDE
+        .get_or_init(|| {
+            let value = std::env::var(FAULTS_ENV).ok()?;
+            match serde_json::from_str::<FaultConfig>(&value) {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    crate::logs::warn("faults", format!("{} 解析失败: {}", FAULTS_ENV, err));
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+/// 启动和保存配置时调用；release 构建中故障注入始终关闭
+pub fn apply_fault_config(config: &FaultConfig) {
+    let config = env_override().unwrap_or_else(|| config.clone());
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    if config.enabled {
+        crate::logs::warn("faults", format!("故障注入已开启: {:?}", config));
+    }
+    *fault_state().write() = config;
+}
+
+fn active_config() -> Option<FaultConfig> {
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+    let config = fault_state().read();
+    config.enabled.then(|| config.clone())
+}
+
+// 不需要高质量随机数：时间戳打底的 xorshift，保证同一时刻的多次调用结果不同
+fn next_random() -> u64 {
+    static SEED: AtomicU64 = AtomicU64::new(0);
+    let mut x = SEED.load(Ordering::Relaxed);
+    if x == 0 {
+        x = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15)
+            | 1;
+    }
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    SEED.store(x, Ordering::Relaxed);
+    x
+}
+
+fn roll(rate: f32) -> bool {
+    let rate = rate.clamp(0.0, 1.0) as f64;
+    rate > 0.0 && (next_random() % 10_000) as f64 / 10_000.0 < rate
+}
+
+/// 模型请求前调用：按配置等待、返回与真实服务端相同类型的错误
+pub async fn inject_model_fault() -> Result<(), AppError> {
+    let Some(config) = active_config() else {
+        return Ok(());
+    };
+    if config.model_delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(config.model_delay_ms)).await;
+    }
+    if roll(config.context_overflow_rate) {
+        crate::logs::info("faults", "注入上下文超长错误");
+        return Err(AppError::provider(
+            Some(400),
+            "API 错误 400 Bad Request: [fault] context_length_exceeded".to_string(),
+            None,
+        ));
+    }
+    if roll(config.model_error_rate) {
+        let statuses = if config.model_error_statuses.is_empty() {
+            DEFAULT_MODEL_ERROR_STATUSES
+        } else {
+            config.model_error_statuses.as_slice()
+        };
+        let status = statuses[(next_random() % statuses.len() as u64) as usize];
+        crate::logs::info("faults", format!("注入模型错误 {}", status));
+        let retry_after = if status == 429 { config.retry_after_secs } else { None };
+        return Err(AppError::provider(
+            Some(status),
+            format!("API 错误 {}: [fault] 模拟的服务端错误", status),
+            retry_after,
+        ));
+    }
+    Ok(())
+}
+
+/// 工具执行前调用：命中时返回 IO 错误
+pub fn inject_tool_fault(tool_name: &str) -> Result<(), AppError> {
+    let Some(config) = active_config() else {
+        return Ok(());
+    };
+    let targeted = config.tools.is_empty()
+        || config.tools.iter().any(|tool| tool.eq_ignore_ascii_case(tool_name));
+    if targeted && roll(config.tool_io_error_rate) {
+        crate::logs::info("faults", format!("注入工具 IO 错误: {}", tool_name));
+        return Err(AppError::Io {
+            message: format!("[fault] 模拟的 IO 错误: {}", tool_name),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use crate::commands::{invoke_skill_internal, AppState, AttachmentInput};
+use crate::logs;
+use crate::storage::{storage_actor, StorageManager, StoragePriority, WatchFolder, WatchFolderConfig};
+use chrono::Local;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+const HISTORY_FILE: &str = "watch_folder_history.json";
+const TICK_MS: u64 = 500;
+const MIN_DEBOUNCE_MS: u64 = 500;
+const MAX_RUN_OUTPUT_CHARS: usize = 2000;
+// 下载或编辑中的临时文件，写完后会改名为正式文件
+const TEMP_EXTENSIONS: &[&str] = &["tmp", "part", "crdownload", "download", "partial", "swp"];
+
+/// 监视文件夹的一次执行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchRun {
+    pub watch_id: String,
+    pub skill: String,
+    pub file: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub status: String,  // success | error
+    #[serde(default)]
+    pub output: String,
+    #[serde(default)]
+    pub error: String,
+}
+
+struct RunningWatchers {
+    config: WatchFolderConfig,
+    _watchers: Vec<RecommendedWatcher>,
+    cancel: CancellationToken,
+}
+
+fn running_watchers() -> &'static ParkingMutex<Option<RunningWatchers>> {
+    static WATCHERS: OnceLock<ParkingMutex<Option<RunningWatchers>>> = OnceLock::new();
+    WATCHERS.get_or_init(|| ParkingMutex::new(None))
+}
+
+/// 按配置启动或重建文件夹监视；配置未变化时不做任何事
+pub fn apply_watch_folder_config(app: &AppHandle, config: &WatchFolderConfig) {
+    let mut slot = running_watchers().lock();
+    if slot.as_ref().map_or(false, |running| running.config == *config) {
+        return;
+    }
+    if let Some(running) = slot.take() {
+        running.cancel.cancel();
+    }
+
+    let folders: Vec<WatchFolder> = config
+        .items
+        .iter()
+        .filter(|folder| folder.enabled && !folder.skill.trim().is_empty())
+        .cloned()
+        .collect();
+    if folders.is_empty() {
+        return;
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watchers = Vec::new();
+    for (idx, folder) in folders.iter().enumerate() {
+        match watch_folder(folder, idx, tx.clone()) {
+            Ok(watcher) => watchers.push(watcher),
+            Err(err) => logs::warn("watch", format!("{}: {}", folder.path, err)),
+        }
+    }
+    let cancel = CancellationToken::new();
+    tauri::async_runtime::spawn(debounce_loop(
+        app.clone(),
+        folders,
+        config.debounce_ms.max(MIN_DEBOUNCE_MS),
+        config.history_limit,
+        rx,
+        cancel.clone(),
+    ));
+    *slot = Some(RunningWatchers {
+        config: config.clone(),
+        _watchers: watchers,
+        cancel,
+    });
+}
+
+pub fn validate_watch_folder(folder: &WatchFolder) -> Result<(), String> {
+    if folder.path.trim().is_empty() {
+        return Err("监视目录不能为空".to_string());
+    }
+    if folder.skill.trim().is_empty() {
+        return Err(format!("监视目录 {} 未选择技能", folder.path.trim()));
+    }
+    if folder.enabled && !expand_path(&folder.path).is_dir() {
+        return Err(format!("监视目录不存在: {}", folder.path.trim()));
+    }
+    Ok(())
+}
+
+fn expand_path(path: &str) -> PathBuf {
+    let path = path.trim();
+    if let Some(rest) = path.strip_prefix("~") {
+        if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') {
+            if let Some(home) = dirs::home_dir() {
+                return home.join(rest.trim_start_matches(&['/', '\\'][..]));
+            }
+        }
+    }
+    PathBuf::from(path)
+}
+
+fn watch_folder(
+    folder: &WatchFolder,
+    idx: usize,
+    tx: mpsc::UnboundedSender<(usize, PathBuf)>,
+) -> Result<RecommendedWatcher, String> {
+    let dir = expand_path(&folder.path);
+    if !dir.is_dir() {
+        return Err("目录不存在".to_string());
+    }
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(err) => {
+                logs::warn("watch", format!("监视出错: {}", err));
+                return;
+            }
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        for path in event.paths {
+            let _ = tx.send((idx, path));
+        }
+    })
+    .map_err(|e| format!("创建文件夹监视失败: {}", e))?;
+    let mode = if folder.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(&dir, mode)
+        .map_err(|e| format!("监视目录失败: {}", e))?;
+    Ok(watcher)
+}
+
+fn matches_folder(folder: &WatchFolder, path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if name.starts_with('.') || name.starts_with("~$") {
+        return false;
+    }
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if TEMP_EXTENSIONS.contains(&ext.as_str()) {
+        return false;
+    }
+    folder.extensions.is_empty()
+        || folder
+            .extensions
+            .iter()
+            .any(|item| item.trim().trim_start_matches('.').eq_ignore_ascii_case(&ext))
+}
+
+/// 同一文件的事件合并：停止变化 debounce_ms 后才触发一次
+async fn debounce_loop(
+    app: AppHandle,
+    folders: Vec<WatchFolder>,
+    debounce_ms: u64,
+    history_limit: usize,
+    mut rx: mpsc::UnboundedReceiver<(usize, PathBuf)>,
+    cancel: CancellationToken,
+) {
+    let debounce = Duration::from_millis(debounce_ms);
+    let mut pending: HashMap<(usize, PathBuf), Instant> = HashMap::new();
+    // 已处理文件的修改时间，文件未再变化时不重复触发
+    let mut handled: HashMap<PathBuf, SystemTime> = HashMap::new();
+    let mut ticker = tokio::time::interval(Duration::from_millis(TICK_MS));
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            event = rx.recv() => match event {
+                Some(key) => {
+                    pending.insert(key, Instant::now());
+                }
+                None => break,
+            },
+            _ = ticker.tick() => {
+                let now = Instant::now();
+                let ready: Vec<(usize, PathBuf)> = pending
+                    .iter()
+                    .filter(|(_, at)| now.duration_since(**at) >= debounce)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in ready {
+                    pending.remove(&key);
+                    if cancel.is_cancelled() {
+                        return;
+                    }
+                    let (idx, path) = key;
+                    let folder = &folders[idx];
+                    if !matches_folder(folder, &path) {
+                        continue;
+                    }
+                    let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+                    if let Some(modified) = modified {
+                        if handled.get(&path) == Some(&modified) {
+                            continue;
+                        }
+                        handled.insert(path.clone(), modified);
+                    }
+                    run_watch(&app, folder, &path, history_limit).await;
+                }
+            }
+        }
+    }
+}
+
+async fn run_watch(app: &AppHandle, folder: &WatchFolder, path: &Path, history_limit: usize) {
+    let file = path.to_string_lossy().to_string();
+    let started = Local::now();
+    let args = if folder.args.trim().is_empty() {
+        file.clone()
+    } else {
+        folder.args.replace("{file}", &file)
+    };
+    let attachment = AttachmentInput {
+        path: file.clone(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        kind: None,
+    };
+    let request_id = format!("watch-{}-{}", folder.id, started.timestamp_millis());
+    let result = invoke_skill_internal(
+        folder.skill.trim().to_string(),
+        Some(args),
+        None,
+        Some(vec![attachment]),
+        Some(request_id),
+        None,
+        app.clone(),
+        app.state::<AppState>(),
+    )
+    .await;
+
+    let (status, output, error) = match result {
+        Ok(output) => ("success", output.chars().take(MAX_RUN_OUTPUT_CHARS).collect(), String::new()),
+        Err(err) => ("error", String::new(), err.to_string()),
+    };
+    let run = WatchRun {
+        watch_id: folder.id.clone(),
+        skill: folder.skill.trim().to_string(),
+        file,
+        started_at: started.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        finished_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        status: status.to_string(),
+        output,
+        error,
+    };
+    if let Err(err) = app.emit("watch-folder-run", &run) {
+        logs::warn("watch", format!("发送执行结果失败: {}", err));
+    }
+    let saved = storage_actor()
+        .run(StoragePriority::Background, move |storage| {
+            storage.append_watch_run(run, history_limit)
+        })
+        .await;
+    if let Err(err) = saved {
+        logs::warn("watch", format!("保存执行记录失败: {}", err));
+    }
+}
+
+impl StorageManager {
+    /// 执行记录（最新在前），watch_id 为空时返回全部
+    pub fn load_watch_runs(&self, watch_id: Option<&str>) -> Result<Vec<WatchRun>, String> {
+        let path = self.get_data_dir().join(HISTORY_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        let runs: Vec<WatchRun> = serde_json::from_str(&content).unwrap_or_default();
+        Ok(runs
+            .into_iter()
+            .rev()
+            .filter(|run| watch_id.map_or(true, |id| run.watch_id == id))
+            .collect())
+    }
+
+    pub fn append_watch_run(&self, run: WatchRun, history_limit: usize) -> Result<(), String> {
+        let path = self.get_data_dir().join(HISTORY_FILE);
+        let mut runs: Vec<WatchRun> = if path.exists() {
+            serde_json::from_str(&self.read_data_string(&path)?).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let watch_id = run.watch_id.clone();
+        runs.push(run);
+        // 每个监视只保留最近 history_limit 条
+        let count = runs.iter().filter(|item| item.watch_id == watch_id).count();
+        let mut excess = count.saturating_sub(history_limit.max(1));
+        runs.retain(|item| {
+            if excess > 0 && item.watch_id == watch_id {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        let content = serde_json::to_string_pretty(&runs)
+            .map_err(|e| format!("序列化执行记录失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+}
@@ -0,0 +1,109 @@
+use crate::commands::{capture_now, open_quick_ask, toggle_capture, AppState};
+use crate::storage::HotkeyConfig;
+use parking_lot::Mutex as ParkingMutex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HotkeyAction {
+    CaptureNow,
+    ToggleCapture,
+    QuickAsk,
+    PushToTalk,
+}
+
+/// 当前注册的快捷键与动作的对应关系
+fn bindings() -> &'static ParkingMutex<HashMap<Shortcut, HotkeyAction>> {
+    static HOTKEY_BINDINGS: OnceLock<ParkingMutex<HashMap<Shortcut, HotkeyAction>>> = OnceLock::new();
+    HOTKEY_BINDINGS.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+pub fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(handle_shortcut)
+        .build()
+}
+
+/// 按配置重新注册全局快捷键；单个快捷键无效或被占用时跳过并返回错误列表
+pub fn register_hotkeys(app: &AppHandle, config: &HotkeyConfig) -> Vec<String> {
+    let manager = app.global_shortcut();
+    let mut map = bindings().lock();
+    for shortcut in map.keys() {
+        let _ = manager.unregister(*shortcut);
+    }
+    map.clear();
+
+    if !config.enabled {
+        return Vec::new();
+    }
+
+    let mut errors = Vec::new();
+    let entries = [
+        (config.capture_now.as_str(), HotkeyAction::CaptureNow),
+        (config.toggle_capture.as_str(), HotkeyAction::ToggleCapture),
+        (config.quick_ask.as_str(), HotkeyAction::QuickAsk),
+        (config.push_to_talk.as_str(), HotkeyAction::PushToTalk),
+    ];
+    for (accelerator, action) in entries {
+        let accelerator = accelerator.trim();
+        if accelerator.is_empty() {
+            continue;
+        }
+        let shortcut: Shortcut = match accelerator.parse() {
+            Ok(shortcut) => shortcut,
+            Err(err) => {
+                errors.push(format!("无效的快捷键 {}: {}", accelerator, err));
+                continue;
+            }
+        };
+        if map.contains_key(&shortcut) {
+            errors.push(format!("快捷键重复: {}", accelerator));
+            continue;
+        }
+        match manager.register(shortcut) {
+            Ok(()) => {
+                map.insert(shortcut, action);
+            }
+            Err(err) => errors.push(format!("注册快捷键 {} 失败: {}", accelerator, err)),
+        }
+    }
+    errors
+}
+
+fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    let Some(action) = bindings().lock().get(shortcut).copied() else {
+        return;
+    };
+    // 按键说话：按下时前端开始录音，松开时停止并调用 transcribe_audio
+    if action == HotkeyAction::PushToTalk {
+        let name = match event.state() {
+            ShortcutState::Pressed => "voice-input-start",
+            ShortcutState::Released => "voice-input-stop",
+        };
+        let _ = app.emit(name, ());
+        return;
+    }
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match action {
+            HotkeyAction::CaptureNow => capture_now(app.state::<AppState>(), app.clone())
+                .await
+                .map(|_| ()),
+            HotkeyAction::ToggleCapture => toggle_capture(app.state::<AppState>(), app.clone())
+                .await
+                .map(|_| ()),
+            HotkeyAction::QuickAsk => open_quick_ask(app.clone()).await,
+            HotkeyAction::PushToTalk => Ok(()),
+        };
+        if let Err(err) = result {
+            eprintln!("快捷键操作失败: {}", err);
+            let _ = app.emit("hotkey-error", err);
+        }
+    });
+}
@@ -1,50 +1,170 @@
 mod analysis;
 mod assistant;
 mod capture;
+mod clipboard;
 mod commands;
+mod error;
+mod export;
+mod faults;
+mod folder_watch;
+mod hotkeys;
+mod logs;
+mod mcp;
+mod metrics;
 mod model;
+mod presets;
+mod profile_schedule;
+mod server;
 mod skills;
+mod snippets;
 mod storage;
+mod tickets;
+mod tts;
+mod workspaces;
+
+// 仅供 benches/ 基准测试使用，不属于对外接口
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::storage::{ContextStrategy, SearchResult, SummaryRecord};
+}
 
 use crate::skills::start_skills_watcher;
-use crate::storage::StorageManager;
+use crate::storage::{start_storage_janitor, StorageManager};
 use commands::{
+    acknowledge_retention_notice,
+    add_alert_rule,
+    add_workspace,
+    answer_tool_approval,
+    approve_skill_suggestion,
+    browse_skill_registry,
+    cancel_queued_chat,
     cancel_request,
+    capture_now,
+    capture_region_now,
+    change_encryption_passphrase,
     chat_with_assistant,
     clear_all_summaries,
+    clear_clipboard_history,
     clear_summaries,
+    close_browser_session,
+    close_chat_window,
     close_notification,
     create_skill,
+    create_ticket,
+    delete_agent_preset,
+    delete_alert_rule,
+    delete_conversation,
     delete_profile,
     delete_skill,
+    delete_snippet,
+    dismiss_skill_suggestion,
+    end_focus_session,
     ensure_bash_runtime,
+    explain_alert,
+    export_audit_trail,
+    export_session,
+    export_summaries,
     focus_main_window,
+    generate_digest,
+    generate_skill_suggestions,
+    generate_timelapse,
+    get_active_context_packs,
+    get_active_requests,
+    get_activity_heatmap,
+    get_app_usage_stats,
+    get_capabilities,
+    get_clipboard_history,
     get_capture_status,
     get_config,
+    get_digest,
+    get_encryption_status,
+    get_experiment_report,
+    get_focus_session,
+    get_metrics,
     get_recent_alerts,
+    get_retention_notice,
     get_skill,
+    get_skill_suggestions,
+    get_skills_delta,
     get_skills_dir,
+    get_storage_usage,
     get_summaries,
     get_system_locale,
+    get_task_output,
+    get_timeline,
+    get_usage_stats,
+    get_watch_folder_history,
+    import_session,
+    install_registry_skill,
     invoke_skill,
+    kill_background_task,
+    list_alert_rules,
+    list_agent_presets,
+    list_background_tasks,
+    list_browser_sessions,
+    list_chat_windows,
+    list_conversations,
+    list_file_changes,
+    list_focus_sessions,
+    list_mcp_servers,
     list_profiles,
+    list_queued_chats,
     // Skills 相关命令
     list_skills,
+    list_snippets,
+    list_tickets,
+    list_tool_approval_rules,
+    list_workspaces,
+    load_conversation,
     load_profile,
     log_ui_locale,
+    open_alert,
+    open_chat_window,
     open_external_url,
+    open_quick_ask,
     open_release_page,
     open_screenshots_dir,
     open_skills_dir,
+    preview_redaction,
+    rate_record,
     read_image_base64,
+    redact_existing_records,
+    reload_mcp_servers,
+    register_tool_approval_listener,
+    remove_tool_approval_rule,
+    remove_workspace,
+    rename_conversation,
+    reset_metrics,
+    revert_file_change,
+    run_alert_action,
+    run_doctor,
+    save_agent_preset,
     save_clipboard_image,
     save_config,
+    save_conversation,
     save_profile,
+    save_snippet,
+    search_screenshots,
+    set_active_workspace,
+    set_capture_indicator,
     // 通知窗口相关命令
     show_notification,
+    snooze_alert,
+    speak_text,
     start_capture,
+    start_focus_session,
     stop_capture,
+    stop_speaking,
+    stop_ui_automation,
+    subscribe_logs,
+    test_alert_rule,
     test_model_connection,
+    toggle_capture,
+    transcribe_audio,
+    unlink_ticket,
+    unlock_storage,
+    unsubscribe_logs,
+    warm_up_model,
     AppState,
 };
 use std::sync::Arc;
@@ -64,7 +184,9 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(hotkeys::plugin())
         .manage(AppState::new())
         .setup(|app| {
             let state = app.state::<AppState>();
@@ -72,6 +194,34 @@ pub fn run() {
             let on_changed = Arc::new(move || {
                 skills_version.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
             });
+            logs::init(app.handle());
+            start_storage_janitor(app.handle().clone());
+            analysis::start_digest_scheduler(app.handle().clone());
+            commands::start_offline_queue_worker(app.handle().clone());
+            analysis::start_skill_suggestion_scheduler(app.handle().clone());
+            analysis::start_embedding_indexer();
+            profile_schedule::start_profile_scheduler(app.handle().clone());
+            let startup_storage = StorageManager::new();
+            let mut startup_config = startup_storage.load_config().unwrap_or_default();
+            for err in hotkeys::register_hotkeys(&app.handle(), &startup_config.hotkeys) {
+                eprintln!("{}", err);
+            }
+            if startup_config.api_server.enabled && startup_config.api_server.token.trim().is_empty() {
+                startup_config.api_server.token = server::generate_token();
+                if let Err(err) = startup_storage.save_config(&startup_config) {
+                    eprintln!("保存 API token 失败: {}", err);
+                }
+            }
+            server::apply_api_server_config(&app.handle(), &startup_config.api_server);
+            folder_watch::apply_watch_folder_config(&app.handle(), &startup_config.watch_folders);
+            faults::apply_fault_config(&startup_config.faults);
+            metrics::apply_metrics_config(&startup_config.metrics);
+            clipboard::apply_clipboard_config(&startup_config.clipboard);
+            storage::apply_audit_config(&startup_config.audit);
+            capture::apply_capture_indicator_config(&app.handle(), &startup_config.capture.indicator);
+            if let Err(err) = storage::apply_redaction_config(&startup_config.redaction) {
+                eprintln!("脱敏规则无效，已停用自动脱敏: {}", err);
+            }
             match start_skills_watcher(&app.handle(), Some(on_changed)) {
                 Ok(watcher) => {
                     let mut guard = state.skills_watcher.lock().unwrap();
@@ -92,34 +242,134 @@ pub fn run() {
             save_profile,
             load_profile,
             delete_profile,
+            test_alert_rule,
             test_model_connection,
+            warm_up_model,
             start_capture,
             stop_capture,
             get_capture_status,
+            capture_now,
+            capture_region_now,
+            toggle_capture,
             chat_with_assistant,
             cancel_request,
+            get_active_requests,
             get_summaries,
             get_recent_alerts,
+            explain_alert,
+            snooze_alert,
             clear_summaries,
             clear_all_summaries,
+            get_storage_usage,
+            get_retention_notice,
+            acknowledge_retention_notice,
+            get_digest,
+            generate_digest,
+            get_active_context_packs,
+            get_skill_suggestions,
+            generate_skill_suggestions,
+            approve_skill_suggestion,
+            dismiss_skill_suggestion,
+            list_conversations,
+            load_conversation,
+            save_conversation,
+            delete_conversation,
+            rename_conversation,
+            export_summaries,
+            export_session,
+            import_session,
+            get_encryption_status,
+            get_experiment_report,
+            unlock_storage,
+            change_encryption_passphrase,
             open_screenshots_dir,
             open_release_page,
             open_external_url,
             save_clipboard_image,
+            rate_record,
+            create_ticket,
+            list_tickets,
+            unlink_ticket,
+            get_watch_folder_history,
+            save_snippet,
+            list_snippets,
+            delete_snippet,
+            answer_tool_approval,
+            list_tool_approval_rules,
+            register_tool_approval_listener,
+            remove_tool_approval_rule,
+            run_doctor,
+            subscribe_logs,
+            unsubscribe_logs,
+            list_file_changes,
+            revert_file_change,
+            get_metrics,
+            reset_metrics,
+            list_workspaces,
+            add_workspace,
+            remove_workspace,
+            set_active_workspace,
+            get_capabilities,
+            open_chat_window,
+            list_chat_windows,
+            close_chat_window,
+            list_browser_sessions,
+            close_browser_session,
+            list_agent_presets,
+            save_agent_preset,
+            delete_agent_preset,
+            get_clipboard_history,
+            clear_clipboard_history,
+            redact_existing_records,
+            preview_redaction,
+            stop_ui_automation,
+            set_capture_indicator,
+            export_audit_trail,
+            generate_timelapse,
+            get_timeline,
+            get_app_usage_stats,
+            start_focus_session,
+            end_focus_session,
+            get_focus_session,
+            list_focus_sessions,
+            search_screenshots,
+            get_activity_heatmap,
+            transcribe_audio,
+            speak_text,
+            stop_speaking,
+            list_queued_chats,
+            cancel_queued_chat,
             read_image_base64,
             ensure_bash_runtime,
+            // 后台任务相关命令
+            list_background_tasks,
+            get_task_output,
+            get_usage_stats,
+            kill_background_task,
             // Skills 相关命令
             list_skills,
             get_skill,
+            get_skills_delta,
             invoke_skill,
             create_skill,
             delete_skill,
             get_skills_dir,
+            browse_skill_registry,
+            install_registry_skill,
             open_skills_dir,
+            // MCP 相关命令
+            list_mcp_servers,
+            reload_mcp_servers,
             // 通知窗口相关命令
             show_notification,
             close_notification,
             focus_main_window,
+            open_alert,
+            run_alert_action,
+            list_alert_rules,
+            add_alert_rule,
+            delete_alert_rule,
+            open_quick_ask,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,175 @@
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+const LOG_BUFFER_SIZE: usize = 1000;
+const DEFAULT_BACKLOG: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "debug" | "trace" => LogLevel::Debug,
+            "warn" | "warning" => LogLevel::Warn,
+            "error" => LogLevel::Error,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+/// 一条结构化日志
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub seq: u64,
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub module: String,  // capture / tool / watch ...
+    pub message: String,
+}
+
+/// 调试控制台的订阅条件
+#[derive(Debug, Clone)]
+struct LogFilter {
+    level: LogLevel,
+    modules: Vec<String>,  // 为空表示全部模块，按前缀匹配（capture 匹配 capture.ocr）
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        entry.level >= self.level
+            && (self.modules.is_empty()
+                || self.modules.iter().any(|module| entry.module.starts_with(module.as_str())))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogSubscription {
+    pub id: String,
+    pub backlog: Vec<LogEntry>,  // 订阅前已有的、符合条件的日志
+}
+
+#[derive(Serialize)]
+struct LogEvent<'a> {
+    subscription_id: &'a str,
+    entry: &'a LogEntry,
+}
+
+#[derive(Default)]
+struct LogHub {
+    buffer: VecDeque<LogEntry>,
+    subscriptions: HashMap<String, LogFilter>,
+}
+
+fn log_hub() -> &'static ParkingMutex<LogHub> {
+    static HUB: OnceLock<ParkingMutex<LogHub>> = OnceLock::new();
+    HUB.get_or_init(|| ParkingMutex::new(LogHub::default()))
+}
+
+fn app_handle() -> &'static OnceLock<AppHandle> {
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+    &APP
+}
+
+static LOG_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// 启动时调用，之后日志会推送给已订阅的前端
+pub fn init(app: &AppHandle) {
+    let _ = app_handle().set(app.clone());
+}
+
+/// 记录日志：写到 stderr、保留在内存环形缓冲里，并推送给订阅者（log-entry 事件）
+pub fn record(level: LogLevel, module: &str, message: impl Into<String>) {
+    let entry = LogEntry {
+        seq: LOG_SEQ.fetch_add(1, Ordering::Relaxed),
+        timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+        level,
+        module: module.to_string(),
+        message: message.into(),
+    };
+    if level >= LogLevel::Info {
+        eprintln!("[{}] {}", entry.module, entry.message);
+    }
+
+    let targets: Vec<String> = {
+        let mut hub = log_hub().lock();
+        if hub.buffer.len() >= LOG_BUFFER_SIZE {
+            hub.buffer.pop_front();
+        }
+        hub.buffer.push_back(entry.clone());
+        hub.subscriptions
+            .iter()
+            .filter(|(_, filter)| filter.matches(&entry))
+            .map(|(id, _)| id.clone())
+            .collect()
+    };
+    if targets.is_empty() {
+        return;
+    }
+    if let Some(app) = app_handle().get() {
+        for id in &targets {
+            let _ = app.emit("log-entry", LogEvent { subscription_id: id, entry: &entry });
+        }
+    }
+}
+
+pub fn debug(module: &str, message: impl Into<String>) {
+    record(LogLevel::Debug, module, message);
+}
+
+pub fn info(module: &str, message: impl Into<String>) {
+    record(LogLevel::Info, module, message);
+}
+
+pub fn warn(module: &str, message: impl Into<String>) {
+    record(LogLevel::Warn, module, message);
+}
+
+pub fn error(module: &str, message: impl Into<String>) {
+    record(LogLevel::Error, module, message);
+}
+
+/// 新建订阅，返回订阅 ID 和最近的符合条件的日志
+pub fn subscribe(level: LogLevel, modules: Vec<String>, backlog: Option<usize>) -> LogSubscription {
+    let filter = LogFilter {
+        level,
+        modules: modules
+            .into_iter()
+            .map(|module| module.trim().to_string())
+            .filter(|module| !module.is_empty())
+            .collect(),
+    };
+    let id = format!(
+        "logs-{}-{}",
+        Local::now().timestamp_millis(),
+        LOG_SEQ.fetch_add(1, Ordering::Relaxed)
+    );
+    let mut hub = log_hub().lock();
+    let limit = backlog.unwrap_or(DEFAULT_BACKLOG);
+    let mut entries: Vec<LogEntry> = hub
+        .buffer
+        .iter()
+        .rev()
+        .filter(|entry| filter.matches(entry))
+        .take(limit)
+        .cloned()
+        .collect();
+    entries.reverse();
+    hub.subscriptions.insert(id.clone(), filter);
+    LogSubscription { id, backlog: entries }
+}
+
+pub fn unsubscribe(id: &str) -> bool {
+    log_hub().lock().subscriptions.remove(id).is_some()
+}
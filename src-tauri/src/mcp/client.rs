@@ -0,0 +1,416 @@
+use crate::storage::McpServerConfig;
+use parking_lot::Mutex as ParkingMutex;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command as TokioCommand};
+use tokio::sync::{oneshot, Mutex as TokioMutex};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+const SSE_ENDPOINT_WAIT_SECS: u64 = 15;
+
+type PendingMap = Arc<ParkingMutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// MCP 服务器暴露的单个工具
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+enum Transport {
+    Stdio {
+        stdin: TokioMutex<ChildStdin>,
+        _child: TokioMutex<Child>,
+    },
+    Sse {
+        client: Client,
+        endpoint: String,
+        headers: HashMap<String, String>,
+    },
+}
+
+/// 与单个 MCP 服务器的连接（JSON-RPC 2.0）
+pub struct McpConnection {
+    pub config: McpServerConfig,
+    transport: Transport,
+    pending: PendingMap,
+    next_id: AtomicU64,
+    reader: JoinHandle<()>,
+    alive: Arc<AtomicBool>,  // 读取任务退出（进程退出或 SSE 断开）后置为 false
+}
+
+impl Drop for McpConnection {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl McpConnection {
+    pub async fn connect(config: &McpServerConfig) -> Result<Self, String> {
+        let connection = match config.transport.trim().to_lowercase().as_str() {
+            "stdio" | "" => Self::connect_stdio(config)?,
+            "sse" => Self::connect_sse(config).await?,
+            other => return Err(format!("unsupported MCP transport: {}", other)),
+        };
+
+        connection
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "opencowork",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }
+                }),
+            )
+            .await?;
+        connection
+            .notify("notifications/initialized", json!({}))
+            .await?;
+        Ok(connection)
+    }
+
+    fn connect_stdio(config: &McpServerConfig) -> Result<Self, String> {
+        let command = config.command.trim();
+        if command.is_empty() {
+            return Err(format!("MCP server {} is missing command", config.name));
+        }
+
+        let mut cmd = build_server_command(command);
+        cmd.args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("start MCP server {} failed: {}", config.name, e))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| "MCP server stdin unavailable".to_string())?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| "MCP server stdout unavailable".to_string())?;
+
+        let pending: PendingMap = Arc::new(ParkingMutex::new(HashMap::new()));
+        let reader_pending = Arc::clone(&pending);
+        let alive = Arc::new(AtomicBool::new(true));
+        let reader_alive = Arc::clone(&alive);
+        let reader = tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                if let Ok(message) = serde_json::from_str::<Value>(line) {
+                    dispatch_response(&reader_pending, message);
+                }
+            }
+            // 进程退出，标记连接已断开并唤醒所有等待中的请求
+            reader_alive.store(false, Ordering::SeqCst);
+            reader_pending.lock().clear();
+        });
+
+        Ok(Self {
+            config: config.clone(),
+            transport: Transport::Stdio {
+                stdin: TokioMutex::new(stdin),
+                _child: TokioMutex::new(child),
+            },
+            pending,
+            next_id: AtomicU64::new(1),
+            reader,
+            alive,
+        })
+    }
+
+    async fn connect_sse(config: &McpServerConfig) -> Result<Self, String> {
+        let url = config.url.trim();
+        if url.is_empty() {
+            return Err(format!("MCP server {} is missing url", config.name));
+        }
+
+        let client = Client::builder()
+            .build()
+            .map_err(|e| format!("create HTTP client failed: {}", e))?;
+        let mut request = client.get(url).header("Accept", "text/event-stream");
+        for (key, value) in &config.headers {
+            request = request.header(key.as_str(), value.as_str());
+        }
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| format!("connect MCP server {} failed: {}", config.name, e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "connect MCP server {} failed: HTTP {}",
+                config.name,
+                response.status()
+            ));
+        }
+
+        let base_url = reqwest::Url::parse(url).map_err(|e| format!("invalid MCP url: {}", e))?;
+        let pending: PendingMap = Arc::new(ParkingMutex::new(HashMap::new()));
+        let reader_pending = Arc::clone(&pending);
+        let alive = Arc::new(AtomicBool::new(true));
+        let reader_alive = Arc::clone(&alive);
+        let (endpoint_tx, endpoint_rx) = oneshot::channel::<String>();
+
+        let reader = tokio::spawn(async move {
+            let mut endpoint_tx = Some(endpoint_tx);
+            let mut buffer = String::new();
+            while let Ok(Some(chunk)) = response.chunk().await {
+                buffer.push_str(&String::from_utf8_lossy(&chunk).replace("\r\n", "\n"));
+                while let Some(pos) = buffer.find("\n\n") {
+                    let block = buffer[..pos].to_string();
+                    buffer.drain(..pos + 2);
+                    let (event, data) = parse_sse_block(&block);
+                    if event == "endpoint" {
+                        if let Some(tx) = endpoint_tx.take() {
+                            let resolved = base_url
+                                .join(data.trim())
+                                .map(|u| u.to_string())
+                                .unwrap_or_else(|_| data.trim().to_string());
+                            let _ = tx.send(resolved);
+                        }
+                    } else if let Ok(message) = serde_json::from_str::<Value>(&data) {
+                        dispatch_response(&reader_pending, message);
+                    }
+                }
+            }
+            reader_alive.store(false, Ordering::SeqCst);
+            reader_pending.lock().clear();
+        });
+
+        let endpoint = match timeout(Duration::from_secs(SSE_ENDPOINT_WAIT_SECS), endpoint_rx).await {
+            Ok(Ok(endpoint)) => endpoint,
+            _ => {
+                reader.abort();
+                return Err(format!("MCP server {} did not send endpoint event", config.name));
+            }
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            transport: Transport::Sse {
+                client,
+                endpoint,
+                headers: config.headers.clone(),
+            },
+            pending,
+            next_id: AtomicU64::new(1),
+            reader,
+            alive,
+        })
+    }
+
+    /// 读取任务仍在运行，即服务器进程/SSE 连接未断开
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>, String> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+            if let Some(items) = result.get("tools").and_then(|v| v.as_array()) {
+                for item in items {
+                    let Some(name) = item.get("name").and_then(|v| v.as_str()) else {
+                        continue;
+                    };
+                    tools.push(McpTool {
+                        name: name.to_string(),
+                        description: item
+                            .get("description")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .to_string(),
+                        input_schema: item
+                            .get("inputSchema")
+                            .cloned()
+                            .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+                    });
+                }
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(tools)
+    }
+
+    /// 调用工具，返回拼接后的文本内容
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<String, String> {
+        let result = self
+            .request("tools/call", json!({ "name": tool, "arguments": arguments }))
+            .await?;
+
+        let mut parts = Vec::new();
+        if let Some(items) = result.get("content").and_then(|v| v.as_array()) {
+            for item in items {
+                match item.get("type").and_then(|v| v.as_str()) {
+                    Some("text") => {
+                        if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
+                            parts.push(text.to_string());
+                        }
+                    }
+                    Some("resource") => {
+                        if let Some(text) = item
+                            .get("resource")
+                            .and_then(|r| r.get("text"))
+                            .and_then(|v| v.as_str())
+                        {
+                            parts.push(text.to_string());
+                        }
+                    }
+                    Some(other) => parts.push(format!("[{} content omitted]", other)),
+                    None => {}
+                }
+            }
+        }
+        let text = parts.join("\n");
+
+        if result.get("isError").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return Err(if text.is_empty() {
+                format!("MCP tool {} failed", tool)
+            } else {
+                text
+            });
+        }
+        Ok(text)
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(err) = self.send(&message).await {
+            self.pending.lock().remove(&id);
+            return Err(err);
+        }
+
+        let wait = Duration::from_millis(self.config.timeout_ms.max(1_000));
+        let response = match timeout(wait, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return Err(format!("MCP server {} disconnected", self.config.name)),
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                return Err(format!("MCP request {} timed out", method));
+            }
+        };
+
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown error");
+            return Err(format!("MCP error: {}", message));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        self.send(&message).await
+    }
+
+    async fn send(&self, message: &Value) -> Result<(), String> {
+        match &self.transport {
+            Transport::Stdio { stdin, .. } => {
+                let mut line = message.to_string();
+                line.push('\n');
+                let mut stdin = stdin.lock().await;
+                stdin
+                    .write_all(line.as_bytes())
+                    .await
+                    .map_err(|e| format!("write to MCP server failed: {}", e))?;
+                stdin
+                    .flush()
+                    .await
+                    .map_err(|e| format!("write to MCP server failed: {}", e))
+            }
+            Transport::Sse {
+                client,
+                endpoint,
+                headers,
+            } => {
+                let mut request = client.post(endpoint.as_str()).json(message);
+                for (key, value) in headers {
+                    request = request.header(key.as_str(), value.as_str());
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| format!("send to MCP server failed: {}", e))?;
+                if !response.status().is_success() {
+                    return Err(format!("send to MCP server failed: HTTP {}", response.status()));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn dispatch_response(pending: &PendingMap, message: Value) {
+    // 只处理带 id 的响应；服务器主动发来的通知直接忽略
+    if message.get("method").is_some() {
+        return;
+    }
+    let Some(id) = message.get("id").and_then(|v| v.as_u64()) else {
+        return;
+    };
+    if let Some(tx) = pending.lock().remove(&id) {
+        let _ = tx.send(message);
+    }
+}
+
+fn parse_sse_block(block: &str) -> (String, String) {
+    let mut event = "message".to_string();
+    let mut data = Vec::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+    }
+    (event, data.join("\n"))
+}
+
+#[cfg(target_os = "windows")]
+fn build_server_command(command: &str) -> TokioCommand {
+    // npx / uvx 等在 Windows 上是 .cmd 脚本，需要经由 cmd 启动
+    let mut cmd = TokioCommand::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(not(target_os = "windows"))]
+fn build_server_command(command: &str) -> TokioCommand {
+    TokioCommand::new(command)
+}
@@ -0,0 +1,314 @@
+mod client;
+
+pub use client::*;
+
+use crate::storage::{McpServerConfig, ToolConfig};
+use parking_lot::Mutex as ParkingMutex;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex as TokioMutex;
+
+const MCP_TOOL_PREFIX: &str = "mcp__";
+const MCP_RECONNECT_COOLDOWN_SECS: u64 = 60;
+
+/// 提供给模型的工具定义（名称已带 mcp__server__ 前缀）
+#[derive(Debug, Clone)]
+pub struct McpToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub transport: String,
+    pub enabled: bool,
+    pub connected: bool,
+    pub error: Option<String>,
+    pub tools: Vec<McpTool>,
+}
+
+struct ServerEntry {
+    connection: Option<Arc<McpConnection>>,
+    tools: Vec<McpTool>,
+    error: Option<String>,
+    failed_at: Option<Instant>,
+    config: McpServerConfig,
+}
+
+fn registry() -> &'static TokioMutex<HashMap<String, ServerEntry>> {
+    static MCP_REGISTRY: OnceLock<TokioMutex<HashMap<String, ServerEntry>>> = OnceLock::new();
+    MCP_REGISTRY.get_or_init(|| TokioMutex::new(HashMap::new()))
+}
+
+/// 工具定义快照，供同步构建 tools 列表时读取
+fn tool_cache() -> &'static ParkingMutex<Vec<McpToolDefinition>> {
+    static MCP_TOOL_CACHE: OnceLock<ParkingMutex<Vec<McpToolDefinition>>> = OnceLock::new();
+    MCP_TOOL_CACHE.get_or_init(|| ParkingMutex::new(Vec::new()))
+}
+
+/// 正在连接中的服务器，避免并发的 sync_servers 重复连接同一服务器
+fn connecting() -> &'static ParkingMutex<HashSet<String>> {
+    static MCP_CONNECTING: OnceLock<ParkingMutex<HashSet<String>>> = OnceLock::new();
+    MCP_CONNECTING.get_or_init(|| ParkingMutex::new(HashSet::new()))
+}
+
+/// 按配置同步 MCP 连接：连接新增/变更的服务器，断开已删除或禁用的服务器。
+/// 连接过程不持有注册表锁，单个服务器超时不会阻塞其他对话
+pub async fn sync_servers(tools_config: &ToolConfig) {
+    let configured: HashMap<String, &McpServerConfig> = tools_config
+        .mcp_servers
+        .iter()
+        .filter(|server| server.enabled && !server.name.trim().is_empty())
+        .map(|server| (sanitize_server_name(&server.name), server))
+        .collect();
+
+    let pending: Vec<(String, McpServerConfig)> = {
+        let mut registry = registry().lock().await;
+        drop_dead_connections(&mut registry);
+        registry.retain(|name, entry| {
+            configured
+                .get(name)
+                .map_or(false, |config| **config == entry.config)
+        });
+        *tool_cache().lock() = build_tool_definitions(&registry);
+
+        let mut connecting = connecting().lock();
+        configured
+            .iter()
+            .filter(|(name, _)| {
+                // 连接失败后冷却一段时间再重试，避免每次对话都卡在超时上
+                registry.get(*name).map_or(true, |entry| {
+                    entry.connection.is_none()
+                        && entry.failed_at.map_or(true, |at| {
+                            at.elapsed() >= Duration::from_secs(MCP_RECONNECT_COOLDOWN_SECS)
+                        })
+                })
+            })
+            .filter(|(name, _)| connecting.insert((*name).clone()))
+            .map(|(name, config)| (name.clone(), (*config).clone()))
+            .collect()
+    };
+    if pending.is_empty() {
+        return;
+    }
+
+    for (name, config) in pending {
+        let entry = match connect_server(&config).await {
+            Ok((connection, tools)) => ServerEntry {
+                connection: Some(Arc::new(connection)),
+                tools,
+                error: None,
+                failed_at: None,
+                config,
+            },
+            Err(err) => {
+                eprintln!("[mcp] connect {} failed: {}", config.name, err);
+                ServerEntry {
+                    connection: None,
+                    tools: Vec::new(),
+                    error: Some(err),
+                    failed_at: Some(Instant::now()),
+                    config,
+                }
+            }
+        };
+        let mut registry = registry().lock().await;
+        connecting().lock().remove(&name);
+        // 连接期间已有其他调用连上时保留已有连接
+        if registry.get(&name).map_or(false, |existing| existing.connection.is_some()) {
+            continue;
+        }
+        registry.insert(name, entry);
+        *tool_cache().lock() = build_tool_definitions(&registry);
+    }
+}
+
+/// 断开全部服务器后按配置重新连接
+pub async fn reload_servers(tools_config: &ToolConfig) {
+    registry().lock().await.clear();
+    tool_cache().lock().clear();
+    sync_servers(tools_config).await;
+}
+
+pub async fn server_statuses(tools_config: &ToolConfig) -> Vec<McpServerStatus> {
+    let mut registry = registry().lock().await;
+    if drop_dead_connections(&mut registry) {
+        *tool_cache().lock() = build_tool_definitions(&registry);
+    }
+    tools_config
+        .mcp_servers
+        .iter()
+        .map(|server| {
+            let entry = registry.get(&sanitize_server_name(&server.name));
+            McpServerStatus {
+                name: server.name.clone(),
+                transport: server.transport.clone(),
+                enabled: server.enabled,
+                connected: entry.map_or(false, |e| e.connection.is_some()),
+                error: entry.and_then(|e| e.error.clone()),
+                tools: entry.map(|e| e.tools.clone()).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// 当前已连接服务器的工具定义
+pub fn tool_definitions() -> Vec<McpToolDefinition> {
+    tool_cache().lock().clone()
+}
+
+/// 解析 mcp__server__tool 形式的工具名
+pub fn parse_tool_name(name: &str) -> Option<(&str, &str)> {
+    let rest = name.strip_prefix(MCP_TOOL_PREFIX)?;
+    let (server, tool) = rest.split_once("__")?;
+    if server.is_empty() || tool.is_empty() {
+        return None;
+    }
+    Some((server, tool))
+}
+
+/// 调用 MCP 工具；服务器未启用或工具不在 allowed_tools 中时拒绝。连接已断开时先重连一次
+pub async fn call_tool(server: &str, tool: &str, arguments: Value) -> Result<String, String> {
+    let (connection, original_name, config) = {
+        let mut registry = registry().lock().await;
+        if drop_dead_connections(&mut registry) {
+            *tool_cache().lock() = build_tool_definitions(&registry);
+        }
+        let entry = registry
+            .get(server)
+            .ok_or_else(|| format!("MCP server not configured: {}", server))?;
+        let cooling_down = entry.failed_at.map_or(false, |at| {
+            at.elapsed() < Duration::from_secs(MCP_RECONNECT_COOLDOWN_SECS)
+        });
+        if entry.connection.is_none() && cooling_down {
+            return Err(format!("MCP server not connected: {}", server));
+        }
+        let original = entry
+            .tools
+            .iter()
+            .find(|t| sanitize_tool_name(&t.name) == tool)
+            .map(|t| t.name.clone())
+            .ok_or_else(|| format!("MCP tool not found: {}", tool))?;
+        if !tool_permitted(&entry.config, &original) {
+            return Err(format!("MCP tool not allowed by server config: {}", original));
+        }
+        (entry.connection.clone(), original, entry.config.clone())
+    };
+
+    let connection = match connection {
+        Some(connection) => connection,
+        None => reconnect_server(server, config).await?,
+    };
+    connection.call_tool(&original_name, arguments).await
+}
+
+/// 把读取任务已退出的连接标记为断开，下次同步或调用时立即重连；返回是否有变化
+fn drop_dead_connections(registry: &mut HashMap<String, ServerEntry>) -> bool {
+    let mut changed = false;
+    for entry in registry.values_mut() {
+        if entry.connection.as_ref().map_or(false, |connection| !connection.is_alive()) {
+            eprintln!("[mcp] server {} disconnected", entry.config.name);
+            entry.connection = None;
+            entry.error = Some(format!("MCP server {} disconnected", entry.config.name));
+            entry.failed_at = None;
+            changed = true;
+        }
+    }
+    changed
+}
+
+async fn reconnect_server(name: &str, config: McpServerConfig) -> Result<Arc<McpConnection>, String> {
+    if !connecting().lock().insert(name.to_string()) {
+        return Err(format!("MCP server not connected: {}", name));
+    }
+    let result = connect_server(&config).await;
+    let mut registry = registry().lock().await;
+    connecting().lock().remove(name);
+    // 重连期间配置被删除或修改时不写回注册表
+    let entry = registry.get_mut(name).filter(|entry| entry.config == config);
+    match result {
+        Ok((connection, tools)) => {
+            let connection = Arc::new(connection);
+            if let Some(entry) = entry {
+                entry.connection = Some(Arc::clone(&connection));
+                entry.tools = tools;
+                entry.error = None;
+                entry.failed_at = None;
+                *tool_cache().lock() = build_tool_definitions(&registry);
+            }
+            Ok(connection)
+        }
+        Err(err) => {
+            eprintln!("[mcp] reconnect {} failed: {}", config.name, err);
+            if let Some(entry) = entry {
+                entry.error = Some(err.clone());
+                entry.failed_at = Some(Instant::now());
+            }
+            Err(err)
+        }
+    }
+}
+
+async fn connect_server(config: &McpServerConfig) -> Result<(McpConnection, Vec<McpTool>), String> {
+    let connection = McpConnection::connect(config).await?;
+    let tools = connection.list_tools().await?;
+    Ok((connection, tools))
+}
+
+fn build_tool_definitions(registry: &HashMap<String, ServerEntry>) -> Vec<McpToolDefinition> {
+    let mut definitions = Vec::new();
+    let mut names: Vec<&String> = registry.keys().collect();
+    names.sort();
+    for name in names {
+        let entry = &registry[name];
+        if entry.connection.is_none() {
+            continue;
+        }
+        for tool in &entry.tools {
+            if !tool_permitted(&entry.config, &tool.name) {
+                continue;
+            }
+            let description = if tool.description.is_empty() {
+                format!("[MCP {}] {}", entry.config.name, tool.name)
+            } else {
+                format!("[MCP {}] {}", entry.config.name, tool.description)
+            };
+            definitions.push(McpToolDefinition {
+                name: format!("{}{}__{}", MCP_TOOL_PREFIX, name, sanitize_tool_name(&tool.name)),
+                description,
+                parameters: tool.input_schema.clone(),
+            });
+        }
+    }
+    definitions
+}
+
+fn tool_permitted(config: &McpServerConfig, tool: &str) -> bool {
+    if config.allowed_tools.is_empty() {
+        return true;
+    }
+    config
+        .allowed_tools
+        .iter()
+        .any(|item| item.trim() == "*" || item.trim().eq_ignore_ascii_case(tool))
+}
+
+/// 模型侧的函数名只允许 [a-zA-Z0-9_-]
+fn sanitize_server_name(name: &str) -> String {
+    let mut name = sanitize_tool_name(name);
+    while name.contains("__") {
+        name = name.replace("__", "_");
+    }
+    name
+}
+
+fn sanitize_tool_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use crate::error::AppError;
+use crate::storage::{MetricsConfig, StorageManager};
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const METRICS_FILE: &str = "metrics.json";
+// 每记录这么多次写一次盘，其余时间只在内存中累计
+const FLUSH_EVERY: u64 = 50;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 耗时统计（毫秒）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Latency {
+    pub count: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    pub avg_ms: u64,
+}
+
+impl Latency {
+    fn record(&mut self, elapsed: Duration) {
+        let ms = elapsed.as_millis() as u64;
+        self.count += 1;
+        self.total_ms = self.total_ms.saturating_add(ms);
+        self.max_ms = self.max_ms.max(ms);
+        self.avg_ms = self.total_ms / self.count;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureMetrics {
+    pub analyzed: u64,
+    pub errors: u64,
+    pub skipped: BTreeMap<String, u64>,  // 按原因：unchanged / privacy / budget
+    pub latency: Latency,  // 单帧截屏+分析耗时
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelMetrics {
+    pub requests: u64,
+    pub errors: BTreeMap<String, u64>,  // 按错误类型：http_429 / http_500 / network / context_overflow ...
+    pub latency: Latency,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolMetrics {
+    pub calls: u64,
+    pub errors: u64,
+    pub latency: Latency,
+}
+
+/// 本地统计，只保存在数据目录中，不会上传
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metrics {
+    pub since: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub capture: CaptureMetrics,
+    #[serde(default)]
+    pub model: ModelMetrics,
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub enabled: bool,
+    pub metrics: Metrics,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    loaded: bool,
+    metrics: Metrics,
+    pending: u64,  // 上次写盘后新增的记录数
+}
+
+fn metrics_state() -> &'static ParkingMutex<MetricsState> {
+    static STATE: OnceLock<ParkingMutex<MetricsState>> = OnceLock::new();
+    STATE.get_or_init(|| ParkingMutex::new(MetricsState::default()))
+}
+
+fn new_metrics() -> Metrics {
+    let now = Local::now().to_rfc3339();
+    Metrics {
+        since: now.clone(),
+        updated_at: now,
+        ..Metrics::default()
+    }
+}
+
+fn load_metrics(storage: &StorageManager) -> Metrics {
+    let path = storage.get_data_dir().join(METRICS_FILE);
+    if !path.exists() {
+        return new_metrics();
+    }
+    storage
+        .read_data_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(new_metrics)
+}
+
+fn save_metrics(storage: &StorageManager, metrics: &Metrics) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(metrics).map_err(|e| format!("序列化统计失败: {}", e))?;
+    storage.write_data_file(&storage.get_data_dir().join(METRICS_FILE), content.as_bytes())
+}
+
+fn ensure_loaded(state: &mut MetricsState) {
+    if !state.loaded {
+        state.metrics = load_metrics(&StorageManager::new());
+        state.loaded = true;
+    }
+}
+
+fn flush(state: &mut MetricsState) {
+    state.pending = 0;
+    if let Err(err) = save_metrics(&StorageManager::new(), &state.metrics) {
+        crate::logs::debug("metrics", format!("保存统计失败: {}", err));
+    }
+}
+
+/// 启动和保存配置时调用；关闭时把已累计的数据写盘
+pub fn apply_metrics_config(config: &MetricsConfig) {
+    let was_enabled = ENABLED.swap(config.enabled, Ordering::Relaxed);
+    if was_enabled && !config.enabled {
+        let mut state = metrics_state().lock();
+        if state.pending > 0 {
+            flush(&mut state);
+        }
+    }
+}
+
+fn update(apply: impl FnOnce(&mut Metrics)) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut state = metrics_state().lock();
+    ensure_loaded(&mut state);
+    apply(&mut state.metrics);
+    state.metrics.updated_at = Local::now().to_rfc3339();
+    state.pending += 1;
+    if state.pending >= FLUSH_EVERY {
+        flush(&mut state);
+    }
+}
+
+pub fn record_capture(elapsed: Duration, result: &Result<bool, String>) {
+    update(|metrics| match result {
+        Ok(true) => {
+            metrics.capture.analyzed += 1;
+            metrics.capture.latency.record(elapsed);
+        }
+        Ok(false) => {}  // 跳过原因由 record_capture_skip 记录
+        Err(_) => metrics.capture.errors += 1,
+    });
+}
+
+pub fn record_capture_skip(reason: &str) {
+    update(|metrics| *metrics.capture.skipped.entry(reason.to_string()).or_default() += 1);
+}
+
+pub fn record_model_request<T>(elapsed: Duration, result: &Result<T, AppError>) {
+    update(|metrics| {
+        metrics.model.requests += 1;
+        metrics.model.latency.record(elapsed);
+        if let Err(err) = result {
+            let kind = match err {
+                AppError::ProviderHttp { status, .. } => format!("http_{}", status),
+                other => other.kind().to_string(),
+            };
+            *metrics.model.errors.entry(kind).or_default() += 1;
+        }
+    });
+}
+
+pub fn record_tool_call(tool: &str, elapsed: Duration, ok: bool) {
+    update(|metrics| {
+        let entry = metrics.tools.entry(tool.to_string()).or_default();
+        entry.calls += 1;
+        if !ok {
+            entry.errors += 1;
+        }
+        entry.latency.record(elapsed);
+    });
+}
+
+/// 当前统计；读取时顺带写盘
+pub fn snapshot() -> MetricsSnapshot {
+    let enabled = ENABLED.load(Ordering::Relaxed);
+    let mut state = metrics_state().lock();
+    ensure_loaded(&mut state);
+    if state.pending > 0 {
+        flush(&mut state);
+    }
+    MetricsSnapshot {
+        enabled,
+        metrics: state.metrics.clone(),
+    }
+}
+
+pub fn reset() -> Result<(), String> {
+    let mut state = metrics_state().lock();
+    state.metrics = new_metrics();
+    state.loaded = true;
+    state.pending = 0;
+    save_metrics(&StorageManager::new(), &state.metrics)
+}
@@ -1,5 +1,9 @@
+use crate::error::AppError;
 use crate::storage::{ApiConfig, StorageManager};
 use crate::commands::ChatHistoryMessage;
+use super::preflight::api_status_error;
+use super::retry::retry_after_secs;
+use super::usage::record_model_usage;
 use chrono::Local;
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
@@ -13,6 +17,7 @@ pub struct ApiClient {
 
 const API_CONNECT_TIMEOUT_SECS: u64 = 15;
 const API_REQUEST_TIMEOUT_SECS: u64 = 120;
+const TOOL_IMAGES_TEXT: &str = "以上工具调用返回的截图：";
 
 #[derive(Serialize)]
 struct ChatRequest {
@@ -21,17 +26,23 @@ struct ChatRequest {
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verbosity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Message {
-    role: String,
+    pub(super) role: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    content: Option<MessageContent>,
+    pub(super) content: Option<MessageContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tool_calls: Option<Vec<ToolCall>>,
+    pub(super) tool_calls: Option<Vec<ToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tool_call_id: Option<String>,
+    pub(super) tool_call_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -46,14 +57,14 @@ pub enum MessageContent {
 pub struct Tool {
     #[serde(rename = "type")]
     tool_type: String,
-    function: ToolFunction,
+    pub(super) function: ToolFunction,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-struct ToolFunction {
-    name: String,
-    description: String,
-    parameters: serde_json::Value,
+pub(super) struct ToolFunction {
+    pub(super) name: String,
+    pub(super) description: String,
+    pub(super) parameters: serde_json::Value,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -62,6 +73,9 @@ pub struct ToolCall {
     #[serde(rename = "type")]
     pub call_type: String,
     pub function: ToolCallFunction,
+    /// Gemini 思考模型返回的签名，后续请求需原样带回；其他提供者为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -73,16 +87,16 @@ pub struct ToolCallFunction {
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct ContentPart {
     #[serde(rename = "type")]
-    content_type: String,
+    pub(super) content_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    text: Option<String>,
+    pub(super) text: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    image_url: Option<ImageUrl>,
+    pub(super) image_url: Option<ImageUrl>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct ImageUrl {
-    url: String,
+    pub(super) url: String,
 }
 
 #[derive(Deserialize)]
@@ -157,15 +171,80 @@ impl ApiClient {
         self.config.request_format == "responses"
     }
 
-    fn responses_reasoning_effort(&self) -> Option<&'static str> {
+    fn is_azure(&self) -> bool {
+        self.config.api_type.trim().eq_ignore_ascii_case("azure")
+    }
+
+    /// Azure 部署名，未填写时沿用模型名
+    fn azure_deployment(&self) -> &str {
+        match self.config.azure_deployment.trim() {
+            "" => self.config.model.trim(),
+            deployment => deployment,
+        }
+    }
+
+    /// Azure 资源地址（https://xxx.openai.azure.com），兼容误填了 /openai 后缀的情况
+    fn azure_base(&self) -> &str {
+        let endpoint = self.config.endpoint.trim().trim_end_matches('/');
+        endpoint.strip_suffix("/openai").unwrap_or(endpoint)
+    }
+
+    fn chat_completions_url(&self) -> String {
+        if self.is_azure() {
+            format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                self.azure_base(),
+                self.azure_deployment(),
+                self.config.azure_api_version.trim()
+            )
+        } else {
+            format!("{}/chat/completions", self.config.endpoint)
+        }
+    }
+
+    fn responses_url(&self) -> String {
+        if self.is_azure() {
+            format!(
+                "{}/openai/responses?api-version={}",
+                self.azure_base(),
+                self.config.azure_api_version.trim()
+            )
+        } else {
+            format!("{}/responses", self.config.endpoint)
+        }
+    }
+
+    /// 请求体中的 model：Azure 按部署名路由
+    fn request_model(&self) -> String {
+        if self.is_azure() {
+            self.azure_deployment().to_string()
+        } else {
+            self.config.model.clone()
+        }
+    }
+
+    /// Responses 格式的推理强度：未配置时 codex 模型默认 high
+    fn responses_reasoning_effort(&self) -> Option<String> {
+        if let Some(effort) = configured_option(&self.config.reasoning_effort) {
+            return Some(effort);
+        }
         let model = self.config.model.to_lowercase();
         if model.contains("codex") {
-            Some("high")
+            Some("high".to_string())
         } else {
             None
         }
     }
 
+    /// Chat Completions 只在显式配置时发送 reasoning_effort，避免不支持的模型报错
+    fn chat_reasoning_effort(&self) -> Option<String> {
+        configured_option(&self.config.reasoning_effort)
+    }
+
+    fn verbosity(&self) -> Option<String> {
+        configured_option(&self.config.verbosity)
+    }
+
     fn messages_to_responses_input(
         messages: &[Message],
     ) -> (Option<String>, Vec<serde_json::Value>) {
@@ -340,6 +419,7 @@ impl ApiClient {
                             id,
                             call_type: "function".to_string(),
                             function: ToolCallFunction { name, arguments },
+                            thought_signature: None,
                         });
                     }
                     _ => {}
@@ -363,11 +443,13 @@ impl ApiClient {
         messages: Vec<Message>,
         max_output_tokens: u32,
         tools: Option<Vec<Tool>>,
-    ) -> Result<ResponsesResult, String> {
-        let url = format!("{}/responses", self.config.endpoint);
+    ) -> Result<ResponsesResult, AppError> {
+        let mut messages = messages;
+        self.preflight_messages(&mut messages, tools.as_deref())?;
+        let url = self.responses_url();
         let (instructions, input) = Self::messages_to_responses_input(&messages);
         let mut body = serde_json::json!({
-            "model": self.config.model.clone(),
+            "model": self.request_model(),
             "input": input,
             "max_output_tokens": max_output_tokens,
         });
@@ -380,6 +462,14 @@ impl ApiClient {
             body["reasoning"] = serde_json::json!({ "effort": effort });
         }
 
+        if let Some(verbosity) = self.verbosity() {
+            body["text"] = serde_json::json!({ "verbosity": verbosity });
+        }
+
+        if let Some(temperature) = self.config.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+
         if let Some(tool_defs) = tools.as_ref() {
             if !tool_defs.is_empty() {
                 body["tools"] = serde_json::Value::Array(Self::tools_to_responses(tool_defs));
@@ -402,49 +492,32 @@ impl ApiClient {
                 }
             })
             .collect();
-        let responses_headers: Vec<(String, String)> = self
-            .config
-            .responses_headers
-            .iter()
-            .filter_map(|(key, value)| {
-                let trimmed = key.trim();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    Some((trimmed.to_string(), value.clone()))
-                }
-            })
-            .collect();
-
         let response = self
             .send_with_proxy_fallback(|client| {
-                let mut request_builder = client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                let mut request_builder = self
+                    .apply_headers(client.post(&url), true)
                     .header("Content-Type", "application/json");
 
                 if !responses_query_params.is_empty() {
                     request_builder = request_builder.query(&responses_query_params);
                 }
 
-                for (key, value) in &responses_headers {
-                    request_builder = request_builder.header(key, value);
-                }
-
                 request_builder.json(&body)
             })
             .await
             .map_err(|e| {
                 write_exchange_log(&log_key, &url, &request_json, None, None, Some(&e.to_string()));
-                format!("Request failed: {}", e)
+                AppError::request_failed(&e, format!("Request failed: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log(&log_key, &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API error {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let json: serde_json::Value = serde_json::from_str(&text)
@@ -453,26 +526,24 @@ impl ApiClient {
         if let Some(error_obj) = json.get("error") {
             // OpenAI Responses returns `"error": null` on success.
             if !error_obj.is_null() {
-                return Err(format!("API error: {}", error_obj));
+                return Err(AppError::provider(None, format!("API error: {}", error_obj), None));
             }
         }
 
         Ok(Self::parse_responses_result(&json))
     }
 
-    pub async fn test_connection(&self) -> Result<(), String> {
+    pub async fn test_connection(&self) -> Result<(), AppError> {
         let url = format!("{}/models", self.config.endpoint);
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .get(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.get(&url), false)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-test", &url, "(none)", None, None, Some(&e.to_string()));
-                format!("连接失败: {}", e)
+                AppError::request_failed(&e, format!("连接失败: {}", e))
             })?;
 
         let status = response.status();
@@ -482,11 +553,160 @@ impl ApiClient {
         if status.is_success() {
             Ok(())
         } else {
-            Err(format!("API 返回错误 {}: {}", status, text))
+            Err(AppError::provider(Some(status.as_u16()), format!("API 返回错误 {}: {}", status, text), None))
+        }
+    }
+
+    /// 调用 /embeddings 计算文本向量，结果与 texts 顺序一致；Azure 下 model 为部署名
+    pub async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let url = if self.is_azure() {
+            format!(
+                "{}/openai/deployments/{}/embeddings?api-version={}",
+                self.azure_base(),
+                model,
+                self.config.azure_api_version.trim()
+            )
+        } else {
+            format!("{}/embeddings", self.config.endpoint)
+        };
+        let body = serde_json::json!({ "model": model, "input": texts });
+
+        let response = self
+            .send_with_proxy_fallback(|client| {
+                self.apply_headers(client.post(&url), false)
+                    .header("Content-Type", "application/json")
+                    .json(&body)
+            })
+            .await
+            .map_err(|e| {
+                write_exchange_log("api-embed", &url, "(embeddings)", None, None, Some(&e.to_string()));
+                AppError::request_failed(&e, format!("请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
+        let text = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            write_exchange_log("api-embed", &url, "(embeddings)", Some(status), Some(&text), None);
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
+        }
+        self.record_usage(&text);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&text).map_err(|e| format!("解析向量响应失败: {}", e))?;
+        let mut items: Vec<(usize, Vec<f32>)> = json["data"]
+            .as_array()
+            .ok_or_else(|| "向量响应缺少 data".to_string())?
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let index = item["index"].as_u64().map_or(i, |index| index as usize);
+                (index, parse_embedding_values(&item["embedding"]))
+            })
+            .collect();
+        items.sort_by_key(|(index, _)| *index);
+        let vectors: Vec<Vec<f32>> = items.into_iter().map(|(_, vector)| vector).collect();
+        if vectors.len() != texts.len() || vectors.iter().any(|vector| vector.is_empty()) {
+            return Err(AppError::other("向量数量与输入不一致"));
+        }
+        Ok(vectors)
+    }
+
+    /// 调用 /audio/transcriptions 把录音转成文字；Azure 下 model 为部署名，language 为空时自动识别
+    pub async fn transcribe(
+        &self,
+        model: &str,
+        audio: &[u8],
+        file_name: &str,
+        language: &str,
+    ) -> Result<String, AppError> {
+        let url = if self.is_azure() {
+            format!(
+                "{}/openai/deployments/{}/audio/transcriptions?api-version={}",
+                self.azure_base(),
+                model,
+                self.config.azure_api_version.trim()
+            )
+        } else {
+            format!("{}/audio/transcriptions", self.config.endpoint)
+        };
+
+        let response = self
+            .send_with_proxy_fallback(|client| {
+                let part = reqwest::multipart::Part::bytes(audio.to_vec()).file_name(file_name.to_string());
+                let mut form = reqwest::multipart::Form::new()
+                    .text("model", model.to_string())
+                    .text("response_format", "json")
+                    .part("file", part);
+                if !language.trim().is_empty() {
+                    form = form.text("language", language.trim().to_string());
+                }
+                self.apply_headers(client.post(&url), false).multipart(form)
+            })
+            .await
+            .map_err(|e| {
+                write_exchange_log("api-transcribe", &url, "(audio)", None, None, Some(&e.to_string()));
+                AppError::request_failed(&e, format!("请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
+        let text = response.text().await.unwrap_or_default();
+        write_exchange_log("api-transcribe", &url, "(audio)", Some(status), Some(&text), None);
+        if !status.is_success() {
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
+        }
+        let json: serde_json::Value =
+            serde_json::from_str(&text).map_err(|e| format!("解析转写响应失败: {}", e))?;
+        json["text"]
+            .as_str()
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| AppError::other("转写响应缺少 text"))
+    }
+
+    /// 调用 /audio/speech 合成语音，返回 mp3 数据；Azure 下 model 为部署名
+    pub async fn synthesize_speech(&self, model: &str, voice: &str, text: &str) -> Result<Vec<u8>, AppError> {
+        let url = if self.is_azure() {
+            format!(
+                "{}/openai/deployments/{}/audio/speech?api-version={}",
+                self.azure_base(),
+                model,
+                self.config.azure_api_version.trim()
+            )
+        } else {
+            format!("{}/audio/speech", self.config.endpoint)
+        };
+        let body = serde_json::json!({
+            "model": model,
+            "voice": voice,
+            "input": text,
+            "response_format": "mp3",
+        });
+
+        let response = self
+            .send_with_proxy_fallback(|client| {
+                self.apply_headers(client.post(&url), false)
+                    .header("Content-Type", "application/json")
+                    .json(&body)
+            })
+            .await
+            .map_err(|e| {
+                write_exchange_log("api-speech", &url, "(speech)", None, None, Some(&e.to_string()));
+                AppError::request_failed(&e, format!("请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            write_exchange_log("api-speech", &url, "(speech)", Some(status), Some(&text), None);
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
+        let bytes = response.bytes().await.map_err(|e| format!("读取语音数据失败: {}", e))?;
+        Ok(bytes.to_vec())
     }
 
-    pub async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String, String> {
+    pub async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String, AppError> {
         if self.use_responses_request_format() {
             let messages = vec![
                 Message {
@@ -507,13 +727,13 @@ impl ApiClient {
                 .await?;
             return result
                 .text
-                .ok_or_else(|| "No content returned".to_string());
+                .ok_or_else(|| AppError::other("No content returned"));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages: vec![
                 Message {
                     role: "system".to_string(),
@@ -530,31 +750,35 @@ impl ApiClient {
             ],
             max_tokens: 2048,
             tools: None,
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-chat", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-chat", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -563,7 +787,7 @@ impl ApiClient {
             .message
             .content
             .clone()
-            .ok_or_else(|| "没有返回内容".to_string())
+            .ok_or_else(|| AppError::other("没有返回内容"))
     }
 
 
@@ -573,7 +797,7 @@ impl ApiClient {
         system_prompt: &str,
         user_message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         if self.use_responses_request_format() {
             let mut messages = vec![Message {
                 role: "system".to_string(),
@@ -603,10 +827,10 @@ impl ApiClient {
                 .await?;
             return result
                 .text
-                .ok_or_else(|| "No content returned".to_string());
+                .ok_or_else(|| AppError::other("No content returned"));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
         let mut messages = vec![Message {
             role: "system".to_string(),
@@ -633,36 +857,40 @@ impl ApiClient {
             tool_call_id: None,
         });
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages,
             max_tokens: 2048,
             tools: None,
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-chat-history", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-chat-history", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -671,7 +899,7 @@ impl ApiClient {
             .message
             .content
             .clone()
-            .ok_or_else(|| "没有返回内容".to_string())
+            .ok_or_else(|| AppError::other("没有返回内容"))
     }
 
     pub async fn chat_with_history_with_images(
@@ -680,7 +908,7 @@ impl ApiClient {
         user_message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
         image_urls: &[String],
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         if self.use_responses_request_format() {
             let mut messages = vec![Message {
                 role: "system".to_string(),
@@ -711,10 +939,10 @@ impl ApiClient {
                 .await?;
             return result
                 .text
-                .ok_or_else(|| "No content returned".to_string());
+                .ok_or_else(|| AppError::other("No content returned"));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
         let mut messages = vec![Message {
             role: "system".to_string(),
@@ -740,36 +968,40 @@ impl ApiClient {
             tool_call_id: None,
         });
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages,
             max_tokens: 2048,
             tools: None,
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-chat-history", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-chat-history", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -778,7 +1010,7 @@ impl ApiClient {
             .message
             .content
             .clone()
-            .ok_or_else(|| "没有返回内容".to_string())
+            .ok_or_else(|| AppError::other("没有返回内容"))
     }
 
     fn build_user_message_content(user_message: &str, image_urls: &[String]) -> MessageContent {
@@ -804,6 +1036,19 @@ impl ApiClient {
         MessageContent::Parts(parts)
     }
 
+    /// 工具返回的截图只能放在 user 消息里发送；只随本次请求发送，不写入返回的对话记录
+    pub fn tool_images_message(image_urls: &[String]) -> Option<Message> {
+        if image_urls.is_empty() {
+            return None;
+        }
+        Some(Message {
+            role: "user".to_string(),
+            content: Some(Self::build_user_message_content(TOOL_IMAGES_TEXT, image_urls)),
+            tool_calls: None,
+            tool_call_id: None,
+        })
+    }
+
     fn format_api_error(error: &ApiError) -> String {
         let mut details = Vec::new();
         if let Some(code) = &error.code {
@@ -828,32 +1073,35 @@ impl ApiClient {
         }
     }
 
-    fn parse_chat_response(text: &str) -> Result<ChatResponse, String> {
+    fn parse_chat_response(text: &str) -> Result<ChatResponse, AppError> {
         let chat_response: ChatResponse = serde_json::from_str(text)
             .map_err(|e| format!("解析响应失败: {}", e))?;
         if let Some(error) = &chat_response.error {
-            return Err(Self::format_api_error(error));
+            return Err(AppError::provider(None, Self::format_api_error(error), None));
         }
         Ok(chat_response)
     }
-    pub async fn analyze_image(&self, image_base64: &str, prompt: &str) -> Result<String, String> {
+    /// 多张图片放在同一条用户消息中，按顺序分析
+    pub async fn analyze_images(&self, images_base64: &[String], prompt: &str) -> Result<String, AppError> {
+        let image_parts = || {
+            let mut parts = vec![ContentPart {
+                content_type: "text".to_string(),
+                text: Some(prompt.to_string()),
+                image_url: None,
+            }];
+            parts.extend(images_base64.iter().map(|image_base64| ContentPart {
+                content_type: "image_url".to_string(),
+                text: None,
+                image_url: Some(ImageUrl {
+                    url: format!("data:image/jpeg;base64,{}", image_base64),
+                }),
+            }));
+            parts
+        };
         if self.use_responses_request_format() {
             let messages = vec![Message {
                 role: "user".to_string(),
-                content: Some(MessageContent::Parts(vec![
-                    ContentPart {
-                        content_type: "text".to_string(),
-                        text: Some(prompt.to_string()),
-                        image_url: None,
-                    },
-                    ContentPart {
-                        content_type: "image_url".to_string(),
-                        text: None,
-                        image_url: Some(ImageUrl {
-                            url: format!("data:image/jpeg;base64,{}", image_base64),
-                        }),
-                    },
-                ])),
+                content: Some(MessageContent::Parts(image_parts())),
                 tool_calls: None,
                 tool_call_id: None,
             }];
@@ -862,59 +1110,50 @@ impl ApiClient {
                 .await?;
             return result
                 .text
-                .ok_or_else(|| "No content returned".to_string());
+                .ok_or_else(|| AppError::other("No content returned"));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages: vec![Message {
                 role: "user".to_string(),
-                content: Some(MessageContent::Parts(vec![
-                    ContentPart {
-                        content_type: "text".to_string(),
-                        text: Some(prompt.to_string()),
-                        image_url: None,
-                    },
-                    ContentPart {
-                        content_type: "image_url".to_string(),
-                        text: None,
-                        image_url: Some(ImageUrl {
-                            url: format!("data:image/jpeg;base64,{}", image_base64),
-                        }),
-                    },
-                ])),
+                content: Some(MessageContent::Parts(image_parts())),
                 tool_calls: None,
                 tool_call_id: None,
             }],
             max_tokens: 10000,
             tools: None,
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-image", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-image", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -923,9 +1162,16 @@ impl ApiClient {
             .message
             .content
             .clone()
-            .ok_or_else(|| "没有返回内容".to_string())
+            .ok_or_else(|| AppError::other("没有返回内容"))
     }
-    pub async fn test_connection_with_fallback(&self) -> Result<(), String> {
+    pub async fn test_connection_with_fallback(&self) -> Result<(), AppError> {
+        // Azure 没有按部署列出模型的 /models 接口，直接用最小对话请求测试
+        if self.is_azure() {
+            if self.azure_deployment().is_empty() {
+                return Err(AppError::other("Azure OpenAI 需要填写部署名称"));
+            }
+            return self.test_chat_connection().await;
+        }
         if self.test_connection().await.is_ok() {
             return Ok(());
         }
@@ -934,7 +1180,7 @@ impl ApiClient {
         self.test_chat_connection().await
     }
 
-    async fn test_chat_connection(&self) -> Result<(), String> {
+    async fn test_chat_connection(&self) -> Result<(), AppError> {
         if self.use_responses_request_format() {
             let messages = vec![Message {
                 role: "user".to_string(),
@@ -948,10 +1194,10 @@ impl ApiClient {
             return Ok(());
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
         let request = ChatRequest {
-            model: self.config.model.clone(),
+            model: self.request_model(),
             messages: vec![Message {
                 role: "user".to_string(),
                 content: Some(MessageContent::Text("ping".to_string())),
@@ -960,6 +1206,9 @@ impl ApiClient {
             }],
             max_tokens: 1,
             tools: None,
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
 
         let request_json = serde_json::to_string_pretty(&request)
@@ -967,26 +1216,25 @@ impl ApiClient {
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-test-chat", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("Request failed: {}", e)
+                AppError::request_failed(&e, format!("Request failed: {}", e))
             })?;
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-test-chat", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if status.is_success() {
             Ok(())
         } else {
-            Err(format!("API error {}: {}", status, text))
+            Err(AppError::provider(Some(status.as_u16()), format!("API error {}: {}", status, text), None))
         }
     }
 
@@ -1170,6 +1418,197 @@ impl ApiClient {
             });
         }
 
+        if is_tool_allowed("task_status") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "task_status".to_string(),
+                    description: "Check background commands started with a trailing &. Without task_id, lists all tasks; with task_id, returns status and the tail of its output.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "task_id": { "type": "string", "description": "Background task ID (e.g. bg-...)" },
+                            "max_bytes": { "type": "integer", "description": "Optional max output bytes to return from the end" }
+                        }
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("ocr") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "ocr".to_string(),
+                    description: "Extract text from an image locally (no vision model needed). Pass the timestamp of a history record to read its stored screenshot, or a screenshot file name / image path.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "timestamp": { "type": "string", "description": "History record timestamp, e.g. 2024-01-01T12:00:00" },
+                            "path": { "type": "string", "description": "Screenshot file name (relative to the screenshots dir) or image path" }
+                        }
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("read_attachment_file") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "read_attachment_file".to_string(),
+                    description: "Read a text file inside a folder the user attached to the conversation. The attachment lists the folder tree and a few key files; use this to open any other file in it. Only works within attached folders.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "path": { "type": "string", "description": "File path relative to the attached folder (as shown in the folder tree)" },
+                            "dir": { "type": "string", "description": "Optional attached folder name or path when several folders are attached" },
+                            "max_chars": { "type": "integer", "description": "Optional max characters to return (default 20000)" }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("snippets") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "snippets".to_string(),
+                    description: "Look up snippets (commands, configs, code) the user saved from earlier answers. action=search lists matching snippets by keyword; action=get returns the full content of one snippet by id.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "action": { "type": "string", "enum": ["search", "get"], "description": "search (default) or get" },
+                            "query": { "type": "string", "description": "Keywords matched against title, tags and content; empty lists recent snippets" },
+                            "id": { "type": "string", "description": "Snippet ID for action=get" },
+                            "limit": { "type": "integer", "description": "Optional max results for search" }
+                        }
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("git") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "git".to_string(),
+                    description: "Run git in a repository and get structured JSON. Read-only actions: status, diff (staged, rev, paths), log (limit, rev, paths), branch (list). Actions that change the repository need extra permission: add (paths or all=true), commit (message), restore (paths, staged), branch with create=<name>. Prefer this over Bash for git.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "action": { "type": "string", "enum": ["status", "diff", "log", "branch", "add", "commit", "restore"] },
+                            "path": { "type": "string", "description": "Repository directory; defaults to the current workspace" },
+                            "paths": { "type": "array", "items": { "type": "string" }, "description": "Files relative to the repository" },
+                            "staged": { "type": "boolean", "description": "diff: show staged changes; restore: unstage instead of discarding" },
+                            "rev": { "type": "string", "description": "Commit, branch or range for diff/log, start point for branch create" },
+                            "limit": { "type": "integer", "description": "log: max commits (default 20)" },
+                            "all": { "type": "boolean", "description": "add: stage all changes" },
+                            "message": { "type": "string", "description": "commit message" },
+                            "create": { "type": "string", "description": "branch: name of a new branch to create" }
+                        },
+                        "required": ["action"]
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("clipboard_read") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "clipboard_read".to_string(),
+                    description: "Read the current clipboard text. With history=true, search what the user copied earlier (requires clipboard history to be enabled); results are newest first.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "history": { "type": "boolean", "description": "Search clipboard history instead of reading the current clipboard" },
+                            "query": { "type": "string", "description": "history: keywords to match" },
+                            "date": { "type": "string", "description": "history: only this day (YYYY-MM-DD)" },
+                            "days": { "type": "integer", "description": "history: how many recent days to search (default 7)" },
+                            "limit": { "type": "integer", "description": "history: max entries (default 20)" }
+                        }
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("clipboard_write") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "clipboard_write".to_string(),
+                    description: "Put text on the clipboard so the user can paste it directly.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "text": { "type": "string", "description": "Text to copy" }
+                        },
+                        "required": ["text"]
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("ui_action") {
+            let step_properties = serde_json::json!({
+                "action": { "type": "string", "enum": ["move", "click", "type", "key", "scroll", "wait"] },
+                "x": { "type": "integer", "description": "Screen x in pixels (move/click)" },
+                "y": { "type": "integer", "description": "Screen y in pixels (move/click)" },
+                "button": { "type": "string", "enum": ["left", "right", "middle"] },
+                "double": { "type": "boolean", "description": "click: double click" },
+                "text": { "type": "string", "description": "type: text to type" },
+                "key": { "type": "string", "description": "key: enter, tab, escape, up, f5, a ..." },
+                "modifiers": { "type": "array", "items": { "type": "string" }, "description": "key: ctrl, shift, alt, meta" },
+                "amount": { "type": "integer", "description": "scroll: lines, negative scrolls up/left" },
+                "horizontal": { "type": "boolean" },
+                "ms": { "type": "integer", "description": "wait: milliseconds" }
+            });
+            let mut properties = step_properties.clone();
+            properties["steps"] = serde_json::json!({
+                "type": "array",
+                "items": { "type": "object", "properties": step_properties },
+                "description": "Run several actions in order instead of a single action"
+            });
+            properties["delay_ms"] = serde_json::json!({ "type": "integer", "description": "Pause between steps (default 150)" });
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "ui_action".to_string(),
+                    description: "Control the desktop mouse and keyboard (only when the user enabled desktop automation). Use a single action or a steps list. Every action is logged; moving the mouse to the top-left corner stops it.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": properties
+                    }),
+                },
+            });
+        }
+
+        if is_tool_allowed("browser") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "browser".to_string(),
+                    description: "Control a headless browser. Actions: navigate (url), snapshot (list interactive elements with refs like @e1), click (selector), type (selector, text, submit), extract_text (optional selector, default whole page), screenshot (full_page; the image is returned to you), close. Selectors are CSS selectors or refs from snapshot. Pages and logins persist per session until closed or idle.".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "action": { "type": "string", "enum": ["navigate", "snapshot", "click", "type", "extract_text", "screenshot", "close"] },
+                            "url": { "type": "string", "description": "navigate: page URL" },
+                            "selector": { "type": "string", "description": "CSS selector or @ref from snapshot" },
+                            "text": { "type": "string", "description": "type: text to fill in" },
+                            "submit": { "type": "boolean", "description": "type: press Enter after filling" },
+                            "full_page": { "type": "boolean", "description": "screenshot: capture the full scrollable page" },
+                            "session": { "type": "string", "description": "Optional session name to keep separate pages; defaults to default" }
+                        },
+                        "required": ["action"]
+                    }),
+                },
+            });
+        }
+
         if is_tool_allowed("progress_update") {
             tools.push(Tool {
                 tool_type: "function".to_string(),
@@ -1240,6 +1679,31 @@ impl ApiClient {
                                 "additionalProperties": { "type": "string" },
                                 "description": "可选的元数据键值对"
                             },
+                            "output_schema": {
+                                "type": "object",
+                                "description": "可选，最终回答需符合的 JSON Schema（用于表格、清单等结构化结果）"
+                            },
+                            "icon": {
+                                "type": "string",
+                                "description": "可选，技能图标（emoji 或图片地址）"
+                            },
+                            "category": {
+                                "type": "string",
+                                "description": "可选，技能分类，如 写作、开发、办公"
+                            },
+                            "author": {
+                                "type": "string",
+                                "description": "可选，作者"
+                            },
+                            "homepage": {
+                                "type": "string",
+                                "description": "可选，主页或文档地址"
+                            },
+                            "examples": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                                "description": "可选，示例提问，用于技能目录展示"
+                            },
                         },
                         "required": ["action", "name"]
                     }),
@@ -1247,6 +1711,40 @@ impl ApiClient {
             });
         }
 
+        if is_tool_allowed("manage_config") {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "manage_config".to_string(),
+                    description: "查看或提议修改应用设置。只支持截屏间隔、对话上下文模式和提醒置信度阈值；action=get 返回当前值，action=propose 提议修改，用户在界面上确认后才会保存。".to_string(),
+                    parameters: serde_json::json!({
+                        "type": "object",
+                        "properties": {
+                            "action": {
+                                "type": "string",
+                                "enum": ["get", "propose"],
+                                "description": "get=读取当前设置，propose=提议修改"
+                            },
+                            "changes": {
+                                "type": "object",
+                                "properties": {
+                                    "capture_interval_ms": { "type": "integer", "description": "截屏间隔（毫秒），1000-3600000" },
+                                    "context_mode": { "type": "string", "enum": ["auto", "always", "off"], "description": "对话是否附带屏幕记录上下文" },
+                                    "alert_confidence_threshold": { "type": "number", "description": "提醒置信度阈值，0-1，越高提醒越少" }
+                                },
+                                "description": "propose 时要修改的设置，只填需要改的项"
+                            },
+                            "reason": {
+                                "type": "string",
+                                "description": "向用户说明修改原因"
+                            }
+                        },
+                        "required": ["action"]
+                    }),
+                },
+            });
+        }
+
         if is_tool_allowed("invoke_skill") && !skills.is_empty() {
             let skill_names: Vec<String> = skills
                 .iter()
@@ -1291,6 +1789,20 @@ impl ApiClient {
             }
         }
 
+        // 已连接的 MCP 服务器工具
+        for definition in crate::mcp::tool_definitions() {
+            if is_tool_allowed(&definition.name) {
+                tools.push(Tool {
+                    tool_type: "function".to_string(),
+                    function: ToolFunction {
+                        name: definition.name,
+                        description: definition.description,
+                        parameters: definition.parameters,
+                    },
+                });
+            }
+        }
+
         tools
     }
 
@@ -1301,7 +1813,7 @@ impl ApiClient {
         user_message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
         tools: Vec<Tool>,
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         if self.use_responses_request_format() {
             let mut messages = vec![Message {
                 role: "system".to_string(),
@@ -1360,7 +1872,7 @@ impl ApiClient {
             ));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
         let mut messages = vec![Message {
             role: "system".to_string(),
@@ -1391,36 +1903,40 @@ impl ApiClient {
         messages.push(user_message.clone());
         messages_for_return.push(user_message);
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages,
             max_tokens: 2048,
             tools: if tools.is_empty() { None } else { Some(tools) },
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-chat-tools", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-chat-tools", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -1461,7 +1977,7 @@ impl ApiClient {
         history: Option<Vec<ChatHistoryMessage>>,
         tools: Vec<Tool>,
         image_urls: &[String],
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         if self.use_responses_request_format() {
             let mut messages = vec![Message {
                 role: "system".to_string(),
@@ -1521,7 +2037,7 @@ impl ApiClient {
             ));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
         let mut messages = vec![Message {
             role: "system".to_string(),
@@ -1551,36 +2067,40 @@ impl ApiClient {
         messages.push(user_message.clone());
         messages_for_return.push(user_message);
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages,
             max_tokens: 2048,
             tools: if tools.is_empty() { None } else { Some(tools) },
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-chat-tools", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-chat-tools", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -1617,8 +2137,9 @@ impl ApiClient {
         system_prompt: &str,
         messages_so_far: Vec<Message>,
         tool_results: Vec<(String, String)>,
+        tool_images: &[String],
         tools: Vec<Tool>,
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         if self.use_responses_request_format() {
             let mut messages = vec![Message {
                 role: "system".to_string(),
@@ -1641,6 +2162,7 @@ impl ApiClient {
                 messages.push(tool_message.clone());
                 messages_for_return.push(tool_message);
             }
+            messages.extend(Self::tool_images_message(tool_images));
 
             let result = self
                 .send_responses_request(
@@ -1672,7 +2194,7 @@ impl ApiClient {
             ));
         }
 
-        let url = format!("{}/chat/completions", self.config.endpoint);
+        let url = self.chat_completions_url();
 
         let mut messages = vec![Message {
             role: "system".to_string(),
@@ -1697,37 +2219,42 @@ impl ApiClient {
             messages.push(tool_message.clone());
             messages_for_return.push(tool_message);
         }
+        messages.extend(Self::tool_images_message(tool_images));
 
-        let request = ChatRequest {
-            model: self.config.model.clone(),
+        let mut request = ChatRequest {
+            model: self.request_model(),
             messages,
             max_tokens: 2048,
             tools: if tools.is_empty() { None } else { Some(tools) },
+            reasoning_effort: self.chat_reasoning_effort(),
+            verbosity: self.verbosity(),
+            temperature: self.config.temperature,
         };
+        self.preflight_messages(&mut request.messages, request.tools.as_deref())?;
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
         let response = self
             .send_with_proxy_fallback(|client| {
-                client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.config.api_key))
+                self.apply_headers(client.post(&url), false)
                     .header("Content-Type", "application/json")
                     .json(&request)
             })
             .await
             .map_err(|e| {
                 write_exchange_log("api-chat-tool-result", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
+                AppError::request_failed(&e, format!("请求失败: {}", e))
             })?;
 
         let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("api-chat-tool-result", &url, &request_json, Some(status), Some(&text), None);
+        self.record_usage(&text);
 
         if !status.is_success() {
-            return Err(format!("API 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), api_status_error(status, &text), retry_after));
         }
 
         let chat_response = Self::parse_chat_response(&text)?;
@@ -1760,6 +2287,54 @@ impl ApiClient {
         Ok(ChatWithToolsResult::Text(content))
     }
 
+    fn record_usage(&self, body: &str) {
+        record_model_usage(
+            &self.config.api_type,
+            &self.config.model,
+            self.config.pricing.as_ref(),
+            body,
+        );
+    }
+
+    /// 鉴权和自定义请求头：custom_headers 作用于所有请求，responses_headers 只用于 Responses 接口；
+    /// 自定义了 Authorization 时不再添加 Bearer；Azure 使用 api-key 请求头
+    fn apply_headers(&self, builder: reqwest::RequestBuilder, responses: bool) -> reqwest::RequestBuilder {
+        let mut headers: Vec<(&str, &str)> = Vec::new();
+        if !self.config.organization.trim().is_empty() {
+            headers.push(("OpenAI-Organization", self.config.organization.trim()));
+        }
+        if !self.config.project.trim().is_empty() {
+            headers.push(("OpenAI-Project", self.config.project.trim()));
+        }
+        let extra = self
+            .config
+            .custom_headers
+            .iter()
+            .chain(self.config.responses_headers.iter().filter(|_| responses));
+        for (key, value) in extra {
+            let key = key.trim();
+            if !key.is_empty() {
+                headers.push((key, value.as_str()));
+            }
+        }
+
+        let mut builder = builder;
+        let has_custom_auth = headers.iter().any(|(key, _)| {
+            key.eq_ignore_ascii_case("authorization") || key.eq_ignore_ascii_case("api-key")
+        });
+        if !has_custom_auth {
+            builder = if self.is_azure() {
+                builder.header("api-key", self.config.api_key.as_str())
+            } else {
+                builder.header("Authorization", format!("Bearer {}", self.config.api_key))
+            };
+        }
+        for (key, value) in headers {
+            builder = builder.header(key, value);
+        }
+        builder
+    }
+
     async fn send_with_proxy_fallback<F>(&self, make_request: F) -> Result<reqwest::Response, reqwest::Error>
     where
         F: Fn(&Client) -> reqwest::RequestBuilder,
@@ -1777,6 +2352,16 @@ impl ApiClient {
     }
 }
 
+/// 空值或 auto 表示不发送，由服务端决定
+fn configured_option(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() || value == "auto" {
+        None
+    } else {
+        Some(value)
+    }
+}
+
 fn message_text_content(content: Option<&MessageContent>) -> String {
     match content {
         Some(MessageContent::Text(text)) => text.clone(),
@@ -1805,7 +2390,7 @@ fn normalize_history_role(role: &str) -> Option<String> {
     }
 }
 
-fn history_message_to_message(msg: ChatHistoryMessage) -> Option<Message> {
+pub(super) fn history_message_to_message(msg: ChatHistoryMessage) -> Option<Message> {
     let role = normalize_history_role(&msg.role)?;
     let tool_calls = msg.tool_calls.map(|calls| {
         calls
@@ -1817,6 +2402,7 @@ fn history_message_to_message(msg: ChatHistoryMessage) -> Option<Message> {
                     name: call.name,
                     arguments: call.arguments,
                 },
+                thought_signature: None,
             })
             .collect::<Vec<_>>()
     });
@@ -1864,7 +2450,14 @@ fn should_retry_without_proxy(error: &reqwest::Error) -> bool {
         || message.contains("10061")
 }
 
-fn write_exchange_log(
+pub(super) fn parse_embedding_values(value: &serde_json::Value) -> Vec<f32> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+        .unwrap_or_default()
+}
+
+pub(super) fn write_exchange_log(
     prefix: &str,
     url: &str,
     request_body: &str,
@@ -98,6 +98,14 @@ fn classify_model_error(detail: &str) -> ModelErrorInfo {
         };
     }
 
+    if is_image_unsupported_error(&lower) {
+        return ModelErrorInfo {
+            error_type: "image_unsupported",
+            message: "当前模型不支持图片输入，截屏已改用 OCR 文字分析".to_string(),
+            suggestion: "在设置中为截屏选择支持视觉的模型（如 GPT-4o、Gemini、llava），并安装 OCR 引擎（tesseract）以便期间继续分析".to_string(),
+        };
+    }
+
     if lower.contains("400")
         || lower.contains("404")
         || lower.contains("invalid")
@@ -129,10 +137,21 @@ fn classify_model_error(detail: &str) -> ModelErrorInfo {
     }
 }
 
-pub fn is_transient_model_error(detail: &str) -> bool {
-    matches!(
-        classify_model_error(detail).error_type,
-        "timeout" | "network" | "rate_limit" | "server_error"
-    )
+/// 各服务商拒绝图片输入时的报错关键字（传入小写文本）
+pub fn is_image_unsupported_error(lower: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "does not support image",
+        "doesn't support image",
+        "does not support vision",
+        "image input is not supported",
+        "image inputs are not supported",
+        "image_url is only supported",
+        "unknown variant `image_url`",
+        "vision is not supported",
+        "model does not support images",
+        "不支持图片",
+        "不支持图像",
+        "不支持视觉",
+    ];
+    MARKERS.iter().any(|marker| lower.contains(marker))
 }
-
@@ -0,0 +1,533 @@
+use crate::commands::ChatHistoryMessage;
+use crate::error::AppError;
+use crate::storage::GeminiConfig;
+use super::api::{
+    history_message_to_message, parse_embedding_values, write_exchange_log, ApiClient, ChatWithToolsResult, ContentPart, ImageUrl,
+    Message, MessageContent, Tool, ToolCall, ToolCallFunction,
+};
+use super::retry::retry_after_secs;
+use super::usage::record_model_usage;
+use chrono::Local;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const GEMINI_CONNECT_TIMEOUT_SECS: u64 = 15;
+const GEMINI_REQUEST_TIMEOUT_SECS: u64 = 120;
+const GEMINI_MAX_OUTPUT_TOKENS: u32 = 2048;
+// Gemini 函数参数只支持 OpenAPI Schema 子集，这些字段会导致请求被拒绝
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$ref",
+    "$defs",
+    "definitions",
+    "additionalProperties",
+    "patternProperties",
+    "default",
+    "examples",
+    "const",
+];
+
+/// Google Generative Language API 客户端（contents/parts 消息格式）
+pub struct GeminiClient {
+    config: GeminiConfig,
+    client: Client,
+}
+
+/// 解析后的一次回复
+struct GeminiReply {
+    text: Option<String>,
+    tool_calls: Vec<ToolCall>,
+}
+
+impl GeminiClient {
+    pub fn new(config: &GeminiConfig) -> Self {
+        Self {
+            config: config.clone(),
+            client: build_gemini_client(),
+        }
+    }
+
+    fn endpoint(&self) -> String {
+        self.config.endpoint.trim().trim_end_matches('/').to_string()
+    }
+
+    fn model(&self) -> String {
+        let model = self.config.model.trim();
+        model.strip_prefix("models/").unwrap_or(model).to_string()
+    }
+
+    pub async fn test_connection(&self) -> Result<(), AppError> {
+        if self.config.api_key.trim().is_empty() {
+            return Err(AppError::other("未配置 Gemini API Key"));
+        }
+        let url = format!("{}/models/{}", self.endpoint(), self.model());
+        let response = self
+            .client
+            .get(&url)
+            .header("x-goog-api-key", self.config.api_key.trim())
+            .send()
+            .await
+            .map_err(|e| AppError::request_failed(&e, format!("请求失败: {}", e)))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        Err(AppError::provider(Some(status.as_u16()), gemini_status_error(status, &text), None))
+    }
+
+    /// batchEmbedContents 计算文本向量，结果与 texts 顺序一致
+    pub async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        if self.config.api_key.trim().is_empty() {
+            return Err(AppError::other("未配置 Gemini API Key"));
+        }
+        let model = model.trim().strip_prefix("models/").unwrap_or(model.trim());
+        let url = format!("{}/models/{}:batchEmbedContents", self.endpoint(), model);
+        let requests: Vec<Value> = texts
+            .iter()
+            .map(|text| json!({ "model": format!("models/{}", model), "content": { "parts": [{ "text": text }] } }))
+            .collect();
+        let response = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", self.config.api_key.trim())
+            .header("Content-Type", "application/json")
+            .json(&json!({ "requests": requests }))
+            .send()
+            .await
+            .map_err(|e| {
+                write_exchange_log("gemini-embed", &url, "(embeddings)", None, None, Some(&e.to_string()));
+                AppError::request_failed(&e, format!("请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
+        let text = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            write_exchange_log("gemini-embed", &url, "(embeddings)", Some(status), Some(&text), None);
+            return Err(AppError::provider(Some(status.as_u16()), gemini_status_error(status, &text), retry_after));
+        }
+        let json: Value = serde_json::from_str(&text).map_err(|e| format!("解析向量响应失败: {}", e))?;
+        let vectors: Vec<Vec<f32>> = json["embeddings"]
+            .as_array()
+            .map(|items| items.iter().map(|item| parse_embedding_values(&item["values"])).collect())
+            .unwrap_or_default();
+        if vectors.len() != texts.len() || vectors.iter().any(|vector| vector.is_empty()) {
+            return Err(AppError::other("向量数量与输入不一致"));
+        }
+        Ok(vectors)
+    }
+
+    pub async fn chat_with_history(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        history: Option<Vec<ChatHistoryMessage>>,
+    ) -> Result<String, AppError> {
+        self.chat_with_history_with_images(system_prompt, user_message, history, &[])
+            .await
+    }
+
+    /// image_urls 为 data URL（data:image/png;base64,...），其他地址 Gemini 无法直接读取，会被跳过
+    pub async fn chat_with_history_with_images(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        history: Option<Vec<ChatHistoryMessage>>,
+        image_urls: &[String],
+    ) -> Result<String, AppError> {
+        let mut messages = history_messages(history);
+        messages.push(user_message_with_images(user_message, image_urls));
+        let reply = self
+            .generate("gemini-chat", system_prompt, &messages, &[])
+            .await?;
+        reply.text.ok_or_else(|| AppError::other("没有返回内容"))
+    }
+
+    pub async fn analyze_images(&self, images_base64: &[String], prompt: &str) -> Result<String, AppError> {
+        let image_urls: Vec<String> = images_base64
+            .iter()
+            .map(|image_base64| format!("data:image/jpeg;base64,{}", image_base64))
+            .collect();
+        let messages = vec![user_message_with_images(prompt, &image_urls)];
+        let reply = self.generate("gemini-image", "", &messages, &[]).await?;
+        reply.text.ok_or_else(|| AppError::other("没有返回内容"))
+    }
+
+    /// 带 Tool Use 的对话；返回的 messages 与 OpenAI 兼容接口格式一致，供 run_tool_loop 继续使用
+    pub async fn chat_with_tools(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        history: Option<Vec<ChatHistoryMessage>>,
+        tools: Vec<Tool>,
+        image_urls: &[String],
+    ) -> Result<ChatWithToolsResult, AppError> {
+        let mut messages = history_messages(history);
+        messages.push(user_message_with_images(user_message, image_urls));
+        let reply = self
+            .generate("gemini-chat-tools", system_prompt, &messages, &tools)
+            .await?;
+        into_tools_result(reply, messages)
+    }
+
+    pub async fn continue_with_tool_results(
+        &self,
+        system_prompt: &str,
+        messages_so_far: Vec<Message>,
+        tool_results: Vec<(String, String)>,
+        tool_images: &[String],
+        tools: Vec<Tool>,
+    ) -> Result<ChatWithToolsResult, AppError> {
+        let mut messages = messages_so_far;
+        for (tool_call_id, tool_result) in tool_results {
+            messages.push(Message {
+                role: "tool".to_string(),
+                content: Some(MessageContent::Text(tool_result)),
+                tool_calls: None,
+                tool_call_id: Some(tool_call_id),
+            });
+        }
+        let reply = match ApiClient::tool_images_message(tool_images) {
+            Some(images) => {
+                let mut request_messages = messages.clone();
+                request_messages.push(images);
+                self.generate("gemini-chat-tool-result", system_prompt, &request_messages, &tools)
+                    .await?
+            }
+            None => {
+                self.generate("gemini-chat-tool-result", system_prompt, &messages, &tools)
+                    .await?
+            }
+        };
+        into_tools_result(reply, messages)
+    }
+
+    async fn generate(
+        &self,
+        log_prefix: &str,
+        system_prompt: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<GeminiReply, AppError> {
+        if self.config.api_key.trim().is_empty() {
+            return Err(AppError::other("未配置 Gemini API Key"));
+        }
+        let url = format!("{}/models/{}:generateContent", self.endpoint(), self.model());
+
+        let mut body = json!({
+            "contents": build_contents(messages),
+            "generationConfig": { "maxOutputTokens": GEMINI_MAX_OUTPUT_TOKENS },
+        });
+        if let Some(temperature) = self.config.temperature {
+            body["generationConfig"]["temperature"] = json!(temperature);
+        }
+        let system_text = messages
+            .iter()
+            .filter(|message| message.role == "system")
+            .map(|message| message_text(message.content.as_ref()))
+            .chain(std::iter::once(system_prompt.to_string()))
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if !system_text.is_empty() {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system_text }] });
+        }
+        if !tools.is_empty() {
+            let declarations: Vec<Value> = tools.iter().map(function_declaration).collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        let request_json = serde_json::to_string_pretty(&body)
+            .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
+        let response = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", self.config.api_key.trim())
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| {
+                write_exchange_log(log_prefix, &url, &request_json, None, None, Some(&e.to_string()));
+                AppError::request_failed(&e, format!("请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+        let retry_after = retry_after_secs(response.headers());
+        let text = response.text().await.unwrap_or_default();
+        write_exchange_log(log_prefix, &url, &request_json, Some(status), Some(&text), None);
+        record_model_usage("gemini", &self.config.model, self.config.pricing.as_ref(), &text);
+
+        if !status.is_success() {
+            return Err(AppError::provider(Some(status.as_u16()), gemini_status_error(status, &text), retry_after));
+        }
+        parse_reply(&text)
+    }
+}
+
+fn build_gemini_client() -> Client {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(GEMINI_CONNECT_TIMEOUT_SECS))
+        .timeout(Duration::from_secs(GEMINI_REQUEST_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_else(|_| Client::new())
+}
+
+fn gemini_status_error(status: reqwest::StatusCode, text: &str) -> String {
+    let message = serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|json| json["error"]["message"].as_str().map(|m| m.to_string()))
+        .unwrap_or_else(|| text.chars().take(500).collect());
+    format!("Gemini 错误 {}: {}", status, message)
+}
+
+fn history_messages(history: Option<Vec<ChatHistoryMessage>>) -> Vec<Message> {
+    history
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(history_message_to_message)
+        .collect()
+}
+
+fn user_message_with_images(text: &str, image_urls: &[String]) -> Message {
+    let content = if image_urls.is_empty() {
+        MessageContent::Text(text.to_string())
+    } else {
+        // 与 OpenAI 兼容格式一致，发送时再转换为 inline_data
+        let mut parts = vec![ContentPart {
+            content_type: "text".to_string(),
+            text: Some(text.to_string()),
+            image_url: None,
+        }];
+        parts.extend(image_urls.iter().map(|url| ContentPart {
+            content_type: "image_url".to_string(),
+            text: None,
+            image_url: Some(ImageUrl { url: url.clone() }),
+        }));
+        MessageContent::Parts(parts)
+    };
+    Message {
+        role: "user".to_string(),
+        content: Some(content),
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+fn message_text(content: Option<&MessageContent>) -> String {
+    match content {
+        Some(MessageContent::Text(text)) => text.clone(),
+        Some(MessageContent::Parts(parts)) => parts
+            .iter()
+            .filter_map(|part| part.text.clone())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        None => String::new(),
+    }
+}
+
+/// data:image/png;base64,xxx -> inline_data
+fn inline_data_part(url: &str) -> Option<Value> {
+    let rest = url.strip_prefix("data:")?;
+    let (mime, data) = rest.split_once(";base64,")?;
+    Some(json!({ "inline_data": { "mime_type": mime, "data": data } }))
+}
+
+fn content_parts(content: Option<&MessageContent>) -> Vec<Value> {
+    match content {
+        Some(MessageContent::Text(text)) if !text.is_empty() => vec![json!({ "text": text })],
+        Some(MessageContent::Parts(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.content_type.as_str() {
+                "text" => part.text.as_ref().map(|text| json!({ "text": text })),
+                "image_url" => part.image_url.as_ref().and_then(|image| inline_data_part(&image.url)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// OpenAI 格式消息转换为 Gemini contents：assistant -> model，tool 结果 -> functionResponse；
+/// 相邻同角色的消息合并，保证同一轮的多个函数结果放在一起
+fn build_contents(messages: &[Message]) -> Vec<Value> {
+    let mut call_names: HashMap<String, String> = HashMap::new();
+    let mut contents: Vec<(String, Vec<Value>)> = Vec::new();
+
+    for message in messages {
+        let (role, parts) = match message.role.as_str() {
+            "system" => continue,
+            "assistant" => {
+                let mut parts = content_parts(message.content.as_ref());
+                for call in message.tool_calls.iter().flatten() {
+                    call_names.insert(call.id.clone(), call.function.name.clone());
+                    let args: Value = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| json!({}));
+                    let mut part = json!({ "functionCall": { "name": call.function.name, "args": args } });
+                    if let Some(signature) = &call.thought_signature {
+                        part["thoughtSignature"] = json!(signature);
+                    }
+                    parts.push(part);
+                }
+                ("model", parts)
+            }
+            "tool" => {
+                let id = message.tool_call_id.clone().unwrap_or_default();
+                let name = call_names.get(&id).cloned().unwrap_or_else(|| id.clone());
+                let result = message_text(message.content.as_ref());
+                (
+                    "user",
+                    vec![json!({
+                        "functionResponse": { "name": name, "response": { "content": result } }
+                    })],
+                )
+            }
+            _ => ("user", content_parts(message.content.as_ref())),
+        };
+        if parts.is_empty() {
+            continue;
+        }
+        match contents.last_mut() {
+            Some((last_role, last_parts)) if last_role == role => last_parts.extend(parts),
+            _ => contents.push((role.to_string(), parts)),
+        }
+    }
+
+    contents
+        .into_iter()
+        .map(|(role, parts)| json!({ "role": role, "parts": parts }))
+        .collect()
+}
+
+fn function_declaration(tool: &Tool) -> Value {
+    let mut declaration = json!({
+        "name": tool.function.name,
+        "description": tool.function.description,
+    });
+    let parameters = sanitize_schema(&tool.function.parameters);
+    // 没有参数的函数不能带空的 object schema
+    let has_properties = parameters
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .map_or(false, |p| !p.is_empty());
+    if has_properties {
+        declaration["parameters"] = parameters;
+    }
+    declaration
+}
+
+/// 去掉 Gemini 不支持的 JSON Schema 字段；"type": ["string", "null"] 转为 nullable
+fn sanitize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => {
+            let mut result = Map::new();
+            for (key, value) in map {
+                if UNSUPPORTED_SCHEMA_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                if key == "type" {
+                    if let Value::Array(types) = value {
+                        let main = types.iter().find(|t| t.as_str() != Some("null"));
+                        result.insert("type".to_string(), main.cloned().unwrap_or(json!("string")));
+                        if types.iter().any(|t| t.as_str() == Some("null")) {
+                            result.insert("nullable".to_string(), json!(true));
+                        }
+                        continue;
+                    }
+                }
+                // properties 的键是参数名，不能当作 schema 关键字过滤
+                let value = if key == "properties" {
+                    match value {
+                        Value::Object(props) => Value::Object(
+                            props
+                                .iter()
+                                .map(|(name, prop)| (name.clone(), sanitize_schema(prop)))
+                                .collect(),
+                        ),
+                        other => other.clone(),
+                    }
+                } else {
+                    sanitize_schema(value)
+                };
+                result.insert(key.clone(), value);
+            }
+            Value::Object(result)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sanitize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+fn parse_reply(text: &str) -> Result<GeminiReply, AppError> {
+    let json: Value = serde_json::from_str(text).map_err(|e| format!("解析响应失败: {}", e))?;
+    if let Some(message) = json["error"]["message"].as_str() {
+        return Err(AppError::provider(None, format!("Gemini 错误: {}", message), None));
+    }
+    let Some(candidate) = json["candidates"].as_array().and_then(|c| c.first()) else {
+        return match json["promptFeedback"]["blockReason"].as_str() {
+            Some(reason) => Err(AppError::other(format!("Gemini 拒绝了请求: {}", reason))),
+            None => Err(AppError::other("Gemini 响应缺少 candidates")),
+        };
+    };
+
+    let mut texts = Vec::new();
+    let mut tool_calls = Vec::new();
+    let stamp = Local::now().timestamp_millis();
+    for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+        if part["thought"].as_bool() == Some(true) {
+            continue;
+        }
+        if let Some(call) = part.get("functionCall") {
+            let name = call["name"].as_str().unwrap_or_default().to_string();
+            let id = call["id"]
+                .as_str()
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| format!("gemini_call_{}_{}", stamp, tool_calls.len()));
+            let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+            tool_calls.push(ToolCall {
+                id,
+                call_type: "function".to_string(),
+                function: ToolCallFunction {
+                    name,
+                    arguments: args.to_string(),
+                },
+                thought_signature: part["thoughtSignature"].as_str().map(|s| s.to_string()),
+            });
+        } else if let Some(text) = part["text"].as_str() {
+            texts.push(text.to_string());
+        }
+    }
+
+    let text = texts.join("");
+    if text.is_empty() && tool_calls.is_empty() {
+        let reason = candidate["finishReason"].as_str().unwrap_or("UNKNOWN");
+        return Err(AppError::other(format!("Gemini 没有返回内容（finishReason: {}）", reason)));
+    }
+    Ok(GeminiReply {
+        text: if text.is_empty() { None } else { Some(text) },
+        tool_calls,
+    })
+}
+
+fn into_tools_result(reply: GeminiReply, mut messages: Vec<Message>) -> Result<ChatWithToolsResult, AppError> {
+    if reply.tool_calls.is_empty() {
+        return reply
+            .text
+            .map(ChatWithToolsResult::Text)
+            .ok_or_else(|| AppError::other("没有返回内容"));
+    }
+    messages.push(Message {
+        role: "assistant".to_string(),
+        content: reply.text.map(MessageContent::Text),
+        tool_calls: Some(reply.tool_calls.clone()),
+        tool_call_id: None,
+    });
+    Ok(ChatWithToolsResult::ToolCalls {
+        calls: reply.tool_calls,
+        messages,
+    })
+}
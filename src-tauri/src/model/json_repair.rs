@@ -0,0 +1,254 @@
+use super::ModelManager;
+use crate::storage::ModelConfig;
+use serde_json::Value;
+
+/// 宽松解析模型返回的 JSON：去掉代码块和前后多余文字，修复常见语法错误
+pub fn parse_json_lenient(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(json) = serde_json::from_str::<Value>(text) {
+        return Some(json);
+    }
+
+    let mut candidates = Vec::new();
+    if let Some(inner) = extract_fenced_json(text) {
+        candidates.push(inner);
+    }
+    if let Some(inner) = extract_balanced_json(text) {
+        candidates.push(inner);
+    }
+    if let Some(start) = text.find(['{', '[']) {
+        // 没有闭合的输出（被截断）从第一个括号开始尝试补全
+        candidates.push(text[start..].to_string());
+    }
+
+    for candidate in &candidates {
+        if let Ok(json) = serde_json::from_str::<Value>(candidate) {
+            return Some(json);
+        }
+    }
+    candidates
+        .iter()
+        .find_map(|candidate| serde_json::from_str::<Value>(&repair_json_text(candidate)).ok())
+}
+
+fn extract_fenced_json(text: &str) -> Option<String> {
+    if let Some(start) = text.find("```json") {
+        let rest = &text[start + 7..];
+        return extract_fence_body(rest);
+    }
+
+    if let Some(start) = text.find("```") {
+        let rest = &text[start + 3..];
+        return extract_fence_body(rest);
+    }
+
+    None
+}
+
+fn extract_fence_body(text: &str) -> Option<String> {
+    let end = text.find("```")?;
+    let mut body = text[..end].trim().to_string();
+    if let Some(stripped) = body.strip_prefix("json") {
+        body = stripped.trim_start().to_string();
+    }
+    Some(body)
+}
+
+/// 从第一个 { 开始找到与之配对的 }，忽略字符串内的括号，丢弃后面的多余文字
+fn extract_balanced_json(text: &str) -> Option<String> {
+    let start = text.find('{')?;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, ch) in text[start..].char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(text[start..start + offset + 1].to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 修复常见错误：注释、尾随逗号、Python 字面量、字符串内的裸换行、缺失的闭合括号
+fn repair_json_text(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut stack: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = 0usize;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        if in_string {
+            match ch {
+                _ if escaped => {
+                    escaped = false;
+                    out.push(ch);
+                }
+                '\\' => {
+                    escaped = true;
+                    out.push(ch);
+                }
+                '"' => {
+                    in_string = false;
+                    out.push(ch);
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => {}
+                '\t' => out.push_str("\\t"),
+                _ => out.push(ch),
+            }
+            i += 1;
+            continue;
+        }
+
+        match ch {
+            '"' => {
+                in_string = true;
+                out.push(ch);
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '{' => {
+                stack.push('}');
+                out.push(ch);
+            }
+            '[' => {
+                stack.push(']');
+                out.push(ch);
+            }
+            '}' | ']' => {
+                trim_trailing_comma(&mut out);
+                if stack.last() == Some(&ch) {
+                    stack.pop();
+                }
+                out.push(ch);
+                if stack.is_empty() {
+                    // 顶层结构已结束，丢弃后面的文字
+                    return out;
+                }
+            }
+            _ if ch.is_ascii_alphabetic() => {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                out.push_str(match word.as_str() {
+                    "True" => "true",
+                    "False" => "false",
+                    "None" => "null",
+                    other => other,
+                });
+                continue;
+            }
+            _ => out.push(ch),
+        }
+        i += 1;
+    }
+
+    if in_string {
+        out.push('"');
+    }
+    while let Some(close) = stack.pop() {
+        trim_trailing_comma(&mut out);
+        out.push(close);
+    }
+    out
+}
+
+fn trim_trailing_comma(out: &mut String) {
+    let trimmed_len = out.trim_end().len();
+    if out[..trimmed_len].ends_with(',') {
+        out.truncate(trimmed_len - 1);
+    }
+}
+
+impl ModelManager {
+    /// 本地修复失败时，让模型按原输出重新给出合法 JSON（只重试一次）
+    pub async fn repair_json_response(
+        &self,
+        config: &ModelConfig,
+        raw: &str,
+        expected: &str,
+    ) -> Option<Value> {
+        if let Some(json) = parse_json_lenient(raw) {
+            return Some(json);
+        }
+        let raw: String = raw.chars().take(8000).collect();
+        let prompt = format!(
+            "下面的输出本应是一个 JSON 对象（{}），但无法解析。请根据其内容输出修正后的 JSON，只输出 JSON，不要任何解释或代码块标记。\n\n原始输出：\n{}",
+            expected, raw
+        );
+        let repaired = self
+            .chat_with_system_prompt(config, "你负责把格式错误的输出修正为合法 JSON。", &prompt, None)
+            .await
+            .ok()?;
+        parse_json_lenient(&repaired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn strips_code_fences() {
+        let text = "```json\n{\"summary\": \"编辑代码\"}\n```";
+        assert_eq!(parse_json_lenient(text), Some(json!({ "summary": "编辑代码" })));
+    }
+
+    #[test]
+    fn drops_surrounding_text_without_breaking_on_braces_in_strings() {
+        let text = "分析结果如下：{\"summary\": \"a } b\", \"n\": 1} 以上。";
+        assert_eq!(parse_json_lenient(text), Some(json!({ "summary": "a } b", "n": 1 })));
+    }
+
+    #[test]
+    fn repairs_trailing_commas_comments_and_python_literals() {
+        let text = "{\n  // 注释\n  \"ok\": True,\n  \"err\": None,\n  \"list\": [1, 2,],\n  \"text\": \"True\",\n}";
+        assert_eq!(
+            parse_json_lenient(text),
+            Some(json!({ "ok": true, "err": null, "list": [1, 2], "text": "True" }))
+        );
+    }
+
+    #[test]
+    fn escapes_raw_newlines_inside_strings() {
+        let text = "{\"detail\": \"第一行\n第二行\"}";
+        assert_eq!(parse_json_lenient(text), Some(json!({ "detail": "第一行\n第二行" })));
+    }
+
+    #[test]
+    fn closes_truncated_output() {
+        assert_eq!(parse_json_lenient("{\"items\": [1, 2,"), Some(json!({ "items": [1, 2] })));
+        assert_eq!(parse_json_lenient("{\"summary\": \"写到一半"), Some(json!({ "summary": "写到一半" })));
+    }
+
+    #[test]
+    fn returns_none_without_json() {
+        assert_eq!(parse_json_lenient("模型没有返回任何结构化内容"), None);
+    }
+}
@@ -1,15 +1,27 @@
 mod api;
 mod error;
+mod gemini;
+mod json_repair;
 mod ollama;
+mod preflight;
+mod retry;
+pub mod tokenizer;
 pub mod traits;
+mod usage;
 
 pub use api::*;
 pub use error::*;
+pub use gemini::*;
+pub use json_repair::*;
 pub use ollama::*;
+pub use retry::retry_delay;
+pub use usage::with_usage_feature;
 
-use crate::storage::ModelConfig;
+use crate::error::AppError;
+use crate::storage::{ModelConfig, ModelProfile};
 use crate::commands::ChatHistoryMessage;
 use crate::skills::SkillMetadata;
+use std::time::Instant;
 
 pub struct ModelManager;
 
@@ -18,17 +30,69 @@ impl ModelManager {
         Self
     }
 
-    pub async fn test_connection(&self, config: &ModelConfig) -> Result<(), String> {
+    /// 按名称解析模型配置：匹配命名配置时应用其覆盖项；
+    /// 否则视为当前提供者下的模型 ID（如 skill 的 model 字段写 gpt-4o-mini）
+    pub fn resolve_model_config(config: &ModelConfig, name: Option<&str>) -> ModelConfig {
+        let name = match name.map(|n| n.trim()) {
+            Some(name) if !name.is_empty() && !name.eq_ignore_ascii_case("default") => name,
+            _ => return config.clone(),
+        };
+
+        let mut resolved = config.clone();
+        match config
+            .profiles
+            .iter()
+            .find(|profile| profile.name.trim().eq_ignore_ascii_case(name))
+        {
+            Some(profile) => apply_model_profile(&mut resolved, profile),
+            None => match resolved.provider.as_str() {
+                "ollama" => resolved.ollama.model = name.to_string(),
+                "gemini" => resolved.gemini.model = name.to_string(),
+                _ => {
+                    // Azure 按部署路由，名称同时作为部署名
+                    if resolved.api.api_type.eq_ignore_ascii_case("azure") {
+                        resolved.api.azure_deployment = name.to_string();
+                    }
+                    resolved.api.model = name.to_string();
+                }
+            },
+        }
+        resolved
+    }
+
+    /// 是否支持 Tool Use（工具循环、MCP）
+    pub fn supports_tools(config: &ModelConfig) -> bool {
+        matches!(config.provider.as_str(), "api" | "gemini")
+    }
+
+    /// 预加载本地模型；非 Ollama 提供者无需预热，返回 false
+    pub async fn warm_up_model(&self, config: &ModelConfig) -> Result<bool, AppError> {
+        if config.provider != "ollama" {
+            return Ok(false);
+        }
+        let ollama_client = OllamaClient::new(&config.ollama);
+        if ollama_client.is_model_loaded().await.unwrap_or(false) {
+            return Ok(true);
+        }
+        ollama_client.warm_up().await?;
+        Ok(true)
+    }
+
+    pub async fn test_connection(&self, config: &ModelConfig) -> Result<(), AppError> {
         match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 api_client.test_connection_with_fallback().await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client.test_connection().await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 ollama_client.test_connection().await
             }
-            _ => Err("未知的模型提供者".to_string()),
+            _ => Err(AppError::other("未知的模型提供者")),
         }
     }
 
@@ -37,7 +101,7 @@ impl ModelManager {
         config: &ModelConfig,
         context: &str,
         message: &str,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         let system_prompt = format!(
             r#"你是一个屏幕监控助手，帮助用户回顾和理解他们的操作历史。
 
@@ -47,17 +111,26 @@ impl ModelManager {
             context
         );
 
-        match config.provider.as_str() {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 api_client.chat(&system_prompt, message).await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client.chat_with_history(&system_prompt, message, None).await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 ollama_client.chat(&system_prompt, message).await
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
 
@@ -68,7 +141,7 @@ impl ModelManager {
         context: &str,
         message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         let system_prompt = format!(
             r#"你是一个屏幕监控助手，帮助用户回顾和理解他们的操作历史。
 
@@ -78,17 +151,26 @@ impl ModelManager {
             context
         );
 
-        match config.provider.as_str() {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 api_client.chat_with_history(&system_prompt, message, history).await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client.chat_with_history(&system_prompt, message, history).await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 ollama_client.chat_with_history(&system_prompt, message, history).await
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
     pub async fn chat_with_history_with_images(
@@ -99,7 +181,7 @@ impl ModelManager {
         history: Option<Vec<ChatHistoryMessage>>,
         image_urls: Vec<String>,
         image_base64: Vec<String>,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         let system_prompt = format!(
             r#"你是一个屏幕监控助手，帮助用户回顾和理解他们的操作历史。
 
@@ -109,21 +191,32 @@ impl ModelManager {
             context
         );
 
-        match config.provider.as_str() {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 api_client
                     .chat_with_history_with_images(&system_prompt, message, history, &image_urls)
                     .await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client
+                    .chat_with_history_with_images(&system_prompt, message, history, &image_urls)
+                    .await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 ollama_client
                     .chat_with_history_with_images(&system_prompt, message, history, &image_base64)
                     .await
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
     /// 使用自定义 system prompt 进行对话（用于 skills）
@@ -133,18 +226,27 @@ impl ModelManager {
         system_prompt: &str,
         message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
-    ) -> Result<String, String> {
-        match config.provider.as_str() {
+    ) -> Result<String, AppError> {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 api_client.chat_with_history(system_prompt, message, history).await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client.chat_with_history(system_prompt, message, history).await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 ollama_client.chat_with_history(system_prompt, message, history).await
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
     pub async fn chat_with_system_prompt_with_images(
@@ -155,25 +257,36 @@ impl ModelManager {
         history: Option<Vec<ChatHistoryMessage>>,
         image_urls: Vec<String>,
         image_base64: Vec<String>,
-    ) -> Result<String, String> {
-        match config.provider.as_str() {
+    ) -> Result<String, AppError> {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 api_client
                     .chat_with_history_with_images(system_prompt, message, history, &image_urls)
                     .await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client
+                    .chat_with_history_with_images(system_prompt, message, history, &image_urls)
+                    .await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 ollama_client
                     .chat_with_history_with_images(system_prompt, message, history, &image_base64)
                     .await
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
-    /// 带 Tool Use 的对话（仅 API 模式支持）
+    /// 带 Tool Use 的对话（API 和 Gemini 支持）
     pub async fn chat_with_tools(
         &self,
         config: &ModelConfig,
@@ -181,7 +294,7 @@ impl ModelManager {
         message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
         available_skills: &[SkillMetadata],
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         let system_prompt = format!(
             r#"你是一个屏幕监控助手，帮助用户回顾和理解他们的操作历史。
 
@@ -216,7 +329,7 @@ impl ModelManager {
         available_skills: &[SkillMetadata],
         image_urls: Vec<String>,
         image_base64: Vec<String>,
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         let system_prompt = format!(
             r#"你是一个屏幕监控助手，帮助用户回顾和理解他们的操作历史。
 
@@ -251,7 +364,7 @@ impl ModelManager {
         message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
         available_skills: &[SkillMetadata],
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         self.chat_with_tools_with_system_prompt_filtered(config, system_prompt, message, history, available_skills, &None).await
     }
 
@@ -263,8 +376,10 @@ impl ModelManager {
         history: Option<Vec<ChatHistoryMessage>>,
         available_skills: &[SkillMetadata],
         allowed_tools: &Option<Vec<String>>,
-    ) -> Result<ChatWithToolsResult, String> {
-        match config.provider.as_str() {
+    ) -> Result<ChatWithToolsResult, AppError> {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 let tools = ApiClient::create_skill_tools(available_skills, allowed_tools);
@@ -272,6 +387,13 @@ impl ModelManager {
                     .chat_with_tools(system_prompt, message, history, tools)
                     .await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                let tools = ApiClient::create_skill_tools(available_skills, allowed_tools);
+                gemini_client
+                    .chat_with_tools(system_prompt, message, history, tools, &[])
+                    .await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 let result = ollama_client
@@ -279,8 +401,11 @@ impl ModelManager {
                     .await?;
                 Ok(ChatWithToolsResult::Text(result))
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
     pub async fn chat_with_tools_with_system_prompt_with_images(
@@ -292,7 +417,7 @@ impl ModelManager {
         available_skills: &[SkillMetadata],
         image_urls: Vec<String>,
         image_base64: Vec<String>,
-    ) -> Result<ChatWithToolsResult, String> {
+    ) -> Result<ChatWithToolsResult, AppError> {
         self.chat_with_tools_with_system_prompt_with_images_filtered(
             config, system_prompt, message, history, available_skills, image_urls, image_base64, &None
         ).await
@@ -308,8 +433,10 @@ impl ModelManager {
         image_urls: Vec<String>,
         image_base64: Vec<String>,
         allowed_tools: &Option<Vec<String>>,
-    ) -> Result<ChatWithToolsResult, String> {
-        match config.provider.as_str() {
+    ) -> Result<ChatWithToolsResult, AppError> {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 let tools = ApiClient::create_skill_tools(available_skills, allowed_tools);
@@ -317,6 +444,13 @@ impl ModelManager {
                     .chat_with_tools_with_images(system_prompt, message, history, tools, &image_urls)
                     .await
             }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                let tools = ApiClient::create_skill_tools(available_skills, allowed_tools);
+                gemini_client
+                    .chat_with_tools(system_prompt, message, history, tools, &image_urls)
+                    .await
+            }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
                 let result = ollama_client
@@ -324,8 +458,11 @@ impl ModelManager {
                     .await?;
                 Ok(ChatWithToolsResult::Text(result))
             }
-            _ => Err("未知的模型提供者".to_string()),
-        }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
     pub async fn continue_with_tool_results(
@@ -335,8 +472,8 @@ impl ModelManager {
         messages_so_far: Vec<api::Message>,
         tool_results: Vec<(String, String)>,
         available_skills: &[SkillMetadata],
-    ) -> Result<ChatWithToolsResult, String> {
-        self.continue_with_tool_results_filtered(config, system_prompt, messages_so_far, tool_results, available_skills, &None).await
+    ) -> Result<ChatWithToolsResult, AppError> {
+        self.continue_with_tool_results_filtered(config, system_prompt, messages_so_far, tool_results, &[], available_skills, &None).await
     }
 
     pub async fn continue_with_tool_results_filtered(
@@ -345,20 +482,106 @@ impl ModelManager {
         system_prompt: &str,
         messages_so_far: Vec<api::Message>,
         tool_results: Vec<(String, String)>,
+        tool_images: &[String],
         available_skills: &[SkillMetadata],
         allowed_tools: &Option<Vec<String>>,
-    ) -> Result<ChatWithToolsResult, String> {
-        match config.provider.as_str() {
+    ) -> Result<ChatWithToolsResult, AppError> {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
                 let tools = ApiClient::create_skill_tools(available_skills, allowed_tools);
                 api_client
-                    .continue_with_tool_results(system_prompt, messages_so_far, tool_results, tools)
+                    .continue_with_tool_results(system_prompt, messages_so_far, tool_results, tool_images, tools)
                     .await
             }
-            "ollama" => Err("Ollama 不支持 tool use".to_string()),
-            _ => Err("未知的模型提供者".to_string()),
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                let tools = ApiClient::create_skill_tools(available_skills, allowed_tools);
+                gemini_client
+                    .continue_with_tool_results(system_prompt, messages_so_far, tool_results, tool_images, tools)
+                    .await
+            }
+            "ollama" => Err(AppError::other("Ollama 不支持 tool use")),
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
+    }
+
+    /// 按提供者使用的 embedding 模型，model 为空时取默认模型
+    pub fn embedding_model(config: &ModelConfig, model: &str) -> String {
+        match model.trim() {
+            "" => match config.provider.as_str() {
+                "gemini" => "text-embedding-004",
+                "ollama" => "nomic-embed-text",
+                _ => "text-embedding-3-small",
+            }
+            .to_string(),
+            model => model.to_string(),
+        }
+    }
+
+    /// 计算文本向量，结果与 texts 顺序一致
+    pub async fn embed_texts(&self, config: &ModelConfig, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let model = Self::embedding_model(config, model);
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
+            "api" => ApiClient::new(&config.api).embed(&model, texts).await,
+            "gemini" => GeminiClient::new(&config.gemini).embed(&model, texts).await,
+            "ollama" => OllamaClient::new(&config.ollama).embed(&model, texts).await,
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
+    }
+
+    /// 语音转文字，目前只有 API 提供者（OpenAI 兼容接口）支持
+    pub async fn transcribe_audio(
+        &self,
+        config: &ModelConfig,
+        model: &str,
+        audio: &[u8],
+        file_name: &str,
+        language: &str,
+    ) -> Result<String, AppError> {
+        if config.provider != "api" {
+            return Err(AppError::other("当前模型提供者不支持语音转写，请改用 API 提供者或本地 whisper.cpp"));
+        }
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = ApiClient::new(&config.api)
+            .transcribe(model, audio, file_name, language)
+            .await;
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
+    }
+
+    /// 文字转语音（mp3），目前只有 API 提供者支持
+    pub async fn synthesize_speech(
+        &self,
+        config: &ModelConfig,
+        model: &str,
+        voice: &str,
+        text: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        if config.provider != "api" {
+            return Err(AppError::other("当前模型提供者不支持语音合成，请改用系统语音"));
         }
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = ApiClient::new(&config.api).synthesize_speech(model, voice, text).await;
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
     }
 
     pub async fn analyze_image(
@@ -366,17 +589,117 @@ impl ModelManager {
         config: &ModelConfig,
         image_base64: &str,
         prompt: &str,
-    ) -> Result<String, String> {
-        match config.provider.as_str() {
+    ) -> Result<String, AppError> {
+        self.analyze_images(config, &[image_base64.to_string()], prompt).await
+    }
+
+    /// 多张图片合并为一次请求（批量截屏分析）
+    pub async fn analyze_images(
+        &self,
+        config: &ModelConfig,
+        images_base64: &[String],
+        prompt: &str,
+    ) -> Result<String, AppError> {
+        crate::faults::inject_model_fault().await?;
+        let started = Instant::now();
+        let result = match config.provider.as_str() {
             "api" => {
                 let api_client = ApiClient::new(&config.api);
-                api_client.analyze_image(image_base64, prompt).await
+                api_client.analyze_images(images_base64, prompt).await
+            }
+            "gemini" => {
+                let gemini_client = GeminiClient::new(&config.gemini);
+                gemini_client.analyze_images(images_base64, prompt).await
             }
             "ollama" => {
                 let ollama_client = OllamaClient::new(&config.ollama);
-                ollama_client.analyze_image(image_base64, prompt).await
+                ollama_client.analyze_images(images_base64, prompt).await
+            }
+            _ => Err(AppError::other("未知的模型提供者")),
+        };
+        crate::metrics::record_model_request(started.elapsed(), &result);
+        audit_model_request(config, &result);
+        result
+    }
+}
+
+/// 合规审计：记录哪个模型端点收到了数据
+fn audit_model_request<T>(config: &ModelConfig, result: &Result<T, AppError>) {
+    if !crate::storage::audit_enabled() {
+        return;
+    }
+    let (endpoint, model) = match config.provider.as_str() {
+        "api" => (&config.api.endpoint, &config.api.model),
+        "gemini" => (&config.gemini.endpoint, &config.gemini.model),
+        "ollama" => (&config.ollama.endpoint, &config.ollama.model),
+        _ => return,
+    };
+    crate::storage::record_audit_event(
+        "model_request",
+        serde_json::json!({
+            "provider": config.provider,
+            "endpoint": endpoint,
+            "model": model,
+            "feature": usage::current_usage_feature(),
+            "success": result.is_ok(),
+        }),
+    );
+}
+
+fn apply_model_profile(config: &mut ModelConfig, profile: &ModelProfile) {
+    let non_empty = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+    };
+
+    if let Some(provider) = non_empty(&profile.provider) {
+        config.provider = provider;
+    }
+    match config.provider.as_str() {
+        "ollama" => {
+            if let Some(model) = non_empty(&profile.model) {
+                config.ollama.model = model;
+            }
+            if let Some(endpoint) = non_empty(&profile.endpoint) {
+                config.ollama.endpoint = endpoint;
+            }
+        }
+        "gemini" => {
+            if let Some(model) = non_empty(&profile.model) {
+                config.gemini.model = model;
+            }
+            if let Some(endpoint) = non_empty(&profile.endpoint) {
+                config.gemini.endpoint = endpoint;
+            }
+            if let Some(api_key) = non_empty(&profile.api_key) {
+                config.gemini.api_key = api_key;
+            }
+        }
+        _ => {
+            if let Some(model) = non_empty(&profile.model) {
+                config.api.model = model;
+            }
+            if let Some(endpoint) = non_empty(&profile.endpoint) {
+                config.api.endpoint = endpoint;
+            }
+            if let Some(api_key) = non_empty(&profile.api_key) {
+                config.api.api_key = api_key;
+            }
+            if let Some(request_format) = non_empty(&profile.request_format) {
+                config.api.request_format = request_format;
+            }
+            if let Some(effort) = non_empty(&profile.reasoning_effort) {
+                config.api.reasoning_effort = effort;
+            }
+            if let Some(verbosity) = non_empty(&profile.verbosity) {
+                config.api.verbosity = verbosity;
+            }
+            if let Some(deployment) = non_empty(&profile.deployment) {
+                config.api.azure_deployment = deployment;
             }
-            _ => Err("未知的模型提供者".to_string()),
         }
     }
 }
@@ -1,5 +1,7 @@
+use crate::error::AppError;
 use crate::storage::{OllamaConfig, StorageManager};
 use crate::commands::ChatHistoryMessage;
+use super::usage::record_model_usage;
 use chrono::Local;
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
@@ -20,6 +22,16 @@ struct GenerateRequest {
     system: Option<String>,
     images: Option<Vec<String>>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct PsResponse {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
 }
 
 #[derive(Deserialize)]
@@ -27,6 +39,12 @@ struct GenerateResponse {
     response: String,
 }
 
+#[derive(Deserialize)]
+struct EmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<Vec<f32>>,
+}
+
 #[derive(Deserialize)]
 struct TagsResponse {
     models: Vec<ModelInfo>,
@@ -45,7 +63,117 @@ impl OllamaClient {
         }
     }
 
-    pub async fn test_connection(&self) -> Result<(), String> {
+    fn options(&self) -> Option<serde_json::Value> {
+        self.config
+            .temperature
+            .map(|temperature| serde_json::json!({ "temperature": temperature }))
+    }
+
+    /// keep_alive 可以是时长字符串（30m）或秒数（-1 表示永久常驻）
+    fn keep_alive(&self) -> Option<serde_json::Value> {
+        let value = self.config.keep_alive.trim();
+        if value.is_empty() {
+            return None;
+        }
+        Some(match value.parse::<i64>() {
+            Ok(seconds) => serde_json::Value::from(seconds),
+            Err(_) => serde_json::Value::from(value),
+        })
+    }
+
+    /// 模型是否已加载到内存（/api/ps）
+    pub async fn is_model_loaded(&self) -> Result<bool, AppError> {
+        let url = format!("{}/api/ps", self.config.endpoint);
+        let response = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(OLLAMA_CONNECT_TIMEOUT_SECS))
+            .send()
+            .await
+            .map_err(|e| AppError::request_failed(&e, format!("连接 Ollama 失败: {}", e)))?;
+        let ps: PsResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("解析响应失败: {}", e))?;
+        Ok(ps.models.iter().any(|m| m.name.starts_with(&self.config.model)))
+    }
+
+    /// 预加载模型：发送空 prompt，Ollama 只加载模型不生成内容
+    pub async fn warm_up(&self) -> Result<(), AppError> {
+        let url = format!("{}/api/generate", self.config.endpoint);
+        let request = GenerateRequest {
+            model: self.config.model.clone(),
+            prompt: String::new(),
+            system: None,
+            images: None,
+            stream: false,
+            keep_alive: self.keep_alive(),
+            options: self.options(),
+        };
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(response) => response,
+            Err(e) => return Err(self.describe_request_error(&e).await),
+        };
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AppError::provider(Some(status.as_u16()), format!("Ollama 错误 {}: {}", status, text), None));
+        }
+        Ok(())
+    }
+
+    /// 超时时区分"模型仍在加载"和一般的请求失败
+    async fn describe_request_error(&self, err: &reqwest::Error) -> AppError {
+        let message = if err.is_timeout() {
+            match self.is_model_loaded().await {
+                Ok(false) => format!(
+                    "Ollama 模型 {} 仍在加载中（首次加载或空闲后被卸载较慢），请稍后重试，或调大 keep_alive 让模型常驻内存",
+                    self.config.model
+                ),
+                _ => format!("Ollama 请求超时: {}", err),
+            }
+        } else if err.is_connect() {
+            format!("连接 Ollama 失败，请确认 Ollama 已启动: {}", err)
+        } else {
+            format!("请求失败: {}", err)
+        };
+        AppError::request_failed(err, message)
+    }
+
+    /// /api/embed 计算文本向量，结果与 texts 顺序一致
+    pub async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let url = format!("{}/api/embed", self.config.endpoint);
+        let body = serde_json::json!({
+            "model": model,
+            "input": texts,
+            "keep_alive": self.keep_alive(),
+        });
+        let response = self
+            .client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| {
+                write_exchange_log("ollama-embed", &url, "(embeddings)", None, None, Some(&e.to_string()));
+                AppError::request_failed(&e, format!("连接 Ollama 失败: {}", e))
+            })?;
+
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            write_exchange_log("ollama-embed", &url, "(embeddings)", Some(status), Some(&text), None);
+            return Err(AppError::provider(Some(status.as_u16()), format!("Ollama 返回错误 {}: {}", status, text), None));
+        }
+        let response: EmbedResponse =
+            serde_json::from_str(&text).map_err(|e| format!("解析向量响应失败: {}", e))?;
+        if response.embeddings.len() != texts.len() || response.embeddings.iter().any(|vector| vector.is_empty()) {
+            return Err(AppError::other("向量数量与输入不一致"));
+        }
+        Ok(response.embeddings)
+    }
+
+    pub async fn test_connection(&self) -> Result<(), AppError> {
         let url = format!("{}/api/tags", self.config.endpoint);
 
         let response = self
@@ -55,7 +183,7 @@ impl OllamaClient {
             .await
             .map_err(|e| {
                 write_exchange_log("ollama-test", &url, "(none)", None, None, Some(&e.to_string()));
-                format!("连接 Ollama 失败: {}", e)
+                AppError::request_failed(&e, format!("连接 Ollama 失败: {}", e))
             })?;
 
         let status = response.status();
@@ -75,17 +203,17 @@ impl OllamaClient {
             if model_exists {
                 Ok(())
             } else {
-                Err(format!(
+                Err(AppError::other(format!(
                     "模型 {} 未找到，请先运行 'ollama pull {}'",
                     self.config.model, self.config.model
-                ))
+                )))
             }
         } else {
-            Err(format!("Ollama 返回错误 {}: {}", status, text))
+            Err(AppError::provider(Some(status.as_u16()), format!("Ollama 返回错误 {}: {}", status, text), None))
         }
     }
 
-    pub async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String, String> {
+    pub async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String, AppError> {
         let url = format!("{}/api/generate", self.config.endpoint);
 
         let request = GenerateRequest {
@@ -94,28 +222,28 @@ impl OllamaClient {
             system: Some(system_prompt.to_string()),
             images: None,
             stream: false,
+            keep_alive: self.keep_alive(),
+            options: self.options(),
         };
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(response) => response,
+            Err(e) => {
                 write_exchange_log("ollama-chat", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
-            })?;
+                return Err(self.describe_request_error(&e).await);
+            }
+        };
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("ollama-chat", &url, &request_json, Some(status), Some(&text), None);
+        record_model_usage("ollama", &self.config.model, None, &text);
 
         if !status.is_success() {
-            return Err(format!("Ollama 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), format!("Ollama 错误 {}: {}", status, text), None));
         }
 
         let generate_response: GenerateResponse = serde_json::from_str(&text)
@@ -131,7 +259,7 @@ impl OllamaClient {
         system_prompt: &str,
         user_message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         let url = format!("{}/api/generate", self.config.endpoint);
 
         // Build prompt with history
@@ -152,28 +280,28 @@ impl OllamaClient {
             system: Some(system_prompt.to_string()),
             images: None,
             stream: false,
+            keep_alive: self.keep_alive(),
+            options: self.options(),
         };
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(response) => response,
+            Err(e) => {
                 write_exchange_log("ollama-chat-history", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
-            })?;
+                return Err(self.describe_request_error(&e).await);
+            }
+        };
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("ollama-chat-history", &url, &request_json, Some(status), Some(&text), None);
+        record_model_usage("ollama", &self.config.model, None, &text);
 
         if !status.is_success() {
-            return Err(format!("Ollama 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), format!("Ollama 错误 {}: {}", status, text), None));
         }
 
         let generate_response: GenerateResponse = serde_json::from_str(&text)
@@ -188,7 +316,7 @@ impl OllamaClient {
         user_message: &str,
         history: Option<Vec<ChatHistoryMessage>>,
         images: &[String],
-    ) -> Result<String, String> {
+    ) -> Result<String, AppError> {
         let url = format!("{}/api/generate", self.config.endpoint);
 
         let mut full_prompt = String::new();
@@ -208,28 +336,28 @@ impl OllamaClient {
             system: Some(system_prompt.to_string()),
             images: if images.is_empty() { None } else { Some(images.to_vec()) },
             stream: false,
+            keep_alive: self.keep_alive(),
+            options: self.options(),
         };
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(response) => response,
+            Err(e) => {
                 write_exchange_log("ollama-chat-history", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
-            })?;
+                return Err(self.describe_request_error(&e).await);
+            }
+        };
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("ollama-chat-history", &url, &request_json, Some(status), Some(&text), None);
+        record_model_usage("ollama", &self.config.model, None, &text);
 
         if !status.is_success() {
-            return Err(format!("Ollama 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), format!("Ollama 错误 {}: {}", status, text), None));
         }
 
         let generate_response: GenerateResponse = serde_json::from_str(&text)
@@ -237,37 +365,37 @@ impl OllamaClient {
 
         Ok(generate_response.response)
     }
-    pub async fn analyze_image(&self, image_base64: &str, prompt: &str) -> Result<String, String> {
+    pub async fn analyze_images(&self, images_base64: &[String], prompt: &str) -> Result<String, AppError> {
         let url = format!("{}/api/generate", self.config.endpoint);
 
         let request = GenerateRequest {
             model: self.config.model.clone(),
             prompt: prompt.to_string(),
             system: None,
-            images: Some(vec![image_base64.to_string()]),
+            images: Some(images_base64.to_vec()),
             stream: false,
+            keep_alive: self.keep_alive(),
+            options: self.options(),
         };
 
         let request_json = serde_json::to_string_pretty(&request)
             .unwrap_or_else(|e| format!("无法序列化请求: {}", e));
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| {
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(response) => response,
+            Err(e) => {
                 write_exchange_log("ollama-image", &url, &request_json, None, None, Some(&e.to_string()));
-                format!("请求失败: {}", e)
-            })?;
+                return Err(self.describe_request_error(&e).await);
+            }
+        };
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
         write_exchange_log("ollama-image", &url, &request_json, Some(status), Some(&text), None);
+        record_model_usage("ollama", &self.config.model, None, &text);
 
         if !status.is_success() {
-            return Err(format!("Ollama 错误 {}: {}", status, text));
+            return Err(AppError::provider(Some(status.as_u16()), format!("Ollama 错误 {}: {}", status, text), None));
         }
 
         let generate_response: GenerateResponse = serde_json::from_str(&text)
@@ -0,0 +1,229 @@
+use super::{ApiClient, ContentPart, Message, MessageContent, Tool};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use reqwest::StatusCode;
+
+/// 压缩图片时的尺寸与质量档位，依次尝试直到请求体足够小
+const IMAGE_DOWNSCALE_STEPS: &[(u32, u8)] = &[(1600, 75), (1280, 65), (960, 55), (640, 50)];
+/// 裁剪文本时保留的最少字符数
+const MIN_TRIMMED_CHARS: usize = 2000;
+
+/// 请求体中可单独压缩或裁剪的部分
+struct RequestPart {
+    message_idx: usize,
+    part_idx: Option<usize>,
+    label: String,
+    bytes: usize,
+    trimmable: bool,
+}
+
+impl ApiClient {
+    /// 发送前估算序列化后的请求体大小；超出上限时先压缩图片，再裁剪工具输出和较早的历史，
+    /// 仍然超出则返回具体是哪一部分过大
+    pub(super) fn preflight_messages(
+        &self,
+        messages: &mut [Message],
+        tools: Option<&[Tool]>,
+    ) -> Result<(), String> {
+        let limit = self.config.max_request_bytes;
+        if limit == 0 {
+            return Ok(());
+        }
+        let tools_bytes = tools.map_or(0, |tools| json_len(&tools));
+        let total = |messages: &[Message]| json_len(&messages) + tools_bytes + 256;
+        if total(messages) <= limit {
+            return Ok(());
+        }
+
+        for (max_side, quality) in IMAGE_DOWNSCALE_STEPS {
+            if !downscale_images(messages, *max_side, *quality) {
+                break;
+            }
+            if total(messages) <= limit {
+                return Ok(());
+            }
+        }
+
+        // 最后一条用户消息是本轮问题，不裁剪；其余文本从大到小裁剪
+        let last_user = messages.iter().rposition(|msg| msg.role == "user");
+        loop {
+            let current = total(messages);
+            if current <= limit {
+                return Ok(());
+            }
+            let candidate = collect_parts(messages)
+                .into_iter()
+                .filter(|part| part.trimmable && Some(part.message_idx) != last_user)
+                .filter(|part| messages[part.message_idx].role != "system")
+                .filter(|part| part.bytes > MIN_TRIMMED_CHARS * 2)
+                .max_by_key(|part| part.bytes);
+            let Some(part) = candidate else {
+                break;
+            };
+            let excess = current - limit;
+            let keep_bytes = part.bytes.saturating_sub(excess + 512).max(MIN_TRIMMED_CHARS);
+            trim_text_part(&mut messages[part.message_idx], part.part_idx, keep_bytes);
+        }
+
+        let current = total(messages);
+        let mut parts = collect_parts(messages);
+        parts.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        let mut details: Vec<String> = parts
+            .iter()
+            .take(3)
+            .map(|part| format!("{} {}", part.label, format_bytes(part.bytes)))
+            .collect();
+        if tools_bytes > limit / 4 {
+            details.push(format!("工具定义 {}", format_bytes(tools_bytes)));
+        }
+        Err(format!(
+            "请求体过大：{}，上限 {}。压缩图片并裁剪历史后仍超出，占用最多的部分：{}",
+            format_bytes(current),
+            format_bytes(limit),
+            details.join("；")
+        ))
+    }
+}
+
+/// 非 2xx 响应的错误信息；413 单独说明是请求体过大，避免被当作上下文超长
+pub(super) fn api_status_error(status: StatusCode, text: &str) -> String {
+    if status == StatusCode::PAYLOAD_TOO_LARGE {
+        return format!(
+            "请求体过大 (HTTP 413)，服务端拒绝接收。请减少附件图片或降低 max_request_bytes 上限: {}",
+            text
+        );
+    }
+    format!("API 错误 {}: {}", status, text)
+}
+
+fn json_len<T: serde::Serialize + ?Sized>(value: &T) -> usize {
+    serde_json::to_vec(value).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
+fn collect_parts(messages: &[Message]) -> Vec<RequestPart> {
+    let mut parts = Vec::new();
+    let mut image_no = 0usize;
+    for (message_idx, msg) in messages.iter().enumerate() {
+        let role_label = match (msg.role.as_str(), msg.tool_call_id.as_deref()) {
+            ("tool", Some(id)) => format!("工具输出 {}", id),
+            ("system", _) => "系统提示词".to_string(),
+            (role, _) => format!("消息 #{} ({})", message_idx, role),
+        };
+        match msg.content.as_ref() {
+            Some(MessageContent::Text(text)) => parts.push(RequestPart {
+                message_idx,
+                part_idx: None,
+                label: role_label,
+                bytes: text.len(),
+                trimmable: true,
+            }),
+            Some(MessageContent::Parts(items)) => {
+                for (part_idx, item) in items.iter().enumerate() {
+                    if let Some(image) = &item.image_url {
+                        image_no += 1;
+                        parts.push(RequestPart {
+                            message_idx,
+                            part_idx: Some(part_idx),
+                            label: format!("图片 #{} ({})", image_no, role_label),
+                            bytes: image.url.len(),
+                            trimmable: false,
+                        });
+                    } else if let Some(text) = &item.text {
+                        parts.push(RequestPart {
+                            message_idx,
+                            part_idx: Some(part_idx),
+                            label: role_label.clone(),
+                            bytes: text.len(),
+                            trimmable: true,
+                        });
+                    }
+                }
+            }
+            None => {}
+        }
+        if let Some(calls) = &msg.tool_calls {
+            let bytes = json_len(calls);
+            if bytes > 0 {
+                parts.push(RequestPart {
+                    message_idx,
+                    part_idx: None,
+                    label: format!("工具调用参数 (消息 #{})", message_idx),
+                    bytes,
+                    trimmable: false,
+                });
+            }
+        }
+    }
+    parts
+}
+
+/// 把 data URL 图片缩放并重新编码为 JPEG；有任何一张变小则返回 true
+fn downscale_images(messages: &mut [Message], max_side: u32, quality: u8) -> bool {
+    let mut changed = false;
+    for msg in messages.iter_mut() {
+        let Some(MessageContent::Parts(items)) = msg.content.as_mut() else {
+            continue;
+        };
+        for item in items.iter_mut() {
+            let Some(image) = item.image_url.as_mut() else {
+                continue;
+            };
+            if let Some(smaller) = downscale_data_url(&image.url, max_side, quality) {
+                if smaller.len() < image.url.len() {
+                    image.url = smaller;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+fn downscale_data_url(url: &str, max_side: u32, quality: u8) -> Option<String> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    if !header.ends_with(";base64") {
+        return None;
+    }
+    let bytes = BASE64.decode(data.trim()).ok()?;
+    let image = image::load_from_memory(&bytes).ok()?;
+    let image = if image.width() > max_side || image.height() > max_side {
+        image.resize(max_side, max_side, FilterType::Triangle)
+    } else {
+        image
+    };
+    let mut buffer = Vec::new();
+    JpegEncoder::new_with_quality(&mut buffer, quality)
+        .encode_image(&image.to_rgb8())
+        .ok()?;
+    Some(format!("data:image/jpeg;base64,{}", BASE64.encode(&buffer)))
+}
+
+fn trim_text_part(msg: &mut Message, part_idx: Option<usize>, keep_bytes: usize) {
+    let text = match (msg.content.as_mut(), part_idx) {
+        (Some(MessageContent::Text(text)), None) => text,
+        (Some(MessageContent::Parts(items)), Some(idx)) => match items.get_mut(idx) {
+            Some(ContentPart { text: Some(text), .. }) => text,
+            _ => return,
+        },
+        _ => return,
+    };
+    if text.len() <= keep_bytes {
+        return;
+    }
+    let mut cut = keep_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let removed = text.len() - cut;
+    text.truncate(cut);
+    text.push_str(&format!("\n...[内容过长，已截断 {} 字节以满足请求体大小限制]", removed));
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
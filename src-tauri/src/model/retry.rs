@@ -0,0 +1,56 @@
+use crate::error::AppError;
+use crate::storage::RetryPolicy;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 读取响应头 Retry-After（秒数或 HTTP 日期），返回需要等待的秒数
+pub(super) fn retry_after_secs(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.with_timezone(&Utc) - Utc::now()).num_seconds();
+    Some(secs.max(0) as u64)
+}
+
+/// 第 attempt 次（从 1 开始）失败后的等待时间；返回 None 表示不再重试
+pub fn retry_delay(policy: &RetryPolicy, attempt: usize, err: &AppError) -> Option<Duration> {
+    if attempt == 0 || attempt > policy.max_retries || !err.is_retryable() {
+        return None;
+    }
+    if err.is_rate_limited() && !policy.retry_on_rate_limit {
+        return None;
+    }
+
+    let exponent = (attempt - 1).min(16) as u32;
+    let mut delay_ms = policy
+        .base_delay_ms
+        .saturating_mul(1u64 << exponent)
+        .min(policy.max_delay_ms.max(policy.base_delay_ms));
+    let jitter = policy.jitter.clamp(0.0, 1.0) as f64;
+    if jitter > 0.0 && delay_ms > 0 {
+        let spread = (delay_ms as f64 * jitter) as u64;
+        delay_ms = delay_ms - spread / 2 + pseudo_random(spread + 1);
+    }
+
+    if policy.honor_retry_after {
+        if let Some(secs) = err.retry_after() {
+            if secs > policy.max_retry_after_secs {
+                return None;
+            }
+            delay_ms = delay_ms.max(secs * 1000);
+        }
+    }
+    Some(Duration::from_millis(delay_ms))
+}
+
+// 抖动不需要高质量随机数，用时间戳纳秒部分即可
+fn pseudo_random(bound: u64) -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    nanos % bound.max(1)
+}
@@ -0,0 +1,101 @@
+use crate::storage::ModelConfig;
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
+
+/// 文本 token 计数接口
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// 基于 tiktoken 编码的精确计数
+struct BpeCounter(CoreBPE);
+
+impl TokenCounter for BpeCounter {
+    fn count(&self, text: &str) -> usize {
+        self.0.encode_ordinary(text).len()
+    }
+}
+
+/// 分词器不可用时的估算：CJK 约 1 字 1 token，字母数字约 4 字符 1 token，符号单独计
+pub struct HeuristicCounter;
+
+impl TokenCounter for HeuristicCounter {
+    fn count(&self, text: &str) -> usize {
+        let mut tokens = 0usize;
+        let mut word_chars = 0usize;
+        for ch in text.chars() {
+            if ch.is_ascii_alphanumeric() {
+                word_chars += 1;
+                continue;
+            }
+            tokens += (word_chars + 3) / 4;
+            word_chars = 0;
+            if ch.is_whitespace() {
+                if ch == '\n' {
+                    tokens += 1;
+                }
+            } else {
+                tokens += 1;
+            }
+        }
+        tokens + (word_chars + 3) / 4 + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    O200k,
+    Cl100k,
+}
+
+/// 按模型名选择编码：新一代 OpenAI 模型用 o200k_base，其余（含非 OpenAI 模型的近似）用 cl100k_base
+fn encoding_for_model(model: &str) -> Encoding {
+    let model = model.trim().to_lowercase();
+    let model = model.rsplit('/').next().unwrap_or(&model);
+    let o200k_prefixes = ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "o1", "o3", "o4", "chatgpt-4o"];
+    if o200k_prefixes.iter().any(|prefix| model.starts_with(prefix)) {
+        Encoding::O200k
+    } else {
+        Encoding::Cl100k
+    }
+}
+
+fn bpe_counter(encoding: Encoding) -> Option<&'static BpeCounter> {
+    static O200K: OnceLock<Option<BpeCounter>> = OnceLock::new();
+    static CL100K: OnceLock<Option<BpeCounter>> = OnceLock::new();
+    let cell = match encoding {
+        Encoding::O200k => &O200K,
+        Encoding::Cl100k => &CL100K,
+    };
+    cell.get_or_init(|| {
+        let loaded = match encoding {
+            Encoding::O200k => tiktoken_rs::o200k_base(),
+            Encoding::Cl100k => tiktoken_rs::cl100k_base(),
+        };
+        match loaded {
+            Ok(bpe) => Some(BpeCounter(bpe)),
+            Err(err) => {
+                eprintln!("[tokenizer] 加载 {:?} 失败，改用估算: {}", encoding, err);
+                None
+            }
+        }
+    })
+    .as_ref()
+}
+
+pub fn token_counter_for_model(model: &str) -> &'static dyn TokenCounter {
+    match bpe_counter(encoding_for_model(model)) {
+        Some(counter) => counter,
+        None => &HeuristicCounter,
+    }
+}
+
+/// 当前提供者实际使用的模型对应的计数器
+pub fn token_counter_for_config(config: &ModelConfig) -> &'static dyn TokenCounter {
+    let model = match config.provider.as_str() {
+        "ollama" => config.ollama.model.as_str(),
+        "gemini" => config.gemini.model.as_str(),
+        _ => config.api.model.as_str(),
+    };
+    token_counter_for_model(model)
+}
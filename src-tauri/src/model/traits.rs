@@ -1,14 +1,15 @@
+use crate::error::AppError;
 use async_trait::async_trait;
 
 /// 模型提供者的统一接口
 #[async_trait]
 pub trait ModelProvider: Send + Sync {
     /// 测试连接
-    async fn test_connection(&self) -> Result<(), String>;
+    async fn test_connection(&self) -> Result<(), AppError>;
 
     /// 文本对话
-    async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String, String>;
+    async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String, AppError>;
 
     /// 图片分析
-    async fn analyze_image(&self, image_base64: &str, prompt: &str) -> Result<String, String>;
+    async fn analyze_image(&self, image_base64: &str, prompt: &str) -> Result<String, AppError>;
 }
@@ -0,0 +1,118 @@
+use crate::storage::{storage_actor, ModelPricing, StoragePriority, UsageEntry};
+use chrono::Local;
+use serde_json::Value;
+use std::future::Future;
+
+tokio::task_local! {
+    static USAGE_FEATURE: &'static str;
+}
+
+/// 内置参考价格（美元 / 百万 token：输入, 输出），按模型名最长前缀匹配，仅用于估算
+const BUILTIN_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-4-vision", 10.0, 30.0),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("o4-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("o1-mini", 1.1, 4.4),
+    ("o1", 15.0, 60.0),
+    ("deepseek-chat", 0.27, 1.1),
+    ("deepseek-reasoner", 0.55, 2.19),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash-lite", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.0-flash-lite", 0.075, 0.3),
+    ("gemini-2.0-flash", 0.1, 0.4),
+];
+
+/// 在 future 执行期间把模型用量归到指定功能（capture / chat / skills）
+pub async fn with_usage_feature<F: Future>(feature: &'static str, fut: F) -> F::Output {
+    USAGE_FEATURE.scope(feature, fut).await
+}
+
+pub(super) fn current_usage_feature() -> &'static str {
+    USAGE_FEATURE.try_with(|feature| *feature).unwrap_or("chat")
+}
+
+/// 从响应中读取 token 数：兼容 Chat Completions、Responses、Gemini 和 Ollama 的字段名
+fn parse_token_usage(body: &str) -> Option<(u64, u64)> {
+    let json: Value = serde_json::from_str(body).ok()?;
+    let count = |value: &Value, keys: &[&str]| keys.iter().find_map(|key| value.get(*key)?.as_u64());
+    if let Some(usage) = json.get("usage").filter(|usage| usage.is_object()) {
+        let prompt = count(usage, &["prompt_tokens", "input_tokens"]).unwrap_or(0);
+        let completion = count(usage, &["completion_tokens", "output_tokens"]).unwrap_or(0);
+        return Some((prompt, completion));
+    }
+    if let Some(usage) = json.get("usageMetadata").filter(|usage| usage.is_object()) {
+        // Gemini 的思考 token 按输出计费
+        let prompt = count(usage, &["promptTokenCount"]).unwrap_or(0);
+        let completion = count(usage, &["candidatesTokenCount"]).unwrap_or(0)
+            + count(usage, &["thoughtsTokenCount"]).unwrap_or(0);
+        return Some((prompt, completion));
+    }
+    let prompt = count(&json, &["prompt_eval_count"]);
+    let completion = count(&json, &["eval_count"]);
+    if prompt.is_none() && completion.is_none() {
+        return None;
+    }
+    Some((prompt.unwrap_or(0), completion.unwrap_or(0)))
+}
+
+fn estimate_cost(model: &str, pricing: Option<&ModelPricing>, prompt: u64, completion: u64) -> f64 {
+    let (input, output) = match pricing {
+        Some(pricing) => (pricing.input_per_million, pricing.output_per_million),
+        None => {
+            let model = model.trim().to_lowercase();
+            let model = model.rsplit('/').next().unwrap_or(&model);
+            match BUILTIN_PRICES
+                .iter()
+                .filter(|(prefix, _, _)| model.starts_with(prefix))
+                .max_by_key(|(prefix, _, _)| prefix.len())
+            {
+                Some((_, input, output)) => (*input, *output),
+                None => return 0.0,
+            }
+        }
+    };
+    (prompt as f64 * input + completion as f64 * output) / 1_000_000.0
+}
+
+/// 记录一次成功请求的用量；写入走后台存储队列，不阻塞调用方
+pub fn record_model_usage(provider: &str, model: &str, pricing: Option<&ModelPricing>, body: &str) {
+    let Some((prompt_tokens, completion_tokens)) = parse_token_usage(body) else {
+        return;
+    };
+    let cost_usd = if provider == "ollama" {
+        0.0
+    } else {
+        estimate_cost(model, pricing, prompt_tokens, completion_tokens)
+    };
+    let entry = UsageEntry {
+        feature: current_usage_feature().to_string(),
+        provider: provider.to_string(),
+        model: model.to_string(),
+        requests: 1,
+        prompt_tokens,
+        completion_tokens,
+        cost_usd,
+    };
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    tokio::spawn(async move {
+        let result = storage_actor()
+            .run(StoragePriority::Background, move |storage| storage.record_usage(&date, entry))
+            .await;
+        if let Err(err) = result {
+            eprintln!("[usage] 记录用量失败: {}", err);
+        }
+    });
+}
@@ -0,0 +1,132 @@
+use crate::model::ModelManager;
+use crate::storage::{AgentPreset, Config, StorageManager};
+
+const TOOL_MODES: &[&str] = &["unset", "whitelist", "allow_all"];
+const MAX_TEMPERATURE: f32 = 2.0;
+
+fn normalize_list(items: Option<Vec<String>>) -> Option<Vec<String>> {
+    items.map(|items| {
+        items
+            .into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    })
+}
+
+fn validate_preset(preset: AgentPreset) -> Result<AgentPreset, String> {
+    let name = preset.name.trim().to_string();
+    if name.is_empty() {
+        return Err("预设名称不能为空".to_string());
+    }
+    if name.eq_ignore_ascii_case("default") {
+        return Err("default 为保留名称，表示不使用预设".to_string());
+    }
+    let tool_mode = preset
+        .tool_mode
+        .map(|mode| mode.trim().to_lowercase())
+        .filter(|mode| !mode.is_empty());
+    if let Some(mode) = &tool_mode {
+        if !TOOL_MODES.contains(&mode.as_str()) {
+            return Err(format!("未知的工具模式: {}", mode));
+        }
+    }
+    if let Some(temperature) = preset.temperature {
+        if !(0.0..=MAX_TEMPERATURE).contains(&temperature) {
+            return Err(format!("温度需在 0 到 {} 之间", MAX_TEMPERATURE));
+        }
+    }
+    Ok(AgentPreset {
+        name,
+        description: preset.description.trim().to_string(),
+        system_prompt: preset.system_prompt.trim().to_string(),
+        model: preset.model.map(|model| model.trim().to_string()).filter(|model| !model.is_empty()),
+        temperature: preset.temperature,
+        tool_mode,
+        allowed_commands: normalize_list(preset.allowed_commands),
+        allowed_dirs: normalize_list(preset.allowed_dirs),
+        ask_approval: preset.ask_approval,
+    })
+}
+
+/// 按名称查找预设；为空或 default 表示不使用预设
+pub fn find_agent_preset(config: &Config, name: Option<&str>) -> Result<Option<AgentPreset>, String> {
+    let name = match name.map(str::trim) {
+        Some(name) if !name.is_empty() && !name.eq_ignore_ascii_case("default") => name,
+        _ => return Ok(None),
+    };
+    config
+        .agent_presets
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| format!("预设不存在: {}", name))
+}
+
+/// 把预设应用到本次请求使用的配置副本上
+pub fn apply_agent_preset(config: &mut Config, preset: &AgentPreset) {
+    if preset.model.is_some() {
+        config.model = ModelManager::resolve_model_config(&config.model, preset.model.as_deref());
+    }
+    if let Some(temperature) = preset.temperature {
+        config.model.api.temperature = Some(temperature);
+        config.model.ollama.temperature = Some(temperature);
+        config.model.gemini.temperature = Some(temperature);
+    }
+    if let Some(mode) = &preset.tool_mode {
+        config.tools.mode = mode.clone();
+    }
+    if let Some(commands) = &preset.allowed_commands {
+        config.tools.allowed_commands = commands.clone();
+    }
+    if let Some(dirs) = &preset.allowed_dirs {
+        config.tools.allowed_dirs = dirs.clone();
+    }
+    if let Some(ask_approval) = preset.ask_approval {
+        config.tools.ask_approval = ask_approval;
+    }
+}
+
+/// 预设的附加提示词，放在上下文之后
+pub fn preset_prompt_section(preset: &AgentPreset) -> String {
+    if preset.system_prompt.is_empty() {
+        return String::new();
+    }
+    format!("\n\n## 当前预设：{}\n{}", preset.name, preset.system_prompt)
+}
+
+impl StorageManager {
+    pub fn list_agent_presets(&self) -> Result<Vec<AgentPreset>, String> {
+        Ok(self.load_config()?.agent_presets)
+    }
+
+    /// 新建或按名称覆盖预设
+    pub fn save_agent_preset(&self, preset: AgentPreset) -> Result<AgentPreset, String> {
+        let preset = validate_preset(preset)?;
+        let mut config = self.load_config()?;
+        match config
+            .agent_presets
+            .iter_mut()
+            .find(|item| item.name.eq_ignore_ascii_case(&preset.name))
+        {
+            Some(existing) => *existing = preset.clone(),
+            None => config.agent_presets.push(preset.clone()),
+        }
+        self.save_config(&config)?;
+        Ok(preset)
+    }
+
+    pub fn delete_agent_preset(&self, name: &str) -> Result<bool, String> {
+        let mut config = self.load_config()?;
+        let before = config.agent_presets.len();
+        config
+            .agent_presets
+            .retain(|preset| !preset.name.eq_ignore_ascii_case(name.trim()));
+        if config.agent_presets.len() == before {
+            return Ok(false);
+        }
+        self.save_config(&config)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,107 @@
+use crate::commands::{apply_config, restart_capture_if_running};
+use crate::logs;
+use crate::storage::{ProfileScheduleConfig, ProfileScheduleRule, StorageManager};
+use chrono::{DateTime, Datelike, Local, NaiveTime, Timelike};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+const PROFILE_SCHEDULE_CHECK_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize)]
+struct ProfileSwitchEvent {
+    profile: String,
+    previous: String,
+    capture_restarted: bool,
+}
+
+fn parse_clock(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+pub fn validate_profile_schedule(config: &ProfileScheduleConfig) -> Result<(), String> {
+    for rule in &config.rules {
+        if rule.profile.trim().is_empty() {
+            return Err("自动切换规则缺少配置方案名称".to_string());
+        }
+        if parse_clock(&rule.start).is_none() || parse_clock(&rule.end).is_none() {
+            return Err(format!("自动切换规则「{}」的时间格式应为 HH:MM", rule.profile));
+        }
+        if rule.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+            return Err(format!("自动切换规则「{}」的星期应为 1-7", rule.profile));
+        }
+    }
+    Ok(())
+}
+
+fn rule_matches(rule: &ProfileScheduleRule, now: &DateTime<Local>) -> bool {
+    let (Some(start), Some(end)) = (parse_clock(&rule.start), parse_clock(&rule.end)) else {
+        return false;
+    };
+    let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or_default();
+    let weekday = now.weekday().number_from_monday();
+    let on_day = |day: u32| rule.weekdays.is_empty() || rule.weekdays.contains(&day);
+    if start <= end {
+        on_day(weekday) && time >= start && time < end
+    } else {
+        // 跨夜时段的后半段算在开始那天
+        let yesterday = if weekday == 1 { 7 } else { weekday - 1 };
+        (on_day(weekday) && time >= start) || (on_day(yesterday) && time < end)
+    }
+}
+
+/// 当前时间应使用的配置方案，None 表示保持不变
+pub fn scheduled_profile(config: &ProfileScheduleConfig, now: &DateTime<Local>) -> Option<String> {
+    config
+        .rules
+        .iter()
+        .find(|rule| rule_matches(rule, now))
+        .map(|rule| rule.profile.trim().to_string())
+        .or_else(|| Some(config.fallback_profile.trim().to_string()))
+        .filter(|profile| !profile.is_empty())
+}
+
+/// 每分钟检查一次，时段变化时载入对应的配置方案；切换后截屏正在运行则按新配置重启
+pub fn start_profile_scheduler(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(err) = check_profile_schedule(&app_handle).await {
+                logs::warn("profiles", format!("自动切换配置方案失败: {}", err));
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(PROFILE_SCHEDULE_CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+async fn check_profile_schedule(app_handle: &AppHandle) -> Result<(), String> {
+    let storage = StorageManager::new();
+    let schedule = storage.load_config()?.profile_schedule;
+    if !schedule.enabled {
+        return Ok(());
+    }
+    let Some(profile) = scheduled_profile(&schedule, &Local::now()) else {
+        return Ok(());
+    };
+    // 已切换过的时段不再重复载入，期间手动修改的设置会保留到下一个时段
+    if profile == schedule.active_profile {
+        return Ok(());
+    }
+
+    let mut config = storage.load_profile(&profile)?;
+    config.profile_schedule = ProfileScheduleConfig {
+        active_profile: profile.clone(),
+        ..schedule.clone()
+    };
+    apply_config(app_handle, config.clone())?;
+    let capture_restarted = restart_capture_if_running(app_handle, config).await;
+
+    logs::info("profiles", format!("已按计划切换到配置方案 {}", profile));
+    let _ = app_handle.emit(
+        "profile-switched",
+        ProfileSwitchEvent {
+            profile,
+            previous: schedule.active_profile,
+            capture_restarted,
+        },
+    );
+    Ok(())
+}
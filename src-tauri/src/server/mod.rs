@@ -0,0 +1,291 @@
+use crate::commands::{chat_with_assistant_internal, get_capture_status, list_skills, AppState, ChatHistoryMessage, ChatOptions};
+use crate::storage::{storage_actor, ApiServerConfig, StoragePriority};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use parking_lot::Mutex as ParkingMutex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use tauri::{AppHandle, EventId, Listener, Manager};
+use tokio::sync::{broadcast, oneshot};
+
+/// 转发给 WebSocket 客户端的应用事件
+const FORWARDED_EVENTS: &[&str] = &[
+    "assistant-alert",
+    "alert-verified",
+    "capture-status-changed",
+    "digest-generated",
+    "queued-chat-completed",
+];
+
+#[derive(Clone)]
+struct ServerState {
+    app: AppHandle,
+    token: Arc<String>,
+    events: broadcast::Sender<String>,
+}
+
+struct RunningServer {
+    config: ApiServerConfig,
+    shutdown: oneshot::Sender<()>,
+    listeners: Vec<EventId>,
+}
+
+fn running_server() -> &'static ParkingMutex<Option<RunningServer>> {
+    static API_SERVER: OnceLock<ParkingMutex<Option<RunningServer>>> = OnceLock::new();
+    API_SERVER.get_or_init(|| ParkingMutex::new(None))
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 按配置启动、重启或关闭本地接口；配置未变化时不做任何事
+pub fn apply_api_server_config(app: &AppHandle, config: &ApiServerConfig) {
+    let mut slot = running_server().lock();
+    if slot.as_ref().map_or(!config.enabled, |running| running.config == *config) {
+        return;
+    }
+    if let Some(running) = slot.take() {
+        for id in running.listeners {
+            app.unlisten(id);
+        }
+        let _ = running.shutdown.send(());
+    }
+    if !config.enabled {
+        return;
+    }
+    if config.token.trim().is_empty() {
+        eprintln!("[api] token 为空，本地接口未启动");
+        return;
+    }
+
+    let (events, _) = broadcast::channel::<String>(64);
+    let listeners = FORWARDED_EVENTS
+        .iter()
+        .map(|name| {
+            let events = events.clone();
+            let name = name.to_string();
+            app.listen_any(name.clone(), move |event| {
+                let payload = serde_json::from_str::<Value>(event.payload()).unwrap_or(Value::Null);
+                let _ = events.send(
+                    json!({ "type": "event", "event": name, "payload": payload }).to_string(),
+                );
+            })
+        })
+        .collect();
+
+    let state = ServerState {
+        app: app.clone(),
+        token: Arc::new(config.token.trim().to_string()),
+        events,
+    };
+    let router = Router::new()
+        .route("/summaries", get(get_summaries_handler))
+        .route("/chat", post(chat_handler))
+        .route("/skills", get(skills_handler))
+        .route("/capture/status", get(capture_status_handler))
+        .route("/ws", get(ws_handler))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state);
+
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let port = config.port;
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("[api] 监听 127.0.0.1:{} 失败: {}", port, err);
+                return;
+            }
+        };
+        println!("[api] listening on http://127.0.0.1:{}", port);
+        let result = axum::serve(listener, router)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        if let Err(err) = result {
+            eprintln!("[api] 服务异常退出: {}", err);
+        }
+    });
+
+    *slot = Some(RunningServer {
+        config: config.clone(),
+        shutdown,
+        listeners,
+    });
+}
+
+/// 校验 Authorization: Bearer <token>；WebSocket 客户端也可以用 ?token= 传入
+async fn require_token(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let header_token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string());
+    let query_token = request.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| {
+            pair.strip_prefix("token=")
+                .and_then(|v| urlencoding::decode(v).ok())
+                .map(|v| v.into_owned())
+        })
+    });
+    let authorized = header_token
+        .or(query_token)
+        .map_or(false, |token| tokens_match(&token, &state.token));
+    if !authorized {
+        return error_response(StatusCode::UNAUTHORIZED, "invalid token".to_string());
+    }
+    next.run(request).await
+}
+
+/// 恒定时间比较令牌，避免按首个不同字节提前返回泄露令牌内容
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    let (provided, expected) = (provided.as_bytes(), expected.as_bytes());
+    if provided.len() != expected.len() {
+        return false;
+    }
+    provided
+        .iter()
+        .zip(expected)
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+fn json_result<T: serde::Serialize>(result: Result<T, String>) -> Response {
+    match result {
+        Ok(value) => Json(value).into_response(),
+        Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+async fn get_summaries_handler(Query(params): Query<HashMap<String, String>>) -> Response {
+    let date = params
+        .get("date")
+        .cloned()
+        .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    json_result(
+        storage_actor()
+            .run(StoragePriority::Interactive, move |storage| storage.get_summaries(&date))
+            .await,
+    )
+}
+
+#[derive(Deserialize)]
+struct ChatRequest {
+    message: String,
+    #[serde(default)]
+    history: Option<Vec<ChatHistoryMessage>>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    plan_only: Option<bool>,
+    #[serde(default)]
+    preset: Option<String>,
+}
+
+async fn run_chat(app: &AppHandle, request: ChatRequest) -> Result<Value, String> {
+    if request.message.trim().is_empty() {
+        return Err("message 不能为空".to_string());
+    }
+    let options = ChatOptions {
+        history: request.history,
+        model: request.model,
+        plan_only: request.plan_only,
+        preset: request.preset,
+        ..Default::default()
+    };
+    let response = chat_with_assistant_internal(
+        request.message,
+        Some(options),
+        None,
+        app.clone(),
+        app.state::<AppState>(),
+    )
+    .await?;
+    Ok(serde_json::from_str(&response).unwrap_or_else(|_| json!({ "response": response })))
+}
+
+async fn chat_handler(State(state): State<ServerState>, Json(request): Json<ChatRequest>) -> Response {
+    json_result(run_chat(&state.app, request).await)
+}
+
+async fn skills_handler(State(state): State<ServerState>) -> Response {
+    json_result(list_skills(state.app.state::<AppState>()).await)
+}
+
+async fn capture_status_handler(State(state): State<ServerState>) -> Response {
+    json_result(get_capture_status(state.app.state::<AppState>(), state.app.clone()).await)
+}
+
+async fn ws_handler(State(state): State<ServerState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+/// WebSocket：推送应用事件；客户端可发送 {"type":"chat","id":..,"message":..} 或 {"type":"ping"}
+async fn handle_socket(mut socket: WebSocket, state: ServerState) {
+    let mut events = state.events.subscribe();
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                let reply = handle_socket_message(&state, &text).await;
+                if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            event = events.recv() => {
+                match event {
+                    Ok(payload) => {
+                        if socket.send(Message::Text(payload)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+}
+
+async fn handle_socket_message(state: &ServerState, text: &str) -> Value {
+    let Ok(message) = serde_json::from_str::<Value>(text) else {
+        return json!({ "type": "error", "error": "invalid json" });
+    };
+    let id = message.get("id").cloned().unwrap_or(Value::Null);
+    match message.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+        "ping" => json!({ "type": "pong", "id": id }),
+        "chat" => {
+            let request = match serde_json::from_value::<ChatRequest>(message.clone()) {
+                Ok(request) => request,
+                Err(err) => return json!({ "type": "error", "id": id, "error": err.to_string() }),
+            };
+            match run_chat(&state.app, request).await {
+                Ok(result) => json!({ "type": "chat_result", "id": id, "result": result }),
+                Err(err) => json!({ "type": "error", "id": id, "error": err }),
+            }
+        }
+        other => json!({ "type": "error", "id": id, "error": format!("unknown type: {}", other) }),
+    }
+}
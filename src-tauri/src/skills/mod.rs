@@ -1,4 +1,7 @@
 mod parser;
+pub mod registry;
+mod schema;
+mod usage;
 
 use crate::storage::StorageManager;
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
@@ -9,6 +12,11 @@ use std::time::{Duration, Instant};
 use tauri::Emitter;
 
 pub use parser::SkillParser;
+pub use schema::{
+    render_structured_output, split_structured_output, structured_output_instruction,
+    validate_against_schema,
+};
+pub use usage::{load_skill_usage, rank_skills, record_skill_use, SkillUsage};
 
 const DEFAULT_SCRIPT_PS1: &str = r#"# PowerShell placeholder for this skill.
 # Usage:
@@ -98,6 +106,21 @@ pub struct SkillMetadata {
     pub disable_model_invocation: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<std::collections::HashMap<String, String>>,
+    /// 最终回答的 JSON Schema（frontmatter 的 output-schema）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    /// 技能图标（emoji、图片 URL 或技能目录内的相对路径）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    /// 示例提问，用于技能目录展示
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub examples: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -108,6 +131,12 @@ pub struct SkillFrontmatterOverrides {
     pub user_invocable: Option<bool>,
     pub disable_model_invocation: Option<bool>,
     pub metadata: Option<std::collections::HashMap<String, String>>,
+    pub output_schema: Option<serde_json::Value>,
+    pub icon: Option<String>,
+    pub category: Option<String>,
+    pub author: Option<String>,
+    pub homepage: Option<String>,
+    pub examples: Option<Vec<String>>,
 }
 
 /// 完整的 Skill（激活时加载）
@@ -156,6 +185,10 @@ impl SkillManager {
 
         for entry in entries.flatten() {
             let path = entry.path();
+            // 跳过隐藏目录（如市场安装时的临时目录）
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
             if path.is_dir() {
                 if let Some(skill_md) = Self::resolve_skill_md_path(&path) {
                     match SkillParser::parse_metadata(&skill_md) {
@@ -472,6 +505,53 @@ fn build_skill_frontmatter(
         }
     }
 
+    let output_schema = overrides
+        .output_schema
+        .clone()
+        .or_else(|| existing.and_then(|m| m.output_schema.clone()));
+    if let Some(schema) = output_schema.filter(|s| s.is_object()) {
+        if let Ok(yaml) = serde_yaml::to_string(&schema) {
+            lines.push("output-schema:".to_string());
+            for line in yaml.lines().filter(|l| *l != "---") {
+                lines.push(format!("  {}", line));
+            }
+        }
+    }
+
+    // 目录展示信息
+    let catalog_fields = [
+        ("icon", overrides.icon.clone().or_else(|| existing.and_then(|m| m.icon.clone()))),
+        ("category", overrides.category.clone().or_else(|| existing.and_then(|m| m.category.clone()))),
+        ("author", overrides.author.clone().or_else(|| existing.and_then(|m| m.author.clone()))),
+        ("homepage", overrides.homepage.clone().or_else(|| existing.and_then(|m| m.homepage.clone()))),
+    ];
+    for (key, value) in catalog_fields {
+        if let Some(value) = value {
+            let value = value.trim();
+            if !value.is_empty() {
+                lines.push(format!("{}: {}", key, yaml_quote(value)));
+            }
+        }
+    }
+
+    let examples = overrides
+        .examples
+        .clone()
+        .or_else(|| existing.and_then(|m| m.examples.clone()));
+    if let Some(examples) = examples {
+        let examples: Vec<&str> = examples
+            .iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .collect();
+        if !examples.is_empty() {
+            lines.push("examples:".to_string());
+            for example in examples {
+                lines.push(format!("  - {}", yaml_quote(example)));
+            }
+        }
+    }
+
     lines.join("\n")
 }
 
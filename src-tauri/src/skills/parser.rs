@@ -8,7 +8,7 @@ struct SkillFrontmatter {
     name: Option<String>,
     description: Option<String>,
     #[serde(rename = "allowed-tools")]
-    allowed_tools: Option<AllowedToolsField>,
+    allowed_tools: Option<StringOrList>,
     model: Option<String>,
     context: Option<String>,
     #[serde(rename = "user-invocable")]
@@ -16,11 +16,18 @@ struct SkillFrontmatter {
     #[serde(rename = "disable-model-invocation")]
     disable_model_invocation: Option<bool>,
     metadata: Option<HashMap<String, String>>,
+    #[serde(rename = "output-schema")]
+    output_schema: Option<serde_json::Value>,
+    icon: Option<String>,
+    category: Option<String>,
+    author: Option<String>,
+    homepage: Option<String>,
+    examples: Option<StringOrList>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
-enum AllowedToolsField {
+enum StringOrList {
     Text(String),
     List(Vec<String>),
 }
@@ -43,6 +50,12 @@ impl SkillParser {
             user_invocable: frontmatter.user_invocable,
             disable_model_invocation: frontmatter.disable_model_invocation,
             metadata: frontmatter.metadata,
+            output_schema: frontmatter.output_schema,
+            icon: Self::clean_text(frontmatter.icon),
+            category: Self::clean_text(frontmatter.category),
+            author: Self::clean_text(frontmatter.author),
+            homepage: Self::clean_text(frontmatter.homepage),
+            examples: Self::parse_examples(frontmatter.examples),
         })
     }
 
@@ -63,6 +76,12 @@ impl SkillParser {
                 user_invocable: frontmatter.user_invocable,
                 disable_model_invocation: frontmatter.disable_model_invocation,
                 metadata: frontmatter.metadata,
+                output_schema: frontmatter.output_schema,
+                icon: Self::clean_text(frontmatter.icon),
+                category: Self::clean_text(frontmatter.category),
+                author: Self::clean_text(frontmatter.author),
+                homepage: Self::clean_text(frontmatter.homepage),
+                examples: Self::parse_examples(frontmatter.examples),
             },
             instructions,
             path: path.to_string_lossy().to_string(),
@@ -102,11 +121,11 @@ impl SkillParser {
         Ok(content[instructions_start..].trim().to_string())
     }
 
-    fn parse_allowed_tools(value: Option<AllowedToolsField>) -> Option<Vec<String>> {
+    fn parse_allowed_tools(value: Option<StringOrList>) -> Option<Vec<String>> {
         let mut tools = Vec::new();
         match value {
             None => return None,
-            Some(AllowedToolsField::Text(text)) => {
+            Some(StringOrList::Text(text)) => {
                 for token in text.split([',', ' ', '\n', '\t']) {
                     let token = token.trim();
                     if !token.is_empty() {
@@ -114,7 +133,7 @@ impl SkillParser {
                     }
                 }
             }
-            Some(AllowedToolsField::List(list)) => {
+            Some(StringOrList::List(list)) => {
                 for item in list {
                     let item = item.trim();
                     if !item.is_empty() {
@@ -130,6 +149,30 @@ impl SkillParser {
         }
     }
 
+    /// 示例按条保留，单个字符串视为一条示例
+    fn parse_examples(value: Option<StringOrList>) -> Option<Vec<String>> {
+        let items = match value? {
+            StringOrList::Text(text) => vec![text],
+            StringOrList::List(list) => list,
+        };
+        let examples: Vec<String> = items
+            .into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+        if examples.is_empty() {
+            None
+        } else {
+            Some(examples)
+        }
+    }
+
+    fn clean_text(value: Option<String>) -> Option<String> {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
     fn resolve_name(path: &Path, frontmatter_name: Option<String>) -> Result<String, String> {
         if let Some(name) = frontmatter_name {
             let name = name.trim();
@@ -0,0 +1,326 @@
+use super::{SkillManager, SkillMetadata, SkillParser, DEFAULT_SKILL_MD_FILE, LOWERCASE_SKILL_MD_FILE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const REGISTRY_MARKER_FILE: &str = ".registry.json";
+const MAX_PACKAGE_BYTES: usize = 20 * 1024 * 1024;
+// 解压后的大小上限，防止压缩炸弹
+const MAX_ENTRY_UNPACKED_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_TOTAL_UNPACKED_BYTES: u64 = 100 * 1024 * 1024;
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrySkill {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub homepage: String,
+    pub url: String,    // zip 包地址
+    pub sha256: String, // zip 包的 SHA-256（十六进制）
+    // 以下字段由本地填充
+    #[serde(default)]
+    pub installed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryIndex {
+    #[serde(default)]
+    skills: Vec<RegistrySkill>,
+}
+
+/// 记录 skill 的来源，用于判断是否可以从 registry 升级
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryMarker {
+    name: String,
+    version: String,
+    sha256: String,
+    source: String,
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .user_agent("OpenCowork")
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))
+}
+
+/// 拉取远程索引，并标记本地已安装的版本
+pub async fn fetch_registry(manager: &SkillManager, index_url: &str) -> Result<Vec<RegistrySkill>, String> {
+    let index_url = index_url.trim();
+    if index_url.is_empty() {
+        return Err("未配置 skill 市场地址".to_string());
+    }
+    let response = http_client()?
+        .get(index_url)
+        .send()
+        .await
+        .map_err(|e| format!("获取 skill 索引失败: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("获取 skill 索引失败: HTTP {}", response.status()));
+    }
+    let index: RegistryIndex = response
+        .json()
+        .await
+        .map_err(|e| format!("解析 skill 索引失败: {}", e))?;
+
+    let mut skills: Vec<RegistrySkill> = index
+        .skills
+        .into_iter()
+        .filter(|skill| SkillManager::validate_skill_name(&skill.name).is_ok())
+        .collect();
+    for skill in &mut skills {
+        let dir = manager.get_skills_dir().join(&skill.name);
+        skill.installed = dir.exists();
+        skill.installed_version = read_marker(&dir).map(|marker| marker.version);
+    }
+    skills.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(skills)
+}
+
+pub fn find_registry_skill(skills: &[RegistrySkill], name: &str) -> Result<RegistrySkill, String> {
+    skills
+        .iter()
+        .find(|skill| skill.name == name)
+        .cloned()
+        .ok_or_else(|| format!("skill 市场中没有 {}", name))
+}
+
+/// 下载并校验 zip 包，解压到 skills 目录；同名的本地（非市场安装）skill 不会被覆盖
+pub async fn install_registry_skill(
+    manager: &SkillManager,
+    entry: &RegistrySkill,
+    index_url: &str,
+) -> Result<SkillMetadata, String> {
+    SkillManager::validate_skill_name(&entry.name)?;
+    let expected = entry.sha256.trim().to_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{} 缺少有效的 sha256，拒绝安装", entry.name));
+    }
+
+    let target = manager.get_skills_dir().join(&entry.name);
+    if target.exists() && read_marker(&target).is_none() {
+        return Err(format!("本地已存在同名 skill '{}'，请先重命名或删除", entry.name));
+    }
+
+    let mut response = http_client()?
+        .get(entry.url.trim())
+        .send()
+        .await
+        .map_err(|e| format!("下载 skill 失败: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("下载 skill 失败: HTTP {}", response.status()));
+    }
+    // 声明的长度超限时直接放弃；未声明或声明不实时边读边计数，超限即停止下载
+    if response.content_length().map_or(false, |len| len > MAX_PACKAGE_BYTES as u64) {
+        return Err("skill 包过大".to_string());
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("下载 skill 失败: {}", e))? {
+        if bytes.len() + chunk.len() > MAX_PACKAGE_BYTES {
+            return Err("skill 包过大".to_string());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let actual = Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    if actual != expected {
+        return Err(format!("校验失败：期望 {}，实际 {}", expected, actual));
+    }
+
+    let staging = manager
+        .get_skills_dir()
+        .join(format!(".install-{}", entry.name));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(|e| format!("清理临时目录失败: {}", e))?;
+    }
+    let result = unpack_and_replace(&bytes, &staging, &target, entry, index_url);
+    if staging.exists() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+fn unpack_and_replace(
+    bytes: &[u8],
+    staging: &Path,
+    target: &Path,
+    entry: &RegistrySkill,
+    index_url: &str,
+) -> Result<SkillMetadata, String> {
+    extract_zip(bytes, staging)?;
+    let root = find_skill_root(staging)
+        .ok_or_else(|| "skill 包中没有 SKILL.md".to_string())?;
+    let skill_md = if root.join(DEFAULT_SKILL_MD_FILE).exists() {
+        root.join(DEFAULT_SKILL_MD_FILE)
+    } else {
+        root.join(LOWERCASE_SKILL_MD_FILE)
+    };
+    let metadata = SkillParser::parse_metadata(&skill_md)?;
+    if metadata.name != entry.name {
+        return Err(format!(
+            "skill 包名称不一致：索引为 {}，SKILL.md 为 {}",
+            entry.name, metadata.name
+        ));
+    }
+
+    let marker = RegistryMarker {
+        name: entry.name.clone(),
+        version: entry.version.clone(),
+        sha256: entry.sha256.trim().to_lowercase(),
+        source: index_url.to_string(),
+    };
+    let marker_json = serde_json::to_string_pretty(&marker)
+        .map_err(|e| format!("序列化安装信息失败: {}", e))?;
+    fs::write(root.join(REGISTRY_MARKER_FILE), marker_json)
+        .map_err(|e| format!("写入安装信息失败: {}", e))?;
+
+    if target.exists() {
+        fs::remove_dir_all(target).map_err(|e| format!("移除旧版本失败: {}", e))?;
+    }
+    fs::rename(&root, target).map_err(|e| format!("安装 skill 失败: {}", e))?;
+    Ok(metadata)
+}
+
+/// 压缩包内的路径必须是相对路径且不含 ..（zip 中可能用 / 或 \ 分隔）
+fn is_safe_entry_name(name: &str) -> bool {
+    let name = name.replace('\\', "/");
+    if name.starts_with('/') || name.contains(':') {
+        return false;
+    }
+    name.split('/').all(|part| part != "..")
+}
+
+fn extract_zip(bytes: &[u8], dest: &Path) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("打开 skill 包失败: {}", e))?;
+    let mut total: u64 = 0;
+    for idx in 0..archive.len() {
+        let file = archive
+            .by_index(idx)
+            .map_err(|e| format!("读取 skill 包失败: {}", e))?;
+        // 拒绝绝对路径和 ../ 等越界路径
+        let relative = file
+            .enclosed_name()
+            .filter(|_| is_safe_entry_name(file.name()))
+            .map(|p| p.to_path_buf());
+        let Some(relative) = relative else {
+            return Err(format!("skill 包包含非法路径: {}", file.name()));
+        };
+        let out_path = dest.join(relative);
+        if file.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| format!("创建目录失败: {}", e))?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        // 按实际解压出的字节计数，不信任包内声明的大小
+        let mut content = Vec::new();
+        file.take(MAX_ENTRY_UNPACKED_BYTES + 1)
+            .read_to_end(&mut content)
+            .map_err(|e| format!("解压失败: {}", e))?;
+        if content.len() as u64 > MAX_ENTRY_UNPACKED_BYTES {
+            return Err(format!("skill 包中的文件过大: {}", relative.display()));
+        }
+        total += content.len() as u64;
+        if total > MAX_TOTAL_UNPACKED_BYTES {
+            return Err("skill 包解压后过大".to_string());
+        }
+        fs::write(&out_path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// SKILL.md 可以在压缩包根目录，也可以在唯一的顶层目录中
+fn find_skill_root(staging: &Path) -> Option<PathBuf> {
+    let has_skill_md =
+        |dir: &Path| dir.join(DEFAULT_SKILL_MD_FILE).exists() || dir.join(LOWERCASE_SKILL_MD_FILE).exists();
+    if has_skill_md(staging) {
+        return Some(staging.to_path_buf());
+    }
+    let dirs: Vec<PathBuf> = fs::read_dir(staging)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    match dirs.as_slice() {
+        [only] if has_skill_md(only) => Some(only.clone()),
+        _ => None,
+    }
+}
+
+fn read_marker(dir: &Path) -> Option<RegistryMarker> {
+    let content = fs::read_to_string(dir.join(REGISTRY_MARKER_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn zip_with(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn temp_dest(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("opencowork-registry-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn rejects_absolute_and_parent_paths() {
+        assert!(is_safe_entry_name("demo/SKILL.md"));
+        assert!(!is_safe_entry_name("/etc/passwd"));
+        assert!(!is_safe_entry_name("demo/../../evil"));
+        assert!(!is_safe_entry_name("..\\evil"));
+        assert!(!is_safe_entry_name("C:/evil"));
+
+        let dest = temp_dest("traversal");
+        let bytes = zip_with(&[("../evil.txt", b"x")]);
+        assert!(extract_zip(&bytes, &dest).is_err());
+        assert!(!dest.parent().unwrap().join("evil.txt").exists());
+        let _ = fs::remove_dir_all(&dest);
+    }
+
+    #[test]
+    fn rejects_entries_over_the_unpacked_limit() {
+        let dest = temp_dest("oversize");
+        let big = vec![0u8; MAX_ENTRY_UNPACKED_BYTES as usize + 1];
+        let bytes = zip_with(&[("demo/big.bin", &big)]);
+        assert!(extract_zip(&bytes, &dest).unwrap_err().contains("过大"));
+        let _ = fs::remove_dir_all(&dest);
+    }
+
+    #[test]
+    fn extracts_regular_packages() {
+        let dest = temp_dest("regular");
+        let bytes = zip_with(&[("demo/SKILL.md", b"---\nname: demo\n---\n")]);
+        extract_zip(&bytes, &dest).unwrap();
+        assert!(dest.join("demo").join("SKILL.md").is_file());
+        let _ = fs::remove_dir_all(&dest);
+    }
+}
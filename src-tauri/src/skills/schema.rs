@@ -0,0 +1,205 @@
+use serde_json::Value;
+
+const MAX_SCHEMA_ERRORS: usize = 8;
+
+/// 追加到系统提示词中，要求模型在最终回答末尾输出符合 schema 的 JSON
+pub fn structured_output_instruction(schema: &Value) -> String {
+    let schema_text = serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string());
+    format!(
+        "\n\n## Structured Output\nThis skill declares an output schema. After finishing all tool calls, end your final answer with exactly one ```json fenced block whose content is a JSON value conforming to this JSON Schema. Text before the block is shown to the user as-is.\n```json\n{}\n```",
+        schema_text
+    )
+}
+
+/// 从最终回答中拆出说明文字和最后一个 JSON 代码块
+pub fn split_structured_output(text: &str) -> (String, Option<Value>) {
+    let Some(start) = text.rfind("```json") else {
+        let trimmed = text.trim();
+        return match serde_json::from_str::<Value>(trimmed) {
+            Ok(value) if value.is_object() || value.is_array() => (String::new(), Some(value)),
+            _ => (text.to_string(), None),
+        };
+    };
+    let body_start = start + "```json".len();
+    let Some(body_len) = text[body_start..].find("```") else {
+        return (text.to_string(), None);
+    };
+    let body = text[body_start..body_start + body_len].trim();
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => {
+            let rest = format!(
+                "{}{}",
+                &text[..start],
+                &text[body_start + body_len + 3..]
+            );
+            (rest.trim().to_string(), Some(value))
+        }
+        Err(_) => (text.to_string(), None),
+    }
+}
+
+/// 校验常用的 JSON Schema 子集：type / properties / required / items / enum / minItems / maxItems
+pub fn validate_against_schema(value: &Value, schema: &Value) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    validate_node(value, schema, "$", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        errors.truncate(MAX_SCHEMA_ERRORS);
+        Err(errors)
+    }
+}
+
+fn validate_node(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    if errors.len() >= MAX_SCHEMA_ERRORS {
+        return;
+    }
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(list) => list.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(value, t)) {
+            errors.push(format!("{}: 期望类型 {}，实际为 {}", path, types.join("|"), type_name(value)));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(|v| v.as_array()) {
+        if !options.contains(value) {
+            errors.push(format!("{}: 取值不在 enum 范围内", path));
+        }
+    }
+
+    if let Value::Object(map) = value {
+        if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !map.contains_key(key) {
+                    errors.push(format!("{}: 缺少必填字段 {}", path, key));
+                }
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(|v| v.as_object()) {
+            for (key, child_schema) in properties {
+                if let Some(child) = map.get(key) {
+                    validate_node(child, child_schema, &format!("{}.{}", path, key), errors);
+                }
+            }
+        }
+    }
+
+    if let Value::Array(items) = value {
+        if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+            if (items.len() as u64) < min {
+                errors.push(format!("{}: 至少需要 {} 项", path, min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+            if items.len() as u64 > max {
+                errors.push(format!("{}: 最多 {} 项", path, max));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (idx, item) in items.iter().enumerate() {
+                validate_node(item, item_schema, &format!("{}[{}]", path, idx), errors);
+            }
+        }
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// 没有说明文字时，把结构化结果渲染成 Markdown（对象数组渲染为表格，字符串数组渲染为清单）
+pub fn render_structured_output(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut out = Vec::new();
+            for (key, child) in map {
+                match child {
+                    Value::Array(_) | Value::Object(_) => {
+                        out.push(format!("**{}**\n\n{}", key, render_structured_output(child)));
+                    }
+                    _ => out.push(format!("- **{}**: {}", key, scalar_text(child))),
+                }
+            }
+            out.join("\n\n")
+        }
+        Value::Array(items) if !items.is_empty() && items.iter().all(|v| v.is_object()) => {
+            render_table(items)
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| format!("- {}", scalar_text(item)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => scalar_text(other),
+    }
+}
+
+fn render_table(rows: &[Value]) -> String {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        if let Some(map) = row.as_object() {
+            for key in map.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+    }
+    let mut lines = vec![
+        format!("| {} |", columns.join(" | ")),
+        format!("|{}|", vec![" --- "; columns.len()].join("|")),
+    ];
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|col| {
+                row.get(col)
+                    .map(scalar_text)
+                    .unwrap_or_default()
+                    .replace('|', "\\|")
+                    .replace('\n', " ")
+            })
+            .collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n")
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(true) => "✅".to_string(),
+        Value::Bool(false) => "⬜".to_string(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,158 @@
+use super::SkillMetadata;
+use crate::storage::StorageManager;
+use chrono::{DateTime, Local};
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+const SKILL_USAGE_FILE: &str = "skill_usage.json";
+// 使用记录的影响每 14 天减半
+const USAGE_HALF_LIFE_DAYS: f64 = 14.0;
+// 每个命中的问题词计的分数，最多计 MAX_RELEVANT_TERMS 个，避免长问题压过使用频率
+const RELEVANCE_WEIGHT: f64 = 2.0;
+const MAX_RELEVANT_TERMS: usize = 4;
+
+/// 技能成功调用的次数和最近一次时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillUsage {
+    pub count: u32,
+    pub last_used: DateTime<Local>,
+}
+
+fn usage_lock() -> &'static ParkingMutex<()> {
+    static LOCK: OnceLock<ParkingMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| ParkingMutex::new(()))
+}
+
+pub fn load_skill_usage() -> HashMap<String, SkillUsage> {
+    let storage = StorageManager::new();
+    let path = storage.get_data_dir().join(SKILL_USAGE_FILE);
+    if !path.exists() {
+        return HashMap::new();
+    }
+    storage
+        .read_data_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 记录一次成功调用，用于系统提示词中的技能排序
+pub fn record_skill_use(name: &str) {
+    let _guard = usage_lock().lock();
+    let mut usage = load_skill_usage();
+    let entry = usage.entry(name.to_string()).or_insert_with(|| SkillUsage {
+        count: 0,
+        last_used: Local::now(),
+    });
+    entry.count = entry.count.saturating_add(1);
+    entry.last_used = Local::now();
+    let storage = StorageManager::new();
+    let saved = serde_json::to_string_pretty(&usage)
+        .map_err(|e| format!("序列化技能使用记录失败: {}", e))
+        .and_then(|content| {
+            storage.write_data_file(&storage.get_data_dir().join(SKILL_USAGE_FILE), content.as_bytes())
+        });
+    if let Err(err) = saved {
+        crate::logs::warn("skills", format!("保存技能使用记录失败: {}", err));
+    }
+}
+
+/// 英文按单词、中文按相邻两字切分，用于粗略的相关度匹配
+fn query_terms(text: &str) -> HashSet<String> {
+    let lower = text.to_lowercase();
+    let mut terms: HashSet<String> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| word.len() >= 2)
+        .map(str::to_string)
+        .collect();
+    let cjk: Vec<char> = lower.chars().filter(|c| !c.is_ascii() && c.is_alphanumeric()).collect();
+    terms.extend(cjk.windows(2).map(|pair| pair.iter().collect::<String>()));
+    terms
+}
+
+/// 按与问题的相关度和使用频率/最近使用排序，最多保留 limit 个（0 表示不限制）；
+/// 返回保留的技能和未列出的数量
+pub fn rank_skills<'a>(
+    skills: Vec<&'a SkillMetadata>,
+    query: &str,
+    usage: &HashMap<String, SkillUsage>,
+    limit: usize,
+    now: DateTime<Local>,
+) -> (Vec<&'a SkillMetadata>, usize) {
+    let terms = query_terms(query);
+    let mut scored: Vec<(f64, &SkillMetadata)> = skills
+        .into_iter()
+        .map(|skill| {
+            let haystack = format!(
+                "{} {} {}",
+                skill.name.replace('-', " "),
+                skill.description,
+                skill.category.as_deref().unwrap_or("")
+            )
+            .to_lowercase();
+            let matched = terms.iter().filter(|term| haystack.contains(term.as_str())).count();
+            let relevance = matched.min(MAX_RELEVANT_TERMS) as f64;
+            let frequency = usage.get(&skill.name).map_or(0.0, |item| {
+                let days = now.signed_duration_since(item.last_used).num_hours().max(0) as f64 / 24.0;
+                (1.0 + item.count as f64).ln() * 0.5f64.powf(days / USAGE_HALF_LIFE_DAYS)
+            });
+            (relevance * RELEVANCE_WEIGHT + frequency, skill)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    let total = scored.len();
+    if limit > 0 {
+        scored.truncate(limit);
+    }
+    let omitted = total - scored.len();
+    (scored.into_iter().map(|(_, skill)| skill).collect(), omitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn skill(name: &str, description: &str) -> SkillMetadata {
+        SkillMetadata {
+            name: name.to_string(),
+            description: description.to_string(),
+            allowed_tools: None,
+            model: None,
+            context: None,
+            user_invocable: None,
+            disable_model_invocation: None,
+            metadata: None,
+            output_schema: None,
+            icon: None,
+            category: None,
+            author: None,
+            homepage: None,
+            examples: None,
+        }
+    }
+
+    #[test]
+    fn test_rank_skills_prefers_relevant_then_recent() {
+        let now = Local::now();
+        let skills = vec![
+            skill("export", "导出今天的工作记录"),
+            skill("weekly-report", "生成周报"),
+            skill("translate", "翻译选中的文字"),
+        ];
+        let mut usage = HashMap::new();
+        usage.insert(
+            "translate".to_string(),
+            SkillUsage {
+                count: 5,
+                last_used: now - Duration::days(1),
+            },
+        );
+        let (ranked, omitted) = rank_skills(skills.iter().collect(), "帮我写一份周报", &usage, 2, now);
+        let names: Vec<&str> = ranked.iter().map(|skill| skill.name.as_str()).collect();
+        assert_eq!(names, vec!["weekly-report", "translate"]);
+        assert_eq!(omitted, 1);
+    }
+}
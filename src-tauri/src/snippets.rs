@@ -0,0 +1,190 @@
+use crate::storage::StorageManager;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const SNIPPETS_FILE: &str = "snippets.json";
+const MAX_TITLE_CHARS: usize = 80;
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// 从助手回复中保存下来的片段（命令、配置、代码等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub language: String,  // bash / json / yaml ...，空表示纯文本
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub conversation_id: String,  // 来源会话
+    pub created_at: String,
+}
+
+/// 标题为空时取内容第一行非空文本
+fn default_title(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| line.trim().trim_start_matches("```").trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("未命名片段")
+        .chars()
+        .take(MAX_TITLE_CHARS)
+        .collect()
+}
+
+fn snippet_matches(snippet: &Snippet, terms: &[String]) -> bool {
+    let haystack = format!(
+        "{}\n{}\n{}\n{}",
+        snippet.title,
+        snippet.tags.join(" "),
+        snippet.language,
+        snippet.content
+    )
+    .to_lowercase();
+    terms.iter().all(|term| haystack.contains(term.as_str()))
+}
+
+impl StorageManager {
+    fn load_snippets(&self) -> Result<Vec<Snippet>, String> {
+        let path = self.get_data_dir().join(SNIPPETS_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_snippets(&self, snippets: &[Snippet]) -> Result<(), String> {
+        let path = self.get_data_dir().join(SNIPPETS_FILE);
+        if snippets.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("删除片段文件失败: {}", e))?;
+            }
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(snippets)
+            .map_err(|e| format!("序列化片段失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    pub fn add_snippet(
+        &self,
+        title: Option<String>,
+        content: String,
+        language: Option<String>,
+        tags: Vec<String>,
+        conversation_id: Option<String>,
+    ) -> Result<Snippet, String> {
+        if content.trim().is_empty() {
+            return Err("片段内容不能为空".to_string());
+        }
+        let now = Local::now();
+        let title = match title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) {
+            Some(title) => title.chars().take(MAX_TITLE_CHARS).collect(),
+            None => default_title(&content),
+        };
+        let mut tags: Vec<String> = tags
+            .into_iter()
+            .map(|tag| tag.trim().trim_start_matches('#').to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.dedup();
+        let snippet = Snippet {
+            id: format!("snip-{}", now.timestamp_millis()),
+            title,
+            content,
+            language: language.unwrap_or_default().trim().to_lowercase(),
+            tags,
+            conversation_id: conversation_id.unwrap_or_default(),
+            created_at: now.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        };
+        let mut snippets = self.load_snippets()?;
+        snippets.push(snippet.clone());
+        self.save_snippets(&snippets)?;
+        Ok(snippet)
+    }
+
+    /// 按关键字搜索（标题、标签、语言、内容，空格分隔的词需全部命中），最新在前
+    pub fn search_snippets(&self, query: Option<&str>, limit: Option<usize>) -> Result<Vec<Snippet>, String> {
+        let terms: Vec<String> = query
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|term| term.to_lowercase())
+            .collect();
+        let snippets = self.load_snippets()?;
+        let iter = snippets
+            .into_iter()
+            .rev()
+            .filter(|snippet| terms.is_empty() || snippet_matches(snippet, &terms));
+        Ok(match limit {
+            Some(limit) => iter.take(limit.max(1)).collect(),
+            None => iter.collect(),
+        })
+    }
+
+    pub fn get_snippet(&self, id: &str) -> Result<Snippet, String> {
+        self.load_snippets()?
+            .into_iter()
+            .find(|snippet| snippet.id == id)
+            .ok_or_else(|| format!("未找到片段: {}", id))
+    }
+
+    pub fn delete_snippet(&self, id: &str) -> Result<bool, String> {
+        let mut snippets = self.load_snippets()?;
+        let before = snippets.len();
+        snippets.retain(|snippet| snippet.id != id);
+        if snippets.len() == before {
+            return Ok(false);
+        }
+        self.save_snippets(&snippets)?;
+        Ok(true)
+    }
+}
+
+/// snippets 工具：search 返回标题列表，get 返回完整内容
+pub fn snippets_tool(storage: &StorageManager, args: &serde_json::Value) -> Result<String, String> {
+    let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("search");
+    match action {
+        "get" => {
+            let id = args
+                .get("id")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing id parameter".to_string())?;
+            let snippet = storage.get_snippet(id.trim())?;
+            let fence = if snippet.language.is_empty() { "" } else { snippet.language.as_str() };
+            Ok(format!(
+                "{} ({})\n```{}\n{}\n```",
+                snippet.title, snippet.id, fence, snippet.content
+            ))
+        }
+        "search" => {
+            let query = args.get("query").and_then(|v| v.as_str());
+            let limit = args
+                .get("limit")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize)
+                .unwrap_or(DEFAULT_SEARCH_LIMIT);
+            let snippets = storage.search_snippets(query, Some(limit))?;
+            if snippets.is_empty() {
+                return Ok("没有找到匹配的片段。".to_string());
+            }
+            let lines: Vec<String> = snippets
+                .iter()
+                .map(|snippet| {
+                    let mut line = format!("- {} | {}", snippet.id, snippet.title);
+                    if !snippet.language.is_empty() {
+                        line.push_str(&format!(" [{}]", snippet.language));
+                    }
+                    if !snippet.tags.is_empty() {
+                        line.push_str(&format!(" #{}", snippet.tags.join(" #")));
+                    }
+                    line
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        other => Ok(format!("未知操作: {}", other)),
+    }
+}
@@ -0,0 +1,94 @@
+use super::StorageManager;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use tokio::sync::oneshot;
+
+/// 存储任务优先级：交互式读取（对话检索）总是先于后台写入（截屏保存）执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoragePriority {
+    Interactive,
+    Background,
+}
+
+type StorageJob = Box<dyn FnOnce(&StorageManager) + Send>;
+
+#[derive(Default)]
+struct JobQueues {
+    interactive: VecDeque<StorageJob>,
+    background: VecDeque<StorageJob>,
+}
+
+/// 单线程存储 actor：摘要记录和各类索引（日索引、文字层、向量）的读写都经由这里串行化，
+/// 按优先级出队；对话检索用 Interactive，截屏保存和后台任务用 Background
+pub struct StorageActor {
+    queues: Arc<(Mutex<JobQueues>, Condvar)>,
+}
+
+impl StorageActor {
+    fn start() -> Self {
+        let queues: Arc<(Mutex<JobQueues>, Condvar)> =
+            Arc::new((Mutex::new(JobQueues::default()), Condvar::new()));
+        let worker_queues = Arc::clone(&queues);
+
+        std::thread::Builder::new()
+            .name("storage-actor".to_string())
+            .spawn(move || {
+                let storage = StorageManager::new();
+                let (lock, cvar) = &*worker_queues;
+                loop {
+                    let job = {
+                        let mut queues = match lock.lock() {
+                            Ok(guard) => guard,
+                            Err(poisoned) => poisoned.into_inner(),
+                        };
+                        loop {
+                            if let Some(job) = queues.interactive.pop_front() {
+                                break job;
+                            }
+                            if let Some(job) = queues.background.pop_front() {
+                                break job;
+                            }
+                            queues = match cvar.wait(queues) {
+                                Ok(guard) => guard,
+                                Err(poisoned) => poisoned.into_inner(),
+                            };
+                        }
+                    };
+                    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job(&storage)));
+                }
+            })
+            .expect("failed to start storage actor thread");
+
+        Self { queues }
+    }
+
+    /// 提交任务并等待结果
+    pub async fn run<T, F>(&self, priority: StoragePriority, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&StorageManager) -> Result<T, String> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: StorageJob = Box::new(move |storage| {
+            let _ = tx.send(f(storage));
+        });
+
+        {
+            let (lock, cvar) = &*self.queues;
+            let mut queues = lock.lock().map_err(|_| "存储队列不可用".to_string())?;
+            match priority {
+                StoragePriority::Interactive => queues.interactive.push_back(job),
+                StoragePriority::Background => queues.background.push_back(job),
+            }
+            cvar.notify_one();
+        }
+
+        rx.await.map_err(|_| "存储任务执行失败".to_string())?
+    }
+}
+
+/// 全局存储 actor
+pub fn storage_actor() -> &'static StorageActor {
+    static STORAGE_ACTOR: OnceLock<StorageActor> = OnceLock::new();
+    STORAGE_ACTOR.get_or_init(StorageActor::start)
+}
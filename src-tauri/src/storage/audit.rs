@@ -0,0 +1,163 @@
+use super::{storage_actor, AuditConfig, StorageManager, StoragePriority};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use chrono::{Local, NaiveDate};
+use ed25519_dalek::SigningKey;
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+const AUDIT_DIR: &str = "audit";
+const SIGNING_KEY_FILE: &str = "signing.key";
+
+static AUDIT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 一条审计事件，按天逐行追加到 data_dir/audit/YYYY-MM-DD.jsonl，不随摘要保留天数清理。
+/// 每行带 prev_hash（上一行原文的 sha256），删除或改动中间的行会使链断开
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: String,
+    pub kind: String,  // capture / model_request / tool_call / file_change / file_revert / alert_action
+    pub detail: serde_json::Value,
+}
+
+// 同一天的事件文件可能被存储队列和修改日志同时写入；锁内保存各天最后一行的哈希
+fn audit_chain() -> &'static ParkingMutex<HashMap<String, String>> {
+    static CHAIN: OnceLock<ParkingMutex<HashMap<String, String>>> = OnceLock::new();
+    CHAIN.get_or_init(|| ParkingMutex::new(HashMap::new()))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn apply_audit_config(config: &AuditConfig) {
+    AUDIT_ENABLED.store(config.enabled, Ordering::Relaxed);
+}
+
+pub fn audit_enabled() -> bool {
+    AUDIT_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 在异步流程中记录审计事件；写入走后台存储队列，不阻塞调用方
+pub fn record_audit_event(kind: &str, detail: serde_json::Value) {
+    if !audit_enabled() {
+        return;
+    }
+    let event = AuditEvent {
+        timestamp: Local::now().to_rfc3339(),
+        kind: kind.to_string(),
+        detail,
+    };
+    tauri::async_runtime::spawn(async move {
+        let result = storage_actor()
+            .run(StoragePriority::Background, move |storage| storage.append_audit_event(event))
+            .await;
+        if let Err(err) = result {
+            eprintln!("[audit] 记录审计事件失败: {}", err);
+        }
+    });
+}
+
+impl StorageManager {
+    fn audit_path(&self, date: &str) -> PathBuf {
+        self.data_dir.join(AUDIT_DIR).join(format!("{}.jsonl", date))
+    }
+
+    // 旧版本整天保存为一个 JSON 数组
+    fn legacy_audit_path(&self, date: &str) -> PathBuf {
+        self.data_dir.join(AUDIT_DIR).join(format!("{}.json", date))
+    }
+
+    fn audit_lines(&self, date: &str) -> Vec<String> {
+        let path = self.audit_path(date);
+        if !path.exists() {
+            return Vec::new();
+        }
+        self.read_data_lines(&path).unwrap_or_default()
+    }
+
+    pub fn load_audit_events(&self, date: &str) -> Vec<AuditEvent> {
+        let legacy = self.legacy_audit_path(date);
+        let mut events: Vec<AuditEvent> = if legacy.exists() {
+            self.read_data_string(&legacy)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        events.extend(
+            self.audit_lines(date)
+                .iter()
+                .filter_map(|line| serde_json::from_str(line).ok()),
+        );
+        events
+    }
+
+    pub fn append_audit_event(&self, event: AuditEvent) -> Result<(), String> {
+        let date = event
+            .timestamp
+            .get(..10)
+            .filter(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+        let mut chain = audit_chain().lock();
+        fs::create_dir_all(self.data_dir.join(AUDIT_DIR)).map_err(|e| format!("创建审计目录失败: {}", e))?;
+        // 每天首次写入时从文件最后一行接上哈希链
+        let prev_hash = match chain.get(&date) {
+            Some(hash) => hash.clone(),
+            None => self
+                .audit_lines(&date)
+                .last()
+                .map(|line| sha256_hex(line.as_bytes()))
+                .unwrap_or_default(),
+        };
+        let mut line = serde_json::to_value(&event).map_err(|e| format!("序列化审计事件失败: {}", e))?;
+        line["prev_hash"] = serde_json::Value::String(prev_hash);
+        let line = line.to_string();
+        self.append_data_line(&self.audit_path(&date), &line)?;
+        chain.insert(date, sha256_hex(line.as_bytes()));
+        Ok(())
+    }
+
+    /// 已在存储线程内的调用方（如修改日志）直接写入
+    pub fn record_audit_event_sync(&self, kind: &str, detail: serde_json::Value) {
+        if !audit_enabled() {
+            return;
+        }
+        let event = AuditEvent {
+            timestamp: Local::now().to_rfc3339(),
+            kind: kind.to_string(),
+            detail,
+        };
+        if let Err(err) = self.append_audit_event(event) {
+            eprintln!("[audit] 记录审计事件失败: {}", err);
+        }
+    }
+
+    /// 导出签名用的本机密钥，首次使用时生成，与审计记录保存在同一目录。
+    /// 未启用存储加密时密钥和记录都是明文，能改动数据目录的人也能重新生成哈希链和签名，
+    /// 签名只能证明导出文件在导出后未被改动；启用加密后两者都只能在解锁后读写
+    pub fn audit_signing_key(&self) -> Result<SigningKey, String> {
+        let path = self.data_dir.join(AUDIT_DIR).join(SIGNING_KEY_FILE);
+        if path.exists() {
+            let bytes = self.read_data_file(&path)?;
+            let bytes: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| "审计签名密钥已损坏".to_string())?;
+            return Ok(SigningKey::from_bytes(&bytes));
+        }
+        fs::create_dir_all(self.data_dir.join(AUDIT_DIR)).map_err(|e| format!("创建审计目录失败: {}", e))?;
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        self.write_data_file(&path, &bytes)?;
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+}
@@ -0,0 +1,131 @@
+use super::Config;
+use serde_json::Value;
+
+impl Config {
+    /// 把前端提交的部分配置合并到当前配置上：提交里没有的段和字段保持原值。
+    /// 只有配置结构体会逐字段合并，映射表、列表和可选值整体替换，删除其中的条目才能生效
+    pub fn merged_with(&self, update: Value) -> Result<Config, String> {
+        let template = serde_json::to_value(Config::default()).map_err(|e| e.to_string())?;
+        let mut merged = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        merge_value(&mut merged, update, Some(&template));
+        serde_json::from_value(merged).map_err(|e| format!("配置格式错误: {}", e))
+    }
+}
+
+// 默认配置里是非空对象的位置对应结构体，其余（如 HashMap 默认为空对象）按值替换
+fn merge_value(base: &mut Value, update: Value, template: Option<&Value>) {
+    let is_struct = matches!(template, Some(Value::Object(fields)) if !fields.is_empty());
+    match (base, update) {
+        (Value::Object(base), Value::Object(update)) if is_struct => {
+            for (key, value) in update {
+                let field_template = template.and_then(|t| t.get(&key));
+                match base.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value, field_template),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, update) => *base = update,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 与设置页 buildConfigFromForm 提交的内容一致：只包含表单管理的字段
+    fn form_payload() -> Value {
+        json!({
+            "model": {
+                "provider": "api",
+                "api": {
+                    "type": "openai",
+                    "request_format": "chat_completions",
+                    "responses_query_params": {},
+                    "responses_headers": {},
+                    "endpoint": "https://api.openai.com/v1",
+                    "api_key": "sk-new",
+                    "model": "gpt-4o",
+                },
+                "ollama": { "endpoint": "http://localhost:11434", "model": "llava" },
+            },
+            "capture": {
+                "enabled": true,
+                "interval_ms": 2000,
+                "compress_quality": 80,
+                "skip_unchanged": true,
+                "change_threshold": 0.95,
+                "recent_summary_limit": 8,
+                "recent_detail_limit": 3,
+                "alert_confidence_threshold": 0.7,
+                "alert_cooldown_seconds": 120,
+            },
+            "storage": {
+                "retention_days": 7,
+                "max_screenshots": 10000,
+                "max_context_chars": 1000000,
+                "max_context_tokens": 128000,
+                "context_compress_trigger_ratio": 0.92,
+                "auto_clear_on_start": false,
+                "context_mode": "auto",
+                "context_detail_hours": 24,
+            },
+            "tools": {
+                "mode": "whitelist",
+                "allowed_commands": [],
+                "allowed_dirs": [],
+                "ask_approval": false,
+            },
+            "ui": { "show_progress": true },
+        })
+    }
+
+    #[test]
+    fn form_save_keeps_fields_the_form_does_not_manage() {
+        let mut stored = Config::default();
+        stored.capture.privacy.blocked_apps = vec!["KeePass".to_string()];
+        stored.capture.alert_webhook_url = "https://example.com/hook".to_string();
+        stored.capture.alert_idle_threshold_seconds = 42;
+        stored.capture.target.mode = "window".to_string();
+        stored.capture.target.window_title = "Editor".to_string();
+        stored.model.api.responses_headers.insert("X-Old".to_string(), "1".to_string());
+        stored.model.api.organization = "org-1".to_string();
+        stored.model.profiles = serde_json::from_value(json!([{ "name": "fast", "model": "gpt-4o-mini" }])).unwrap();
+        stored.api_server.enabled = true;
+        stored.api_server.token = "secret".to_string();
+        stored.tools.mcp_servers = serde_json::from_value(json!([{ "name": "files", "command": "mcp-files" }])).unwrap();
+
+        let merged = stored.merged_with(form_payload()).unwrap();
+
+        // 表单字段生效
+        assert_eq!(merged.capture.interval_ms, 2000);
+        assert_eq!(merged.model.api.api_key, "sk-new");
+        assert_eq!(merged.tools.mode, "whitelist");
+        // 映射表按提交整体替换，删除的请求头不会残留
+        assert!(merged.model.api.responses_headers.is_empty());
+        // 表单不管理的段和字段保持原值
+        assert_eq!(merged.capture.privacy.blocked_apps, vec!["KeePass".to_string()]);
+        assert_eq!(merged.capture.alert_webhook_url, "https://example.com/hook");
+        assert_eq!(merged.capture.alert_idle_threshold_seconds, 42);
+        assert_eq!(merged.capture.target.mode, "window");
+        assert_eq!(merged.capture.target.window_title, "Editor");
+        assert_eq!(merged.model.api.organization, "org-1");
+        assert_eq!(merged.model.profiles.len(), 1);
+        assert_eq!(merged.model.profiles[0].name, "fast");
+        assert!(merged.api_server.enabled);
+        assert_eq!(merged.api_server.token, "secret");
+        assert_eq!(merged.tools.mcp_servers.len(), 1);
+        assert_eq!(merged.tools.mcp_servers[0].name, "files");
+    }
+
+    #[test]
+    fn lists_in_the_update_replace_stored_lists() {
+        let mut stored = Config::default();
+        stored.tools.mcp_servers = serde_json::from_value(json!([{ "name": "files", "command": "mcp-files" }])).unwrap();
+        let merged = stored.merged_with(json!({ "tools": { "mcp_servers": [] } })).unwrap();
+        assert!(merged.tools.mcp_servers.is_empty());
+    }
+}
@@ -0,0 +1,549 @@
+use super::{AggregatedRecord, SearchQuery, SearchResult, SummaryRecord};
+use chrono::NaiveDateTime;
+use std::fmt::Write as _;
+
+const AGGREGATE_HEADER: &str = "## 操作概要\n\n";
+const RECORD_HEADER: &str = "## 详细记录\n\n";
+const DETAIL_PREFIX: &str = "  细节: ";
+const DETAIL_OMITTED: &str = "  ...(细节已省略)\n";
+const RECORDS_OMITTED: &str = "...(更多记录已省略)\n";
+// 预留给标题、省略提示等超出预算的少量文本
+const CONTEXT_SLACK_CHARS: usize = 64;
+// 连续至少这么多条相似记录才合并成一行
+const MIN_COMPACT_RUN: usize = 3;
+// 摘要字符二元组的 Dice 相似度达到该值视为几乎相同
+const COMPACT_SIMILARITY: f32 = 0.7;
+// 相邻记录间隔超过该值时不合并，中间可能离开过
+const MAX_COMPACT_GAP_SECS: i64 = 10 * 60;
+
+/// 上下文构建策略：决定哪些记录优先进入有限的上下文预算
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextStrategy {
+    /// 最近的记录优先（默认）
+    RecentFirst,
+    /// 有问题/报错的记录优先，其余按时间补齐
+    IssueFirst,
+    /// 只保留指定应用的记录；未指定时从问题关键词中推断
+    AppFiltered(Option<String>),
+    /// 按与问题关键词的相关度排序
+    Semantic(Vec<String>),
+}
+
+impl ContextStrategy {
+    /// 解析策略名（recent_first | issue_first | app_filtered[:app] | semantic）
+    pub fn parse(name: &str, query: &SearchQuery) -> Self {
+        let name = name.trim();
+        let (kind, arg) = match name.split_once(':') {
+            Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+            None => (name, None),
+        };
+        match kind.to_lowercase().replace('-', "_").as_str() {
+            "issue_first" | "issues" => ContextStrategy::IssueFirst,
+            "app_filtered" | "app" => ContextStrategy::AppFiltered(
+                arg.filter(|a| !a.is_empty()).map(|a| a.to_string()),
+            ),
+            "semantic" | "relevance" => ContextStrategy::Semantic(query.keywords.clone()),
+            _ => ContextStrategy::RecentFirst,
+        }
+    }
+
+    /// 聚合概要占总预算的比例，其余留给详细记录（未用完的部分会顺延给详细记录）
+    pub fn default_aggregate_ratio(&self) -> f32 {
+        match self {
+            ContextStrategy::RecentFirst => 0.4,
+            ContextStrategy::IssueFirst => 0.3,
+            ContextStrategy::AppFiltered(_) => 0.3,
+            ContextStrategy::Semantic(_) => 0.2,
+        }
+    }
+}
+
+impl SearchResult {
+    /// 按策略构建上下文字符串，控制在指定字符数内
+    pub fn build_context(
+        &self,
+        strategy: &ContextStrategy,
+        aggregate_ratio: Option<f32>,
+        max_chars: usize,
+        include_detail: bool,
+        detail_cutoff: Option<&str>,
+    ) -> String {
+        let app_filter = match strategy {
+            ContextStrategy::AppFiltered(Some(app)) => Some(app.to_lowercase()),
+            ContextStrategy::AppFiltered(None) => self.infer_app_filter(),
+            _ => None,
+        };
+
+        let ratio = aggregate_ratio
+            .unwrap_or_else(|| strategy.default_aggregate_ratio())
+            .clamp(0.0, 1.0);
+        let aggregate_budget = (max_chars as f32 * ratio) as usize;
+
+        let mut context = String::with_capacity(max_chars.saturating_add(CONTEXT_SLACK_CHARS));
+        let aggregated: Vec<&AggregatedRecord> = self
+            .aggregated
+            .iter()
+            .filter(|agg| {
+                app_filter.as_ref().map_or(true, |app| {
+                    agg.apps.iter().any(|a| a.to_lowercase().contains(app))
+                })
+            })
+            .collect();
+        let aggregate_used = render_aggregates(&aggregated, aggregate_budget, &mut context);
+
+        let records: Vec<&SummaryRecord> = self
+            .records
+            .iter()
+            .filter(|record| {
+                app_filter
+                    .as_ref()
+                    .map_or(true, |app| record_matches_app(record, app))
+            })
+            .collect();
+        let ordered = prioritize_records(strategy, compact_records(&records));
+        render_records(
+            &ordered,
+            max_chars.saturating_sub(aggregate_used),
+            include_detail,
+            detail_cutoff,
+            &mut context,
+        );
+
+        if context.is_empty() {
+            context = "目前没有相关的操作记录。".to_string();
+        }
+
+        context
+    }
+
+    /// 从记录中找出现次数最多的应用，作为 app_filtered 的默认过滤条件
+    fn infer_app_filter(&self) -> Option<String> {
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for record in &self.records {
+            let app = record.app.trim().to_lowercase();
+            if !app.is_empty() && app != "unknown" {
+                *counts.entry(app).or_insert(0) += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .map(|(app, _)| app)
+    }
+}
+
+fn record_matches_app(record: &SummaryRecord, app: &str) -> bool {
+    record.app.to_lowercase().contains(app)
+        || record.process_name.to_lowercase().contains(app)
+        || record.window_title.to_lowercase().contains(app)
+}
+
+/// 上下文中的一行：单条记录，或一段连续相似记录合并后的结果
+#[derive(Clone, Copy)]
+struct ContextEntry<'a> {
+    first: &'a SummaryRecord,
+    record: &'a SummaryRecord,  // 代表记录（最新一条），摘要、排序和细节都取自它
+    count: usize,
+}
+
+impl<'a> ContextEntry<'a> {
+    fn single(record: &'a SummaryRecord) -> Self {
+        Self {
+            first: record,
+            record,
+            count: 1,
+        }
+    }
+}
+
+fn parse_record_time(record: &SummaryRecord) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(record.timestamp.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+/// 摘要的字符二元组集合，排序去重后写入复用的缓冲区；每个二元组打包成一个 u64
+fn summary_bigrams(summary: &str, out: &mut Vec<u64>) {
+    out.clear();
+    let mut prev: Option<char> = None;
+    for c in summary.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        if let Some(p) = prev {
+            out.push(((p as u64) << 32) | c as u64);
+        }
+        prev = Some(c);
+    }
+    out.sort_unstable();
+    out.dedup();
+}
+
+fn bigram_similarity(a: &[u64], b: &[u64]) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let (mut i, mut j, mut shared) = (0, 0, 0usize);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    2.0 * shared as f32 / (a.len() + b.len()) as f32
+}
+
+/// 把连续、同一应用、摘要几乎相同且没有问题的记录合并为一行，给有区别的事件和细节留出预算；
+/// 输入按时间升序，相似度与每段的第一条比较，避免逐条漂移
+fn compact_records<'a>(records: &[&'a SummaryRecord]) -> Vec<ContextEntry<'a>> {
+    let mut entries = Vec::with_capacity(records.len());
+    let mut run: Vec<&SummaryRecord> = Vec::new();
+    let mut run_end: Option<NaiveDateTime> = None;
+    let mut anchor = Vec::new();
+    let mut bigrams = Vec::new();
+    let flush = |run: &mut Vec<&'a SummaryRecord>, entries: &mut Vec<ContextEntry<'a>>| {
+        if run.len() >= MIN_COMPACT_RUN {
+            entries.push(ContextEntry {
+                first: run[0],
+                record: run[run.len() - 1],
+                count: run.len(),
+            });
+        } else {
+            entries.extend(run.iter().copied().map(ContextEntry::single));
+        }
+        run.clear();
+    };
+
+    for &record in records {
+        if record.has_issue {
+            flush(&mut run, &mut entries);
+            entries.push(ContextEntry::single(record));
+            continue;
+        }
+        summary_bigrams(&record.summary, &mut bigrams);
+        let time = parse_record_time(record);
+        let joins = run.last().map_or(false, |last| {
+            let close = match (run_end, time) {
+                (Some(prev), Some(current)) => (current - prev).num_seconds() <= MAX_COMPACT_GAP_SECS,
+                _ => false,
+            };
+            close
+                && last.app.trim().eq_ignore_ascii_case(record.app.trim())
+                && bigram_similarity(&anchor, &bigrams) >= COMPACT_SIMILARITY
+        });
+        if !joins {
+            flush(&mut run, &mut entries);
+            std::mem::swap(&mut anchor, &mut bigrams);
+        }
+        run.push(record);
+        run_end = time;
+    }
+    flush(&mut run, &mut entries);
+    entries
+}
+
+/// 返回按优先级排列的记录（越靠前越先占用预算）
+fn prioritize_records<'a>(
+    strategy: &ContextStrategy,
+    entries: Vec<ContextEntry<'a>>,
+) -> Vec<ContextEntry<'a>> {
+    let mut ordered = entries;
+    // 记录按时间升序存储，先反转为最新优先
+    ordered.reverse();
+    match strategy {
+        ContextStrategy::RecentFirst | ContextStrategy::AppFiltered(_) => {}
+        ContextStrategy::IssueFirst => {
+            ordered.sort_by_key(|entry| !entry.record.has_issue);
+        }
+        ContextStrategy::Semantic(keywords) => {
+            let keywords: Vec<String> = keywords
+                .iter()
+                .map(|k| k.trim().to_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect();
+            if !keywords.is_empty() {
+                // 打分需要小写化全文，每条记录只算一次
+                ordered.sort_by_cached_key(|entry| std::cmp::Reverse(relevance_score(entry.record, &keywords)));
+            }
+        }
+    }
+    ordered
+}
+
+fn relevance_score(record: &SummaryRecord, keywords: &[String]) -> usize {
+    let summary = record.summary.to_lowercase();
+    let detail = record.detail.to_lowercase();
+    let app_fields = [
+        record.app.to_lowercase(),
+        record.process_name.to_lowercase(),
+        record.window_title.to_lowercase(),
+    ];
+    let record_keywords: Vec<String> = record.keywords.iter().map(|k| k.to_lowercase()).collect();
+    keywords
+        .iter()
+        .map(|kw| {
+            summary.matches(kw.as_str()).count() * 3
+                + app_fields
+                    .iter()
+                    .map(|field| field.matches(kw.as_str()).count())
+                    .sum::<usize>()
+                    * 2
+                + detail.matches(kw.as_str()).count()
+                + record_keywords.iter().filter(|k| *k == kw).count() * 2
+        })
+        .sum()
+}
+
+fn render_aggregates(aggregated: &[&AggregatedRecord], budget: usize, context: &mut String) -> usize {
+    if aggregated.is_empty() {
+        return 0;
+    }
+
+    // 直接写入输出缓冲区，一条都放不下时回退
+    let start = context.len();
+    context.push_str(AGGREGATE_HEADER);
+    let mut used = AGGREGATE_HEADER.len();
+    let mut added = false;
+    let mut line = String::new();
+    for agg in aggregated {
+        line.clear();
+        let _ = writeln!(
+            line,
+            "- [{} {} ~ {}] {}",
+            agg.start_time.get(..10).unwrap_or(&agg.start_time),
+            agg.start_time.get(11..16).unwrap_or(""),
+            agg.end_time.get(11..16).unwrap_or(""),
+            agg.summary
+        );
+        if used + line.len() > budget {
+            break;
+        }
+        context.push_str(&line);
+        used += line.len();
+        added = true;
+
+        // 如果有错误，添加错误信息
+        if let Some(ref err) = agg.error_summary {
+            line.clear();
+            let _ = writeln!(line, "  ⚠️ 错误: {}", err);
+            if used + line.len() <= budget {
+                context.push_str(&line);
+                used += line.len();
+            }
+        }
+    }
+
+    if !added {
+        context.truncate(start);
+        return 0;
+    }
+    context.push('\n');
+    used + 1
+}
+
+#[derive(Clone, Copy)]
+enum DetailRender {
+    Skip,
+    Full,
+    Omitted,
+}
+
+fn record_time_parts(record: &SummaryRecord) -> (&str, &str) {
+    let timestamp = record.timestamp.as_str();
+    (timestamp.get(..10).unwrap_or(timestamp), timestamp.get(11..19).unwrap_or(""))
+}
+
+/// 合并行的时间段和截屏次数："14:00–14:25"、"（12 次截屏）"
+fn run_parts(entry: &ContextEntry) -> (String, String) {
+    let start = entry.first.timestamp.get(11..16).unwrap_or("");
+    let end = entry.record.timestamp.get(11..16).unwrap_or("");
+    (format!("{}–{}", start, end), format!("（{} 次截屏）", entry.count))
+}
+
+/// "- [日期 时间] 摘要\n" 的字节数，合并行为 "- [日期 开始–结束] 摘要（N 次截屏）\n"
+fn entry_line_len(entry: &ContextEntry) -> usize {
+    let (date, time) = record_time_parts(entry.record);
+    if entry.count > 1 {
+        let (span, suffix) = run_parts(entry);
+        return "- [".len() + date.len() + 1 + span.len() + "] ".len() + entry.record.summary.len() + suffix.len() + 1;
+    }
+    "- [".len() + date.len() + 1 + time.len() + "] ".len() + entry.record.summary.len() + 1
+}
+
+fn render_records(
+    ordered: &[ContextEntry],
+    budget: usize,
+    include_detail: bool,
+    detail_cutoff: Option<&str>,
+    context: &mut String,
+) {
+    if ordered.is_empty() {
+        return;
+    }
+
+    // 先只按长度选出放得下的记录，再按时间顺序一次写入，避免逐条拼接临时字符串
+    let mut current_len = RECORD_HEADER.len();
+    let mut selected: Vec<(ContextEntry, DetailRender)> = Vec::new();
+    let mut truncated = false;
+
+    for &entry in ordered {
+        let record = entry.record;
+        let line_len = entry_line_len(&entry);
+        if current_len + line_len > budget {
+            truncated = true;
+            break;
+        }
+        current_len += line_len;
+
+        let allow_detail = include_detail
+            && detail_cutoff.map_or(true, |cutoff| record.timestamp.as_str() >= cutoff);
+        if !allow_detail || record.detail.is_empty() {
+            selected.push((entry, DetailRender::Skip));
+            continue;
+        }
+        // 换行替换为空格，长度不变
+        let detail_len = DETAIL_PREFIX.len() + record.detail.len() + 1;
+        if current_len + detail_len > budget {
+            current_len += DETAIL_OMITTED.len();
+            truncated = true;
+            selected.push((entry, DetailRender::Omitted));
+            break;
+        }
+        current_len += detail_len;
+        selected.push((entry, DetailRender::Full));
+    }
+
+    if selected.is_empty() {
+        return;
+    }
+
+    // 输出时恢复时间顺序
+    selected.sort_by(|a, b| a.0.first.timestamp.cmp(&b.0.first.timestamp));
+    context.reserve(current_len + RECORDS_OMITTED.len());
+    context.push_str(RECORD_HEADER);
+    for (entry, detail) in selected {
+        let record = entry.record;
+        let (date, time) = record_time_parts(record);
+        context.push_str("- [");
+        context.push_str(date);
+        context.push(' ');
+        if entry.count > 1 {
+            let (span, suffix) = run_parts(&entry);
+            context.push_str(&span);
+            context.push_str("] ");
+            context.push_str(&record.summary);
+            context.push_str(&suffix);
+        } else {
+            context.push_str(time);
+            context.push_str("] ");
+            context.push_str(&record.summary);
+        }
+        context.push('\n');
+        match detail {
+            DetailRender::Skip => {}
+            DetailRender::Full => {
+                context.push_str(DETAIL_PREFIX);
+                for (index, part) in record.detail.split('\n').enumerate() {
+                    if index > 0 {
+                        context.push(' ');
+                    }
+                    context.push_str(part);
+                }
+                context.push('\n');
+            }
+            DetailRender::Omitted => context.push_str(DETAIL_OMITTED),
+        }
+    }
+    if truncated {
+        context.push_str(RECORDS_OMITTED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_day(count: usize) -> SearchResult {
+        let records = (0..count)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "timestamp": format!("2024-05-01T{:02}:{:02}:{:02}", i / 3600, i / 60 % 60, i % 60),
+                    "summary": format!("在编辑器中修改 context.rs 第 {} 行并运行 cargo test", i),
+                    "app": if i % 3 == 0 { "Code" } else { "Terminal" },
+                    "action": "active",
+                    "keywords": ["cargo", "test"],
+                    "has_issue": i % 50 == 0,
+                    "detail": "终端输出：\nerror[E0308]: mismatched types\n  --> src/main.rs:10:5",
+                }))
+                .unwrap()
+            })
+            .collect();
+        SearchResult {
+            records,
+            aggregated: Vec::new(),
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_records_rendered_in_time_order_within_budget() {
+        let result = sample_day(200);
+        let context = result.build_context(&ContextStrategy::IssueFirst, Some(0.0), 4000, true, None);
+        assert!(context.starts_with(RECORD_HEADER));
+        assert!(context.len() <= 4000 + DETAIL_OMITTED.len() + RECORDS_OMITTED.len());
+
+        let times: Vec<&str> = context
+            .lines()
+            .filter_map(|line| line.strip_prefix("- [2024-05-01 "))
+            .map(|line| &line[..8])
+            .collect();
+        assert!(!times.is_empty());
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+        // 细节中的换行替换为空格，保持一条细节一行
+        assert!(context
+            .lines()
+            .filter(|line| line.starts_with(DETAIL_PREFIX))
+            .all(|line| line.contains("error[E0308]")));
+    }
+
+    #[test]
+    fn test_similar_consecutive_records_are_compacted() {
+        let mut records: Vec<SummaryRecord> = (0..12)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "timestamp": format!("2024-05-01T14:{:02}:00", i),
+                    "summary": if i % 2 == 0 { "在 Chrome 中浏览网页" } else { "在 Chrome 中浏览网页内容" },
+                    "app": "Chrome",
+                    "action": "browse",
+                    "keywords": [],
+                }))
+                .unwrap()
+            })
+            .collect();
+        records.push(
+            serde_json::from_value(serde_json::json!({
+                "timestamp": "2024-05-01T14:12:00",
+                "summary": "终端中 cargo build 报错",
+                "app": "Terminal",
+                "action": "build",
+                "keywords": [],
+                "has_issue": true,
+            }))
+            .unwrap(),
+        );
+        let result = SearchResult {
+            records,
+            aggregated: Vec::new(),
+            source: "test".to_string(),
+        };
+        let context = result.build_context(&ContextStrategy::RecentFirst, Some(0.0), 4000, false, None);
+        let lines: Vec<&str> = context.lines().filter(|line| line.starts_with("- [")).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- [2024-05-01 14:00–14:11] 在 Chrome 中浏览网页内容（12 次截屏）",
+                "- [2024-05-01 14:12:00] 终端中 cargo build 报错",
+            ]
+        );
+    }
+}
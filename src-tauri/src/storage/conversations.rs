@@ -0,0 +1,224 @@
+use super::StorageManager;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const CONVERSATIONS_DIR: &str = "conversations";
+const INDEX_FILE: &str = "index.json";
+const DEFAULT_TITLE: &str = "新对话";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationAttachment {
+    pub path: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub timestamp: String,
+    // 该轮的工具调用上下文（与 ChatResponse.tool_context 相同结构）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_context: Vec<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_skill: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<ConversationAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    // 标题来源：空为首条消息截取，model 为模型生成，user 为用户重命名
+    #[serde(default)]
+    pub title_source: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub active_skill: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<ConversationMessage>,
+}
+
+/// 会话列表项（index.json），列表页不需要读取每个会话文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub message_count: usize,
+    #[serde(default)]
+    pub preview: String,
+    #[serde(default)]
+    pub title_source: String,
+}
+
+impl StorageManager {
+    fn conversations_dir(&self) -> Result<PathBuf, String> {
+        let dir = self.data_dir.join(CONVERSATIONS_DIR);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建会话目录失败: {}", e))?;
+        Ok(dir)
+    }
+
+    fn conversation_path(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("无效的会话 ID: {}", id));
+        }
+        Ok(self.conversations_dir()?.join(format!("{}.json", id)))
+    }
+
+    fn load_conversation_index(&self) -> Result<Vec<ConversationSummary>, String> {
+        let path = self.conversations_dir()?.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_conversation_index(&self, index: &[ConversationSummary]) -> Result<(), String> {
+        let path = self.conversations_dir()?.join(INDEX_FILE);
+        let content = serde_json::to_string_pretty(index)
+            .map_err(|e| format!("序列化会话列表失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    /// 按最近更新时间倒序
+    pub fn list_conversations(&self) -> Result<Vec<ConversationSummary>, String> {
+        let mut index = self.load_conversation_index()?;
+        index.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(index)
+    }
+
+    pub fn load_conversation(&self, id: &str) -> Result<Conversation, String> {
+        let path = self.conversation_path(id)?;
+        if !path.exists() {
+            return Err(format!("会话不存在: {}", id));
+        }
+        let content = self.read_data_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| format!("解析会话失败: {}", e))
+    }
+
+    /// 保存（新建或覆盖）会话，id 为空时生成新 ID；返回保存后的列表项
+    pub fn save_conversation(&self, mut conversation: Conversation) -> Result<ConversationSummary, String> {
+        let now = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        if conversation.id.trim().is_empty() {
+            conversation.id = Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+        }
+        // 前端保存时通常不带标题信息，沿用已有的标题
+        if conversation.title_source.is_empty() {
+            if let Ok(existing) = self.load_conversation(&conversation.id) {
+                if !existing.title_source.is_empty() {
+                    conversation.title = existing.title;
+                    conversation.title_source = existing.title_source;
+                }
+                if conversation.created_at.is_empty() {
+                    conversation.created_at = existing.created_at;
+                }
+            }
+        }
+        if conversation.created_at.is_empty() {
+            conversation.created_at = now.clone();
+        }
+        conversation.updated_at = now;
+        if conversation.title.trim().is_empty() {
+            conversation.title = default_title(&conversation);
+        }
+
+        let path = self.conversation_path(&conversation.id)?;
+        let content = serde_json::to_string(&conversation)
+            .map_err(|e| format!("序列化会话失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())?;
+
+        let summary = summarize(&conversation);
+        let mut index = self.load_conversation_index()?;
+        index.retain(|item| item.id != summary.id);
+        index.push(summary.clone());
+        self.save_conversation_index(&index)?;
+        Ok(summary)
+    }
+
+    pub fn delete_conversation(&self, id: &str) -> Result<(), String> {
+        let path = self.conversation_path(id)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除会话失败: {}", e))?;
+        }
+        let mut index = self.load_conversation_index()?;
+        index.retain(|item| item.id != id);
+        self.save_conversation_index(&index)
+    }
+
+    pub fn rename_conversation(&self, id: &str, title: &str) -> Result<ConversationSummary, String> {
+        self.set_conversation_title(id, title, "user")
+    }
+
+    /// 写入标题；模型生成的标题不会覆盖用户手动改过的标题
+    pub fn set_conversation_title(
+        &self,
+        id: &str,
+        title: &str,
+        source: &str,
+    ) -> Result<ConversationSummary, String> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err("标题不能为空".to_string());
+        }
+        let mut conversation = self.load_conversation(id)?;
+        if source == "model" && conversation.title_source == "user" {
+            return Ok(summarize(&conversation));
+        }
+        conversation.title = title.to_string();
+        conversation.title_source = source.to_string();
+        let path = self.conversation_path(id)?;
+        let content = serde_json::to_string(&conversation)
+            .map_err(|e| format!("序列化会话失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())?;
+
+        let summary = summarize(&conversation);
+        let mut index = self.load_conversation_index()?;
+        index.retain(|item| item.id != id);
+        index.push(summary.clone());
+        self.save_conversation_index(&index)?;
+        Ok(summary)
+    }
+}
+
+fn default_title(conversation: &Conversation) -> String {
+    conversation
+        .messages
+        .iter()
+        .find(|msg| msg.role == "user" && !msg.content.trim().is_empty())
+        .map(|msg| msg.content.trim().lines().next().unwrap_or("").chars().take(30).collect())
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string())
+}
+
+fn summarize(conversation: &Conversation) -> ConversationSummary {
+    let preview = conversation
+        .messages
+        .iter()
+        .rev()
+        .find(|msg| !msg.content.trim().is_empty())
+        .map(|msg| msg.content.trim().chars().take(80).collect())
+        .unwrap_or_default();
+    ConversationSummary {
+        id: conversation.id.clone(),
+        title: conversation.title.clone(),
+        created_at: conversation.created_at.clone(),
+        updated_at: conversation.updated_at.clone(),
+        message_count: conversation.messages.len(),
+        preview,
+        title_source: conversation.title_source.clone(),
+    }
+}
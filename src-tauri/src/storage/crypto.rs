@@ -0,0 +1,546 @@
+use super::StorageManager;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const ENCRYPTION_FILE: &str = "encryption.json";
+const ENCRYPTED_MAGIC: &[u8] = b"OCENC1\0";
+const NONCE_LEN: usize = 12;
+const VERIFIER_PLAINTEXT: &[u8] = b"opencowork-encryption-check";
+const REKEY_TEMP_SUFFIX: &str = ".rekey";
+const REKEY_BACKUP_SUFFIX: &str = ".rekey-old";
+// 逐行追加的数据日志（.jsonl）按行加密：前缀 + base64(MAGIC + nonce + 密文)
+const LINE_LOG_EXTENSION: &str = "jsonl";
+const ENCRYPTED_LINE_PREFIX: &str = "OCENC1:";
+
+/// 经 write_data_file 保存的数据位置（相对数据目录）：以 / 结尾为目录，否则为文件名或文件名前缀。
+/// 启用加密时只加密这些位置下的明文文件；更换或关闭加密时所有已加密文件都会重写
+const ENCRYPTED_DATA_PATHS: &[&str] = &[
+    "summaries/",
+    "screenshots/",
+    "conversations/",
+    "ocr_index/",
+    "embeddings/",
+    "clipboard/",
+    "usage/",
+    "audit/",
+    "digests/",
+    "file_changes/",
+    "attachments/quick-ask-",
+    "summary_index.json",
+    "heatmap_cache.json",
+    "pending_analysis.json",
+    "retention_notice.json",
+    "skill_usage.json",
+    "skill_suggestions.json",
+    "snippets.json",
+    "tickets.json",
+    "metrics.json",
+    "focus_sessions.json",
+    "alert_suppressions.json",
+    "watch_folder_history.json",
+    "file_changes.json",
+    "offline_queue.json",
+    "tool_approvals.json",
+];
+
+type DataKey = [u8; 32];
+
+/// 加密参数：盐和用于校验密码的密文，不保存任何密钥
+#[derive(Serialize, Deserialize)]
+struct EncryptionMeta {
+    salt: String,
+    verifier: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+}
+
+/// 更换密码期间持有写锁，数据文件的读写持有读锁，避免重写过程中混入旧密钥写入的文件
+fn rotation_lock() -> &'static RwLock<()> {
+    static ROTATION_LOCK: OnceLock<RwLock<()>> = OnceLock::new();
+    ROTATION_LOCK.get_or_init(|| RwLock::new(()))
+}
+
+/// 解锁后的密钥只保存在内存中
+fn unlocked_key() -> &'static RwLock<Option<DataKey>> {
+    static UNLOCKED_KEY: OnceLock<RwLock<Option<DataKey>>> = OnceLock::new();
+    UNLOCKED_KEY.get_or_init(|| RwLock::new(None))
+}
+
+impl StorageManager {
+    fn encryption_meta_path(&self) -> PathBuf {
+        self.data_dir.join(ENCRYPTION_FILE)
+    }
+
+    pub fn encryption_enabled(&self) -> bool {
+        self.encryption_meta_path().exists()
+    }
+
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        EncryptionStatus {
+            enabled: self.encryption_enabled(),
+            unlocked: unlocked_key().read().is_some(),
+        }
+    }
+
+    /// 已启用加密但尚未解锁时拒绝写入，避免落盘明文
+    pub fn ensure_unlocked(&self) -> Result<(), String> {
+        if self.encryption_enabled() && unlocked_key().read().is_none() {
+            return Err("存储已加密，请先输入密码解锁".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn unlock_storage(&self, passphrase: &str) -> Result<(), String> {
+        let meta = self
+            .load_encryption_meta()?
+            .ok_or_else(|| "未启用存储加密".to_string())?;
+        let key = verify_passphrase(&meta, passphrase)?;
+        *unlocked_key().write() = Some(key);
+        Ok(())
+    }
+
+    /// 启用、更换或关闭加密（new_passphrase 为空时关闭），重写所有经 write_data_file 保存的文件。
+    /// 先把新内容写入临时文件，全部成功后再逐个替换并更新加密参数；任何一步失败都回滚，
+    /// 旧密码始终可用。重写期间阻塞其他数据读写（含截屏保存）
+    pub fn change_encryption_passphrase(
+        &self,
+        old_passphrase: Option<&str>,
+        new_passphrase: Option<&str>,
+    ) -> Result<usize, String> {
+        self.ensure_dirs()?;
+        let _rotation = rotation_lock().write();
+        let old_key = match self.load_encryption_meta()? {
+            Some(meta) => Some(verify_passphrase(
+                &meta,
+                old_passphrase.ok_or_else(|| "请输入当前密码".to_string())?,
+            )?),
+            None => None,
+        };
+
+        let new_meta_and_key = match new_passphrase.map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            Some(passphrase) => {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                let key = derive_key(passphrase, &salt)?;
+                let verifier = encrypt_with(&key, VERIFIER_PLAINTEXT)?;
+                Some((
+                    EncryptionMeta {
+                        salt: BASE64.encode(salt),
+                        verifier: BASE64.encode(verifier),
+                    },
+                    key,
+                ))
+            }
+            None => None,
+        };
+        if old_key.is_none() && new_meta_and_key.is_none() {
+            return Err("新密码不能为空".to_string());
+        }
+        let new_key = new_meta_and_key.as_ref().map(|(_, key)| *key);
+
+        // 1. 新内容写入临时文件，失败时删除临时文件，原文件和加密参数都不动
+        let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let staging = self.stage_rekeyed_files(old_key.as_ref(), new_key.as_ref(), &mut staged);
+        if let Err(err) = staging {
+            for (_, temp) in &staged {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(err);
+        }
+
+        // 2. 逐个替换，原文件先改名保留，失败时全部换回
+        let mut swapped: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let swap_result = staged.iter().try_for_each(|(path, temp)| {
+            let backup = sibling_with_suffix(path, REKEY_BACKUP_SUFFIX);
+            fs::rename(path, &backup).map_err(|e| format!("替换 {:?} 失败: {}", path, e))?;
+            if let Err(err) = fs::rename(temp, path) {
+                let _ = fs::rename(&backup, path);
+                return Err(format!("替换 {:?} 失败: {}", path, err));
+            }
+            swapped.push((path.clone(), backup));
+            Ok(())
+        });
+
+        // 3. 数据全部替换后再更新加密参数
+        let meta_result = swap_result.and_then(|_| match &new_meta_and_key {
+            Some((meta, _)) => {
+                let content = serde_json::to_string_pretty(meta)
+                    .map_err(|e| format!("序列化加密参数失败: {}", e))?;
+                let temp = sibling_with_suffix(&self.encryption_meta_path(), REKEY_TEMP_SUFFIX);
+                fs::write(&temp, content).map_err(|e| format!("保存加密参数失败: {}", e))?;
+                fs::rename(&temp, self.encryption_meta_path()).map_err(|e| {
+                    let _ = fs::remove_file(&temp);
+                    format!("保存加密参数失败: {}", e)
+                })
+            }
+            None => fs::remove_file(self.encryption_meta_path()).map_err(|e| format!("关闭加密失败: {}", e)),
+        });
+        if let Err(err) = meta_result {
+            for (path, backup) in swapped.iter().rev() {
+                let _ = fs::rename(backup, path);
+            }
+            for (_, temp) in &staged {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(err);
+        }
+
+        for (_, backup) in &swapped {
+            let _ = fs::remove_file(backup);
+        }
+        *unlocked_key().write() = new_key;
+        Ok(swapped.len())
+    }
+
+    /// 找出需要重写的文件：任何已加密的文件，以及启用加密时受管位置下的明文文件；
+    /// 重写后的内容写入同目录的临时文件，已写入的记录在 staged 中
+    fn stage_rekeyed_files(
+        &self,
+        old_key: Option<&DataKey>,
+        new_key: Option<&DataKey>,
+        staged: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<(), String> {
+        for path in list_files(&self.data_dir) {
+            let Ok(relative) = path.strip_prefix(&self.data_dir) else {
+                continue;
+            };
+            if relative == Path::new(ENCRYPTION_FILE) || is_rekey_leftover(&path) {
+                continue;
+            }
+            let managed = is_encrypted_data_path(relative);
+            if managed && is_line_log(&path) {
+                if let Some(output) = rekey_line_log(&path, old_key, new_key)? {
+                    let temp = sibling_with_suffix(&path, REKEY_TEMP_SUFFIX);
+                    fs::write(&temp, output).map_err(|e| format!("写入 {:?} 失败: {}", temp, e))?;
+                    staged.push((path, temp));
+                }
+                continue;
+            }
+            if !managed && !file_starts_with_magic(&path) {
+                continue;
+            }
+            let data = fs::read(&path).map_err(|e| format!("读取 {:?} 失败: {}", path, e))?;
+            let plain = if is_encrypted(&data) {
+                let key = old_key.ok_or_else(|| format!("无法解密 {:?}", path))?;
+                decrypt_with(key, &data).map_err(|e| format!("{:?}: {}", path, e))?
+            } else if new_key.is_some() {
+                data
+            } else {
+                continue;
+            };
+            let output = match new_key {
+                Some(key) => encrypt_with(key, &plain)?,
+                None => plain,
+            };
+            let temp = sibling_with_suffix(&path, REKEY_TEMP_SUFFIX);
+            fs::write(&temp, output).map_err(|e| format!("写入 {:?} 失败: {}", temp, e))?;
+            staged.push((path, temp));
+        }
+        Ok(())
+    }
+
+    /// 读取数据文件，加密内容自动解密，明文文件原样返回
+    pub fn read_data_file(&self, path: &Path) -> Result<Vec<u8>, String> {
+        let _rotation = rotation_lock().read();
+        let data = fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+        if !is_encrypted(&data) {
+            return Ok(data);
+        }
+        let key = (*unlocked_key().read()).ok_or_else(|| "存储已加密，请先输入密码解锁".to_string())?;
+        decrypt_with(&key, &data)
+    }
+
+    pub fn read_data_string(&self, path: &Path) -> Result<String, String> {
+        let data = self.read_data_file(path)?;
+        String::from_utf8(data).map_err(|e| format!("文件不是有效的 UTF-8: {}", e))
+    }
+
+    /// 写入数据文件，启用加密时写入密文；新增的数据位置需登记在 ENCRYPTED_DATA_PATHS 中
+    pub fn write_data_file(&self, path: &Path, data: &[u8]) -> Result<(), String> {
+        debug_assert!(
+            path.strip_prefix(&self.data_dir).map_or(true, is_encrypted_data_path),
+            "{:?} 未登记在 ENCRYPTED_DATA_PATHS 中",
+            path
+        );
+        let _rotation = rotation_lock().read();
+        let output = if self.encryption_enabled() {
+            let key = (*unlocked_key().read()).ok_or_else(|| "存储已加密，请先输入密码解锁".to_string())?;
+            encrypt_with(&key, data)?
+        } else {
+            data.to_vec()
+        };
+        fs::write(path, output).map_err(|e| format!("写入文件失败: {}", e))
+    }
+
+    /// 向数据日志（.jsonl）追加一行，启用加密时只加密这一行，不重写整个文件
+    pub fn append_data_line(&self, path: &Path, line: &str) -> Result<(), String> {
+        use std::io::Write;
+
+        debug_assert!(
+            path.strip_prefix(&self.data_dir).map_or(true, is_encrypted_data_path),
+            "{:?} 未登记在 ENCRYPTED_DATA_PATHS 中",
+            path
+        );
+        debug_assert!(is_line_log(path) && !line.contains('\n'));
+        let _rotation = rotation_lock().read();
+        let output = if self.encryption_enabled() {
+            let key = (*unlocked_key().read()).ok_or_else(|| "存储已加密，请先输入密码解锁".to_string())?;
+            encrypt_line(&key, line)?
+        } else {
+            line.to_string()
+        };
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("打开文件失败: {}", e))?;
+        file.write_all(format!("{}\n", output).as_bytes())
+            .map_err(|e| format!("写入文件失败: {}", e))
+    }
+
+    /// 读取数据日志的所有行，加密行自动解密
+    pub fn read_data_lines(&self, path: &Path) -> Result<Vec<String>, String> {
+        let _rotation = rotation_lock().read();
+        let content = fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e))?;
+        let key = *unlocked_key().read();
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match line.strip_prefix(ENCRYPTED_LINE_PREFIX) {
+                Some(_) => {
+                    let key = key.as_ref().ok_or_else(|| "存储已加密，请先输入密码解锁".to_string())?;
+                    decrypt_line(key, line)
+                }
+                None => Ok(line.to_string()),
+            })
+            .collect()
+    }
+
+    fn load_encryption_meta(&self) -> Result<Option<EncryptionMeta>, String> {
+        let path = self.encryption_meta_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("读取加密参数失败: {}", e))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("解析加密参数失败: {}", e))
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_MAGIC)
+}
+
+fn is_encrypted_data_path(relative: &Path) -> bool {
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    ENCRYPTED_DATA_PATHS.iter().any(|entry| match entry.strip_suffix('/') {
+        Some(dir) => relative.starts_with(entry) || relative == dir,
+        None => relative.starts_with(entry),
+    })
+}
+
+fn is_line_log(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == LINE_LOG_EXTENSION)
+}
+
+/// 逐行重写数据日志；没有需要重写的行时返回 None
+fn rekey_line_log(
+    path: &Path,
+    old_key: Option<&DataKey>,
+    new_key: Option<&DataKey>,
+) -> Result<Option<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("读取 {:?} 失败: {}", path, e))?;
+    let mut changed = false;
+    let mut output = String::with_capacity(content.len());
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let plain = if line.starts_with(ENCRYPTED_LINE_PREFIX) {
+            let key = old_key.ok_or_else(|| format!("无法解密 {:?}", path))?;
+            changed = true;
+            decrypt_line(key, line).map_err(|e| format!("{:?}: {}", path, e))?
+        } else {
+            line.to_string()
+        };
+        match new_key {
+            Some(key) => {
+                changed = true;
+                output.push_str(&encrypt_line(key, &plain)?);
+            }
+            None => output.push_str(&plain),
+        }
+        output.push('\n');
+    }
+    Ok(changed.then_some(output))
+}
+
+fn encrypt_line(key: &DataKey, line: &str) -> Result<String, String> {
+    Ok(format!("{}{}", ENCRYPTED_LINE_PREFIX, BASE64.encode(encrypt_with(key, line.as_bytes())?)))
+}
+
+fn decrypt_line(key: &DataKey, line: &str) -> Result<String, String> {
+    let data = line
+        .strip_prefix(ENCRYPTED_LINE_PREFIX)
+        .and_then(|body| BASE64.decode(body.trim()).ok())
+        .ok_or_else(|| "加密数据格式无效".to_string())?;
+    String::from_utf8(decrypt_with(key, &data)?).map_err(|e| format!("文件不是有效的 UTF-8: {}", e))
+}
+
+fn file_starts_with_magic(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; ENCRYPTED_MAGIC.len()];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_or(false, |_| header == ENCRYPTED_MAGIC)
+}
+
+fn sibling_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// 上次更换密码中断时残留的临时文件
+fn is_rekey_leftover(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    name.ends_with(REKEY_TEMP_SUFFIX) || name.ends_with(REKEY_BACKUP_SUFFIX)
+}
+
+/// 递归列出目录下的所有文件
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(entry.path()),
+                Ok(kind) if kind.is_file() => files.push(entry.path()),
+                _ => {}
+            }
+        }
+    }
+    files
+}
+
+fn verify_passphrase(meta: &EncryptionMeta, passphrase: &str) -> Result<DataKey, String> {
+    let salt = BASE64
+        .decode(meta.salt.as_bytes())
+        .map_err(|e| format!("加密参数损坏: {}", e))?;
+    let verifier = BASE64
+        .decode(meta.verifier.as_bytes())
+        .map_err(|e| format!("加密参数损坏: {}", e))?;
+    let key = derive_key(passphrase.trim(), &salt)?;
+    match decrypt_with(&key, &verifier) {
+        Ok(plain) if plain == VERIFIER_PLAINTEXT => Ok(key),
+        _ => Err("密码错误".to_string()),
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<DataKey, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("密钥派生失败: {}", e))?;
+    Ok(key)
+}
+
+/// 格式：MAGIC + nonce(12) + AES-256-GCM 密文
+fn encrypt_with(key: &DataKey, plain: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plain)
+        .map_err(|_| "加密失败".to_string())?;
+    let mut out = Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(ENCRYPTED_MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn decrypt_with(key: &DataKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    let body = data
+        .strip_prefix(ENCRYPTED_MAGIC)
+        .filter(|body| body.len() > NONCE_LEN)
+        .ok_or_else(|| "加密数据格式无效".to_string())?;
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "解密失败（密码错误或数据损坏）".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_rotate_and_disable_round_trip() {
+        let data_dir = std::env::temp_dir().join(format!("opencowork-crypto-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&data_dir);
+        let storage = StorageManager { data_dir: data_dir.clone() };
+        storage.ensure_dirs().unwrap();
+        fs::create_dir_all(data_dir.join("audit")).unwrap();
+
+        let summary = data_dir.join("summaries").join("2024-05-06.json");
+        let suppressions = data_dir.join("alert_suppressions.json");
+        let signing_key = data_dir.join("audit").join("signing.key");
+        let unmanaged = data_dir.join("profiles").join("default.json");
+        fs::write(&summary, b"summary").unwrap();
+        fs::write(&suppressions, b"[]").unwrap();
+        fs::write(&signing_key, b"key").unwrap();
+        fs::write(&unmanaged, b"{}").unwrap();
+        let log = data_dir.join("audit").join("2024-05-06.jsonl");
+        storage.append_data_line(&log, r#"{"seq":1}"#).unwrap();
+
+        // 启用：受管文件全部加密，其他文件不动；数据日志逐行加密
+        assert_eq!(storage.change_encryption_passphrase(None, Some("first")).unwrap(), 4);
+        for path in [&summary, &suppressions, &signing_key] {
+            assert!(is_encrypted(&fs::read(path).unwrap()));
+        }
+        assert!(fs::read_to_string(&log).unwrap().starts_with(ENCRYPTED_LINE_PREFIX));
+        storage.append_data_line(&log, r#"{"seq":2}"#).unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 2);
+        assert_eq!(fs::read(&unmanaged).unwrap(), b"{}");
+        assert_eq!(storage.read_data_file(&summary).unwrap(), b"summary");
+
+        let conversation = data_dir.join("conversations").join("c1.json");
+        fs::create_dir_all(conversation.parent().unwrap()).unwrap();
+        storage.write_data_file(&conversation, b"hello").unwrap();
+
+        // 更换：旧密码失效，内容不变，不留临时文件
+        assert!(storage.change_encryption_passphrase(Some("wrong"), Some("second")).is_err());
+        assert_eq!(storage.change_encryption_passphrase(Some("first"), Some("second")).unwrap(), 5);
+        assert!(storage.unlock_storage("first").is_err());
+        storage.unlock_storage("second").unwrap();
+        assert_eq!(storage.read_data_file(&conversation).unwrap(), b"hello");
+        assert_eq!(storage.read_data_file(&signing_key).unwrap(), b"key");
+        assert_eq!(storage.read_data_lines(&log).unwrap(), vec![r#"{"seq":1}"#, r#"{"seq":2}"#]);
+        assert!(list_files(&data_dir).iter().all(|path| !is_rekey_leftover(path)));
+
+        // 关闭：全部恢复为明文
+        assert_eq!(storage.change_encryption_passphrase(Some("second"), None).unwrap(), 5);
+        assert!(!storage.encryption_enabled());
+        assert_eq!(fs::read(&summary).unwrap(), b"summary");
+        assert_eq!(fs::read(&suppressions).unwrap(), b"[]");
+        assert_eq!(fs::read_to_string(&log).unwrap(), "{\"seq\":1}\n{\"seq\":2}\n");
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,144 @@
+use super::StorageManager;
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+const DAY_INDEX_FILE: &str = "summary_index.json";
+// 并发读取天文件的线程数上限，避免保留期较长时同时打开过多文件
+const DAY_LOAD_CONCURRENCY: usize = 4;
+
+/// 单天摘要文件的索引；文件大小或修改时间变化时视为过期，重新读取该天
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DayIndexEntry {
+    file_len: u64,
+    modified_ms: u64,
+    records: usize,
+    issues: usize,
+}
+
+fn day_index_lock() -> &'static ParkingMutex<()> {
+    static LOCK: OnceLock<ParkingMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| ParkingMutex::new(()))
+}
+
+impl StorageManager {
+    fn day_index_path(&self) -> PathBuf {
+        self.data_dir.join(DAY_INDEX_FILE)
+    }
+
+    pub(super) fn summary_file_stamp(&self, date: &str) -> Option<(u64, u64)> {
+        let path = self.data_dir.join("summaries").join(format!("{}.json", date));
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified_ms = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_millis() as u64);
+        Some((metadata.len(), modified_ms))
+    }
+
+    fn load_day_index(&self) -> BTreeMap<String, DayIndexEntry> {
+        let path = self.day_index_path();
+        if !path.exists() {
+            return BTreeMap::new();
+        }
+        self.read_data_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn build_day_index_entry(&self, date: &str) -> Option<DayIndexEntry> {
+        // 先取文件状态再读取，读取期间被写入时下次会判定为过期
+        let (file_len, modified_ms) = self.summary_file_stamp(date)?;
+        let records = self.get_summaries(date).ok()?;
+        Some(DayIndexEntry {
+            file_len,
+            modified_ms,
+            records: records.len(),
+            issues: records.iter().filter(|record| record.has_issue).count(),
+        })
+    }
+
+    /// 并发读取多天的数据（最多 DAY_LOAD_CONCURRENCY 个线程），结果按 dates 的顺序返回
+    pub fn load_days_parallel<T, F>(&self, dates: &[String], load: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&StorageManager, &str) -> T + Sync,
+    {
+        if dates.len() <= 1 {
+            return dates.iter().map(|date| load(self, date)).collect();
+        }
+        let chunk_size = dates.len().div_ceil(DAY_LOAD_CONCURRENCY);
+        let load = &load;
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = dates
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || chunk.iter().map(|date| load(self, date)).collect::<Vec<T>>())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|err| std::panic::resume_unwind(err)))
+                .collect()
+        })
+    }
+
+    /// 从 dates 中筛出有问题记录的日期，没有摘要文件或没有问题记录的天直接跳过；
+    /// 索引缺失或过期的天会并发重新读取并写回索引
+    pub fn dates_with_issues(&self, dates: &[String]) -> Vec<String> {
+        let _guard = day_index_lock().lock();
+        let mut index = self.load_day_index();
+        let stamps: Vec<Option<(u64, u64)>> = dates
+            .iter()
+            .map(|date| self.summary_file_stamp(date))
+            .collect();
+
+        let mut changed = false;
+        let mut stale = Vec::new();
+        for (date, stamp) in dates.iter().zip(&stamps) {
+            match (stamp, index.get(date)) {
+                (None, Some(_)) => {
+                    index.remove(date);
+                    changed = true;
+                }
+                (None, None) => {}
+                (Some((len, modified)), Some(entry))
+                    if entry.file_len == *len && entry.modified_ms == *modified => {}
+                (Some(_), _) => stale.push(date.clone()),
+            }
+        }
+        if !stale.is_empty() {
+            let entries = self.load_days_parallel(&stale, |storage, date| storage.build_day_index_entry(date));
+            for (date, entry) in stale.into_iter().zip(entries) {
+                match entry {
+                    Some(entry) => index.insert(date, entry),
+                    None => index.remove(&date),
+                };
+            }
+            changed = true;
+        }
+        if changed {
+            let saved = serde_json::to_string(&index)
+                .map_err(|e| format!("序列化摘要索引失败: {}", e))
+                .and_then(|content| self.write_data_file(&self.day_index_path(), content.as_bytes()));
+            if let Err(err) = saved {
+                crate::logs::warn("storage", format!("保存摘要索引失败: {}", err));
+            }
+        }
+
+        dates
+            .iter()
+            .zip(&stamps)
+            .filter(|(date, stamp)| {
+                // 读取失败、未能建立索引的天仍交给调用方读取
+                stamp.is_some() && index.get(*date).map_or(true, |entry| entry.issues > 0)
+            })
+            .map(|(date, _)| date.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,195 @@
+use super::{SearchQuery, SearchResult, StorageManager, SummaryRecord, TimeRange};
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+const EMBEDDINGS_DIR: &str = "embeddings";
+const MAX_EMBEDDING_TEXT_CHARS: usize = 1000;
+
+/// 对话检索用的问题向量及筛选参数
+#[derive(Debug, Clone)]
+pub struct SemanticQuery {
+    pub model: String,
+    pub vector: Vec<f32>,
+    pub min_similarity: f32,
+    pub max_results: usize,
+    pub range: Option<TimeRange>,  // 语义匹配的时间范围，空时沿用问题中的时间范围
+}
+
+/// 单天摘要记录的向量，按记录时间索引；换用其他模型时整天重新计算
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DailyEmbeddings {
+    model: String,
+    vectors: BTreeMap<String, Vec<f32>>,
+}
+
+/// 用于计算向量的记录文本
+pub fn embedding_text(record: &SummaryRecord) -> String {
+    let mut parts = vec![record.summary.as_str(), record.app.as_str(), record.action.as_str()];
+    parts.extend([record.intent.as_str(), record.issue_summary.as_str(), record.window_title.as_str()]);
+    let keywords = record.keywords.join(" ");
+    parts.push(&keywords);
+    parts.push(&record.detail);
+    let text = parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    text.chars().take(MAX_EMBEDDING_TEXT_CHARS).collect()
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// 时间范围对应的起始时间（含）、结束时间（不含）和涉及的日期
+fn range_bounds(range: &TimeRange, now: NaiveDateTime) -> (NaiveDateTime, NaiveDateTime, Vec<String>) {
+    let today = now.date().and_hms_opt(0, 0, 0).unwrap_or(now);
+    let (start, end) = match range {
+        TimeRange::Recent(minutes) => (now - Duration::minutes(*minutes as i64), now + Duration::minutes(1)),
+        TimeRange::Today => (today, now + Duration::minutes(1)),
+        TimeRange::Days(days) => (today - Duration::days((*days).max(1) as i64 - 1), now + Duration::minutes(1)),
+        TimeRange::Between(start, end) => (*start, *end),
+    };
+    let mut dates = Vec::new();
+    let mut day = start.date();
+    while day <= end.date() {
+        dates.push(day.format("%Y-%m-%d").to_string());
+        day += Duration::days(1);
+    }
+    (start, end, dates)
+}
+
+impl StorageManager {
+    fn embeddings_path(&self, date: &str) -> PathBuf {
+        self.get_data_dir().join(EMBEDDINGS_DIR).join(format!("{}.json", date))
+    }
+
+    fn load_day_embeddings(&self, date: &str) -> DailyEmbeddings {
+        let path = self.embeddings_path(date);
+        if !path.exists() {
+            return DailyEmbeddings::default();
+        }
+        self.read_data_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// 当天还没有向量（或向量来自其他模型）的记录，返回 (时间, 文本)，最多 limit 条
+    pub fn records_missing_embeddings(&self, date: &str, model: &str, limit: usize) -> Vec<(String, String)> {
+        let Ok(records) = self.get_summaries(date) else {
+            return Vec::new();
+        };
+        let daily = self.load_day_embeddings(date);
+        records
+            .iter()
+            .filter(|record| daily.model != model || !daily.vectors.contains_key(&record.timestamp))
+            .map(|record| (record.timestamp.clone(), embedding_text(record)))
+            .filter(|(_, text)| !text.is_empty())
+            .take(limit)
+            .collect()
+    }
+
+    pub fn save_embeddings(&self, date: &str, model: &str, items: Vec<(String, Vec<f32>)>) -> Result<(), String> {
+        let dir = self.get_data_dir().join(EMBEDDINGS_DIR);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建向量目录失败: {}", e))?;
+        let mut daily = self.load_day_embeddings(date);
+        if daily.model != model {
+            daily = DailyEmbeddings {
+                model: model.to_string(),
+                vectors: BTreeMap::new(),
+            };
+        }
+        daily.vectors.extend(items);
+        let content = serde_json::to_string(&daily).map_err(|e| format!("序列化向量失败: {}", e))?;
+        self.write_data_file(&self.embeddings_path(date), content.as_bytes())
+    }
+
+    /// 清理超过保留天数的向量
+    pub fn prune_embeddings(&self, retention_days: u32) -> usize {
+        if retention_days == 0 {
+            return 0;
+        }
+        let cutoff = (Local::now() - Duration::days(retention_days as i64))
+            .format("%Y-%m-%d")
+            .to_string();
+        let Ok(entries) = fs::read_dir(self.get_data_dir().join(EMBEDDINGS_DIR)) else {
+            return 0;
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map_or(false, |date| date < cutoff.as_str())
+            })
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+
+    /// 把时间范围内与问题语义相近、但关键词没有命中的记录并入检索结果
+    pub(super) fn blend_semantic_matches(&self, query: &SearchQuery, semantic: &SemanticQuery, result: &mut SearchResult) {
+        let range = semantic.range.as_ref().unwrap_or(&query.time_range);
+        let (start, end, dates) = range_bounds(range, Local::now().naive_local());
+        let start = start.format("%Y-%m-%dT%H:%M:%S").to_string();
+        let end = end.format("%Y-%m-%dT%H:%M:%S").to_string();
+
+        let mut scored: Vec<(f32, String)> = self
+            .load_days_parallel(&dates, |storage, date| storage.load_day_embeddings(date))
+            .into_iter()
+            .filter(|daily| daily.model == semantic.model)
+            .flat_map(|daily| daily.vectors.into_iter())
+            .filter(|(timestamp, _)| *timestamp >= start && *timestamp < end)
+            .map(|(timestamp, vector)| (cosine_similarity(&semantic.vector, &vector), timestamp))
+            .filter(|(score, _)| *score >= semantic.min_similarity)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(semantic.max_results);
+        if scored.is_empty() {
+            return;
+        }
+
+        let existing: HashSet<String> = result.records.iter().map(|record| record.timestamp.clone()).collect();
+        let wanted: HashSet<String> = scored
+            .into_iter()
+            .map(|(_, timestamp)| timestamp)
+            .filter(|timestamp| !existing.contains(timestamp))
+            .collect();
+        if wanted.is_empty() {
+            return;
+        }
+        let wanted_dates: Vec<String> = dates
+            .into_iter()
+            .filter(|date| wanted.iter().any(|timestamp| timestamp.starts_with(date.as_str())))
+            .collect();
+        let matched: Vec<SummaryRecord> = self
+            .load_days_parallel(&wanted_dates, |storage, date| storage.get_summaries(date).unwrap_or_default())
+            .into_iter()
+            .flatten()
+            .filter(|record| wanted.contains(&record.timestamp))
+            .collect();
+        if matched.is_empty() {
+            return;
+        }
+        result.records.extend(matched);
+        result.records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        result.source = format!("{}+语义匹配", result.source);
+    }
+}
@@ -0,0 +1,200 @@
+use super::{StorageManager, SummaryRecord};
+use chrono::{Duration, Local};
+use parking_lot::Mutex as ParkingMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const HEATMAP_CACHE_FILE: &str = "heatmap_cache.json";
+
+/// 某天某小时的活动量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapHour {
+    pub hour: u32,
+    pub records: usize,
+    pub dominant_app: String,
+    #[serde(default)]
+    pub intensity: f32,  // 相对整个周期内最活跃小时的强度 0-1，查询时计算
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HeatmapDay {
+    pub date: String,
+    pub records: usize,
+    pub hours: Vec<HeatmapHour>,  // 只包含有记录的小时
+}
+
+/// 日历热力图数据
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityHeatmap {
+    pub period: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub max_records: usize,  // 单个小时的最大记录数
+    pub days: Vec<HeatmapDay>,
+}
+
+/// 单天的小时统计缓存；摘要文件大小或修改时间变化时重新统计该天
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HeatmapCacheEntry {
+    file_len: u64,
+    modified_ms: u64,
+    hours: Vec<HeatmapHour>,
+}
+
+fn heatmap_cache_lock() -> &'static ParkingMutex<()> {
+    static LOCK: OnceLock<ParkingMutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| ParkingMutex::new(()))
+}
+
+/// 周期名称对应的天数：week、month、quarter、year，默认 month
+pub fn heatmap_period_days(period: &str) -> Result<i64, String> {
+    match period.trim().to_lowercase().as_str() {
+        "week" => Ok(7),
+        "" | "month" => Ok(30),
+        "quarter" => Ok(90),
+        "year" => Ok(365),
+        other => Err(format!("不支持的周期: {}（可选 week、month、quarter、year）", other)),
+    }
+}
+
+impl StorageManager {
+    fn heatmap_cache_path(&self) -> PathBuf {
+        self.data_dir.join(HEATMAP_CACHE_FILE)
+    }
+
+    fn load_heatmap_cache(&self) -> BTreeMap<String, HeatmapCacheEntry> {
+        let path = self.heatmap_cache_path();
+        if !path.exists() {
+            return BTreeMap::new();
+        }
+        self.read_data_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn build_heatmap_entry(&self, date: &str) -> Option<HeatmapCacheEntry> {
+        let (file_len, modified_ms) = self.summary_file_stamp(date)?;
+        let records = self.get_summaries(date).ok()?;
+        Some(HeatmapCacheEntry {
+            file_len,
+            modified_ms,
+            hours: hourly_activity(&records),
+        })
+    }
+
+    /// 最近一个周期内每天每小时的活动量和主要应用；
+    /// 各天的统计缓存在 heatmap_cache.json，只重新统计缓存缺失或摘要有变化的天
+    pub fn get_activity_heatmap(&self, period: &str) -> Result<ActivityHeatmap, String> {
+        let days = heatmap_period_days(period)?;
+        let today = Local::now().date_naive();
+        let dates: Vec<String> = (0..days)
+            .rev()
+            .map(|offset| (today - Duration::days(offset)).format("%Y-%m-%d").to_string())
+            .collect();
+
+        let _guard = heatmap_cache_lock().lock();
+        let mut cache = self.load_heatmap_cache();
+        let stamps: Vec<Option<(u64, u64)>> = dates.iter().map(|date| self.summary_file_stamp(date)).collect();
+        let mut changed = false;
+        let mut stale = Vec::new();
+        for (date, stamp) in dates.iter().zip(&stamps) {
+            match (stamp, cache.get(date)) {
+                (None, Some(_)) => {
+                    cache.remove(date);
+                    changed = true;
+                }
+                (None, None) => {}
+                (Some((len, modified)), Some(entry))
+                    if entry.file_len == *len && entry.modified_ms == *modified => {}
+                (Some(_), _) => stale.push(date.clone()),
+            }
+        }
+        if !stale.is_empty() {
+            let entries = self.load_days_parallel(&stale, |storage, date| storage.build_heatmap_entry(date));
+            for (date, entry) in stale.into_iter().zip(entries) {
+                match entry {
+                    Some(entry) => cache.insert(date, entry),
+                    None => cache.remove(&date),
+                };
+            }
+            changed = true;
+        }
+        // 只保留最长周期内的天，避免缓存无限增长
+        let oldest = (today - Duration::days(heatmap_period_days("year")?)).format("%Y-%m-%d").to_string();
+        let before = cache.len();
+        cache.retain(|date, _| *date >= oldest);
+        changed |= cache.len() != before;
+        if changed {
+            let saved = serde_json::to_string(&cache)
+                .map_err(|e| format!("序列化热力图缓存失败: {}", e))
+                .and_then(|content| self.write_data_file(&self.heatmap_cache_path(), content.as_bytes()));
+            if let Err(err) = saved {
+                crate::logs::warn("storage", format!("保存热力图缓存失败: {}", err));
+            }
+        }
+
+        let mut heatmap_days: Vec<HeatmapDay> = dates
+            .iter()
+            .filter_map(|date| {
+                let hours = cache.get(date)?.hours.clone();
+                Some(HeatmapDay {
+                    date: date.clone(),
+                    records: hours.iter().map(|hour| hour.records).sum(),
+                    hours,
+                })
+            })
+            .collect();
+        let max_records = heatmap_days
+            .iter()
+            .flat_map(|day| day.hours.iter().map(|hour| hour.records))
+            .max()
+            .unwrap_or(0);
+        for hour in heatmap_days.iter_mut().flat_map(|day| day.hours.iter_mut()) {
+            hour.intensity = if max_records > 0 {
+                hour.records as f32 / max_records as f32
+            } else {
+                0.0
+            };
+        }
+
+        Ok(ActivityHeatmap {
+            period: if period.trim().is_empty() { "month".to_string() } else { period.trim().to_lowercase() },
+            start_date: dates.first().cloned().unwrap_or_default(),
+            end_date: dates.last().cloned().unwrap_or_default(),
+            max_records,
+            days: heatmap_days,
+        })
+    }
+}
+
+/// 按小时统计记录数和出现最多的应用
+fn hourly_activity(records: &[SummaryRecord]) -> Vec<HeatmapHour> {
+    let mut hours: BTreeMap<u32, HashMap<&str, usize>> = BTreeMap::new();
+    for record in records {
+        let Some(hour) = record.timestamp.get(11..13).and_then(|value| value.parse::<u32>().ok()) else {
+            continue;
+        };
+        let app = if record.app.trim().is_empty() { "Unknown" } else { record.app.trim() };
+        *hours.entry(hour).or_default().entry(app).or_insert(0) += 1;
+    }
+    hours
+        .into_iter()
+        .map(|(hour, apps)| {
+            let records = apps.values().sum();
+            let dominant_app = apps
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(app, _)| app.to_string())
+                .unwrap_or_default();
+            HeatmapHour {
+                hour,
+                records,
+                dominant_app,
+                intensity: 0.0,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,379 @@
+use super::{storage_actor, StorageConfig, StorageManager, StoragePriority};
+use crate::logs;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+const JANITOR_INTERVAL_SECS: u64 = 60 * 60;
+const RETENTION_NOTICE_FILE: &str = "retention_notice.json";
+// 首次清理前先通知用户，宽限期过后才真正删除
+const RETENTION_NOTICE_GRACE_HOURS: i64 = 24;
+
+/// 各类数据占用的磁盘空间（字节）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageUsage {
+    pub screenshots_bytes: u64,
+    pub screenshots_count: usize,
+    pub summaries_bytes: u64,
+    pub summaries_days: usize,
+    pub aggregated_bytes: u64,
+    pub logs_bytes: u64,
+    pub profiles_bytes: u64,
+    pub other_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// 一次清理的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JanitorReport {
+    pub removed_summary_days: usize,
+    pub removed_screenshots: usize,
+    pub freed_bytes: u64,
+    #[serde(skip)]
+    previewed: HashSet<PathBuf>,  // dry_run 时已计入的截图，避免摘要和截图两轮重复统计
+}
+
+impl JanitorReport {
+    fn is_empty(&self) -> bool {
+        self.removed_summary_days == 0 && self.removed_screenshots == 0
+    }
+}
+
+/// 首次清理前发出的 storage-retention-notice 事件：将要删除的数据和开始删除的时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionNotice {
+    pub removed_summary_days: usize,
+    pub removed_screenshots: usize,
+    pub freed_bytes: u64,
+    pub purge_after: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RetentionNoticeState {
+    notified_at: String,
+    // 界面确认前一直保留，启动时事件可能早于界面挂载而丢失，界面挂载后再查询
+    #[serde(default)]
+    pending: Option<RetentionNotice>,
+}
+
+enum JanitorOutcome {
+    Cleaned(JanitorReport),
+    Notice(RetentionNotice),
+    Waiting,
+}
+
+impl StorageManager {
+    /// 统计数据目录下各分类的占用
+    pub fn storage_usage(&self) -> Result<StorageUsage, String> {
+        self.ensure_dirs()?;
+        let mut usage = StorageUsage::default();
+        let entries = fs::read_dir(&self.data_dir)
+            .map_err(|e| format!("读取数据目录失败: {}", e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (bytes, count) = dir_size(&path);
+            match name.as_str() {
+                "screenshots" => {
+                    usage.screenshots_bytes = bytes;
+                    usage.screenshots_count = count;
+                }
+                "summaries" => {
+                    usage.summaries_bytes = bytes;
+                    usage.summaries_days = count;
+                }
+                "aggregated" => usage.aggregated_bytes = bytes,
+                "logs" => usage.logs_bytes = bytes,
+                "profiles" => usage.profiles_bytes = bytes,
+                _ => usage.other_bytes += bytes,
+            }
+            usage.total_bytes += bytes;
+        }
+        Ok(usage)
+    }
+
+    /// 按保留天数、截图数量和磁盘上限清理截图与摘要；dry_run 时只统计不删除
+    pub fn run_janitor(&self, config: &StorageConfig, dry_run: bool) -> Result<JanitorReport, String> {
+        self.ensure_dirs()?;
+        let mut report = JanitorReport::default();
+        let today = Local::now().format("%Y-%m-%d").to_string();
+
+        // 1. 超过保留天数的摘要（连同其引用的截图）和截图
+        if config.retention_days > 0 {
+            let cutoff = (Local::now() - Duration::days(config.retention_days as i64))
+                .format("%Y-%m-%d")
+                .to_string();
+            for (date, path) in self.summary_files() {
+                if date < cutoff {
+                    self.remove_summary_day(&date, &path, &mut report, dry_run);
+                }
+            }
+            if !dry_run {
+                self.prune_clipboard_history(config.retention_days);
+                self.prune_ocr_index(config.retention_days);
+                self.prune_embeddings(config.retention_days);
+            }
+            let cutoff_compact = cutoff.replace('-', "");
+            for (path, size) in self.screenshot_files() {
+                if screenshot_date(&path).map_or(false, |d| d < cutoff_compact) {
+                    remove_screenshot(&path, size, &mut report, dry_run);
+                }
+            }
+        }
+
+        // 2. 截图数量上限，先删最旧的
+        let mut screenshots = self.screenshot_files();
+        if config.max_screenshots > 0 && screenshots.len() > config.max_screenshots as usize {
+            let excess = screenshots.len() - config.max_screenshots as usize;
+            for (path, size) in screenshots.drain(..excess) {
+                remove_screenshot(&path, size, &mut report, dry_run);
+            }
+        }
+
+        // 3. 磁盘上限：先删最旧的截图，仍超出再删最旧的摘要（保留当天）
+        if config.max_disk_usage_mb > 0 {
+            let limit = config.max_disk_usage_mb.saturating_mul(1024 * 1024);
+            let summaries = self.summary_files();
+            let mut used: u64 = screenshots.iter().map(|(_, size)| *size).sum::<u64>()
+                + summaries
+                    .iter()
+                    .map(|(_, path)| file_size(path))
+                    .sum::<u64>();
+            for (path, size) in screenshots {
+                if used <= limit {
+                    break;
+                }
+                remove_screenshot(&path, size, &mut report, dry_run);
+                used = used.saturating_sub(size);
+            }
+            for (date, path) in summaries {
+                if used <= limit || date == today {
+                    break;
+                }
+                let before = report.freed_bytes;
+                self.remove_summary_day(&date, &path, &mut report, dry_run);
+                used = used.saturating_sub(report.freed_bytes - before);
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn retention_notice_state(&self) -> Option<RetentionNoticeState> {
+        let path = self.data_dir.join(RETENTION_NOTICE_FILE);
+        serde_json::from_str(&self.read_data_string(&path).ok()?).ok()
+    }
+
+    fn save_retention_notice_state(&self, state: &RetentionNoticeState) -> Result<(), String> {
+        let content =
+            serde_json::to_string(state).map_err(|e| format!("序列化清理通知状态失败: {}", e))?;
+        self.write_data_file(&self.data_dir.join(RETENTION_NOTICE_FILE), content.as_bytes())
+    }
+
+    fn retention_notified_at(&self) -> Option<DateTime<Local>> {
+        let state = self.retention_notice_state()?;
+        DateTime::parse_from_rfc3339(&state.notified_at)
+            .ok()
+            .map(|at| at.with_timezone(&Local))
+    }
+
+    /// 尚未被界面确认的清理通知
+    pub fn pending_retention_notice(&self) -> Option<RetentionNotice> {
+        self.retention_notice_state()?.pending
+    }
+
+    /// 界面展示通知后确认，之后不再重复提示
+    pub fn acknowledge_retention_notice(&self) -> Result<(), String> {
+        match self.retention_notice_state() {
+            Some(mut state) if state.pending.is_some() => {
+                state.pending = None;
+                self.save_retention_notice_state(&state)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 第一次要删除数据时只通知不删除，宽限期后再按规则清理
+    fn janitor_tick(&self, config: &StorageConfig) -> Result<JanitorOutcome, String> {
+        let now = Local::now();
+        match self.retention_notified_at() {
+            Some(at) if now.signed_duration_since(at) >= Duration::hours(RETENTION_NOTICE_GRACE_HOURS) => {
+                self.run_janitor(config, false).map(JanitorOutcome::Cleaned)
+            }
+            Some(_) => Ok(JanitorOutcome::Waiting),
+            None => {
+                let preview = self.run_janitor(config, true)?;
+                if preview.is_empty() {
+                    return Ok(JanitorOutcome::Cleaned(preview));
+                }
+                let notice = RetentionNotice {
+                    removed_summary_days: preview.removed_summary_days,
+                    removed_screenshots: preview.removed_screenshots,
+                    freed_bytes: preview.freed_bytes,
+                    purge_after: (now + Duration::hours(RETENTION_NOTICE_GRACE_HOURS))
+                        .format("%Y-%m-%dT%H:%M:%S")
+                        .to_string(),
+                };
+                self.save_retention_notice_state(&RetentionNoticeState {
+                    notified_at: now.to_rfc3339(),
+                    pending: Some(notice.clone()),
+                })?;
+                Ok(JanitorOutcome::Notice(notice))
+            }
+        }
+    }
+
+    /// 按日期升序返回摘要文件
+    fn summary_files(&self) -> Vec<(String, PathBuf)> {
+        let mut files: Vec<(String, PathBuf)> = fs::read_dir(self.data_dir.join("summaries"))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+                    .filter_map(|path| {
+                        let date = path.file_stem()?.to_str()?.to_string();
+                        Some((date, path))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files
+    }
+
+    /// 按文件名（即时间）升序返回截图及大小
+    fn screenshot_files(&self) -> Vec<(PathBuf, u64)> {
+        let mut files: Vec<(PathBuf, u64)> = fs::read_dir(self.data_dir.join("screenshots"))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let meta = entry.metadata().ok()?;
+                        meta.is_file().then(|| (entry.path(), meta.len()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort_by(|a, b| a.0.file_name().cmp(&b.0.file_name()));
+        files
+    }
+
+    fn remove_summary_day(&self, date: &str, path: &Path, report: &mut JanitorReport, dry_run: bool) {
+        let screenshots_dir = self.data_dir.join("screenshots");
+        let refs: Vec<PathBuf> = self
+            .read_data_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<super::DailySummary>(&content).ok())
+            .map(|daily| {
+                daily
+                    .records
+                    .into_iter()
+                    .filter(|record| !record.detail_ref.is_empty())
+                    .map(|record| screenshots_dir.join(record.detail_ref))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let size = file_size(path);
+        if !dry_run {
+            if let Err(err) = fs::remove_file(path) {
+                eprintln!("清理摘要 {} 失败: {}", date, err);
+                return;
+            }
+        }
+        report.removed_summary_days += 1;
+        report.freed_bytes += size;
+        for shot in refs {
+            let size = file_size(&shot);
+            if size > 0 {
+                remove_screenshot(&shot, size, report, dry_run);
+            }
+        }
+    }
+}
+
+/// 启动后台清理：启动时立即执行一次，之后每小时执行
+pub fn start_storage_janitor(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let result = storage_actor()
+                .run(StoragePriority::Background, |storage| {
+                    let config = storage.load_config()?;
+                    storage.janitor_tick(&config.storage)
+                })
+                .await;
+            match result {
+                Ok(JanitorOutcome::Notice(notice)) => {
+                    logs::info(
+                        "janitor",
+                        format!(
+                            "按保留规则将于 {} 后删除 {} 天摘要、{} 张截图",
+                            notice.purge_after, notice.removed_summary_days, notice.removed_screenshots
+                        ),
+                    );
+                    let _ = app_handle.emit("storage-retention-notice", notice);
+                }
+                Ok(JanitorOutcome::Cleaned(report)) if !report.is_empty() => {
+                    println!(
+                        "[janitor] removed {} screenshots, {} summary days, freed {} bytes",
+                        report.removed_screenshots, report.removed_summary_days, report.freed_bytes
+                    );
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("[janitor] cleanup failed: {}", err),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(JANITOR_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+fn remove_screenshot(path: &Path, size: u64, report: &mut JanitorReport, dry_run: bool) {
+    let removed = if dry_run {
+        report.previewed.insert(path.to_path_buf())
+    } else {
+        fs::remove_file(path).is_ok()
+    };
+    if removed {
+        report.removed_screenshots += 1;
+        report.freed_bytes += size;
+    }
+}
+
+/// 截图文件名形如 20240101-120000-.123.jpg，取前 8 位日期
+fn screenshot_date(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let date = name.get(..8)?;
+    date.chars().all(|c| c.is_ascii_digit()).then(|| date.to_string())
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+}
+
+/// 递归统计目录大小与直接子文件数量
+fn dir_size(path: &Path) -> (u64, usize) {
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return (0, 0),
+    };
+    if meta.is_file() {
+        return (meta.len(), 1);
+    }
+    let mut bytes = 0u64;
+    let mut count = 0usize;
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let child = entry.path();
+            if child.is_dir() {
+                bytes += dir_size(&child).0;
+            } else {
+                bytes += file_size(&child);
+                count += 1;
+            }
+        }
+    }
+    (bytes, count)
+}
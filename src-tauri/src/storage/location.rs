@@ -0,0 +1,223 @@
+use super::{migrate_legacy_data_dir, StorageManager};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 显式指定数据目录；可包含 {user}，多个系统账户共用同一位置时按用户隔离
+const DATA_DIR_ENV: &str = "OPENCOWORK_DATA_DIR";
+const USER_PLACEHOLDER: &str = "{user}";
+
+/// 自检结果的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorCheck {
+    pub id: String,
+    pub level: String,  // ok | warn | error
+    pub message: String,
+}
+
+impl DoctorCheck {
+    fn new(id: &str, level: &str, message: String) -> Self {
+        Self {
+            id: id.to_string(),
+            level: level.to_string(),
+            message,
+        }
+    }
+}
+
+/// 当前系统账户名，用于按用户隔离数据目录
+pub fn current_user_name() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+fn data_dir_override() -> Option<PathBuf> {
+    let value = std::env::var(DATA_DIR_ENV).ok()?;
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(PathBuf::from(value.replace(USER_PLACEHOLDER, &current_user_name())))
+}
+
+/// 数据目录：环境变量指定 > 本地应用数据目录（Windows 为 %LOCALAPPDATA%，不随漫游配置同步）>
+/// 用户主目录；都取不到时放到临时目录下按用户区分，避免多个账户写进同一个相对目录
+pub(super) fn resolve_data_dir() -> PathBuf {
+    if let Some(dir) = data_dir_override() {
+        return dir;
+    }
+    let Some(base_dir) = dirs::data_local_dir() else {
+        return match dirs::home_dir() {
+            Some(home) => home.join(".opencowork").join("data"),
+            None => std::env::temp_dir()
+                .join(format!("opencowork-{}", current_user_name()))
+                .join("data"),
+        };
+    };
+    let data_dir = base_dir.join("opencowork").join("data");
+    if data_dir.exists() {
+        return data_dir;
+    }
+
+    // 旧版本名称和漫游目录（Windows %APPDATA%）中的数据迁到本地目录
+    let mut legacy_dirs = vec![base_dir.join("screen-assistant").join("data")];
+    if let Some(roaming) = dirs::data_dir().filter(|dir| *dir != base_dir) {
+        legacy_dirs.push(roaming.join("opencowork").join("data"));
+        legacy_dirs.push(roaming.join("screen-assistant").join("data"));
+    }
+    for legacy_dir in legacy_dirs {
+        if !legacy_dir.exists() {
+            continue;
+        }
+        if let Err(err) = migrate_legacy_data_dir(&legacy_dir, &data_dir) {
+            eprintln!("Failed to migrate legacy data dir: {}", err);
+            return legacy_dir;
+        }
+        break;
+    }
+    data_dir
+}
+
+/// 数据目录是否位于网络共享（UNC 路径、网络驱动器或 NFS/SMB 挂载）
+pub fn is_network_path(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    if text.starts_with(r"\\?\UNC\") {
+        return true;
+    }
+    if text.starts_with(r"\\") && !text.starts_with(r"\\?\") && !text.starts_with(r"\\.\") {
+        return true;
+    }
+    platform_is_network_path(path)
+}
+
+#[cfg(target_os = "windows")]
+fn platform_is_network_path(path: &Path) -> bool {
+    use std::path::Component;
+    use windows_sys::Win32::Storage::FileSystem::{GetDriveTypeW, DRIVE_REMOTE};
+
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return false;
+    };
+    let root: Vec<u16> = format!("{}\\", prefix.as_os_str().to_string_lossy())
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn platform_is_network_path(path: &Path) -> bool {
+    const NETWORK_FS: &[&str] = &["nfs", "nfs4", "cifs", "smbfs", "smb3", "afpfs", "webdav", "fuse.sshfs"];
+    // (挂载点, 文件系统类型)，取与路径匹配的最长挂载点
+    let mounts = mount_table();
+    mounts
+        .iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())
+        .map_or(false, |(_, fs_type)| NETWORK_FS.contains(&fs_type.as_str()))
+}
+
+#[cfg(target_os = "linux")]
+fn mount_table() -> Vec<(String, String)> {
+    fs::read_to_string("/proc/mounts")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let mount_point = parts.nth(1)?.replace("\\040", " ");
+            let fs_type = parts.next()?.to_lowercase();
+            Some((mount_point, fs_type))
+        })
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn mount_table() -> Vec<(String, String)> {
+    // macOS: "//user@server/share on /Volumes/share (smbfs, nodev, ...)"
+    let output = std::process::Command::new("mount")
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).to_string())
+        .unwrap_or_default();
+    output
+        .lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(" on ")?;
+            let (mount_point, options) = rest.rsplit_once(" (")?;
+            let fs_type = options.split(',').next()?.trim().to_lowercase();
+            Some((mount_point.to_string(), fs_type))
+        })
+        .collect()
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn mount_table() -> Vec<(String, String)> {
+    Vec::new()
+}
+
+impl StorageManager {
+    /// 数据目录自检：可写、是否在网络共享/漫游目录、是否按用户隔离
+    pub fn doctor_checks(&self) -> Vec<DoctorCheck> {
+        let data_dir = self.get_data_dir();
+        let display = data_dir.display().to_string();
+        let mut checks = Vec::new();
+
+        let probe = data_dir.join(".write-test");
+        let writable = fs::create_dir_all(data_dir)
+            .and_then(|_| fs::write(&probe, b"ok"))
+            .and_then(|_| fs::remove_file(&probe));
+        checks.push(match writable {
+            Ok(()) => DoctorCheck::new("data_dir_writable", "ok", format!("数据目录可写: {}", display)),
+            Err(err) => DoctorCheck::new(
+                "data_dir_writable",
+                "error",
+                format!("数据目录不可写: {} ({})", display, err),
+            ),
+        });
+
+        checks.push(if is_network_path(data_dir) {
+            DoctorCheck::new(
+                "data_dir_network",
+                "warn",
+                format!(
+                    "数据目录位于网络共享上: {}。网络中断或多台机器同时使用会导致读写失败、数据损坏，建议通过环境变量 {} 改到本地磁盘",
+                    display, DATA_DIR_ENV
+                ),
+            )
+        } else {
+            DoctorCheck::new("data_dir_network", "ok", "数据目录位于本地磁盘".to_string())
+        });
+
+        let roaming = dirs::data_dir().filter(|dir| Some(dir) != dirs::data_local_dir().as_ref());
+        if roaming.map_or(false, |dir| data_dir.starts_with(dir)) {
+            checks.push(DoctorCheck::new(
+                "data_dir_roaming",
+                "warn",
+                format!("数据目录位于漫游配置文件中: {}，截图和记录会随登录同步，导致登录/注销变慢", display),
+            ));
+        }
+
+        let in_home = dirs::home_dir().map_or(false, |home| data_dir.starts_with(home));
+        let per_user_override = std::env::var(DATA_DIR_ENV).map_or(false, |value| value.contains(USER_PLACEHOLDER));
+        checks.push(if in_home || per_user_override {
+            DoctorCheck::new(
+                "data_dir_per_user",
+                "ok",
+                format!("数据按系统账户隔离（当前账户: {}）", current_user_name()),
+            )
+        } else {
+            DoctorCheck::new(
+                "data_dir_per_user",
+                "warn",
+                format!(
+                    "数据目录不在当前账户的用户目录下: {}，本机其他账户可能读写同一份数据；可在 {} 中使用 {} 按账户区分",
+                    display, DATA_DIR_ENV, USER_PLACEHOLDER
+                ),
+            )
+        });
+        checks
+    }
+}
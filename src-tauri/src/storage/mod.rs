@@ -1,4 +1,39 @@
-use chrono::{DateTime, Local, Duration, Timelike};
+mod actor;
+mod audit;
+mod config_merge;
+mod context;
+mod conversations;
+mod crypto;
+mod day_index;
+mod embeddings;
+mod heatmap;
+mod janitor;
+mod location;
+mod ocr_index;
+mod pending;
+mod redaction;
+mod time_expr;
+mod timeline;
+mod usage;
+
+pub use actor::*;
+pub use audit::*;
+pub use context::*;
+pub use conversations::*;
+pub use crypto::*;
+pub use day_index::*;
+pub use embeddings::*;
+pub use heatmap::*;
+pub use janitor::*;
+pub use location::*;
+pub use ocr_index::*;
+pub use pending::*;
+pub use redaction::*;
+pub use time_expr::*;
+pub use timeline::*;
+pub use usage::*;
+
+use chrono::{DateTime, Local, Duration, NaiveDateTime, Timelike};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -17,152 +52,1282 @@ pub struct Config {
     #[serde(default)]
     pub global_prompt: GlobalPromptConfig,
     #[serde(default)]
+    pub context_packs: ContextPackConfig,
+    #[serde(default)]
+    pub tickets: TicketConfig,
+    #[serde(default)]
+    pub watch_folders: WatchFolderConfig,
+    #[serde(default)]
     pub ui: UiConfig,
+    #[serde(default)]
+    pub hotkeys: HotkeyConfig,
+    #[serde(default)]
+    pub api_server: ApiServerConfig,
+    #[serde(default)]
+    pub faults: FaultConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub workspaces: WorkspaceConfig,
+    #[serde(default)]
+    pub browser: BrowserConfig,
+    #[serde(default)]
+    pub agent_presets: Vec<AgentPreset>,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub profile_schedule: ProfileScheduleConfig,
+    #[serde(default)]
+    pub embedding: EmbeddingConfig,
+    #[serde(default)]
+    pub calendar: CalendarConfig,
+    #[serde(default)]
+    pub speech: SpeechConfig,
+}
+
+// ============ 全局提示词配置 ============
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalPromptItem {
+    pub id: String,           // 唯一标识 (UUID)
+    pub name: String,         // 提示词名称（如"个人信息"、"公司信息"）
+    pub content: String,      // 提示词内容
+    pub enabled: bool,        // 是否启用
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GlobalPromptConfig {
+    #[serde(default)]
+    pub items: Vec<GlobalPromptItem>,
+}
+
+// ============ 外部工单配置 ============
+
+/// 从提醒创建 GitHub / Jira 工单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketConfig {
+    #[serde(default)]
+    pub provider: String,  // github | jira，空表示未启用
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub github_repo: String,  // owner/repo
+    #[serde(default)]
+    pub github_token: String,
+    #[serde(default)]
+    pub jira_base_url: String,  // https://xxx.atlassian.net
+    #[serde(default)]
+    pub jira_email: String,
+    #[serde(default)]
+    pub jira_api_token: String,
+    #[serde(default)]
+    pub jira_project_key: String,
+    #[serde(default = "default_jira_issue_type")]
+    pub jira_issue_type: String,
+}
+
+impl Default for TicketConfig {
+    fn default() -> Self {
+        Self {
+            provider: String::new(),
+            labels: Vec::new(),
+            github_repo: String::new(),
+            github_token: String::new(),
+            jira_base_url: String::new(),
+            jira_email: String::new(),
+            jira_api_token: String::new(),
+            jira_project_key: String::new(),
+            jira_issue_type: default_jira_issue_type(),
+        }
+    }
+}
+
+fn default_jira_issue_type() -> String {
+    "Bug".to_string()
+}
+
+// ============ 监视文件夹配置 ============
+
+/// 文件出现在监视目录时自动调用 skill（如：总结放入 ~/Inbox 的每个 PDF）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchFolder {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub path: String,  // 监视目录，支持 ~ 开头
+    pub skill: String,
+    #[serde(default)]
+    pub args: String,  // 传给 skill 的参数，{file} 替换为文件路径，空时直接传文件路径
+    #[serde(default)]
+    pub extensions: Vec<String>,  // 只处理这些扩展名（如 pdf），空表示全部
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default = "default_watch_folder_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchFolderConfig {
+    #[serde(default)]
+    pub items: Vec<WatchFolder>,
+    #[serde(default = "default_watch_folder_debounce_ms")]
+    pub debounce_ms: u64,  // 文件停止变化这么久后才触发，避免处理未写完的文件
+    #[serde(default = "default_watch_folder_history_limit")]
+    pub history_limit: usize,  // 每个监视保留的执行记录条数
+}
+
+impl Default for WatchFolderConfig {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            debounce_ms: default_watch_folder_debounce_ms(),
+            history_limit: default_watch_folder_history_limit(),
+        }
+    }
+}
+
+fn default_watch_folder_enabled() -> bool {
+    true
+}
+
+fn default_watch_folder_debounce_ms() -> u64 {
+    3000
+}
+
+fn default_watch_folder_history_limit() -> usize {
+    50
+}
+
+// ============ 场景资料包配置 ============
+
+/// 绑定到场景的参考资料（文档、runbook），场景最近出现过时自动加入对话上下文
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPack {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub scenes: Vec<String>,  // 匹配记录的 scene 字段（如 coding、npm-install），不区分大小写
+    #[serde(default)]
+    pub content: String,      // 直接填写的资料内容
+    #[serde(default)]
+    pub files: Vec<String>,   // 资料文件路径（文本文件）
+    #[serde(default = "default_context_pack_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPackConfig {
+    #[serde(default)]
+    pub items: Vec<ContextPack>,
+    #[serde(default = "default_context_pack_lookback_minutes")]
+    pub lookback_minutes: u32,  // 只看最近多少分钟的记录判断当前场景
+    #[serde(default = "default_context_pack_max_chars")]
+    pub max_chars: usize,       // 注入的资料总字数上限
+}
+
+impl Default for ContextPackConfig {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            lookback_minutes: default_context_pack_lookback_minutes(),
+            max_chars: default_context_pack_max_chars(),
+        }
+    }
+}
+
+fn default_context_pack_enabled() -> bool {
+    true
+}
+
+fn default_context_pack_lookback_minutes() -> u32 {
+    15
+}
+
+fn default_context_pack_max_chars() -> usize {
+    8000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelConfig {
+    pub provider: String,
+    pub api: ApiConfig,
+    pub ollama: OllamaConfig,
+    #[serde(default)]
+    pub gemini: GeminiConfig,
+    #[serde(default)]
+    pub profiles: Vec<ModelProfile>,  // 命名模型配置（如 fast / vision / coding）
+    #[serde(default)]
+    pub capture_profile: String,  // 截屏分析使用的模型配置名，空表示默认模型
+    #[serde(default)]
+    pub summarizer_profile: String,  // 历史压缩/工具输出摘要使用的模型配置名，空表示不调用模型
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub retry_overrides: HashMap<String, RetryPolicy>,  // 按提供者（api / ollama / gemini）覆盖重试策略
+    #[serde(default = "default_queue_when_offline")]
+    pub queue_when_offline: bool,  // 无法连接模型时把对话请求加入离线队列，恢复后自动发送
+}
+
+fn default_queue_when_offline() -> bool {
+    true
+}
+
+impl ModelConfig {
+    /// 当前提供者生效的重试策略
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        self.retry_overrides.get(&self.provider).unwrap_or(&self.retry)
+    }
+}
+
+/// 模型请求失败（超时、网络、限流、5xx）时的重试策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    #[serde(default = "default_retry_max_retries")]
+    pub max_retries: usize,
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub base_delay_ms: u64,  // 首次重试等待时间，之后每次翻倍
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub max_delay_ms: u64,
+    #[serde(default = "default_retry_jitter")]
+    pub jitter: f32,  // 随机抖动比例 (0.0-1.0)，避免多个请求同时重试
+    #[serde(default = "default_retry_on_rate_limit")]
+    pub retry_on_rate_limit: bool,  // 429 限流时是否重试
+    #[serde(default = "default_honor_retry_after")]
+    pub honor_retry_after: bool,  // 按服务端 Retry-After 等待
+    #[serde(default = "default_max_retry_after_secs")]
+    pub max_retry_after_secs: u64,  // Retry-After 超过此值时不再等待，直接报错
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_retry_max_retries(),
+            base_delay_ms: default_retry_base_delay_ms(),
+            max_delay_ms: default_retry_max_delay_ms(),
+            jitter: default_retry_jitter(),
+            retry_on_rate_limit: default_retry_on_rate_limit(),
+            honor_retry_after: default_honor_retry_after(),
+            max_retry_after_secs: default_max_retry_after_secs(),
+        }
+    }
+}
+
+fn default_retry_max_retries() -> usize {
+    2
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    400
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    10_000
+}
+
+fn default_retry_jitter() -> f32 {
+    0.2
+}
+
+fn default_retry_on_rate_limit() -> bool {
+    true
+}
+
+fn default_honor_retry_after() -> bool {
+    true
+}
+
+fn default_max_retry_after_secs() -> u64 {
+    60
+}
+
+/// 命名模型配置：只覆盖填写的字段，其余沿用默认模型配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelProfile {
+    pub name: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub request_format: Option<String>,
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+    #[serde(default)]
+    pub verbosity: Option<String>,
+    #[serde(default)]
+    pub deployment: Option<String>,  // Azure 部署名
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    #[serde(rename = "type")]
+    pub api_type: String,  // openai | azure（其余按 OpenAI 兼容接口处理）
+    #[serde(default = "default_api_request_format")]
+    pub request_format: String,
+    #[serde(default)]
+    pub responses_query_params: HashMap<String, String>,
+    #[serde(default)]
+    pub responses_headers: HashMap<String, String>,  // 仅 Responses 接口使用的请求头
+    #[serde(default)]
+    pub custom_headers: HashMap<String, String>,  // 所有请求都附带的请求头，可覆盖 Authorization
+    #[serde(default)]
+    pub organization: String,  // OpenAI-Organization
+    #[serde(default)]
+    pub project: String,  // OpenAI-Project
+    pub endpoint: String,
+    pub api_key: String,
+    pub model: String,
+    #[serde(default = "default_max_request_bytes")]
+    pub max_request_bytes: usize,  // 请求体大小上限（字节），超出时先压缩图片、裁剪历史；0 表示不检查
+    #[serde(default)]
+    pub reasoning_effort: String,  // 推理强度：minimal / low / medium / high，空表示自动
+    #[serde(default)]
+    pub verbosity: String,  // 输出详略：low / medium / high，空表示模型默认
+    #[serde(default)]
+    pub pricing: Option<ModelPricing>,  // 用量费用估算价格，空时按内置价格表
+    #[serde(default)]
+    pub azure_deployment: String,  // Azure 部署名，空时使用 model
+    #[serde(default = "default_azure_api_version")]
+    pub azure_api_version: String,
+    #[serde(default)]
+    pub temperature: Option<f32>,  // 采样温度，空表示模型默认
+}
+
+/// 模型价格（美元 / 百万 token）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+fn default_azure_api_version() -> String {
+    "2024-10-21".to_string()
+}
+
+fn default_api_request_format() -> String {
+    "chat_completions".to_string()
+}
+
+fn default_max_request_bytes() -> usize {
+    20 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    pub endpoint: String,
+    pub model: String,
+    #[serde(default = "default_ollama_keep_alive")]
+    pub keep_alive: String,  // 模型常驻内存时长，如 30m / 2h / -1（永久），空表示使用 Ollama 默认值
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+fn default_ollama_keep_alive() -> String {
+    "30m".to_string()
+}
+
+/// Google Gemini（Generative Language API 原生接口）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiConfig {
+    #[serde(default = "default_gemini_endpoint")]
+    pub endpoint: String,
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_gemini_model")]
+    pub model: String,
+    #[serde(default)]
+    pub pricing: Option<ModelPricing>,  // 自定义价格，空则按内置价格表估算
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: default_gemini_endpoint(),
+            api_key: String::new(),
+            model: default_gemini_model(),
+            pricing: None,
+            temperature: None,
+        }
+    }
+}
+
+fn default_gemini_endpoint() -> String {
+    "https://generativelanguage.googleapis.com/v1beta".to_string()
+}
+
+fn default_gemini_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+    pub interval_ms: u64,
+    pub compress_quality: u8,
+    #[serde(default = "default_skip_unchanged")]
+    pub skip_unchanged: bool,  // 跳过无变化的画面，节省token
+    #[serde(default = "default_change_threshold")]
+    pub change_threshold: f32,  // 变化阈值 (0.0-1.0)，越小越敏感
+    #[serde(default = "default_recent_summary_limit")]
+    pub recent_summary_limit: usize,  // 近期摘要条数（用于上下文参考）
+    #[serde(default = "default_recent_detail_limit")]
+    pub recent_detail_limit: usize,  // 近期 detail 条数（用于截图分析上下文）
+    #[serde(default = "default_alert_confidence_threshold")]
+    pub alert_confidence_threshold: f32,  // issue 提醒触发阈值
+    #[serde(default = "default_alert_cooldown_seconds")]
+    pub alert_cooldown_seconds: u64,  // issue 提醒冷却时间（秒）
+    #[serde(default = "default_alert_idle_threshold_seconds")]
+    pub alert_idle_threshold_seconds: u64,  // 用户空闲超过 N 秒视为离开，提醒暂存到回来后推送，0 表示不检测
+    #[serde(default)]
+    pub alert_webhook_url: String,  // 用户离开时同时把提醒 POST 到此地址
+    #[serde(default = "default_alert_verify_minutes")]
+    pub alert_verify_minutes: u64,  // 给出建议后 N 分钟复查问题是否解决，0 表示不复查
+    #[serde(default = "default_alert_escalation_captures")]
+    pub alert_escalation_captures: u32,  // 暂停期间高紧急度问题连续出现 N 次后升级提醒，0 表示不升级
+    #[serde(default = "default_alert_max_per_hour")]
+    pub alert_max_per_hour: u32,  // 每小时最多推送的提醒数（所有问题合计），接近上限时先丢弃低紧急度提醒，0 表示不限制
+    #[serde(default)]
+    pub ocr_enabled: bool,  // 画面仅文字变化时用本地 OCR 文本代替截图发送给模型
+    #[serde(default = "default_ocr_command")]
+    pub ocr_command: String,  // OCR 可执行文件（tesseract 兼容）
+    #[serde(default = "default_ocr_languages")]
+    pub ocr_languages: String,  // OCR 语言包，如 chi_sim+eng
+    #[serde(default = "default_ocr_text_only_threshold")]
+    pub ocr_text_only_threshold: f32,  // 与上一帧相似度高于此值时只发送 OCR 文本
+    #[serde(default)]
+    pub ocr_index_enabled: bool,  // 每张截图都做本地 OCR 并保存文字层，供截图文字检索
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRule>,  // 本地规则提醒，不依赖模型判断
+    #[serde(default)]
+    pub prompt_experiment: PromptExperiment,
+    #[serde(default)]
+    pub budget: CaptureBudget,
+    #[serde(default)]
+    pub target: CaptureTarget,  // 截屏范围，随配置档案保存
+    #[serde(default)]
+    pub indicator: CaptureIndicatorConfig,
+    #[serde(default)]
+    pub region_diff: RegionDiffConfig,
+    #[serde(default)]
+    pub batch: CaptureBatchConfig,
+}
+
+/// 批量分析：攒下多张有变化的截图，合并为一次模型调用，适合按请求次数计费的接口
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureBatchConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_batch_max_frames")]
+    pub max_frames: usize,  // 攒够多少帧立即分析
+    #[serde(default = "default_batch_window_seconds")]
+    pub window_seconds: u64,  // 第一帧等待超过此时长后即使未攒够也分析
+}
+
+fn default_batch_max_frames() -> usize {
+    5
+}
+
+fn default_batch_window_seconds() -> u64 {
+    120
+}
+
+impl Default for CaptureBatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_frames: default_batch_max_frames(),
+            window_seconds: default_batch_window_seconds(),
+        }
+    }
+}
+
+/// 分块变化检测：把画面切成网格逐块比较，整图哈希容易漏掉的小范围变化（如终端里新出现的一行报错）也能识别
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionDiffConfig {
+    #[serde(default)]
+    pub enabled: bool,  // 开启后由区块比较决定画面是否变化，代替整图相似度
+    #[serde(default = "default_region_diff_cols")]
+    pub cols: u32,
+    #[serde(default = "default_region_diff_rows")]
+    pub rows: u32,
+    #[serde(default = "default_region_diff_pixel_tolerance")]
+    pub pixel_tolerance: u8,  // 采样点灰度差超过此值才算变化
+    #[serde(default = "default_region_diff_min_changed_samples")]
+    pub min_changed_samples: u32,  // 区块内变化的采样点不少于此数才算区块变化，过滤光标闪烁
+    #[serde(default)]
+    pub crop_changed: bool,  // 只把变化区域裁剪后发送给模型，节省视觉 token
+    #[serde(default = "default_region_diff_max_crop_ratio")]
+    pub max_crop_ratio: f32,  // 变化区域占整屏比例超过此值时仍发送整张截图
+}
+
+fn default_region_diff_cols() -> u32 {
+    8
+}
+
+fn default_region_diff_rows() -> u32 {
+    6
+}
+
+fn default_region_diff_pixel_tolerance() -> u8 {
+    24
+}
+
+fn default_region_diff_min_changed_samples() -> u32 {
+    3
+}
+
+fn default_region_diff_max_crop_ratio() -> f32 {
+    0.5
+}
+
+impl Default for RegionDiffConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cols: default_region_diff_cols(),
+            rows: default_region_diff_rows(),
+            pixel_tolerance: default_region_diff_pixel_tolerance(),
+            min_changed_samples: default_region_diff_min_changed_samples(),
+            crop_changed: false,
+            max_crop_ratio: default_region_diff_max_crop_ratio(),
+        }
+    }
+}
+
+/// 截屏指示器：分析屏幕期间在屏幕角落显示一个小圆点
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureIndicatorConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub required: bool,  // 必须显示：指示器无法显示时不截屏，截屏期间不能手动隐藏
+    #[serde(default = "default_indicator_position")]
+    pub position: String,  // top-right / top-left / bottom-right / bottom-left
+}
+
+fn default_indicator_position() -> String {
+    "top-right".to_string()
+}
+
+impl Default for CaptureIndicatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required: false,
+            position: default_indicator_position(),
+        }
+    }
+}
+
+/// 截屏分析预算，0 表示不限制；超出后截图仍保存，延后到预算恢复时再分析
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureBudget {
+    #[serde(default)]
+    pub max_analyses_per_hour: u32,
+    #[serde(default)]
+    pub max_analyses_per_day: u32,
+    #[serde(default)]
+    pub daily_cost_cap_usd: f64,  // 按用量记录估算的当日截屏分析费用上限
+}
+
+/// 截屏分析提示词 A/B 实验：启用后相邻帧交替使用两个变体，记录中标注变体名
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptExperiment {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub variant_a: String,  // 提示词模板，空表示内置提示词；可使用 {window_hint} 和 {recent_context}
+    #[serde(default)]
+    pub variant_b: String,
+}
+
+/// 关键词/正则提醒规则：匹配 OCR 文字、窗口标题或应用名时直接触发提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_alert_rule_enabled")]
+    pub enabled: bool,
+    pub pattern: String,
+    #[serde(default)]
+    pub is_regex: bool,  // false 时按关键词（忽略大小写）匹配
+    #[serde(default = "default_alert_rule_fields")]
+    pub fields: Vec<String>,  // 匹配范围：ocr / title / app
+    #[serde(default)]
+    pub apps: Vec<String>,  // 只在这些应用中生效，空表示全部
+    #[serde(default = "default_alert_rule_urgency")]
+    pub urgency: String,  // high / medium / low
+    #[serde(default)]
+    pub message: String,  // 提醒内容，空时使用规则名和命中文字
+    #[serde(default)]
+    pub suggestion: String,
+}
+
+fn default_alert_rule_enabled() -> bool {
+    true
+}
+
+fn default_alert_rule_fields() -> Vec<String> {
+    vec!["ocr".to_string(), "title".to_string()]
+}
+
+fn default_alert_rule_urgency() -> String {
+    "high".to_string()
+}
+
+/// 截屏范围：screen 为整个主屏，region 为固定区域，window 为指定窗口
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureTarget {
+    #[serde(default = "default_capture_target_mode")]
+    pub mode: String,
+    #[serde(default)]
+    pub region: Option<CaptureRegion>,
+    #[serde(default)]
+    pub window_handle: Option<u64>,  // 窗口句柄（Windows HWND / X11 窗口 ID），优先于标题和进程匹配
+    #[serde(default)]
+    pub window_title: String,  // 窗口标题包含的关键词
+    #[serde(default)]
+    pub window_process: String,  // 进程名/应用名
+}
+
+/// 屏幕坐标区域，多屏时以虚拟桌面为坐标系
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CaptureRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn default_capture_target_mode() -> String {
+    "screen".to_string()
+}
+
+impl Default for CaptureTarget {
+    fn default() -> Self {
+        Self {
+            mode: default_capture_target_mode(),
+            region: None,
+            window_handle: None,
+            window_title: String::new(),
+            window_process: String::new(),
+        }
+    }
+}
+
+/// 隐私排除规则：命中时不截图，只记录一条“已跳过（私密）”
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    #[serde(default = "default_privacy_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_privacy_blocked_apps")]
+    pub blocked_apps: Vec<String>,  // 进程名/应用名，如 1Password、KeePass
+    #[serde(default = "default_privacy_blocked_title_keywords")]
+    pub blocked_title_keywords: Vec<String>,  // 窗口标题关键词，如 网上银行、password
+    #[serde(default)]
+    pub time_rules: Vec<PrivacyTimeRule>,  // 不截屏的时间段
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyTimeRule {
+    pub start: String,  // HH:MM
+    pub end: String,    // HH:MM，早于 start 表示跨午夜
+    #[serde(default)]
+    pub weekdays: Vec<u32>,  // 1=周一 … 7=周日，空表示每天
+}
+
+fn default_privacy_enabled() -> bool {
+    true
+}
+
+fn default_privacy_blocked_apps() -> Vec<String> {
+    ["1Password", "KeePass", "KeePassXC", "Bitwarden", "LastPass", "Dashlane", "Keychain Access"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_privacy_blocked_title_keywords() -> Vec<String> {
+    ["网上银行", "网银", "online banking", "password manager", "InPrivate", "无痕"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_privacy_enabled(),
+            blocked_apps: default_privacy_blocked_apps(),
+            blocked_title_keywords: default_privacy_blocked_title_keywords(),
+            time_rules: Vec::new(),
+        }
+    }
+}
+
+fn default_skip_unchanged() -> bool {
+    true  // 默认启用，节省token
+}
+
+fn default_change_threshold() -> f32 {
+    0.95  // 相似度超过95%认为无变化
+}
+
+fn default_recent_summary_limit() -> usize {
+    8
+}
+
+fn default_recent_detail_limit() -> usize {
+    3
+}
+
+fn default_alert_confidence_threshold() -> f32 {
+    0.7
+}
+
+fn default_alert_cooldown_seconds() -> u64 {
+    120
+}
+
+fn default_alert_idle_threshold_seconds() -> u64 {
+    180
+}
+
+fn default_alert_verify_minutes() -> u64 {
+    5
+}
+
+fn default_alert_max_per_hour() -> u32 {
+    6
+}
+
+fn default_alert_escalation_captures() -> u32 {
+    3
+}
+
+fn default_ocr_command() -> String {
+    "tesseract".to_string()
+}
+
+fn default_ocr_languages() -> String {
+    "chi_sim+eng".to_string()
+}
+
+fn default_ocr_text_only_threshold() -> f32 {
+    0.85
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    pub retention_days: u32,
+    pub max_screenshots: u32,
+    #[serde(default = "default_max_disk_usage_mb")]
+    pub max_disk_usage_mb: u64,  // 截图与摘要的磁盘上限（MB），0 表示不限制
+    #[serde(default = "default_max_context_chars")]
+    pub max_context_chars: usize,
+    #[serde(default = "default_max_context_tokens")]
+    pub max_context_tokens: usize,
+    #[serde(default = "default_context_compress_trigger_ratio")]
+    pub context_compress_trigger_ratio: f32,
+    #[serde(default)]
+    pub auto_clear_on_start: bool,  // 启动时自动清空历史
+    #[serde(default = "default_context_mode")]
+    pub context_mode: String,  // 对话上下文模式：auto | always | off
+    #[serde(default = "default_context_detail_hours")]
+    pub context_detail_hours: u32,  // detail 仅保留最近 N 小时
+    #[serde(default = "default_context_strategy")]
+    pub context_strategy: String,  // 上下文策略：recent_first | issue_first | app_filtered[:app] | semantic
+    #[serde(default)]
+    pub context_aggregate_ratio: Option<f32>,  // 聚合概要占上下文预算的比例，空则使用策略默认值
+    #[serde(default = "default_aggregation_granularity")]
+    pub aggregation_granularity: String,  // 历史聚合粒度：stored | block_15m | app_session | scene
+    #[serde(default = "default_digest_enabled")]
+    pub digest_enabled: bool,  // 自动生成日报/周报
+    #[serde(default)]
+    pub skill_suggestions_enabled: bool,  // 每周根据反复出现的问题生成 skill 建议
+}
+
+fn default_max_disk_usage_mb() -> u64 {
+    0
+}
+
+fn default_max_context_chars() -> usize {
+    1_000_000
+}
+
+fn default_max_context_tokens() -> usize {
+    128_000
+}
+
+fn default_context_compress_trigger_ratio() -> f32 {
+    0.92
+}
+
+fn default_context_mode() -> String {
+    "auto".to_string()
+}
+
+fn default_context_detail_hours() -> u32 {
+    24
+}
+
+fn default_context_strategy() -> String {
+    "recent_first".to_string()
+}
+
+fn default_aggregation_granularity() -> String {
+    "stored".to_string()
+}
+
+fn default_digest_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    #[serde(default = "default_show_progress")]
+    pub show_progress: bool,
+    #[serde(default = "default_notification_backend")]
+    pub notification_backend: String,  // window（自绘通知窗口）| native（系统通知，遵循勿扰模式）
+}
+
+fn default_show_progress() -> bool {
+    true
+}
+
+fn default_notification_backend() -> String {
+    "window".to_string()
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            show_progress: default_show_progress(),
+            notification_backend: default_notification_backend(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HotkeyConfig {
+    #[serde(default = "default_hotkeys_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_hotkey_capture_now")]
+    pub capture_now: String,  // 立即截屏并分析
+    #[serde(default = "default_hotkey_toggle_capture")]
+    pub toggle_capture: String,  // 开始/停止截屏
+    #[serde(default = "default_hotkey_quick_ask")]
+    pub quick_ask: String,  // 带当前截图打开快捷提问窗口
+    #[serde(default = "default_hotkey_push_to_talk")]
+    pub push_to_talk: String,  // 按住录音，松开后转写并填入输入框
+}
+
+// 全局快捷键会抢占其他应用的同名快捷键（如另存为），默认关闭，需在配置中手动开启
+fn default_hotkeys_enabled() -> bool {
+    false
+}
+
+fn default_hotkey_capture_now() -> String {
+    "CmdOrCtrl+Shift+S".to_string()
+}
+
+fn default_hotkey_toggle_capture() -> String {
+    "CmdOrCtrl+Shift+P".to_string()
+}
+
+fn default_hotkey_quick_ask() -> String {
+    "CmdOrCtrl+Shift+Space".to_string()
+}
+
+fn default_hotkey_push_to_talk() -> String {
+    "CmdOrCtrl+Shift+M".to_string()
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_hotkeys_enabled(),
+            capture_now: default_hotkey_capture_now(),
+            toggle_capture: default_hotkey_toggle_capture(),
+            quick_ask: default_hotkey_quick_ask(),
+            push_to_talk: default_hotkey_push_to_talk(),
+        }
+    }
+}
+
+/// 本地 HTTP/WebSocket 接口，只监听 127.0.0.1
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiServerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_api_server_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub token: String,  // 为空时启动时自动生成
+}
+
+fn default_api_server_port() -> u16 {
+    17321
+}
+
+impl Default for ApiServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_api_server_port(),
+            token: String::new(),
+        }
+    }
+}
+
+/// 故障注入（仅 debug 构建生效），用于在集成测试里触发重试/上下文超长/降级逻辑
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct FaultConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub model_error_rate: f32,  // 模型请求返回 HTTP 错误的概率 (0.0-1.0)
+    #[serde(default)]
+    pub model_error_statuses: Vec<u16>,  // 随机选取的状态码，为空时使用 429/500
+    #[serde(default)]
+    pub retry_after_secs: Option<u64>,  // 注入 429 时附带的 Retry-After
+    #[serde(default)]
+    pub context_overflow_rate: f32,  // 模型请求报上下文超长的概率
+    #[serde(default)]
+    pub model_delay_ms: u64,  // 每次模型请求前额外等待，模拟慢响应
+    #[serde(default)]
+    pub tool_io_error_rate: f32,  // 工具调用返回 IO 错误的概率
+    #[serde(default)]
+    pub tools: Vec<String>,  // 只对这些工具注入 IO 错误，为空表示全部
+}
+
+/// 用户登记的工作目录，工具默认在当前工作区下读写和运行命令
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Workspace {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkspaceConfig {
+    #[serde(default)]
+    pub items: Vec<Workspace>,
+    #[serde(default)]
+    pub active: Option<String>,  // 当前工作区名称，为空时使用 allowed_dirs 的第一个目录
+    #[serde(default = "default_workspace_auto_detect")]
+    pub auto_detect: bool,  // 添加目录时向上查找项目根目录（.git、Cargo.toml 等）
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            active: None,
+            auto_detect: default_workspace_auto_detect(),
+        }
+    }
+}
+
+fn default_workspace_auto_detect() -> bool {
+    true
+}
+
+/// 脱敏规则：新记录保存前自动替换，也可对已有记录补执行
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedactionRule {
+    pub name: String,
+    #[serde(default = "default_redaction_kind")]
+    pub kind: String,  // keyword（不区分大小写的文本）| regex | email | phone
+    #[serde(default)]
+    pub pattern: String,  // keyword / regex 使用；email / phone 使用内置规则
+    #[serde(default)]
+    pub replacement: String,  // 空表示 [已脱敏]
+    #[serde(default = "default_redaction_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_redaction_kind() -> String {
+    "keyword".to_string()
+}
+
+fn default_redaction_rule_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+}
+
+/// 合规审计记录，默认关闭；开启后记录截屏、模型请求和工具改动文件，可导出为签名的 JSONL
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// 按时间段自动切换配置方案，如工作日 9-18 点使用 work，其余时间使用 personal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ProfileScheduleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub rules: Vec<ProfileScheduleRule>,  // 按顺序匹配，第一条命中的生效
+    #[serde(default)]
+    pub fallback_profile: String,  // 没有规则命中时使用，空表示保持不变
+    #[serde(default)]
+    pub active_profile: String,  // 调度器最近一次切换到的方案
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileScheduleRule {
+    pub profile: String,
+    #[serde(default)]
+    pub weekdays: Vec<u32>,  // 1=周一 ... 7=周日，空表示每天
+    pub start: String,  // HH:MM
+    pub end: String,    // HH:MM，早于 start 时表示跨夜
+}
+
+/// 语义检索，默认关闭；开启后后台为摘要记录计算向量（使用当前模型提供者的 embeddings 接口），
+/// 对话检索时把语义相近的记录并入关键词检索结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmbeddingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub model: String,  // embedding 模型，空表示按提供者使用默认模型
+    #[serde(default = "default_embedding_min_similarity")]
+    pub min_similarity: f32,  // 余弦相似度低于此值的记录不并入结果
+    #[serde(default = "default_embedding_max_results")]
+    pub max_results: usize,
+    #[serde(default = "default_embedding_index_days")]
+    pub index_days: u32,  // 只为最近 N 天的记录计算向量
 }
 
-// ============ 全局提示词配置 ============
+fn default_embedding_min_similarity() -> f32 {
+    0.45
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GlobalPromptItem {
-    pub id: String,           // 唯一标识 (UUID)
-    pub name: String,         // 提示词名称（如"个人信息"、"公司信息"）
-    pub content: String,      // 提示词内容
-    pub enabled: bool,        // 是否启用
+fn default_embedding_max_results() -> usize {
+    8
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct GlobalPromptConfig {
-    #[serde(default)]
-    pub items: Vec<GlobalPromptItem>,
+fn default_embedding_index_days() -> u32 {
+    30
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ModelConfig {
-    pub provider: String,
-    pub api: ApiConfig,
-    pub ollama: OllamaConfig,
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: String::new(),
+            min_similarity: default_embedding_min_similarity(),
+            max_results: default_embedding_max_results(),
+            index_days: default_embedding_index_days(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ApiConfig {
-    #[serde(rename = "type")]
-    pub api_type: String,
-    #[serde(default = "default_api_request_format")]
-    pub request_format: String,
-    #[serde(default)]
-    pub responses_query_params: HashMap<String, String>,
+/// 解析「本周」「昨天」「工作时间」等说法时使用的一周起始日、一天起始时刻和工作时间
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CalendarConfig {
+    #[serde(default = "default_week_start")]
+    pub week_start: String,  // auto（按系统区域设置）| monday | sunday | saturday
     #[serde(default)]
-    pub responses_headers: HashMap<String, String>,
-    pub endpoint: String,
-    pub api_key: String,
-    pub model: String,
+    pub day_start_hour: u32,  // 0-23，凌晨未到该时刻的记录算作前一天
+    #[serde(default = "default_work_start")]
+    pub work_start: String,  // HH:MM
+    #[serde(default = "default_work_end")]
+    pub work_end: String,
 }
 
-fn default_api_request_format() -> String {
-    "chat_completions".to_string()
+fn default_week_start() -> String {
+    "auto".to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OllamaConfig {
-    pub endpoint: String,
-    pub model: String,
+fn default_work_start() -> String {
+    "09:00".to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CaptureConfig {
-    pub enabled: bool,
-    pub interval_ms: u64,
-    pub compress_quality: u8,
-    #[serde(default = "default_skip_unchanged")]
-    pub skip_unchanged: bool,  // 跳过无变化的画面，节省token
-    #[serde(default = "default_change_threshold")]
-    pub change_threshold: f32,  // 变化阈值 (0.0-1.0)，越小越敏感
-    #[serde(default = "default_recent_summary_limit")]
-    pub recent_summary_limit: usize,  // 近期摘要条数（用于上下文参考）
-    #[serde(default = "default_recent_detail_limit")]
-    pub recent_detail_limit: usize,  // 近期 detail 条数（用于截图分析上下文）
-    #[serde(default = "default_alert_confidence_threshold")]
-    pub alert_confidence_threshold: f32,  // issue 提醒触发阈值
-    #[serde(default = "default_alert_cooldown_seconds")]
-    pub alert_cooldown_seconds: u64,  // issue 提醒冷却时间（秒）
+fn default_work_end() -> String {
+    "18:00".to_string()
 }
 
-fn default_skip_unchanged() -> bool {
-    true  // 默认启用，节省token
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            week_start: default_week_start(),
+            day_start_hour: 0,
+            work_start: default_work_start(),
+            work_end: default_work_end(),
+        }
+    }
 }
 
-fn default_change_threshold() -> f32 {
-    0.95  // 相似度超过95%认为无变化
+/// 语音输入：api 使用当前 API 提供者的 /audio/transcriptions 接口，whisper_cpp 在本地调用 whisper.cpp；
+/// 朗读：system 使用系统自带语音，api 使用 /audio/speech 接口合成后本地播放
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpeechConfig {
+    #[serde(default = "default_stt_provider")]
+    pub stt_provider: String,  // api | whisper_cpp
+    #[serde(default = "default_stt_model")]
+    pub stt_model: String,  // api 下的转写模型，Azure 为部署名
+    #[serde(default)]
+    pub language: String,  // 语言代码（如 zh、en），空表示自动识别
+    #[serde(default)]
+    pub whisper_cpp_binary: String,  // whisper.cpp 可执行文件，空表示在 PATH 中查找 whisper-cli
+    #[serde(default)]
+    pub whisper_cpp_model: String,  // ggml 模型文件路径
+    #[serde(default = "default_tts_provider")]
+    pub tts_provider: String,  // system | api
+    #[serde(default = "default_tts_model")]
+    pub tts_model: String,
+    #[serde(default)]
+    pub tts_voice: String,  // 系统语音名或 API 音色（如 alloy），空表示默认
+    #[serde(default)]
+    pub speak_alerts: bool,  // 推送高紧急度提醒时自动朗读
 }
 
-fn default_recent_summary_limit() -> usize {
-    8
+fn default_tts_provider() -> String {
+    "system".to_string()
 }
 
-fn default_recent_detail_limit() -> usize {
-    3
+fn default_tts_model() -> String {
+    "tts-1".to_string()
 }
 
-fn default_alert_confidence_threshold() -> f32 {
-    0.7
+fn default_stt_provider() -> String {
+    "api".to_string()
 }
 
-fn default_alert_cooldown_seconds() -> u64 {
-    120
+fn default_stt_model() -> String {
+    "whisper-1".to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StorageConfig {
-    pub retention_days: u32,
-    pub max_screenshots: u32,
-    #[serde(default = "default_max_context_chars")]
-    pub max_context_chars: usize,
-    #[serde(default = "default_max_context_tokens")]
-    pub max_context_tokens: usize,
-    #[serde(default = "default_context_compress_trigger_ratio")]
-    pub context_compress_trigger_ratio: f32,
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            stt_provider: default_stt_provider(),
+            stt_model: default_stt_model(),
+            language: String::new(),
+            whisper_cpp_binary: String::new(),
+            whisper_cpp_model: String::new(),
+            tts_provider: default_tts_provider(),
+            tts_model: default_tts_model(),
+            tts_voice: String::new(),
+            speak_alerts: false,
+        }
+    }
+}
+
+/// 剪贴板历史记录，默认关闭；按天保存在数据目录，保留天数与摘要一致，命中隐私规则时不记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClipboardConfig {
     #[serde(default)]
-    pub auto_clear_on_start: bool,  // 启动时自动清空历史
-    #[serde(default = "default_context_mode")]
-    pub context_mode: String,  // 对话上下文模式：auto | always | off
-    #[serde(default = "default_context_detail_hours")]
-    pub context_detail_hours: u32,  // detail 仅保留最近 N 小时
+    pub history_enabled: bool,
+    #[serde(default = "default_clipboard_poll_ms")]
+    pub poll_interval_ms: u64,
+    #[serde(default = "default_clipboard_max_chars")]
+    pub max_entry_chars: usize,  // 单条记录最多保存的字符数，超出部分截断
 }
 
-fn default_max_context_chars() -> usize {
-    1_000_000
+fn default_clipboard_poll_ms() -> u64 {
+    1500
 }
 
-fn default_max_context_tokens() -> usize {
-    128_000
+fn default_clipboard_max_chars() -> usize {
+    4000
 }
 
-fn default_context_compress_trigger_ratio() -> f32 {
-    0.92
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            history_enabled: false,
+            poll_interval_ms: default_clipboard_poll_ms(),
+            max_entry_chars: default_clipboard_max_chars(),
+        }
+    }
 }
 
-fn default_context_mode() -> String {
-    "auto".to_string()
+/// 助手预设：对话时按名称选用，覆盖提示词、工具权限、模型和温度，不修改全局配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct AgentPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub system_prompt: String,  // 追加到系统提示词末尾
+    #[serde(default)]
+    pub model: Option<String>,  // 命名模型配置或模型 ID，空表示默认模型
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub tool_mode: Option<String>,  // unset | whitelist | allow_all，空表示沿用全局
+    #[serde(default)]
+    pub allowed_commands: Option<Vec<String>>,
+    #[serde(default)]
+    pub allowed_dirs: Option<Vec<String>>,
+    #[serde(default)]
+    pub ask_approval: Option<bool>,
 }
 
-fn default_context_detail_hours() -> u32 {
-    24
+/// 本地统计（截屏、跳过、模型错误、工具调用和耗时），默认关闭，数据只保存在本机
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UiConfig {
-    #[serde(default = "default_show_progress")]
-    pub show_progress: bool,
+/// 浏览器工具，通过 agent-browser 命令行（基于 Playwright）驱动无头浏览器
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BrowserConfig {
+    #[serde(default = "default_browser_command")]
+    pub command: String,  // agent-browser 可执行文件，可填完整路径
+    #[serde(default)]
+    pub headed: bool,  // 显示浏览器窗口，便于调试
+    #[serde(default = "default_browser_send_screenshots")]
+    pub send_screenshots: bool,  // 截图作为图片发回模型，模型不支持图片时关闭
+    #[serde(default = "default_browser_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,  // 会话空闲超过该时间自动关闭
 }
 
-fn default_show_progress() -> bool {
+fn default_browser_command() -> String {
+    "agent-browser".to_string()
+}
+
+fn default_browser_send_screenshots() -> bool {
     true
 }
 
-impl Default for UiConfig {
+fn default_browser_idle_timeout_secs() -> u64 {
+    600
+}
+
+impl Default for BrowserConfig {
     fn default() -> Self {
         Self {
-            show_progress: default_show_progress(),
+            command: default_browser_command(),
+            headed: false,
+            send_screenshots: default_browser_send_screenshots(),
+            idle_timeout_secs: default_browser_idle_timeout_secs(),
         }
     }
 }
@@ -175,22 +1340,88 @@ pub struct ToolConfig {
     pub allowed_commands: Vec<String>,
     #[serde(default)]
     pub allowed_dirs: Vec<String>,
+    #[serde(default = "default_ask_approval")]
+    pub ask_approval: bool,  // whitelist 模式下，白名单外的 Bash/Write 询问用户而不是直接拒绝
+    #[serde(default)]
+    pub confirm_edit_lines: usize,  // Write/Edit 改动超过该行数时需用户确认，0 表示不确认
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerConfig>,  // 外部 MCP 服务器
+    #[serde(default = "default_skill_registry_url")]
+    pub skill_registry_url: String,  // skill 市场索引地址
+    #[serde(default = "default_max_prompt_skills")]
+    pub max_prompt_skills: usize,  // 系统提示词中最多列出的技能数（按相关度和使用情况排序），0 表示全部列出
+    #[serde(default)]
+    pub automation: bool,  // 允许 ui_action 操作鼠标键盘，独立于工具模式，默认关闭
 }
 
 fn default_tool_mode() -> String {
     "unset".to_string()
 }
 
+// 需要前端授权对话框答复，默认关闭，白名单外的操作直接拒绝
+fn default_ask_approval() -> bool {
+    false
+}
+
+fn default_max_prompt_skills() -> usize {
+    20
+}
+
+fn default_skill_registry_url() -> String {
+    "https://raw.githubusercontent.com/mypengpengli/OpenCowork-skills/main/index.json".to_string()
+}
+
 impl Default for ToolConfig {
     fn default() -> Self {
         Self {
             mode: default_tool_mode(),
             allowed_commands: Vec::new(),
             allowed_dirs: Vec::new(),
+            ask_approval: default_ask_approval(),
+            confirm_edit_lines: 0,
+            mcp_servers: Vec::new(),
+            skill_registry_url: default_skill_registry_url(),
+            max_prompt_skills: default_max_prompt_skills(),
+            automation: false,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerConfig {
+    pub name: String,
+    #[serde(default = "default_mcp_transport")]
+    pub transport: String,  // stdio | sse
+    #[serde(default = "default_mcp_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub command: String,  // stdio: 启动命令
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub url: String,  // sse: 服务地址
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,  // 允许调用的工具，空表示全部允许
+    #[serde(default = "default_mcp_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_mcp_transport() -> String {
+    "stdio".to_string()
+}
+
+fn default_mcp_enabled() -> bool {
+    true
+}
+
+fn default_mcp_timeout_ms() -> u64 {
+    60_000
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -201,14 +1432,33 @@ impl Default for Config {
                     request_format: default_api_request_format(),
                     responses_query_params: HashMap::new(),
                     responses_headers: HashMap::new(),
+                    custom_headers: HashMap::new(),
+                    organization: String::new(),
+                    project: String::new(),
                     endpoint: "https://api.openai.com/v1".to_string(),
                     api_key: String::new(),
                     model: "gpt-4-vision-preview".to_string(),
+                    max_request_bytes: default_max_request_bytes(),
+                    reasoning_effort: String::new(),
+                    verbosity: String::new(),
+                    pricing: None,
+                    azure_deployment: String::new(),
+                    azure_api_version: default_azure_api_version(),
+                    temperature: None,
                 },
                 ollama: OllamaConfig {
                     endpoint: "http://localhost:11434".to_string(),
                     model: "llava".to_string(),
+                    keep_alive: default_ollama_keep_alive(),
+                    temperature: None,
                 },
+                gemini: GeminiConfig::default(),
+                profiles: Vec::new(),
+                capture_profile: String::new(),
+                summarizer_profile: String::new(),
+                retry: RetryPolicy::default(),
+                retry_overrides: HashMap::new(),
+                queue_when_offline: default_queue_when_offline(),
             },
             capture: CaptureConfig {
                 enabled: true,
@@ -220,24 +1470,61 @@ impl Default for Config {
                 recent_detail_limit: 3,
                 alert_confidence_threshold: 0.7,
                 alert_cooldown_seconds: 120,
+                alert_idle_threshold_seconds: default_alert_idle_threshold_seconds(),
+                alert_webhook_url: String::new(),
+                alert_verify_minutes: default_alert_verify_minutes(),
+                alert_escalation_captures: default_alert_escalation_captures(),
+                alert_max_per_hour: default_alert_max_per_hour(),
+                ocr_enabled: false,
+                ocr_command: default_ocr_command(),
+                ocr_languages: default_ocr_languages(),
+                ocr_text_only_threshold: default_ocr_text_only_threshold(),
+                ocr_index_enabled: false,
+                privacy: PrivacyConfig::default(),
+                alert_rules: Vec::new(),
+                prompt_experiment: PromptExperiment::default(),
+                budget: CaptureBudget::default(),
+                target: CaptureTarget::default(),
+                indicator: CaptureIndicatorConfig::default(),
+                region_diff: RegionDiffConfig::default(),
+                batch: CaptureBatchConfig::default(),
             },
             storage: StorageConfig {
                 retention_days: 7,
                 max_screenshots: 10000,
+                max_disk_usage_mb: default_max_disk_usage_mb(),
                 max_context_chars: 1_000_000,
                 max_context_tokens: default_max_context_tokens(),
                 context_compress_trigger_ratio: default_context_compress_trigger_ratio(),
                 auto_clear_on_start: false,
                 context_mode: default_context_mode(),
                 context_detail_hours: default_context_detail_hours(),
+                context_strategy: default_context_strategy(),
+                context_aggregate_ratio: None,
+                aggregation_granularity: default_aggregation_granularity(),
+                digest_enabled: default_digest_enabled(),
+                skill_suggestions_enabled: false,
             },
-            tools: ToolConfig {
-                mode: default_tool_mode(),
-                allowed_commands: Vec::new(),
-                allowed_dirs: Vec::new(),
-            },
+            tools: ToolConfig::default(),
             global_prompt: GlobalPromptConfig::default(),
+            context_packs: ContextPackConfig::default(),
+            tickets: TicketConfig::default(),
+            watch_folders: WatchFolderConfig::default(),
             ui: UiConfig::default(),
+            hotkeys: HotkeyConfig::default(),
+            api_server: ApiServerConfig::default(),
+            faults: FaultConfig::default(),
+            metrics: MetricsConfig::default(),
+            workspaces: WorkspaceConfig::default(),
+            browser: BrowserConfig::default(),
+            agent_presets: Vec::new(),
+            clipboard: ClipboardConfig::default(),
+            redaction: RedactionConfig::default(),
+            audit: AuditConfig::default(),
+            profile_schedule: ProfileScheduleConfig::default(),
+            embedding: EmbeddingConfig::default(),
+            calendar: CalendarConfig::default(),
+            speech: SpeechConfig::default(),
         }
     }
 }
@@ -275,6 +1562,28 @@ pub struct SummaryRecord {
     pub urgency: String,          // 紧急程度: high/medium/low
     #[serde(default)]
     pub related_skill: String,    // 预留：相关 Skill 名称
+    // 截屏时的前台窗口信息
+    #[serde(default)]
+    pub window_title: String,
+    #[serde(default)]
+    pub process_name: String,
+    // 提醒复查结果: resolved | unresolved，未复查为空
+    #[serde(default)]
+    pub resolution: String,
+    #[serde(default)]
+    pub resolution_note: String,
+    // 提示词实验变体（如 concise:B），未参与实验为空
+    #[serde(default)]
+    pub prompt_variant: String,
+    // 用户对分析结果的评价: good | bad，未评价为空
+    #[serde(default)]
+    pub feedback: String,
+    // 关联的外部工单（GitHub / Jira）链接
+    #[serde(default)]
+    pub ticket_url: String,
+    // 分块变化检测识别出的变化区域（截图像素坐标），未开启时为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed_regions: Vec<CaptureRegion>,
 }
 
 /// 聚合记录（5分钟级别）
@@ -310,17 +1619,9 @@ pub struct StorageManager {
 
 impl StorageManager {
     pub fn new() -> Self {
-        let base_dir = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
-        let mut data_dir = base_dir.join("opencowork").join("data");
-        let legacy_dir = base_dir.join("screen-assistant").join("data");
-        if !data_dir.exists() && legacy_dir.exists() {
-            if let Err(err) = migrate_legacy_data_dir(&legacy_dir, &data_dir) {
-                eprintln!("Failed to migrate legacy data dir: {}", err);
-                data_dir = legacy_dir;
-            }
+        Self {
+            data_dir: resolve_data_dir(),
         }
-
-        Self { data_dir }
     }
 
     /// 获取数据目录路径
@@ -466,7 +1767,8 @@ impl StorageManager {
             return Ok(Vec::new());
         }
 
-        let content = fs::read_to_string(&summary_path)
+        let content = self
+            .read_data_string(&summary_path)
             .map_err(|e| format!("读取摘要失败: {}", e))?;
 
         let daily: DailySummary = serde_json::from_str(&content)
@@ -515,7 +1817,8 @@ impl StorageManager {
         let summary_path = self.data_dir.join("summaries").join(format!("{}.json", date));
 
         let mut daily = if summary_path.exists() {
-            let content = fs::read_to_string(&summary_path)
+            let content = self
+                .read_data_string(&summary_path)
                 .map_err(|e| format!("读取摘要失败: {}", e))?;
             serde_json::from_str(&content).unwrap_or(DailySummary {
                 date: date.to_string(),
@@ -532,7 +1835,15 @@ impl StorageManager {
             }
         };
 
-        daily.records.push(record.clone());
+        // 保存前按脱敏规则替换敏感内容
+        let mut record = record.clone();
+        redact_new_record(&mut record);
+
+        // 补分析的记录按时间插入，保持有序
+        let position = daily
+            .records
+            .partition_point(|item| item.timestamp <= record.timestamp);
+        daily.records.insert(position, record);
 
         // 检查是否需要聚合（每300条触发一次，约5分钟）
         if daily.records.len() % 300 == 0 {
@@ -542,10 +1853,55 @@ impl StorageManager {
         let content = serde_json::to_string_pretty(&daily)
             .map_err(|e| format!("序列化摘要失败: {}", e))?;
 
-        fs::write(&summary_path, content)
+        self.write_data_file(&summary_path, content.as_bytes())
             .map_err(|e| format!("保存摘要失败: {}", e))
     }
 
+    /// 修改某条记录并写回当天的摘要文件，返回修改后的记录
+    pub fn update_record<F>(&self, timestamp: &str, update: F) -> Result<SummaryRecord, String>
+    where
+        F: FnOnce(&mut SummaryRecord),
+    {
+        let date = timestamp
+            .get(..10)
+            .ok_or_else(|| format!("无效的时间戳: {}", timestamp))?;
+        let mut daily = self.load_daily(date)?;
+        let record = daily
+            .records
+            .iter_mut()
+            .find(|record| record.timestamp == timestamp)
+            .ok_or_else(|| format!("未找到记录: {}", timestamp))?;
+        update(record);
+        let updated = record.clone();
+
+        let summary_path = self.data_dir.join("summaries").join(format!("{}.json", date));
+        let content = serde_json::to_string_pretty(&daily)
+            .map_err(|e| format!("序列化摘要失败: {}", e))?;
+        self.write_data_file(&summary_path, content.as_bytes())
+            .map_err(|e| format!("保存摘要失败: {}", e))?;
+        Ok(updated)
+    }
+
+    /// 记录提醒的复查结果
+    pub fn set_record_resolution(
+        &self,
+        timestamp: &str,
+        resolution: &str,
+        note: &str,
+    ) -> Result<(), String> {
+        self.update_record(timestamp, |record| {
+            record.resolution = resolution.to_string();
+            record.resolution_note = note.to_string();
+        })
+        .map(|_| ())
+    }
+
+    /// 记录用户对某条分析结果的评价，用于提示词实验对比
+    pub fn set_record_feedback(&self, timestamp: &str, feedback: &str) -> Result<(), String> {
+        self.update_record(timestamp, |record| record.feedback = feedback.to_string())
+            .map(|_| ())
+    }
+
     pub fn delete_summaries_for_date(&self, date: &str) -> Result<usize, String> {
         self.ensure_dirs()?;
         let summary_path = self.data_dir.join("summaries").join(format!("{}.json", date));
@@ -553,7 +1909,8 @@ impl StorageManager {
             return Ok(0);
         }
 
-        let content = fs::read_to_string(&summary_path)
+        let content = self
+            .read_data_string(&summary_path)
             .map_err(|e| format!("读取摘要失败: {}", e))?;
         let daily: DailySummary = serde_json::from_str(&content)
             .map_err(|e| format!("解析摘要失败: {}", e))?;
@@ -593,7 +1950,7 @@ impl StorageManager {
                 continue;
             }
 
-            let content = match fs::read_to_string(&path) {
+            let content = match self.read_data_string(&path) {
                 Ok(value) => value,
                 Err(_) => {
                     let _ = fs::remove_file(&path);
@@ -704,8 +2061,16 @@ impl StorageManager {
 
     // ============ 智能检索 ============
 
-    /// 根据时间范围和关键词智能检索记录
+    /// 根据时间范围和关键词智能检索记录；带有问题向量时并入语义相近的记录
     pub fn smart_search(&self, query: &SearchQuery) -> Result<SearchResult, String> {
+        let mut result = self.search_time_range(query)?;
+        if let Some(semantic) = &query.semantic {
+            self.blend_semantic_matches(query, semantic, &mut result);
+        }
+        Ok(result)
+    }
+
+    fn search_time_range(&self, query: &SearchQuery) -> Result<SearchResult, String> {
         let today = Local::now().format("%Y-%m-%d").to_string();
 
         match query.time_range {
@@ -742,25 +2107,32 @@ impl StorageManager {
                     })
                 } else {
                     // 无关键词：返回聚合记录 + 最近的原始记录
-                    let recent: Vec<_> = daily.records.into_iter().rev().take(20).collect();
+                    let aggregated = self.select_aggregates(&daily, query.aggregation);
+                    let mut recent: Vec<_> = daily.records.into_iter().rev().take(20).collect();
+                    recent.reverse();
                     Ok(SearchResult {
                         records: recent,
-                        aggregated: daily.aggregated,
+                        aggregated,
                         source: "聚合记录".to_string(),
                     })
                 }
             }
             TimeRange::Days(days) => {
-                // 多天：只使用聚合记录
-                let mut all_aggregated = Vec::new();
-
-                for i in 0..days {
-                    let date = (Local::now() - Duration::days(i as i64))
-                        .format("%Y-%m-%d").to_string();
-                    if let Ok(daily) = self.load_daily(&date) {
-                        all_aggregated.extend(daily.aggregated);
-                    }
-                }
+                // 多天：只使用聚合记录；各天的读取、解析和聚合并发进行
+                let dates: Vec<String> = (0..days)
+                    .map(|i| (Local::now() - Duration::days(i as i64)).format("%Y-%m-%d").to_string())
+                    .collect();
+                let mut all_aggregated: Vec<AggregatedRecord> = self
+                    .load_days_parallel(&dates, |storage, date| {
+                        storage
+                            .load_daily(date)
+                            .map(|daily| storage.select_aggregates(&daily, query.aggregation))
+                            .unwrap_or_default()
+                    })
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                all_aggregated.sort_by(|a, b| a.start_time.cmp(&b.start_time));
 
                 Ok(SearchResult {
                     records: Vec::new(),
@@ -768,7 +2140,122 @@ impl StorageManager {
                     source: "历史聚合".to_string(),
                 })
             }
+            TimeRange::Between(start, end) => {
+                let mut dates = Vec::new();
+                let mut day = start.date();
+                while day <= end.date() {
+                    dates.push(day.format("%Y-%m-%d").to_string());
+                    day += Duration::days(1);
+                }
+                let start_str = start.format("%Y-%m-%dT%H:%M:%S").to_string();
+                let end_str = end.format("%Y-%m-%dT%H:%M:%S").to_string();
+
+                // 一天以内或有关键词时返回区间内的原始记录，跨多天时返回聚合记录
+                if end - start <= Duration::days(1) || !query.keywords.is_empty() {
+                    let records: Vec<SummaryRecord> = self
+                        .load_days_parallel(&dates, |storage, date| storage.get_summaries(date).unwrap_or_default())
+                        .into_iter()
+                        .flatten()
+                        .filter(|r| r.timestamp >= start_str && r.timestamp < end_str)
+                        .filter(|r| query.matches_keywords(r))
+                        .collect();
+                    return Ok(SearchResult {
+                        records,
+                        aggregated: Vec::new(),
+                        source: "时间区间".to_string(),
+                    });
+                }
+                let mut aggregated: Vec<AggregatedRecord> = self
+                    .load_days_parallel(&dates, |storage, date| {
+                        storage
+                            .load_daily(date)
+                            .map(|daily| storage.select_aggregates(&daily, query.aggregation))
+                            .unwrap_or_default()
+                    })
+                    .into_iter()
+                    .flatten()
+                    .filter(|a| a.end_time >= start_str && a.start_time < end_str)
+                    .collect();
+                aggregated.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+                Ok(SearchResult {
+                    records: Vec::new(),
+                    aggregated,
+                    source: "历史聚合".to_string(),
+                })
+            }
+        }
+    }
+
+    fn select_aggregates(
+        &self,
+        daily: &DailySummary,
+        granularity: AggregationGranularity,
+    ) -> Vec<AggregatedRecord> {
+        if granularity == AggregationGranularity::Stored || daily.records.is_empty() {
+            return daily.aggregated.clone();
         }
+        self.aggregate_by(&daily.records, granularity)
+    }
+
+    /// 按粒度把按时间排序的原始记录分段聚合
+    pub fn aggregate_by(
+        &self,
+        records: &[SummaryRecord],
+        granularity: AggregationGranularity,
+    ) -> Vec<AggregatedRecord> {
+        let mut groups: Vec<(String, Vec<SummaryRecord>)> = Vec::new();
+        for record in records {
+            let key = match granularity {
+                AggregationGranularity::Stored => String::new(),
+                AggregationGranularity::Block15m => {
+                    let minute: u32 = record
+                        .timestamp
+                        .get(14..16)
+                        .and_then(|m| m.parse().ok())
+                        .unwrap_or(0);
+                    format!(
+                        "{}{:02}",
+                        record.timestamp.get(..14).unwrap_or(&record.timestamp),
+                        minute / 15 * 15
+                    )
+                }
+                AggregationGranularity::AppSession => record.app.trim().to_string(),
+                AggregationGranularity::Scene => {
+                    let scene = record.scene.trim();
+                    if scene.is_empty() { "other".to_string() } else { scene.to_string() }
+                }
+            };
+            match groups.last_mut() {
+                Some((last_key, items)) if *last_key == key => items.push(record.clone()),
+                _ => groups.push((key, vec![record.clone()])),
+            }
+        }
+
+        // 应用会话：过短的切换并入上一段，避免来回切窗口产生碎片
+        if granularity == AggregationGranularity::AppSession {
+            let mut merged: Vec<(String, Vec<SummaryRecord>)> = Vec::new();
+            for (key, items) in groups {
+                match merged.last_mut() {
+                    Some((_, last_items)) if items.len() < 3 => last_items.extend(items),
+                    Some((last_key, last_items)) if *last_key == key => last_items.extend(items),
+                    _ => merged.push((key, items)),
+                }
+            }
+            groups = merged;
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, mut items)| {
+                // aggregate_records 期望最新的记录在前
+                items.reverse();
+                let mut aggregated = self.aggregate_records(&items);
+                if granularity == AggregationGranularity::Scene {
+                    aggregated.summary = format!("[{}] {}", key, aggregated.summary);
+                }
+                aggregated
+            })
+            .collect()
     }
 
     fn load_daily(&self, date: &str) -> Result<DailySummary, String> {
@@ -783,7 +2270,8 @@ impl StorageManager {
             });
         }
 
-        let content = fs::read_to_string(&path)
+        let content = self
+            .read_data_string(&path)
             .map_err(|e| format!("读取失败: {}", e))?;
 
         serde_json::from_str(&content)
@@ -886,6 +2374,28 @@ pub enum TimeRange {
     Recent(u32),  // 最近N分钟
     Today,        // 今天
     Days(u32),    // 最近N天
+    Between(NaiveDateTime, NaiveDateTime),  // 本地时间区间 [开始, 结束)
+}
+
+/// 聚合粒度：stored 使用保存时按条数生成的聚合，其余在检索时按原始记录重新分组
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AggregationGranularity {
+    #[default]
+    Stored,
+    Block15m,   // 每 15 分钟一段
+    AppSession, // 连续使用同一应用为一段
+    Scene,      // 连续相同场景为一段
+}
+
+impl AggregationGranularity {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "block_15m" | "15m" | "block" => AggregationGranularity::Block15m,
+            "app_session" | "app" => AggregationGranularity::AppSession,
+            "scene" => AggregationGranularity::Scene,
+            _ => AggregationGranularity::Stored,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -893,6 +2403,8 @@ pub struct SearchQuery {
     pub time_range: TimeRange,
     pub keywords: Vec<String>,
     pub include_detail: bool,
+    pub aggregation: AggregationGranularity,
+    pub semantic: Option<SemanticQuery>,  // 开启语义检索时的问题向量
 }
 
 impl SearchQuery {
@@ -901,9 +2413,10 @@ impl SearchQuery {
             return true;
         }
 
-        let text = format!("{} {} {}",
+        let text = format!("{} {} {} {}",
             record.summary,
             record.app,
+            format!("{} {}", record.window_title, record.process_name),
             format!("{} {}", record.detail, record.keywords.join(" "))
         ).to_lowercase();
 
@@ -927,101 +2440,3 @@ impl Default for SearchResult {
         }
     }
 }
-
-impl SearchResult {
-    /// 构建上下文字符串，控制在指定token数内
-    pub fn build_context(
-        &self,
-        max_chars: usize,
-        include_detail: bool,
-        detail_cutoff: Option<&str>,
-    ) -> String {
-        let mut context = String::new();
-        let mut current_len = 0;
-
-        // 先添加聚合记录（概要）
-        if !self.aggregated.is_empty() {
-            context.push_str("## 操作概要\n\n");
-            for agg in &self.aggregated {
-                let line = format!(
-                    "- [{} {} ~ {}] {}\n",
-                    &agg.start_time[..10],
-                    &agg.start_time[11..16],
-                    &agg.end_time[11..16],
-                    agg.summary
-                );
-                if current_len + line.len() > max_chars {
-                    break;
-                }
-                context.push_str(&line);
-                current_len += line.len();
-
-                // 如果有错误，添加错误信息
-                if let Some(ref err) = agg.error_summary {
-                    let err_line = format!("  ⚠️ 错误: {}\n", err);
-                    if current_len + err_line.len() <= max_chars {
-                        context.push_str(&err_line);
-                        current_len += err_line.len();
-                    }
-                }
-            }
-            context.push('\n');
-        }
-
-        // 再添加详细记录
-        if !self.records.is_empty() {
-            context.push_str("## 详细记录\n\n");
-            let mut entries: Vec<String> = Vec::new();
-            let mut truncated = false;
-
-            for record in self.records.iter().rev() {
-                let line = format!(
-                    "- [{} {}] {}\n",
-                    &record.timestamp[..10],
-                    &record.timestamp[11..19],
-                    record.summary
-                );
-                if current_len + line.len() > max_chars {
-                    truncated = true;
-                    break;
-                }
-
-                let mut entry = String::new();
-                entry.push_str(&line);
-                current_len += line.len();
-
-                let allow_detail = include_detail
-                    && detail_cutoff.map_or(true, |cutoff| record.timestamp.as_str() >= cutoff);
-                if allow_detail && !record.detail.is_empty() {
-                    let detail_text = record.detail.replace('\n', " ");
-                    let detail_line = format!("  细节: {}\n", detail_text);
-                    if current_len + detail_line.len() > max_chars {
-                        entry.push_str("  ...(细节已省略)\n");
-                        current_len += "  ...(细节已省略)\n".len();
-                        truncated = true;
-                        entries.push(entry);
-                        break;
-                    }
-                    entry.push_str(&detail_line);
-                    current_len += detail_line.len();
-                }
-
-                entries.push(entry);
-            }
-
-            entries.reverse();
-            for entry in entries {
-                context.push_str(&entry);
-            }
-            if truncated {
-                context.push_str("...(更多记录已省略)\n");
-            }
-        }
-
-        if context.is_empty() {
-            context = "目前没有相关的操作记录。".to_string();
-        }
-
-        context
-    }
-}
@@ -0,0 +1,169 @@
+use super::StorageManager;
+use chrono::{Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const OCR_INDEX_DIR: &str = "ocr_index";
+const DEFAULT_SCREENSHOT_SEARCH_DAYS: i64 = 7;
+const DEFAULT_SCREENSHOT_SEARCH_LIMIT: usize = 20;
+
+/// OCR 识别出的一行文字及其在截图中的位置（像素）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 一张截图的文字层
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrTextLayer {
+    pub timestamp: String,
+    pub screenshot: String,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    pub lines: Vec<OcrLine>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DailyOcrIndex {
+    date: String,
+    layers: Vec<OcrTextLayer>,
+}
+
+/// 截图文字检索结果，matches 为命中的文字行
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenshotTextMatch {
+    pub timestamp: String,
+    pub screenshot_ref: String,
+    pub width: u32,
+    pub height: u32,
+    pub matches: Vec<OcrLine>,
+}
+
+impl StorageManager {
+    fn ocr_index_path(&self, date: &str) -> PathBuf {
+        self.get_data_dir().join(OCR_INDEX_DIR).join(format!("{}.json", date))
+    }
+
+    fn load_ocr_index_day(&self, date: &str) -> DailyOcrIndex {
+        let path = self.ocr_index_path(date);
+        if !path.exists() {
+            return DailyOcrIndex {
+                date: date.to_string(),
+                layers: Vec::new(),
+            };
+        }
+        self.read_data_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| DailyOcrIndex {
+                date: date.to_string(),
+                layers: Vec::new(),
+            })
+    }
+
+    /// 保存截图的文字层，同一张截图重复识别时覆盖旧结果
+    pub fn save_ocr_layer(&self, layer: OcrTextLayer) -> Result<(), String> {
+        let Some(date) = layer.timestamp.get(..10).map(str::to_string) else {
+            return Err(format!("无效的截图时间: {}", layer.timestamp));
+        };
+        let dir = self.get_data_dir().join(OCR_INDEX_DIR);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建文字索引目录失败: {}", e))?;
+        let mut daily = self.load_ocr_index_day(&date);
+        daily.layers.retain(|existing| existing.screenshot != layer.screenshot);
+        daily.layers.push(layer);
+        let content = serde_json::to_string(&daily).map_err(|e| format!("序列化文字索引失败: {}", e))?;
+        self.write_data_file(&self.ocr_index_path(&date), content.as_bytes())
+    }
+
+    /// 在截图文字层中查找文本（忽略大小写和空白），最新的在前；截图已被清理的结果不返回
+    pub fn search_screenshot_text(
+        &self,
+        text: &str,
+        date: Option<&str>,
+        days: Option<i64>,
+        limit: Option<usize>,
+    ) -> Result<Vec<ScreenshotTextMatch>, String> {
+        let needle = normalize_for_match(text);
+        if needle.is_empty() {
+            return Err("请输入要查找的文字".to_string());
+        }
+        let dates: Vec<String> = match date.map(str::trim).filter(|d| !d.is_empty()) {
+            Some(date) => {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("日期格式应为 YYYY-MM-DD: {}", date))?;
+                vec![date.to_string()]
+            }
+            None => {
+                let today = Local::now().date_naive();
+                (0..days.unwrap_or(DEFAULT_SCREENSHOT_SEARCH_DAYS).max(1))
+                    .map(|offset| (today - Duration::days(offset)).format("%Y-%m-%d").to_string())
+                    .collect()
+            }
+        };
+        let screenshots_dir = self.screenshots_dir()?;
+        let limit = limit.unwrap_or(DEFAULT_SCREENSHOT_SEARCH_LIMIT).max(1);
+        let mut results = Vec::new();
+        for date in dates {
+            let mut layers = self.load_ocr_index_day(&date).layers;
+            layers.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            for layer in layers {
+                let matches: Vec<OcrLine> = layer
+                    .lines
+                    .into_iter()
+                    .filter(|line| normalize_for_match(&line.text).contains(&needle))
+                    .collect();
+                if matches.is_empty() || !screenshots_dir.join(&layer.screenshot).is_file() {
+                    continue;
+                }
+                results.push(ScreenshotTextMatch {
+                    timestamp: layer.timestamp,
+                    screenshot_ref: layer.screenshot,
+                    width: layer.width,
+                    height: layer.height,
+                    matches,
+                });
+                if results.len() >= limit {
+                    return Ok(results);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// 清理超过保留天数的文字索引
+    pub fn prune_ocr_index(&self, retention_days: u32) -> usize {
+        if retention_days == 0 {
+            return 0;
+        }
+        let cutoff = (Local::now() - Duration::days(retention_days as i64))
+            .format("%Y-%m-%d")
+            .to_string();
+        let Ok(entries) = fs::read_dir(self.get_data_dir().join(OCR_INDEX_DIR)) else {
+            return 0;
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map_or(false, |date| date < cutoff.as_str())
+            })
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+}
+
+/// OCR 常把 "0x80070005" 识别成 "0x8007 0005"，比较前去掉空白并转小写
+fn normalize_for_match(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,108 @@
+use super::StorageManager;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const PENDING_FILE: &str = "pending_analysis.json";
+const MAX_PENDING_CAPTURES: usize = 500;
+
+/// 已保存截图、尚未分析的帧（超出预算或网络故障），之后补分析并回填摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingCapture {
+    pub timestamp: String,
+    pub screenshot_ref: String,
+    #[serde(default)]
+    pub image_hash: u64,
+    #[serde(default)]
+    pub window_title: String,
+    #[serde(default)]
+    pub process_name: String,
+    #[serde(default)]
+    pub reason: String,  // budget / network
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+impl StorageManager {
+    fn pending_captures_path(&self) -> PathBuf {
+        self.data_dir.join(PENDING_FILE)
+    }
+
+    pub fn load_pending_captures(&self) -> Result<Vec<PendingCapture>, String> {
+        let path = self.pending_captures_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_pending_captures(&self, items: &[PendingCapture]) -> Result<(), String> {
+        let path = self.pending_captures_path();
+        if items.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("删除待分析队列失败: {}", e))?;
+            }
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(items)
+            .map_err(|e| format!("序列化待分析队列失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    /// 加入队列；超出上限时丢弃最早的帧，返回队列长度
+    pub fn enqueue_pending_capture(&self, item: PendingCapture) -> Result<usize, String> {
+        let mut items = self.load_pending_captures()?;
+        items.retain(|existing| existing.timestamp != item.timestamp);
+        items.push(item);
+        if items.len() > MAX_PENDING_CAPTURES {
+            let overflow = items.len() - MAX_PENDING_CAPTURES;
+            items.drain(..overflow);
+        }
+        self.save_pending_captures(&items)?;
+        Ok(items.len())
+    }
+
+    /// 最早入队的一帧
+    pub fn next_pending_capture(&self) -> Result<Option<PendingCapture>, String> {
+        Ok(self.load_pending_captures()?.into_iter().next())
+    }
+
+    pub fn remove_pending_capture(&self, timestamp: &str) -> Result<(), String> {
+        let mut items = self.load_pending_captures()?;
+        let before = items.len();
+        items.retain(|item| item.timestamp != timestamp);
+        if items.len() != before {
+            self.save_pending_captures(&items)?;
+        }
+        Ok(())
+    }
+
+    /// 记录一次补分析失败；不再重试或达到上限时移出队列，返回是否仍在队列中
+    pub fn record_pending_attempt(
+        &self,
+        timestamp: &str,
+        retry: bool,
+        max_attempts: u32,
+    ) -> Result<bool, String> {
+        let mut items = self.load_pending_captures()?;
+        let Some(index) = items.iter().position(|item| item.timestamp == timestamp) else {
+            return Ok(false);
+        };
+        items[index].attempts += 1;
+        let keep = retry && items[index].attempts < max_attempts;
+        if keep {
+            // 移到队尾，先处理其他帧
+            let item = items.remove(index);
+            items.push(item);
+        } else {
+            items.remove(index);
+        }
+        self.save_pending_captures(&items)?;
+        Ok(keep)
+    }
+
+    pub fn pending_capture_count(&self) -> usize {
+        self.load_pending_captures().map(|items| items.len()).unwrap_or(0)
+    }
+}
@@ -0,0 +1,271 @@
+use super::{DailySummary, RedactionConfig, RedactionRule, StorageManager, SummaryRecord};
+use parking_lot::Mutex as ParkingMutex;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::fs;
+use std::sync::{Arc, OnceLock};
+
+const DEFAULT_REPLACEMENT: &str = "[已脱敏]";
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+// 常见手机号/电话：+86 138 0000 0000、(010) 1234-5678 等
+const PHONE_PATTERN: &str = r"(?:\+?\d{1,3}[\s-]?)?(?:\(\d{2,4}\)[\s-]?)?\d{3,4}[\s-]?\d{4}(?:[\s-]?\d{3,4})?";
+
+struct CompiledRule {
+    regex: Regex,
+    replacement: String,
+}
+
+/// 编译后的脱敏规则
+pub struct Redactor {
+    rules: Vec<CompiledRule>,
+}
+
+/// 补执行脱敏的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RedactionReport {
+    pub days: usize,  // 有改动的天数
+    pub records: usize,  // 有改动的记录数
+    pub replacements: usize,
+}
+
+fn compile_rule(rule: &RedactionRule) -> Result<Regex, String> {
+    let pattern = match rule.kind.as_str() {
+        "email" => EMAIL_PATTERN.to_string(),
+        "phone" => PHONE_PATTERN.to_string(),
+        "regex" => rule.pattern.clone(),
+        "keyword" => regex::escape(rule.pattern.trim()),
+        other => return Err(format!("未知的脱敏规则类型: {}", other)),
+    };
+    if pattern.trim().is_empty() {
+        return Err(format!("脱敏规则 {} 缺少匹配内容", rule.name));
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(rule.kind == "keyword")
+        .build()
+        .map_err(|e| format!("脱敏规则 {} 无效: {}", rule.name, e))
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in config.rules.iter().filter(|rule| rule.enabled) {
+            let replacement = if rule.replacement.is_empty() {
+                DEFAULT_REPLACEMENT.to_string()
+            } else {
+                rule.replacement.clone()
+            };
+            rules.push(CompiledRule {
+                regex: compile_rule(rule)?,
+                replacement,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 替换文本中的敏感内容，返回替换次数
+    pub fn redact_text(&self, text: &mut String) -> usize {
+        let mut count = 0;
+        for rule in &self.rules {
+            let matches = rule.regex.find_iter(text).count();
+            if matches > 0 {
+                // 替换内容按字面处理，不展开 $1 等引用
+                *text = rule
+                    .regex
+                    .replace_all(text, regex::NoExpand(&rule.replacement))
+                    .into_owned();
+                count += matches;
+            }
+        }
+        count
+    }
+
+    pub fn redact_record(&self, record: &mut SummaryRecord) -> usize {
+        let mut count = 0;
+        for field in [
+            &mut record.summary,
+            &mut record.action,
+            &mut record.detail,
+            &mut record.issue_summary,
+            &mut record.suggestion,
+            &mut record.intent,
+            &mut record.window_title,
+            &mut record.resolution_note,
+        ] {
+            count += self.redact_text(field);
+        }
+        for keyword in record.keywords.iter_mut() {
+            count += self.redact_text(keyword);
+        }
+        count
+    }
+
+    fn redact_daily(&self, daily: &mut DailySummary) -> (usize, usize) {
+        let mut records = 0;
+        let mut replacements = 0;
+        for record in daily.records.iter_mut() {
+            let count = self.redact_record(record);
+            if count > 0 {
+                records += 1;
+                replacements += count;
+            }
+        }
+        for aggregated in daily.aggregated.iter_mut() {
+            replacements += self.redact_text(&mut aggregated.summary);
+            for item in aggregated.main_activities.iter_mut().chain(aggregated.keywords.iter_mut()) {
+                replacements += self.redact_text(item);
+            }
+            if let Some(error_summary) = aggregated.error_summary.as_mut() {
+                replacements += self.redact_text(error_summary);
+            }
+        }
+        if let Some(day_summary) = daily.day_summary.as_mut() {
+            replacements += self.redact_text(day_summary);
+        }
+        (records, replacements)
+    }
+}
+
+// 当前生效的规则，启动和保存配置时更新；保存新记录时使用
+fn active_redactor() -> &'static ParkingMutex<Option<Arc<Redactor>>> {
+    static REDACTOR: OnceLock<ParkingMutex<Option<Arc<Redactor>>>> = OnceLock::new();
+    REDACTOR.get_or_init(|| ParkingMutex::new(None))
+}
+
+/// 规则无效时返回错误，并停用自动脱敏，避免带着半套规则继续写入
+pub fn apply_redaction_config(config: &RedactionConfig) -> Result<(), String> {
+    let mut slot = active_redactor().lock();
+    *slot = None;
+    if !config.enabled {
+        return Ok(());
+    }
+    let redactor = Redactor::new(config)?;
+    if !redactor.is_empty() {
+        *slot = Some(Arc::new(redactor));
+    }
+    Ok(())
+}
+
+pub(super) fn redact_new_record(record: &mut SummaryRecord) {
+    let redactor = active_redactor().lock().clone();
+    if let Some(redactor) = redactor {
+        redactor.redact_record(record);
+    }
+}
+
+impl StorageManager {
+    /// 按当前规则改写已保存的所有摘要和详情
+    pub fn redact_existing_records(&self, config: &RedactionConfig) -> Result<RedactionReport, String> {
+        let redactor = Redactor::new(config)?;
+        let mut report = RedactionReport::default();
+        if redactor.is_empty() {
+            return Ok(report);
+        }
+        let dir = self.data_dir.join("summaries");
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Ok(report);
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(mut daily) = self
+                .read_data_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<DailySummary>(&content).ok())
+            else {
+                continue;
+            };
+            let (records, replacements) = redactor.redact_daily(&mut daily);
+            if replacements == 0 {
+                continue;
+            }
+            let content = serde_json::to_string_pretty(&daily)
+                .map_err(|e| format!("序列化摘要失败: {}", e))?;
+            self.write_data_file(&path, content.as_bytes())
+                .map_err(|e| format!("保存摘要失败: {}", e))?;
+            report.days += 1;
+            report.records += records;
+            report.replacements += replacements;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: &str, pattern: &str) -> RedactionRule {
+        RedactionRule {
+            name: kind.to_string(),
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            replacement: String::new(),
+            enabled: true,
+        }
+    }
+
+    fn redactor(rules: Vec<RedactionRule>) -> Redactor {
+        Redactor::new(&RedactionConfig { enabled: true, rules }).unwrap()
+    }
+
+    fn redact(redactor: &Redactor, text: &str) -> (String, usize) {
+        let mut text = text.to_string();
+        let count = redactor.redact_text(&mut text);
+        (text, count)
+    }
+
+    #[test]
+    fn email_rule_replaces_addresses() {
+        let redactor = redactor(vec![rule("email", "")]);
+        assert_eq!(
+            redact(&redactor, "发给 alice.b@example.com 和 bob@mail.co"),
+            ("发给 [已脱敏] 和 [已脱敏]".to_string(), 2)
+        );
+        assert_eq!(redact(&redactor, "没有地址 @ 这里"), ("没有地址 @ 这里".to_string(), 0));
+    }
+
+    #[test]
+    fn phone_rule_replaces_common_formats() {
+        let redactor = redactor(vec![rule("phone", "")]);
+        assert_eq!(redact(&redactor, "电话 +86 138 0000 0000 联系"), ("电话 [已脱敏] 联系".to_string(), 1));
+        assert_eq!(redact(&redactor, "座机 (010) 1234-5678"), ("座机 [已脱敏]".to_string(), 1));
+        assert_eq!(redact(&redactor, "版本 1.2"), ("版本 1.2".to_string(), 0));
+    }
+
+    #[test]
+    fn keyword_rule_is_literal_and_case_insensitive() {
+        let redactor = redactor(vec![rule("keyword", " Project.X ")]);
+        assert_eq!(
+            redact(&redactor, "project.x 与 PROJECT.X，不含 projectYX"),
+            ("[已脱敏] 与 [已脱敏]，不含 projectYX".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn regex_replacement_is_not_expanded() {
+        let mut custom = rule("regex", r"token-(\d+)");
+        custom.replacement = "$1-hidden".to_string();
+        let redactor = redactor(vec![custom]);
+        assert_eq!(redact(&redactor, "token-123"), ("$1-hidden".to_string(), 1));
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped() {
+        let mut disabled = rule("regex", "(");
+        disabled.enabled = false;
+        assert!(redactor(vec![disabled]).is_empty());
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        for invalid in [rule("regex", "("), rule("keyword", "  "), rule("unknown", "x")] {
+            let config = RedactionConfig { enabled: true, rules: vec![invalid] };
+            assert!(Redactor::new(&config).is_err());
+        }
+    }
+}
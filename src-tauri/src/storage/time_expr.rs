@@ -0,0 +1,563 @@
+use super::{CalendarConfig, TimeRange};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use regex::Regex;
+use std::sync::OnceLock;
+
+// 只写了时刻、没写上午/下午时，小于该值的钟点按下午理解（"3点到5点" 多指下午）
+const AMBIGUOUS_PM_BEFORE: u32 = 7;
+// 单个时刻（"around 3pm"、"3点左右"）前后各取的分钟数
+const POINT_WINDOW_MINUTES: i64 = 30;
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("invalid time expression regex"))
+}
+
+/// 与地区、作息相关的解析参数
+#[derive(Debug, Clone)]
+pub struct TimeParseOptions {
+    pub week_start: Weekday,
+    pub day_start_hour: u32,  // 一天从几点算起，熬夜用户可设为 4，凌晨的记录算作前一天
+    pub work_start: Duration,
+    pub work_end: Duration,
+}
+
+impl Default for TimeParseOptions {
+    fn default() -> Self {
+        Self {
+            week_start: Weekday::Mon,
+            day_start_hour: 0,
+            work_start: Duration::hours(9),
+            work_end: Duration::hours(18),
+        }
+    }
+}
+
+impl TimeParseOptions {
+    fn day_offset(&self) -> Duration {
+        Duration::hours(self.day_start_hour.min(23) as i64)
+    }
+
+    /// 逻辑上的「今天」：未到 day_start_hour 时仍算前一天
+    fn today(&self, now: NaiveDateTime) -> NaiveDate {
+        (now - self.day_offset()).date()
+    }
+
+    fn day_begin(&self, date: NaiveDate) -> NaiveDateTime {
+        date.and_time(NaiveTime::MIN) + self.day_offset()
+    }
+
+    fn week_begin(&self, today: NaiveDate) -> NaiveDate {
+        let back = (today.weekday().num_days_from_monday() + 7 - self.week_start.num_days_from_monday()) % 7;
+        today - Duration::days(back as i64)
+    }
+}
+
+impl CalendarConfig {
+    /// 转为解析参数，设置有误时返回错误
+    pub fn time_options(&self) -> Result<TimeParseOptions, String> {
+        let week_start = match self.week_start.trim().to_lowercase().as_str() {
+            "" | "auto" => locale_week_start(),
+            "monday" | "mon" => Weekday::Mon,
+            "sunday" | "sun" => Weekday::Sun,
+            "saturday" | "sat" => Weekday::Sat,
+            other => return Err(format!("不支持的一周起始日: {}（可选 auto、monday、sunday、saturday）", other)),
+        };
+        if self.day_start_hour > 23 {
+            return Err("一天起始时刻应为 0-23".to_string());
+        }
+        let clock = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map(|time| time - NaiveTime::MIN)
+                .map_err(|_| format!("工作时间格式应为 HH:MM: {}", value))
+        };
+        let (work_start, work_end) = (clock(&self.work_start)?, clock(&self.work_end)?);
+        if work_end <= work_start {
+            return Err("下班时间应晚于上班时间".to_string());
+        }
+        Ok(TimeParseOptions {
+            week_start,
+            day_start_hour: self.day_start_hour,
+            work_start,
+            work_end,
+        })
+    }
+}
+
+/// 按系统区域设置推断一周从哪天开始，无法判断时为周一
+pub fn locale_week_start() -> Weekday {
+    let locale = sys_locale::get_locale().unwrap_or_default();
+    // "en-US"、"zh-Hans-CN"、"en_US.UTF-8" 中的地区代码
+    let region = locale
+        .split(['.', '@'])
+        .next()
+        .and_then(|tag| tag.split(['_', '-']).skip(1).find(|part| part.len() == 2))
+        .unwrap_or("")
+        .to_uppercase();
+    match region.as_str() {
+        "US" | "CA" | "MX" | "BR" | "JP" | "KR" | "TW" | "HK" | "MO" | "IL" | "PH" | "IN" | "ZA" => Weekday::Sun,
+        "EG" | "SA" | "AE" | "QA" | "KW" | "BH" | "OM" | "JO" | "IQ" | "SY" | "LY" | "DZ" | "AF" | "IR" => Weekday::Sat,
+        _ => Weekday::Mon,
+    }
+}
+
+/// 解析中英文时间表达（"last 20 minutes"、"昨天下午"、"between 2pm and 4pm"、"周二"），
+/// 无法识别时返回 None；now 为本地时间
+pub fn parse_time_expression(text: &str, now: NaiveDateTime) -> Option<TimeRange> {
+    parse_time_expression_with(text, now, &TimeParseOptions::default())
+}
+
+/// 同 parse_time_expression，按配置的一周起始日、一天起始时刻和工作时间解析
+pub fn parse_time_expression_with(text: &str, now: NaiveDateTime, options: &TimeParseOptions) -> Option<TimeRange> {
+    let text = text.to_lowercase();
+    let today = options.today(now);
+    if let Some(range) = parse_relative(&text, now, today, options) {
+        return Some(range);
+    }
+
+    let day = parse_day(&text, today, options);
+    let clock = parse_clock_range(&text).or_else(|| parse_part_of_day(&text, options));
+    match (day, clock) {
+        (None, None) => None,
+        (Some(DayAnchor::Today), None) if options.day_start_hour == 0 => Some(TimeRange::Today),
+        (Some(DayAnchor::Today), None) => Some(TimeRange::Between(options.day_begin(today), now)),
+        (Some(DayAnchor::ThisWeek), _) => {
+            Some(TimeRange::Between(options.day_begin(options.week_begin(today)), now))
+        }
+        (Some(DayAnchor::LastWeek), _) => {
+            let this_week = options.week_begin(today);
+            Some(TimeRange::Between(
+                options.day_begin(this_week - Duration::days(7)),
+                options.day_begin(this_week),
+            ))
+        }
+        (Some(DayAnchor::Date(date)), None) => Some(TimeRange::Between(
+            options.day_begin(date),
+            options.day_begin(date + Duration::days(1)),
+        )),
+        (anchor, Some((start, end))) => {
+            let date = match anchor {
+                Some(DayAnchor::Date(date)) => date,
+                _ => today,
+            };
+            // 早于一天起始时刻的钟点属于下一个自然日（day_start_hour 为 4 时「昨晚 1 点」是今天凌晨）
+            let at = |offset: Duration| {
+                let offset = if offset < options.day_offset() { offset + Duration::days(1) } else { offset };
+                date.and_time(NaiveTime::MIN) + offset
+            };
+            let start = at(start);
+            let mut end = at(end);
+            // 跨午夜的区间（"昨晚 11 点到 1 点"）结束时间落在下一天
+            if end < start {
+                end += Duration::days(1);
+            }
+            Some(TimeRange::Between(start, end))
+        }
+    }
+}
+
+enum DayAnchor {
+    Today,
+    ThisWeek,
+    LastWeek,
+    Date(NaiveDate),
+}
+
+fn number_word(word: &str) -> Option<u32> {
+    let value = match word {
+        "a" | "an" | "one" | "一" => 1,
+        "two" | "couple" | "a couple of" | "二" | "两" => 2,
+        "three" | "few" | "a few" | "三" => 3,
+        "four" | "四" => 4,
+        "five" | "五" => 5,
+        "six" | "六" => 6,
+        "seven" | "七" => 7,
+        "eight" | "八" => 8,
+        "nine" | "九" => 9,
+        "ten" | "十" => 10,
+        "十一" => 11,
+        "十二" => 12,
+        "fifteen" | "十五" => 15,
+        "twenty" | "二十" => 20,
+        "thirty" | "三十" => 30,
+        "半" => return None,
+        other => return other.parse().ok(),
+    };
+    Some(value)
+}
+
+/// "last 20 minutes"、"past 2 hours"、"最近三天"、"过去半小时"、"just now"、"刚才"
+fn parse_relative(text: &str, now: NaiveDateTime, today: NaiveDate, options: &TimeParseOptions) -> Option<TimeRange> {
+    static EN: OnceLock<Regex> = OnceLock::new();
+    static ZH: OnceLock<Regex> = OnceLock::new();
+    let en = regex(
+        &EN,
+        r"\b(?:last|past|previous|in the last|in the past)\s+(\d+|an?|one|two|three|four|five|six|seven|eight|nine|ten|fifteen|twenty|thirty|a couple of|couple|a few|few)?\s*(minutes?|mins?|hours?|hrs?|days?|weeks?)\b",
+    );
+    let zh = regex(
+        &ZH,
+        r"(?:(?:最近|过去)\s*(\d+|十五|二十|三十|[一二两三四五六七八九十半])?|前\s*(\d+|十五|二十|三十|[一二两三四五六七八九十半]))\s*个?\s*(分钟|小时|钟头|天|日|周|星期|礼拜)",
+    );
+
+    let (amount, unit) = if let Some(caps) = en.captures(text) {
+        // 不带数量的 "last week" 指上一个自然周，交给 parse_day
+        if caps.get(1).is_none() && caps[2].starts_with('w') {
+            return None;
+        }
+        let amount = caps.get(1).map_or(Some(1), |m| number_word(m.as_str()));
+        (amount, caps[2].chars().next().unwrap_or('m'))
+    } else if let Some(caps) = zh.captures(text) {
+        // 「前」后必须带数字，避免把「前天」当成最近一天
+        let word = caps.get(1).or(caps.get(2)).map(|m| m.as_str());
+        let unit = match &caps[3] {
+            "分钟" => 'm',
+            "小时" | "钟头" => 'h',
+            "天" | "日" => 'd',
+            _ => 'w',
+        };
+        // "半小时" 按 30 分钟处理
+        if word == Some("半") && unit == 'h' {
+            return Some(TimeRange::Recent(30));
+        }
+        (word.map_or(Some(1), number_word), unit)
+    } else if text.contains("just now")
+        || text.contains("a moment ago")
+        || text.contains("刚才")
+        || text.contains("刚刚")
+    {
+        return Some(TimeRange::Recent(5));
+    } else {
+        return None;
+    };
+
+    let amount = amount?.max(1);
+    Some(match unit {
+        'm' => TimeRange::Recent(amount),
+        'h' => TimeRange::Recent(amount * 60),
+        'd' => TimeRange::Between(options.day_begin(today - Duration::days(amount as i64 - 1)), now),
+        _ => TimeRange::Between(now - Duration::days(amount as i64 * 7), now),
+    })
+}
+
+/// 星期几前的修饰：英文 last 指最近一个（不含今天），中文「上」指上一周
+#[derive(PartialEq)]
+enum WeekdayShift {
+    None,
+    Last,
+    PreviousWeek,
+}
+
+fn parse_weekday(text: &str) -> Option<(Weekday, WeekdayShift)> {
+    static EN: OnceLock<Regex> = OnceLock::new();
+    static ZH: OnceLock<Regex> = OnceLock::new();
+    let en = regex(
+        &EN,
+        r"\b(last\s+)?(monday|tuesday|tues?|wednesday|wed|thursday|thurs?|thu|friday|fri|saturday|sunday)\b",
+    );
+    let zh = regex(&ZH, r"(上)?(?:周|星期|礼拜)([一二三四五六日天])");
+    if let Some(caps) = en.captures(text) {
+        let day = match &caps[2][..3] {
+            "mon" => Weekday::Mon,
+            "tue" => Weekday::Tue,
+            "wed" => Weekday::Wed,
+            "thu" => Weekday::Thu,
+            "fri" => Weekday::Fri,
+            "sat" => Weekday::Sat,
+            _ => Weekday::Sun,
+        };
+        let shift = if caps.get(1).is_some() { WeekdayShift::Last } else { WeekdayShift::None };
+        return Some((day, shift));
+    }
+    let caps = zh.captures(text)?;
+    let day = match &caps[2] {
+        "一" => Weekday::Mon,
+        "二" => Weekday::Tue,
+        "三" => Weekday::Wed,
+        "四" => Weekday::Thu,
+        "五" => Weekday::Fri,
+        "六" => Weekday::Sat,
+        _ => Weekday::Sun,
+    };
+    let shift = if caps.get(1).is_some() { WeekdayShift::PreviousWeek } else { WeekdayShift::None };
+    Some((day, shift))
+}
+
+fn parse_day(text: &str, today: NaiveDate, options: &TimeParseOptions) -> Option<DayAnchor> {
+    if text.contains("day before yesterday") || text.contains("前天") {
+        return Some(DayAnchor::Date(today - Duration::days(2)));
+    }
+    if text.contains("yesterday") || text.contains("last night") || text.contains("昨天") || text.contains("昨晚") {
+        return Some(DayAnchor::Date(today - Duration::days(1)));
+    }
+    if text.contains("this week") || text.contains("本周") || text.contains("这周") || text.contains("这个星期") {
+        return Some(DayAnchor::ThisWeek);
+    }
+    if let Some((weekday, shift)) = parse_weekday(text) {
+        // 默认取最近一次经过的该星期几（可以是今天）
+        let today_index = today.weekday().num_days_from_monday() as i64;
+        let target_index = weekday.num_days_from_monday() as i64;
+        let date = match shift {
+            WeekdayShift::None => today - Duration::days((today_index - target_index).rem_euclid(7)),
+            WeekdayShift::Last => today - Duration::days((today_index - target_index - 1).rem_euclid(7) + 1),
+            WeekdayShift::PreviousWeek => {
+                // 「上周二」按一周起始日划分的上一周里的周二
+                let week_start = options.week_start.num_days_from_monday() as i64;
+                options.week_begin(today) - Duration::days(7) + Duration::days((target_index - week_start).rem_euclid(7))
+            }
+        };
+        return Some(if date == today { DayAnchor::Today } else { DayAnchor::Date(date) });
+    }
+    if text.contains("last week")
+        || text.contains("previous week")
+        || text.contains("上周")
+        || text.contains("上个星期")
+        || text.contains("上星期")
+        || text.contains("上个礼拜")
+        || text.contains("上礼拜")
+    {
+        return Some(DayAnchor::LastWeek);
+    }
+    if text.contains("today") || text.contains("今天") || text.contains("今日") {
+        return Some(DayAnchor::Today);
+    }
+    None
+}
+
+/// 上午/下午、工作时间等时段，返回相对当天零点的起止
+fn parse_part_of_day(text: &str, options: &TimeParseOptions) -> Option<(Duration, Duration)> {
+    let hours = |start: i64, end: i64| Some((Duration::hours(start), Duration::hours(end)));
+    if text.contains("after work") || text.contains("下班后") || text.contains("下班以后") {
+        return Some((options.work_end, Duration::days(1)));
+    }
+    if text.contains("working hours")
+        || text.contains("work hours")
+        || text.contains("during work")
+        || text.contains("工作时间")
+        || text.contains("上班时间")
+        || text.contains("上班的时候")
+    {
+        return Some((options.work_start, options.work_end));
+    }
+    // afternoon 含 noon，需先判断
+    if text.contains("afternoon") || text.contains("下午") {
+        hours(12, 18)
+    } else if text.contains("morning") || text.contains("上午") || text.contains("早上") {
+        hours(6, 12)
+    } else if text.contains("noon") || text.contains("lunch") || text.contains("中午") {
+        hours(11, 14)
+    } else if text.contains("evening")
+        || text.contains("tonight")
+        || text.contains("last night")
+        || text.contains("晚上")
+        || text.contains("今晚")
+        || text.contains("昨晚")
+    {
+        hours(18, 24)
+    } else {
+        None
+    }
+}
+
+/// 一个时刻：小时、分钟和上午/下午标记（Some(true) 表示下午）
+struct ClockTime {
+    hour: u32,
+    minute: u32,
+    pm: Option<bool>,
+    explicit: bool,  // 带冒号、「点」或上午/下午标记，单独的数字不算时刻
+    zero_padded: bool,  // "01:30" 这类补零写法按 24 小时制理解，不猜下午
+}
+
+impl ClockTime {
+    fn to_duration(&self, default_pm: Option<bool>) -> Option<Duration> {
+        let mut hour = self.hour;
+        match self.pm.or(default_pm) {
+            Some(true) if hour < 12 => hour += 12,
+            Some(false) if hour == 12 => hour = 0,
+            None if hour < AMBIGUOUS_PM_BEFORE && !self.zero_padded => hour += 12,
+            _ => {}
+        }
+        if hour > 24 || self.minute > 59 {
+            return None;
+        }
+        Some(Duration::hours(hour as i64) + Duration::minutes(self.minute as i64))
+    }
+}
+
+fn meridiem(value: &str) -> Option<bool> {
+    match value {
+        "am" | "a.m." | "上午" | "早上" | "凌晨" => Some(false),
+        "pm" | "p.m." | "下午" | "晚上" | "中午" => Some(true),
+        _ => None,
+    }
+}
+
+// 一个时刻的分组：前置上午/下午、小时、冒号分钟、「点」、点后分钟、后置 am/pm
+const CLOCK_PATTERN: &str = r"(上午|下午|早上|晚上|中午|凌晨)?\s*(\d{1,2}|十[一二]?|[一二三四五六七八九十两])(?:[:：](\d{2})|\s*([点時时])(\d{1,2}|半)?分?)?\s*(am\b|pm\b|a\.m\.|p\.m\.)?";
+const CLOCK_GROUPS: usize = 6;
+
+/// base 为该时刻第一个分组的序号
+fn clock_at(caps: &regex::Captures, base: usize) -> Option<ClockTime> {
+    let group = |offset: usize| caps.get(base + offset).map(|m| m.as_str());
+    let hour_text = group(1)?;
+    let prefix = group(0);
+    // 中文数字的钟点容易和「一点」「两点」等说法混淆，需带上午/下午
+    if !hour_text.chars().all(|c| c.is_ascii_digit()) && prefix.is_none() {
+        return None;
+    }
+    let minute = match group(2).or(group(4)) {
+        Some("半") => 30,
+        Some(value) => value.parse().ok()?,
+        None => 0,
+    };
+    let suffix = group(5);
+    Some(ClockTime {
+        hour: number_word(hour_text)?,
+        minute,
+        pm: prefix.or(suffix).and_then(meridiem),
+        explicit: prefix.is_some() || suffix.is_some() || group(2).is_some() || group(3).is_some(),
+        zero_padded: hour_text.len() == 2 && hour_text.starts_with('0'),
+    })
+}
+
+/// "between 2pm and 4pm"、"14:00-16:00"、"下午2点到4点"、"around 3pm"、"3点左右"
+fn parse_clock_range(text: &str) -> Option<(Duration, Duration)> {
+    static RANGE: OnceLock<Regex> = OnceLock::new();
+    static POINT: OnceLock<Regex> = OnceLock::new();
+    let range = regex(
+        &RANGE,
+        &format!(r"{CLOCK_PATTERN}\s*(?:-|–|~|to|and|until|till|到|至)\s*{CLOCK_PATTERN}"),
+    );
+    let point = regex(&POINT, CLOCK_PATTERN);
+
+    for caps in range.captures_iter(text) {
+        let (Some(start), Some(end)) = (clock_at(&caps, 1), clock_at(&caps, 1 + CLOCK_GROUPS)) else {
+            continue;
+        };
+        // 两端都没有钟点标记的（如 "2-3 天"）不是时间
+        if !start.explicit && !end.explicit {
+            continue;
+        }
+        // "2 to 4pm"、"下午2点到4点"：只有一端写了上午/下午时，另一端沿用
+        let end_time = end.to_duration(start.pm)?;
+        let start_time = start.to_duration(end.pm.filter(|_| start.hour <= end.hour))?;
+        return Some((start_time, end_time));
+    }
+    for caps in point.captures_iter(text) {
+        let Some(time) = clock_at(&caps, 1).filter(|time| time.explicit) else {
+            continue;
+        };
+        let Some(time) = time.to_duration(None) else {
+            continue;
+        };
+        let window = Duration::minutes(POINT_WINDOW_MINUTES);
+        return Some(((time - window).max(Duration::zero()), (time + window).min(Duration::days(1))));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-06-13 是周四
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 13)
+            .unwrap()
+            .and_hms_opt(16, 30, 0)
+            .unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn between(text: &str) -> (NaiveDateTime, NaiveDateTime) {
+        match parse_time_expression(text, now()) {
+            Some(TimeRange::Between(start, end)) => (start, end),
+            other => panic!("{:?} parsed as {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn relative_minutes_and_hours() {
+        assert!(matches!(parse_time_expression("what did I do in the last 20 minutes", now()), Some(TimeRange::Recent(20))));
+        assert!(matches!(parse_time_expression("past two hours", now()), Some(TimeRange::Recent(120))));
+        assert!(matches!(parse_time_expression("最近20分钟在干嘛", now()), Some(TimeRange::Recent(20))));
+        assert!(matches!(parse_time_expression("过去半小时", now()), Some(TimeRange::Recent(30))));
+        assert!(matches!(parse_time_expression("刚才那个报错", now()), Some(TimeRange::Recent(5))));
+        assert!(matches!(parse_time_expression("show me the error from just now", now()), Some(TimeRange::Recent(5))));
+    }
+
+    #[test]
+    fn day_words() {
+        assert!(matches!(parse_time_expression("今天做了什么", now()), Some(TimeRange::Today)));
+        assert_eq!(between("what was I doing yesterday"), (at(12, 0, 0), at(13, 0, 0)));
+        assert_eq!(between("昨天下午看的文档"), (at(12, 12, 0), at(12, 18, 0)));
+        assert_eq!(between("yesterday afternoon"), (at(12, 12, 0), at(12, 18, 0)));
+        assert_eq!(between("last 3 days"), (at(11, 0, 0), now()));
+        assert_eq!(between("这周的报错"), (at(10, 0, 0), now()));
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(between("Tuesday"), (at(11, 0, 0), at(12, 0, 0)));
+        assert_eq!(between("周二上午在 Chrome 里看了什么"), (at(11, 6, 0), at(11, 12, 0)));
+        assert_eq!(between("last friday"), (at(7, 0, 0), at(8, 0, 0)));
+        assert!(matches!(parse_time_expression("thursday", now()), Some(TimeRange::Today)));
+    }
+
+    #[test]
+    fn clock_ranges() {
+        assert_eq!(between("between 2pm and 4pm"), (at(13, 14, 0), at(13, 16, 0)));
+        assert_eq!(between("from 2 to 4pm in VS Code"), (at(13, 14, 0), at(13, 16, 0)));
+        assert_eq!(between("10:30-11:45 的会议"), (at(13, 10, 30), at(13, 11, 45)));
+        assert_eq!(between("下午2点到4点"), (at(13, 14, 0), at(13, 16, 0)));
+        assert_eq!(between("昨天上午9点到11点半 error"), (at(12, 9, 0), at(12, 11, 30)));
+        assert_eq!(between("yesterday around 3pm"), (at(12, 14, 30), at(12, 15, 30)));
+        assert_eq!(between("3点左右的 Terminal 报错"), (at(13, 14, 30), at(13, 15, 30)));
+    }
+
+    #[test]
+    fn locale_week_and_working_hours() {
+        let sunday_start = TimeParseOptions {
+            week_start: Weekday::Sun,
+            ..TimeParseOptions::default()
+        };
+        let parse = |text: &str, options: &TimeParseOptions| match parse_time_expression_with(text, now(), options) {
+            Some(TimeRange::Between(start, end)) => (start, end),
+            other => panic!("{:?} parsed as {:?}", text, other),
+        };
+        assert_eq!(parse("this week", &sunday_start), (at(9, 0, 0), now()));
+        assert_eq!(between("上周"), (at(3, 0, 0), at(10, 0, 0)));
+        assert_eq!(parse("last week", &sunday_start), (at(2, 0, 0), at(9, 0, 0)));
+        assert_eq!(parse("上周日", &sunday_start), (at(2, 0, 0), at(3, 0, 0)));
+        assert_eq!(between("上周日"), (at(9, 0, 0), at(10, 0, 0)));
+
+        let custom = TimeParseOptions {
+            work_start: Duration::hours(10),
+            work_end: Duration::hours(19),
+            day_start_hour: 4,
+            ..TimeParseOptions::default()
+        };
+        assert_eq!(parse("yesterday during working hours", &custom), (at(12, 10, 0), at(12, 19, 0)));
+        assert_eq!(parse("昨天下班后", &custom), (at(12, 19, 0), at(13, 0, 0)));
+        // 一天从 4 点算起：昨天覆盖到今天凌晨 4 点，凌晨 2 点属于「昨天」的夜里
+        assert_eq!(parse("昨天", &custom), (at(12, 4, 0), at(13, 4, 0)));
+        assert_eq!(parse("yesterday 1am-3am", &custom), (at(13, 1, 0), at(13, 3, 0)));
+        assert_eq!(parse("今天", &custom), (at(13, 4, 0), now()));
+    }
+
+    #[test]
+    fn yesterday_evening_ranges_cross_midnight() {
+        assert_eq!(between("昨天晚上"), (at(12, 18, 0), at(13, 0, 0)));
+        assert_eq!(between("yesterday 22:00-01:30"), (at(12, 22, 0), at(13, 1, 30)));
+    }
+
+    #[test]
+    fn no_time_expression() {
+        assert!(parse_time_expression("how do I fix this npm error", now()).is_none());
+        assert!(parse_time_expression("帮我看看 main.rs 第 3 行", now()).is_none());
+    }
+}
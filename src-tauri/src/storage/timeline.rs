@@ -0,0 +1,105 @@
+use super::{StorageManager, SummaryRecord};
+use chrono::{NaiveDateTime, Timelike};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+pub const DEFAULT_TIMELINE_BUCKET_MINUTES: u32 = 60;
+const MAX_TIMELINE_INTENTS: usize = 3;
+
+/// 时间线上的一个时间段，聚合该时段内的摘要记录
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineBucket {
+    pub start: String,  // HH:MM
+    pub end: String,
+    pub records: usize,
+    pub dominant_app: String,
+    pub intents: Vec<String>,  // 按出现次数排序
+    pub issue_count: usize,
+    pub screenshot_ref: Option<String>,  // 代表截图，优先取有问题的记录
+}
+
+impl StorageManager {
+    /// 按 bucket_minutes 分段聚合某天的摘要记录，只返回有记录的时间段
+    pub fn get_timeline(&self, date: &str, bucket_minutes: u32) -> Result<Vec<TimelineBucket>, String> {
+        let bucket_minutes = bucket_minutes.clamp(1, 24 * 60);
+        let records = self.get_summaries(date)?;
+
+        let mut buckets: BTreeMap<u32, Vec<&SummaryRecord>> = BTreeMap::new();
+        for record in &records {
+            let Some(minute) = minute_of_day(&record.timestamp) else {
+                continue;
+            };
+            buckets
+                .entry(minute / bucket_minutes)
+                .or_default()
+                .push(record);
+        }
+
+        Ok(buckets
+            .into_iter()
+            .map(|(index, records)| build_bucket(index * bucket_minutes, bucket_minutes, &records))
+            .collect())
+    }
+}
+
+fn minute_of_day(timestamp: &str) -> Option<u32> {
+    let time = NaiveDateTime::parse_from_str(timestamp.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()?;
+    Some(time.hour() * 60 + time.minute())
+}
+
+fn format_minute(minute: u32) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// 按出现次数降序，次数相同时保留先出现的
+fn ranked<'a>(values: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (order, value) in values.enumerate() {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        counts.entry(value).or_insert((0, order)).0 += 1;
+    }
+    let mut ranked: Vec<(&str, (usize, usize))> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+    ranked.into_iter().map(|(value, _)| value).collect()
+}
+
+fn build_bucket(start: u32, bucket_minutes: u32, records: &[&SummaryRecord]) -> TimelineBucket {
+    // 隐私跳过的记录只计数，不参与应用、意图和代表截图
+    let visible: Vec<&SummaryRecord> = records
+        .iter()
+        .copied()
+        .filter(|record| record.action != "private")
+        .collect();
+    let dominant_app = ranked(visible.iter().map(|record| record.app.as_str()))
+        .first()
+        .map(|app| app.to_string())
+        .unwrap_or_default();
+    let intents = ranked(visible.iter().map(|record| record.intent.as_str()))
+        .into_iter()
+        .take(MAX_TIMELINE_INTENTS)
+        .map(str::to_string)
+        .collect();
+    let with_screenshot: Vec<&SummaryRecord> = visible
+        .iter()
+        .copied()
+        .filter(|record| !record.detail_ref.is_empty())
+        .collect();
+    let screenshot_ref = with_screenshot
+        .iter()
+        .find(|record| record.has_issue)
+        .or_else(|| with_screenshot.get(with_screenshot.len() / 2))
+        .map(|record| record.detail_ref.clone());
+
+    TimelineBucket {
+        start: format_minute(start),
+        end: format_minute((start + bucket_minutes).min(24 * 60)),
+        records: records.len(),
+        dominant_app,
+        intents,
+        issue_count: records.iter().filter(|record| record.has_issue).count(),
+        screenshot_ref,
+    }
+}
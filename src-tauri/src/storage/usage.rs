@@ -0,0 +1,148 @@
+use super::StorageManager;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const USAGE_DIR: &str = "usage";
+const MAX_USAGE_STATS_DAYS: i64 = 366;
+
+/// 按 功能 + 提供者 + 模型 聚合的每日用量，存为 data_dir/usage/YYYY-MM-DD.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub feature: String,  // capture / chat / skills
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub requests: u64,
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub cost_usd: f64,  // 按价格表估算
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.requests += entry.requests;
+        self.prompt_tokens += entry.prompt_tokens;
+        self.completion_tokens += entry.completion_tokens;
+        self.cost_usd += entry.cost_usd;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    pub start_date: String,
+    pub end_date: String,
+    pub total: UsageTotals,
+    pub days: Vec<UsageGroup>,      // key 为日期
+    pub features: Vec<UsageGroup>,  // key 为功能
+    pub models: Vec<UsageGroup>,    // key 为 provider/model
+}
+
+impl StorageManager {
+    fn usage_path(&self, date: &str) -> Result<PathBuf, String> {
+        let dir = self.data_dir.join(USAGE_DIR);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建用量目录失败: {}", e))?;
+        Ok(dir.join(format!("{}.json", date)))
+    }
+
+    pub fn load_usage(&self, date: &str) -> Result<Vec<UsageEntry>, String> {
+        let path = self.usage_path(date)?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    /// 累加一次请求的用量
+    pub fn record_usage(&self, date: &str, entry: UsageEntry) -> Result<(), String> {
+        let mut entries = self.load_usage(date)?;
+        match entries.iter_mut().find(|item| {
+            item.feature == entry.feature && item.provider == entry.provider && item.model == entry.model
+        }) {
+            Some(item) => {
+                item.requests += entry.requests;
+                item.prompt_tokens += entry.prompt_tokens;
+                item.completion_tokens += entry.completion_tokens;
+                item.cost_usd += entry.cost_usd;
+            }
+            None => entries.push(entry),
+        }
+        let content = serde_json::to_string_pretty(&entries)
+            .map_err(|e| format!("序列化用量失败: {}", e))?;
+        self.write_data_file(&self.usage_path(date)?, content.as_bytes())
+    }
+
+    pub fn usage_stats(&self, start: NaiveDate, end: NaiveDate) -> Result<UsageStats, String> {
+        if end < start {
+            return Err("结束日期不能早于开始日期".to_string());
+        }
+        if (end - start).num_days() >= MAX_USAGE_STATS_DAYS {
+            return Err(format!("统计范围不能超过 {} 天", MAX_USAGE_STATS_DAYS));
+        }
+
+        let mut total = UsageTotals::default();
+        let mut days = Vec::new();
+        let mut features: BTreeMap<String, UsageTotals> = BTreeMap::new();
+        let mut models: BTreeMap<String, UsageTotals> = BTreeMap::new();
+        let mut date = start;
+        while date <= end {
+            let key = date.format("%Y-%m-%d").to_string();
+            let mut day = UsageTotals::default();
+            for entry in self.load_usage(&key)? {
+                total.add(&entry);
+                day.add(&entry);
+                features.entry(entry.feature.clone()).or_default().add(&entry);
+                models
+                    .entry(format!("{}/{}", entry.provider, entry.model))
+                    .or_default()
+                    .add(&entry);
+            }
+            days.push(UsageGroup { key, totals: day });
+            date = match date.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        let into_groups = |map: BTreeMap<String, UsageTotals>| {
+            let mut groups: Vec<UsageGroup> = map
+                .into_iter()
+                .map(|(key, totals)| UsageGroup { key, totals })
+                .collect();
+            groups.sort_by(|a, b| {
+                (b.totals.prompt_tokens + b.totals.completion_tokens)
+                    .cmp(&(a.totals.prompt_tokens + a.totals.completion_tokens))
+            });
+            groups
+        };
+        Ok(UsageStats {
+            start_date: start.format("%Y-%m-%d").to_string(),
+            end_date: end.format("%Y-%m-%d").to_string(),
+            total,
+            days,
+            features: into_groups(features),
+            models: into_groups(models),
+        })
+    }
+}
@@ -0,0 +1,255 @@
+use crate::storage::{StorageManager, SummaryRecord, TicketConfig};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::time::Duration;
+
+const TICKETS_FILE: &str = "tickets.json";
+const TICKET_REQUEST_TIMEOUT_SECS: u64 = 20;
+const MAX_TITLE_CHARS: usize = 120;
+
+/// 提醒与外部工单的关联；同一提醒再次出现时引用已有工单，不再重复提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketLink {
+    pub alert_key: String,
+    pub provider: String,  // github | jira
+    pub ticket_key: String,  // #123 / PROJ-45
+    pub url: String,
+    pub title: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub record_timestamp: String,
+}
+
+impl StorageManager {
+    pub fn load_ticket_links(&self) -> Result<Vec<TicketLink>, String> {
+        let path = self.get_data_dir().join(TICKETS_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = self.read_data_string(&path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn save_ticket_links(&self, links: &[TicketLink]) -> Result<(), String> {
+        let path = self.get_data_dir().join(TICKETS_FILE);
+        if links.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("删除工单关联失败: {}", e))?;
+            }
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(links)
+            .map_err(|e| format!("序列化工单关联失败: {}", e))?;
+        self.write_data_file(&path, content.as_bytes())
+    }
+
+    pub fn find_ticket_link(&self, alert_key: &str) -> Option<TicketLink> {
+        if alert_key.is_empty() {
+            return None;
+        }
+        self.load_ticket_links()
+            .ok()?
+            .into_iter()
+            .find(|link| link.alert_key == alert_key)
+    }
+
+    pub fn add_ticket_link(&self, link: TicketLink) -> Result<(), String> {
+        let mut links = self.load_ticket_links()?;
+        links.retain(|item| link.alert_key.is_empty() || item.alert_key != link.alert_key);
+        links.push(link);
+        self.save_ticket_links(&links)
+    }
+
+    /// 取消关联（如工单已关闭），之后该问题会重新提醒
+    pub fn remove_ticket_link(&self, alert_key: &str) -> Result<bool, String> {
+        let mut links = self.load_ticket_links()?;
+        let before = links.len();
+        links.retain(|item| item.alert_key != alert_key);
+        if links.len() == before {
+            return Ok(false);
+        }
+        self.save_ticket_links(&links)?;
+        Ok(true)
+    }
+
+    pub fn find_record(&self, timestamp: &str) -> Result<SummaryRecord, String> {
+        let date = timestamp
+            .get(..10)
+            .ok_or_else(|| format!("无效的时间戳: {}", timestamp))?;
+        self.get_summaries(date)?
+            .into_iter()
+            .find(|record| record.timestamp == timestamp)
+            .ok_or_else(|| format!("未找到记录: {}", timestamp))
+    }
+}
+
+fn ticket_title(record: &SummaryRecord) -> String {
+    let summary = if record.issue_summary.is_empty() {
+        record.summary.as_str()
+    } else {
+        record.issue_summary.as_str()
+    };
+    let title = if record.issue_type.is_empty() {
+        summary.to_string()
+    } else {
+        format!("[{}] {}", record.issue_type, summary)
+    };
+    title.chars().take(MAX_TITLE_CHARS).collect()
+}
+
+fn ticket_body(record: &SummaryRecord) -> String {
+    let mut lines = vec![
+        format!("时间: {}", record.timestamp),
+        format!("应用: {}", record.app),
+    ];
+    if !record.window_title.is_empty() {
+        lines.push(format!("窗口: {}", record.window_title));
+    }
+    if !record.scene.is_empty() {
+        lines.push(format!("场景: {}", record.scene));
+    }
+    lines.push(String::new());
+    lines.push(format!("问题: {}", record.issue_summary));
+    lines.push(format!("概述: {}", record.summary));
+    if !record.detail.is_empty() {
+        lines.push(format!("画面详情: {}", record.detail));
+    }
+    if !record.suggestion.is_empty() {
+        lines.push(format!("建议: {}", record.suggestion));
+    }
+    lines.push(String::new());
+    lines.push("（由 OpenCowork 根据屏幕提醒创建）".to_string());
+    lines.join("\n")
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(TICKET_REQUEST_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+async fn create_github_issue(config: &TicketConfig, title: &str, body: &str) -> Result<(String, String), String> {
+    let repo = config.github_repo.trim().trim_matches('/');
+    if repo.split('/').count() != 2 {
+        return Err("GitHub 仓库格式应为 owner/repo".to_string());
+    }
+    if config.github_token.trim().is_empty() {
+        return Err("未配置 GitHub Token".to_string());
+    }
+    let mut payload = json!({ "title": title, "body": body });
+    if !config.labels.is_empty() {
+        payload["labels"] = json!(config.labels);
+    }
+    let response = http_client()
+        .post(format!("https://api.github.com/repos/{}/issues", repo))
+        .header("Authorization", format!("Bearer {}", config.github_token.trim()))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "OpenCowork")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| format!("创建 GitHub issue 失败: {}", e))?;
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("GitHub 返回 {}: {}", status, error_message(&text)));
+    }
+    let json: Value = serde_json::from_str(&text).map_err(|e| format!("解析 GitHub 响应失败: {}", e))?;
+    let url = json["html_url"].as_str().unwrap_or_default().to_string();
+    let number = json["number"].as_u64().map(|n| format!("#{}", n)).unwrap_or_default();
+    Ok((number, url))
+}
+
+async fn create_jira_issue(config: &TicketConfig, title: &str, body: &str) -> Result<(String, String), String> {
+    let base = config.jira_base_url.trim().trim_end_matches('/');
+    if base.is_empty() || config.jira_project_key.trim().is_empty() {
+        return Err("未配置 Jira 地址或项目".to_string());
+    }
+    if config.jira_email.trim().is_empty() || config.jira_api_token.trim().is_empty() {
+        return Err("未配置 Jira 账号或 API Token".to_string());
+    }
+    // Jira 标签不能包含空格
+    let labels: Vec<String> = config
+        .labels
+        .iter()
+        .map(|label| label.trim().replace(' ', "-"))
+        .filter(|label| !label.is_empty())
+        .collect();
+    let payload = json!({
+        "fields": {
+            "project": { "key": config.jira_project_key.trim() },
+            "summary": title,
+            "description": body,
+            "issuetype": { "name": config.jira_issue_type.trim() },
+            "labels": labels,
+        }
+    });
+    let credentials = BASE64.encode(format!(
+        "{}:{}",
+        config.jira_email.trim(),
+        config.jira_api_token.trim()
+    ));
+    let response = http_client()
+        .post(format!("{}/rest/api/2/issue", base))
+        .header("Authorization", format!("Basic {}", credentials))
+        .header("Accept", "application/json")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| format!("创建 Jira issue 失败: {}", e))?;
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("Jira 返回 {}: {}", status, error_message(&text)));
+    }
+    let json: Value = serde_json::from_str(&text).map_err(|e| format!("解析 Jira 响应失败: {}", e))?;
+    let key = json["key"].as_str().unwrap_or_default().to_string();
+    Ok((key.clone(), format!("{}/browse/{}", base, key)))
+}
+
+fn error_message(text: &str) -> String {
+    let json: Value = match serde_json::from_str(text) {
+        Ok(json) => json,
+        Err(_) => return text.chars().take(300).collect(),
+    };
+    if let Some(message) = json["message"].as_str() {
+        return message.to_string();
+    }
+    if let Some(messages) = json["errorMessages"].as_array().filter(|m| !m.is_empty()) {
+        return messages
+            .iter()
+            .filter_map(|m| m.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+    json["errors"].to_string()
+}
+
+/// 按配置的工单系统为记录创建工单，返回关联信息（未保存）
+pub async fn create_ticket(config: &TicketConfig, record: &SummaryRecord, alert_key: &str) -> Result<TicketLink, String> {
+    let title = ticket_title(record);
+    let body = ticket_body(record);
+    let provider = config.provider.trim().to_lowercase();
+    let (ticket_key, url) = match provider.as_str() {
+        "github" => create_github_issue(config, &title, &body).await?,
+        "jira" => create_jira_issue(config, &title, &body).await?,
+        "" => return Err("未配置工单系统（github / jira）".to_string()),
+        other => return Err(format!("不支持的工单系统: {}", other)),
+    };
+    if url.is_empty() {
+        return Err("工单系统未返回链接".to_string());
+    }
+    Ok(TicketLink {
+        alert_key: alert_key.to_string(),
+        provider,
+        ticket_key,
+        url,
+        title,
+        created_at: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        record_timestamp: record.timestamp.clone(),
+    })
+}
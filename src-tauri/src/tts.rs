@@ -0,0 +1,206 @@
+use crate::logs;
+use crate::model::{with_usage_feature, ModelManager};
+use crate::storage::Config;
+use chrono::Local;
+use parking_lot::Mutex as ParkingMutex;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
+
+// 单次朗读的字符上限（API 的 input 限制为 4096）
+const MAX_SPEAK_CHARS: usize = 4000;
+
+/// 正在进行的朗读；新的朗读会打断上一条
+fn current_speech() -> &'static ParkingMutex<Option<CancellationToken>> {
+    static CURRENT: OnceLock<ParkingMutex<Option<CancellationToken>>> = OnceLock::new();
+    CURRENT.get_or_init(|| ParkingMutex::new(None))
+}
+
+pub fn stop_speaking() -> bool {
+    match current_speech().lock().take() {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// 朗读文字：system 使用系统自带语音，api 使用当前 API 提供者合成后本地播放；
+/// 开始播放即返回，不等待读完
+pub async fn speak(config: &Config, text: &str, voice: Option<&str>) -> Result<(), String> {
+    let text: String = text.trim().chars().take(MAX_SPEAK_CHARS).collect();
+    if text.is_empty() {
+        return Err("朗读内容为空".to_string());
+    }
+    let voice = voice
+        .map(str::trim)
+        .filter(|voice| !voice.is_empty())
+        .unwrap_or(config.speech.tts_voice.trim())
+        .to_string();
+
+    let (child, temp_file) = match config.speech.tts_provider.as_str() {
+        "api" => {
+            let api_voice = if voice.is_empty() { "alloy" } else { voice.as_str() };
+            let audio = with_usage_feature(
+                "speech",
+                ModelManager::new().synthesize_speech(&config.model, config.speech.tts_model.trim(), api_voice, &text),
+            )
+            .await?;
+            let path = std::env::temp_dir().join(format!("opencowork-tts-{}.mp3", Local::now().timestamp_millis()));
+            tokio::fs::write(&path, audio)
+                .await
+                .map_err(|e| format!("保存语音失败: {}", e))?;
+            match spawn_player(&path) {
+                Ok(child) => (child, Some(path)),
+                Err(err) => {
+                    let _ = tokio::fs::remove_file(&path).await;
+                    return Err(err);
+                }
+            }
+        }
+        _ => (spawn_system_voice(&text, &voice).await?, None),
+    };
+
+    stop_speaking();
+    let token = CancellationToken::new();
+    *current_speech().lock() = Some(token.clone());
+    tokio::spawn(wait_for_speech(child, token, temp_file));
+    Ok(())
+}
+
+/// 等待播放结束或被打断，随后清理临时文件
+async fn wait_for_speech(mut child: Child, token: CancellationToken, temp_file: Option<PathBuf>) {
+    tokio::select! {
+        status = child.wait() => {
+            if let Ok(status) = status {
+                if !status.success() {
+                    logs::warn("tts", format!("朗读进程异常退出: {}", status));
+                }
+            }
+        }
+        _ = token.cancelled() => {
+            let _ = child.kill().await;
+        }
+    }
+    if let Some(path) = temp_file {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+/// 按顺序尝试候选命令，返回第一个能启动的进程
+fn spawn_first(candidates: Vec<Command>, what: &str) -> Result<Child, String> {
+    let mut last_error = String::new();
+    for mut command in candidates {
+        match command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            Ok(child) => return Ok(child),
+            Err(err) => last_error = err.to_string(),
+        }
+    }
+    Err(format!("无法启动{}: {}", what, last_error))
+}
+
+fn spawn_player(path: &Path) -> Result<Child, String> {
+    let mut candidates = Vec::new();
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("afplay");
+        command.arg(path);
+        candidates.push(command);
+    } else if cfg!(target_os = "windows") {
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg(
+                "Add-Type -AssemblyName PresentationCore; \
+                 $p = New-Object System.Windows.Media.MediaPlayer; \
+                 $p.Open([uri]$env:OPENCOWORK_TTS_FILE); $p.Play(); \
+                 while (-not $p.NaturalDuration.HasTimeSpan) { Start-Sleep -Milliseconds 100 }; \
+                 Start-Sleep -Milliseconds ([int]$p.NaturalDuration.TimeSpan.TotalMilliseconds + 200)",
+            )
+            .env("OPENCOWORK_TTS_FILE", path);
+        candidates.push(command);
+    } else {
+        let mut ffplay = Command::new("ffplay");
+        ffplay.args(["-nodisp", "-autoexit", "-loglevel", "quiet"]).arg(path);
+        candidates.push(ffplay);
+        let mut mpv = Command::new("mpv");
+        mpv.args(["--no-video", "--really-quiet"]).arg(path);
+        candidates.push(mpv);
+    }
+    for command in candidates.iter_mut() {
+        command.stdin(Stdio::null());
+    }
+    spawn_first(candidates, "音频播放器")
+}
+
+/// 系统语音：macOS 用 say，Windows 用 System.Speech，Linux 依次尝试 espeak-ng / espeak / spd-say；
+/// 文字通过标准输入或环境变量传入，避免命令行转义问题
+async fn spawn_system_voice(text: &str, voice: &str) -> Result<Child, String> {
+    let mut candidates = Vec::new();
+    let mut via_stdin = true;
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("say");
+        if !voice.is_empty() {
+            command.args(["-v", voice]);
+        }
+        candidates.push(command);
+    } else if cfg!(target_os = "windows") {
+        via_stdin = false;
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg(
+                "Add-Type -AssemblyName System.Speech; \
+                 $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+                 if ($env:OPENCOWORK_TTS_VOICE) { $s.SelectVoice($env:OPENCOWORK_TTS_VOICE) }; \
+                 $s.Speak($env:OPENCOWORK_TTS_TEXT)",
+            )
+            .env("OPENCOWORK_TTS_TEXT", text)
+            .env("OPENCOWORK_TTS_VOICE", voice);
+        candidates.push(command);
+    } else {
+        for binary in ["espeak-ng", "espeak"] {
+            let mut command = Command::new(binary);
+            command.arg("--stdin");
+            if !voice.is_empty() {
+                command.args(["-v", voice]);
+            }
+            candidates.push(command);
+        }
+        let mut command = Command::new("spd-say");
+        command.args(["-w", "-e"]);
+        candidates.push(command);
+    }
+    for command in candidates.iter_mut() {
+        command.stdin(if via_stdin { Stdio::piped() } else { Stdio::null() });
+    }
+    let mut child = spawn_first(candidates, "系统语音")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|e| format!("发送朗读内容失败: {}", e))?;
+    }
+    Ok(child)
+}
+
+/// 开启 speak_alerts 时朗读高紧急度提醒，失败只记日志
+pub fn speak_alert(config: &Config, message: &str, suggestion: &str, urgency: &str) {
+    if !config.speech.speak_alerts || urgency != "high" {
+        return;
+    }
+    let text = if suggestion.trim().is_empty() {
+        message.to_string()
+    } else {
+        format!("{}。{}", message.trim_end_matches('。'), suggestion)
+    };
+    let config = config.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = speak(&config, &text, None).await {
+            logs::warn("tts", format!("朗读提醒失败: {}", err));
+        }
+    });
+}
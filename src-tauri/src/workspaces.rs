@@ -0,0 +1,146 @@
+use crate::storage::{Config, StorageManager, Workspace};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+// 版本库根目录优先；没有版本库时再找项目清单文件
+const VCS_MARKERS: &[&str] = &[".git", ".hg", ".svn"];
+const PROJECT_MARKERS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "CMakeLists.txt",
+    "Makefile",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceStatus {
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+    pub exists: bool,
+}
+
+/// 从 path 向上查找项目根目录：最近的版本库根目录，其次是最近的含项目清单的目录
+pub fn detect_project_root(path: &Path) -> Option<PathBuf> {
+    let start = if path.is_file() { path.parent()? } else { path };
+    let has_marker = |dir: &Path, markers: &[&str]| markers.iter().any(|marker| dir.join(marker).exists());
+    start
+        .ancestors()
+        .find(|dir| has_marker(dir, VCS_MARKERS))
+        .or_else(|| start.ancestors().find(|dir| has_marker(dir, PROJECT_MARKERS)))
+        .map(Path::to_path_buf)
+}
+
+/// 当前工作区目录；未设置或目录已不存在时返回 None
+pub fn active_workspace_dir(config: &Config) -> Option<PathBuf> {
+    let active = config.workspaces.active.as_deref()?;
+    let workspace = config.workspaces.items.iter().find(|item| item.name == active)?;
+    let path = PathBuf::from(&workspace.path);
+    path.is_dir().then_some(path)
+}
+
+fn default_workspace_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+impl StorageManager {
+    pub fn list_workspaces(&self) -> Result<Vec<WorkspaceStatus>, String> {
+        let config = self.load_config()?;
+        let active = config.workspaces.active.as_deref();
+        Ok(config
+            .workspaces
+            .items
+            .iter()
+            .map(|item| WorkspaceStatus {
+                name: item.name.clone(),
+                path: item.path.clone(),
+                active: Some(item.name.as_str()) == active,
+                exists: Path::new(&item.path).is_dir(),
+            })
+            .collect())
+    }
+
+    /// 登记工作区；开启 auto_detect 时登记所在项目的根目录。同一路径重复添加时返回已有项
+    pub fn add_workspace(&self, path: &str, name: Option<String>) -> Result<Workspace, String> {
+        let mut config = self.load_config()?;
+        let raw = PathBuf::from(path.trim());
+        if !raw.is_absolute() {
+            return Err(format!("工作区需要绝对路径: {}", path));
+        }
+        if !raw.exists() {
+            return Err(format!("目录不存在: {}", path));
+        }
+        let dir = if config.workspaces.auto_detect {
+            detect_project_root(&raw).unwrap_or(raw)
+        } else {
+            raw
+        };
+        let dir = if dir.is_file() {
+            dir.parent().map(Path::to_path_buf).unwrap_or(dir)
+        } else {
+            dir
+        };
+        let dir_text = dir.display().to_string();
+        if let Some(existing) = config.workspaces.items.iter().find(|item| item.path == dir_text) {
+            return Ok(existing.clone());
+        }
+
+        let base_name = name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| default_workspace_name(&dir));
+        let mut workspace_name = base_name.clone();
+        let mut suffix = 2;
+        while config.workspaces.items.iter().any(|item| item.name == workspace_name) {
+            workspace_name = format!("{}-{}", base_name, suffix);
+            suffix += 1;
+        }
+        let workspace = Workspace {
+            name: workspace_name,
+            path: dir_text,
+        };
+        config.workspaces.items.push(workspace.clone());
+        self.save_config(&config)?;
+        Ok(workspace)
+    }
+
+    pub fn remove_workspace(&self, name: &str) -> Result<bool, String> {
+        let mut config = self.load_config()?;
+        let before = config.workspaces.items.len();
+        config.workspaces.items.retain(|item| item.name != name);
+        if config.workspaces.items.len() == before {
+            return Ok(false);
+        }
+        if config.workspaces.active.as_deref() == Some(name) {
+            config.workspaces.active = None;
+        }
+        self.save_config(&config)?;
+        Ok(true)
+    }
+
+    /// 切换当前工作区；target 可以是已登记的名称，也可以是目录路径（自动登记）；为空表示取消
+    pub fn set_active_workspace(&self, target: Option<&str>) -> Result<Option<Workspace>, String> {
+        let target = target.map(str::trim).filter(|value| !value.is_empty());
+        let workspace = match target {
+            None => None,
+            Some(value) => {
+                let config = self.load_config()?;
+                match config.workspaces.items.iter().find(|item| item.name == value) {
+                    Some(item) => Some(item.clone()),
+                    None if Path::new(value).is_absolute() => Some(self.add_workspace(value, None)?),
+                    None => return Err(format!("工作区不存在: {}", value)),
+                }
+            }
+        };
+        let mut config = self.load_config()?;
+        config.workspaces.active = workspace.as_ref().map(|item| item.name.clone());
+        self.save_config(&config)?;
+        Ok(workspace)
+    }
+}
@@ -65,9 +65,17 @@ const messages: Record<Locale, Messages> = {
     'main.tools.mode.whitelist': '白名单模式（推荐）',
     'main.tools.mode.allowAll': '全允许模式（风险较高）',
     'main.tools.mode.hint': '白名单模式下只允许设置中的命令和目录执行。',
+    'main.approval.title': '工具授权',
+    'main.approval.desc': '{{scope}} 请求通过 {{tool}} 执行白名单之外的操作：',
+    'main.approval.remember': '记住此选择（可在设置中撤销）',
+    'main.approval.deny': '拒绝',
+    'main.approval.allowOnce': '仅本次允许',
+    'main.approval.allowSession': '本次会话允许',
+    'main.approval.failed': '提交授权失败：{{error}}',
     'main.alert.noneToday': '今天没有历史提醒',
     'main.alert.loaded': '已加载今天 {{count}} 条提醒',
     'main.alert.loadFailed': '加载今天提醒失败: {{error}}',
+    'main.storage.retentionNotice': '按数据保留规则，将于 {{time}} 后删除 {{days}} 天的摘要和 {{screenshots}} 张截图。如需保留，请在设置中调整保留天数。',
     'main.chat.newConfirm': '确定新建对话吗？当前对话将被清空。',
     'main.chat.newSuccess': '已新建对话',
     'main.chat.saved': '对话已保存: {{title}}',
@@ -197,6 +205,8 @@ const messages: Record<Locale, Messages> = {
     'settings.form.toolsAllowedDirsPlaceholder': '每行一个目录，例如: C:\\work\\files',
     'settings.form.toolsPickWorkspace': '选择工作区',
     'settings.form.toolsAllowedDirsHint': '第一行将作为默认工作区',
+    'settings.form.toolsAskApproval': '白名单外询问授权',
+    'settings.form.toolsAskApprovalHint': '开启后，白名单之外的命令和目录会在对话窗口弹窗询问，否则直接拒绝',
     'settings.form.retentionDays': '保留天数',
     'settings.form.daysUnit': '天',
     'settings.form.contextSize': '上下文大小',
@@ -458,9 +468,17 @@ const messages: Record<Locale, Messages> = {
     'main.tools.mode.whitelist': 'Whitelist mode (recommended)',
     'main.tools.mode.allowAll': 'Allow all (higher risk)',
     'main.tools.mode.hint': 'Whitelist mode only allows commands and directories configured in settings.',
+    'main.approval.title': 'Tool Approval',
+    'main.approval.desc': '{{scope}} wants to use {{tool}} for an action outside the whitelist:',
+    'main.approval.remember': 'Remember this choice (can be revoked in settings)',
+    'main.approval.deny': 'Deny',
+    'main.approval.allowOnce': 'Allow once',
+    'main.approval.allowSession': 'Allow for this session',
+    'main.approval.failed': 'Failed to submit approval: {{error}}',
     'main.alert.noneToday': 'No alerts today',
     'main.alert.loaded': 'Loaded {{count}} alerts today',
     'main.alert.loadFailed': "Failed to load today's alerts: {{error}}",
+    'main.storage.retentionNotice': 'Per your retention settings, {{days}} days of summaries and {{screenshots}} screenshots will be deleted after {{time}}. Adjust retention in Settings to keep them.',
     'main.chat.newConfirm': 'Start a new conversation? Current chat will be cleared.',
     'main.chat.newSuccess': 'New conversation started',
     'main.chat.saved': 'Conversation saved: {{title}}',
@@ -590,6 +608,8 @@ const messages: Record<Locale, Messages> = {
     'settings.form.toolsAllowedDirsPlaceholder': 'One per line, e.g. C:\\work\\files',
     'settings.form.toolsPickWorkspace': 'Pick workspace',
     'settings.form.toolsAllowedDirsHint': 'First line is used as the default workspace',
+    'settings.form.toolsAskApproval': 'Ask outside whitelist',
+    'settings.form.toolsAskApprovalHint': 'When on, commands and directories outside the whitelist prompt in the chat window; otherwise they are denied',
     'settings.form.retentionDays': 'Retention Days',
     'settings.form.daysUnit': 'days',
     'settings.form.contextSize': 'Context Size',
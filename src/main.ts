@@ -6,6 +6,7 @@ import MainView from './views/MainView.vue'
 import SettingsView from './views/SettingsView.vue'
 import HistoryView from './views/HistoryView.vue'
 import NotificationView from './views/NotificationView.vue'
+import CaptureIndicatorView from './views/CaptureIndicatorView.vue'
 import { useChatStore } from './stores/chat'
 import { useLocaleStore } from './stores/locale'
 import { useSkillsStore } from './stores/skills'
@@ -18,9 +19,13 @@ const router = createRouter({
     { path: '/settings', name: 'settings', component: SettingsView },
     { path: '/history', name: 'history', component: HistoryView },
     { path: '/notification', name: 'notification', component: NotificationView },
+    { path: '/capture-indicator', name: 'capture-indicator', component: CaptureIndicatorView, meta: { bare: true } },
   ],
 })
 
+// 截屏指示器窗口只显示状态点，不注册提醒、技能等全局监听
+const isCaptureIndicatorWindow = window.location.pathname === '/capture-indicator'
+
 const pinia = createPinia()
 const app = createApp(App)
 
@@ -73,7 +78,9 @@ async function syncLocaleWithSystem() {
 }
 
 syncLocaleWithSystem()
-skillsStore.startSkillsWatcher()
+if (!isCaptureIndicatorWindow) {
+  skillsStore.startSkillsWatcher()
+}
 
 const t = (key: string, params?: Record<string, string | number>) =>
   translate(localeStore.locale, key, params)
@@ -149,11 +156,19 @@ function formatAlertContent(
   return content
 }
 
+// 每个窗口都会执行本文件；会弹出通知的监听只在主窗口注册，避免同一提醒弹出多次
+async function isMainWindow() {
+  const { getCurrentWebviewWindow } = await import('@tauri-apps/api/webviewWindow')
+  return getCurrentWebviewWindow().label === 'main'
+}
+
 async function setupAlertListener() {
   try {
+    if (!(await isMainWindow())) return
     const { listen } = await import('@tauri-apps/api/event')
     const { invoke } = await import('@tauri-apps/api/core')
     await listen<{
+      key?: string
       timestamp: string
       issue_type?: string
       error_type?: string
@@ -164,6 +179,7 @@ async function setupAlertListener() {
       help_type?: string
       urgency?: string
       related_skill?: string
+      actions?: unknown[]
     }>('assistant-alert', async (event) => {
       const alert = event.payload
       const alertType = alert.issue_type || alert.error_type || 'unknown'
@@ -175,6 +191,7 @@ async function setupAlertListener() {
         content,
         timestamp: alert.timestamp,
         alertKey: `${alertType}|${alert.message}|${alert.timestamp}`,
+        alertSource: alert.key,
       })
       lastAlertTimestamp = alert.timestamp
 
@@ -187,6 +204,8 @@ async function setupAlertListener() {
           summary: alert.message || '',
           suggestion: alert.suggestion || '',
           urgency: alert.urgency || 'medium',
+          key: alert.key || null,
+          actions: alert.actions && alert.actions.length > 0 ? alert.actions : null,
         })
       } catch (err) {
         console.error('显示通知窗口失败:', err)
@@ -197,11 +216,14 @@ async function setupAlertListener() {
   }
 }
 
-setupAlertListener()
-ensureBashRuntimeOnStartup()
+if (!isCaptureIndicatorWindow) {
+  setupAlertListener()
+  ensureBashRuntimeOnStartup()
+}
 
 async function setupModelErrorListener() {
   try {
+    if (!(await isMainWindow())) return
     const { listen } = await import('@tauri-apps/api/event')
     const { invoke } = await import('@tauri-apps/api/core')
     await listen<{
@@ -241,7 +263,9 @@ async function setupModelErrorListener() {
   }
 }
 
-setupModelErrorListener()
+if (!isCaptureIndicatorWindow) {
+  setupModelErrorListener()
+}
 
 async function pollAlerts() {
   try {
@@ -272,4 +296,6 @@ async function pollAlerts() {
   }
 }
 
-setInterval(pollAlerts, 5000)
+if (!isCaptureIndicatorWindow) {
+  setInterval(pollAlerts, 5000)
+}
@@ -38,6 +38,7 @@ export interface ChatMessage {
   timestamp: string
   isAlert?: boolean  // 是否是主动提示的警告消息
   alertKey?: string
+  alertSource?: string  // 后端提醒 key，点击通知时据此定位消息
   attachments?: ChatAttachment[]
   toolSteps?: ToolStep[]
   toolContext?: ToolContextMessage[]  // 工具调用上下文
@@ -122,6 +123,15 @@ export const useChatStore = defineStore('chat', () => {
     return false
   }
 
+  // 独立聊天窗口按会话 ID 打开对话：已保存的会话继续，否则以该 ID 新建
+  function openSession(id: string) {
+    if (loadConversation(id)) {
+      return
+    }
+    clearMessages()
+    activeConversationId.value = id
+  }
+
   // 删除已保存的对话
   function deleteConversation(id: string) {
     const index = savedConversations.value.findIndex(c => c.id === id)
@@ -213,6 +223,7 @@ export const useChatStore = defineStore('chat', () => {
     clearMessages,
     newConversation,
     loadConversation,
+    openSession,
     deleteConversation,
   }
 })
@@ -43,6 +43,7 @@ export interface AppConfig {
     mode: 'unset' | 'whitelist' | 'allow_all'
     allowed_commands: string[]
     allowed_dirs: string[]
+    ask_approval: boolean
   }
   ui: {
     show_progress: boolean
@@ -92,6 +93,7 @@ export const useSettingsStore = defineStore('settings', () => {
       mode: 'unset',
       allowed_commands: [],
       allowed_dirs: [],
+      ask_approval: false,
     },
     ui: {
       show_progress: true,